@@ -737,8 +737,10 @@ fn define_memory(
     let adjust_sp_down = shared.by_name("adjust_sp_down");
     let adjust_sp_down_imm = shared.by_name("adjust_sp_down_imm");
     let adjust_sp_up_imm = shared.by_name("adjust_sp_up_imm");
+    let atomic_cas = shared.by_name("atomic_cas");
     let copy_nop = shared.by_name("copy_nop");
     let fill = shared.by_name("fill");
+    let fence = shared.by_name("fence");
     let fill_nop = shared.by_name("fill_nop");
     let istore16 = shared.by_name("istore16");
     let istore16_complex = shared.by_name("istore16_complex");
@@ -766,6 +768,7 @@ fn define_memory(
     let uload8 = shared.by_name("uload8");
     let uload8_complex = shared.by_name("uload8_complex");
     let x86_pop = x86.by_name("x86_pop");
+    let x86_probestack_loop = x86.by_name("x86_probestack_loop");
     let x86_push = x86.by_name("x86_push");
 
     // Shorthands for recipes.
@@ -781,7 +784,10 @@ fn define_memory(
     let rec_ldWithIndex = r.template("ldWithIndex");
     let rec_ldWithIndexDisp32 = r.template("ldWithIndexDisp32");
     let rec_ldWithIndexDisp8 = r.template("ldWithIndexDisp8");
+    let rec_lock_cmpxchg = r.template("lock_cmpxchg");
+    let rec_mfence = r.recipe("mfence");
     let rec_popq = r.template("popq");
+    let rec_probestack_loop = r.recipe("probestack_loop");
     let rec_pushq = r.template("pushq");
     let rec_regfill32 = r.template("regfill32");
     let rec_regspill32 = r.template("regspill32");
@@ -1010,6 +1016,40 @@ fn define_memory(
         adjust_sp_down_imm,
         rec_adjustsp_id.opcodes(&CMP_IMM).rrr(5).rex().w(),
     );
+
+    // Allocate stack in a loop of inline stack probes.
+    e.enc64_rec(x86_probestack_loop, rec_probestack_loop, 0);
+
+    // Atomic compare-and-swap. The other atomic instructions are legalized in terms of it, plain
+    // loads and stores, and fences. There's no byte encoding for X86_32, where the replacement
+    // value would have to be in an ABCD register.
+    e.enc32(
+        atomic_cas.bind(I32).bind(Any),
+        rec_lock_cmpxchg.opcodes(&CMPXCHG),
+    );
+    e.enc64(
+        atomic_cas.bind(I32).bind(Any),
+        rec_lock_cmpxchg.opcodes(&CMPXCHG).rex(),
+    );
+    e.enc64(
+        atomic_cas.bind(I64).bind(Any),
+        rec_lock_cmpxchg.opcodes(&CMPXCHG).rex().w(),
+    );
+    e.enc32(
+        atomic_cas.bind(I16).bind(Any),
+        rec_lock_cmpxchg.opcodes(&CMPXCHG_16),
+    );
+    e.enc64(
+        atomic_cas.bind(I16).bind(Any),
+        rec_lock_cmpxchg.opcodes(&CMPXCHG_16).rex(),
+    );
+    e.enc64(
+        atomic_cas.bind(I8).bind(Any),
+        rec_lock_cmpxchg.opcodes(&CMPXCHG_BYTE).rex(),
+    );
+
+    e.enc32_rec(fence, rec_mfence, 0);
+    e.enc64_rec(fence, rec_mfence, 0);
 }
 
 #[inline(never)]
@@ -2438,6 +2478,8 @@ fn define_control_flow(
     let jump_table_base = shared.by_name("jump_table_base");
     let jump_table_entry = shared.by_name("jump_table_entry");
     let return_ = shared.by_name("return");
+    let return_call = shared.by_name("return_call");
+    let return_call_indirect = shared.by_name("return_call_indirect");
     let trap = shared.by_name("trap");
     let trapff = shared.by_name("trapff");
     let trapif = shared.by_name("trapif");
@@ -2451,6 +2493,9 @@ fn define_control_flow(
     let rec_call_id = r.template("call_id");
     let rec_call_plt_id = r.template("call_plt_id");
     let rec_call_r = r.template("call_r");
+    let rec_tail_call_id = r.template("tail_call_id");
+    let rec_tail_call_plt_id = r.template("tail_call_plt_id");
+    let rec_tail_call_r = r.template("tail_call_r");
     let rec_debugtrap = r.recipe("debugtrap");
     let rec_indirect_jmp = r.template("indirect_jmp");
     let rec_jmpb = r.template("jmpb");
@@ -2497,6 +2542,29 @@ fn define_control_flow(
         rec_call_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
     );
 
+    // Tail calls, with the same encoding choices as calls.
+    e.enc32(return_call, rec_tail_call_id.opcodes(&JUMP_NEAR_RELATIVE));
+    let is_colocated_func = InstructionPredicate::new_is_colocated_func(&*formats.call, "func_ref");
+    e.enc64_instp(
+        return_call,
+        rec_tail_call_id.opcodes(&JUMP_NEAR_RELATIVE),
+        is_colocated_func,
+    );
+    e.enc64_isap(
+        return_call,
+        rec_tail_call_plt_id.opcodes(&JUMP_NEAR_RELATIVE),
+        is_pic,
+    );
+
+    e.enc32(
+        return_call_indirect.bind(I32),
+        rec_tail_call_r.opcodes(&JUMP_ABSOLUTE).rrr(4),
+    );
+    e.enc64(
+        return_call_indirect.bind(I64),
+        rec_tail_call_r.opcodes(&JUMP_ABSOLUTE).rrr(4),
+    );
+
     e.enc32(return_, rec_ret.opcodes(&RET_NEAR));
     e.enc64(return_, rec_ret.opcodes(&RET_NEAR));
 
@@ -257,6 +257,28 @@ pub(crate) fn define(
         .can_load(true),
     );
 
+    let Size = &Operand::new("Size", &immediates.imm64).with_doc("Bytes of stack to allocate");
+
+    ig.push(
+        Inst::new(
+            "x86_probestack_loop",
+            r#"
+    Allocates ``Size`` bytes of stack in a loop, one guard page at a time.
+
+    After every page, the stack pointer is stored to the new top of stack, so
+    that the guard page is hit before anything beyond it. ``Size`` must be a
+    multiple of the page size set by ``probestack_size_log2``.
+
+    The loop counter is kept in %rax, which is clobbered, so this may only be
+    used in a prologue. It is only implemented in 64-bit mode.
+    "#,
+            &formats.unary_imm,
+        )
+        .operands_in(vec![Size])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
     let y = &Operand::new("y", iWord);
     let rflags = &Operand::new("rflags", iflags);
 
@@ -51,11 +51,15 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
 
     // List of instructions.
     let insts = &shared.instructions;
+    let atomic_load = insts.by_name("atomic_load");
+    let atomic_rmw = insts.by_name("atomic_rmw");
+    let atomic_store = insts.by_name("atomic_store");
     let band = insts.by_name("band");
     let bor = insts.by_name("bor");
     let clz = insts.by_name("clz");
     let ctz = insts.by_name("ctz");
     let fcmp = insts.by_name("fcmp");
+    let fence = insts.by_name("fence");
     let fcvt_from_uint = insts.by_name("fcvt_from_uint");
     let fcvt_to_sint = insts.by_name("fcvt_to_sint");
     let fcvt_to_uint = insts.by_name("fcvt_to_uint");
@@ -70,11 +74,13 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let isub = insts.by_name("isub");
     let ishl = insts.by_name("ishl");
     let ireduce = insts.by_name("ireduce");
+    let load = insts.by_name("load");
     let popcnt = insts.by_name("popcnt");
     let sdiv = insts.by_name("sdiv");
     let selectif = insts.by_name("selectif");
     let smulhi = insts.by_name("smulhi");
     let srem = insts.by_name("srem");
+    let store = insts.by_name("store");
     let tls_value = insts.by_name("tls_value");
     let udiv = insts.by_name("udiv");
     let umulhi = insts.by_name("umulhi");
@@ -349,6 +355,24 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
         ],
     );
 
+    // Atomics.
+    //
+    // Aligned loads and stores are atomic on x86, and loads aren't reordered with other memory
+    // accesses, but stores may be reordered with later loads, so sequentially consistent stores
+    // need a fence. Read-modify-write operations are compare-and-swap loops.
+    let flags = var("flags");
+    let p = var("p");
+    let offset_0 = Literal::constant(&imm.offset32, 0);
+    expand.legalize(
+        def!(a = atomic_load(flags, p)),
+        vec![def!(a = load(flags, p, offset_0))],
+    );
+    expand.legalize(
+        def!(atomic_store(flags, x, p)),
+        vec![def!(store(flags, x, p, offset_0)), def!(fence())],
+    );
+    expand.custom_legalize(atomic_rmw, "expand_atomic_rmw");
+
     expand.custom_legalize(ineg, "convert_ineg");
     expand.custom_legalize(tls_value, "expand_tls_value");
     widen.custom_legalize(ineg, "convert_ineg");
@@ -85,6 +85,18 @@ pub static CMPPD: [u8; 3] = [0x66, 0x0f, 0xc2];
 /// imm8 as comparison predicate (SSE).
 pub static CMPPS: [u8; 2] = [0x0f, 0xc2];
 
+/// Compare AL with r/m8; if equal, store r8 into r/m8, else load r/m8 into AL (with a LOCK prefix,
+/// atomically).
+pub static CMPXCHG_BYTE: [u8; 2] = [0x0f, 0xb0];
+
+/// Compare {EAX, RAX} with r/m{32,64}; if equal, store r{32,64} into r/m{32,64}, else load
+/// r/m{32,64} into {EAX, RAX} (with a LOCK prefix, atomically).
+pub static CMPXCHG: [u8; 2] = [0x0f, 0xb1];
+
+/// Compare AX with r/m16; if equal, store r16 into r/m16, else load r/m16 into AX (with a LOCK
+/// prefix, atomically).
+pub static CMPXCHG_16: [u8; 3] = [0x66, 0x0f, 0xb1];
+
 /// Convert four packed signed doubleword integers from xmm2/mem to four packed single-precision
 /// floating-point values in xmm1 (SSE2).
 pub static CVTDQ2PS: [u8; 2] = [0x0f, 0x5b];
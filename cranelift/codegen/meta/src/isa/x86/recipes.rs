@@ -501,6 +501,19 @@ pub(crate) fn define<'shared>(
         EncodingRecipeBuilder::new("debugtrap", &formats.nullary, 1).emit("sink.put1(0xcc);"),
     );
 
+    // MFENCE, no operands.
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("mfence", &formats.nullary, 3)
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.put1(0x0f);
+                    sink.put1(0xae);
+                    sink.put1(0xf0);
+                "#,
+            ),
+    );
+
     // XX opcode, no ModR/M.
     recipes.add_template_recipe(EncodingRecipeBuilder::new("trap", &formats.trap, 0).emit(
         r#"
@@ -905,6 +918,38 @@ pub(crate) fn define<'shared>(
         .inferred_rex_compute_size("size_with_inferred_rex_for_inreg1"),
     );
 
+    // LOCK XX /r for CMPXCHG: address in r, expected value in %rax, replacement in r. Loaded value
+    // in %rax.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("lock_cmpxchg", &formats.atomic_cas, 2)
+            .operands_in(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_rax),
+                OperandConstraint::RegClass(gpr),
+            ])
+            .operands_out(vec![reg_rax])
+            .compute_size("size_plus_maybe_sib_or_offset_for_inreg_0")
+            .emit(
+                r#"
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    // LOCK prefix.
+                    sink.put1(0xf0);
+                    {{PUT_OP}}(bits, rex2(in_reg0, in_reg2), sink);
+                    if needs_sib_byte(in_reg0) {
+                        modrm_sib(in_reg2, sink);
+                        sib_noindex(in_reg0, sink);
+                    } else if needs_offset(in_reg0) {
+                        modrm_disp8(in_reg0, in_reg2, sink);
+                        sink.put1(0);
+                    } else {
+                        modrm_rm(in_reg0, in_reg2, sink);
+                    }
+                "#,
+            ),
+    );
+
     // XX /r for BLEND* instructions
     recipes.add_template_inferred(
         EncodingRecipeBuilder::new("blend", &formats.ternary, 1)
@@ -1232,6 +1277,38 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Stack probe loop, counting the pages down in %rax:
+    //
+    //     mov $pages, %eax
+    //   1:
+    //     sub $page_size, %rsp
+    //     mov %rsp, (%rsp)
+    //     dec %eax
+    //     jnz 1b
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("probestack_loop", &formats.unary_imm, 20).emit(
+            r#"
+                let page_size = 1u32 << isa.flags().probestack_size_log2();
+                let imm: i64 = imm.into();
+                debug_assert!(imm > 0 && imm % i64::from(page_size) == 0);
+                sink.put1(0xb8);
+                sink.put4((imm / i64::from(page_size)) as u32);
+                sink.put1(0x48);
+                sink.put1(0x81);
+                sink.put1(0xec);
+                sink.put4(page_size);
+                sink.put1(0x48);
+                sink.put1(0x89);
+                sink.put1(0x24);
+                sink.put1(0x24);
+                sink.put1(0xff);
+                sink.put1(0xc8);
+                sink.put1(0x75);
+                sink.put1(0xf1);
+            "#,
+        ),
+    );
+
     {
         recipes.add_template_recipe(
             EncodingRecipeBuilder::new("adjustsp_ib", &formats.unary_imm, 2)
@@ -2468,6 +2545,49 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Tail calls jump to the callee, once the epilogue has been inserted before them.
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_id", &formats.call, 4).emit(
+            r#"
+            {{PUT_OP}}(bits, BASE_REX, sink);
+            // The addend adjusts for the difference between the end of the
+            // instruction and the beginning of the immediate field.
+            sink.reloc_external(func.srclocs[inst],
+                                Reloc::X86CallPCRel4,
+                                &func.dfg.ext_funcs[func_ref].name,
+                                -4);
+            sink.put4(0);
+        "#,
+        ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_plt_id", &formats.call, 4).emit(
+            r#"
+            {{PUT_OP}}(bits, BASE_REX, sink);
+            sink.reloc_external(func.srclocs[inst],
+                                Reloc::X86CallPLTRel4,
+                                &func.dfg.ext_funcs[func_ref].name,
+                                -4);
+            sink.put4(0);
+        "#,
+        ),
+    );
+
+    // The callee address must be in a register which is neither restored by the epilogue nor
+    // used to pass arguments.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_r", &formats.call_indirect, 1)
+            .operands_in(vec![reg_rax])
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex1(RU::rax.into()), sink);
+                    modrm_r_bits(RU::rax.into(), bits, sink);
+                "#,
+            ),
+    );
+
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("ret", &formats.multiary, 0)
             .emit("{{PUT_OP}}(bits, BASE_REX, sink);"),
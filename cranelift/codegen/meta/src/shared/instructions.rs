@@ -473,6 +473,50 @@ fn define_control_flow(
         .is_call(true),
     );
 
+    let FN = &Operand::new("FN", &entities.func_ref)
+        .with_doc("function to call, declared by `function`");
+    let args = &Operand::new("args", &entities.varargs).with_doc("call arguments");
+    ig.push(
+        Inst::new(
+            "return_call",
+            r#"
+        Direct tail call.
+
+        Tail call a function which has been declared in the preamble. The
+        argument types must match the function's signature, the caller and
+        callee calling conventions must be the same, and the callee must have
+        the same return types as the caller.
+
+        The callee reuses the caller's stack frame, which is torn down before
+        control is transferred, and returns directly to the caller's caller.
+        "#,
+            &formats.call,
+        )
+        .operands_in(vec![FN, args])
+        .is_call(true)
+        .is_terminator(true),
+    );
+
+    let SIG = &Operand::new("SIG", &entities.sig_ref).with_doc("function signature");
+    let callee = &Operand::new("callee", iAddr).with_doc("address of function to call");
+    let args = &Operand::new("args", &entities.varargs).with_doc("call arguments");
+    ig.push(
+        Inst::new(
+            "return_call_indirect",
+            r#"
+        Indirect tail call.
+
+        Tail call the function pointed to by `callee` with the given arguments.
+        The called function must match the specified signature, and the same
+        restrictions as for `return_call` apply.
+        "#,
+            &formats.call_indirect,
+        )
+        .operands_in(vec![SIG, callee, args])
+        .is_call(true)
+        .is_terminator(true),
+    );
+
     let FN = &Operand::new("FN", &entities.func_ref)
         .with_doc("function to call, declared by `function`");
     let addr = &Operand::new("addr", iAddr);
@@ -4120,5 +4164,115 @@ pub(crate) fn define(
         .is_ghost(true),
     );
 
+    let AtomicMem = &TypeVar::new(
+        "AtomicMem",
+        "Any type that can be stored in memory, which can be used in an atomic operation",
+        TypeSetBuilder::new().ints(8..64).build(),
+    );
+
+    let MemFlags = &Operand::new("MemFlags", &imm.memflags);
+    let AtomicRmwOp = &Operand::new("AtomicRmwOp", &imm.atomic_rmw_op);
+    let p = &Operand::new("p", iAddr);
+    let x = &Operand::new("x", AtomicMem).with_doc("Value to be atomically stored");
+    let e = &Operand::new("e", AtomicMem).with_doc("Expected value in CAS");
+    let a = &Operand::new("a", AtomicMem).with_doc("Value atomically loaded");
+
+    ig.push(
+        Inst::new(
+            "atomic_rmw",
+            r#"
+        Atomically read-modify-write memory at `p`, with second operand `x`.
+
+        The old value is returned. `p` has the type of the target word size,
+        and `x` may be an integer type of 8, 16, 32 or 64 bits, even on a
+        32-bit target. The type of the returned value is the same as the type
+        of `x`. This operation is sequentially consistent and creates
+        happens-before edges that order normal (non-atomic) loads and stores.
+        "#,
+            &formats.atomic_rmw,
+        )
+        .operands_in(vec![MemFlags, AtomicRmwOp, p, x])
+        .operands_out(vec![a])
+        .can_load(true)
+        .can_store(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "atomic_cas",
+            r#"
+        Perform an atomic compare-and-swap operation on memory at `p`, with
+        expected value `e`, storing `x` if the value at `p` equals `e`.
+
+        The old value at `p` is returned, regardless of whether the operation
+        succeeds or fails. `p` has the type of the target word size, and `x`
+        and `e` must have the same type and the same size, which may be an
+        integer type of 8, 16, 32 or 64 bits, even on a 32-bit target. The type
+        of the returned value is the same as the type of `x` and `e`. This
+        operation is sequentially consistent and creates happens-before edges
+        that order normal (non-atomic) loads and stores.
+        "#,
+            &formats.atomic_cas,
+        )
+        .operands_in(vec![MemFlags, p, e, x])
+        .operands_out(vec![a])
+        .can_load(true)
+        .can_store(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "atomic_load",
+            r#"
+        Atomically load from memory at `p`.
+
+        This is a polymorphic instruction that can load any value type which
+        has a memory representation. It should only be used for integer types
+        with 8, 16, 32 or 64 bits. This operation is sequentially consistent
+        and creates happens-before edges that order normal (non-atomic) loads
+        and stores.
+        "#,
+            &formats.load_no_offset,
+        )
+        .operands_in(vec![MemFlags, p])
+        .operands_out(vec![a])
+        .can_load(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "atomic_store",
+            r#"
+        Atomically store `x` to memory at `p`.
+
+        This is a polymorphic instruction that can store any value type with a
+        memory representation. It should only be used for integer types with
+        8, 16, 32 or 64 bits. This operation is sequentially consistent and
+        creates happens-before edges that order normal (non-atomic) loads and
+        stores.
+        "#,
+            &formats.store_no_offset,
+        )
+        .operands_in(vec![MemFlags, x, p])
+        .can_store(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "fence",
+            r#"
+        A memory fence. This must provide ordering to ensure that, at a
+        minimum, neither loads nor stores of any kind may move forwards or
+        backwards across the fence. This operation is sequentially consistent.
+        "#,
+            &formats.nullary,
+        )
+        .other_side_effects(true),
+    );
+
     ig.build()
 }
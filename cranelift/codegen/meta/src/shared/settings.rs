@@ -195,6 +195,7 @@ pub(crate) fn define() -> SettingGroup {
             "baldrdash_system_v",
             "baldrdash_windows",
             "probestack",
+            "tail",
         ],
     );
 
@@ -249,13 +250,28 @@ pub(crate) fn define() -> SettingGroup {
             The log2 of the size of the stack guard region.
 
             Stack frames larger than this size will have stack overflow checked
-            by calling the probestack function.
+            by stack probes.
 
             The default is 12, which translates to a size of 4096.
             "#,
         12,
     );
 
+    settings.add_enum(
+        "probestack_strategy",
+        r#"
+            Controls how stack probes are emitted when `enable_probestack` is set.
+
+            Supported strategies:
+
+            - `outline`: Calls the probestack function, which must be provided by
+              the runtime.
+            - `inline`: Touches each page of the new frame in the prologue while
+              allocating it, so no probestack function is needed.
+            "#,
+        vec!["outline", "inline"],
+    );
+
     // Jump table options.
 
     settings.add_bool(
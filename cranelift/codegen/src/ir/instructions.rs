@@ -67,6 +67,15 @@ impl Opcode {
             _ => false,
         }
     }
+
+    /// Returns true if the instruction is a tail call, which transfers control to the callee
+    /// without returning to the calling function.
+    pub fn is_tail_call(&self) -> bool {
+        match self {
+            Opcode::ReturnCall | Opcode::ReturnCallIndirect => true,
+            _ => false,
+        }
+    }
 }
 
 // This trait really belongs in cranelift-reader where it is used by the `.clif` file parser, but since
@@ -140,6 +140,10 @@ struct ABISig {
 /// with 32-bit arithmetic: for now, 128 MB.
 static STACK_ARG_RET_SIZE_LIMIT: u64 = 128 * 1024 * 1024;
 
+/// The largest number of guard-sized pages whose inline stack probes are
+/// unrolled in the prologue; larger frames are probed in a loop.
+const PROBESTACK_UNROLL_PAGES: u32 = 3;
+
 // Spidermonkey specific ABI convention.
 
 /// This is SpiderMonkey's `WasmTableCallSigReg`.
@@ -503,6 +507,7 @@ impl AArch64ABIBody {
             call_conv == isa::CallConv::SystemV
                 || call_conv == isa::CallConv::Fast
                 || call_conv == isa::CallConv::Cold
+                || call_conv == isa::CallConv::Tail
                 || call_conv.extends_baldrdash(),
             "Unsupported calling convention: {:?}",
             call_conv
@@ -642,6 +647,155 @@ impl AArch64ABIBody {
             });
         }
     }
+
+    /// Allocate `frame_size` bytes of stack in the prologue.
+    ///
+    /// When inline stack probes are enabled and the frame is larger than the
+    /// guard page, the frame is allocated at most one page at a time, storing
+    /// `xzr` to the new top of stack after every step so that the guard page is
+    /// hit before anything beyond it. Up to `PROBESTACK_UNROLL_PAGES` pages,
+    /// the sequence is unrolled; beyond that, the whole pages are probed by a
+    /// loop, and only the remainder is unrolled. Outline probes, i.e. calls to
+    /// the probestack function, aren't supported by this backend.
+    fn allocate_frame(&self, frame_size: u32, insts: &mut Vec<Inst>) {
+        let page_size = 1u32 << self.flags.probestack_size_log2();
+
+        if !self.flags.enable_probestack()
+            || self.flags.probestack_strategy() != settings::ProbestackStrategy::Inline
+            || frame_size <= page_size
+        {
+            return push_sub(frame_size, insts);
+        }
+
+        let mut remaining = frame_size;
+        let pages = frame_size / page_size;
+        if pages > PROBESTACK_UNROLL_PAGES {
+            if let Some(page) = Imm12::maybe_from_u64(page_size as u64) {
+                let counter = writable_spilltmp_reg();
+                insts.extend(Inst::load_constant(counter, pages as u64));
+                insts.push(Inst::StackProbeLoop { counter, page });
+                remaining -= pages * page_size;
+            }
+        }
+        while remaining > 0 {
+            let step = core::cmp::min(remaining, page_size);
+            push_sub(step, insts);
+            // str xzr, [sp]
+            insts.push(Inst::Store64 {
+                rd: zero_reg(),
+                mem: MemArg::reg(stack_reg()),
+                srcloc: None,
+            });
+            remaining -= step;
+        }
+
+        fn push_sub(amount: u32, insts: &mut Vec<Inst>) {
+            // sub sp, sp, #amount
+            if let Some(imm12) = Imm12::maybe_from_u64(amount as u64) {
+                insts.push(Inst::AluRRImm12 {
+                    alu_op: ALUOp::Sub64,
+                    rd: writable_stack_reg(),
+                    rn: stack_reg(),
+                    imm12,
+                });
+            } else {
+                let tmp = writable_spilltmp_reg();
+                insts.push(Inst::LoadConst64 {
+                    rd: tmp,
+                    const_data: amount as u64,
+                });
+                insts.push(Inst::AluRRRExtend {
+                    alu_op: ALUOp::Sub64,
+                    rd: writable_stack_reg(),
+                    rn: stack_reg(),
+                    rm: tmp.to_reg(),
+                    extendop: ExtendOp::UXTX,
+                });
+            }
+        }
+    }
+
+    /// Restore the clobbered callee-saved registers and pop the frame set up by
+    /// the prologue, leaving the stack as it was on entry. This is shared
+    /// between returns and tail calls.
+    fn gen_frame_teardown(&self) -> Vec<Inst> {
+        let mut insts = vec![];
+
+        // Restore clobbered registers.
+        let (clobbered_int, clobbered_vec) =
+            get_callee_saves(self.call_conv, self.clobbered.to_vec());
+
+        for (i, reg) in clobbered_vec.iter().enumerate() {
+            insts.push(Inst::FpuLoad128 {
+                rd: Writable::from_reg(reg.to_reg().to_reg()),
+                mem: MemArg::Unscaled(stack_reg(), SImm9::maybe_from_i64((i * 16) as i64).unwrap()),
+                srcloc: None,
+            });
+        }
+        let vec_save_bytes = clobbered_vec.len() * 16;
+        if vec_save_bytes != 0 {
+            insts.push(Inst::AluRRImm12 {
+                alu_op: ALUOp::Add64,
+                rd: writable_stack_reg(),
+                rn: stack_reg(),
+                imm12: Imm12::maybe_from_u64(vec_save_bytes as u64).unwrap(),
+            });
+        }
+
+        for reg_pair in clobbered_int.chunks(2).rev() {
+            let (r1, r2) = if reg_pair.len() == 2 {
+                (
+                    reg_pair[0].map(|r| r.to_reg()),
+                    reg_pair[1].map(|r| r.to_reg()),
+                )
+            } else {
+                (reg_pair[0].map(|r| r.to_reg()), writable_zero_reg())
+            };
+
+            debug_assert!(r1.to_reg().get_class() == RegClass::I64);
+            debug_assert!(r2.to_reg().get_class() == RegClass::I64);
+
+            // ldp r1, r2, [sp], #16
+            insts.push(Inst::LoadP64 {
+                rt: r1,
+                rt2: r2,
+                mem: PairMemArg::PostIndexed(
+                    writable_stack_reg(),
+                    SImm7Scaled::maybe_from_i64(16, types::I64).unwrap(),
+                ),
+            });
+        }
+
+        // N.B.: we do *not* emit a nominal-SP adjustment here, because (i) there will be no
+        // references to nominal-SP offsets before the return or tail call that follows, and (ii) the instruction
+        // emission tracks running SP offset linearly (in straight-line order), not according to
+        // the CFG, so early returns in the middle of function bodies would cause an incorrect
+        // offset for the rest of the body.
+
+        if !self.call_conv.extends_baldrdash() {
+            // The MOV (alias of ORR) interprets x31 as XZR, so use an ADD here.
+            // MOV to SP is an alias of ADD.
+            insts.push(Inst::AluRRImm12 {
+                alu_op: ALUOp::Add64,
+                rd: writable_stack_reg(),
+                rn: fp_reg(),
+                imm12: Imm12 {
+                    bits: 0,
+                    shift12: false,
+                },
+            });
+            insts.push(Inst::LoadP64 {
+                rt: writable_fp_reg(),
+                rt2: writable_link_reg(),
+                mem: PairMemArg::PostIndexed(
+                    writable_stack_reg(),
+                    SImm7Scaled::maybe_from_i64(16, types::I64).unwrap(),
+                ),
+            });
+        }
+
+        insts
+    }
 }
 
 fn load_stack(mem: MemArg, into_reg: Writable<Reg>, ty: Type) -> Inst {
@@ -1072,31 +1226,7 @@ impl ABIBody for AArch64ABIBody {
                 }
             }
             if total_stacksize > 0 {
-                // sub sp, sp, #total_stacksize
-                if let Some(imm12) = Imm12::maybe_from_u64(total_stacksize as u64) {
-                    let sub_inst = Inst::AluRRImm12 {
-                        alu_op: ALUOp::Sub64,
-                        rd: writable_stack_reg(),
-                        rn: stack_reg(),
-                        imm12,
-                    };
-                    insts.push(sub_inst);
-                } else {
-                    let tmp = writable_spilltmp_reg();
-                    let const_inst = Inst::LoadConst64 {
-                        rd: tmp,
-                        const_data: total_stacksize as u64,
-                    };
-                    let sub_inst = Inst::AluRRRExtend {
-                        alu_op: ALUOp::Sub64,
-                        rd: writable_stack_reg(),
-                        rn: stack_reg(),
-                        rm: tmp.to_reg(),
-                        extendop: ExtendOp::UXTX,
-                    };
-                    insts.push(const_inst);
-                    insts.push(sub_inst);
-                }
+                self.allocate_frame(total_stacksize, &mut insts);
             }
         }
 
@@ -1163,79 +1293,8 @@ impl ABIBody for AArch64ABIBody {
     }
 
     fn gen_epilogue(&self) -> Vec<Inst> {
-        let mut insts = vec![];
-
-        // Restore clobbered registers.
-        let (clobbered_int, clobbered_vec) =
-            get_callee_saves(self.call_conv, self.clobbered.to_vec());
-
-        for (i, reg) in clobbered_vec.iter().enumerate() {
-            insts.push(Inst::FpuLoad128 {
-                rd: Writable::from_reg(reg.to_reg().to_reg()),
-                mem: MemArg::Unscaled(stack_reg(), SImm9::maybe_from_i64((i * 16) as i64).unwrap()),
-                srcloc: None,
-            });
-        }
-        let vec_save_bytes = clobbered_vec.len() * 16;
-        if vec_save_bytes != 0 {
-            insts.push(Inst::AluRRImm12 {
-                alu_op: ALUOp::Add64,
-                rd: writable_stack_reg(),
-                rn: stack_reg(),
-                imm12: Imm12::maybe_from_u64(vec_save_bytes as u64).unwrap(),
-            });
-        }
-
-        for reg_pair in clobbered_int.chunks(2).rev() {
-            let (r1, r2) = if reg_pair.len() == 2 {
-                (
-                    reg_pair[0].map(|r| r.to_reg()),
-                    reg_pair[1].map(|r| r.to_reg()),
-                )
-            } else {
-                (reg_pair[0].map(|r| r.to_reg()), writable_zero_reg())
-            };
-
-            debug_assert!(r1.to_reg().get_class() == RegClass::I64);
-            debug_assert!(r2.to_reg().get_class() == RegClass::I64);
-
-            // ldp r1, r2, [sp], #16
-            insts.push(Inst::LoadP64 {
-                rt: r1,
-                rt2: r2,
-                mem: PairMemArg::PostIndexed(
-                    writable_stack_reg(),
-                    SImm7Scaled::maybe_from_i64(16, types::I64).unwrap(),
-                ),
-            });
-        }
-
-        // N.B.: we do *not* emit a nominal-SP adjustment here, because (i) there will be no
-        // references to nominal-SP offsets before the return below, and (ii) the instruction
-        // emission tracks running SP offset linearly (in straight-line order), not according to
-        // the CFG, so early returns in the middle of function bodies would cause an incorrect
-        // offset for the rest of the body.
-
+        let mut insts = self.gen_frame_teardown();
         if !self.call_conv.extends_baldrdash() {
-            // The MOV (alias of ORR) interprets x31 as XZR, so use an ADD here.
-            // MOV to SP is an alias of ADD.
-            insts.push(Inst::AluRRImm12 {
-                alu_op: ALUOp::Add64,
-                rd: writable_stack_reg(),
-                rn: fp_reg(),
-                imm12: Imm12 {
-                    bits: 0,
-                    shift12: false,
-                },
-            });
-            insts.push(Inst::LoadP64 {
-                rt: writable_fp_reg(),
-                rt2: writable_link_reg(),
-                mem: PairMemArg::PostIndexed(
-                    writable_stack_reg(),
-                    SImm7Scaled::maybe_from_i64(16, types::I64).unwrap(),
-                ),
-            });
             insts.push(Inst::Ret {});
         }
 
@@ -1243,6 +1302,15 @@ impl ABIBody for AArch64ABIBody {
         insts
     }
 
+    fn gen_tail_call_epilogue(&self) -> Vec<Inst> {
+        // Tail calls out of Baldrdash functions are rejected when lowering,
+        // so the frame is always ours to pop here.
+        debug_assert!(!self.call_conv.extends_baldrdash());
+        let insts = self.gen_frame_teardown();
+        debug!("Tail call epilogue: {:?}", insts);
+        insts
+    }
+
     fn frame_size(&self) -> u32 {
         self.total_frame_size
             .expect("frame size not computed before prologue generation")
@@ -1311,6 +1379,7 @@ impl AArch64ABICall {
         extname: &ir::ExternalName,
         dist: RelocDistance,
         loc: ir::SourceLoc,
+        opcode: ir::Opcode,
     ) -> CodegenResult<AArch64ABICall> {
         let sig = ABISig::from_func_sig(sig)?;
         let (uses, defs) = abisig_to_uses_and_defs(&sig);
@@ -1320,7 +1389,7 @@ impl AArch64ABICall {
             defs,
             dest: CallDest::ExtName(extname.clone(), dist),
             loc,
-            opcode: ir::Opcode::Call,
+            opcode,
         })
     }
 
@@ -1492,4 +1561,66 @@ impl ABICall for AArch64ABICall {
             }),
         }
     }
+
+    fn emit_tail_call<C: LowerCtx<I = Self::I>>(&mut self, ctx: &mut C) -> CodegenResult<()> {
+        if self.sig.stack_arg_space != 0 || self.sig.stack_ret_arg.is_some() {
+            return Err(CodegenError::Unsupported(
+                "tail calls with stack arguments or return values".into(),
+            ));
+        }
+        if self.sig.call_conv.extends_baldrdash() {
+            return Err(CodegenError::Unsupported(format!(
+                "tail calls with the {} calling convention",
+                self.sig.call_conv
+            )));
+        }
+
+        // The callee never returns here, so nothing is clobbered from our
+        // point of view; only the argument registers are used.
+        let uses = mem::replace(&mut self.uses, Default::default());
+        let defs = Vec::new();
+        match &self.dest {
+            &CallDest::ExtName(ref name, RelocDistance::Near) => ctx.emit(Inst::TailCall {
+                info: Box::new(CallInfo {
+                    dest: name.clone(),
+                    uses,
+                    defs,
+                    loc: self.loc,
+                    opcode: self.opcode,
+                }),
+            }),
+            &CallDest::ExtName(ref name, RelocDistance::Far) => {
+                ctx.emit(Inst::LoadExtName {
+                    rd: writable_spilltmp_reg(),
+                    name: Box::new(name.clone()),
+                    offset: 0,
+                    srcloc: self.loc,
+                });
+                ctx.emit(Inst::TailCallInd {
+                    info: Box::new(CallIndInfo {
+                        rn: spilltmp_reg(),
+                        uses,
+                        defs,
+                        loc: self.loc,
+                        opcode: self.opcode,
+                    }),
+                });
+            }
+            &CallDest::Reg(reg) => {
+                // The epilogue restores callee-saved registers before the
+                // jump, so the target must live in a register it leaves alone.
+                ctx.emit(Inst::mov(writable_spilltmp_reg(), reg));
+                ctx.emit(Inst::TailCallInd {
+                    info: Box::new(CallIndInfo {
+                        rn: spilltmp_reg(),
+                        uses,
+                        defs,
+                        loc: self.loc,
+                        opcode: self.opcode,
+                    }),
+                });
+            }
+        }
+        Ok(())
+    }
 }
@@ -3,7 +3,7 @@
 use crate::binemit::{CodeOffset, Reloc};
 use crate::ir::constant::ConstantData;
 use crate::ir::types::*;
-use crate::ir::TrapCode;
+use crate::ir::{AtomicRmwOp, TrapCode, Type};
 use crate::isa::aarch64::inst::*;
 use crate::isa::aarch64::lower::ty_bits;
 
@@ -375,6 +375,52 @@ fn enc_vec_lanes(q: u32, u: u32, size: u32, opcode: u32, rd: Writable<Reg>, rn:
         | machreg_to_vec(rd.to_reg())
 }
 
+/// The `size` field of the load/store-exclusive and load-acquire/store-release instructions.
+fn enc_ldst_ordered_size(ty: Type) -> u32 {
+    match ty {
+        I8 => 0b00,
+        I16 => 0b01,
+        I32 => 0b10,
+        I64 => 0b11,
+        _ => panic!("Unsupported type for an atomic access: {}", ty),
+    }
+}
+
+fn enc_ldaxr(ty: Type, rt: Writable<Reg>, rn: Reg) -> u32 {
+    0b00_001000_0_1_0_11111_1_11111_00000_00000
+        | enc_ldst_ordered_size(ty) << 30
+        | machreg_to_gpr(rn) << 5
+        | machreg_to_gpr(rt.to_reg())
+}
+
+fn enc_stlxr(ty: Type, rs: Writable<Reg>, rt: Reg, rn: Reg) -> u32 {
+    0b00_001000_0_0_0_00000_1_11111_00000_00000
+        | enc_ldst_ordered_size(ty) << 30
+        | machreg_to_gpr(rs.to_reg()) << 16
+        | machreg_to_gpr(rn) << 5
+        | machreg_to_gpr(rt)
+}
+
+fn enc_ldar(ty: Type, rt: Writable<Reg>, rn: Reg) -> u32 {
+    0b00_001000_1_1_0_11111_1_11111_00000_00000
+        | enc_ldst_ordered_size(ty) << 30
+        | machreg_to_gpr(rn) << 5
+        | machreg_to_gpr(rt.to_reg())
+}
+
+fn enc_stlr(ty: Type, rt: Reg, rn: Reg) -> u32 {
+    0b00_001000_1_0_0_11111_1_11111_00000_00000
+        | enc_ldst_ordered_size(ty) << 30
+        | machreg_to_gpr(rn) << 5
+        | machreg_to_gpr(rt)
+}
+
+/// Encodes a `CBNZ` of the 32-bit register `rt` to `insns` instructions before it.
+fn enc_cbnz32_backward(insns: u32, rt: Reg) -> u32 {
+    let off_18_0 = (insns as i32).wrapping_neg() as u32 & ((1 << 19) - 1);
+    enc_cmpbr(0b0_011010_1, off_18_0, rt)
+}
+
 /// State carried between emissions of a sequence of instructions.
 #[derive(Default, Clone, Debug)]
 pub struct EmitState {
@@ -916,6 +962,150 @@ impl MachInstEmit for Inst {
                     }
                 }
             }
+            &Inst::AtomicRMW {
+                ty,
+                op,
+                rd,
+                r_addr,
+                r_arg,
+                r_tmp,
+                r_status,
+                srcloc,
+            } => {
+                // This sequence is *one* instruction in the vcode, and is expanded only here at
+                // emission time, so that the regalloc can't insert spills or reloads between the
+                // exclusive load and store, which would clear the exclusive monitor.
+                //
+                // again:
+                //   ldaxr{b,h} rd, [r_addr]
+                //   <op> r_tmp, rd, r_arg
+                //   stlxr{b,h} r_status, r_tmp, [r_addr]
+                //   cbnz r_status, again
+                if let Some(srcloc) = srcloc {
+                    sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+                }
+                sink.put4(enc_ldaxr(ty, rd, r_addr.to_reg()));
+                let alu_op = match (op, ty == I64) {
+                    (AtomicRmwOp::Add, false) => Some(ALUOp::Add32),
+                    (AtomicRmwOp::Add, true) => Some(ALUOp::Add64),
+                    (AtomicRmwOp::Sub, false) => Some(ALUOp::Sub32),
+                    (AtomicRmwOp::Sub, true) => Some(ALUOp::Sub64),
+                    (AtomicRmwOp::And, false) => Some(ALUOp::And32),
+                    (AtomicRmwOp::And, true) => Some(ALUOp::And64),
+                    (AtomicRmwOp::Or, false) => Some(ALUOp::Orr32),
+                    (AtomicRmwOp::Or, true) => Some(ALUOp::Orr64),
+                    (AtomicRmwOp::Xor, false) => Some(ALUOp::Eor32),
+                    (AtomicRmwOp::Xor, true) => Some(ALUOp::Eor64),
+                    (AtomicRmwOp::Xchg, _) => None,
+                };
+                let inst = match alu_op {
+                    Some(alu_op) => Inst::AluRRR {
+                        alu_op,
+                        rd: r_tmp,
+                        rn: rd.to_reg(),
+                        rm: r_arg.to_reg(),
+                    },
+                    None => Inst::mov(r_tmp, r_arg.to_reg()),
+                };
+                inst.emit(sink, flags, state);
+                sink.put4(enc_stlxr(ty, r_status, r_tmp.to_reg(), r_addr.to_reg()));
+                sink.put4(enc_cbnz32_backward(3, r_status.to_reg()));
+            }
+            &Inst::AtomicCAS {
+                ty,
+                rd,
+                r_addr,
+                r_expected,
+                r_replacement,
+                r_status,
+                srcloc,
+            } => {
+                // As for `AtomicRMW`, this is expanded only here at emission time.
+                //
+                // again:
+                //   ldaxr{b,h} rd, [r_addr]
+                //   cmp rd, r_expected
+                //   b.ne out
+                //   stlxr{b,h} r_status, r_replacement, [r_addr]
+                //   cbnz r_status, again
+                // out:
+                if let Some(srcloc) = srcloc {
+                    sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+                }
+                sink.put4(enc_ldaxr(ty, rd, r_addr.to_reg()));
+                let inst = Inst::AluRRR {
+                    alu_op: if ty == I64 {
+                        ALUOp::SubS64
+                    } else {
+                        ALUOp::SubS32
+                    },
+                    rd: writable_zero_reg(),
+                    rn: rd.to_reg(),
+                    rm: r_expected.to_reg(),
+                };
+                inst.emit(sink, flags, state);
+                sink.put4(enc_cbr(0b01010100, 3, 0b0, Cond::Ne.bits()));
+                sink.put4(enc_stlxr(
+                    ty,
+                    r_status,
+                    r_replacement.to_reg(),
+                    r_addr.to_reg(),
+                ));
+                sink.put4(enc_cbnz32_backward(4, r_status.to_reg()));
+            }
+            &Inst::AtomicLoad {
+                ty,
+                rd,
+                r_addr,
+                srcloc,
+            } => {
+                if let Some(srcloc) = srcloc {
+                    sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+                }
+                sink.put4(enc_ldar(ty, rd, r_addr));
+            }
+            &Inst::AtomicStore {
+                ty,
+                rd,
+                r_addr,
+                srcloc,
+            } => {
+                if let Some(srcloc) = srcloc {
+                    sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+                }
+                sink.put4(enc_stlr(ty, rd, r_addr));
+            }
+            &Inst::Fence => {
+                sink.put4(0xd5033bbf);
+            }
+            &Inst::StackProbeLoop { counter, page } => {
+                // again:
+                //   sub sp, sp, #page
+                //   str xzr, [sp]
+                //   sub w_counter, w_counter, #1
+                //   cbnz w_counter, again
+                let inst = Inst::AluRRImm12 {
+                    alu_op: ALUOp::Sub64,
+                    rd: writable_stack_reg(),
+                    rn: stack_reg(),
+                    imm12: page,
+                };
+                inst.emit(sink, flags, state);
+                let inst = Inst::Store64 {
+                    rd: zero_reg(),
+                    mem: MemArg::reg(stack_reg()),
+                    srcloc: None,
+                };
+                inst.emit(sink, flags, state);
+                let inst = Inst::AluRRImm12 {
+                    alu_op: ALUOp::Sub32,
+                    rd: counter,
+                    rn: counter.to_reg(),
+                    imm12: Imm12::maybe_from_u64(1).unwrap(),
+                };
+                inst.emit(sink, flags, state);
+                sink.put4(enc_cbnz32_backward(3, counter.to_reg()));
+            }
             &Inst::Mov { rd, rm } => {
                 assert!(rd.to_reg().get_class() == rm.get_class());
                 assert!(rm.get_class() == RegClass::I64);
@@ -1449,6 +1639,14 @@ impl MachInstEmit for Inst {
                     sink.add_call_site(info.loc, info.opcode);
                 }
             }
+            &Inst::TailCall { ref info } => {
+                // No call site is recorded: control never comes back here.
+                sink.add_reloc(info.loc, Reloc::Arm64Call, &info.dest, 0);
+                sink.put4(enc_jump26(0b000101, 0));
+            }
+            &Inst::TailCallInd { ref info } => {
+                sink.put4(enc_br(info.rn));
+            }
             &Inst::CondBr {
                 taken,
                 not_taken,
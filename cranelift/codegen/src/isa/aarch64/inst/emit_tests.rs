@@ -1,4 +1,5 @@
 use crate::ir::types::*;
+use crate::ir::AtomicRmwOp;
 use crate::isa::aarch64::inst::*;
 use crate::isa::test_utils;
 use crate::settings;
@@ -1636,6 +1637,87 @@ fn test_aarch64_binemit() {
         "ldp x8, x25, [x12], #504",
     ));
 
+    insns.push((
+        Inst::AtomicRMW {
+            ty: I64,
+            op: AtomicRmwOp::Add,
+            rd: writable_xreg(27),
+            r_addr: writable_xreg(25),
+            r_arg: writable_xreg(26),
+            r_tmp: writable_xreg(28),
+            r_status: writable_xreg(24),
+            srcloc: None,
+        },
+        "3BFF5FC87C031A8B3CFF18C8B8FFFF35",
+        "ldaxr x27, [x25] ; add x28, x27, x26 ; stlxr w24, x28, [x25] ; cbnz w24, pc-12",
+    ));
+    insns.push((
+        Inst::AtomicRMW {
+            ty: I8,
+            op: AtomicRmwOp::Xchg,
+            rd: writable_xreg(27),
+            r_addr: writable_xreg(25),
+            r_arg: writable_xreg(26),
+            r_tmp: writable_xreg(28),
+            r_status: writable_xreg(24),
+            srcloc: None,
+        },
+        "3BFF5F08FC031AAA3CFF1808B8FFFF35",
+        "ldaxrb w27, [x25] ; mov w28, w26 ; stlxrb w24, w28, [x25] ; cbnz w24, pc-12",
+    ));
+    insns.push((
+        Inst::AtomicCAS {
+            ty: I32,
+            rd: writable_xreg(27),
+            r_addr: writable_xreg(25),
+            r_expected: writable_xreg(26),
+            r_replacement: writable_xreg(28),
+            r_status: writable_xreg(24),
+            srcloc: None,
+        },
+        "3BFF5F887F031A6B610000543CFF188898FFFF35",
+        "ldaxr w27, [x25] ; cmp w27, w26 ; b.ne pc+12 ; stlxr w24, w28, [x25] ; cbnz w24, pc-16",
+    ));
+    insns.push((
+        Inst::AtomicLoad {
+            ty: I16,
+            rd: writable_xreg(1),
+            r_addr: xreg(2),
+            srcloc: None,
+        },
+        "41FCDF48",
+        "ldarh w1, [x2]",
+    ));
+    insns.push((
+        Inst::AtomicLoad {
+            ty: I64,
+            rd: writable_xreg(1),
+            r_addr: xreg(2),
+            srcloc: None,
+        },
+        "41FCDFC8",
+        "ldar x1, [x2]",
+    ));
+    insns.push((
+        Inst::AtomicStore {
+            ty: I32,
+            rd: xreg(1),
+            r_addr: xreg(2),
+            srcloc: None,
+        },
+        "41FC9F88",
+        "stlr w1, [x2]",
+    ));
+    insns.push((Inst::Fence, "BF3B03D5", "dmb ish"));
+    insns.push((
+        Inst::StackProbeLoop {
+            counter: writable_xreg(16),
+            page: Imm12::maybe_from_u64(4096).unwrap(),
+        },
+        "FF0740D1FF0300F910060051B0FFFF35",
+        "sub sp, sp, #4096 ; str xzr, [sp] ; sub w16, w16, #1 ; cbnz w16, pc-12",
+    ));
+
     insns.push((
         Inst::Mov {
             rd: writable_xreg(8),
@@ -2688,6 +2770,34 @@ fn test_aarch64_binemit() {
         "blr x10",
     ));
 
+    insns.push((
+        Inst::TailCall {
+            info: Box::new(CallInfo {
+                dest: ExternalName::testcase("test0"),
+                uses: Vec::new(),
+                defs: Vec::new(),
+                loc: SourceLoc::default(),
+                opcode: Opcode::ReturnCall,
+            }),
+        },
+        "00000014",
+        "b 0",
+    ));
+
+    insns.push((
+        Inst::TailCallInd {
+            info: Box::new(CallIndInfo {
+                rn: xreg(16),
+                uses: Vec::new(),
+                defs: Vec::new(),
+                loc: SourceLoc::default(),
+                opcode: Opcode::ReturnCallIndirect,
+            }),
+        },
+        "00021FD6",
+        "br x16",
+    ));
+
     insns.push((
         Inst::IndirectBr {
             rn: xreg(3),
@@ -8,7 +8,7 @@ use crate::ir::types::{
     B1, B16, B16X8, B32, B32X4, B64, B64X2, B8, B8X16, F32, F32X2, F32X4, F64, F64X2, FFLAGS, I128,
     I16, I16X4, I16X8, I32, I32X2, I32X4, I64, I64X2, I8, I8X16, I8X8, IFLAGS,
 };
-use crate::ir::{ExternalName, Opcode, SourceLoc, TrapCode, Type};
+use crate::ir::{AtomicRmwOp, ExternalName, Opcode, SourceLoc, TrapCode, Type};
 use crate::machinst::*;
 use crate::{settings, CodegenError, CodegenResult};
 
@@ -490,6 +490,64 @@ pub enum Inst {
         mem: PairMemArg,
     },
 
+    /// An atomic read-modify-write sequence, as one compound instruction: an `LDAXR` of the old
+    /// value of the `ty`-sized location at `r_addr` into `rd`, the computation of `op` on it and
+    /// `r_arg` into `r_tmp`, and an `STLXR` of `r_tmp`, repeated until the exclusive store
+    /// succeeds. `r_addr` and `r_arg` are marked as modified, although they're preserved, so that
+    /// the register allocator doesn't assign them the same registers as the outputs.
+    AtomicRMW {
+        ty: Type,
+        op: AtomicRmwOp,
+        rd: Writable<Reg>,
+        r_addr: Writable<Reg>,
+        r_arg: Writable<Reg>,
+        r_tmp: Writable<Reg>,
+        r_status: Writable<Reg>,
+        srcloc: Option<SourceLoc>,
+    },
+
+    /// An atomic compare-and-swap sequence, as one compound instruction: an `LDAXR` of the old
+    /// value of the `ty`-sized location at `r_addr` into `rd` and, if it's equal to `r_expected`,
+    /// an `STLXR` of `r_replacement`, repeated until the exclusive store succeeds. `r_expected`
+    /// must be zero-extended for 8- and 16-bit types. As in `AtomicRMW`, the inputs are marked as
+    /// modified.
+    AtomicCAS {
+        ty: Type,
+        rd: Writable<Reg>,
+        r_addr: Writable<Reg>,
+        r_expected: Writable<Reg>,
+        r_replacement: Writable<Reg>,
+        r_status: Writable<Reg>,
+        srcloc: Option<SourceLoc>,
+    },
+
+    /// A load-acquire (`LDAR`) of the `ty`-sized location at `r_addr`, zero-extended.
+    AtomicLoad {
+        ty: Type,
+        rd: Writable<Reg>,
+        r_addr: Reg,
+        srcloc: Option<SourceLoc>,
+    },
+
+    /// A store-release (`STLR`) of `rd` to the `ty`-sized location at `r_addr`.
+    AtomicStore {
+        ty: Type,
+        rd: Reg,
+        r_addr: Reg,
+        srcloc: Option<SourceLoc>,
+    },
+
+    /// A full memory barrier (`DMB ISH`).
+    Fence,
+
+    /// A stack probe loop, as one compound instruction: moves SP down by `page` bytes as many
+    /// times as the initial value of `counter`, storing zero to the new top of stack after every
+    /// step. `counter` is decremented down to zero along the way.
+    StackProbeLoop {
+        counter: Writable<Reg>,
+        page: Imm12,
+    },
+
     /// A MOV instruction. These are encoded as ORR's (AluRRR form) but we
     /// keep them separate at the `Inst` level for better pretty-printing
     /// and faster `is_move()` logic.
@@ -794,6 +852,18 @@ pub enum Inst {
         info: Box<CallIndInfo>,
     },
 
+    /// A tail call: a direct branch to another function, made after the epilogue has popped this
+    /// function's frame. Like `Call`, this allows only a +/- 128MB offset; far destinations use a
+    /// `LoadExtName` / `TailCallInd` sequence instead.
+    TailCall {
+        info: Box<CallInfo>,
+    },
+    /// An indirect tail call. The target register must not be restored by the epilogue, so it is
+    /// always the spilltmp register.
+    TailCallInd {
+        info: Box<CallIndInfo>,
+    },
+
     // ---- branches (exactly one must appear at end of BB) ----
     /// A machine return instruction.
     Ret,
@@ -1174,6 +1244,46 @@ fn aarch64_get_regs(inst: &Inst, collector: &mut RegUsageCollector) {
             collector.add_def(rt2);
             pairmemarg_regs(mem, collector);
         }
+        &Inst::AtomicRMW {
+            rd,
+            r_addr,
+            r_arg,
+            r_tmp,
+            r_status,
+            ..
+        } => {
+            collector.add_def(rd);
+            collector.add_mod(r_addr);
+            collector.add_mod(r_arg);
+            collector.add_def(r_tmp);
+            collector.add_def(r_status);
+        }
+        &Inst::AtomicCAS {
+            rd,
+            r_addr,
+            r_expected,
+            r_replacement,
+            r_status,
+            ..
+        } => {
+            collector.add_def(rd);
+            collector.add_mod(r_addr);
+            collector.add_mod(r_expected);
+            collector.add_mod(r_replacement);
+            collector.add_def(r_status);
+        }
+        &Inst::AtomicLoad { rd, r_addr, .. } => {
+            collector.add_def(rd);
+            collector.add_use(r_addr);
+        }
+        &Inst::AtomicStore { rd, r_addr, .. } => {
+            collector.add_use(rd);
+            collector.add_use(r_addr);
+        }
+        &Inst::Fence => {}
+        &Inst::StackProbeLoop { counter, .. } => {
+            collector.add_mod(counter);
+        }
         &Inst::Mov { rd, rm } => {
             collector.add_def(rd);
             collector.add_use(rm);
@@ -1346,6 +1456,13 @@ fn aarch64_get_regs(inst: &Inst, collector: &mut RegUsageCollector) {
             collector.add_defs(&*info.defs);
             collector.add_use(info.rn);
         }
+        &Inst::TailCall { ref info } => {
+            collector.add_uses(&*info.uses);
+        }
+        &Inst::TailCallInd { ref info } => {
+            collector.add_uses(&*info.uses);
+            collector.add_use(info.rn);
+        }
         &Inst::CondBr { ref kind, .. } | &Inst::OneWayCondBr { ref kind, .. } => match kind {
             CondBrKind::Zero(rt) | CondBrKind::NotZero(rt) => {
                 collector.add_use(*rt);
@@ -1629,6 +1746,56 @@ fn aarch64_map_regs<RUM: RegUsageMapper>(inst: &mut Inst, mapper: &RUM) {
             map_def(mapper, rt2);
             map_pairmem(mapper, mem);
         }
+        &mut Inst::AtomicRMW {
+            ref mut rd,
+            ref mut r_addr,
+            ref mut r_arg,
+            ref mut r_tmp,
+            ref mut r_status,
+            ..
+        } => {
+            map_def(mapper, rd);
+            map_mod(mapper, r_addr);
+            map_mod(mapper, r_arg);
+            map_def(mapper, r_tmp);
+            map_def(mapper, r_status);
+        }
+        &mut Inst::AtomicCAS {
+            ref mut rd,
+            ref mut r_addr,
+            ref mut r_expected,
+            ref mut r_replacement,
+            ref mut r_status,
+            ..
+        } => {
+            map_def(mapper, rd);
+            map_mod(mapper, r_addr);
+            map_mod(mapper, r_expected);
+            map_mod(mapper, r_replacement);
+            map_def(mapper, r_status);
+        }
+        &mut Inst::AtomicLoad {
+            ref mut rd,
+            ref mut r_addr,
+            ..
+        } => {
+            map_def(mapper, rd);
+            map_use(mapper, r_addr);
+        }
+        &mut Inst::AtomicStore {
+            ref mut rd,
+            ref mut r_addr,
+            ..
+        } => {
+            map_use(mapper, rd);
+            map_use(mapper, r_addr);
+        }
+        &mut Inst::Fence => {}
+        &mut Inst::StackProbeLoop {
+            ref mut counter, ..
+        } => {
+            map_mod(mapper, counter);
+        }
         &mut Inst::Mov {
             ref mut rd,
             ref mut rm,
@@ -1949,6 +2116,17 @@ fn aarch64_map_regs<RUM: RegUsageMapper>(inst: &mut Inst, mapper: &RUM) {
             }
             map_use(mapper, &mut info.rn);
         }
+        &mut Inst::TailCall { ref mut info } => {
+            for r in info.uses.iter_mut() {
+                map_use(mapper, r);
+            }
+        }
+        &mut Inst::TailCallInd { ref mut info } => {
+            for r in info.uses.iter_mut() {
+                map_use(mapper, r);
+            }
+            map_use(mapper, &mut info.rn);
+        }
         &mut Inst::CondBr { ref mut kind, .. } | &mut Inst::OneWayCondBr { ref mut kind, .. } => {
             map_br(mapper, kind);
         }
@@ -2022,6 +2200,7 @@ impl MachInst for Inst {
     fn is_term<'a>(&'a self) -> MachTerminator<'a> {
         match self {
             &Inst::Ret | &Inst::EpiloguePlaceholder => MachTerminator::Ret,
+            &Inst::TailCall { .. } | &Inst::TailCallInd { .. } => MachTerminator::TailCall,
             &Inst::Jump { dest } => MachTerminator::Uncond(dest.as_label().unwrap()),
             &Inst::CondBr {
                 taken, not_taken, ..
@@ -2140,6 +2319,16 @@ fn mem_finalize_for_show(mem: &MemArg, mb_rru: Option<&RealRegUniverse>) -> (Str
     (mem_str, mem)
 }
 
+/// The suffix of the names of the load/store-exclusive and load-acquire/store-release
+/// instructions accessing `ty`-sized locations.
+fn atomic_suffix(ty: Type) -> &'static str {
+    match ty {
+        I8 => "b",
+        I16 => "h",
+        _ => "",
+    }
+}
+
 impl ShowWithRRU for Inst {
     fn show_rru(&self, mb_rru: Option<&RealRegUniverse>) -> String {
         fn op_name_size(alu_op: ALUOp) -> (&'static str, InstSize) {
@@ -2409,6 +2598,96 @@ impl ShowWithRRU for Inst {
                 let mem = mem.show_rru_sized(mb_rru, /* size = */ 8);
                 format!("ldp {}, {}, {}", rt, rt2, mem)
             }
+            &Inst::AtomicRMW {
+                ty,
+                op,
+                rd,
+                r_addr,
+                r_arg,
+                r_tmp,
+                r_status,
+                ..
+            } => {
+                let size = InstSize::from_ty(ty);
+                let suffix = atomic_suffix(ty);
+                let rd = show_ireg_sized(rd.to_reg(), mb_rru, size);
+                let r_addr = r_addr.to_reg().show_rru(mb_rru);
+                let r_arg = show_ireg_sized(r_arg.to_reg(), mb_rru, size);
+                let r_tmp = show_ireg_sized(r_tmp.to_reg(), mb_rru, size);
+                let r_status = show_ireg_sized(r_status.to_reg(), mb_rru, InstSize::Size32);
+                let op = match op {
+                    AtomicRmwOp::Add => format!("add {}, {}, {}", r_tmp, rd, r_arg),
+                    AtomicRmwOp::Sub => format!("sub {}, {}, {}", r_tmp, rd, r_arg),
+                    AtomicRmwOp::And => format!("and {}, {}, {}", r_tmp, rd, r_arg),
+                    AtomicRmwOp::Or => format!("orr {}, {}, {}", r_tmp, rd, r_arg),
+                    AtomicRmwOp::Xor => format!("eor {}, {}, {}", r_tmp, rd, r_arg),
+                    AtomicRmwOp::Xchg => format!("mov {}, {}", r_tmp, r_arg),
+                };
+                format!(
+                    "ldaxr{} {}, [{}] ; {} ; stlxr{} {}, {}, [{}] ; cbnz {}, pc-12",
+                    suffix, rd, r_addr, op, suffix, r_status, r_tmp, r_addr, r_status
+                )
+            }
+            &Inst::AtomicCAS {
+                ty,
+                rd,
+                r_addr,
+                r_expected,
+                r_replacement,
+                r_status,
+                ..
+            } => {
+                let size = InstSize::from_ty(ty);
+                let suffix = atomic_suffix(ty);
+                let rd = show_ireg_sized(rd.to_reg(), mb_rru, size);
+                let r_addr = r_addr.to_reg().show_rru(mb_rru);
+                let r_expected = show_ireg_sized(r_expected.to_reg(), mb_rru, size);
+                let r_replacement = show_ireg_sized(r_replacement.to_reg(), mb_rru, size);
+                let r_status = show_ireg_sized(r_status.to_reg(), mb_rru, InstSize::Size32);
+                format!(
+                    concat!(
+                        "ldaxr{} {}, [{}] ; ",
+                        "cmp {}, {} ; ",
+                        "b.ne pc+12 ; ",
+                        "stlxr{} {}, {}, [{}] ; ",
+                        "cbnz {}, pc-16"
+                    ),
+                    suffix,
+                    rd,
+                    r_addr,
+                    rd,
+                    r_expected,
+                    suffix,
+                    r_status,
+                    r_replacement,
+                    r_addr,
+                    r_status
+                )
+            }
+            &Inst::AtomicLoad { ty, rd, r_addr, .. } => {
+                let rd = show_ireg_sized(rd.to_reg(), mb_rru, InstSize::from_ty(ty));
+                let r_addr = r_addr.show_rru(mb_rru);
+                format!("ldar{} {}, [{}]", atomic_suffix(ty), rd, r_addr)
+            }
+            &Inst::AtomicStore { ty, rd, r_addr, .. } => {
+                let rd = show_ireg_sized(rd, mb_rru, InstSize::from_ty(ty));
+                let r_addr = r_addr.show_rru(mb_rru);
+                format!("stlr{} {}, [{}]", atomic_suffix(ty), rd, r_addr)
+            }
+            &Inst::Fence => "dmb ish".to_string(),
+            &Inst::StackProbeLoop { counter, page } => {
+                let counter = show_ireg_sized(counter.to_reg(), mb_rru, InstSize::Size32);
+                let page = page.show_rru(mb_rru);
+                format!(
+                    concat!(
+                        "sub sp, sp, {} ; ",
+                        "str xzr, [sp] ; ",
+                        "sub {}, {}, #1 ; ",
+                        "cbnz {}, pc-12"
+                    ),
+                    page, counter, counter, counter
+                )
+            }
             &Inst::Mov { rd, rm } => {
                 let rd = rd.to_reg().show_rru(mb_rru);
                 let rm = rm.show_rru(mb_rru);
@@ -2852,6 +3131,11 @@ impl ShowWithRRU for Inst {
                 let rn = info.rn.show_rru(mb_rru);
                 format!("blr {}", rn)
             }
+            &Inst::TailCall { .. } => format!("b 0"),
+            &Inst::TailCallInd { ref info, .. } => {
+                let rn = info.rn.show_rru(mb_rru);
+                format!("br {}", rn)
+            }
             &Inst::Ret => "ret".to_string(),
             &Inst::EpiloguePlaceholder => "epilogue placeholder".to_string(),
             &Inst::Jump { ref dest } => {
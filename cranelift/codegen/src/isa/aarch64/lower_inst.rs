@@ -988,6 +988,90 @@ pub(crate) fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(
             });
         }
 
+        Opcode::AtomicRmw | Opcode::AtomicCas => {
+            let ty = ty.unwrap();
+            let memflags = ctx.memflags(insn).expect("memory flags");
+            let srcloc = if !memflags.notrap() {
+                Some(ctx.srcloc(insn))
+            } else {
+                None
+            };
+            // The sequences modify their inputs (see `Inst::AtomicRMW`), so copy them into
+            // temporaries first.
+            let copy_input = |ctx: &mut C, input, narrow_mode| {
+                let reg = put_input_in_reg(ctx, input, narrow_mode);
+                let tmp = ctx.alloc_tmp(RegClass::I64, I64);
+                ctx.emit(Inst::gen_move(tmp, reg, I64));
+                tmp
+            };
+            let r_addr = copy_input(ctx, inputs[0], NarrowValueMode::None);
+            let rd = get_output_reg(ctx, outputs[0]);
+            let r_status = ctx.alloc_tmp(RegClass::I64, I32);
+            if op == Opcode::AtomicRmw {
+                let op = match *ctx.data(insn) {
+                    InstructionData::AtomicRmw { op, .. } => op,
+                    _ => unreachable!(),
+                };
+                let r_arg = copy_input(ctx, inputs[1], NarrowValueMode::None);
+                let r_tmp = ctx.alloc_tmp(RegClass::I64, I64);
+                ctx.emit(Inst::AtomicRMW {
+                    ty,
+                    op,
+                    rd,
+                    r_addr,
+                    r_arg,
+                    r_tmp,
+                    r_status,
+                    srcloc,
+                });
+            } else {
+                // The loaded value is zero-extended, so the expected one must be as well.
+                let r_expected = copy_input(ctx, inputs[1], NarrowValueMode::ZeroExtend32);
+                let r_replacement = copy_input(ctx, inputs[2], NarrowValueMode::None);
+                ctx.emit(Inst::AtomicCAS {
+                    ty,
+                    rd,
+                    r_addr,
+                    r_expected,
+                    r_replacement,
+                    r_status,
+                    srcloc,
+                });
+            }
+        }
+
+        Opcode::AtomicLoad | Opcode::AtomicStore => {
+            let memflags = ctx.memflags(insn).expect("memory flags");
+            let srcloc = if !memflags.notrap() {
+                Some(ctx.srcloc(insn))
+            } else {
+                None
+            };
+            if op == Opcode::AtomicLoad {
+                let r_addr = put_input_in_reg(ctx, inputs[0], NarrowValueMode::None);
+                let rd = get_output_reg(ctx, outputs[0]);
+                ctx.emit(Inst::AtomicLoad {
+                    ty: ty.unwrap(),
+                    rd,
+                    r_addr,
+                    srcloc,
+                });
+            } else {
+                let rd = put_input_in_reg(ctx, inputs[0], NarrowValueMode::None);
+                let r_addr = put_input_in_reg(ctx, inputs[1], NarrowValueMode::None);
+                ctx.emit(Inst::AtomicStore {
+                    ty: ctx.input_ty(insn, 0),
+                    rd,
+                    r_addr,
+                    srcloc,
+                });
+            }
+        }
+
+        Opcode::Fence => {
+            ctx.emit(Inst::Fence);
+        }
+
         Opcode::StackAddr => {
             let (stack_slot, offset) = match *ctx.data(insn) {
                 InstructionData::StackLoad {
@@ -1383,7 +1467,7 @@ pub(crate) fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(
                     assert!(inputs.len() == sig.params.len());
                     assert!(outputs.len() == sig.returns.len());
                     (
-                        AArch64ABICall::from_func(sig, &extname, dist, loc)?,
+                        AArch64ABICall::from_func(sig, &extname, dist, loc, op)?,
                         &inputs[..],
                     )
                 }
@@ -1411,6 +1495,38 @@ pub(crate) fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(
             abi.emit_stack_post_adjust(ctx);
         }
 
+        Opcode::ReturnCall | Opcode::ReturnCallIndirect => {
+            let loc = ctx.srcloc(insn);
+            let (mut abi, inputs) = match op {
+                Opcode::ReturnCall => {
+                    let (extname, dist) = ctx.call_target(insn).unwrap();
+                    let extname = extname.clone();
+                    let sig = ctx.call_sig(insn).unwrap();
+                    assert!(inputs.len() == sig.params.len());
+                    (
+                        AArch64ABICall::from_func(sig, &extname, dist, loc, op)?,
+                        &inputs[..],
+                    )
+                }
+                Opcode::ReturnCallIndirect => {
+                    let ptr = put_input_in_reg(ctx, inputs[0], NarrowValueMode::ZeroExtend64);
+                    let sig = ctx.call_sig(insn).unwrap();
+                    assert!(inputs.len() - 1 == sig.params.len());
+                    (AArch64ABICall::from_ptr(sig, ptr, loc, op)?, &inputs[1..])
+                }
+                _ => unreachable!(),
+            };
+
+            // The frame is reused, so there is no stack adjustment around the
+            // call and no return values to copy back.
+            assert!(inputs.len() == abi.num_args());
+            for (i, input) in inputs.iter().enumerate() {
+                let arg_reg = put_input_in_reg(ctx, *input, NarrowValueMode::None);
+                abi.emit_copy_reg_to_arg(ctx, i, arg_reg);
+            }
+            abi.emit_tail_call(ctx)?;
+        }
+
         Opcode::GetPinnedReg => {
             let rd = get_output_reg(ctx, outputs[0]);
             ctx.emit(Inst::mov(rd, xreg(PINNED_REG)));
@@ -2040,6 +2156,7 @@ pub(crate) fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(
         | Opcode::X86Fmax
         | Opcode::X86Push
         | Opcode::X86Pop
+        | Opcode::X86ProbestackLoop
         | Opcode::X86Bsr
         | Opcode::X86Bsf
         | Opcode::X86Pblendw
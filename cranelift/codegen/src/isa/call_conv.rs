@@ -25,6 +25,9 @@ pub enum CallConv {
     BaldrdashWindows,
     /// Specialized convention for the probestack function
     Probestack,
+    /// Convention for functions performing tail calls with `return_call`, using the System V
+    /// register assignment on all platforms
+    Tail,
 }
 
 impl CallConv {
@@ -50,6 +53,7 @@ impl CallConv {
             LibcallCallConv::BaldrdashSystemV => Self::BaldrdashSystemV,
             LibcallCallConv::BaldrdashWindows => Self::BaldrdashWindows,
             LibcallCallConv::Probestack => Self::Probestack,
+            LibcallCallConv::Tail => Self::Tail,
         }
     }
 
@@ -80,6 +84,7 @@ impl fmt::Display for CallConv {
             Self::BaldrdashSystemV => "baldrdash_system_v",
             Self::BaldrdashWindows => "baldrdash_windows",
             Self::Probestack => "probestack",
+            Self::Tail => "tail",
         })
     }
 }
@@ -95,6 +100,7 @@ impl str::FromStr for CallConv {
             "baldrdash_system_v" => Ok(Self::BaldrdashSystemV),
             "baldrdash_windows" => Ok(Self::BaldrdashWindows),
             "probestack" => Ok(Self::Probestack),
+            "tail" => Ok(Self::Tail),
             _ => Err(()),
         }
     }
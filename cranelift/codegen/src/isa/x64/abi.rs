@@ -18,6 +18,10 @@ use args::*;
 /// with 32-bit arithmetic: for now, 128 MB.
 static STACK_ARG_RET_SIZE_LIMIT: u64 = 128 * 1024 * 1024;
 
+/// The largest number of guard-sized pages whose inline stack probes are unrolled in the prologue;
+/// larger frames are probed in a loop.
+const PROBESTACK_UNROLL_PAGES: u32 = 3;
+
 #[derive(Clone, Debug)]
 enum ABIArg {
     Reg(RealReg, ir::Type),
@@ -68,6 +72,17 @@ pub(crate) struct X64ABIBody {
 
     /// The settings controlling this function's compilation.
     flags: settings::Flags,
+
+    /// Whether or not this function is a "leaf", meaning it calls no other functions.
+    is_leaf: bool,
+
+    /// If this function has a stack limit specified, then `Reg` is where the stack limit will be
+    /// located after the instructions specified have been executed.
+    ///
+    /// The instructions are inserted in the prologue, after register allocation, so they're
+    /// manually register-allocated and only use caller-saved registers that aren't used for
+    /// arguments.
+    stack_limit: Option<(Reg, Vec<Inst>)>,
 }
 
 fn in_int_reg(ty: types::Type) -> bool {
@@ -80,7 +95,9 @@ fn in_int_reg(ty: types::Type) -> bool {
         | types::B8
         | types::B16
         | types::B32
-        | types::B64 => true,
+        | types::B64
+        | types::R32
+        | types::R64 => true,
         _ => false,
     }
 }
@@ -88,11 +105,30 @@ fn in_int_reg(ty: types::Type) -> bool {
 fn in_vec_reg(ty: types::Type) -> bool {
     match ty {
         types::F32 | types::F64 => true,
+        types::B8X16
+        | types::I8X16
+        | types::B16X8
+        | types::I16X8
+        | types::B32X4
+        | types::I32X4
+        | types::F32X4
+        | types::B64X2
+        | types::I64X2
+        | types::F64X2 => true,
         _ => false,
     }
 }
 
-fn get_intreg_for_arg_systemv(idx: usize) -> Option<Reg> {
+fn get_intreg_for_arg(call_conv: isa::CallConv, idx: usize) -> Option<Reg> {
+    if call_conv == isa::CallConv::WindowsFastcall {
+        return match idx {
+            0 => Some(regs::rcx()),
+            1 => Some(regs::rdx()),
+            2 => Some(regs::r8()),
+            3 => Some(regs::r9()),
+            _ => None,
+        };
+    }
     match idx {
         0 => Some(regs::rdi()),
         1 => Some(regs::rsi()),
@@ -104,7 +140,16 @@ fn get_intreg_for_arg_systemv(idx: usize) -> Option<Reg> {
     }
 }
 
-fn get_fltreg_for_arg_systemv(idx: usize) -> Option<Reg> {
+fn get_fltreg_for_arg(call_conv: isa::CallConv, idx: usize) -> Option<Reg> {
+    if call_conv == isa::CallConv::WindowsFastcall {
+        return match idx {
+            0 => Some(regs::xmm0()),
+            1 => Some(regs::xmm1()),
+            2 => Some(regs::xmm2()),
+            3 => Some(regs::xmm3()),
+            _ => None,
+        };
+    }
     match idx {
         0 => Some(regs::xmm0()),
         1 => Some(regs::xmm1()),
@@ -118,18 +163,20 @@ fn get_fltreg_for_arg_systemv(idx: usize) -> Option<Reg> {
     }
 }
 
-fn get_intreg_for_retval_systemv(idx: usize) -> Option<Reg> {
-    match idx {
-        0 => Some(regs::rax()),
-        1 => Some(regs::rdx()),
+fn get_intreg_for_retval(call_conv: isa::CallConv, idx: usize) -> Option<Reg> {
+    match (idx, call_conv) {
+        (0, _) => Some(regs::rax()),
+        (1, isa::CallConv::WindowsFastcall) => None,
+        (1, _) => Some(regs::rdx()),
         _ => None,
     }
 }
 
-fn get_fltreg_for_retval_systemv(idx: usize) -> Option<Reg> {
-    match idx {
-        0 => Some(regs::xmm0()),
-        1 => Some(regs::xmm1()),
+fn get_fltreg_for_retval(call_conv: isa::CallConv, idx: usize) -> Option<Reg> {
+    match (idx, call_conv) {
+        (0, _) => Some(regs::xmm0()),
+        (1, isa::CallConv::WindowsFastcall) => None,
+        (1, _) => Some(regs::xmm1()),
         _ => None,
     }
 }
@@ -142,16 +189,99 @@ fn is_callee_save_systemv(r: RealReg) -> bool {
             _ => false,
         },
         RegClass::V128 => false,
-        _ => unimplemented!(),
+        _ => unreachable!("x64 only has integer and vector registers"),
+    }
+}
+
+fn is_callee_save_fastcall(r: RealReg) -> bool {
+    use regs::*;
+    match r.get_class() {
+        RegClass::I64 => match r.get_hw_encoding() as u8 {
+            ENC_RBX | ENC_RBP | ENC_RSI | ENC_RDI | ENC_R12 | ENC_R13 | ENC_R14 | ENC_R15 => true,
+            _ => false,
+        },
+        // XMM6 to XMM15 are callee-saved.
+        RegClass::V128 => r.get_hw_encoding() >= 6,
+        _ => unreachable!("x64 only has integer and vector registers"),
     }
 }
 
-fn get_callee_saves(regs: Vec<Writable<RealReg>>) -> Vec<Writable<RealReg>> {
+fn get_callee_saves(
+    call_conv: isa::CallConv,
+    regs: Vec<Writable<RealReg>>,
+) -> Vec<Writable<RealReg>> {
     regs.into_iter()
-        .filter(|r| is_callee_save_systemv(r.to_reg()))
+        .filter(|r| match call_conv {
+            isa::CallConv::WindowsFastcall => is_callee_save_fastcall(r.to_reg()),
+            _ => is_callee_save_systemv(r.to_reg()),
+        })
         .collect()
 }
 
+/// Generates the instructions necessary for the `gv` to be materialized into a register.
+///
+/// Global values are usually lowered by the legalizer, but the prologue is generated too late in
+/// the pipeline for this, so only the few global values used for stack limits are supported
+/// here. Intermediate values are loaded in %r10, which isn't used to pass arguments and is
+/// caller-saved.
+fn gen_stack_limit(
+    f: &ir::Function,
+    abi: &ABISig,
+    gv: ir::GlobalValue,
+) -> CodegenResult<(Reg, Vec<Inst>)> {
+    let mut insts = Vec::new();
+    let reg = generate_gv(f, abi, gv, &mut insts)?;
+    return Ok((reg, insts));
+
+    fn generate_gv(
+        f: &ir::Function,
+        abi: &ABISig,
+        gv: ir::GlobalValue,
+        insts: &mut Vec<Inst>,
+    ) -> CodegenResult<Reg> {
+        match f.global_values[gv] {
+            // Return the direct register the vmcontext is in.
+            ir::GlobalValueData::VMContext => {
+                get_special_purpose_param_register(f, abi, ir::ArgumentPurpose::VMContext)
+                    .ok_or_else(|| {
+                        CodegenError::Unsupported(
+                            "stack limit without a vmctx parameter in the x64 backend".into(),
+                        )
+                    })
+            }
+            // Load our base value into a register, then load from that register into a
+            // temporary register.
+            ir::GlobalValueData::Load { base, offset, .. } => {
+                let base = generate_gv(f, abi, base, insts)?;
+                let into_reg = Writable::from_reg(regs::r10());
+                let offset: i32 = offset.into();
+                insts.push(Inst::mov64_m_r(
+                    Amode::imm_reg(offset as u32, base),
+                    into_reg,
+                    None,
+                ));
+                Ok(into_reg.to_reg())
+            }
+            ref other => Err(CodegenError::Unsupported(format!(
+                "global value {} for the stack limit in the x64 backend",
+                other
+            ))),
+        }
+    }
+}
+
+fn get_special_purpose_param_register(
+    f: &ir::Function,
+    abi: &ABISig,
+    purpose: ir::ArgumentPurpose,
+) -> Option<Reg> {
+    let idx = f.signature.special_param_index(purpose)?;
+    match abi.args[idx] {
+        ABIArg::Reg(reg, _) => Some(reg.to_reg()),
+        ABIArg::Stack(..) => None,
+    }
+}
+
 impl X64ABIBody {
     /// Create a new body ABI instance.
     pub(crate) fn new(f: &ir::Function, flags: settings::Flags) -> CodegenResult<Self> {
@@ -159,7 +289,12 @@ impl X64ABIBody {
 
         let call_conv = f.signature.call_conv;
         debug_assert!(
-            call_conv == isa::CallConv::SystemV || call_conv.extends_baldrdash(),
+            call_conv == isa::CallConv::SystemV
+                || call_conv == isa::CallConv::WindowsFastcall
+                || call_conv == isa::CallConv::Fast
+                || call_conv == isa::CallConv::Cold
+                || call_conv == isa::CallConv::Tail
+                || call_conv.extends_baldrdash(),
             "unsupported or unimplemented calling convetion {}",
             call_conv
         );
@@ -175,6 +310,18 @@ impl X64ABIBody {
             stack_slots.push(off);
         }
 
+        // Figure out what instructions, if any, will be needed to check the stack limit. This
+        // can either be specified as a special-purpose argument or as a global value which often
+        // calculates the stack limit from the arguments.
+        let stack_limit =
+            match get_special_purpose_param_register(f, &sig, ir::ArgumentPurpose::StackLimit) {
+                Some(reg) => Some((reg, Vec::new())),
+                None => match f.stack_limit {
+                    Some(gv) => Some(gen_stack_limit(f, &sig, gv)?),
+                    None => None,
+                },
+            };
+
         Ok(Self {
             sig,
             stack_slots,
@@ -185,6 +332,8 @@ impl X64ABIBody {
             frame_size_bytes: None,
             call_conv: f.signature.call_conv.clone(),
             flags,
+            is_leaf: f.is_leaf(),
+            stack_limit,
         })
     }
 
@@ -200,6 +349,166 @@ impl X64ABIBody {
             16 // frame pointer + return address.
         }
     }
+
+    /// Inserts instructions necessary for checking the stack limit into the prologue.
+    ///
+    /// The stack check traps if the stack pointer would go below the threshold held in
+    /// `stack_limit` once `stack_size` bytes have been allocated. This happens after register
+    /// allocation, so this only uses %r11 as a temporary: it's caller-saved and not used for
+    /// arguments, and nothing is live in it at this point.
+    fn insert_stack_check(&self, stack_limit: Reg, stack_size: u32, insts: &mut Vec<Inst>) {
+        // With no explicit stack allocated we can just emit the simple check of the stack
+        // pointer against the stack limit register, and trap if it's out of bounds.
+        if stack_size == 0 {
+            return push_check(stack_limit, insts);
+        }
+
+        // Note that the 32k stack size here is pretty special. See the documentation in
+        // x86/abi.rs for why this is here. The general idea is that we're protecting against
+        // overflow in the addition that happens below.
+        if stack_size >= 32 * 1024 {
+            push_check(stack_limit, insts);
+        }
+
+        // Add the `stack_size` to `stack_limit`, placing the result in a scratch register.
+        let scratch = Writable::from_reg(regs::r11());
+        insts.push(Inst::lea(Amode::imm_reg(stack_size, stack_limit), scratch));
+        push_check(scratch.to_reg(), insts);
+
+        fn push_check(stack_limit: Reg, insts: &mut Vec<Inst>) {
+            // Trap if %rsp is (unsigned) below the limit.
+            insts.push(Inst::cmp_rmi_r(8, RegMemImm::reg(stack_limit), regs::rsp()));
+            insts.push(Inst::trap_if(
+                CC::B,
+                ir::TrapCode::StackOverflow,
+                ir::SourceLoc::default(),
+            ));
+        }
+    }
+
+    /// Allocates `frame_size` bytes of stack in the prologue.
+    ///
+    /// When inline stack probes are enabled and the frame is larger than the guard page, the frame
+    /// is allocated at most one page at a time, storing %rsp to the new top of stack after every
+    /// step so that the guard page is hit before anything beyond it. Up to
+    /// `PROBESTACK_UNROLL_PAGES` pages, the sequence is unrolled; beyond that, the whole pages are
+    /// probed by a loop, and only the remainder is unrolled.
+    /// Outline probes, i.e. calls to the probestack function, aren't supported by this backend.
+    fn allocate_frame(&self, frame_size: u32, insts: &mut Vec<Inst>) {
+        let r_rsp = regs::rsp();
+        let w_rsp = Writable::from_reg(r_rsp);
+        let page_size = 1u32 << self.flags.probestack_size_log2();
+
+        if !self.flags.enable_probestack()
+            || self.flags.probestack_strategy() != settings::ProbestackStrategy::Inline
+            || frame_size <= page_size
+        {
+            insts.push(Inst::alu_rmi_r(
+                true,
+                AluRmiROpcode::Sub,
+                RegMemImm::imm(frame_size),
+                w_rsp,
+            ));
+            return;
+        }
+
+        let mut remaining = frame_size;
+        let pages = frame_size / page_size;
+        if pages > PROBESTACK_UNROLL_PAGES {
+            // %r11 is neither an argument nor a callee-saved register, so it's free here.
+            insts.push(Inst::stack_probe_loop(
+                Writable::from_reg(regs::r11()),
+                pages,
+                page_size,
+            ));
+            remaining -= pages * page_size;
+        }
+        while remaining > 0 {
+            let step = std::cmp::min(remaining, page_size);
+            insts.push(Inst::alu_rmi_r(
+                true,
+                AluRmiROpcode::Sub,
+                RegMemImm::imm(step),
+                w_rsp,
+            ));
+            insts.push(Inst::mov_r_m(8, r_rsp, Amode::imm_reg(0, r_rsp), None));
+            remaining -= step;
+        }
+    }
+
+    /// Restores the clobbered callee-saved registers and pops the frame set up by the prologue,
+    /// leaving %rsp where it was on entry. This is shared between returns and tail calls.
+    fn gen_frame_teardown(&self) -> Vec<Inst> {
+        let mut insts = vec![];
+
+        // Undo what we did in the prologue.
+
+        // Restore regs: first the vector registers, which were saved last, then pop the
+        // integer registers.
+        let clobbered = get_callee_saves(self.call_conv, self.clobbered.to_vec());
+        let xmm_saves: Vec<_> = clobbered
+            .iter()
+            .filter(|reg| reg.to_reg().get_class() == RegClass::V128)
+            .map(|reg| reg.map(|r| r.to_reg()))
+            .collect();
+        if !xmm_saves.is_empty() {
+            let r_rsp = regs::rsp();
+            for (i, reg) in xmm_saves.iter().enumerate() {
+                insts.push(Inst::xmm_mov_rm_r(
+                    SseOpcode::Movdqu,
+                    RegMem::mem(Amode::imm_reg(16 * i as u32, r_rsp)),
+                    *reg,
+                    None,
+                ));
+            }
+            insts.push(Inst::alu_rmi_r(
+                true,
+                AluRmiROpcode::Add,
+                RegMemImm::imm(16 * xmm_saves.len() as u32),
+                Writable::from_reg(r_rsp),
+            ));
+        }
+
+        for wreg in clobbered.into_iter().rev() {
+            let rreg = wreg.to_reg();
+            match rreg.get_class() {
+                RegClass::I64 => {
+                    // TODO: make these conversion sequences less cumbersome.
+                    insts.push(Inst::pop64(Writable::from_reg(rreg.to_reg())));
+                }
+                RegClass::V128 => {}
+                _ => unreachable!("x64 only has integer and vector registers"),
+            }
+        }
+
+        // No need to adjust the virtual sp offset here:
+        // - this would create issues when there's a return in the middle of a function,
+        // - and nothing in this sequence may try to access stack slots from the nominal SP.
+
+        // Clear the spill area and the 16-alignment padding below it.
+        if !self.call_conv.extends_baldrdash() {
+            let frame_size = self.frame_size_bytes.unwrap();
+            if frame_size > 0 {
+                let r_rsp = regs::rsp();
+                let w_rsp = Writable::from_reg(r_rsp);
+                insts.push(Inst::alu_rmi_r(
+                    true,
+                    AluRmiROpcode::Add,
+                    RegMemImm::imm(frame_size as u32),
+                    w_rsp,
+                ));
+            }
+        }
+
+        // Baldrdash generates its own preamble.
+        if !self.call_conv.extends_baldrdash() {
+            // Undo the "traditional" pre-preamble
+            // RSP before the call will be 0 % 16.  So here, it is 8 % 16.
+            insts.push(Inst::pop64(Writable::from_reg(regs::rbp())));
+        }
+
+        insts
+    }
 }
 
 impl ABIBody for X64ABIBody {
@@ -285,7 +594,7 @@ impl ABIBody for X64ABIBody {
                     1 | 8 => Some(ExtMode::BQ),
                     16 => Some(ExtMode::WQ),
                     32 => Some(ExtMode::LQ),
-                    64 => None,
+                    64 | 128 => None,
                     _ => unreachable!(),
                 };
 
@@ -294,15 +603,17 @@ impl ABIBody for X64ABIBody {
                     (ArgumentExtension::Uext, Some(ext_mode)) => {
                         ret.push(Inst::movzx_rm_r(
                             ext_mode,
-                            RegMem::reg(r.to_reg()),
+                            RegMem::reg(from_reg.to_reg()),
                             dest_reg,
+                            None,
                         ));
                     }
                     (ArgumentExtension::Sext, Some(ext_mode)) => {
                         ret.push(Inst::movsx_rm_r(
                             ext_mode,
-                            RegMem::reg(r.to_reg()),
+                            RegMem::reg(from_reg.to_reg()),
                             dest_reg,
+                            None,
                         ));
                     }
                     _ => ret.push(Inst::gen_move(dest_reg, from_reg.to_reg(), ty)),
@@ -315,7 +626,7 @@ impl ABIBody for X64ABIBody {
                     1 | 8 => Some(ExtMode::BQ),
                     16 => Some(ExtMode::WQ),
                     32 => Some(ExtMode::LQ),
-                    64 => None,
+                    64 | 128 => None,
                     _ => unreachable!(),
                 };
 
@@ -326,6 +637,7 @@ impl ABIBody for X64ABIBody {
                             ext_mode,
                             RegMem::reg(from_reg.to_reg()),
                             from_reg,
+                            None,
                         ));
                     }
                     (ArgumentExtension::Sext, Some(ext_mode)) => {
@@ -333,6 +645,7 @@ impl ABIBody for X64ABIBody {
                             ext_mode,
                             RegMem::reg(from_reg.to_reg()),
                             from_reg,
+                            None,
                         ));
                     }
                     _ => {}
@@ -375,24 +688,62 @@ impl ABIBody for X64ABIBody {
 
     fn load_stackslot(
         &self,
-        _slot: StackSlot,
-        _offset: u32,
-        _ty: Type,
-        _into_reg: Writable<Reg>,
+        slot: StackSlot,
+        offset: u32,
+        ty: Type,
+        into_reg: Writable<Reg>,
     ) -> Inst {
-        unimplemented!("load_stackslot")
+        // Offset from beginning of stackslot area, which is at nominal-SP.
+        let stack_off = self.stack_slots[slot.as_u32() as usize] as i64;
+        let sp_off: i64 = stack_off + (offset as i64);
+        assert!(sp_off <= u32::max_value() as i64, "large stack offsets nyi");
+        trace!("load_stackslot: slot {} -> sp_off {}", slot, sp_off);
+        load_stack(
+            SyntheticAmode::nominal_sp_offset(sp_off as u32),
+            into_reg,
+            ty,
+        )
     }
 
-    fn store_stackslot(&self, _slot: StackSlot, _offset: u32, _ty: Type, _from_reg: Reg) -> Inst {
-        unimplemented!("store_stackslot")
+    fn store_stackslot(&self, slot: StackSlot, offset: u32, ty: Type, from_reg: Reg) -> Inst {
+        // Offset from beginning of stackslot area, which is at nominal-SP.
+        let stack_off = self.stack_slots[slot.as_u32() as usize] as i64;
+        let sp_off: i64 = stack_off + (offset as i64);
+        assert!(sp_off <= u32::max_value() as i64, "large stack offsets nyi");
+        trace!("store_stackslot: slot {} -> sp_off {}", slot, sp_off);
+        store_stack(
+            SyntheticAmode::nominal_sp_offset(sp_off as u32),
+            from_reg,
+            ty,
+        )
     }
 
-    fn load_spillslot(&self, _slot: SpillSlot, _ty: Type, _into_reg: Writable<Reg>) -> Inst {
-        unimplemented!("load_spillslot")
+    fn load_spillslot(&self, slot: SpillSlot, ty: Type, into_reg: Writable<Reg>) -> Inst {
+        // Offset from beginning of spillslot area, which is at nominal-SP + stack_slots_size.
+        let islot = slot.get() as i64;
+        let spill_off = islot * 8;
+        let sp_off = self.stack_slots_size as i64 + spill_off;
+        debug_assert!(sp_off <= u32::max_value() as i64, "large spill offsets NYI");
+        trace!("load_spillslot: slot {:?} -> sp_off {}", slot, sp_off);
+        load_stack(
+            SyntheticAmode::nominal_sp_offset(sp_off as u32),
+            into_reg,
+            ty,
+        )
     }
 
-    fn store_spillslot(&self, _slot: SpillSlot, _ty: Type, _from_reg: Reg) -> Inst {
-        unimplemented!("store_spillslot")
+    fn store_spillslot(&self, slot: SpillSlot, ty: Type, from_reg: Reg) -> Inst {
+        // Offset from beginning of spillslot area, which is at nominal-SP + stack_slots_size.
+        let islot = slot.get() as i64;
+        let spill_off = islot * 8;
+        let sp_off = self.stack_slots_size as i64 + spill_off;
+        debug_assert!(sp_off <= u32::max_value() as i64, "large spill offsets NYI");
+        trace!("store_spillslot: slot {:?} -> sp_off {}", slot, sp_off);
+        store_stack(
+            SyntheticAmode::nominal_sp_offset(sp_off as u32),
+            from_reg,
+            ty,
+        )
     }
 
     fn gen_prologue(&mut self) -> Vec<Inst> {
@@ -412,12 +763,13 @@ impl ABIBody for X64ABIBody {
             insts.push(Inst::mov_r_r(true, r_rsp, w_rbp));
         }
 
-        let clobbered = get_callee_saves(self.clobbered.to_vec());
+        let clobbered = get_callee_saves(self.call_conv, self.clobbered.to_vec());
         let callee_saved_used: usize = clobbered
             .iter()
             .map(|reg| match reg.to_reg().get_class() {
                 RegClass::I64 => 8,
-                _ => todo!(),
+                RegClass::V128 => 16,
+                _ => unreachable!(),
             })
             .sum();
 
@@ -442,28 +794,52 @@ impl ABIBody for X64ABIBody {
         debug_assert_eq!((frame_size + callee_saved_used) % 16, 0, "misaligned stack");
 
         if !self.call_conv.extends_baldrdash() {
+            // Leaf functions with zero stack don't need a stack check if one's specified,
+            // otherwise always insert the stack check.
+            let stack_size = frame_size + callee_saved_used;
+            if stack_size > 0 || !self.is_leaf {
+                if let Some((reg, stack_limit_load)) = &self.stack_limit {
+                    insts.extend_from_slice(stack_limit_load);
+                    self.insert_stack_check(*reg, stack_size as u32, &mut insts);
+                }
+            }
+
             // Explicitly allocate the frame.
-            let w_rsp = Writable::from_reg(r_rsp);
             if frame_size > 0 {
-                insts.push(Inst::alu_rmi_r(
-                    true,
-                    AluRmiROpcode::Sub,
-                    RegMemImm::imm(frame_size as u32),
-                    w_rsp,
-                ));
+                self.allocate_frame(frame_size as u32, &mut insts);
             }
         }
 
         // Save callee saved registers that we trash. Keep track of how much space we've used, so
-        // as to know what we have to do to get the base of the spill area 0 % 16.
-        let clobbered = get_callee_saves(self.clobbered.to_vec());
+        // as to know what we have to do to get the base of the spill area 0 % 16. Integer
+        // registers are pushed first; vector registers are then stored in a dedicated area below
+        // them.
+        let mut xmm_saves = Vec::new();
         for reg in clobbered {
             let r_reg = reg.to_reg();
             match r_reg.get_class() {
                 RegClass::I64 => {
                     insts.push(Inst::push64(RegMemImm::reg(r_reg.to_reg())));
                 }
-                _ => unimplemented!(),
+                RegClass::V128 => xmm_saves.push(r_reg.to_reg()),
+                _ => unreachable!("x64 only has integer and vector registers"),
+            }
+        }
+
+        if !xmm_saves.is_empty() {
+            insts.push(Inst::alu_rmi_r(
+                true,
+                AluRmiROpcode::Sub,
+                RegMemImm::imm(16 * xmm_saves.len() as u32),
+                Writable::from_reg(r_rsp),
+            ));
+            for (i, reg) in xmm_saves.into_iter().enumerate() {
+                insts.push(Inst::xmm_mov_r_m(
+                    SseOpcode::Movdqu,
+                    reg,
+                    Amode::imm_reg(16 * i as u32, r_rsp),
+                    None,
+                ));
             }
         }
 
@@ -481,53 +857,21 @@ impl ABIBody for X64ABIBody {
     }
 
     fn gen_epilogue(&self) -> Vec<Inst> {
-        let mut insts = vec![];
-
-        // Undo what we did in the prologue.
-
-        // Restore regs.
-        let clobbered = get_callee_saves(self.clobbered.to_vec());
-        for wreg in clobbered.into_iter().rev() {
-            let rreg = wreg.to_reg();
-            match rreg.get_class() {
-                RegClass::I64 => {
-                    // TODO: make these conversion sequences less cumbersome.
-                    insts.push(Inst::pop64(Writable::from_reg(rreg.to_reg())));
-                }
-                _ => unimplemented!(),
-            }
-        }
-
-        // No need to adjust the virtual sp offset here:
-        // - this would create issues when there's a return in the middle of a function,
-        // - and nothing in this sequence may try to access stack slots from the nominal SP.
-
-        // Clear the spill area and the 16-alignment padding below it.
+        let mut insts = self.gen_frame_teardown();
+        // Baldrdash generates its own return sequence.
         if !self.call_conv.extends_baldrdash() {
-            let frame_size = self.frame_size_bytes.unwrap();
-            if frame_size > 0 {
-                let r_rsp = regs::rsp();
-                let w_rsp = Writable::from_reg(r_rsp);
-                insts.push(Inst::alu_rmi_r(
-                    true,
-                    AluRmiROpcode::Add,
-                    RegMemImm::imm(frame_size as u32),
-                    w_rsp,
-                ));
-            }
-        }
-
-        // Baldrdash generates its own preamble.
-        if !self.call_conv.extends_baldrdash() {
-            // Undo the "traditional" pre-preamble
-            // RSP before the call will be 0 % 16.  So here, it is 8 % 16.
-            insts.push(Inst::pop64(Writable::from_reg(regs::rbp())));
             insts.push(Inst::ret());
         }
-
         insts
     }
 
+    fn gen_tail_call_epilogue(&self) -> Vec<Inst> {
+        // Tail calls out of Baldrdash functions are rejected when lowering, so the frame is
+        // always ours to pop here.
+        debug_assert!(!self.call_conv.extends_baldrdash());
+        self.gen_frame_teardown()
+    }
+
     fn frame_size(&self) -> u32 {
         self.frame_size_bytes
             .expect("frame size not computed before prologue generation") as u32
@@ -543,18 +887,40 @@ impl ABIBody for X64ABIBody {
         }
     }
 
-    fn gen_spill(&self, _to_slot: SpillSlot, _from_reg: RealReg, _ty: Type) -> Inst {
-        unimplemented!()
+    fn gen_spill(&self, to_slot: SpillSlot, from_reg: RealReg, ty: Type) -> Inst {
+        self.store_spillslot(to_slot, ty, from_reg.to_reg())
     }
 
-    fn gen_reload(&self, _to_reg: Writable<RealReg>, _from_slot: SpillSlot, _ty: Type) -> Inst {
-        unimplemented!()
+    fn gen_reload(&self, to_reg: Writable<RealReg>, from_slot: SpillSlot, ty: Type) -> Inst {
+        self.load_spillslot(from_slot, ty, to_reg.map(|r| r.to_reg()))
     }
 }
 
 fn get_caller_saves(call_conv: isa::CallConv) -> Vec<Writable<Reg>> {
     let mut caller_saved = Vec::new();
 
+    if call_conv == isa::CallConv::WindowsFastcall {
+        // Windows fastcall calling convention:
+        // - GPR: RAX, RCX, RDX and R8 to R11.
+        caller_saved.push(Writable::from_reg(regs::rax()));
+        caller_saved.push(Writable::from_reg(regs::rcx()));
+        caller_saved.push(Writable::from_reg(regs::rdx()));
+        caller_saved.push(Writable::from_reg(regs::r8()));
+        caller_saved.push(Writable::from_reg(regs::r9()));
+        caller_saved.push(Writable::from_reg(regs::r10()));
+        caller_saved.push(Writable::from_reg(regs::r11()));
+
+        // - XMM: XMM0 to XMM5.
+        caller_saved.push(Writable::from_reg(regs::xmm0()));
+        caller_saved.push(Writable::from_reg(regs::xmm1()));
+        caller_saved.push(Writable::from_reg(regs::xmm2()));
+        caller_saved.push(Writable::from_reg(regs::xmm3()));
+        caller_saved.push(Writable::from_reg(regs::xmm4()));
+        caller_saved.push(Writable::from_reg(regs::xmm5()));
+
+        return caller_saved;
+    }
+
     // Systemv calling convention:
     // - GPR: all except RBX, RBP, R12 to R15 (which are callee-saved).
     caller_saved.push(Writable::from_reg(regs::rsi()));
@@ -655,13 +1021,20 @@ fn compute_arg_locs(
     add_ret_area_ptr: bool,
 ) -> CodegenResult<(Vec<ABIArg>, i64, Option<usize>)> {
     let is_baldrdash = call_conv.extends_baldrdash();
+    let is_fastcall = call_conv == isa::CallConv::WindowsFastcall;
 
-    // XXX assume SystemV at the moment.
+    // XXX assume SystemV or Windows fastcall at the moment.
     debug_assert!(!is_baldrdash, "baldrdash nyi");
 
     let mut next_gpr = 0;
     let mut next_vreg = 0;
-    let mut next_stack: u64 = 0;
+    // The Windows fastcall convention has the caller reserve a 32-bytes "shadow space" for the
+    // register arguments, right above the return address.
+    let mut next_stack: u64 = if is_fastcall && args_or_rets == ArgsOrRets::Args {
+        32
+    } else {
+        0
+    };
     let mut ret = vec![];
 
     for i in 0..params.len() {
@@ -679,27 +1052,34 @@ fn compute_arg_locs(
             | &ir::ArgumentPurpose::Normal
             | &ir::ArgumentPurpose::StackLimit
             | &ir::ArgumentPurpose::SignatureId => {}
-            _ => panic!(
-                "Unsupported argument purpose {:?} in signature: {:?}",
-                param.purpose, params
-            ),
+            _ => {
+                return Err(CodegenError::Unsupported(format!(
+                    "argument purpose {:?} in the x64 backend",
+                    param.purpose
+                )))
+            }
         }
 
         let intreg = in_int_reg(param.value_type);
         let vecreg = in_vec_reg(param.value_type);
-        debug_assert!(intreg || vecreg);
+        if !intreg && !vecreg {
+            return Err(CodegenError::Unsupported(format!(
+                "arguments and return values of type {} in the x64 backend",
+                param.value_type
+            )));
+        }
         debug_assert!(!(intreg && vecreg));
 
         let (next_reg, candidate) = if intreg {
             let candidate = match args_or_rets {
-                ArgsOrRets::Args => get_intreg_for_arg_systemv(next_gpr),
-                ArgsOrRets::Rets => get_intreg_for_retval_systemv(next_gpr),
+                ArgsOrRets::Args => get_intreg_for_arg(call_conv, next_gpr),
+                ArgsOrRets::Rets => get_intreg_for_retval(call_conv, next_gpr),
             };
             (&mut next_gpr, candidate)
         } else {
             let candidate = match args_or_rets {
-                ArgsOrRets::Args => get_fltreg_for_arg_systemv(next_gpr),
-                ArgsOrRets::Rets => get_fltreg_for_retval_systemv(next_gpr),
+                ArgsOrRets::Args => get_fltreg_for_arg(call_conv, next_vreg),
+                ArgsOrRets::Rets => get_fltreg_for_retval(call_conv, next_vreg),
             };
             (&mut next_vreg, candidate)
         };
@@ -710,6 +1090,13 @@ fn compute_arg_locs(
         } else if let Some(reg) = candidate {
             ret.push(ABIArg::Reg(reg.to_real_reg(), param.value_type));
             *next_reg += 1;
+            if is_fastcall {
+                // Registers are assigned by argument position in fastcall: the n-th argument
+                // goes either in the n-th integer register or in the n-th vector register.
+                let next = usize::max(next_gpr, next_vreg);
+                next_gpr = next;
+                next_vreg = next;
+            }
         } else {
             // Compute size. Every arg takes a minimum slot of 8 bytes. (16-byte
             // stack alignment happens separately after all args.)
@@ -729,7 +1116,7 @@ fn compute_arg_locs(
 
     let extra_arg = if add_ret_area_ptr {
         debug_assert!(args_or_rets == ArgsOrRets::Args);
-        if let Some(reg) = get_intreg_for_arg_systemv(next_gpr) {
+        if let Some(reg) = get_intreg_for_arg(call_conv, next_gpr) {
             ret.push(ABIArg::Reg(reg.to_real_reg(), ir::types::I64));
         } else {
             ret.push(ABIArg::Stack(next_stack as i64, ir::types::I64));
@@ -817,42 +1204,56 @@ fn adjust_stack<C: LowerCtx<I = Inst>>(ctx: &mut C, amount: u64, is_sub: bool) {
             Writable::from_reg(regs::rsp()),
         ));
     } else {
-        // TODO will require a scratch register.
-        unimplemented!("adjust stack with large offset");
+        unreachable!("stack arguments are limited to STACK_ARG_RET_SIZE_LIMIT bytes");
     }
 }
 
-fn load_stack(mem: Amode, into_reg: Writable<Reg>, ty: Type) -> Inst {
+fn load_stack(mem: impl Into<SyntheticAmode>, into_reg: Writable<Reg>, ty: Type) -> Inst {
     let ext_mode = match ty {
         types::B1 | types::B8 | types::I8 => Some(ExtMode::BQ),
         types::B16 | types::I16 => Some(ExtMode::WQ),
-        types::B32 | types::I32 => Some(ExtMode::LQ),
-        types::B64 | types::I64 => None,
-        types::F32 => todo!("f32 load_stack"),
-        types::F64 => todo!("f64 load_stack"),
-        _ => unimplemented!("load_stack({})", ty),
+        types::B32 | types::I32 | types::R32 => Some(ExtMode::LQ),
+        types::B64 | types::I64 | types::R64 => None,
+        types::F32 => {
+            return Inst::xmm_mov_rm_r(SseOpcode::Movss, RegMem::mem(mem), into_reg, None)
+        }
+        types::F64 => {
+            return Inst::xmm_mov_rm_r(SseOpcode::Movsd, RegMem::mem(mem), into_reg, None)
+        }
+        _ if ty.is_vector() && ty.bits() == 128 => {
+            return Inst::xmm_mov_rm_r(SseOpcode::Movdqu, RegMem::mem(mem), into_reg, None)
+        }
+        _ => unreachable!("no register holds values of type {}", ty),
     };
 
+    let mem = mem.into();
     match ext_mode {
-        Some(ext_mode) => Inst::movsx_rm_r(ext_mode, RegMem::mem(mem), into_reg),
-        None => Inst::mov64_m_r(mem, into_reg),
+        Some(ext_mode) => Inst::movsx_rm_r(ext_mode, RegMem::mem(mem), into_reg, None),
+        None => Inst::mov64_m_r(mem, into_reg, None),
     }
 }
 
-fn store_stack(mem: Amode, from_reg: Reg, ty: Type) -> Inst {
+fn store_stack(mem: impl Into<SyntheticAmode>, from_reg: Reg, ty: Type) -> Inst {
     let (is_int, size) = match ty {
         types::B1 | types::B8 | types::I8 => (true, 1),
         types::B16 | types::I16 => (true, 2),
-        types::B32 | types::I32 => (true, 4),
-        types::B64 | types::I64 => (true, 8),
+        types::B32 | types::I32 | types::R32 => (true, 4),
+        types::B64 | types::I64 | types::R64 => (true, 8),
         types::F32 => (false, 4),
         types::F64 => (false, 8),
-        _ => unimplemented!("store_stack({})", ty),
+        _ if ty.is_vector() && ty.bits() == 128 => (false, 16),
+        _ => unreachable!("no register holds values of type {}", ty),
     };
     if is_int {
-        Inst::mov_r_m(size, from_reg, mem)
+        Inst::mov_r_m(size, from_reg, mem, None)
     } else {
-        unimplemented!("f32/f64 store_stack");
+        let op = match size {
+            4 => SseOpcode::Movss,
+            8 => SseOpcode::Movsd,
+            16 => SseOpcode::Movdqu,
+            _ => unreachable!(),
+        };
+        Inst::xmm_mov_r_m(op, from_reg, mem, None)
     }
 }
 
@@ -873,6 +1274,7 @@ impl X64ABICall {
         extname: &ir::ExternalName,
         dist: RelocDistance,
         loc: ir::SourceLoc,
+        opcode: ir::Opcode,
     ) -> CodegenResult<Self> {
         let sig = ABISig::from_func_sig(sig)?;
         let (uses, defs) = abisig_to_uses_and_defs(&sig);
@@ -882,7 +1284,7 @@ impl X64ABICall {
             defs,
             dest: CallDest::ExtName(extname.clone(), dist),
             loc,
-            opcode: ir::Opcode::Call,
+            opcode,
         })
     }
 
@@ -1012,4 +1414,39 @@ impl ABICall for X64ABICall {
             )),
         }
     }
+
+    fn emit_tail_call<C: LowerCtx<I = Self::I>>(&mut self, ctx: &mut C) -> CodegenResult<()> {
+        if self.sig.stack_arg_space != 0 || self.sig.stack_ret_arg.is_some() {
+            return Err(CodegenError::Unsupported(
+                "tail calls with stack arguments or return values".into(),
+            ));
+        }
+        if self.sig.call_conv.extends_baldrdash() {
+            return Err(CodegenError::Unsupported(format!(
+                "tail calls with the {} calling convention",
+                self.sig.call_conv
+            )));
+        }
+
+        // The callee never returns here, so nothing is clobbered from our point of view; only
+        // the argument registers are used.
+        let mut uses = mem::replace(&mut self.uses, Default::default());
+        match &self.dest {
+            &CallDest::ExtName(ref name, ref _reloc_distance) => ctx.emit(Inst::tail_call_known(
+                name.clone(),
+                uses,
+                self.loc,
+                self.opcode,
+            )),
+            &CallDest::Reg(reg) => {
+                // The epilogue restores callee-saved registers before the jump, so the target
+                // goes in %r11: it's caller-saved and never used for arguments.
+                let target = regs::r11();
+                ctx.emit(Inst::gen_move(Writable::from_reg(target), reg, I64));
+                uses.push(target);
+                ctx.emit(Inst::tail_call_unknown(target, uses, self.loc, self.opcode));
+            }
+        }
+        Ok(())
+    }
 }
@@ -277,13 +277,16 @@ pub(crate) enum InstructionSet {
     SSE41,
 }
 
-/// Some scalar SSE operations requiring 2 operands r/m and r.
-/// TODO: Below only includes scalar operations. To be seen if packed will be added here.
+/// Some SSE operations requiring 2 operands r/m and r.
 #[derive(Clone, PartialEq)]
 pub enum SseOpcode {
+    Addps,
+    Addpd,
     Addss,
     Addsd,
+    Andpd,
     Andps,
+    Andnpd,
     Andnps,
     Comiss,
     Comisd,
@@ -297,6 +300,8 @@ pub enum SseOpcode {
     Cvtss2sd,
     Cvttss2si,
     Cvttsd2si,
+    Divps,
+    Divpd,
     Divss,
     Divsd,
     Insertps,
@@ -306,21 +311,49 @@ pub enum SseOpcode {
     Minsd,
     Movaps,
     Movd,
+    Movdqu,
+    Movq,
     Movss,
     Movsd,
+    Movups,
+    Mulps,
+    Mulpd,
     Mulss,
     Mulsd,
+    Orpd,
     Orps,
+    Paddb,
+    Paddd,
+    Paddq,
+    Paddw,
+    Pand,
+    Pandn,
+    Pmulld,
+    Pmullw,
+    Por,
+    Pshufd,
+    Psubb,
+    Psubd,
+    Psubq,
+    Psubw,
+    Punpcklqdq,
+    Pxor,
     Rcpss,
     Roundss,
     Roundsd,
     Rsqrtss,
+    Sqrtps,
+    Sqrtpd,
     Sqrtss,
     Sqrtsd,
+    Subps,
+    Subpd,
     Subss,
     Subsd,
     Ucomiss,
     Ucomisd,
+    Xorpd,
+    Xorps,
 }
 
 impl SseOpcode {
@@ -328,53 +361,92 @@ impl SseOpcode {
     pub(crate) fn available_from(&self) -> InstructionSet {
         use InstructionSet::*;
         match self {
-            SseOpcode::Addss
+            SseOpcode::Addps
+            | SseOpcode::Addss
             | SseOpcode::Andps
             | SseOpcode::Andnps
             | SseOpcode::Cvtsi2ss
             | SseOpcode::Cvtss2si
             | SseOpcode::Cvttss2si
+            | SseOpcode::Divps
             | SseOpcode::Divss
             | SseOpcode::Maxss
             | SseOpcode::Movaps
             | SseOpcode::Minss
             | SseOpcode::Movss
+            | SseOpcode::Movups
+            | SseOpcode::Mulps
             | SseOpcode::Mulss
             | SseOpcode::Orps
             | SseOpcode::Rcpss
             | SseOpcode::Rsqrtss
+            | SseOpcode::Sqrtps
+            | SseOpcode::Subps
             | SseOpcode::Subss
             | SseOpcode::Ucomiss
             | SseOpcode::Sqrtss
             | SseOpcode::Comiss
-            | SseOpcode::Cmpss => SSE,
+            | SseOpcode::Cmpss
+            | SseOpcode::Xorps => SSE,
 
-            SseOpcode::Addsd
+            SseOpcode::Addpd
+            | SseOpcode::Addsd
+            | SseOpcode::Andpd
+            | SseOpcode::Andnpd
             | SseOpcode::Cvtsd2ss
             | SseOpcode::Cvtsd2si
             | SseOpcode::Cvtsi2sd
             | SseOpcode::Cvtss2sd
             | SseOpcode::Cvttsd2si
+            | SseOpcode::Divpd
             | SseOpcode::Divsd
             | SseOpcode::Maxsd
             | SseOpcode::Minsd
             | SseOpcode::Movd
+            | SseOpcode::Movdqu
+            | SseOpcode::Movq
             | SseOpcode::Movsd
+            | SseOpcode::Mulpd
             | SseOpcode::Mulsd
+            | SseOpcode::Orpd
+            | SseOpcode::Paddb
+            | SseOpcode::Paddd
+            | SseOpcode::Paddq
+            | SseOpcode::Paddw
+            | SseOpcode::Pand
+            | SseOpcode::Pandn
+            | SseOpcode::Pmullw
+            | SseOpcode::Por
+            | SseOpcode::Pshufd
+            | SseOpcode::Psubb
+            | SseOpcode::Psubd
+            | SseOpcode::Psubq
+            | SseOpcode::Psubw
+            | SseOpcode::Punpcklqdq
+            | SseOpcode::Pxor
+            | SseOpcode::Sqrtpd
             | SseOpcode::Sqrtsd
+            | SseOpcode::Subpd
             | SseOpcode::Subsd
             | SseOpcode::Ucomisd
             | SseOpcode::Comisd
-            | SseOpcode::Cmpsd => SSE2,
+            | SseOpcode::Cmpsd
+            | SseOpcode::Xorpd => SSE2,
 
-            SseOpcode::Insertps | SseOpcode::Roundss | SseOpcode::Roundsd => SSE41,
+            SseOpcode::Insertps | SseOpcode::Pmulld | SseOpcode::Roundss | SseOpcode::Roundsd => {
+                SSE41
+            }
         }
     }
 
     /// Returns the src operand size for an instruction
     pub(crate) fn src_size(&self) -> u8 {
         match self {
-            SseOpcode::Movd => 4,
+            SseOpcode::Movd
+            | SseOpcode::Cvtsi2ss
+            | SseOpcode::Cvtsi2sd
+            | SseOpcode::Cvttss2si
+            | SseOpcode::Cvttsd2si => 4,
             _ => 8,
         }
     }
@@ -423,6 +495,40 @@ impl fmt::Debug for SseOpcode {
             SseOpcode::Cmpss => "cmpss",
             SseOpcode::Cmpsd => "cmpsd",
             SseOpcode::Insertps => "insertps",
+            SseOpcode::Addps => "addps",
+            SseOpcode::Addpd => "addpd",
+            SseOpcode::Andpd => "andpd",
+            SseOpcode::Andnpd => "andnpd",
+            SseOpcode::Divps => "divps",
+            SseOpcode::Divpd => "divpd",
+            SseOpcode::Movdqu => "movdqu",
+            SseOpcode::Movq => "movq",
+            SseOpcode::Movups => "movups",
+            SseOpcode::Mulps => "mulps",
+            SseOpcode::Mulpd => "mulpd",
+            SseOpcode::Orpd => "orpd",
+            SseOpcode::Paddb => "paddb",
+            SseOpcode::Paddd => "paddd",
+            SseOpcode::Paddq => "paddq",
+            SseOpcode::Paddw => "paddw",
+            SseOpcode::Pand => "pand",
+            SseOpcode::Pandn => "pandn",
+            SseOpcode::Pmulld => "pmulld",
+            SseOpcode::Pmullw => "pmullw",
+            SseOpcode::Por => "por",
+            SseOpcode::Pshufd => "pshufd",
+            SseOpcode::Psubb => "psubb",
+            SseOpcode::Psubd => "psubd",
+            SseOpcode::Psubq => "psubq",
+            SseOpcode::Psubw => "psubw",
+            SseOpcode::Punpcklqdq => "punpcklqdq",
+            SseOpcode::Pxor => "pxor",
+            SseOpcode::Sqrtps => "sqrtps",
+            SseOpcode::Sqrtpd => "sqrtpd",
+            SseOpcode::Subps => "subps",
+            SseOpcode::Subpd => "subpd",
+            SseOpcode::Xorpd => "xorpd",
+            SseOpcode::Xorps => "xorps",
         };
         write!(fmt, "{}", name)
     }
@@ -485,12 +591,15 @@ impl ToString for ExtMode {
     }
 }
 
-/// These indicate the form of a scalar shift: left, signed right, unsigned right.
+/// These indicate the form of a scalar shift or rotate: left, signed right, unsigned right,
+/// rotate left, rotate right.
 #[derive(Clone)]
 pub enum ShiftKind {
     Left,
     RightZ,
     RightS,
+    RotateLeft,
+    RotateRight,
 }
 
 impl fmt::Debug for ShiftKind {
@@ -499,6 +608,8 @@ impl fmt::Debug for ShiftKind {
             ShiftKind::Left => "shl",
             ShiftKind::RightZ => "shr",
             ShiftKind::RightS => "sar",
+            ShiftKind::RotateLeft => "rol",
+            ShiftKind::RotateRight => "ror",
         };
         write!(fmt, "{}", name)
     }
@@ -510,6 +621,60 @@ impl ToString for ShiftKind {
     }
 }
 
+/// What kind of division or remainder instruction this is?
+#[derive(Clone, Copy, PartialEq)]
+pub enum DivOrRemKind {
+    SignedDiv,
+    UnsignedDiv,
+    SignedRem,
+    UnsignedRem,
+}
+
+impl DivOrRemKind {
+    pub(crate) fn is_signed(&self) -> bool {
+        match self {
+            DivOrRemKind::SignedDiv | DivOrRemKind::SignedRem => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_div(&self) -> bool {
+        match self {
+            DivOrRemKind::SignedDiv | DivOrRemKind::UnsignedDiv => true,
+            _ => false,
+        }
+    }
+}
+
+/// Unary operations reading a r/m operand and writing a register: bit scans and population
+/// count.
+#[derive(Clone, Copy, PartialEq)]
+pub enum UnaryRmROpcode {
+    /// Bit-scan reverse.
+    Bsr,
+    /// Bit-scan forward.
+    Bsf,
+    /// Population count; requires the POPCNT extension.
+    Popcnt,
+}
+
+impl fmt::Debug for UnaryRmROpcode {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            UnaryRmROpcode::Bsr => "bsr",
+            UnaryRmROpcode::Bsf => "bsf",
+            UnaryRmROpcode::Popcnt => "popcnt",
+        };
+        write!(fmt, "{}", name)
+    }
+}
+
+impl ToString for UnaryRmROpcode {
+    fn to_string(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
 /// These indicate condition code tests.  Not all are represented since not all are useful in
 /// compiler-generated code.
 #[derive(Copy, Clone)]
@@ -540,6 +705,11 @@ pub enum CC {
     /// not-negative
     NS = 9,
 
+    /// parity
+    P = 10,
+    /// not parity
+    NP = 11,
+
     /// < signed
     L = 12,
     /// >= signed
@@ -586,6 +756,9 @@ impl CC {
             CC::S => CC::NS,
             CC::NS => CC::S,
 
+            CC::P => CC::NP,
+            CC::NP => CC::P,
+
             CC::L => CC::NL,
             CC::NL => CC::L,
 
@@ -612,6 +785,8 @@ impl fmt::Debug for CC {
             CC::NBE => "nbe",
             CC::S => "s",
             CC::NS => "ns",
+            CC::P => "p",
+            CC::NP => "np",
             CC::L => "l",
             CC::NL => "nl",
             CC::LE => "le",
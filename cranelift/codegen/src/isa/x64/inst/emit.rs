@@ -2,6 +2,7 @@ use log::debug;
 use regalloc::Reg;
 
 use crate::binemit::Reloc;
+use crate::ir::immediates::{Ieee32, Ieee64};
 use crate::isa::x64::inst::*;
 
 fn low8_will_sign_extend_to_64(x: u32) -> bool {
@@ -340,6 +341,69 @@ fn emit_simm(sink: &mut MachBuffer<Inst>, size: u8, simm32: u32) {
     }
 }
 
+/// Emits a one way conditional jump if CC is set (true).
+fn one_way_jmp(sink: &mut MachBuffer<Inst>, cc: CC, label: MachLabel) {
+    let cond_start = sink.cur_offset();
+    let cond_disp_off = cond_start + 2;
+    sink.use_label_at_offset(cond_disp_off, label, LabelUse::JmpRel32);
+    sink.put1(0x0F);
+    sink.put1(0x80 + cc.get_enc());
+    sink.put4(0x0);
+}
+
+/// Returns the REX flags for an integer operation on `size` bytes, retaining the redundant REX
+/// prefix for byte-sized operations on registers whose encoding requires it.
+fn rex_for_size(size: u8, enc: u8) -> RexFlags {
+    match size {
+        8 => RexFlags::set_w(),
+        4 | 2 => RexFlags::clear_w(),
+        1 => {
+            let mut rex = RexFlags::clear_w();
+            // Here, a redundant REX prefix changes the meaning of the instruction.
+            if enc >= 4 && enc <= 7 {
+                rex.always_emit();
+            }
+            rex
+        }
+        _ => panic!("unexpected operand size {}", size),
+    }
+}
+
+/// Emits a unary instruction of the F6/F7 group, on a register or memory operand of `size` bytes.
+fn emit_f7_group(
+    sink: &mut MachBuffer<Inst>,
+    state: &mut EmitState,
+    size: u8,
+    subopcode: u8,
+    src: &RegMem,
+) {
+    let prefix = if size == 2 {
+        LegacyPrefix::_66
+    } else {
+        LegacyPrefix::None
+    };
+    let opcode = if size == 1 { 0xF6 } else { 0xF7 };
+    match src {
+        RegMem::Reg { reg } => {
+            let enc = int_reg_enc(*reg);
+            let rex = rex_for_size(size, enc);
+            emit_std_enc_enc(sink, prefix, opcode, 1, subopcode, enc, rex)
+        }
+        RegMem::Mem { addr } => {
+            let rex = rex_for_size(size, 0);
+            emit_std_enc_mem(
+                sink,
+                prefix,
+                opcode,
+                1,
+                subopcode,
+                &addr.finalize(state),
+                rex,
+            )
+        }
+    }
+}
+
 /// The top-level emit function.
 ///
 /// Important!  Do not add improved (shortened) encoding cases to existing
@@ -395,7 +459,7 @@ fn emit_simm(sink: &mut MachBuffer<Inst>, size: u8, simm32: u32) {
 pub(crate) fn emit(
     inst: &Inst,
     sink: &mut MachBuffer<Inst>,
-    _flags: &settings::Flags,
+    flags: &settings::Flags,
     state: &mut EmitState,
 ) {
     match inst {
@@ -516,6 +580,173 @@ pub(crate) fn emit(
             }
         }
 
+        Inst::UnaryRmR { size, op, src, dst } => {
+            let (prefix, rex_flags) = match size {
+                2 => (LegacyPrefix::_66, RexFlags::clear_w()),
+                4 => (LegacyPrefix::None, RexFlags::clear_w()),
+                8 => (LegacyPrefix::None, RexFlags::set_w()),
+                _ => unreachable!(),
+            };
+
+            let (opcode, prefix) = match op {
+                UnaryRmROpcode::Bsr => (0x0fbd, prefix),
+                UnaryRmROpcode::Bsf => (0x0fbc, prefix),
+                UnaryRmROpcode::Popcnt => {
+                    // The 16-bits variant would require two legacy prefixes.
+                    debug_assert!(*size != 2, "no support for 16-bits popcnt");
+                    (0x0fb8, LegacyPrefix::_F3)
+                }
+            };
+
+            match src {
+                RegMem::Reg { reg: src } => {
+                    emit_std_reg_reg(sink, prefix, opcode, 2, dst.to_reg(), *src, rex_flags)
+                }
+                RegMem::Mem { addr: src } => emit_std_reg_mem(
+                    sink,
+                    prefix,
+                    opcode,
+                    2,
+                    dst.to_reg(),
+                    &src.finalize(state),
+                    rex_flags,
+                ),
+            }
+        }
+
+        Inst::Not { size, src } => {
+            // NOT is F6/F7 /2.
+            emit_f7_group(sink, state, *size, 2, &RegMem::reg(src.to_reg()));
+        }
+
+        Inst::Neg { size, src } => {
+            // NEG is F6/F7 /3.
+            emit_f7_group(sink, state, *size, 3, &RegMem::reg(src.to_reg()));
+        }
+
+        Inst::Div {
+            size,
+            signed,
+            divisor,
+            loc,
+        } => {
+            // DIV is F6/F7 /6, IDIV is F6/F7 /7. The division can trap, so record where.
+            sink.add_trap(*loc, TrapCode::IntegerDivisionByZero);
+            let subopcode = if *signed { 7 } else { 6 };
+            emit_f7_group(sink, state, *size, subopcode, divisor);
+        }
+
+        Inst::MulHi { size, signed, rhs } => {
+            // MUL is F6/F7 /4, IMUL (one operand) is F6/F7 /5.
+            let subopcode = if *signed { 5 } else { 4 };
+            emit_f7_group(sink, state, *size, subopcode, rhs);
+        }
+
+        Inst::SignExtendRaxRdx { size } => {
+            match size {
+                2 => sink.put1(0x66),
+                4 => {}
+                8 => sink.put1(0x48),
+                _ => unreachable!(),
+            }
+            sink.put1(0x99);
+        }
+
+        Inst::CheckedDivOrRemSeq {
+            kind,
+            size,
+            divisor,
+            loc,
+        } => {
+            // Generates the following code sequence:
+            //
+            // ;; check divide by zero:
+            // cmp 0 %divisor
+            // jnz $after_trap
+            // ud2
+            // $after_trap:
+            //
+            // ;; for signed modulo/div:
+            // cmp -1 %divisor
+            // jnz $do_op
+            // ;;   for signed modulo, result is 0
+            //    mov #0, %rdx
+            //    j $done
+            // ;;   for signed div, the result is the negated dividend, which overflows iff the
+            // ;;   dividend is the minimal integer.
+            //    neg %rax
+            //    jno $done
+            //    ud2
+            //
+            // $do_op:
+            // div %divisor
+            //
+            // $done:
+            // Check if the divisor is zero, first.
+            let inst = Inst::cmp_rmi_r(*size, RegMemImm::imm(0), *divisor);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::trap_if(CC::Z, TrapCode::IntegerDivisionByZero, *loc);
+            inst.emit(sink, flags, state);
+
+            let (do_op, done_label) = if kind.is_signed() {
+                // Now check if the divisor is -1.
+                let inst = Inst::cmp_rmi_r(*size, RegMemImm::imm(0xffffffff), *divisor);
+                inst.emit(sink, flags, state);
+
+                let do_op = sink.get_label();
+
+                // If not equal, jump to do-op.
+                one_way_jmp(sink, CC::NZ, do_op);
+
+                // Here, divisor == -1.
+                if !kind.is_div() {
+                    // x % -1 = 0; put the result into the destination, $rdx.
+                    let done_label = sink.get_label();
+
+                    let inst = Inst::imm_r(*size == 8, 0, Writable::from_reg(regs::rdx()));
+                    inst.emit(sink, flags, state);
+
+                    let inst = Inst::jmp_known(BranchTarget::Label(done_label));
+                    inst.emit(sink, flags, state);
+
+                    (Some(do_op), Some(done_label))
+                } else {
+                    // x / -1 = -x, which overflows iff x is the minimal integer.
+                    let done_label = sink.get_label();
+
+                    let inst = Inst::neg(*size, Writable::from_reg(regs::rax()));
+                    inst.emit(sink, flags, state);
+
+                    let inst = Inst::trap_if(CC::O, TrapCode::IntegerOverflow, *loc);
+                    inst.emit(sink, flags, state);
+
+                    let inst = Inst::jmp_known(BranchTarget::Label(done_label));
+                    inst.emit(sink, flags, state);
+
+                    (Some(do_op), Some(done_label))
+                }
+            } else {
+                (None, None)
+            };
+
+            if let Some(do_op) = do_op {
+                sink.bind_label(do_op);
+            }
+
+            // Fill in the high parts: this is done by lowering, since rdx is marked as modified
+            // by this instruction.
+            let inst = Inst::div(*size, kind.is_signed(), RegMem::reg(*divisor), *loc);
+            inst.emit(sink, flags, state);
+
+            // Lowering takes care of moving the result back into the right register, see comment
+            // there.
+
+            if let Some(done) = done_label {
+                sink.bind_label(done);
+            }
+        }
+
         Inst::Imm_R {
             dst_is_64,
             simm64,
@@ -546,7 +777,12 @@ pub(crate) fn emit(
             emit_std_reg_reg(sink, LegacyPrefix::None, 0x89, 1, *src, dst.to_reg(), rex);
         }
 
-        Inst::MovZX_RM_R { ext_mode, src, dst } => {
+        Inst::MovZX_RM_R {
+            ext_mode,
+            src,
+            dst,
+            srcloc,
+        } => {
             let (opcodes, num_opcodes, rex_flags) = match ext_mode {
                 ExtMode::BL => {
                     // MOVZBL is (REX.W==0) 0F B6 /r
@@ -588,27 +824,45 @@ pub(crate) fn emit(
                     *src,
                     rex_flags,
                 ),
-                RegMem::Mem { addr: src } => emit_std_reg_mem(
-                    sink,
-                    LegacyPrefix::None,
-                    opcodes,
-                    num_opcodes,
-                    dst.to_reg(),
-                    &src.finalize(state),
-                    rex_flags,
-                ),
+                RegMem::Mem { addr: src } => {
+                    let src = &src.finalize(state);
+
+                    if let Some(srcloc) = *srcloc {
+                        // Register the offset at which the actual load instruction starts.
+                        sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+                    }
+
+                    emit_std_reg_mem(
+                        sink,
+                        LegacyPrefix::None,
+                        opcodes,
+                        num_opcodes,
+                        dst.to_reg(),
+                        src,
+                        rex_flags,
+                    )
+                }
             }
         }
 
-        Inst::Mov64_M_R { src, dst } => emit_std_reg_mem(
-            sink,
-            LegacyPrefix::None,
-            0x8B,
-            1,
-            dst.to_reg(),
-            &src.finalize(state),
-            RexFlags::set_w(),
-        ),
+        Inst::Mov64_M_R { src, dst, srcloc } => {
+            let src = &src.finalize(state);
+
+            if let Some(srcloc) = *srcloc {
+                // Register the offset at which the actual load instruction starts.
+                sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+            }
+
+            emit_std_reg_mem(
+                sink,
+                LegacyPrefix::None,
+                0x8B,
+                1,
+                dst.to_reg(),
+                src,
+                RexFlags::set_w(),
+            )
+        }
 
         Inst::LoadEffectiveAddress { addr, dst } => emit_std_reg_mem(
             sink,
@@ -620,7 +874,12 @@ pub(crate) fn emit(
             RexFlags::set_w(),
         ),
 
-        Inst::MovSX_RM_R { ext_mode, src, dst } => {
+        Inst::MovSX_RM_R {
+            ext_mode,
+            src,
+            dst,
+            srcloc,
+        } => {
             let (opcodes, num_opcodes, rex_flags) = match ext_mode {
                 ExtMode::BL => {
                     // MOVSBL is (REX.W==0) 0F BE /r
@@ -654,21 +913,40 @@ pub(crate) fn emit(
                     *src,
                     rex_flags,
                 ),
-                RegMem::Mem { addr: src } => emit_std_reg_mem(
-                    sink,
-                    LegacyPrefix::None,
-                    opcodes,
-                    num_opcodes,
-                    dst.to_reg(),
-                    &src.finalize(state),
-                    rex_flags,
-                ),
+                RegMem::Mem { addr: src } => {
+                    let src = &src.finalize(state);
+
+                    if let Some(srcloc) = *srcloc {
+                        // Register the offset at which the actual load instruction starts.
+                        sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+                    }
+
+                    emit_std_reg_mem(
+                        sink,
+                        LegacyPrefix::None,
+                        opcodes,
+                        num_opcodes,
+                        dst.to_reg(),
+                        src,
+                        rex_flags,
+                    )
+                }
             }
         }
 
-        Inst::Mov_R_M { size, src, dst } => {
+        Inst::Mov_R_M {
+            size,
+            src,
+            dst,
+            srcloc,
+        } => {
             let dst = &dst.finalize(state);
 
+            if let Some(srcloc) = *srcloc {
+                // Register the offset at which the actual store instruction starts.
+                sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+            }
+
             match size {
                 1 => {
                     // This is one of the few places where the presence of a
@@ -736,6 +1014,8 @@ pub(crate) fn emit(
         } => {
             let enc_dst = int_reg_enc(dst.to_reg());
             let subopcode = match kind {
+                ShiftKind::RotateLeft => 0,
+                ShiftKind::RotateRight => 1,
                 ShiftKind::Left => 4,
                 ShiftKind::RightZ => 5,
                 ShiftKind::RightS => 7,
@@ -749,14 +1029,14 @@ pub(crate) fn emit(
 
             match num_bits {
                 None => {
-                    // SHL/SHR/SAR %cl, reg32 is (REX.W==0) D3 /subopcode
-                    // SHL/SHR/SAR %cl, reg64 is (REX.W==1) D3 /subopcode
+                    // SHL/SHR/SAR/ROL/ROR %cl, reg32 is (REX.W==0) D3 /subopcode
+                    // SHL/SHR/SAR/ROL/ROR %cl, reg64 is (REX.W==1) D3 /subopcode
                     emit_std_enc_enc(sink, LegacyPrefix::None, 0xD3, 1, subopcode, enc_dst, rex);
                 }
 
                 Some(num_bits) => {
-                    // SHL/SHR/SAR $ib, reg32 is (REX.W==0) C1 /subopcode ib
-                    // SHL/SHR/SAR $ib, reg64 is (REX.W==1) C1 /subopcode ib
+                    // SHL/SHR/SAR/ROL/ROR $ib, reg32 is (REX.W==0) C1 /subopcode ib
+                    // SHL/SHR/SAR/ROL/ROR $ib, reg64 is (REX.W==1) C1 /subopcode ib
                     // When the shift amount is 1, there's an even shorter encoding, but we don't
                     // bother with that nicety here.
                     emit_std_enc_enc(sink, LegacyPrefix::None, 0xC1, 1, subopcode, enc_dst, rex);
@@ -834,6 +1114,52 @@ pub(crate) fn emit(
             }
         }
 
+        Inst::Test_RMI_R {
+            size,
+            src: src_e,
+            dst: reg_g,
+        } => {
+            let prefix = if *size == 2 {
+                LegacyPrefix::_66
+            } else {
+                LegacyPrefix::None
+            };
+
+            let mut rex = rex_for_size(*size, int_reg_enc(*reg_g));
+
+            match src_e {
+                RegMemImm::Reg { reg: reg_e } => {
+                    if *size == 1 {
+                        // Check whether the E register forces the use of a redundant REX.
+                        let enc_e = int_reg_enc(*reg_e);
+                        if enc_e >= 4 && enc_e <= 7 {
+                            rex.always_emit();
+                        }
+                    }
+
+                    // TEST r/m, r is 84 /r (byte) or 85 /r. The operation is symmetric, so use
+                    // the same operand order as gcc/llvm.
+                    let opcode = if *size == 1 { 0x84 } else { 0x85 };
+                    emit_std_reg_reg(sink, prefix, opcode, 1, *reg_e, *reg_g, rex);
+                }
+
+                RegMemImm::Mem { addr } => {
+                    let addr = &addr.finalize(state);
+                    let opcode = if *size == 1 { 0x84 } else { 0x85 };
+                    emit_std_reg_mem(sink, prefix, opcode, 1, *reg_g, addr, rex);
+                }
+
+                RegMemImm::Imm { simm32 } => {
+                    // TEST r/m, imm is F6 /0 ib (byte) or F7 /0 id; there's no short form with a
+                    // sign-extended 8-bits immediate.
+                    let opcode = if *size == 1 { 0xF6 } else { 0xF7 };
+                    let enc_g = int_reg_enc(*reg_g);
+                    emit_std_enc_enc(sink, prefix, opcode, 1, 0 /*subopcode*/, enc_g, rex);
+                    emit_simm(sink, *size, *simm32);
+                }
+            }
+        }
+
         Inst::Setcc { cc, dst } => {
             let opcode = 0x0f90 + cc.get_enc() as u32;
             let mut rex_flags = RexFlags::clear_w();
@@ -849,6 +1175,31 @@ pub(crate) fn emit(
             );
         }
 
+        Inst::Cmove {
+            size,
+            cc,
+            src,
+            dst: reg_g,
+        } => {
+            let (prefix, rex_flags) = match size {
+                2 => (LegacyPrefix::_66, RexFlags::clear_w()),
+                4 => (LegacyPrefix::None, RexFlags::clear_w()),
+                8 => (LegacyPrefix::None, RexFlags::set_w()),
+                _ => unreachable!("invalid size spec for cmove"),
+            };
+            // CMOVcc r/m, r is 0F 40+cc /r.
+            let opcode = 0x0F40 + cc.get_enc() as u32;
+            match src {
+                RegMem::Reg { reg: reg_e } => {
+                    emit_std_reg_reg(sink, prefix, opcode, 2, reg_g.to_reg(), *reg_e, rex_flags);
+                }
+                RegMem::Mem { addr } => {
+                    let addr = &addr.finalize(state);
+                    emit_std_reg_mem(sink, prefix, opcode, 2, reg_g.to_reg(), addr, rex_flags);
+                }
+            }
+        }
+
         Inst::Push64 { src } => {
             match src {
                 RegMemImm::Reg { reg } => {
@@ -943,6 +1294,28 @@ pub(crate) fn emit(
             }
         }
 
+        Inst::TailCallKnown { dest, loc, .. } => {
+            // No call site is recorded: control never comes back here.
+            sink.put1(0xE9);
+            // The addend adjusts for the difference between the end of the instruction and the
+            // beginning of the immediate field.
+            sink.add_reloc(*loc, Reloc::X86CallPCRel4, &dest, -4);
+            sink.put4(0);
+        }
+
+        Inst::TailCallUnknown { dest, .. } => {
+            let reg_enc = int_reg_enc(*dest);
+            emit_std_enc_enc(
+                sink,
+                LegacyPrefix::None,
+                0xFF,
+                1,
+                4, /*subopcode*/
+                reg_enc,
+                RexFlags::clear_w(),
+            );
+        }
+
         Inst::Ret {} => sink.put1(0xC3),
 
         Inst::JmpKnown { dst } => {
@@ -997,6 +1370,105 @@ pub(crate) fn emit(
             sink.put4(nt_disp);
         }
 
+        Inst::JmpIf { cc, taken } => {
+            let cond_start = sink.cur_offset();
+            let cond_disp_off = cond_start + 2;
+            if let Some(l) = taken.as_label() {
+                sink.use_label_at_offset(cond_disp_off, l, LabelUse::JmpRel32);
+                // Since this is not a terminator, don't enroll in the branch inversion mechanism.
+            }
+
+            let taken_disp = taken.as_offset32_or_zero();
+            let taken_disp = taken_disp as u32;
+            sink.put1(0x0F);
+            sink.put1(0x80 + cc.get_enc());
+            sink.put4(taken_disp);
+        }
+
+        Inst::JmpTableSeq {
+            idx,
+            tmp1,
+            tmp2,
+            targets,
+            default_target,
+            ..
+        } => {
+            // This sequence is *one* instruction in the vcode, and is expanded only here at
+            // emission time, because we cannot allow the regalloc to insert spills/reloads in
+            // the middle; we depend on hardcoded PC-rel addressing below.
+            //
+            // We don't have to worry about emitting islands, because the only label-use type has a
+            // maximum range of 2 GB. If we later consider using shorter-range label references,
+            // this will need to be revisited.
+
+            // We generate the following sequence:
+            // ;; generated by lowering: cmp #jmp_table_size, %idx
+            // jnb $default_target
+            // movl %idx, %tmp2
+            // lea start_of_jump_table_offset(%rip), %tmp1
+            // movslq [%tmp1, %tmp2, 4], %tmp2 ;; shift of 2, viz. multiply index by 4
+            // addq %tmp2, %tmp1
+            // j *%tmp1
+            // $start_of_jump_table:
+            // -- jump table entries
+            let default_label = match default_target {
+                BranchTarget::Label(label) => label,
+                _ => unreachable!(),
+            };
+            one_way_jmp(sink, CC::NB, *default_label); // idx unsigned >= jmp table size
+
+            // Copy the index (and make sure to clear the high 32-bits lane of tmp2).
+            let inst = Inst::movzx_rm_r(ExtMode::LQ, RegMem::reg(*idx), *tmp2, None);
+            inst.emit(sink, flags, state);
+
+            // Load base address of jump table: lea start_of_jump_table(%rip), %tmp1, viz.
+            // (REX.W==1) 8D /r with a RIP-relative operand.
+            let start_of_jumptable = sink.get_label();
+            let enc_tmp1 = int_reg_enc(tmp1.to_reg());
+            sink.put1(0x48 | (((enc_tmp1 >> 3) & 1) << 2));
+            sink.put1(0x8D);
+            sink.put1(encode_modrm(0, enc_tmp1 & 7, 0b101));
+            let disp_off = sink.cur_offset();
+            sink.use_label_at_offset(disp_off, start_of_jumptable, LabelUse::JmpRel32);
+            sink.put4(0);
+
+            // Load value out of jump table.
+            let inst = Inst::movsx_rm_r(
+                ExtMode::LQ,
+                RegMem::mem(Amode::imm_reg_reg_shift(0, tmp1.to_reg(), tmp2.to_reg(), 2)),
+                *tmp2,
+                None,
+            );
+            inst.emit(sink, flags, state);
+
+            // Add base of jump table to jump-table-sourced block offset.
+            let inst = Inst::alu_rmi_r(
+                true, /* is_64 */
+                AluRmiROpcode::Add,
+                RegMemImm::reg(tmp2.to_reg()),
+                *tmp1,
+            );
+            inst.emit(sink, flags, state);
+
+            // Branch to computed address.
+            let inst = Inst::jmp_unknown(RegMem::reg(tmp1.to_reg()));
+            inst.emit(sink, flags, state);
+
+            // Emit jump table (table of 32-bit offsets).
+            sink.bind_label(start_of_jumptable);
+            let jt_off = sink.cur_offset();
+            for &target in targets.iter() {
+                let word_off = sink.cur_offset();
+                // off_into_table is an addend here embedded in the label to be later patched at
+                // the end of codegen. The offset is initially relative to this jump table entry;
+                // with the extra addend, it'll be relative to the jump table's start, after
+                // patching.
+                let off_into_table = word_off - jt_off;
+                sink.use_label_at_offset(word_off, target.as_label().unwrap(), LabelUse::PCRel32);
+                sink.put4(off_into_table);
+            }
+        }
+
         Inst::JmpUnknown { target } => {
             match target {
                 RegMem::Reg { reg } => {
@@ -1031,13 +1503,23 @@ pub(crate) fn emit(
             op,
             src: src_e,
             dst: reg_g,
+            srcloc,
         } => {
             let rex = RexFlags::clear_w();
             let (prefix, opcode) = match op {
                 SseOpcode::Movaps => (LegacyPrefix::None, 0x0F28),
                 SseOpcode::Movd => (LegacyPrefix::_66, 0x0F6E),
+                SseOpcode::Movdqu => (LegacyPrefix::_F3, 0x0F6F),
+                SseOpcode::Movq => (LegacyPrefix::_F3, 0x0F7E),
                 SseOpcode::Movsd => (LegacyPrefix::_F2, 0x0F10),
                 SseOpcode::Movss => (LegacyPrefix::_F3, 0x0F10),
+                SseOpcode::Movups => (LegacyPrefix::None, 0x0F10),
+                SseOpcode::Sqrtps => (LegacyPrefix::None, 0x0F51),
+                SseOpcode::Sqrtpd => (LegacyPrefix::_66, 0x0F51),
+                SseOpcode::Sqrtss => (LegacyPrefix::_F3, 0x0F51),
+                SseOpcode::Sqrtsd => (LegacyPrefix::_F2, 0x0F51),
+                SseOpcode::Cvtss2sd => (LegacyPrefix::_F3, 0x0F5A),
+                SseOpcode::Cvtsd2ss => (LegacyPrefix::_F2, 0x0F5A),
                 _ => unimplemented!("Opcode {:?} not implemented", op),
             };
 
@@ -1048,6 +1530,10 @@ pub(crate) fn emit(
 
                 RegMem::Mem { addr } => {
                     let addr = &addr.finalize(state);
+                    if let Some(srcloc) = *srcloc {
+                        // Register the offset at which the actual load instruction starts.
+                        sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+                    }
                     emit_std_reg_mem(sink, prefix, opcode, 2, reg_g.to_reg(), addr, rex);
                 }
             }
@@ -1059,23 +1545,175 @@ pub(crate) fn emit(
             dst: reg_g,
         } => {
             let rex = RexFlags::clear_w();
+            let (prefix, opcode, length) = match op {
+                SseOpcode::Addps => (LegacyPrefix::None, 0x0F58, 2),
+                SseOpcode::Addpd => (LegacyPrefix::_66, 0x0F58, 2),
+                SseOpcode::Addss => (LegacyPrefix::_F3, 0x0F58, 2),
+                SseOpcode::Addsd => (LegacyPrefix::_F2, 0x0F58, 2),
+                SseOpcode::Andpd => (LegacyPrefix::_66, 0x0F54, 2),
+                SseOpcode::Andps => (LegacyPrefix::None, 0x0F54, 2),
+                SseOpcode::Andnpd => (LegacyPrefix::_66, 0x0F55, 2),
+                SseOpcode::Andnps => (LegacyPrefix::None, 0x0F55, 2),
+                SseOpcode::Divps => (LegacyPrefix::None, 0x0F5E, 2),
+                SseOpcode::Divpd => (LegacyPrefix::_66, 0x0F5E, 2),
+                SseOpcode::Divss => (LegacyPrefix::_F3, 0x0F5E, 2),
+                SseOpcode::Divsd => (LegacyPrefix::_F2, 0x0F5E, 2),
+                SseOpcode::Maxss => (LegacyPrefix::_F3, 0x0F5F, 2),
+                SseOpcode::Maxsd => (LegacyPrefix::_F2, 0x0F5F, 2),
+                SseOpcode::Minss => (LegacyPrefix::_F3, 0x0F5D, 2),
+                SseOpcode::Minsd => (LegacyPrefix::_F2, 0x0F5D, 2),
+                SseOpcode::Mulps => (LegacyPrefix::None, 0x0F59, 2),
+                SseOpcode::Mulpd => (LegacyPrefix::_66, 0x0F59, 2),
+                SseOpcode::Mulss => (LegacyPrefix::_F3, 0x0F59, 2),
+                SseOpcode::Mulsd => (LegacyPrefix::_F2, 0x0F59, 2),
+                SseOpcode::Orpd => (LegacyPrefix::_66, 0x0F56, 2),
+                SseOpcode::Orps => (LegacyPrefix::None, 0x0F56, 2),
+                SseOpcode::Paddb => (LegacyPrefix::_66, 0x0FFC, 2),
+                SseOpcode::Paddw => (LegacyPrefix::_66, 0x0FFD, 2),
+                SseOpcode::Paddd => (LegacyPrefix::_66, 0x0FFE, 2),
+                SseOpcode::Paddq => (LegacyPrefix::_66, 0x0FD4, 2),
+                SseOpcode::Pand => (LegacyPrefix::_66, 0x0FDB, 2),
+                SseOpcode::Pandn => (LegacyPrefix::_66, 0x0FDF, 2),
+                SseOpcode::Pmulld => (LegacyPrefix::_66, 0x0F3840, 3),
+                SseOpcode::Pmullw => (LegacyPrefix::_66, 0x0FD5, 2),
+                SseOpcode::Por => (LegacyPrefix::_66, 0x0FEB, 2),
+                SseOpcode::Psubb => (LegacyPrefix::_66, 0x0FF8, 2),
+                SseOpcode::Psubw => (LegacyPrefix::_66, 0x0FF9, 2),
+                SseOpcode::Psubd => (LegacyPrefix::_66, 0x0FFA, 2),
+                SseOpcode::Psubq => (LegacyPrefix::_66, 0x0FFB, 2),
+                SseOpcode::Punpcklqdq => (LegacyPrefix::_66, 0x0F6C, 2),
+                SseOpcode::Pxor => (LegacyPrefix::_66, 0x0FEF, 2),
+                SseOpcode::Subps => (LegacyPrefix::None, 0x0F5C, 2),
+                SseOpcode::Subpd => (LegacyPrefix::_66, 0x0F5C, 2),
+                SseOpcode::Subss => (LegacyPrefix::_F3, 0x0F5C, 2),
+                SseOpcode::Subsd => (LegacyPrefix::_F2, 0x0F5C, 2),
+                SseOpcode::Sqrtss => (LegacyPrefix::_F3, 0x0F51, 2),
+                SseOpcode::Sqrtsd => (LegacyPrefix::_F2, 0x0F51, 2),
+                SseOpcode::Xorps => (LegacyPrefix::None, 0x0F57, 2),
+                SseOpcode::Xorpd => (LegacyPrefix::_66, 0x0F57, 2),
+                _ => unimplemented!("Opcode {:?} not implemented", op),
+            };
+
+            match src_e {
+                RegMem::Reg { reg: reg_e } => {
+                    emit_std_reg_reg(sink, prefix, opcode, length, reg_g.to_reg(), *reg_e, rex);
+                }
+
+                RegMem::Mem { addr } => {
+                    let addr = &addr.finalize(state);
+                    emit_std_reg_mem(sink, prefix, opcode, length, reg_g.to_reg(), addr, rex);
+                }
+            }
+        }
+
+        Inst::XMM_RM_R_IMM { op, src, dst, imm } => {
+            let (prefix, opcode, length) = match op {
+                SseOpcode::Pshufd => (LegacyPrefix::_66, 0x0F70, 2),
+                SseOpcode::Roundss => (LegacyPrefix::_66, 0x0F3A0A, 3),
+                SseOpcode::Roundsd => (LegacyPrefix::_66, 0x0F3A0B, 3),
+                _ => unimplemented!("Opcode {:?} not implemented", op),
+            };
+
+            match src {
+                RegMem::Reg { reg } => {
+                    emit_std_reg_reg(
+                        sink,
+                        prefix,
+                        opcode,
+                        length,
+                        dst.to_reg(),
+                        *reg,
+                        RexFlags::clear_w(),
+                    );
+                }
+                RegMem::Mem { addr } => {
+                    let addr = &addr.finalize(state);
+                    emit_std_reg_mem(
+                        sink,
+                        prefix,
+                        opcode,
+                        length,
+                        dst.to_reg(),
+                        addr,
+                        RexFlags::clear_w(),
+                    );
+                }
+            }
+            sink.put1(*imm);
+        }
+
+        Inst::XMM_Mov_R_M {
+            op,
+            src,
+            dst,
+            srcloc,
+        } => {
             let (prefix, opcode) = match op {
-                SseOpcode::Addss => (LegacyPrefix::_F3, 0x0F58),
-                SseOpcode::Andps => (LegacyPrefix::None, 0x0F54),
-                SseOpcode::Andnps => (LegacyPrefix::None, 0x0F55),
-                SseOpcode::Divss => (LegacyPrefix::_F3, 0x0F5E),
-                SseOpcode::Mulss => (LegacyPrefix::_F3, 0x0F59),
-                SseOpcode::Orps => (LegacyPrefix::None, 0x0F56),
-                SseOpcode::Subss => (LegacyPrefix::_F3, 0x0F5C),
-                SseOpcode::Sqrtss => (LegacyPrefix::_F3, 0x0F51),
+                SseOpcode::Movaps => (LegacyPrefix::None, 0x0F29),
+                SseOpcode::Movdqu => (LegacyPrefix::_F3, 0x0F7F),
+                SseOpcode::Movsd => (LegacyPrefix::_F2, 0x0F11),
+                SseOpcode::Movss => (LegacyPrefix::_F3, 0x0F11),
+                SseOpcode::Movups => (LegacyPrefix::None, 0x0F11),
                 _ => unimplemented!("Opcode {:?} not implemented", op),
             };
+            let dst = &dst.finalize(state);
+            if let Some(srcloc) = *srcloc {
+                // Register the offset at which the actual store instruction starts.
+                sink.add_trap(srcloc, TrapCode::HeapOutOfBounds);
+            }
+            emit_std_reg_mem(sink, prefix, opcode, 2, *src, dst, RexFlags::clear_w());
+        }
+
+        Inst::XmmToGpr {
+            op,
+            src,
+            dst,
+            dst_size,
+        } => {
+            let (prefix, opcode, dst_first) = match op {
+                // Movd and movq use the G operand for the XMM source.
+                SseOpcode::Movd => (LegacyPrefix::_66, 0x0F7E, false),
+                SseOpcode::Movq => (LegacyPrefix::_66, 0x0F7E, false),
+                SseOpcode::Cvttss2si => (LegacyPrefix::_F3, 0x0F2C, true),
+                SseOpcode::Cvttsd2si => (LegacyPrefix::_F2, 0x0F2C, true),
+                _ => panic!("unexpected opcode {:?}", op),
+            };
+            let rex = match dst_size {
+                8 => RexFlags::set_w(),
+                _ => RexFlags::clear_w(),
+            };
 
+            let (reg_g, reg_e) = if dst_first {
+                (dst.to_reg(), *src)
+            } else {
+                (*src, dst.to_reg())
+            };
+
+            emit_std_reg_reg(sink, prefix, opcode, 2, reg_g, reg_e, rex);
+        }
+
+        Inst::GprToXmm {
+            op,
+            src: src_e,
+            dst: reg_g,
+            src_size,
+        } => {
+            let (prefix, opcode) = match op {
+                SseOpcode::Movd => (LegacyPrefix::_66, 0x0F6E),
+                SseOpcode::Movq => (LegacyPrefix::_66, 0x0F6E),
+                SseOpcode::Cvtsi2ss => (LegacyPrefix::_F3, 0x0F2A),
+                SseOpcode::Cvtsi2sd => (LegacyPrefix::_F2, 0x0F2A),
+                _ => panic!("unexpected opcode {:?}", op),
+            };
+            let rex = match *src_size {
+                8 => RexFlags::set_w(),
+                4 => RexFlags::clear_w(),
+                _ => unreachable!(),
+            };
             match src_e {
                 RegMem::Reg { reg: reg_e } => {
                     emit_std_reg_reg(sink, prefix, opcode, 2, reg_g.to_reg(), *reg_e, rex);
                 }
-
                 RegMem::Mem { addr } => {
                     let addr = &addr.finalize(state);
                     emit_std_reg_mem(sink, prefix, opcode, 2, reg_g.to_reg(), addr, rex);
@@ -1083,6 +1721,668 @@ pub(crate) fn emit(
             }
         }
 
+        Inst::XMM_Cmp_RM_R { op, src, dst } => {
+            let rex = RexFlags::clear_w();
+            let (prefix, opcode) = match op {
+                SseOpcode::Ucomisd => (LegacyPrefix::_66, 0x0F2E),
+                SseOpcode::Ucomiss => (LegacyPrefix::None, 0x0F2E),
+                _ => unimplemented!("Emit xmm cmp rm r"),
+            };
+
+            match src {
+                RegMem::Reg { reg } => {
+                    emit_std_reg_reg(sink, prefix, opcode, 2, *dst, *reg, rex);
+                }
+                RegMem::Mem { addr } => {
+                    let addr = &addr.finalize(state);
+                    emit_std_reg_mem(sink, prefix, opcode, 2, *dst, addr, rex);
+                }
+            }
+        }
+
+        Inst::XmmCmove {
+            is_64,
+            cc,
+            src,
+            dst,
+        } => {
+            // Lowering of the Select IR opcode when the input is an fcmp relies on the fact that
+            // this doesn't clobber flags. Make sure to not do so here.
+            let next = sink.get_label();
+
+            // Jump if cc is *not* set.
+            one_way_jmp(sink, cc.invert(), next);
+
+            let op = if *is_64 {
+                SseOpcode::Movsd
+            } else {
+                SseOpcode::Movss
+            };
+            let inst = Inst::xmm_mov_rm_r(op, src.clone(), *dst, None);
+            inst.emit(sink, flags, state);
+
+            sink.bind_label(next);
+        }
+
+        Inst::XmmMinMaxSeq {
+            is_min,
+            is_f64,
+            lhs,
+            rhs_dst,
+        } => {
+            // Generates the following sequence:
+            // ucomiss/ucomisd %lhs, %rhs_dst
+            // jnz do_min_max
+            // jp propagate_nan
+            //
+            // ;; ordered and equal: propagate the sign bit (for -0 vs 0):
+            // {and,or}{ss,sd} %lhs, %rhs_dst
+            // j done
+            //
+            // ;; to get the desired NaN behavior (signalling NaN transformed into a quiet NaN, the
+            // ;; NaN value is returned), we add both inputs.
+            // propagate_nan:
+            // add{ss,sd} %lhs, %rhs_dst
+            // j done
+            //
+            // do_min_max:
+            // {min,max}{ss,sd} %lhs, %rhs_dst
+            //
+            // done:
+            let done = sink.get_label();
+            let propagate_nan = sink.get_label();
+            let do_min_max = sink.get_label();
+
+            let (add_op, cmp_op, and_op, or_op, min_max_op) = if *is_f64 {
+                (
+                    SseOpcode::Addsd,
+                    SseOpcode::Ucomisd,
+                    SseOpcode::Andpd,
+                    SseOpcode::Orpd,
+                    if *is_min {
+                        SseOpcode::Minsd
+                    } else {
+                        SseOpcode::Maxsd
+                    },
+                )
+            } else {
+                (
+                    SseOpcode::Addss,
+                    SseOpcode::Ucomiss,
+                    SseOpcode::Andps,
+                    SseOpcode::Orps,
+                    if *is_min {
+                        SseOpcode::Minss
+                    } else {
+                        SseOpcode::Maxss
+                    },
+                )
+            };
+
+            let inst = Inst::xmm_cmp_rm_r(cmp_op, RegMem::reg(*lhs), rhs_dst.to_reg());
+            inst.emit(sink, flags, state);
+
+            one_way_jmp(sink, CC::NZ, do_min_max);
+            one_way_jmp(sink, CC::P, propagate_nan);
+
+            // Ordered and equal. The operands are bit-identical unless they are zero
+            // and negative zero. These instructions merge the sign bits in that
+            // case, and are no-ops otherwise.
+            let op = if *is_min { or_op } else { and_op };
+            let inst = Inst::xmm_rm_r(op, RegMem::reg(*lhs), *rhs_dst);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::jmp_known(BranchTarget::Label(done));
+            inst.emit(sink, flags, state);
+
+            // x86's min/max are not symmetric; if either operand is a NaN, they return the
+            // read-only operand: perform an addition between the two operands, which has the
+            // desired NaN propagation effects.
+            sink.bind_label(propagate_nan);
+            let inst = Inst::xmm_rm_r(add_op, RegMem::reg(*lhs), *rhs_dst);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::jmp_known(BranchTarget::Label(done));
+            inst.emit(sink, flags, state);
+
+            sink.bind_label(do_min_max);
+            let inst = Inst::xmm_rm_r(min_max_op, RegMem::reg(*lhs), *rhs_dst);
+            inst.emit(sink, flags, state);
+
+            sink.bind_label(done);
+        }
+
+        Inst::CvtUint64ToFloatSeq {
+            to_f64,
+            src,
+            dst,
+            tmp_gpr1,
+            tmp_gpr2,
+        } => {
+            // Emit the following sequence:
+            //
+            //  cmp $0, %src
+            //  jl handle_negative
+            //
+            //  ;; handle positive, which can't overflow
+            //  cvtsi2sd/cvtsi2ss %src, %dst
+            //  j done
+            //
+            //  ;; handle negative: see below for an explanation of what it's doing.
+            //  handle_negative:
+            //  mov %src, %tmp_gpr1
+            //  shr $1, %tmp_gpr1
+            //  mov %src, %tmp_gpr2
+            //  and $1, %tmp_gpr2
+            //  or %tmp_gpr1, %tmp_gpr2
+            //  cvtsi2sd/cvtsi2ss %tmp_gpr2, %dst
+            //  addsd/addss %dst, %dst
+            //
+            //  done:
+
+            assert!(src != tmp_gpr1);
+            assert!(src != tmp_gpr2);
+            assert!(tmp_gpr1 != tmp_gpr2);
+
+            let handle_negative = sink.get_label();
+            let done = sink.get_label();
+
+            // If x seen as a signed int is not negative, a signed-conversion will do the right
+            // thing.
+            // TODO use tst src, src here.
+            let inst = Inst::cmp_rmi_r(8, RegMemImm::imm(0), src.to_reg());
+            inst.emit(sink, flags, state);
+
+            one_way_jmp(sink, CC::L, handle_negative);
+
+            // Handle a positive int64, which is the "easy" case: a signed conversion will do the
+            // right thing.
+            let op = if *to_f64 {
+                SseOpcode::Cvtsi2sd
+            } else {
+                SseOpcode::Cvtsi2ss
+            };
+            let inst = Inst::gpr_to_xmm(op, RegMem::reg(src.to_reg()), 8, *dst);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::jmp_known(BranchTarget::Label(done));
+            inst.emit(sink, flags, state);
+
+            sink.bind_label(handle_negative);
+
+            // Divide x by two to get it in range for the signed conversion, keep the LSB, and
+            // scale it back up on the FP side.
+            let inst = Inst::gen_move(*tmp_gpr1, src.to_reg(), I64);
+            inst.emit(sink, flags, state);
+
+            // tmp_gpr1 := src >> 1
+            let inst = Inst::shift_r(/*is_64*/ true, ShiftKind::RightZ, Some(1), *tmp_gpr1);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::gen_move(*tmp_gpr2, src.to_reg(), I64);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::alu_rmi_r(
+                true, /* 64bits */
+                AluRmiROpcode::And,
+                RegMemImm::imm(1),
+                *tmp_gpr2,
+            );
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::alu_rmi_r(
+                true, /* 64bits */
+                AluRmiROpcode::Or,
+                RegMemImm::reg(tmp_gpr1.to_reg()),
+                *tmp_gpr2,
+            );
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::gpr_to_xmm(op, RegMem::reg(tmp_gpr2.to_reg()), 8, *dst);
+            inst.emit(sink, flags, state);
+
+            let add_op = if *to_f64 {
+                SseOpcode::Addsd
+            } else {
+                SseOpcode::Addss
+            };
+            let inst = Inst::xmm_rm_r(add_op, RegMem::reg(dst.to_reg()), *dst);
+            inst.emit(sink, flags, state);
+
+            sink.bind_label(done);
+        }
+
+        Inst::CvtFloatToSintSeq {
+            src_is_f64,
+            dst_size,
+            is_saturating,
+            src,
+            dst,
+            tmp_gpr,
+            tmp_xmm,
+            srcloc,
+        } => {
+            // Emits the following common sequence:
+            //
+            // cvttss2si/cvttsd2si %src, %dst
+            // cmp $1, %dst
+            // jno done
+            //
+            // Then, for saturating conversions:
+            //
+            // ;; check for NaN
+            // ucomiss/ucomisd %src, %src
+            // jnp not_nan
+            // xor %dst, %dst
+            //
+            // ;; positive inputs get saturated to INT_MAX; negative ones to INT_MIN, which is
+            // ;; already in %dst.
+            // xorpd %tmp_xmm, %tmp_xmm
+            // ucomiss/ucomisd %src, %tmp_xmm
+            // jnb done
+            // mov $INT_MAX, %dst
+            //
+            // done:
+            //
+            // Then, for non-saturating conversions:
+            //
+            // ;; check for NaN
+            // ucomiss/ucomisd %src, %src
+            // jnp not_nan
+            // ud2 trap BadConversionToInteger
+            //
+            // ;; check if INT_MIN was the correct result, against a magic constant:
+            // not_nan:
+            // mov $magic, %tmp_gpr
+            // movq/movd %tmp_gpr, %tmp_xmm
+            // ucomiss/ucomisd %tmp_xmm, %src
+            // jnb/jnbe $check_positive
+            // ud2 trap IntegerOverflow
+            //
+            // ;; if positive, it was a real overflow
+            // check_positive:
+            // xorpd %tmp_xmm, %tmp_xmm
+            // ucomiss/ucomisd %src, %tmp_xmm
+            // jnb done
+            // ud2 trap IntegerOverflow
+            //
+            // done:
+
+            let src = src.to_reg();
+
+            let (cast_op, cmp_op, trunc_op) = if *src_is_f64 {
+                (SseOpcode::Movq, SseOpcode::Ucomisd, SseOpcode::Cvttsd2si)
+            } else {
+                (SseOpcode::Movd, SseOpcode::Ucomiss, SseOpcode::Cvttss2si)
+            };
+
+            let done = sink.get_label();
+            let not_nan = sink.get_label();
+
+            // The truncation.
+            let inst = Inst::xmm_to_gpr(trunc_op, src, *dst, *dst_size);
+            inst.emit(sink, flags, state);
+
+            // Compare against 1, in case of overflow the dst operand was INT_MIN.
+            let inst = Inst::cmp_rmi_r(*dst_size, RegMemImm::imm(1), dst.to_reg());
+            inst.emit(sink, flags, state);
+
+            one_way_jmp(sink, CC::NO, done); // no overflow => done
+
+            // Check for NaN.
+
+            let inst = Inst::xmm_cmp_rm_r(cmp_op, RegMem::reg(src), src);
+            inst.emit(sink, flags, state);
+
+            one_way_jmp(sink, CC::NP, not_nan); // go to not_nan if not a NaN
+
+            if *is_saturating {
+                // For NaN, emit 0.
+                let inst = Inst::alu_rmi_r(
+                    *dst_size == 8,
+                    AluRmiROpcode::Xor,
+                    RegMemImm::reg(dst.to_reg()),
+                    *dst,
+                );
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::jmp_known(BranchTarget::Label(done));
+                inst.emit(sink, flags, state);
+
+                sink.bind_label(not_nan);
+
+                // If the input was positive, saturate to INT_MAX.
+
+                // Zero out tmp_xmm.
+                let inst =
+                    Inst::xmm_rm_r(SseOpcode::Xorpd, RegMem::reg(tmp_xmm.to_reg()), *tmp_xmm);
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::xmm_cmp_rm_r(cmp_op, RegMem::reg(src), tmp_xmm.to_reg());
+                inst.emit(sink, flags, state);
+
+                // Jump if >= to done.
+                one_way_jmp(sink, CC::NB, done);
+
+                // Otherwise, put INT_MAX.
+                if *dst_size == 8 {
+                    let inst = Inst::imm_r(true, 0x7fffffffffffffff, *dst);
+                    inst.emit(sink, flags, state);
+                } else {
+                    let inst = Inst::imm_r(false, 0x7fffffff, *dst);
+                    inst.emit(sink, flags, state);
+                }
+            } else {
+                let check_positive = sink.get_label();
+
+                let inst = Inst::trap(*srcloc, TrapCode::BadConversionToInteger);
+                inst.emit(sink, flags, state);
+
+                // Check if INT_MIN was the correct result: determine the smallest floating point
+                // number that would convert to INT_MIN, put it in a temporary register, and compare
+                // against the src register.
+                // If the src register is less (or in some cases, less-or-equal) than the threshold,
+                // trap!
+
+                sink.bind_label(not_nan);
+
+                let mut no_overflow_cc = CC::NB; // >=
+                let output_bits = *dst_size * 8;
+                match *src_is_f64 {
+                    false => {
+                        // An f32 can represent INT_MIN exactly, and the next number is a whole
+                        // rounding step (more than 1) away from it.
+                        let cst = Ieee32::pow2(output_bits - 1).neg().bits();
+                        let inst = Inst::imm_r(true, cst as u64, *tmp_gpr);
+                        inst.emit(sink, flags, state);
+                    }
+                    true => {
+                        // An f64 can represent `INT_MIN - 1` exactly when converting to i32, so
+                        // anything strictly above it truncates to INT_MIN. For i64, the next
+                        // representable number below INT_MIN is more than 1 away, like for f32.
+                        let cst = if output_bits < 64 {
+                            no_overflow_cc = CC::NBE; // >
+                            Ieee64::fcvt_to_sint_negative_overflow(output_bits)
+                        } else {
+                            Ieee64::pow2(output_bits - 1).neg()
+                        };
+                        let inst = Inst::imm_r(true, cst.bits(), *tmp_gpr);
+                        inst.emit(sink, flags, state);
+                    }
+                }
+
+                let inst = Inst::gpr_to_xmm(
+                    cast_op,
+                    RegMem::reg(tmp_gpr.to_reg()),
+                    if *src_is_f64 { 8 } else { 4 },
+                    *tmp_xmm,
+                );
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::xmm_cmp_rm_r(cmp_op, RegMem::reg(tmp_xmm.to_reg()), src);
+                inst.emit(sink, flags, state);
+
+                // jump over trap if src >= or > threshold
+                one_way_jmp(sink, no_overflow_cc, check_positive);
+
+                let inst = Inst::trap(*srcloc, TrapCode::IntegerOverflow);
+                inst.emit(sink, flags, state);
+
+                // If positive, it was a real overflow.
+
+                sink.bind_label(check_positive);
+
+                // Zero out the tmp_xmm register.
+                let inst =
+                    Inst::xmm_rm_r(SseOpcode::Xorpd, RegMem::reg(tmp_xmm.to_reg()), *tmp_xmm);
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::xmm_cmp_rm_r(cmp_op, RegMem::reg(src), tmp_xmm.to_reg());
+                inst.emit(sink, flags, state);
+
+                one_way_jmp(sink, CC::NB, done); // jump over trap if 0 >= src
+
+                let inst = Inst::trap(*srcloc, TrapCode::IntegerOverflow);
+                inst.emit(sink, flags, state);
+            }
+
+            sink.bind_label(done);
+        }
+
+        Inst::CvtFloatToUintSeq {
+            src_is_f64,
+            dst_size,
+            is_saturating,
+            src,
+            dst,
+            tmp_gpr,
+            tmp_xmm,
+            srcloc,
+        } => {
+            // The only difference in behavior between saturating and non-saturating is how we
+            // handle errors. Emits the following sequence:
+            //
+            // mov 2**(int_width - 1), %tmp_gpr
+            // movq/movd %tmp_gpr, %tmp_xmm
+            // ucomiss/ucomisd %tmp_xmm, %src
+            // jnb is_large
+            //
+            // ;; check for NaN inputs
+            // jnp not_nan
+            // -- non-saturating: ud2 trap BadConversionToInteger
+            // -- saturating: xor %dst, %dst; j done
+            //
+            // not_nan:
+            // cvttss2si/cvttsd2si %src, %dst
+            // cmp $0, %dst
+            // jnl done
+            // -- non-saturating: ud2 trap IntegerOverflow
+            // -- saturating: xor %dst, %dst; j done
+            //
+            // is_large:
+            // subss/subsd %tmp_xmm, %src ; <-- we clobber %src here
+            // cvttss2si/cvttsd2si %src, %dst
+            // cmp $0, %dst
+            // jnl next_is_large
+            // -- non-saturating: ud2 trap IntegerOverflow
+            // -- saturating: mov $UINT_MAX, %dst; j done
+            //
+            // next_is_large:
+            // add 2**(int_width -1), %dst ;; 2 instructions for 64-bits integers
+            //
+            // done:
+
+            assert!(tmp_xmm != src, "tmp_xmm clobbers src!");
+
+            let (sub_op, cast_op, cmp_op, trunc_op) = if *src_is_f64 {
+                (
+                    SseOpcode::Subsd,
+                    SseOpcode::Movq,
+                    SseOpcode::Ucomisd,
+                    SseOpcode::Cvttsd2si,
+                )
+            } else {
+                (
+                    SseOpcode::Subss,
+                    SseOpcode::Movd,
+                    SseOpcode::Ucomiss,
+                    SseOpcode::Cvttss2si,
+                )
+            };
+
+            let done = sink.get_label();
+
+            let cst = if *src_is_f64 {
+                Ieee64::pow2(*dst_size * 8 - 1).bits()
+            } else {
+                Ieee32::pow2(*dst_size * 8 - 1).bits() as u64
+            };
+
+            let inst = Inst::imm_r(true, cst, *tmp_gpr);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::gpr_to_xmm(
+                cast_op,
+                RegMem::reg(tmp_gpr.to_reg()),
+                if *src_is_f64 { 8 } else { 4 },
+                *tmp_xmm,
+            );
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::xmm_cmp_rm_r(cmp_op, RegMem::reg(tmp_xmm.to_reg()), src.to_reg());
+            inst.emit(sink, flags, state);
+
+            let handle_large = sink.get_label();
+            one_way_jmp(sink, CC::NB, handle_large); // jump to handle_large if src >= large_threshold
+
+            let not_nan = sink.get_label();
+            one_way_jmp(sink, CC::NP, not_nan); // jump over trap if not NaN
+
+            if *is_saturating {
+                // Emit 0.
+                let inst = Inst::alu_rmi_r(
+                    *dst_size == 8,
+                    AluRmiROpcode::Xor,
+                    RegMemImm::reg(dst.to_reg()),
+                    *dst,
+                );
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::jmp_known(BranchTarget::Label(done));
+                inst.emit(sink, flags, state);
+            } else {
+                // Trap.
+                let inst = Inst::trap(*srcloc, TrapCode::BadConversionToInteger);
+                inst.emit(sink, flags, state);
+            }
+
+            sink.bind_label(not_nan);
+
+            // Actual truncation for small inputs: if the result is not positive, then we had an
+            // overflow.
+
+            let inst = Inst::xmm_to_gpr(trunc_op, src.to_reg(), *dst, *dst_size);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::cmp_rmi_r(*dst_size, RegMemImm::imm(0), dst.to_reg());
+            inst.emit(sink, flags, state);
+
+            one_way_jmp(sink, CC::NL, done); // if dst >= 0, jump to done
+
+            if *is_saturating {
+                // The input was "small" (< 2**(width -1)), so the only way to get an integer
+                // overflow is because the input was too small: saturate to the min value, i.e. 0.
+                let inst = Inst::alu_rmi_r(
+                    *dst_size == 8,
+                    AluRmiROpcode::Xor,
+                    RegMemImm::reg(dst.to_reg()),
+                    *dst,
+                );
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::jmp_known(BranchTarget::Label(done));
+                inst.emit(sink, flags, state);
+            } else {
+                // Trap.
+                let inst = Inst::trap(*srcloc, TrapCode::IntegerOverflow);
+                inst.emit(sink, flags, state);
+            }
+
+            // Now handle large inputs.
+
+            sink.bind_label(handle_large);
+
+            let inst = Inst::xmm_rm_r(sub_op, RegMem::reg(tmp_xmm.to_reg()), *src);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::xmm_to_gpr(trunc_op, src.to_reg(), *dst, *dst_size);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::cmp_rmi_r(*dst_size, RegMemImm::imm(0), dst.to_reg());
+            inst.emit(sink, flags, state);
+
+            let next_is_large = sink.get_label();
+            one_way_jmp(sink, CC::NL, next_is_large); // if dst >= 0, jump to next_is_large
+
+            if *is_saturating {
+                // The input was "large" (>= 2**(width -1)), so the only way to get an integer
+                // overflow is because the input was too large: saturate to the max value.
+                let inst = Inst::imm_r(
+                    true,
+                    if *dst_size == 8 {
+                        u64::max_value()
+                    } else {
+                        u32::max_value() as u64
+                    },
+                    *dst,
+                );
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::jmp_known(BranchTarget::Label(done));
+                inst.emit(sink, flags, state);
+            } else {
+                let inst = Inst::trap(*srcloc, TrapCode::IntegerOverflow);
+                inst.emit(sink, flags, state);
+            }
+
+            sink.bind_label(next_is_large);
+
+            if *dst_size == 8 {
+                let inst = Inst::imm_r(true, 1 << 63, *tmp_gpr);
+                inst.emit(sink, flags, state);
+
+                let inst = Inst::alu_rmi_r(
+                    true,
+                    AluRmiROpcode::Add,
+                    RegMemImm::reg(tmp_gpr.to_reg()),
+                    *dst,
+                );
+                inst.emit(sink, flags, state);
+            } else {
+                let inst =
+                    Inst::alu_rmi_r(false, AluRmiROpcode::Add, RegMemImm::imm(1 << 31), *dst);
+                inst.emit(sink, flags, state);
+            }
+
+            sink.bind_label(done);
+        }
+
+        Inst::LoadExtName {
+            dst,
+            name,
+            offset,
+            srcloc,
+        } => {
+            // The full address can be encoded in the register, with a relocation.
+            // Generates: movabsq $name, %dst
+            let enc_dst = int_reg_enc(dst.to_reg());
+            sink.put1(0x48 | ((enc_dst >> 3) & 1));
+            sink.put1(0xB8 | (enc_dst & 7));
+            sink.add_reloc(*srcloc, Reloc::Abs8, name, *offset);
+            if flags.emit_all_ones_funcaddrs() {
+                sink.put8(u64::max_value());
+            } else {
+                sink.put8(0);
+            }
+        }
+
+        Inst::TrapIf {
+            cc,
+            trap_code,
+            srcloc,
+        } => {
+            let else_label = sink.get_label();
+
+            // Jump over if the invert of CC is set (i.e. CC is not set).
+            one_way_jmp(sink, cc.invert(), else_label);
+
+            // Trap!
+            let inst = Inst::trap(*srcloc, *trap_code);
+            inst.emit(sink, flags, state);
+
+            sink.bind_label(else_label);
+        }
+
         Inst::Hlt => {
             sink.put1(0xcc);
         }
@@ -1093,6 +2393,40 @@ pub(crate) fn emit(
             sink.put1(0x0b);
         }
 
+        Inst::StackProbeLoop {
+            tmp,
+            pages,
+            page_size,
+        } => {
+            // The sequence is:
+            //
+            //      movl $pages, %tmp
+            //   loop:
+            //      subq $page_size, %rsp
+            //      movq %rsp, 0(%rsp)
+            //      subl $1, %tmp
+            //      jnz loop
+            let r_rsp = regs::rsp();
+            let w_rsp = Writable::from_reg(r_rsp);
+
+            let inst = Inst::imm_r(false, *pages as u64, *tmp);
+            inst.emit(sink, flags, state);
+
+            let loop_label = sink.get_label();
+            sink.bind_label(loop_label);
+
+            let inst = Inst::alu_rmi_r(true, AluRmiROpcode::Sub, RegMemImm::imm(*page_size), w_rsp);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::mov_r_m(8, r_rsp, Amode::imm_reg(0, r_rsp), None);
+            inst.emit(sink, flags, state);
+
+            let inst = Inst::alu_rmi_r(false, AluRmiROpcode::Sub, RegMemImm::imm(1), *tmp);
+            inst.emit(sink, flags, state);
+
+            one_way_jmp(sink, CC::NZ, loop_label);
+        }
+
         Inst::VirtualSPOffsetAdj { offset } => {
             debug!(
                 "virtual sp offset adjusted by {} -> {}",
@@ -1102,7 +2436,90 @@ pub(crate) fn emit(
             state.virtual_sp_offset += offset;
         }
 
-        Inst::Nop { .. } | Inst::EpiloguePlaceholder => {
+        Inst::Nop { len } => {
+            // These encodings can all be found in Intel's architecture manual, at the NOP
+            // instruction description.
+            let mut len = *len;
+            while len != 0 {
+                let emitted = u8::min(len, 9);
+                match emitted {
+                    0 => {}
+                    1 => sink.put1(0x90), // NOP
+                    2 => {
+                        // 66 NOP
+                        sink.put1(0x66);
+                        sink.put1(0x90);
+                    }
+                    3 => {
+                        // NOP [EAX]
+                        sink.put1(0x0F);
+                        sink.put1(0x1F);
+                        sink.put1(0x00);
+                    }
+                    4 => {
+                        // NOP 0(EAX), with 0 a 1-byte immediate.
+                        sink.put1(0x0F);
+                        sink.put1(0x1F);
+                        sink.put1(0x40);
+                        sink.put1(0x00);
+                    }
+                    5 => {
+                        // NOP [EAX, EAX, 1]
+                        sink.put1(0x0F);
+                        sink.put1(0x1F);
+                        sink.put1(0x44);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                    }
+                    6 => {
+                        // 66 NOP [EAX, EAX, 1]
+                        sink.put1(0x66);
+                        sink.put1(0x0F);
+                        sink.put1(0x1F);
+                        sink.put1(0x44);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                    }
+                    7 => {
+                        // NOP 0[EAX], but 0 is a 4 bytes immediate.
+                        sink.put1(0x0F);
+                        sink.put1(0x1F);
+                        sink.put1(0x80);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                    }
+                    8 => {
+                        // NOP 0[EAX, EAX, 1], with 0 a 4 bytes immediate.
+                        sink.put1(0x0F);
+                        sink.put1(0x1F);
+                        sink.put1(0x84);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                    }
+                    9 => {
+                        // 66 NOP 0[EAX, EAX, 1], with 0 a 4 bytes immediate.
+                        sink.put1(0x66);
+                        sink.put1(0x0F);
+                        sink.put1(0x1F);
+                        sink.put1(0x84);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                        sink.put1(0x00);
+                    }
+                    _ => unreachable!(),
+                }
+                len -= emitted;
+            }
+        }
+
+        Inst::EpiloguePlaceholder => {
             // Generate no code.
         }
     }
@@ -95,82 +95,82 @@ fn test_x64_emit() {
     //
     // Addr_IR, offset zero
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rax), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rax), w_rdi, None),
         "488B38",
         "movq    0(%rax), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rbx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rbx), w_rdi, None),
         "488B3B",
         "movq    0(%rbx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rcx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rcx), w_rdi, None),
         "488B39",
         "movq    0(%rcx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rdx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rdx), w_rdi, None),
         "488B3A",
         "movq    0(%rdx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rbp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rbp), w_rdi, None),
         "488B7D00",
         "movq    0(%rbp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rsp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rsp), w_rdi, None),
         "488B3C24",
         "movq    0(%rsp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rsi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rsi), w_rdi, None),
         "488B3E",
         "movq    0(%rsi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, rdi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, rdi), w_rdi, None),
         "488B3F",
         "movq    0(%rdi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r8), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r8), w_rdi, None),
         "498B38",
         "movq    0(%r8), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r9), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r9), w_rdi, None),
         "498B39",
         "movq    0(%r9), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r10), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r10), w_rdi, None),
         "498B3A",
         "movq    0(%r10), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r11), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r11), w_rdi, None),
         "498B3B",
         "movq    0(%r11), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r12), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r12), w_rdi, None),
         "498B3C24",
         "movq    0(%r12), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r13), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r13), w_rdi, None),
         "498B7D00",
         "movq    0(%r13), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r14), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r14), w_rdi, None),
         "498B3E",
         "movq    0(%r14), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0, r15), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0, r15), w_rdi, None),
         "498B3F",
         "movq    0(%r15), %rdi",
     ));
@@ -178,82 +178,82 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IR, offset max simm8
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rax), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rax), w_rdi, None),
         "488B787F",
         "movq    127(%rax), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rbx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rbx), w_rdi, None),
         "488B7B7F",
         "movq    127(%rbx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rcx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rcx), w_rdi, None),
         "488B797F",
         "movq    127(%rcx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rdx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rdx), w_rdi, None),
         "488B7A7F",
         "movq    127(%rdx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rbp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rbp), w_rdi, None),
         "488B7D7F",
         "movq    127(%rbp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rsp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rsp), w_rdi, None),
         "488B7C247F",
         "movq    127(%rsp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rsi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rsi), w_rdi, None),
         "488B7E7F",
         "movq    127(%rsi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, rdi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, rdi), w_rdi, None),
         "488B7F7F",
         "movq    127(%rdi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r8), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r8), w_rdi, None),
         "498B787F",
         "movq    127(%r8), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r9), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r9), w_rdi, None),
         "498B797F",
         "movq    127(%r9), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r10), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r10), w_rdi, None),
         "498B7A7F",
         "movq    127(%r10), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r11), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r11), w_rdi, None),
         "498B7B7F",
         "movq    127(%r11), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r12), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r12), w_rdi, None),
         "498B7C247F",
         "movq    127(%r12), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r13), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r13), w_rdi, None),
         "498B7D7F",
         "movq    127(%r13), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r14), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r14), w_rdi, None),
         "498B7E7F",
         "movq    127(%r14), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(127, r15), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(127, r15), w_rdi, None),
         "498B7F7F",
         "movq    127(%r15), %rdi",
     ));
@@ -261,82 +261,82 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IR, offset min simm8
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rax), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rax), w_rdi, None),
         "488B7880",
         "movq    -128(%rax), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rbx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rbx), w_rdi, None),
         "488B7B80",
         "movq    -128(%rbx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rcx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rcx), w_rdi, None),
         "488B7980",
         "movq    -128(%rcx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rdx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rdx), w_rdi, None),
         "488B7A80",
         "movq    -128(%rdx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rbp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rbp), w_rdi, None),
         "488B7D80",
         "movq    -128(%rbp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rsp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rsp), w_rdi, None),
         "488B7C2480",
         "movq    -128(%rsp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rsi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rsi), w_rdi, None),
         "488B7E80",
         "movq    -128(%rsi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rdi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, rdi), w_rdi, None),
         "488B7F80",
         "movq    -128(%rdi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r8), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r8), w_rdi, None),
         "498B7880",
         "movq    -128(%r8), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r9), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r9), w_rdi, None),
         "498B7980",
         "movq    -128(%r9), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r10), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r10), w_rdi, None),
         "498B7A80",
         "movq    -128(%r10), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r11), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r11), w_rdi, None),
         "498B7B80",
         "movq    -128(%r11), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r12), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r12), w_rdi, None),
         "498B7C2480",
         "movq    -128(%r12), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r13), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r13), w_rdi, None),
         "498B7D80",
         "movq    -128(%r13), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r14), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r14), w_rdi, None),
         "498B7E80",
         "movq    -128(%r14), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r15), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-128i32 as u32, r15), w_rdi, None),
         "498B7F80",
         "movq    -128(%r15), %rdi",
     ));
@@ -344,82 +344,82 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IR, offset smallest positive simm32
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rax), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rax), w_rdi, None),
         "488BB880000000",
         "movq    128(%rax), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rbx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rbx), w_rdi, None),
         "488BBB80000000",
         "movq    128(%rbx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rcx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rcx), w_rdi, None),
         "488BB980000000",
         "movq    128(%rcx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rdx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rdx), w_rdi, None),
         "488BBA80000000",
         "movq    128(%rdx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rbp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rbp), w_rdi, None),
         "488BBD80000000",
         "movq    128(%rbp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rsp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rsp), w_rdi, None),
         "488BBC2480000000",
         "movq    128(%rsp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rsi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rsi), w_rdi, None),
         "488BBE80000000",
         "movq    128(%rsi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, rdi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, rdi), w_rdi, None),
         "488BBF80000000",
         "movq    128(%rdi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r8), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r8), w_rdi, None),
         "498BB880000000",
         "movq    128(%r8), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r9), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r9), w_rdi, None),
         "498BB980000000",
         "movq    128(%r9), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r10), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r10), w_rdi, None),
         "498BBA80000000",
         "movq    128(%r10), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r11), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r11), w_rdi, None),
         "498BBB80000000",
         "movq    128(%r11), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r12), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r12), w_rdi, None),
         "498BBC2480000000",
         "movq    128(%r12), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r13), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r13), w_rdi, None),
         "498BBD80000000",
         "movq    128(%r13), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r14), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r14), w_rdi, None),
         "498BBE80000000",
         "movq    128(%r14), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(128, r15), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(128, r15), w_rdi, None),
         "498BBF80000000",
         "movq    128(%r15), %rdi",
     ));
@@ -427,82 +427,82 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IR, offset smallest negative simm32
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rax), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rax), w_rdi, None),
         "488BB87FFFFFFF",
         "movq    -129(%rax), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rbx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rbx), w_rdi, None),
         "488BBB7FFFFFFF",
         "movq    -129(%rbx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rcx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rcx), w_rdi, None),
         "488BB97FFFFFFF",
         "movq    -129(%rcx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rdx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rdx), w_rdi, None),
         "488BBA7FFFFFFF",
         "movq    -129(%rdx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rbp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rbp), w_rdi, None),
         "488BBD7FFFFFFF",
         "movq    -129(%rbp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rsp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rsp), w_rdi, None),
         "488BBC247FFFFFFF",
         "movq    -129(%rsp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rsi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rsi), w_rdi, None),
         "488BBE7FFFFFFF",
         "movq    -129(%rsi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rdi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, rdi), w_rdi, None),
         "488BBF7FFFFFFF",
         "movq    -129(%rdi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r8), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r8), w_rdi, None),
         "498BB87FFFFFFF",
         "movq    -129(%r8), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r9), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r9), w_rdi, None),
         "498BB97FFFFFFF",
         "movq    -129(%r9), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r10), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r10), w_rdi, None),
         "498BBA7FFFFFFF",
         "movq    -129(%r10), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r11), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r11), w_rdi, None),
         "498BBB7FFFFFFF",
         "movq    -129(%r11), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r12), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r12), w_rdi, None),
         "498BBC247FFFFFFF",
         "movq    -129(%r12), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r13), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r13), w_rdi, None),
         "498BBD7FFFFFFF",
         "movq    -129(%r13), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r14), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r14), w_rdi, None),
         "498BBE7FFFFFFF",
         "movq    -129(%r14), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r15), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-129i32 as u32, r15), w_rdi, None),
         "498BBF7FFFFFFF",
         "movq    -129(%r15), %rdi",
     ));
@@ -510,82 +510,82 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IR, offset large positive simm32
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rax), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rax), w_rdi, None),
         "488BB877207317",
         "movq    393420919(%rax), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rbx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rbx), w_rdi, None),
         "488BBB77207317",
         "movq    393420919(%rbx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rcx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rcx), w_rdi, None),
         "488BB977207317",
         "movq    393420919(%rcx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rdx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rdx), w_rdi, None),
         "488BBA77207317",
         "movq    393420919(%rdx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rbp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rbp), w_rdi, None),
         "488BBD77207317",
         "movq    393420919(%rbp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rsp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rsp), w_rdi, None),
         "488BBC2477207317",
         "movq    393420919(%rsp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rsi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rsi), w_rdi, None),
         "488BBE77207317",
         "movq    393420919(%rsi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rdi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, rdi), w_rdi, None),
         "488BBF77207317",
         "movq    393420919(%rdi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r8), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r8), w_rdi, None),
         "498BB877207317",
         "movq    393420919(%r8), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r9), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r9), w_rdi, None),
         "498BB977207317",
         "movq    393420919(%r9), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r10), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r10), w_rdi, None),
         "498BBA77207317",
         "movq    393420919(%r10), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r11), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r11), w_rdi, None),
         "498BBB77207317",
         "movq    393420919(%r11), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r12), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r12), w_rdi, None),
         "498BBC2477207317",
         "movq    393420919(%r12), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r13), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r13), w_rdi, None),
         "498BBD77207317",
         "movq    393420919(%r13), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r14), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r14), w_rdi, None),
         "498BBE77207317",
         "movq    393420919(%r14), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r15), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(0x17732077, r15), w_rdi, None),
         "498BBF77207317",
         "movq    393420919(%r15), %rdi",
     ));
@@ -593,82 +593,82 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IR, offset large negative simm32
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rax), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rax), w_rdi, None),
         "488BB8D9A6BECE",
         "movq    -826366247(%rax), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rbx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rbx), w_rdi, None),
         "488BBBD9A6BECE",
         "movq    -826366247(%rbx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rcx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rcx), w_rdi, None),
         "488BB9D9A6BECE",
         "movq    -826366247(%rcx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rdx), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rdx), w_rdi, None),
         "488BBAD9A6BECE",
         "movq    -826366247(%rdx), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rbp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rbp), w_rdi, None),
         "488BBDD9A6BECE",
         "movq    -826366247(%rbp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rsp), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rsp), w_rdi, None),
         "488BBC24D9A6BECE",
         "movq    -826366247(%rsp), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rsi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rsi), w_rdi, None),
         "488BBED9A6BECE",
         "movq    -826366247(%rsi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rdi), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, rdi), w_rdi, None),
         "488BBFD9A6BECE",
         "movq    -826366247(%rdi), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r8), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r8), w_rdi, None),
         "498BB8D9A6BECE",
         "movq    -826366247(%r8), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r9), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r9), w_rdi, None),
         "498BB9D9A6BECE",
         "movq    -826366247(%r9), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r10), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r10), w_rdi, None),
         "498BBAD9A6BECE",
         "movq    -826366247(%r10), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r11), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r11), w_rdi, None),
         "498BBBD9A6BECE",
         "movq    -826366247(%r11), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r12), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r12), w_rdi, None),
         "498BBC24D9A6BECE",
         "movq    -826366247(%r12), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r13), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r13), w_rdi, None),
         "498BBDD9A6BECE",
         "movq    -826366247(%r13), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r14), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r14), w_rdi, None),
         "498BBED9A6BECE",
         "movq    -826366247(%r14), %rdi",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r15), w_rdi),
+        Inst::mov64_m_r(Amode::imm_reg(-0x31415927i32 as u32, r15), w_rdi, None),
         "498BBFD9A6BECE",
         "movq    -826366247(%r15), %rdi",
     ));
@@ -680,42 +680,42 @@ fn test_x64_emit() {
     //
     // Addr_IRRS, offset max simm8
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rax, rax, 0), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rax, rax, 0), w_r11, None),
         "4C8B5C007F",
         "movq    127(%rax,%rax,1), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rdi, rax, 1), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rdi, rax, 1), w_r11, None),
         "4C8B5C477F",
         "movq    127(%rdi,%rax,2), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r8, rax, 2), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r8, rax, 2), w_r11, None),
         "4D8B5C807F",
         "movq    127(%r8,%rax,4), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r15, rax, 3), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r15, rax, 3), w_r11, None),
         "4D8B5CC77F",
         "movq    127(%r15,%rax,8), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rax, rdi, 3), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rax, rdi, 3), w_r11, None),
         "4C8B5CF87F",
         "movq    127(%rax,%rdi,8), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rdi, rdi, 2), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, rdi, rdi, 2), w_r11, None),
         "4C8B5CBF7F",
         "movq    127(%rdi,%rdi,4), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r8, rdi, 1), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r8, rdi, 1), w_r11, None),
         "4D8B5C787F",
         "movq    127(%r8,%rdi,2), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r15, rdi, 0), w_r11),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(127, r15, rdi, 0), w_r11, None),
         "4D8B5C3F7F",
         "movq    127(%r15,%rdi,1), %r11",
     ));
@@ -723,42 +723,74 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IRRS, offset min simm8
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, rax, r8, 2), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, rax, r8, 2),
+            w_r11,
+            None,
+        ),
         "4E8B5C8080",
         "movq    -128(%rax,%r8,4), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, rdi, r8, 3), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, rdi, r8, 3),
+            w_r11,
+            None,
+        ),
         "4E8B5CC780",
         "movq    -128(%rdi,%r8,8), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, r8, r8, 0), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, r8, r8, 0),
+            w_r11,
+            None,
+        ),
         "4F8B5C0080",
         "movq    -128(%r8,%r8,1), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, r15, r8, 1), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, r15, r8, 1),
+            w_r11,
+            None,
+        ),
         "4F8B5C4780",
         "movq    -128(%r15,%r8,2), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, rax, r15, 1), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, rax, r15, 1),
+            w_r11,
+            None,
+        ),
         "4E8B5C7880",
         "movq    -128(%rax,%r15,2), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, rdi, r15, 0), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, rdi, r15, 0),
+            w_r11,
+            None,
+        ),
         "4E8B5C3F80",
         "movq    -128(%rdi,%r15,1), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, r8, r15, 3), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, r8, r15, 3),
+            w_r11,
+            None,
+        ),
         "4F8B5CF880",
         "movq    -128(%r8,%r15,8), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(-128i32 as u32, r15, r15, 2), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(-128i32 as u32, r15, r15, 2),
+            w_r11,
+            None,
+        ),
         "4F8B5CBF80",
         "movq    -128(%r15,%r15,4), %r11",
     ));
@@ -766,42 +798,74 @@ fn test_x64_emit() {
     // ========================================================
     // Addr_IRRS, offset large positive simm32
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, rax, rax, 0), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, rax, rax, 0),
+            w_r11,
+            None,
+        ),
         "4C8B9C00BE25664F",
         "movq    1332094398(%rax,%rax,1), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, rdi, rax, 1), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, rdi, rax, 1),
+            w_r11,
+            None,
+        ),
         "4C8B9C47BE25664F",
         "movq    1332094398(%rdi,%rax,2), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, r8, rax, 2), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, r8, rax, 2),
+            w_r11,
+            None,
+        ),
         "4D8B9C80BE25664F",
         "movq    1332094398(%r8,%rax,4), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, r15, rax, 3), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, r15, rax, 3),
+            w_r11,
+            None,
+        ),
         "4D8B9CC7BE25664F",
         "movq    1332094398(%r15,%rax,8), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, rax, rdi, 3), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, rax, rdi, 3),
+            w_r11,
+            None,
+        ),
         "4C8B9CF8BE25664F",
         "movq    1332094398(%rax,%rdi,8), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, rdi, rdi, 2), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, rdi, rdi, 2),
+            w_r11,
+            None,
+        ),
         "4C8B9CBFBE25664F",
         "movq    1332094398(%rdi,%rdi,4), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, r8, rdi, 1), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, r8, rdi, 1),
+            w_r11,
+            None,
+        ),
         "4D8B9C78BE25664F",
         "movq    1332094398(%r8,%rdi,2), %r11",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(0x4f6625be, r15, rdi, 0), w_r11),
+        Inst::mov64_m_r(
+            Amode::imm_reg_reg_shift(0x4f6625be, r15, rdi, 0),
+            w_r11,
+            None,
+        ),
         "4D8B9C3FBE25664F",
         "movq    1332094398(%r15,%rdi,1), %r11",
     ));
@@ -812,6 +876,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, rax, r8, 2),
             w_r11,
+            None,
         ),
         "4E8B9C8070E9B2D9",
         "movq    -642586256(%rax,%r8,4), %r11",
@@ -820,6 +885,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, rdi, r8, 3),
             w_r11,
+            None,
         ),
         "4E8B9CC770E9B2D9",
         "movq    -642586256(%rdi,%r8,8), %r11",
@@ -828,6 +894,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, r8, r8, 0),
             w_r11,
+            None,
         ),
         "4F8B9C0070E9B2D9",
         "movq    -642586256(%r8,%r8,1), %r11",
@@ -836,6 +903,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, r15, r8, 1),
             w_r11,
+            None,
         ),
         "4F8B9C4770E9B2D9",
         "movq    -642586256(%r15,%r8,2), %r11",
@@ -844,6 +912,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, rax, r15, 1),
             w_r11,
+            None,
         ),
         "4E8B9C7870E9B2D9",
         "movq    -642586256(%rax,%r15,2), %r11",
@@ -852,6 +921,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, rdi, r15, 0),
             w_r11,
+            None,
         ),
         "4E8B9C3F70E9B2D9",
         "movq    -642586256(%rdi,%r15,1), %r11",
@@ -860,6 +930,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, r8, r15, 3),
             w_r11,
+            None,
         ),
         "4F8B9CF870E9B2D9",
         "movq    -642586256(%r8,%r15,8), %r11",
@@ -868,6 +939,7 @@ fn test_x64_emit() {
         Inst::mov64_m_r(
             Amode::imm_reg_reg_shift(-0x264d1690i32 as u32, r15, r15, 2),
             w_r11,
+            None,
         ),
         "4F8B9CBF70E9B2D9",
         "movq    -642586256(%r15,%r15,4), %r11",
@@ -1244,12 +1316,12 @@ fn test_x64_emit() {
     // ========================================================
     // MovZX_RM_R
     insns.push((
-        Inst::movzx_rm_r(ExtMode::BL, RegMem::reg(rax), w_rsi),
+        Inst::movzx_rm_r(ExtMode::BL, RegMem::reg(rax), w_rsi, None),
         "0FB6F0",
         "movzbl  %al, %esi",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::BL, RegMem::reg(r15), w_rsi),
+        Inst::movzx_rm_r(ExtMode::BL, RegMem::reg(r15), w_rsi, None),
         "410FB6F7",
         "movzbl  %r15b, %esi",
     ));
@@ -1258,6 +1330,7 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "0FB671F9",
         "movzbl  -7(%rcx), %esi",
@@ -1267,6 +1340,7 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "410FB658F9",
         "movzbl  -7(%r8), %ebx",
@@ -1276,6 +1350,7 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "450FB64AF9",
         "movzbl  -7(%r10), %r9d",
@@ -1285,17 +1360,18 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "410FB653F9",
         "movzbl  -7(%r11), %edx",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::BQ, RegMem::reg(rax), w_rsi),
+        Inst::movzx_rm_r(ExtMode::BQ, RegMem::reg(rax), w_rsi, None),
         "480FB6F0",
         "movzbq  %al, %rsi",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::BQ, RegMem::reg(r10), w_rsi),
+        Inst::movzx_rm_r(ExtMode::BQ, RegMem::reg(r10), w_rsi, None),
         "490FB6F2",
         "movzbq  %r10b, %rsi",
     ));
@@ -1304,6 +1380,7 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "480FB671F9",
         "movzbq  -7(%rcx), %rsi",
@@ -1313,6 +1390,7 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "490FB658F9",
         "movzbq  -7(%r8), %rbx",
@@ -1322,6 +1400,7 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "4D0FB64AF9",
         "movzbq  -7(%r10), %r9",
@@ -1331,17 +1410,18 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "490FB653F9",
         "movzbq  -7(%r11), %rdx",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::WL, RegMem::reg(rcx), w_rsi),
+        Inst::movzx_rm_r(ExtMode::WL, RegMem::reg(rcx), w_rsi, None),
         "0FB7F1",
         "movzwl  %cx, %esi",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::WL, RegMem::reg(r10), w_rsi),
+        Inst::movzx_rm_r(ExtMode::WL, RegMem::reg(r10), w_rsi, None),
         "410FB7F2",
         "movzwl  %r10w, %esi",
     ));
@@ -1350,6 +1430,7 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "0FB771F9",
         "movzwl  -7(%rcx), %esi",
@@ -1359,6 +1440,7 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "410FB758F9",
         "movzwl  -7(%r8), %ebx",
@@ -1368,6 +1450,7 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "450FB74AF9",
         "movzwl  -7(%r10), %r9d",
@@ -1377,17 +1460,18 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "410FB753F9",
         "movzwl  -7(%r11), %edx",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::WQ, RegMem::reg(rcx), w_rsi),
+        Inst::movzx_rm_r(ExtMode::WQ, RegMem::reg(rcx), w_rsi, None),
         "480FB7F1",
         "movzwq  %cx, %rsi",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::WQ, RegMem::reg(r11), w_rsi),
+        Inst::movzx_rm_r(ExtMode::WQ, RegMem::reg(r11), w_rsi, None),
         "490FB7F3",
         "movzwq  %r11w, %rsi",
     ));
@@ -1396,6 +1480,7 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "480FB771F9",
         "movzwq  -7(%rcx), %rsi",
@@ -1405,6 +1490,7 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "490FB758F9",
         "movzwq  -7(%r8), %rbx",
@@ -1414,6 +1500,7 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "4D0FB74AF9",
         "movzwq  -7(%r10), %r9",
@@ -1423,12 +1510,13 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "490FB753F9",
         "movzwq  -7(%r11), %rdx",
     ));
     insns.push((
-        Inst::movzx_rm_r(ExtMode::LQ, RegMem::reg(rcx), w_rsi),
+        Inst::movzx_rm_r(ExtMode::LQ, RegMem::reg(rcx), w_rsi, None),
         "8BF1",
         "movl    %ecx, %esi",
     ));
@@ -1437,6 +1525,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "8B71F9",
         "movl    -7(%rcx), %esi",
@@ -1446,6 +1535,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "418B58F9",
         "movl    -7(%r8), %ebx",
@@ -1455,6 +1545,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "458B4AF9",
         "movl    -7(%r10), %r9d",
@@ -1464,6 +1555,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "418B53F9",
         "movl    -7(%r11), %edx",
@@ -1472,42 +1564,42 @@ fn test_x64_emit() {
     // ========================================================
     // Mov64_M_R
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, rbx, 0), w_rcx),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, rbx, 0), w_rcx, None),
         "488B8C18B3000000",
         "movq    179(%rax,%rbx,1), %rcx",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, rbx, 0), w_r8),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, rbx, 0), w_r8, None),
         "4C8B8418B3000000",
         "movq    179(%rax,%rbx,1), %r8",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, r9, 0), w_rcx),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, r9, 0), w_rcx, None),
         "4A8B8C08B3000000",
         "movq    179(%rax,%r9,1), %rcx",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, r9, 0), w_r8),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, rax, r9, 0), w_r8, None),
         "4E8B8408B3000000",
         "movq    179(%rax,%r9,1), %r8",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, rbx, 0), w_rcx),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, rbx, 0), w_rcx, None),
         "498B8C1AB3000000",
         "movq    179(%r10,%rbx,1), %rcx",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, rbx, 0), w_r8),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, rbx, 0), w_r8, None),
         "4D8B841AB3000000",
         "movq    179(%r10,%rbx,1), %r8",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, r9, 0), w_rcx),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, r9, 0), w_rcx, None),
         "4B8B8C0AB3000000",
         "movq    179(%r10,%r9,1), %rcx",
     ));
     insns.push((
-        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, r9, 0), w_r8),
+        Inst::mov64_m_r(Amode::imm_reg_reg_shift(179, r10, r9, 0), w_r8, None),
         "4F8B840AB3000000",
         "movq    179(%r10,%r9,1), %r8",
     ));
@@ -1533,12 +1625,12 @@ fn test_x64_emit() {
     // ========================================================
     // MovSX_RM_R
     insns.push((
-        Inst::movsx_rm_r(ExtMode::BL, RegMem::reg(rcx), w_rsi),
+        Inst::movsx_rm_r(ExtMode::BL, RegMem::reg(rcx), w_rsi, None),
         "0FBEF1",
         "movsbl  %cl, %esi",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::BL, RegMem::reg(r14), w_rsi),
+        Inst::movsx_rm_r(ExtMode::BL, RegMem::reg(r14), w_rsi, None),
         "410FBEF6",
         "movsbl  %r14b, %esi",
     ));
@@ -1547,6 +1639,7 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "0FBE71F9",
         "movsbl  -7(%rcx), %esi",
@@ -1556,6 +1649,7 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "410FBE58F9",
         "movsbl  -7(%r8), %ebx",
@@ -1565,6 +1659,7 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "450FBE4AF9",
         "movsbl  -7(%r10), %r9d",
@@ -1574,17 +1669,18 @@ fn test_x64_emit() {
             ExtMode::BL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "410FBE53F9",
         "movsbl  -7(%r11), %edx",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::BQ, RegMem::reg(rcx), w_rsi),
+        Inst::movsx_rm_r(ExtMode::BQ, RegMem::reg(rcx), w_rsi, None),
         "480FBEF1",
         "movsbq  %cl, %rsi",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::BQ, RegMem::reg(r15), w_rsi),
+        Inst::movsx_rm_r(ExtMode::BQ, RegMem::reg(r15), w_rsi, None),
         "490FBEF7",
         "movsbq  %r15b, %rsi",
     ));
@@ -1593,6 +1689,7 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "480FBE71F9",
         "movsbq  -7(%rcx), %rsi",
@@ -1602,6 +1699,7 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "490FBE58F9",
         "movsbq  -7(%r8), %rbx",
@@ -1611,6 +1709,7 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "4D0FBE4AF9",
         "movsbq  -7(%r10), %r9",
@@ -1620,17 +1719,18 @@ fn test_x64_emit() {
             ExtMode::BQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "490FBE53F9",
         "movsbq  -7(%r11), %rdx",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::WL, RegMem::reg(rcx), w_rsi),
+        Inst::movsx_rm_r(ExtMode::WL, RegMem::reg(rcx), w_rsi, None),
         "0FBFF1",
         "movswl  %cx, %esi",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::WL, RegMem::reg(r14), w_rsi),
+        Inst::movsx_rm_r(ExtMode::WL, RegMem::reg(r14), w_rsi, None),
         "410FBFF6",
         "movswl  %r14w, %esi",
     ));
@@ -1639,6 +1739,7 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "0FBF71F9",
         "movswl  -7(%rcx), %esi",
@@ -1648,6 +1749,7 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "410FBF58F9",
         "movswl  -7(%r8), %ebx",
@@ -1657,6 +1759,7 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "450FBF4AF9",
         "movswl  -7(%r10), %r9d",
@@ -1666,17 +1769,18 @@ fn test_x64_emit() {
             ExtMode::WL,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "410FBF53F9",
         "movswl  -7(%r11), %edx",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::WQ, RegMem::reg(rcx), w_rsi),
+        Inst::movsx_rm_r(ExtMode::WQ, RegMem::reg(rcx), w_rsi, None),
         "480FBFF1",
         "movswq  %cx, %rsi",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::WQ, RegMem::reg(r13), w_rsi),
+        Inst::movsx_rm_r(ExtMode::WQ, RegMem::reg(r13), w_rsi, None),
         "490FBFF5",
         "movswq  %r13w, %rsi",
     ));
@@ -1685,6 +1789,7 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "480FBF71F9",
         "movswq  -7(%rcx), %rsi",
@@ -1694,6 +1799,7 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "490FBF58F9",
         "movswq  -7(%r8), %rbx",
@@ -1703,6 +1809,7 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "4D0FBF4AF9",
         "movswq  -7(%r10), %r9",
@@ -1712,17 +1819,18 @@ fn test_x64_emit() {
             ExtMode::WQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "490FBF53F9",
         "movswq  -7(%r11), %rdx",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::LQ, RegMem::reg(rcx), w_rsi),
+        Inst::movsx_rm_r(ExtMode::LQ, RegMem::reg(rcx), w_rsi, None),
         "4863F1",
         "movslq  %ecx, %rsi",
     ));
     insns.push((
-        Inst::movsx_rm_r(ExtMode::LQ, RegMem::reg(r15), w_rsi),
+        Inst::movsx_rm_r(ExtMode::LQ, RegMem::reg(r15), w_rsi, None),
         "4963F7",
         "movslq  %r15d, %rsi",
     ));
@@ -1731,6 +1839,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, rcx)),
             w_rsi,
+            None,
         ),
         "486371F9",
         "movslq  -7(%rcx), %rsi",
@@ -1740,6 +1849,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r8)),
             w_rbx,
+            None,
         ),
         "496358F9",
         "movslq  -7(%r8), %rbx",
@@ -1749,6 +1859,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r10)),
             w_r9,
+            None,
         ),
         "4D634AF9",
         "movslq  -7(%r10), %r9",
@@ -1758,6 +1869,7 @@ fn test_x64_emit() {
             ExtMode::LQ,
             RegMem::mem(Amode::imm_reg(-7i32 as u32, r11)),
             w_rdx,
+            None,
         ),
         "496353F9",
         "movslq  -7(%r11), %rdx",
@@ -1766,329 +1878,454 @@ fn test_x64_emit() {
     // ========================================================
     // Mov_R_M.  Byte stores are tricky.  Check everything carefully.
     insns.push((
-        Inst::mov_r_m(8, rax, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(8, rax, Amode::imm_reg(99, rdi), None),
         "48894763",
         "movq    %rax, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(8, rbx, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(8, rbx, Amode::imm_reg(99, r8), None),
         "49895863",
         "movq    %rbx, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(8, rcx, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(8, rcx, Amode::imm_reg(99, rsi), None),
         "48894E63",
         "movq    %rcx, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(8, rdx, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(8, rdx, Amode::imm_reg(99, r9), None),
         "49895163",
         "movq    %rdx, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(8, rsi, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(8, rsi, Amode::imm_reg(99, rax), None),
         "48897063",
         "movq    %rsi, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(8, rdi, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(8, rdi, Amode::imm_reg(99, r15), None),
         "49897F63",
         "movq    %rdi, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(8, rsp, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(8, rsp, Amode::imm_reg(99, rcx), None),
         "48896163",
         "movq    %rsp, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(8, rbp, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(8, rbp, Amode::imm_reg(99, r14), None),
         "49896E63",
         "movq    %rbp, 99(%r14)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r8, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(8, r8, Amode::imm_reg(99, rdi), None),
         "4C894763",
         "movq    %r8, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r9, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(8, r9, Amode::imm_reg(99, r8), None),
         "4D894863",
         "movq    %r9, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r10, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(8, r10, Amode::imm_reg(99, rsi), None),
         "4C895663",
         "movq    %r10, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r11, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(8, r11, Amode::imm_reg(99, r9), None),
         "4D895963",
         "movq    %r11, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r12, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(8, r12, Amode::imm_reg(99, rax), None),
         "4C896063",
         "movq    %r12, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r13, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(8, r13, Amode::imm_reg(99, r15), None),
         "4D896F63",
         "movq    %r13, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r14, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(8, r14, Amode::imm_reg(99, rcx), None),
         "4C897163",
         "movq    %r14, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(8, r15, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(8, r15, Amode::imm_reg(99, r14), None),
         "4D897E63",
         "movq    %r15, 99(%r14)",
     ));
     //
     insns.push((
-        Inst::mov_r_m(4, rax, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(4, rax, Amode::imm_reg(99, rdi), None),
         "894763",
         "movl    %eax, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(4, rbx, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(4, rbx, Amode::imm_reg(99, r8), None),
         "41895863",
         "movl    %ebx, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(4, rcx, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(4, rcx, Amode::imm_reg(99, rsi), None),
         "894E63",
         "movl    %ecx, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(4, rdx, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(4, rdx, Amode::imm_reg(99, r9), None),
         "41895163",
         "movl    %edx, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(4, rsi, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(4, rsi, Amode::imm_reg(99, rax), None),
         "897063",
         "movl    %esi, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(4, rdi, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(4, rdi, Amode::imm_reg(99, r15), None),
         "41897F63",
         "movl    %edi, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(4, rsp, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(4, rsp, Amode::imm_reg(99, rcx), None),
         "896163",
         "movl    %esp, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(4, rbp, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(4, rbp, Amode::imm_reg(99, r14), None),
         "41896E63",
         "movl    %ebp, 99(%r14)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r8, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(4, r8, Amode::imm_reg(99, rdi), None),
         "44894763",
         "movl    %r8d, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r9, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(4, r9, Amode::imm_reg(99, r8), None),
         "45894863",
         "movl    %r9d, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r10, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(4, r10, Amode::imm_reg(99, rsi), None),
         "44895663",
         "movl    %r10d, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r11, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(4, r11, Amode::imm_reg(99, r9), None),
         "45895963",
         "movl    %r11d, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r12, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(4, r12, Amode::imm_reg(99, rax), None),
         "44896063",
         "movl    %r12d, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r13, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(4, r13, Amode::imm_reg(99, r15), None),
         "45896F63",
         "movl    %r13d, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r14, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(4, r14, Amode::imm_reg(99, rcx), None),
         "44897163",
         "movl    %r14d, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(4, r15, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(4, r15, Amode::imm_reg(99, r14), None),
         "45897E63",
         "movl    %r15d, 99(%r14)",
     ));
     //
     insns.push((
-        Inst::mov_r_m(2, rax, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(2, rax, Amode::imm_reg(99, rdi), None),
         "66894763",
         "movw    %ax, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(2, rbx, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(2, rbx, Amode::imm_reg(99, r8), None),
         "6641895863",
         "movw    %bx, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(2, rcx, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(2, rcx, Amode::imm_reg(99, rsi), None),
         "66894E63",
         "movw    %cx, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(2, rdx, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(2, rdx, Amode::imm_reg(99, r9), None),
         "6641895163",
         "movw    %dx, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(2, rsi, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(2, rsi, Amode::imm_reg(99, rax), None),
         "66897063",
         "movw    %si, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(2, rdi, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(2, rdi, Amode::imm_reg(99, r15), None),
         "6641897F63",
         "movw    %di, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(2, rsp, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(2, rsp, Amode::imm_reg(99, rcx), None),
         "66896163",
         "movw    %sp, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(2, rbp, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(2, rbp, Amode::imm_reg(99, r14), None),
         "6641896E63",
         "movw    %bp, 99(%r14)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r8, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(2, r8, Amode::imm_reg(99, rdi), None),
         "6644894763",
         "movw    %r8w, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r9, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(2, r9, Amode::imm_reg(99, r8), None),
         "6645894863",
         "movw    %r9w, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r10, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(2, r10, Amode::imm_reg(99, rsi), None),
         "6644895663",
         "movw    %r10w, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r11, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(2, r11, Amode::imm_reg(99, r9), None),
         "6645895963",
         "movw    %r11w, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r12, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(2, r12, Amode::imm_reg(99, rax), None),
         "6644896063",
         "movw    %r12w, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r13, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(2, r13, Amode::imm_reg(99, r15), None),
         "6645896F63",
         "movw    %r13w, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r14, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(2, r14, Amode::imm_reg(99, rcx), None),
         "6644897163",
         "movw    %r14w, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(2, r15, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(2, r15, Amode::imm_reg(99, r14), None),
         "6645897E63",
         "movw    %r15w, 99(%r14)",
     ));
     //
     insns.push((
-        Inst::mov_r_m(1, rax, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(1, rax, Amode::imm_reg(99, rdi), None),
         "884763",
         "movb    %al, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(1, rbx, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(1, rbx, Amode::imm_reg(99, r8), None),
         "41885863",
         "movb    %bl, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(1, rcx, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(1, rcx, Amode::imm_reg(99, rsi), None),
         "884E63",
         "movb    %cl, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(1, rdx, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(1, rdx, Amode::imm_reg(99, r9), None),
         "41885163",
         "movb    %dl, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(1, rsi, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(1, rsi, Amode::imm_reg(99, rax), None),
         "40887063",
         "movb    %sil, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(1, rdi, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(1, rdi, Amode::imm_reg(99, r15), None),
         "41887F63",
         "movb    %dil, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(1, rsp, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(1, rsp, Amode::imm_reg(99, rcx), None),
         "40886163",
         "movb    %spl, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(1, rbp, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(1, rbp, Amode::imm_reg(99, r14), None),
         "41886E63",
         "movb    %bpl, 99(%r14)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r8, Amode::imm_reg(99, rdi)),
+        Inst::mov_r_m(1, r8, Amode::imm_reg(99, rdi), None),
         "44884763",
         "movb    %r8b, 99(%rdi)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r9, Amode::imm_reg(99, r8)),
+        Inst::mov_r_m(1, r9, Amode::imm_reg(99, r8), None),
         "45884863",
         "movb    %r9b, 99(%r8)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r10, Amode::imm_reg(99, rsi)),
+        Inst::mov_r_m(1, r10, Amode::imm_reg(99, rsi), None),
         "44885663",
         "movb    %r10b, 99(%rsi)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r11, Amode::imm_reg(99, r9)),
+        Inst::mov_r_m(1, r11, Amode::imm_reg(99, r9), None),
         "45885963",
         "movb    %r11b, 99(%r9)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r12, Amode::imm_reg(99, rax)),
+        Inst::mov_r_m(1, r12, Amode::imm_reg(99, rax), None),
         "44886063",
         "movb    %r12b, 99(%rax)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r13, Amode::imm_reg(99, r15)),
+        Inst::mov_r_m(1, r13, Amode::imm_reg(99, r15), None),
         "45886F63",
         "movb    %r13b, 99(%r15)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r14, Amode::imm_reg(99, rcx)),
+        Inst::mov_r_m(1, r14, Amode::imm_reg(99, rcx), None),
         "44887163",
         "movb    %r14b, 99(%rcx)",
     ));
     insns.push((
-        Inst::mov_r_m(1, r15, Amode::imm_reg(99, r14)),
+        Inst::mov_r_m(1, r15, Amode::imm_reg(99, r14), None),
         "45887E63",
         "movb    %r15b, 99(%r14)",
     ));
 
+    // ========================================================
+    // UnaryRmR
+
+    insns.push((
+        Inst::unary_rm_r(4, UnaryRmROpcode::Bsr, RegMem::reg(rsi), w_rdi),
+        "0FBDFE",
+        "bsrl    %esi, %edi",
+    ));
+    insns.push((
+        Inst::unary_rm_r(8, UnaryRmROpcode::Bsr, RegMem::reg(r15), w_rax),
+        "490FBDC7",
+        "bsrq    %r15, %rax",
+    ));
+    insns.push((
+        Inst::unary_rm_r(
+            4,
+            UnaryRmROpcode::Bsr,
+            RegMem::mem(Amode::imm_reg(123, rax)),
+            w_rcx,
+        ),
+        "0FBD487B",
+        "bsrl    123(%rax), %ecx",
+    ));
+    insns.push((
+        Inst::unary_rm_r(4, UnaryRmROpcode::Bsf, RegMem::reg(r8), w_r9),
+        "450FBCC8",
+        "bsfl    %r8d, %r9d",
+    ));
+    insns.push((
+        Inst::unary_rm_r(8, UnaryRmROpcode::Bsf, RegMem::reg(rdx), w_rcx),
+        "480FBCCA",
+        "bsfq    %rdx, %rcx",
+    ));
+    insns.push((
+        Inst::unary_rm_r(4, UnaryRmROpcode::Popcnt, RegMem::reg(rax), w_rbx),
+        "F30FB8D8",
+        "popcntl %eax, %ebx",
+    ));
+    insns.push((
+        Inst::unary_rm_r(8, UnaryRmROpcode::Popcnt, RegMem::reg(r12), w_r13),
+        "F34D0FB8EC",
+        "popcntq %r12, %r13",
+    ));
+
+    // ========================================================
+    // Not
+
+    insns.push((Inst::not(4, w_rsi), "F7D6", "notl    %esi"));
+    insns.push((Inst::not(8, w_r10), "49F7D2", "notq    %r10"));
+    insns.push((Inst::not(2, w_rdi), "66F7D7", "notw    %di"));
+    insns.push((Inst::not(1, w_rsi), "40F6D6", "notb    %sil"));
+
+    // ========================================================
+    // Neg
+
+    insns.push((Inst::neg(4, w_rsi), "F7DE", "negl    %esi"));
+    insns.push((Inst::neg(8, w_r15), "49F7DF", "negq    %r15"));
+    insns.push((Inst::neg(1, w_rcx), "F6D9", "negb    %cl"));
+    insns.push((Inst::neg(1, w_r12), "41F6DC", "negb    %r12b"));
+
+    // ========================================================
+    // Div
+
+    insns.push((
+        Inst::div(4, true, RegMem::reg(rsi), SourceLoc::default()),
+        "F7FE",
+        "idiv    %esi",
+    ));
+    insns.push((
+        Inst::div(8, true, RegMem::reg(r15), SourceLoc::default()),
+        "49F7FF",
+        "idiv    %r15",
+    ));
+    insns.push((
+        Inst::div(2, true, RegMem::reg(rcx), SourceLoc::default()),
+        "66F7F9",
+        "idiv    %cx",
+    ));
+    insns.push((
+        Inst::div(4, false, RegMem::reg(r14), SourceLoc::default()),
+        "41F7F6",
+        "div     %r14d",
+    ));
+    insns.push((
+        Inst::div(8, false, RegMem::reg(rdi), SourceLoc::default()),
+        "48F7F7",
+        "div     %rdi",
+    ));
+    insns.push((
+        Inst::div(1, false, RegMem::reg(rsi), SourceLoc::default()),
+        "40F6F6",
+        "div     %sil",
+    ));
+
+    // ========================================================
+    // MulHi
+
+    insns.push((
+        Inst::mul_hi(4, true, RegMem::reg(rsi)),
+        "F7EE",
+        "imul    %esi",
+    ));
+    insns.push((
+        Inst::mul_hi(8, true, RegMem::reg(r15)),
+        "49F7EF",
+        "imul    %r15",
+    ));
+    insns.push((
+        Inst::mul_hi(4, false, RegMem::reg(r14)),
+        "41F7E6",
+        "mul     %r14d",
+    ));
+    insns.push((
+        Inst::mul_hi(8, false, RegMem::reg(rdi)),
+        "48F7E7",
+        "mul     %rdi",
+    ));
+
+    // ========================================================
+    // SignExtendRaxRdx
+
+    insns.push((Inst::sign_extend_rax_to_rdx(2), "6699", "cwd"));
+    insns.push((Inst::sign_extend_rax_to_rdx(4), "99", "cdq"));
+    insns.push((Inst::sign_extend_rax_to_rdx(8), "4899", "cqo"));
+
     // ========================================================
     // Shift_R
     insns.push((
@@ -2196,6 +2433,16 @@ fn test_x64_emit() {
         "49C1FD3F",
         "sarq    $63, %r13",
     ));
+    insns.push((
+        Inst::shift_r(false, ShiftKind::RotateLeft, None, w_rdi),
+        "D3C7",
+        "roll    %cl, %edi",
+    ));
+    insns.push((
+        Inst::shift_r(true, ShiftKind::RotateRight, Some(5), w_r15),
+        "49C1CF05",
+        "rorq    $5, %r15",
+    ));
 
     // ========================================================
     // CmpRMIR
@@ -2474,6 +2721,45 @@ fn test_x64_emit() {
         "cmpb    %r13b, %r14b",
     ));
 
+    // ========================================================
+    // TestRMIR
+
+    insns.push((
+        Inst::test_rmi_r(4, RegMemImm::reg(rcx), rax),
+        "85C8",
+        "testl   %ecx, %eax",
+    ));
+    insns.push((
+        Inst::test_rmi_r(8, RegMemImm::reg(r10), rax),
+        "4C85D0",
+        "testq   %r10, %rax",
+    ));
+    insns.push((
+        Inst::test_rmi_r(1, RegMemImm::reg(rsi), rdi),
+        "4084F7",
+        "testb   %sil, %dil",
+    ));
+    insns.push((
+        Inst::test_rmi_r(8, RegMemImm::mem(Amode::imm_reg(99, rdi)), rdx),
+        "48855763",
+        "testq   99(%rdi), %rdx",
+    ));
+    insns.push((
+        Inst::test_rmi_r(4, RegMemImm::imm(127), rdx),
+        "F7C27F000000",
+        "testl   $127, %edx",
+    ));
+    insns.push((
+        Inst::test_rmi_r(8, RegMemImm::imm(-129i32 as u32), r13),
+        "49F7C57FFFFFFF",
+        "testq   $-129, %r13",
+    ));
+    insns.push((
+        Inst::test_rmi_r(1, RegMemImm::imm(7), rbx),
+        "F6C307",
+        "testb   $7, %bl",
+    ));
+
     // ========================================================
     // SetCC
     insns.push((Inst::setcc(CC::O, w_rsi), "400F90C6", "seto    %sil"));
@@ -2481,6 +2767,25 @@ fn test_x64_emit() {
     insns.push((Inst::setcc(CC::Z, w_r14), "410F94C6", "setz    %r14b"));
     insns.push((Inst::setcc(CC::LE, w_r14), "410F9EC6", "setle   %r14b"));
 
+    // ========================================================
+    // Cmove
+
+    insns.push((
+        Inst::cmove(2, CC::Z, RegMem::reg(rdi), w_rsi),
+        "660F44F7",
+        "cmovzw  %di, %si",
+    ));
+    insns.push((
+        Inst::cmove(4, CC::LE, RegMem::reg(r12), w_rsi),
+        "410F4EF4",
+        "cmovlel %r12d, %esi",
+    ));
+    insns.push((
+        Inst::cmove(8, CC::NB, RegMem::reg(r15), w_rbx),
+        "490F43DF",
+        "cmovnbq %r15, %rbx",
+    ));
+
     // ========================================================
     // Push64
     insns.push((Inst::push64(RegMemImm::reg(rdi)), "57", "pushq   %rdi"));
@@ -2572,6 +2877,36 @@ fn test_x64_emit() {
         "call    *321(%r10,%rdx,4)",
     ));
 
+    // ========================================================
+    // TailCallKnown
+    insns.push((
+        Inst::tail_call_known(
+            ExternalName::User {
+                namespace: 0,
+                index: 0,
+            },
+            Vec::new(),
+            SourceLoc::default(),
+            Opcode::ReturnCall,
+        ),
+        "E900000000",
+        "jmp     User { namespace: 0, index: 0 }",
+    ));
+
+    // ========================================================
+    // TailCallUnknown
+    fn tail_call_unknown(reg: Reg) -> Inst {
+        Inst::tail_call_unknown(
+            reg,
+            Vec::new(),
+            SourceLoc::default(),
+            Opcode::ReturnCallIndirect,
+        )
+    }
+
+    insns.push((tail_call_unknown(rax), "FFE0", "jmp     *%rax"));
+    insns.push((tail_call_unknown(r11), "41FFE3", "jmp     *%r11"));
+
     // ========================================================
     // Ret
     insns.push((Inst::ret(), "C3", "ret"));
@@ -2674,12 +3009,12 @@ fn test_x64_emit() {
         "andnps  %xmm4, %xmm11",
     ));
     insns.push((
-        Inst::xmm_mov_rm_r(SseOpcode::Movaps, RegMem::reg(xmm5), w_xmm14),
+        Inst::xmm_mov_rm_r(SseOpcode::Movaps, RegMem::reg(xmm5), w_xmm14, None),
         "440F28F5",
         "movaps  %xmm5, %xmm14",
     ));
     insns.push((
-        Inst::xmm_mov_rm_r(SseOpcode::Movd, RegMem::reg(rax), w_xmm15),
+        Inst::xmm_mov_rm_r(SseOpcode::Movd, RegMem::reg(rax), w_xmm15, None),
         "66440F6EF8",
         "movd    %eax, %xmm15",
     ));
@@ -2694,16 +3029,154 @@ fn test_x64_emit() {
         "orps    %xmm5, %xmm4",
     ));
     insns.push((
-        Inst::xmm_mov_rm_r(SseOpcode::Movss, RegMem::reg(xmm13), w_xmm2),
+        Inst::xmm_mov_rm_r(SseOpcode::Movss, RegMem::reg(xmm13), w_xmm2, None),
         "F3410F10D5",
         "movss   %xmm13, %xmm2",
     ));
     insns.push((
-        Inst::xmm_mov_rm_r(SseOpcode::Movsd, RegMem::reg(xmm14), w_xmm3),
+        Inst::xmm_mov_rm_r(SseOpcode::Movsd, RegMem::reg(xmm14), w_xmm3, None),
         "F2410F10DE",
         "movsd   %xmm14, %xmm3",
     ));
 
+    insns.push((
+        Inst::xmm_rm_r(SseOpcode::Addsd, RegMem::reg(xmm15), w_xmm2),
+        "F2410F58D7",
+        "addsd   %xmm15, %xmm2",
+    ));
+    insns.push((
+        Inst::xmm_rm_r(SseOpcode::Minss, RegMem::reg(xmm4), w_xmm3),
+        "F30F5DDC",
+        "minss   %xmm4, %xmm3",
+    ));
+    insns.push((
+        Inst::xmm_rm_r(SseOpcode::Maxsd, RegMem::reg(xmm8), w_xmm1),
+        "F2410F5FC8",
+        "maxsd   %xmm8, %xmm1",
+    ));
+    insns.push((
+        Inst::xmm_rm_r(SseOpcode::Paddd, RegMem::reg(xmm1), w_xmm7),
+        "660FFEF9",
+        "paddd   %xmm1, %xmm7",
+    ));
+    insns.push((
+        Inst::xmm_rm_r(SseOpcode::Pxor, RegMem::reg(xmm3), w_xmm3),
+        "660FEFDB",
+        "pxor    %xmm3, %xmm3",
+    ));
+    insns.push((
+        Inst::xmm_rm_r(SseOpcode::Pmulld, RegMem::reg(xmm2), w_xmm10),
+        "66440F3840D2",
+        "pmulld  %xmm2, %xmm10",
+    ));
+
+    // ========================================================
+    // XMM_RM_R_IMM
+
+    insns.push((
+        Inst::xmm_rm_r_imm(SseOpcode::Roundss, RegMem::reg(xmm1), w_xmm2, 3),
+        "660F3A0AD103",
+        "roundss $3, %xmm1, %xmm2",
+    ));
+    insns.push((
+        Inst::xmm_rm_r_imm(SseOpcode::Pshufd, RegMem::reg(xmm10), w_xmm0, 0),
+        "66410F70C200",
+        "pshufd  $0, %xmm10, %xmm0",
+    ));
+
+    // ========================================================
+    // XMM_Mov_R_M
+
+    insns.push((
+        Inst::xmm_mov_r_m(SseOpcode::Movaps, xmm5, Amode::imm_reg(0, rax), None),
+        "0F2928",
+        "movaps  %xmm5, 0(%rax)",
+    ));
+    insns.push((
+        Inst::xmm_mov_r_m(SseOpcode::Movss, xmm15, Amode::imm_reg(128, r12), None),
+        "F3450F11BC2480000000",
+        "movss   %xmm15, 128(%r12)",
+    ));
+
+    // ========================================================
+    // XmmToGpr
+
+    insns.push((
+        Inst::xmm_to_gpr(SseOpcode::Movd, xmm0, w_rsi, 4),
+        "660F7EC6",
+        "movd    %xmm0, %esi",
+    ));
+    insns.push((
+        Inst::xmm_to_gpr(SseOpcode::Movq, xmm2, w_rdi, 8),
+        "66480F7ED7",
+        "movq    %xmm2, %rdi",
+    ));
+    insns.push((
+        Inst::xmm_to_gpr(SseOpcode::Cvttss2si, xmm0, w_rsi, 4),
+        "F30F2CF0",
+        "cvttss2si %xmm0, %esi",
+    ));
+    insns.push((
+        Inst::xmm_to_gpr(SseOpcode::Cvttsd2si, xmm0, w_rsi, 8),
+        "F2480F2CF0",
+        "cvttsd2si %xmm0, %rsi",
+    ));
+
+    // ========================================================
+    // GprToXmm
+
+    insns.push((
+        Inst::gpr_to_xmm(SseOpcode::Movd, RegMem::reg(rax), 4, w_xmm15),
+        "66440F6EF8",
+        "movd    %eax, %xmm15",
+    ));
+    insns.push((
+        Inst::gpr_to_xmm(SseOpcode::Movq, RegMem::reg(r8), 8, w_xmm1),
+        "66490F6EC8",
+        "movq    %r8, %xmm1",
+    ));
+    insns.push((
+        Inst::gpr_to_xmm(SseOpcode::Cvtsi2ss, RegMem::reg(rdi), 4, w_xmm15),
+        "F3440F2AFF",
+        "cvtsi2ss %edi, %xmm15",
+    ));
+    insns.push((
+        Inst::gpr_to_xmm(SseOpcode::Cvtsi2sd, RegMem::reg(rsi), 8, w_xmm1),
+        "F2480F2ACE",
+        "cvtsi2sd %rsi, %xmm1",
+    ));
+
+    // ========================================================
+    // XMM_Cmp_RM_R
+
+    insns.push((
+        Inst::xmm_cmp_rm_r(SseOpcode::Ucomiss, RegMem::reg(xmm1), xmm2),
+        "0F2ED1",
+        "ucomiss %xmm1, %xmm2",
+    ));
+    insns.push((
+        Inst::xmm_cmp_rm_r(SseOpcode::Ucomisd, RegMem::reg(xmm0), xmm9),
+        "66440F2EC8",
+        "ucomisd %xmm0, %xmm9",
+    ));
+
+    // ========================================================
+    // LoadExtName
+
+    insns.push((
+        Inst::LoadExtName {
+            dst: w_r11,
+            name: Box::new(ExternalName::User {
+                namespace: 0,
+                index: 0,
+            }),
+            offset: 0,
+            srcloc: SourceLoc::default(),
+        },
+        "49BB0000000000000000",
+        "movabsq u0:0+0, %r11",
+    ));
+
     // ========================================================
     // Misc instructions.
 
@@ -2712,6 +3185,16 @@ fn test_x64_emit() {
     let trap_info = (SourceLoc::default(), TrapCode::UnreachableCodeReached);
     insns.push((Inst::Ud2 { trap_info }, "0F0B", "ud2 unreachable"));
 
+    insns.push((
+        Inst::stack_probe_loop(w_r11, 5, 4096),
+        "41BB050000004881EC00100000488924244183EB010F85EBFFFFFF",
+        "movl    $5, %r11d ; 1: subq    $4096, %rsp ; movq    %rsp, 0(%rsp) ; subl    $1, %r11d ; jnz 1b ;",
+    ));
+
+    insns.push((Inst::nop(1), "90", "nop     len=1"));
+    insns.push((Inst::nop(3), "0F1F00", "nop     len=3"));
+    insns.push((Inst::nop(11), "660F1F8400000000006690", "nop     len=11"));
+
     // ========================================================
     // Actually run the tests!
     let flags = settings::Flags::new(settings::builder());
@@ -10,11 +10,15 @@ use std::convert::TryFrom;
 use crate::ir::types;
 use crate::ir::types::*;
 use crate::ir::Inst as IRInst;
-use crate::ir::{condcodes::IntCC, InstructionData, Opcode, TrapCode, Type};
+use crate::ir::{
+    condcodes::{CondCode, FloatCC, IntCC},
+    InstructionData, Opcode, TrapCode, Type,
+};
 
 use crate::machinst::lower::*;
 use crate::machinst::*;
-use crate::result::CodegenResult;
+use crate::result::{CodegenError, CodegenResult};
+use crate::settings::Flags;
 
 use crate::isa::x64::abi::*;
 use crate::isa::x64::inst::args::*;
@@ -29,7 +33,7 @@ type Ctx<'a> = &'a mut dyn LowerCtx<I = Inst>;
 
 fn is_int_ty(ty: Type) -> bool {
     match ty {
-        types::I8 | types::I16 | types::I32 | types::I64 => true,
+        types::I8 | types::I16 | types::I32 | types::I64 | types::R64 => true,
         _ => false,
     }
 }
@@ -91,6 +95,16 @@ fn inst_condcode(data: &InstructionData) -> IntCC {
     }
 }
 
+fn inst_fp_condcode(data: &InstructionData) -> FloatCC {
+    match data {
+        &InstructionData::BranchFloat { cond, .. }
+        | &InstructionData::FloatCompare { cond, .. }
+        | &InstructionData::FloatCond { cond, .. }
+        | &InstructionData::FloatCondTrap { cond, .. } => cond,
+        _ => panic!("inst_fp_condcode(x64): unhandled: {:?}", data),
+    }
+}
+
 fn ldst_offset(data: &InstructionData) -> Option<i32> {
     match data {
         &InstructionData::Load { offset, .. }
@@ -117,6 +131,19 @@ struct InsnOutput {
     output: usize,
 }
 
+/// Returns the instruction producing the given input, if it's an instance of `op` that can be
+/// merged into the instruction being lowered.
+fn matches_input(ctx: Ctx, input: InsnInput, op: Opcode) -> Option<IRInst> {
+    let inputs = ctx.get_input(input.insn, input.input);
+    inputs.inst.and_then(|(src_inst, _)| {
+        let data = ctx.data(src_inst);
+        if data.opcode() == op {
+            return Some(src_inst);
+        }
+        None
+    })
+}
+
 fn input_to_reg<'a>(ctx: Ctx<'a>, spec: InsnInput) -> Reg {
     let inputs = ctx.get_input(spec.insn, spec.input);
     ctx.use_input_reg(inputs);
@@ -126,12 +153,12 @@ fn input_to_reg<'a>(ctx: Ctx<'a>, spec: InsnInput) -> Reg {
 /// Try to use an immediate for constant inputs, and a register otherwise.
 /// TODO: handle memory as well!
 fn input_to_reg_mem_imm(ctx: Ctx, spec: InsnInput) -> RegMemImm {
+    let input_ty = ctx.input_ty(spec.insn, spec.input);
     let imm = ctx.get_input(spec.insn, spec.input).constant.and_then(|x| {
-        let as_u32 = x as u32;
-        let extended = as_u32 as u64;
-        // If the truncation and sign-extension don't change the value, use it.
-        if extended == x {
-            Some(as_u32)
+        // Immediates are sign-extended to the operation size: only the low 32 bits of a narrow
+        // value are used, and a 64-bit value must be the sign-extension of its low 32 bits.
+        if input_ty.bits() <= 32 || (x as i64) == ((x as i32) as i64) {
+            Some(x as u32)
         } else {
             None
         }
@@ -146,11 +173,215 @@ fn output_to_reg<'a>(ctx: Ctx<'a>, spec: InsnOutput) -> Writable<Reg> {
     ctx.get_output(spec.insn, spec.output)
 }
 
+/// Zero- or sign-extends an integer input narrower than 32 bits into a 32-bit register; wider
+/// inputs are returned as is.
+fn input_to_reg_ext32(ctx: Ctx, spec: InsnInput, signed: bool) -> Reg {
+    let ty = ctx.input_ty(spec.insn, spec.input);
+    let src = input_to_reg(ctx, spec);
+    let ext_mode = match ty.bits() {
+        1 | 8 => ExtMode::BL,
+        16 => ExtMode::WL,
+        _ => return src,
+    };
+    let dst = ctx.alloc_tmp(RegClass::I64, I32);
+    if signed {
+        ctx.emit(Inst::movsx_rm_r(ext_mode, RegMem::reg(src), dst, None));
+    } else {
+        ctx.emit(Inst::movzx_rm_r(ext_mode, RegMem::reg(src), dst, None));
+    }
+    dst.to_reg()
+}
+
+/// Emits a comparison of the two inputs of the given `icmp` or `ifcmp` instruction.
+fn emit_cmp(ctx: Ctx, insn: IRInst) {
+    let ty = ctx.input_ty(insn, 0);
+
+    let inputs = [InsnInput { insn, input: 0 }, InsnInput { insn, input: 1 }];
+
+    // TODO Try to commute the operands (and invert the condition) if one is an immediate.
+    let lhs = input_to_reg(ctx, inputs[0]);
+    let rhs = input_to_reg_mem_imm(ctx, inputs[1]);
+
+    // Cranelift's icmp semantics want to compare lhs - rhs, while Intel gives
+    // us dst - src at the machine instruction level, so invert operands.
+    ctx.emit(Inst::cmp_rmi_r(ty.bytes() as u8, rhs, lhs));
+}
+
+/// The condition(s) under which the result of a floating-point comparison holds.
+enum FcmpCondResult {
+    /// This condition is satisfied.
+    Condition(CC),
+    /// Both conditions are satisfied.
+    AndConditions(CC, CC),
+    /// At least one of the conditions is satisfied.
+    OrConditions(CC, CC),
+}
+
+/// Emits a comparison of the two inputs of the given `fcmp` or `ffcmp` instruction, and returns
+/// the conditions that hold iff `cond_code` holds for the inputs.
+///
+/// The flags are set by `ucomis{s,d}`, which has the following effect on the flags:
+///
+/// | result        | ZF | PF | CF |
+/// |---------------|----|----|----|
+/// | unordered     | 1  | 1  | 1  |
+/// | lhs < rhs     | 0  | 0  | 1  |
+/// | lhs = rhs     | 1  | 0  | 0  |
+/// | lhs > rhs     | 0  | 0  | 0  |
+///
+/// Since unordered results look like "less than" results, the conditions involving a "less than"
+/// check are handled by swapping the operands and checking for "greater than" instead.
+fn emit_fcmp(ctx: Ctx, insn: IRInst, cond_code: FloatCC) -> FcmpCondResult {
+    let (lhs_input, rhs_input, cond_code) = match cond_code {
+        FloatCC::LessThan
+        | FloatCC::LessThanOrEqual
+        | FloatCC::UnorderedOrGreaterThan
+        | FloatCC::UnorderedOrGreaterThanOrEqual => (1, 0, cond_code.reverse()),
+        _ => (0, 1, cond_code),
+    };
+
+    let op = match ctx.input_ty(insn, 0) {
+        F32 => SseOpcode::Ucomiss,
+        F64 => SseOpcode::Ucomisd,
+        ty => panic!("unexpected type {} in fcmp", ty),
+    };
+
+    let lhs = input_to_reg(
+        ctx,
+        InsnInput {
+            insn,
+            input: lhs_input,
+        },
+    );
+    let rhs = input_to_reg(
+        ctx,
+        InsnInput {
+            insn,
+            input: rhs_input,
+        },
+    );
+
+    // As for integer comparisons, Intel compares dst with src, so invert operands.
+    ctx.emit(Inst::xmm_cmp_rm_r(op, RegMem::reg(rhs), lhs));
+
+    match cond_code {
+        FloatCC::Ordered => FcmpCondResult::Condition(CC::NP),
+        FloatCC::Unordered => FcmpCondResult::Condition(CC::P),
+        FloatCC::Equal => FcmpCondResult::AndConditions(CC::NP, CC::Z),
+        FloatCC::NotEqual => FcmpCondResult::OrConditions(CC::P, CC::NZ),
+        FloatCC::OrderedNotEqual => FcmpCondResult::Condition(CC::NZ),
+        FloatCC::UnorderedOrEqual => FcmpCondResult::Condition(CC::Z),
+        FloatCC::GreaterThan => FcmpCondResult::Condition(CC::NBE),
+        FloatCC::GreaterThanOrEqual => FcmpCondResult::Condition(CC::NB),
+        FloatCC::UnorderedOrLessThan => FcmpCondResult::Condition(CC::B),
+        FloatCC::UnorderedOrLessThanOrEqual => FcmpCondResult::Condition(CC::BE),
+        _ => unreachable!("{} should have been reversed", cond_code),
+    }
+}
+
+/// Materializes the boolean result of a floating-point comparison into `dst`.
+fn emit_fcmp_result_to_reg(ctx: Ctx, result: FcmpCondResult, dst: Writable<Reg>) {
+    match result {
+        FcmpCondResult::Condition(cc) => ctx.emit(Inst::setcc(cc, dst)),
+        FcmpCondResult::AndConditions(cc1, cc2) | FcmpCondResult::OrConditions(cc1, cc2) => {
+            let op = match result {
+                FcmpCondResult::AndConditions(..) => AluRmiROpcode::And,
+                _ => AluRmiROpcode::Or,
+            };
+            let tmp = ctx.alloc_tmp(RegClass::I64, I32);
+            ctx.emit(Inst::setcc(cc1, tmp));
+            ctx.emit(Inst::setcc(cc2, dst));
+            ctx.emit(Inst::alu_rmi_r(
+                false,
+                op,
+                RegMemImm::reg(tmp.to_reg()),
+                dst,
+            ));
+        }
+    }
+}
+
+/// Emits the branch(es) to `taken` if the floating-point comparison `result` holds, and to
+/// `not_taken` otherwise.
+fn emit_fcmp_branch(
+    ctx: Ctx,
+    result: FcmpCondResult,
+    taken: BranchTarget,
+    not_taken: BranchTarget,
+) {
+    match result {
+        FcmpCondResult::Condition(cc) => {
+            ctx.emit(Inst::jmp_cond(cc, taken, not_taken));
+        }
+        FcmpCondResult::AndConditions(cc1, cc2) => {
+            ctx.emit(Inst::jmp_if(cc1.invert(), not_taken));
+            ctx.emit(Inst::jmp_cond(cc2, taken, not_taken));
+        }
+        FcmpCondResult::OrConditions(cc1, cc2) => {
+            ctx.emit(Inst::jmp_if(cc1, taken));
+            ctx.emit(Inst::jmp_cond(cc2, taken, not_taken));
+        }
+    }
+}
+
+/// Materializes the bit pattern `bits` of a scalar floating-point constant of type `ty` into the
+/// register `dst`.
+fn emit_scalar_const(ctx: Ctx, ty: Type, bits: u64, dst: Writable<Reg>) {
+    let tmp = ctx.alloc_tmp(RegClass::I64, I64);
+    let (is_64, op, size) = match ty {
+        F32 => (false, SseOpcode::Movd, 4),
+        F64 => (true, SseOpcode::Movq, 8),
+        _ => panic!("unexpected type {} for a scalar constant", ty),
+    };
+    ctx.emit(Inst::imm_r(is_64, bits, tmp));
+    ctx.emit(Inst::gpr_to_xmm(op, RegMem::reg(tmp.to_reg()), size, dst));
+}
+
+/// Materializes the 128-bit constant made of the `hi` and `lo` quadwords into the register `dst`.
+fn emit_vector_const(ctx: Ctx, lo: u64, hi: u64, dst: Writable<Reg>) {
+    let tmp_lo = ctx.alloc_tmp(RegClass::I64, I64);
+    let tmp_hi = ctx.alloc_tmp(RegClass::I64, I64);
+    let tmp_xmm = ctx.alloc_tmp(RegClass::V128, I64X2);
+    ctx.emit(Inst::imm_r(true, lo, tmp_lo));
+    ctx.emit(Inst::gpr_to_xmm(
+        SseOpcode::Movq,
+        RegMem::reg(tmp_lo.to_reg()),
+        8,
+        dst,
+    ));
+    ctx.emit(Inst::imm_r(true, hi, tmp_hi));
+    ctx.emit(Inst::gpr_to_xmm(
+        SseOpcode::Movq,
+        RegMem::reg(tmp_hi.to_reg()),
+        8,
+        tmp_xmm,
+    ));
+    ctx.emit(Inst::xmm_rm_r(
+        SseOpcode::Punpcklqdq,
+        RegMem::reg(tmp_xmm.to_reg()),
+        dst,
+    ));
+}
+
+/// Returns the value of each lane of a vector of type `ty` filled with `lane_bits`, repeated in
+/// each of the two quadwords of a vector register.
+fn splat_lane_bits(ty: Type, lane_bits: u64) -> u64 {
+    match ty.lane_bits() {
+        32 => lane_bits | (lane_bits << 32),
+        64 => lane_bits,
+        _ => panic!("unexpected vector type {}", ty),
+    }
+}
+
 //=============================================================================
 // Top-level instruction lowering entry point, for one instruction.
 
 /// Actually codegen an instruction's results into registers.
-fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> CodegenResult<()> {
+fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(
+    ctx: &mut C,
+    insn: IRInst,
+    flags: &Flags,
+) -> CodegenResult<()> {
     let op = ctx.data(insn).opcode();
 
     let inputs: SmallVec<[InsnInput; 4]> = (0..ctx.num_inputs(insn))
@@ -167,7 +398,7 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
     };
 
     match op {
-        Opcode::Iconst => {
+        Opcode::Iconst | Opcode::Bconst | Opcode::Null => {
             if let Some(w64) = iri_to_u64_imm(ctx, insn) {
                 // Get exactly the bit pattern in 'w64' into the dest.  No
                 // monkeying with sign extension etc.
@@ -175,50 +406,406 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
                 let dst = output_to_reg(ctx, outputs[0]);
                 ctx.emit(Inst::imm_r(dst_is_64, w64, dst));
             } else {
-                unimplemented!();
+                // Only `null` has no immediate.
+                return Err(CodegenError::Unsupported(
+                    "null references in the x64 backend".into(),
+                ));
             }
         }
 
-        Opcode::Iadd | Opcode::Isub => {
-            let lhs = input_to_reg(ctx, inputs[0]);
-            let rhs = input_to_reg_mem_imm(ctx, inputs[1]);
+        Opcode::F32const | Opcode::F64const => {
+            let bits = iri_to_u64_imm(ctx, insn).unwrap();
             let dst = output_to_reg(ctx, outputs[0]);
+            emit_scalar_const(ctx, ty.unwrap(), bits, dst);
+        }
 
-            // TODO For add, try to commute the operands if one is an immediate.
+        Opcode::Vconst => {
+            let bytes = match *ctx.data(insn) {
+                InstructionData::UnaryConst {
+                    constant_handle, ..
+                } => ctx.get_constant_data(constant_handle).clone().into_vec(),
+                _ => unreachable!(),
+            };
+            assert_eq!(bytes.len(), 16);
+            let mut lo = [0u8; 8];
+            let mut hi = [0u8; 8];
+            lo.copy_from_slice(&bytes[..8]);
+            hi.copy_from_slice(&bytes[8..]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            emit_vector_const(ctx, u64::from_le_bytes(lo), u64::from_le_bytes(hi), dst);
+        }
 
-            let is_64 = int_ty_is_64(ty.unwrap());
-            let alu_op = if op == Opcode::Iadd {
-                AluRmiROpcode::Add
+        Opcode::Iadd
+        | Opcode::IaddIfcout
+        | Opcode::Isub
+        | Opcode::Imul
+        | Opcode::Band
+        | Opcode::Bor
+        | Opcode::Bxor => {
+            let ty = ty.unwrap();
+            if ty.is_vector() {
+                let sse_op = match (op, ty.lane_bits()) {
+                    (Opcode::Iadd, 8) => SseOpcode::Paddb,
+                    (Opcode::Iadd, 16) => SseOpcode::Paddw,
+                    (Opcode::Iadd, 32) => SseOpcode::Paddd,
+                    (Opcode::Iadd, 64) => SseOpcode::Paddq,
+                    (Opcode::Isub, 8) => SseOpcode::Psubb,
+                    (Opcode::Isub, 16) => SseOpcode::Psubw,
+                    (Opcode::Isub, 32) => SseOpcode::Psubd,
+                    (Opcode::Isub, 64) => SseOpcode::Psubq,
+                    (Opcode::Imul, 16) => SseOpcode::Pmullw,
+                    (Opcode::Imul, 32) => SseOpcode::Pmulld,
+                    (Opcode::Band, _) => SseOpcode::Pand,
+                    (Opcode::Bor, _) => SseOpcode::Por,
+                    (Opcode::Bxor, _) => SseOpcode::Pxor,
+                    _ => {
+                        return Err(CodegenError::Unsupported(format!(
+                            "{} for type {} in the x64 backend",
+                            op, ty
+                        )))
+                    }
+                };
+                let lhs = input_to_reg(ctx, inputs[0]);
+                let rhs = input_to_reg(ctx, inputs[1]);
+                let dst = output_to_reg(ctx, outputs[0]);
+                ctx.emit(Inst::gen_move(dst, lhs, ty));
+                ctx.emit(Inst::xmm_rm_r(sse_op, RegMem::reg(rhs), dst));
             } else {
-                AluRmiROpcode::Sub
-            };
+                let lhs = input_to_reg(ctx, inputs[0]);
+                let rhs = input_to_reg_mem_imm(ctx, inputs[1]);
+                let dst = output_to_reg(ctx, outputs[0]);
 
-            ctx.emit(Inst::mov_r_r(true, lhs, dst));
-            ctx.emit(Inst::alu_rmi_r(is_64, alu_op, rhs, dst));
-        }
+                // TODO For commutative operations, try to commute the operands if one is an
+                // immediate.
+
+                // Narrow values are computed with 32-bit operations: the high bits of the result
+                // are then undefined, which is fine.
+                let is_64 = ty.bits() == 64;
+                let alu_op = match op {
+                    // The flags defined by `iadd_ifcout` are the ones set by the addition itself.
+                    Opcode::Iadd | Opcode::IaddIfcout => AluRmiROpcode::Add,
+                    Opcode::Isub => AluRmiROpcode::Sub,
+                    Opcode::Imul => AluRmiROpcode::Mul,
+                    Opcode::Band => AluRmiROpcode::And,
+                    Opcode::Bor => AluRmiROpcode::Or,
+                    Opcode::Bxor => AluRmiROpcode::Xor,
+                    _ => unreachable!(),
+                };
 
-        Opcode::Ishl | Opcode::Ushr | Opcode::Sshr => {
-            // TODO: implement imm shift value into insn
-            let dst_ty = ctx.output_ty(insn, 0);
-            assert_eq!(ctx.input_ty(insn, 0), dst_ty);
-            assert!(dst_ty == types::I32 || dst_ty == types::I64);
+                ctx.emit(Inst::mov_r_r(true, lhs, dst));
+                ctx.emit(Inst::alu_rmi_r(is_64, alu_op, rhs, dst));
+            }
+        }
 
+        Opcode::BandNot | Opcode::BorNot | Opcode::BxorNot => {
+            let ty = ty.unwrap();
             let lhs = input_to_reg(ctx, inputs[0]);
             let rhs = input_to_reg(ctx, inputs[1]);
             let dst = output_to_reg(ctx, outputs[0]);
+            if ty.is_vector() {
+                if op != Opcode::BandNot {
+                    return Err(CodegenError::Unsupported(format!(
+                        "{} for type {} in the x64 backend",
+                        op, ty
+                    )));
+                }
+                // pandn computes `!dst & src`.
+                ctx.emit(Inst::gen_move(dst, rhs, ty));
+                ctx.emit(Inst::xmm_rm_r(SseOpcode::Pandn, RegMem::reg(lhs), dst));
+            } else {
+                let is_64 = ty.bits() == 64;
+                let alu_op = match op {
+                    Opcode::BandNot => AluRmiROpcode::And,
+                    Opcode::BorNot => AluRmiROpcode::Or,
+                    Opcode::BxorNot => AluRmiROpcode::Xor,
+                    _ => unreachable!(),
+                };
+                ctx.emit(Inst::mov_r_r(true, rhs, dst));
+                ctx.emit(Inst::not(8, dst));
+                ctx.emit(Inst::alu_rmi_r(is_64, alu_op, RegMemImm::reg(lhs), dst));
+            }
+        }
+
+        Opcode::Bnot => {
+            let ty = ty.unwrap();
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            if ty.is_vector() {
+                let ones = ctx.alloc_tmp(RegClass::V128, ty);
+                emit_vector_const(ctx, u64::max_value(), u64::max_value(), ones);
+                ctx.emit(Inst::gen_move(dst, src, ty));
+                ctx.emit(Inst::xmm_rm_r(
+                    SseOpcode::Pxor,
+                    RegMem::reg(ones.to_reg()),
+                    dst,
+                ));
+            } else {
+                ctx.emit(Inst::mov_r_r(true, src, dst));
+                ctx.emit(Inst::not(8, dst));
+            }
+        }
+
+        Opcode::Ineg => {
+            let ty = ty.unwrap();
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            if ty.is_vector() {
+                let sse_op = match ty.lane_bits() {
+                    8 => SseOpcode::Psubb,
+                    16 => SseOpcode::Psubw,
+                    32 => SseOpcode::Psubd,
+                    64 => SseOpcode::Psubq,
+                    _ => unreachable!(),
+                };
+                // Compute 0 - src.
+                ctx.emit(Inst::gen_move(dst, src, ty));
+                ctx.emit(Inst::xmm_rm_r(
+                    SseOpcode::Pxor,
+                    RegMem::reg(dst.to_reg()),
+                    dst,
+                ));
+                ctx.emit(Inst::xmm_rm_r(sse_op, RegMem::reg(src), dst));
+            } else {
+                ctx.emit(Inst::mov_r_r(true, src, dst));
+                ctx.emit(Inst::neg(8, dst));
+            }
+        }
+
+        Opcode::Ishl | Opcode::Ushr | Opcode::Sshr | Opcode::Rotl | Opcode::Rotr => {
+            let dst_ty = ctx.output_ty(insn, 0);
+            assert_eq!(ctx.input_ty(insn, 0), dst_ty);
+            let bits = dst_ty.bits();
+            assert!(
+                !dst_ty.is_vector() && bits <= 64,
+                "unsupported type {} for {}",
+                dst_ty,
+                op
+            );
 
             let shift_kind = match op {
                 Opcode::Ishl => ShiftKind::Left,
                 Opcode::Ushr => ShiftKind::RightZ,
                 Opcode::Sshr => ShiftKind::RightS,
+                Opcode::Rotl => ShiftKind::RotateLeft,
+                Opcode::Rotr => ShiftKind::RotateRight,
                 _ => unreachable!(),
             };
 
-            let is_64 = dst_ty == types::I64;
-            let w_rcx = Writable::from_reg(regs::rcx());
+            // Narrow values are shifted with 32-bit operations, after the value has been
+            // extended to 32 bits for right shifts. Rotations can't be emulated this way.
+            if bits < 32 && (op == Opcode::Rotl || op == Opcode::Rotr) {
+                return Err(CodegenError::Unsupported(format!(
+                    "{} for type {} in the x64 backend",
+                    op, dst_ty
+                )));
+            }
+            let lhs = if bits < 32 {
+                input_to_reg_ext32(ctx, inputs[0], op == Opcode::Sshr)
+            } else {
+                input_to_reg(ctx, inputs[0])
+            };
+
+            let is_64 = bits == 64;
+            let dst = output_to_reg(ctx, outputs[0]);
+            let count = ctx.get_input(insn, 1).constant;
+
             ctx.emit(Inst::mov_r_r(true, lhs, dst));
-            ctx.emit(Inst::mov_r_r(true, rhs, w_rcx));
-            ctx.emit(Inst::shift_r(is_64, shift_kind, None /*%cl*/, dst));
+            match count {
+                Some(count) => {
+                    let count = (count & (bits as u64 - 1)) as u8;
+                    ctx.emit(Inst::shift_r(is_64, shift_kind, Some(count), dst));
+                }
+                None => {
+                    let rhs = input_to_reg(ctx, inputs[1]);
+                    let w_rcx = Writable::from_reg(regs::rcx());
+                    ctx.emit(Inst::mov_r_r(true, rhs, w_rcx));
+                    if bits < 32 {
+                        // The hardware masks the count to 5 bits; narrower values need a
+                        // narrower mask.
+                        ctx.emit(Inst::alu_rmi_r(
+                            false,
+                            AluRmiROpcode::And,
+                            RegMemImm::imm(bits as u32 - 1),
+                            w_rcx,
+                        ));
+                    }
+                    ctx.emit(Inst::shift_r(is_64, shift_kind, None /*%cl*/, dst));
+                }
+            }
+        }
+
+        Opcode::Clz => {
+            // The count is computed with bsr, which gives the index of the highest set bit, and
+            // sets ZF (leaving its destination undefined) if the source is zero:
+            //
+            // bsr %src, %dst
+            // mov $-1, %tmp
+            // cmovz %tmp, %dst
+            // mov $(bits - 1), %tmp
+            // sub %dst, %tmp
+            // mov %tmp, %dst
+            let ty = ty.unwrap();
+            let src = input_to_reg_ext32(ctx, inputs[0], false);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let is_64 = int_ty_is_64(ty);
+            let (size, op_bits) = if is_64 { (8, 64) } else { (4, 32) };
+
+            let tmp = ctx.alloc_tmp(RegClass::I64, ty);
+            ctx.emit(Inst::unary_rm_r(
+                size,
+                UnaryRmROpcode::Bsr,
+                RegMem::reg(src),
+                dst,
+            ));
+            ctx.emit(Inst::imm_r(
+                is_64,
+                if is_64 {
+                    u64::max_value()
+                } else {
+                    u32::max_value() as u64
+                },
+                tmp,
+            ));
+            ctx.emit(Inst::cmove(size, CC::Z, RegMem::reg(tmp.to_reg()), dst));
+            ctx.emit(Inst::imm_r(is_64, op_bits - 1, tmp));
+            ctx.emit(Inst::alu_rmi_r(
+                is_64,
+                AluRmiROpcode::Sub,
+                RegMemImm::reg(dst.to_reg()),
+                tmp,
+            ));
+            ctx.emit(Inst::mov_r_r(true, tmp.to_reg(), dst));
+
+            // Narrow values have been zero-extended to 32 bits: discount the extra bits.
+            if ty.bits() < 32 {
+                ctx.emit(Inst::alu_rmi_r(
+                    false,
+                    AluRmiROpcode::Sub,
+                    RegMemImm::imm(32 - ty.bits() as u32),
+                    dst,
+                ));
+            }
+        }
+
+        Opcode::Ctz => {
+            // bsf gives the index of the lowest set bit, and sets ZF if the source is zero:
+            //
+            // bsf %src, %dst
+            // mov $bits, %tmp
+            // cmovz %tmp, %dst
+            let ty = ty.unwrap();
+            let src = input_to_reg_ext32(ctx, inputs[0], false);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let is_64 = int_ty_is_64(ty);
+            let size = if is_64 { 8 } else { 4 };
+
+            let tmp = ctx.alloc_tmp(RegClass::I64, ty);
+            ctx.emit(Inst::unary_rm_r(
+                size,
+                UnaryRmROpcode::Bsf,
+                RegMem::reg(src),
+                dst,
+            ));
+            ctx.emit(Inst::imm_r(false, ty.bits() as u64, tmp));
+            ctx.emit(Inst::cmove(size, CC::Z, RegMem::reg(tmp.to_reg()), dst));
+        }
+
+        Opcode::Popcnt => {
+            // TODO when the popcnt extension isn't available, use a bit-twiddling sequence.
+            let ty = ty.unwrap();
+            let src = input_to_reg_ext32(ctx, inputs[0], false);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let size = if int_ty_is_64(ty) { 8 } else { 4 };
+            ctx.emit(Inst::unary_rm_r(
+                size,
+                UnaryRmROpcode::Popcnt,
+                RegMem::reg(src),
+                dst,
+            ));
+        }
+
+        Opcode::Udiv | Opcode::Urem | Opcode::Sdiv | Opcode::Srem => {
+            let kind = match op {
+                Opcode::Udiv => DivOrRemKind::UnsignedDiv,
+                Opcode::Sdiv => DivOrRemKind::SignedDiv,
+                Opcode::Urem => DivOrRemKind::UnsignedRem,
+                Opcode::Srem => DivOrRemKind::SignedRem,
+                _ => unreachable!(),
+            };
+            let is_signed = kind.is_signed();
+
+            // Narrow values are extended and divided with 32-bit operations.
+            let input_ty = ctx.input_ty(insn, 0);
+            let size = if int_ty_is_64(input_ty) { 8 } else { 4 };
+
+            let dividend = input_to_reg_ext32(ctx, inputs[0], is_signed);
+            let divisor = input_to_reg_ext32(ctx, inputs[1], is_signed);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let srcloc = ctx.srcloc(insn);
+
+            // The dividend is in %rdx:%rax, and the result will be in either %rax (division) or
+            // %rdx (remainder).
+            ctx.emit(Inst::gen_move(
+                Writable::from_reg(regs::rax()),
+                dividend,
+                input_ty,
+            ));
+
+            // Fill in the high parts.
+            if is_signed {
+                // sign-extend the sign-bit of rax into rdx, for signed opcodes.
+                ctx.emit(Inst::sign_extend_rax_to_rdx(size));
+            } else {
+                // zero for unsigned opcodes.
+                ctx.emit(Inst::imm_r(
+                    true, /* is_64 */
+                    0,
+                    Writable::from_reg(regs::rdx()),
+                ));
+            }
+
+            if flags.avoid_div_traps() || op == Opcode::Srem {
+                // A vcode meta-instruction is used to lower the inline checks, since they embed
+                // pc-relative offsets that must not change, thus requiring regalloc to not
+                // interfere by introducing spills and reloads. The signed remainder also needs
+                // a special case for a divisor of -1, for which the hardware instruction would
+                // trap on overflow.
+                ctx.emit(Inst::checked_div_or_rem_seq(kind, size, divisor, srcloc));
+            } else {
+                ctx.emit(Inst::div(size, is_signed, RegMem::reg(divisor), srcloc));
+            }
+
+            // Move the result back into the destination reg.
+            let result = if kind.is_div() {
+                regs::rax()
+            } else {
+                regs::rdx()
+            };
+            ctx.emit(Inst::gen_move(dst, result, input_ty));
+        }
+
+        Opcode::Umulhi | Opcode::Smulhi => {
+            let input_ty = ctx.input_ty(insn, 0);
+            let size = if int_ty_is_64(input_ty) { 8 } else { 4 };
+            assert!(
+                input_ty.bits() >= 32,
+                "unsupported type {} for {}",
+                input_ty,
+                op
+            );
+
+            let lhs = input_to_reg(ctx, inputs[0]);
+            let rhs = input_to_reg(ctx, inputs[1]);
+            let dst = output_to_reg(ctx, outputs[0]);
+
+            // The high part of the product of %rax and the operand is written into %rdx.
+            ctx.emit(Inst::gen_move(
+                Writable::from_reg(regs::rax()),
+                lhs,
+                input_ty,
+            ));
+            ctx.emit(Inst::mul_hi(size, op == Opcode::Smulhi, RegMem::reg(rhs)));
+            ctx.emit(Inst::gen_move(dst, regs::rdx(), input_ty));
         }
 
         Opcode::Uextend
@@ -235,21 +822,23 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
             let dst = output_to_reg(ctx, outputs[0]);
 
             let ext_mode = match (src_ty.bits(), dst_ty.bits()) {
-                (1, 32) | (8, 32) => ExtMode::BL,
-                (1, 64) | (8, 64) => ExtMode::BQ,
-                (16, 32) => ExtMode::WL,
-                (16, 64) => ExtMode::WQ,
-                (32, 64) => ExtMode::LQ,
+                (1, 8) | (1, 16) | (1, 32) | (8, 16) | (8, 32) => Some(ExtMode::BL),
+                (1, 64) | (8, 64) => Some(ExtMode::BQ),
+                (16, 32) => Some(ExtMode::WL),
+                (16, 64) => Some(ExtMode::WQ),
+                (32, 64) => Some(ExtMode::LQ),
+                (src_bits, dst_bits) if src_bits >= dst_bits => None,
                 _ => unreachable!(
                     "unexpected extension kind from {:?} to {:?}",
                     src_ty, dst_ty
                 ),
             };
 
-            if op == Opcode::Sextend {
-                ctx.emit(Inst::movsx_rm_r(ext_mode, RegMem::reg(src), dst));
-            } else {
-                // All of these other opcodes are simply a move from a zero-extended source.  Here
+            match ext_mode {
+                Some(ext_mode) if op == Opcode::Sextend => {
+                    ctx.emit(Inst::movsx_rm_r(ext_mode, RegMem::reg(src), dst, None));
+                }
+                // All of these other opcodes are simply a move from a zero-extended source. Here
                 // is why this works, in each case:
                 //
                 // - Bint: Bool-to-int. We always represent a bool as a 0 or 1, so we
@@ -257,42 +846,219 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
                 //
                 // - Breduce, Bextend: changing width of a boolean. We represent a
                 //   bool as a 0 or 1, so again, this is a zero-extend / no-op.
-                //
+                Some(ext_mode) => {
+                    ctx.emit(Inst::movzx_rm_r(ext_mode, RegMem::reg(src), dst, None));
+                }
                 // - Ireduce: changing width of an integer. Smaller ints are stored
                 //   with undefined high-order bits, so we can simply do a copy.
-                ctx.emit(Inst::movzx_rm_r(ext_mode, RegMem::reg(src), dst));
+                None => {
+                    ctx.emit(Inst::mov_r_r(true, src, dst));
+                }
             }
         }
 
+        Opcode::Bmask => {
+            // Bools are represented as 0 or 1 in their low byte, so negating the zero-extended
+            // value gives the mask.
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            ctx.emit(Inst::movzx_rm_r(ExtMode::BQ, RegMem::reg(src), dst, None));
+            ctx.emit(Inst::neg(8, dst));
+        }
+
         Opcode::Icmp => {
             let condcode = inst_condcode(ctx.data(insn));
             let cc = CC::from_intcc(condcode);
-            let ty = ctx.input_ty(insn, 0);
+            if ctx.input_ty(insn, 0).is_vector() {
+                return Err(CodegenError::Unsupported(
+                    "vector icmp in the x64 backend".into(),
+                ));
+            }
+            emit_cmp(ctx, insn);
+            let dst = output_to_reg(ctx, outputs[0]);
+            ctx.emit(Inst::setcc(cc, dst));
+        }
 
-            // TODO Try to commute the operands (and invert the condition) if one is an immediate.
-            let lhs = input_to_reg(ctx, inputs[0]);
-            let rhs = input_to_reg_mem_imm(ctx, inputs[1]);
+        Opcode::Fcmp => {
+            let cond_code = inst_fp_condcode(ctx.data(insn));
+            if ctx.input_ty(insn, 0).is_vector() {
+                return Err(CodegenError::Unsupported(
+                    "vector fcmp in the x64 backend".into(),
+                ));
+            }
+            let result = emit_fcmp(ctx, insn, cond_code);
             let dst = output_to_reg(ctx, outputs[0]);
+            emit_fcmp_result_to_reg(ctx, result, dst);
+        }
 
-            // Cranelift's icmp semantics want to compare lhs - rhs, while Intel gives
-            // us dst - src at the machine instruction level, so invert operands.
-            ctx.emit(Inst::cmp_rmi_r(ty.bytes() as u8, rhs, lhs));
+        Opcode::Trueif => {
+            let cc = CC::from_intcc(inst_condcode(ctx.data(insn)));
+            // Verification ensures that the input is always a single-def ifcmp.
+            let ifcmp = matches_input(ctx, inputs[0], Opcode::Ifcmp).unwrap();
+            emit_cmp(ctx, ifcmp);
+            let dst = output_to_reg(ctx, outputs[0]);
             ctx.emit(Inst::setcc(cc, dst));
         }
 
+        Opcode::Trueff => {
+            let cond_code = inst_fp_condcode(ctx.data(insn));
+            // Verification ensures that the input is always a single-def ffcmp.
+            let ffcmp = matches_input(ctx, inputs[0], Opcode::Ffcmp).unwrap();
+            let result = emit_fcmp(ctx, ffcmp, cond_code);
+            let dst = output_to_reg(ctx, outputs[0]);
+            emit_fcmp_result_to_reg(ctx, result, dst);
+        }
+
+        Opcode::Ifcmp | Opcode::Ffcmp => {
+            // An Ifcmp/Ffcmp must always be seen as a use of a brif/brff, trueif/trueff,
+            // selectif or trapif/trapff instruction, which generates the comparison itself. The
+            // flags pass of the verifier ensures that they are never used across blocks.
+            panic!("Should never reach ifcmp/ffcmp as isel root!");
+        }
+
+        Opcode::IsNull | Opcode::IsInvalid => {
+            // Null references are represented by the constant value 0; invalid references are
+            // represented by the constant value -1.
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let imm = match op {
+                Opcode::IsNull => 0,
+                Opcode::IsInvalid => 0xffff_ffff,
+                _ => unreachable!(),
+            };
+            let size = ctx.input_ty(insn, 0).bytes() as u8;
+            ctx.emit(Inst::cmp_rmi_r(size, RegMemImm::imm(imm), src));
+            ctx.emit(Inst::setcc(CC::Z, dst));
+        }
+
+        Opcode::Select | Opcode::Selectif => {
+            let ty = ty.unwrap();
+
+            // Compute the flags and the condition under which the first value is selected.
+            let cond = if op == Opcode::Selectif {
+                let cc = CC::from_intcc(inst_condcode(ctx.data(insn)));
+                // Verification ensures that the input is always a single-def ifcmp.
+                let ifcmp = matches_input(ctx, inputs[0], Opcode::Ifcmp).unwrap();
+                emit_cmp(ctx, ifcmp);
+                FcmpCondResult::Condition(cc)
+            } else if let Some(fcmp) = matches_input(ctx, inputs[0], Opcode::Fcmp) {
+                let cond_code = inst_fp_condcode(ctx.data(fcmp));
+                emit_fcmp(ctx, fcmp, cond_code)
+            } else if let Some(icmp) = matches_input(ctx, inputs[0], Opcode::Icmp) {
+                let cc = CC::from_intcc(inst_condcode(ctx.data(icmp)));
+                emit_cmp(ctx, icmp);
+                FcmpCondResult::Condition(cc)
+            } else {
+                let cond_ty = ctx.input_ty(insn, 0);
+                let cond = input_to_reg(ctx, inputs[0]);
+                // Only the low byte of a bool is defined.
+                let size = if is_bool_ty(cond_ty) {
+                    1
+                } else {
+                    cond_ty.bytes() as u8
+                };
+                ctx.emit(Inst::test_rmi_r(size, RegMemImm::reg(cond), cond));
+                FcmpCondResult::Condition(CC::NZ)
+            };
+
+            let lhs = input_to_reg(ctx, inputs[1]);
+            let rhs = input_to_reg(ctx, inputs[2]);
+            let dst = output_to_reg(ctx, outputs[0]);
+
+            // Conditional moves don't clobber the flags, so they can be chained.
+            let emit_cmove = |ctx: &mut C, cc: CC, src: Reg| {
+                if is_float_ty(ty) {
+                    ctx.emit(Inst::xmm_cmove(ty == F64, cc, RegMem::reg(src), dst));
+                } else {
+                    let size = u8::max(ty.bytes() as u8, 4);
+                    ctx.emit(Inst::cmove(size, cc, RegMem::reg(src), dst));
+                }
+            };
+
+            if ty.is_vector() {
+                // There's no vector conditional move: materialize the condition as a mask
+                // instead, and compute `(lhs & mask) | (rhs & !mask)`.
+                let cc = match cond {
+                    FcmpCondResult::Condition(cc) => cc,
+                    _ => {
+                        return Err(CodegenError::Unsupported(
+                            "vector select on a float equality condition in the x64 backend".into(),
+                        ))
+                    }
+                };
+                let tmp_gpr = ctx.alloc_tmp(RegClass::I64, I64);
+                let mask = ctx.alloc_tmp(RegClass::V128, I64X2);
+                let tmp_xmm = ctx.alloc_tmp(RegClass::V128, I64X2);
+                ctx.emit(Inst::setcc(cc, tmp_gpr));
+                ctx.emit(Inst::movzx_rm_r(
+                    ExtMode::BQ,
+                    RegMem::reg(tmp_gpr.to_reg()),
+                    tmp_gpr,
+                    None,
+                ));
+                ctx.emit(Inst::neg(8, tmp_gpr));
+                ctx.emit(Inst::gpr_to_xmm(
+                    SseOpcode::Movq,
+                    RegMem::reg(tmp_gpr.to_reg()),
+                    8,
+                    mask,
+                ));
+                ctx.emit(Inst::gpr_to_xmm(
+                    SseOpcode::Movq,
+                    RegMem::reg(tmp_gpr.to_reg()),
+                    8,
+                    tmp_xmm,
+                ));
+                ctx.emit(Inst::xmm_rm_r(
+                    SseOpcode::Punpcklqdq,
+                    RegMem::reg(tmp_xmm.to_reg()),
+                    mask,
+                ));
+                ctx.emit(Inst::gen_move(tmp_xmm, mask.to_reg(), ty));
+                ctx.emit(Inst::xmm_rm_r(SseOpcode::Pandn, RegMem::reg(rhs), tmp_xmm));
+                ctx.emit(Inst::gen_move(dst, lhs, ty));
+                ctx.emit(Inst::xmm_rm_r(
+                    SseOpcode::Pand,
+                    RegMem::reg(mask.to_reg()),
+                    dst,
+                ));
+                ctx.emit(Inst::xmm_rm_r(
+                    SseOpcode::Por,
+                    RegMem::reg(tmp_xmm.to_reg()),
+                    dst,
+                ));
+            } else {
+                match cond {
+                    FcmpCondResult::Condition(cc) => {
+                        ctx.emit(Inst::gen_move(dst, rhs, ty));
+                        emit_cmove(ctx, cc, lhs);
+                    }
+                    FcmpCondResult::AndConditions(cc1, cc2) => {
+                        ctx.emit(Inst::gen_move(dst, lhs, ty));
+                        emit_cmove(ctx, cc1.invert(), rhs);
+                        emit_cmove(ctx, cc2.invert(), rhs);
+                    }
+                    FcmpCondResult::OrConditions(cc1, cc2) => {
+                        ctx.emit(Inst::gen_move(dst, rhs, ty));
+                        emit_cmove(ctx, cc1, lhs);
+                        emit_cmove(ctx, cc2, lhs);
+                    }
+                }
+            }
+        }
+
+        Opcode::Copy => {
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            ctx.emit(Inst::gen_move(dst, src, ty.unwrap()));
+        }
+
         Opcode::FallthroughReturn | Opcode::Return => {
             for i in 0..ctx.num_inputs(insn) {
                 let src_reg = input_to_reg(ctx, inputs[i]);
                 let retval_reg = ctx.retval(i);
-                if src_reg.get_class() == RegClass::I64 {
-                    ctx.emit(Inst::mov_r_r(true, src_reg, retval_reg));
-                } else if src_reg.get_class() == RegClass::V128 {
-                    ctx.emit(Inst::xmm_mov_rm_r(
-                        SseOpcode::Movsd,
-                        RegMem::reg(src_reg),
-                        retval_reg,
-                    ));
-                }
+                let ty = ctx.input_ty(insn, i);
+                ctx.emit(Inst::gen_move(retval_reg, src_reg, ty));
             }
             // N.B.: the Ret itself is generated by the ABI.
         }
@@ -306,7 +1072,7 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
                     assert!(inputs.len() == sig.params.len());
                     assert!(outputs.len() == sig.returns.len());
                     (
-                        X64ABICall::from_func(sig, &extname, dist, loc)?,
+                        X64ABICall::from_func(sig, &extname, dist, loc, op)?,
                         &inputs[..],
                     )
                 }
@@ -336,80 +1102,536 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
             abi.emit_stack_post_adjust(ctx);
         }
 
+        Opcode::ReturnCall | Opcode::ReturnCallIndirect => {
+            let loc = ctx.srcloc(insn);
+            let (mut abi, inputs) = match op {
+                Opcode::ReturnCall => {
+                    let (extname, dist) = ctx.call_target(insn).unwrap();
+                    let sig = ctx.call_sig(insn).unwrap();
+                    assert!(inputs.len() == sig.params.len());
+                    (
+                        X64ABICall::from_func(sig, &extname, dist, loc, op)?,
+                        &inputs[..],
+                    )
+                }
+
+                Opcode::ReturnCallIndirect => {
+                    let ptr = input_to_reg(ctx, inputs[0]);
+                    let sig = ctx.call_sig(insn).unwrap();
+                    assert!(inputs.len() - 1 == sig.params.len());
+                    (X64ABICall::from_ptr(sig, ptr, loc, op)?, &inputs[1..])
+                }
+
+                _ => unreachable!(),
+            };
+
+            // The frame is reused, so there is no stack adjustment around the call and no
+            // return values to copy back.
+            assert!(inputs.len() == abi.num_args());
+            for (i, input) in inputs.iter().enumerate() {
+                let arg_reg = input_to_reg(ctx, *input);
+                abi.emit_copy_reg_to_arg(ctx, i, arg_reg);
+            }
+            abi.emit_tail_call(ctx)?;
+        }
+
+        Opcode::FuncAddr => {
+            let dst = output_to_reg(ctx, outputs[0]);
+            let (extname, _) = ctx.call_target(insn).unwrap();
+            let extname = extname.clone();
+            let loc = ctx.srcloc(insn);
+            ctx.emit(Inst::LoadExtName {
+                dst,
+                name: Box::new(extname),
+                srcloc: loc,
+                offset: 0,
+            });
+        }
+
+        Opcode::SymbolValue => {
+            let dst = output_to_reg(ctx, outputs[0]);
+            let (extname, _, offset) = ctx.symbol_value(insn).unwrap();
+            let extname = extname.clone();
+            let loc = ctx.srcloc(insn);
+            ctx.emit(Inst::LoadExtName {
+                dst,
+                name: Box::new(extname),
+                srcloc: loc,
+                offset,
+            });
+        }
+
         Opcode::Debugtrap => {
             ctx.emit(Inst::Hlt);
         }
 
-        Opcode::Trap => {
+        Opcode::Trap | Opcode::ResumableTrap => {
             let trap_info = (ctx.srcloc(insn), inst_trapcode(ctx.data(insn)).unwrap());
             ctx.emit(Inst::Ud2 { trap_info })
         }
 
+        Opcode::Trapif | Opcode::Trapff => {
+            let srcloc = ctx.srcloc(insn);
+            let trap_code = inst_trapcode(ctx.data(insn)).unwrap();
+
+            if matches_input(ctx, inputs[0], Opcode::IaddIfcout).is_some() {
+                // The flags must not have been clobbered by any other instruction between the
+                // iadd_ifcout and this instruction, as verified by the CLIF validator; so we can
+                // simply use the flags here.
+                let cc = CC::from_intcc(inst_condcode(ctx.data(insn)));
+                ctx.emit(Inst::trap_if(cc, trap_code, srcloc));
+            } else if op == Opcode::Trapif {
+                let cc = CC::from_intcc(inst_condcode(ctx.data(insn)));
+                // Verification ensures that the input is always a single-def ifcmp.
+                let ifcmp = matches_input(ctx, inputs[0], Opcode::Ifcmp).unwrap();
+                emit_cmp(ctx, ifcmp);
+                ctx.emit(Inst::trap_if(cc, trap_code, srcloc));
+            } else {
+                let cond_code = inst_fp_condcode(ctx.data(insn));
+                // Verification ensures that the input is always a single-def ffcmp.
+                let ffcmp = matches_input(ctx, inputs[0], Opcode::Ffcmp).unwrap();
+                match emit_fcmp(ctx, ffcmp, cond_code) {
+                    FcmpCondResult::Condition(cc) => ctx.emit(Inst::trap_if(cc, trap_code, srcloc)),
+                    FcmpCondResult::AndConditions(cc1, cc2) => {
+                        // Trap iff both conditions hold, i.e. iff both setcc results are 1.
+                        let tmp1 = ctx.alloc_tmp(RegClass::I64, I32);
+                        let tmp2 = ctx.alloc_tmp(RegClass::I64, I32);
+                        ctx.emit(Inst::setcc(cc1, tmp1));
+                        ctx.emit(Inst::setcc(cc2, tmp2));
+                        ctx.emit(Inst::test_rmi_r(
+                            1,
+                            RegMemImm::reg(tmp1.to_reg()),
+                            tmp2.to_reg(),
+                        ));
+                        ctx.emit(Inst::trap_if(CC::NZ, trap_code, srcloc));
+                    }
+                    FcmpCondResult::OrConditions(cc1, cc2) => {
+                        ctx.emit(Inst::trap_if(cc1, trap_code, srcloc));
+                        ctx.emit(Inst::trap_if(cc2, trap_code, srcloc));
+                    }
+                }
+            }
+        }
+
         Opcode::Fadd | Opcode::Fsub | Opcode::Fmul | Opcode::Fdiv => {
             let lhs = input_to_reg(ctx, inputs[0]);
             let rhs = input_to_reg(ctx, inputs[1]);
             let dst = output_to_reg(ctx, outputs[0]);
-            let is_64 = flt_ty_is_64(ty.unwrap());
-            if !is_64 {
-                let sse_op = match op {
-                    Opcode::Fadd => SseOpcode::Addss,
-                    Opcode::Fsub => SseOpcode::Subss,
-                    Opcode::Fmul => SseOpcode::Mulss,
-                    Opcode::Fdiv => SseOpcode::Divss,
-                    // TODO Fmax, Fmin.
-                    _ => unimplemented!(),
-                };
-                ctx.emit(Inst::xmm_mov_rm_r(SseOpcode::Movss, RegMem::reg(lhs), dst));
-                ctx.emit(Inst::xmm_rm_r(sse_op, RegMem::reg(rhs), dst));
+            let ty = ty.unwrap();
+            let sse_op = match (op, ty) {
+                (Opcode::Fadd, F32) => SseOpcode::Addss,
+                (Opcode::Fadd, F64) => SseOpcode::Addsd,
+                (Opcode::Fadd, F32X4) => SseOpcode::Addps,
+                (Opcode::Fadd, F64X2) => SseOpcode::Addpd,
+                (Opcode::Fsub, F32) => SseOpcode::Subss,
+                (Opcode::Fsub, F64) => SseOpcode::Subsd,
+                (Opcode::Fsub, F32X4) => SseOpcode::Subps,
+                (Opcode::Fsub, F64X2) => SseOpcode::Subpd,
+                (Opcode::Fmul, F32) => SseOpcode::Mulss,
+                (Opcode::Fmul, F64) => SseOpcode::Mulsd,
+                (Opcode::Fmul, F32X4) => SseOpcode::Mulps,
+                (Opcode::Fmul, F64X2) => SseOpcode::Mulpd,
+                (Opcode::Fdiv, F32) => SseOpcode::Divss,
+                (Opcode::Fdiv, F64) => SseOpcode::Divsd,
+                (Opcode::Fdiv, F32X4) => SseOpcode::Divps,
+                (Opcode::Fdiv, F64X2) => SseOpcode::Divpd,
+                _ => panic!("unexpected type {} for {}", ty, op),
+            };
+            ctx.emit(Inst::gen_move(dst, lhs, ty));
+            ctx.emit(Inst::xmm_rm_r(sse_op, RegMem::reg(rhs), dst));
+        }
+
+        Opcode::Fmin | Opcode::Fmax => {
+            let ty = ty.unwrap();
+            if ty.is_vector() {
+                return Err(CodegenError::Unsupported(format!(
+                    "{} for type {} in the x64 backend",
+                    op, ty
+                )));
+            }
+            let lhs = input_to_reg(ctx, inputs[0]);
+            let rhs = input_to_reg(ctx, inputs[1]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            // The min/max instructions don't have the wasm semantics for NaNs and signed zeros,
+            // so a special sequence is used.
+            ctx.emit(Inst::gen_move(dst, rhs, ty));
+            ctx.emit(Inst::xmm_min_max_seq(
+                op == Opcode::Fmin,
+                flt_ty_is_64(ty),
+                lhs,
+                dst,
+            ));
+        }
+
+        Opcode::Sqrt => {
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let ty = ty.unwrap();
+            let sse_op = match ty {
+                F32 => SseOpcode::Sqrtss,
+                F64 => SseOpcode::Sqrtsd,
+                F32X4 => SseOpcode::Sqrtps,
+                F64X2 => SseOpcode::Sqrtpd,
+                _ => panic!("unexpected type {} for {}", ty, op),
+            };
+            ctx.emit(Inst::xmm_mov_rm_r(sse_op, RegMem::reg(src), dst, None));
+        }
+
+        Opcode::Fneg | Opcode::Fabs => {
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let ty = ty.unwrap();
+
+            // Flip the sign bit with a xor to negate, clear it with an and to get the absolute
+            // value.
+            let sign_bit = if ty.lane_bits() == 64 {
+                0x8000_0000_0000_0000
             } else {
-                unimplemented!("unimplemented lowering for opcode {:?}", op);
+                0x8000_0000
+            };
+            let (mask_bits, sse_op) = match op {
+                Opcode::Fneg => (sign_bit, SseOpcode::Xorps),
+                Opcode::Fabs => (!sign_bit & (sign_bit | (sign_bit - 1)), SseOpcode::Andps),
+                _ => unreachable!(),
+            };
+
+            let mask = ctx.alloc_tmp(RegClass::V128, ty);
+            if ty.is_vector() {
+                let bits = splat_lane_bits(ty, mask_bits);
+                emit_vector_const(ctx, bits, bits, mask);
+            } else {
+                emit_scalar_const(ctx, ty, mask_bits, mask);
             }
+            ctx.emit(Inst::gen_move(dst, src, ty));
+            ctx.emit(Inst::xmm_rm_r(sse_op, RegMem::reg(mask.to_reg()), dst));
         }
 
         Opcode::Fcopysign => {
             let dst = output_to_reg(ctx, outputs[0]);
             let lhs = input_to_reg(ctx, inputs[0]);
             let rhs = input_to_reg(ctx, inputs[1]);
-            if !flt_ty_is_64(ty.unwrap()) {
-                // movabs   0x8000_0000, tmp_gpr1
-                // movd     tmp_gpr1, tmp_xmm1
-                // movaps   tmp_xmm1, dst
-                // andnps   src_1, dst
-                // movss    src_2, tmp_xmm2
-                // andps    tmp_xmm1, tmp_xmm2
-                // orps     tmp_xmm2, dst
-                let tmp_gpr1 = ctx.alloc_tmp(RegClass::I64, I32);
-                let tmp_xmm1 = ctx.alloc_tmp(RegClass::V128, F32);
-                let tmp_xmm2 = ctx.alloc_tmp(RegClass::V128, F32);
-                ctx.emit(Inst::imm_r(true, 0x8000_0000, tmp_gpr1));
-                ctx.emit(Inst::xmm_mov_rm_r(
-                    SseOpcode::Movd,
-                    RegMem::reg(tmp_gpr1.to_reg()),
-                    tmp_xmm1,
+            let ty = ty.unwrap();
+            if ty.is_vector() {
+                return Err(CodegenError::Unsupported(format!(
+                    "{} for type {} in the x64 backend",
+                    op, ty
+                )));
+            }
+            let sign_bit = if flt_ty_is_64(ty) {
+                0x8000_0000_0000_0000
+            } else {
+                0x8000_0000
+            };
+            // mov      sign_bit, tmp_xmm1
+            // movaps   tmp_xmm1, dst
+            // andnps   src_1, dst
+            // movss    src_2, tmp_xmm2
+            // andps    tmp_xmm1, tmp_xmm2
+            // orps     tmp_xmm2, dst
+            let tmp_xmm1 = ctx.alloc_tmp(RegClass::V128, ty);
+            let tmp_xmm2 = ctx.alloc_tmp(RegClass::V128, ty);
+            emit_scalar_const(ctx, ty, sign_bit, tmp_xmm1);
+            ctx.emit(Inst::xmm_mov_rm_r(
+                SseOpcode::Movaps,
+                RegMem::reg(tmp_xmm1.to_reg()),
+                dst,
+                None,
+            ));
+            ctx.emit(Inst::xmm_rm_r(SseOpcode::Andnps, RegMem::reg(lhs), dst));
+            ctx.emit(Inst::gen_move(tmp_xmm2, rhs, ty));
+            ctx.emit(Inst::xmm_rm_r(
+                SseOpcode::Andps,
+                RegMem::reg(tmp_xmm1.to_reg()),
+                tmp_xmm2,
+            ));
+            ctx.emit(Inst::xmm_rm_r(
+                SseOpcode::Orps,
+                RegMem::reg(tmp_xmm2.to_reg()),
+                dst,
+            ));
+        }
+
+        Opcode::Ceil | Opcode::Floor | Opcode::Trunc | Opcode::Nearest => {
+            // TODO the round instructions require SSE4.1; a libcall could be used otherwise.
+            let ty = ty.unwrap();
+            let sse_op = match ty {
+                F32 => SseOpcode::Roundss,
+                F64 => SseOpcode::Roundsd,
+                _ => {
+                    return Err(CodegenError::Unsupported(format!(
+                        "{} for type {} in the x64 backend",
+                        op, ty
+                    )))
+                }
+            };
+            // The immediate is the rounding mode (the precision exception isn't masked).
+            let mode = match op {
+                Opcode::Nearest => 0,
+                Opcode::Floor => 1,
+                Opcode::Ceil => 2,
+                Opcode::Trunc => 3,
+                _ => unreachable!(),
+            };
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            ctx.emit(Inst::xmm_rm_r_imm(sse_op, RegMem::reg(src), dst, mode));
+        }
+
+        Opcode::Fpromote | Opcode::Fdemote => {
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let sse_op = match op {
+                Opcode::Fpromote => SseOpcode::Cvtss2sd,
+                Opcode::Fdemote => SseOpcode::Cvtsd2ss,
+                _ => unreachable!(),
+            };
+            ctx.emit(Inst::xmm_mov_rm_r(sse_op, RegMem::reg(src), dst, None));
+        }
+
+        Opcode::FcvtFromSint => {
+            let src = input_to_reg_ext32(ctx, inputs[0], true);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let src_size = if int_ty_is_64(ctx.input_ty(insn, 0)) {
+                8
+            } else {
+                4
+            };
+            let sse_op = if flt_ty_is_64(ty.unwrap()) {
+                SseOpcode::Cvtsi2sd
+            } else {
+                SseOpcode::Cvtsi2ss
+            };
+            ctx.emit(Inst::gpr_to_xmm(sse_op, RegMem::reg(src), src_size, dst));
+        }
+
+        Opcode::FcvtFromUint => {
+            let input_ty = ctx.input_ty(insn, 0);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let to_f64 = flt_ty_is_64(ty.unwrap());
+
+            if int_ty_is_64(input_ty) {
+                // There's no unsigned conversion instruction: a special sequence is used.
+                let src = input_to_reg(ctx, inputs[0]);
+                let src_copy = ctx.alloc_tmp(RegClass::I64, I64);
+                let tmp_gpr1 = ctx.alloc_tmp(RegClass::I64, I64);
+                let tmp_gpr2 = ctx.alloc_tmp(RegClass::I64, I64);
+                ctx.emit(Inst::mov_r_r(true, src, src_copy));
+                ctx.emit(Inst::cvt_u64_to_float_seq(
+                    to_f64, src_copy, tmp_gpr1, tmp_gpr2, dst,
                 ));
-                ctx.emit(Inst::xmm_mov_rm_r(
-                    SseOpcode::Movaps,
-                    RegMem::reg(tmp_xmm1.to_reg()),
+            } else {
+                // Zero-extended narrower values are always positive 64-bit values, so they can
+                // use the signed conversion.
+                let src = input_to_reg(ctx, inputs[0]);
+                let ext_mode = match input_ty.bits() {
+                    8 => ExtMode::BQ,
+                    16 => ExtMode::WQ,
+                    32 => ExtMode::LQ,
+                    _ => unreachable!(),
+                };
+                let tmp = ctx.alloc_tmp(RegClass::I64, I64);
+                ctx.emit(Inst::movzx_rm_r(ext_mode, RegMem::reg(src), tmp, None));
+                let sse_op = if to_f64 {
+                    SseOpcode::Cvtsi2sd
+                } else {
+                    SseOpcode::Cvtsi2ss
+                };
+                ctx.emit(Inst::gpr_to_xmm(sse_op, RegMem::reg(tmp.to_reg()), 8, dst));
+            }
+        }
+
+        Opcode::FcvtToSint | Opcode::FcvtToSintSat | Opcode::FcvtToUint | Opcode::FcvtToUintSat => {
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+
+            let input_ty = ctx.input_ty(insn, 0);
+            let src_is_f64 = flt_ty_is_64(input_ty);
+            let output_ty = ty.unwrap();
+            let dst_size = if int_ty_is_64(output_ty) { 8 } else { 4 };
+            let is_saturating = op == Opcode::FcvtToSintSat || op == Opcode::FcvtToUintSat;
+
+            // The source is modified by the conversion sequences, so copy it first.
+            let src_copy = ctx.alloc_tmp(RegClass::V128, input_ty);
+            ctx.emit(Inst::gen_move(src_copy, src, input_ty));
+
+            let tmp_xmm = ctx.alloc_tmp(RegClass::V128, input_ty);
+            let tmp_gpr = ctx.alloc_tmp(RegClass::I64, output_ty);
+            let srcloc = ctx.srcloc(insn);
+
+            if op == Opcode::FcvtToSint || op == Opcode::FcvtToSintSat {
+                ctx.emit(Inst::cvt_float_to_sint_seq(
+                    src_is_f64,
+                    dst_size,
+                    is_saturating,
+                    src_copy,
                     dst,
+                    tmp_gpr,
+                    tmp_xmm,
+                    srcloc,
                 ));
-                ctx.emit(Inst::xmm_rm_r(SseOpcode::Andnps, RegMem::reg(lhs), dst));
-                ctx.emit(Inst::xmm_mov_rm_r(
-                    SseOpcode::Movss,
-                    RegMem::reg(rhs),
-                    tmp_xmm2,
-                ));
-                ctx.emit(Inst::xmm_rm_r(
-                    SseOpcode::Andps,
-                    RegMem::reg(tmp_xmm1.to_reg()),
-                    tmp_xmm2,
-                ));
-                ctx.emit(Inst::xmm_rm_r(
-                    SseOpcode::Orps,
-                    RegMem::reg(tmp_xmm2.to_reg()),
+            } else {
+                ctx.emit(Inst::cvt_float_to_uint_seq(
+                    src_is_f64,
+                    dst_size,
+                    is_saturating,
+                    src_copy,
                     dst,
+                    tmp_gpr,
+                    tmp_xmm,
+                    srcloc,
                 ));
-            } else {
-                unimplemented!("{:?} for non 32-bit destination is not supported", op);
+            }
+        }
+
+        Opcode::Bitcast => {
+            let input_ty = ctx.input_ty(insn, 0);
+            let output_ty = ctx.output_ty(insn, 0);
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            match (input_ty, output_ty) {
+                (F32, I32) => ctx.emit(Inst::xmm_to_gpr(SseOpcode::Movd, src, dst, 4)),
+                (I32, F32) => ctx.emit(Inst::gpr_to_xmm(SseOpcode::Movd, RegMem::reg(src), 4, dst)),
+                (F64, I64) => ctx.emit(Inst::xmm_to_gpr(SseOpcode::Movq, src, dst, 8)),
+                (I64, F64) => ctx.emit(Inst::gpr_to_xmm(SseOpcode::Movq, RegMem::reg(src), 8, dst)),
+                _ => unreachable!("invalid bitcast from {:?} to {:?}", input_ty, output_ty),
+            }
+        }
+
+        Opcode::RawBitcast => {
+            // Vectors of any lane type share the same register class.
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            ctx.emit(Inst::gen_move(dst, src, ty.unwrap()));
+        }
+
+        Opcode::Splat => {
+            let ty = ty.unwrap();
+            let lane_ty = ty.lane_type();
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            match lane_ty {
+                F32 => {
+                    ctx.emit(Inst::xmm_rm_r_imm(
+                        SseOpcode::Pshufd,
+                        RegMem::reg(src),
+                        dst,
+                        0,
+                    ));
+                }
+                F64 => {
+                    ctx.emit(Inst::gen_move(dst, src, F64));
+                    ctx.emit(Inst::xmm_rm_r(SseOpcode::Punpcklqdq, RegMem::reg(src), dst));
+                }
+                I64 => {
+                    let tmp = ctx.alloc_tmp(RegClass::V128, ty);
+                    ctx.emit(Inst::gpr_to_xmm(SseOpcode::Movq, RegMem::reg(src), 8, tmp));
+                    ctx.emit(Inst::gpr_to_xmm(SseOpcode::Movq, RegMem::reg(src), 8, dst));
+                    ctx.emit(Inst::xmm_rm_r(
+                        SseOpcode::Punpcklqdq,
+                        RegMem::reg(tmp.to_reg()),
+                        dst,
+                    ));
+                }
+                I8 | I16 | I32 => {
+                    // Replicate narrow values in the low 32 bits of a register first, by
+                    // multiplying the zero-extended value with the right constant.
+                    let src = match lane_ty {
+                        I32 => src,
+                        _ => {
+                            let (ext_mode, factor) = if lane_ty == I8 {
+                                (ExtMode::BL, 0x0101_0101)
+                            } else {
+                                (ExtMode::WL, 0x0001_0001)
+                            };
+                            let tmp = ctx.alloc_tmp(RegClass::I64, I32);
+                            ctx.emit(Inst::movzx_rm_r(ext_mode, RegMem::reg(src), tmp, None));
+                            ctx.emit(Inst::alu_rmi_r(
+                                false,
+                                AluRmiROpcode::Mul,
+                                RegMemImm::imm(factor),
+                                tmp,
+                            ));
+                            tmp.to_reg()
+                        }
+                    };
+                    let tmp = ctx.alloc_tmp(RegClass::V128, ty);
+                    ctx.emit(Inst::gpr_to_xmm(SseOpcode::Movd, RegMem::reg(src), 4, tmp));
+                    ctx.emit(Inst::xmm_rm_r_imm(
+                        SseOpcode::Pshufd,
+                        RegMem::reg(tmp.to_reg()),
+                        dst,
+                        0,
+                    ));
+                }
+                _ => {
+                    return Err(CodegenError::Unsupported(format!(
+                        "splat for type {} in the x64 backend",
+                        ty
+                    )))
+                }
+            }
+        }
+
+        Opcode::Extractlane => {
+            let lane = match *ctx.data(insn) {
+                InstructionData::BinaryImm8 { imm, .. } => imm,
+                _ => unreachable!(),
+            };
+            let src_ty = ctx.input_ty(insn, 0);
+            let src = input_to_reg(ctx, inputs[0]);
+            let dst = output_to_reg(ctx, outputs[0]);
+            let ty = ty.unwrap();
+
+            // Move the 32-bits or 64-bits chunk holding the lane into the lowest part of a
+            // register, with pshufd.
+            let lane_bits = ty.bits() as u8;
+            let bit_offset = lane * lane_bits;
+            let chunk_src = |ctx: &mut C, dst: Writable<Reg>| -> Reg {
+                let imm = match lane_bits {
+                    64 => 0b11_10_11_10 * (bit_offset / 64),
+                    _ => bit_offset / 32,
+                };
+                if imm == 0 {
+                    src
+                } else {
+                    ctx.emit(Inst::xmm_rm_r_imm(
+                        SseOpcode::Pshufd,
+                        RegMem::reg(src),
+                        dst,
+                        imm,
+                    ));
+                    dst.to_reg()
+                }
+            };
+
+            match ty {
+                F32 | F64 => {
+                    let chunk = chunk_src(ctx, dst);
+                    if chunk == src {
+                        ctx.emit(Inst::gen_move(dst, src, ty));
+                    }
+                }
+                I8 | I16 | I32 | I64 => {
+                    let tmp = ctx.alloc_tmp(RegClass::V128, src_ty);
+                    let chunk = chunk_src(ctx, tmp);
+                    if lane_bits == 64 {
+                        ctx.emit(Inst::xmm_to_gpr(SseOpcode::Movq, chunk, dst, 8));
+                    } else {
+                        ctx.emit(Inst::xmm_to_gpr(SseOpcode::Movd, chunk, dst, 4));
+                        // The high bits of narrow values are undefined, so only shift the lane
+                        // into place.
+                        let shift = bit_offset % 32;
+                        if shift != 0 {
+                            ctx.emit(Inst::shift_r(false, ShiftKind::RightZ, Some(shift), dst));
+                        }
+                    }
+                }
+                _ => {
+                    return Err(CodegenError::Unsupported(format!(
+                        "extractlane for type {} in the x64 backend",
+                        src_ty
+                    )))
+                }
             }
         }
 
@@ -442,7 +1664,7 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
                 | Opcode::Sload32Complex
                 | Opcode::Uload32Complex => types::I32,
                 Opcode::Load | Opcode::LoadComplex => ctx.output_ty(insn, 0),
-                _ => unimplemented!(),
+                _ => unreachable!(),
             };
 
             let ext_mode = match elem_ty.bytes() {
@@ -462,7 +1684,7 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
                 _ => false,
             };
 
-            let is_float = is_float_ty(elem_ty);
+            let is_xmm = is_float_ty(elem_ty) || elem_ty.is_vector();
 
             let addr = match op {
                 Opcode::Load
@@ -497,23 +1719,56 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
                 _ => unreachable!(),
             };
 
+            let srcloc = if !ctx
+                .memflags(insn)
+                .expect("load should have memflags")
+                .notrap()
+            {
+                Some(ctx.srcloc(insn))
+            } else {
+                None
+            };
+
             let dst = output_to_reg(ctx, outputs[0]);
-            match (sign_extend, is_float) {
+            match (sign_extend, is_xmm) {
                 (true, false) => {
                     // The load is sign-extended only when the output size is lower than 64 bits,
                     // so ext-mode is defined in this case.
-                    ctx.emit(Inst::movsx_rm_r(ext_mode.unwrap(), RegMem::mem(addr), dst));
+                    ctx.emit(Inst::movsx_rm_r(
+                        ext_mode.unwrap(),
+                        RegMem::mem(addr),
+                        dst,
+                        srcloc,
+                    ));
                 }
                 (false, false) => {
                     if elem_ty.bytes() == 8 {
                         // Use a plain load.
-                        ctx.emit(Inst::mov64_m_r(addr, dst))
+                        ctx.emit(Inst::mov64_m_r(addr, dst, srcloc))
                     } else {
                         // Use a zero-extended load.
-                        ctx.emit(Inst::movzx_rm_r(ext_mode.unwrap(), RegMem::mem(addr), dst))
+                        ctx.emit(Inst::movzx_rm_r(
+                            ext_mode.unwrap(),
+                            RegMem::mem(addr),
+                            dst,
+                            srcloc,
+                        ))
                     }
                 }
-                (_, true) => unimplemented!("FPU loads"),
+                (_, true) => {
+                    let sse_op = match elem_ty {
+                        F32 => SseOpcode::Movss,
+                        F64 => SseOpcode::Movsd,
+                        _ if elem_ty.is_vector() && elem_ty.bits() == 128 => SseOpcode::Movdqu,
+                        _ => {
+                            return Err(CodegenError::Unsupported(format!(
+                                "load of type {} in the x64 backend",
+                                elem_ty
+                            )))
+                        }
+                    };
+                    ctx.emit(Inst::xmm_mov_rm_r(sse_op, RegMem::mem(addr), dst, srcloc))
+                }
             }
         }
 
@@ -534,7 +1789,7 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
                 Opcode::Store | Opcode::StoreComplex => ctx.input_ty(insn, 0),
                 _ => unreachable!(),
             };
-            let is_float = is_float_ty(elem_ty);
+            let is_xmm = is_float_ty(elem_ty) || elem_ty.is_vector();
 
             let addr = match op {
                 Opcode::Store | Opcode::Istore8 | Opcode::Istore16 | Opcode::Istore32 => {
@@ -566,10 +1821,31 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
 
             let src = input_to_reg(ctx, inputs[0]);
 
-            if is_float {
-                unimplemented!("FPU stores");
+            let srcloc = if !ctx
+                .memflags(insn)
+                .expect("store should have memflags")
+                .notrap()
+            {
+                Some(ctx.srcloc(insn))
             } else {
-                ctx.emit(Inst::mov_r_m(elem_ty.bytes() as u8, src, addr));
+                None
+            };
+
+            if is_xmm {
+                let sse_op = match elem_ty {
+                    F32 => SseOpcode::Movss,
+                    F64 => SseOpcode::Movsd,
+                    _ if elem_ty.is_vector() && elem_ty.bits() == 128 => SseOpcode::Movdqu,
+                    _ => {
+                        return Err(CodegenError::Unsupported(format!(
+                            "store of type {} in the x64 backend",
+                            elem_ty
+                        )))
+                    }
+                };
+                ctx.emit(Inst::xmm_mov_r_m(sse_op, src, addr, srcloc));
+            } else {
+                ctx.emit(Inst::mov_r_m(elem_ty.bytes() as u8, src, addr, srcloc));
             }
         }
 
@@ -584,13 +1860,20 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
             };
             let dst = output_to_reg(ctx, outputs[0]);
             let offset: i32 = offset.into();
-            println!("stackslot_addr: {:?} @ off{}", stack_slot, offset);
             let inst = ctx
                 .abi()
                 .stackslot_addr(stack_slot, u32::try_from(offset).unwrap(), dst);
             ctx.emit(inst);
         }
 
+        Opcode::Safepoint => {
+            // Stack maps can't be generated by the register allocator yet; functions containing
+            // safepoints are rejected before lowering.
+            return Err(CodegenError::Unsupported(
+                "safepoints in the x64 backend".into(),
+            ));
+        }
+
         Opcode::IaddImm
         | Opcode::ImulImm
         | Opcode::UdivImm
@@ -601,7 +1884,6 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
         | Opcode::IaddCin
         | Opcode::IaddIfcin
         | Opcode::IaddCout
-        | Opcode::IaddIfcout
         | Opcode::IaddCarry
         | Opcode::IaddIfcarry
         | Opcode::IsubBin
@@ -620,7 +1902,12 @@ fn lower_insn_to_regs<C: LowerCtx<I = Inst>>(ctx: &mut C, insn: IRInst) -> Codeg
         | Opcode::SshrImm => {
             panic!("ALU+imm and ALU+carry ops should not appear here!");
         }
-        _ => unimplemented!("unimplemented lowering for opcode {:?}", op),
+        _ => {
+            return Err(CodegenError::Unsupported(format!(
+                "{} in the x64 backend",
+                op
+            )))
+        }
     }
 
     Ok(())
@@ -633,7 +1920,7 @@ impl LowerBackend for X64Backend {
     type MInst = Inst;
 
     fn lower<C: LowerCtx<I = Inst>>(&self, ctx: &mut C, ir_inst: IRInst) -> CodegenResult<()> {
-        lower_insn_to_regs(ctx, ir_inst)
+        lower_insn_to_regs(ctx, ir_inst, &self.flags)
     }
 
     fn lower_branch_group<C: LowerCtx<I = Inst>>(
@@ -672,25 +1959,48 @@ impl LowerBackend for X64Backend {
 
             match op0 {
                 Opcode::Brz | Opcode::Brnz => {
+                    let flag_input = InsnInput {
+                        insn: branches[0],
+                        input: 0,
+                    };
+
                     let src_ty = ctx.input_ty(branches[0], 0);
-                    if is_int_ty(src_ty) || is_bool_ty(src_ty) {
-                        let src = input_to_reg(
-                            ctx,
-                            InsnInput {
-                                insn: branches[0],
-                                input: 0,
-                            },
-                        );
+
+                    if let Some(icmp) = matches_input(ctx, flag_input, Opcode::Icmp) {
+                        let mut cond_code = inst_condcode(ctx.data(icmp));
+                        if op0 == Opcode::Brz {
+                            cond_code = cond_code.inverse();
+                        }
+                        emit_cmp(ctx, icmp);
+                        let cc = CC::from_intcc(cond_code);
+                        ctx.emit(Inst::jmp_cond(cc, taken, not_taken));
+                    } else if let Some(fcmp) = matches_input(ctx, flag_input, Opcode::Fcmp) {
+                        let mut cond_code = inst_fp_condcode(ctx.data(fcmp));
+                        if op0 == Opcode::Brz {
+                            cond_code = cond_code.inverse();
+                        }
+                        let result = emit_fcmp(ctx, fcmp, cond_code);
+                        emit_fcmp_branch(ctx, result, taken, not_taken);
+                    } else if is_int_ty(src_ty) || is_bool_ty(src_ty) {
+                        let src = input_to_reg(ctx, flag_input);
                         let cc = match op0 {
                             Opcode::Brz => CC::Z,
                             Opcode::Brnz => CC::NZ,
                             _ => unreachable!(),
                         };
-                        let size_bytes = src_ty.bytes() as u8;
+                        // Only the low byte of a bool is defined.
+                        let size_bytes = if is_bool_ty(src_ty) {
+                            1
+                        } else {
+                            src_ty.bytes() as u8
+                        };
                         ctx.emit(Inst::cmp_rmi_r(size_bytes, RegMemImm::imm(0), src));
                         ctx.emit(Inst::jmp_cond(cc, taken, not_taken));
                     } else {
-                        unimplemented!("brz/brnz with non-int type {:?}", src_ty);
+                        return Err(CodegenError::Unsupported(format!(
+                            "{} with type {} in the x64 backend",
+                            op0, src_ty
+                        )));
                     }
                 }
 
@@ -718,12 +2028,41 @@ impl LowerBackend for X64Backend {
                         ctx.emit(Inst::cmp_rmi_r(byte_size, rhs, lhs));
                         ctx.emit(Inst::jmp_cond(cc, taken, not_taken));
                     } else {
-                        unimplemented!("bricmp with non-int type {:?}", src_ty);
+                        return Err(CodegenError::Unsupported(format!(
+                            "{} with type {} in the x64 backend",
+                            op0, src_ty
+                        )));
+                    }
+                }
+
+                Opcode::Brif => {
+                    let flag_input = InsnInput {
+                        insn: branches[0],
+                        input: 0,
+                    };
+                    let cc = CC::from_intcc(inst_condcode(ctx.data(branches[0])));
+
+                    if matches_input(ctx, flag_input, Opcode::IaddIfcout).is_none() {
+                        // Verification ensures that the input is always a single-def ifcmp.
+                        let ifcmp = matches_input(ctx, flag_input, Opcode::Ifcmp).unwrap();
+                        emit_cmp(ctx, ifcmp);
                     }
+                    ctx.emit(Inst::jmp_cond(cc, taken, not_taken));
+                }
+
+                Opcode::Brff => {
+                    let flag_input = InsnInput {
+                        insn: branches[0],
+                        input: 0,
+                    };
+                    let cond_code = inst_fp_condcode(ctx.data(branches[0]));
+                    // Verification ensures that the input is always a single-def ffcmp.
+                    let ffcmp = matches_input(ctx, flag_input, Opcode::Ffcmp).unwrap();
+                    let result = emit_fcmp(ctx, ffcmp, cond_code);
+                    emit_fcmp_branch(ctx, result, taken, not_taken);
                 }
 
-                // TODO: Brif/icmp, Brff/icmp, jump tables
-                _ => unimplemented!("branch opcode"),
+                _ => panic!("unexpected branch opcode: {:?}", op0),
             }
         } else {
             assert!(branches.len() == 1);
@@ -734,7 +2073,59 @@ impl LowerBackend for X64Backend {
                 Opcode::Jump | Opcode::Fallthrough => {
                     ctx.emit(Inst::jmp_known(BranchTarget::Label(targets[0])));
                 }
-                _ => panic!("Unknown branch type!"),
+
+                Opcode::BrTable => {
+                    let jt_size = targets.len() - 1;
+                    assert!(jt_size <= u32::max_value() as usize);
+                    let jt_size = jt_size as u32;
+
+                    let idx = input_to_reg(
+                        ctx,
+                        InsnInput {
+                            insn: branches[0],
+                            input: 0,
+                        },
+                    );
+
+                    // Bounds-check (compute flags from idx - jt_size) and branch to default.
+                    ctx.emit(Inst::cmp_rmi_r(4, RegMemImm::imm(jt_size), idx));
+
+                    // Emit the compound instruction that does:
+                    //
+                    // jnb $default_target
+                    // movl %idx, %tmp2
+                    // lea start_of_jump_table_offset(%rip), %tmp1
+                    // movslq [%tmp1, %tmp2, 4], %tmp2 ;; shift of 2, viz. multiply index by 4
+                    // addq %tmp2, %tmp1
+                    // j *%tmp1
+                    // $start_of_jump_table:
+                    // -- jump table entries
+
+                    // Temporaries, to hold the jump table address and the jump table entry.
+                    let tmp1 = ctx.alloc_tmp(RegClass::I64, I64);
+                    let tmp2 = ctx.alloc_tmp(RegClass::I64, I64);
+
+                    // targets[0] is the default target; the jump table entries follow.
+                    let targets_for_term: Vec<MachLabel> = targets.to_vec();
+                    let default_target = BranchTarget::Label(targets[0]);
+
+                    let jt_targets: Vec<BranchTarget> = targets
+                        .iter()
+                        .skip(1)
+                        .map(|bix| BranchTarget::Label(*bix))
+                        .collect();
+
+                    ctx.emit(Inst::JmpTableSeq {
+                        idx,
+                        tmp1,
+                        tmp2,
+                        default_target,
+                        targets: jt_targets,
+                        targets_for_term,
+                    });
+                }
+
+                _ => panic!("Unknown branch type {:?}", op),
             }
         }
 
@@ -10,7 +10,7 @@ use crate::ir::Function;
 use crate::isa::Builder as IsaBuilder;
 use crate::machinst::pretty_print::ShowWithRRU;
 use crate::machinst::{compile, MachBackend, MachCompileResult, TargetIsaAdapter, VCode};
-use crate::result::CodegenResult;
+use crate::result::{CodegenError, CodegenResult};
 use crate::settings::{self, Flags};
 
 use crate::isa::x64::inst::regs::create_reg_universe_systemv;
@@ -38,6 +38,19 @@ impl X64Backend {
     }
 
     fn compile_vcode(&self, func: &Function, flags: Flags) -> CodegenResult<VCode<inst::Inst>> {
+        // The register allocator can't produce stack maps yet, so live references couldn't be
+        // reported to the runtime at safepoints.
+        if flags.enable_safepoints()
+            && func
+                .dfg
+                .values()
+                .any(|value| func.dfg.value_type(value).is_ref())
+        {
+            return Err(CodegenError::Unsupported(
+                "reference types with safepoints in the x64 backend".into(),
+            ));
+        }
+
         // This performs lowering to VCode, register-allocates the code, computes
         // block layout and finalizes branches. The result is ready for binary emission.
         let abi = Box::new(abi::X64ABIBody::new(&func, flags)?);
@@ -87,15 +100,15 @@ impl MachBackend for X64Backend {
     }
 
     fn unsigned_add_overflow_condition(&self) -> IntCC {
-        // Unsigned `>=`; this corresponds to the carry flag set on x86, which happens on
+        // Unsigned `<`; this corresponds to the carry flag set on x86, which happens on
         // overflow of an add.
-        IntCC::UnsignedGreaterThanOrEqual
+        IntCC::UnsignedLessThan
     }
 
     fn unsigned_sub_overflow_condition(&self) -> IntCC {
-        // unsigned `>=`; this corresponds to the carry flag set on x86, which happens on
+        // unsigned `<`; this corresponds to the carry flag set on x86, which happens on
         // underflow of a subtract (carry is borrow for subtract).
-        IntCC::UnsignedGreaterThanOrEqual
+        IntCC::UnsignedLessThan
     }
 }
 
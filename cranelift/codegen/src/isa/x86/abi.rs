@@ -15,7 +15,7 @@ use crate::ir::{
 };
 use crate::isa::{CallConv, RegClass, RegUnit, TargetIsa};
 use crate::regalloc::RegisterSet;
-use crate::result::CodegenResult;
+use crate::result::{CodegenError, CodegenResult};
 use crate::stack_layout::layout_stack;
 use alloc::borrow::Cow;
 use core::i32;
@@ -483,9 +483,11 @@ fn callee_saved_regs_used(isa: &dyn TargetIsa, func: &ir::Function) -> RegisterS
 }
 
 pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    check_tail_calls(func)?;
+
     match func.signature.call_conv {
-        // For now, just translate fast and cold as system_v.
-        CallConv::Fast | CallConv::Cold | CallConv::SystemV => {
+        // For now, just translate fast, cold and tail as system_v.
+        CallConv::Fast | CallConv::Cold | CallConv::SystemV | CallConv::Tail => {
             system_v_prologue_epilogue(func, isa)
         }
         CallConv::WindowsFastcall => fastcall_prologue_epilogue(func, isa),
@@ -496,6 +498,33 @@ pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> Codege
     }
 }
 
+/// Check that the tail calls of `func` can reuse its stack frame: the epilogue is inserted
+/// before them, so the callee can't receive arguments on the stack.
+fn check_tail_calls(func: &ir::Function) -> CodegenResult<()> {
+    for block in func.layout.blocks() {
+        let inst = match func.layout.last_inst(block) {
+            Some(inst) if func.dfg[inst].opcode().is_tail_call() => inst,
+            _ => continue,
+        };
+        if func.signature.call_conv.extends_baldrdash() {
+            return Err(CodegenError::Unsupported(
+                "tail calls with the Baldrdash calling convention".into(),
+            ));
+        }
+        let sig_ref = func.dfg.call_signature(inst).unwrap();
+        if func.dfg.signatures[sig_ref]
+            .params
+            .iter()
+            .any(|param| param.location.is_stack())
+        {
+            return Err(CodegenError::Unsupported(
+                "tail calls to functions with stack arguments".into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
 fn baldrdash_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
     debug_assert!(
         !isa.flags().enable_probestack(),
@@ -570,7 +599,7 @@ fn fastcall_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> C
 
     // Add CSRs to function signature
     let reg_type = isa.pointer_type();
-    let sp_arg_index = if fpsr_stack_size > 0 {
+    let sp_arg_index = if fpsr_stack_size > 0 || uses_inline_probestack(isa, local_stack_size) {
         let sp_arg = ir::AbiParam::special_reg(
             reg_type,
             ir::ArgumentPurpose::CalleeSaved,
@@ -658,7 +687,9 @@ fn system_v_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> C
     let reg_type = ir::Type::int(u16::from(pointer_width.bits())).unwrap();
     // On X86-32 all parameters, including vmctx, are passed on stack, and we need
     // to extract vmctx from the stack before we can save the frame pointer.
-    let sp_arg_index = if isa.pointer_bits() == 32 {
+    // Inline stack probes also need the stack pointer to touch each page of the frame.
+    let sp_arg_index = if isa.pointer_bits() == 32 || uses_inline_probestack(isa, local_stack_size)
+    {
         let sp_arg = ir::AbiParam::special_reg(
             reg_type,
             ir::ArgumentPurpose::CalleeSaved,
@@ -775,7 +806,15 @@ fn insert_common_prologue(
     // Allocate stack frame storage.
     let mut adjust_sp_inst = None;
     if stack_size > 0 {
-        if isa.flags().enable_probestack() && stack_size > (1 << isa.flags().probestack_size_log2())
+        if uses_inline_probestack(isa, stack_size) {
+            adjust_sp_inst = Some(insert_inline_probestack(
+                pos,
+                stack_size,
+                sp.expect("inline stack probes require SP param"),
+                isa,
+            ));
+        } else if isa.flags().enable_probestack()
+            && stack_size > (1 << isa.flags().probestack_size_log2())
         {
             // Emit a stack probe.
             let rax = RU::rax as RegUnit;
@@ -848,6 +887,49 @@ fn insert_common_prologue(
     );
 }
 
+/// Returns whether a frame of `stack_size` bytes is probed with inline stack probes rather than a
+/// call to the probestack function.
+fn uses_inline_probestack(isa: &dyn TargetIsa, stack_size: i64) -> bool {
+    isa.flags().enable_probestack()
+        && isa.flags().probestack_strategy() == shared_settings::ProbestackStrategy::Inline
+        && stack_size > (1 << isa.flags().probestack_size_log2())
+}
+
+/// The largest number of guard-sized pages whose inline stack probes are unrolled; larger frames
+/// are probed in a loop, keeping prologues short.
+const PROBESTACK_UNROLL_PAGES: i64 = 3;
+
+/// Allocate `stack_size` bytes of stack at most one guard-sized page at a time, touching the stack
+/// after every step so that the guard page is hit before anything beyond it.
+///
+/// The stack pointer itself is what gets stored to each page. Up to `PROBESTACK_UNROLL_PAGES`
+/// pages, the sequence is unrolled; beyond that, the whole pages are probed by a loop in 64-bit
+/// mode, and only the remainder is unrolled. Returns the last instruction of the sequence.
+fn insert_inline_probestack(
+    pos: &mut EncCursor,
+    stack_size: i64,
+    sp: ir::Value,
+    isa: &dyn TargetIsa,
+) -> ir::Inst {
+    let page_size = 1 << isa.flags().probestack_size_log2();
+
+    let mut remaining = stack_size;
+    let mut last_inst = None;
+    let pages = stack_size / page_size;
+    if pages > PROBESTACK_UNROLL_PAGES && isa.pointer_bits() == 64 {
+        last_inst = Some(pos.ins().x86_probestack_loop(Imm64::new(pages * page_size)));
+        remaining -= pages * page_size;
+    }
+    while remaining > 0 {
+        let step = core::cmp::min(remaining, page_size);
+        pos.ins().adjust_sp_down_imm(Imm64::new(step));
+        last_inst = Some(pos.ins().store(ir::MemFlags::trusted(), sp, sp, 0));
+        remaining -= step;
+    }
+
+    last_inst.expect("inline stack probes require a non-empty frame")
+}
+
 /// Inserts code necessary to calculate `gv`.
 ///
 /// Note that this is typically done with `ins().global_value(...)` but that
@@ -967,7 +1049,7 @@ fn insert_stack_check(pos: &mut EncCursor, stack_size: i64, stack_limit_arg: ir:
     );
 }
 
-/// Find all `return` instructions and insert epilogues before them.
+/// Find all `return` and tail call instructions and insert epilogues before them.
 fn insert_common_epilogues(
     pos: &mut EncCursor,
     stack_size: i64,
@@ -978,14 +1060,15 @@ fn insert_common_epilogues(
     while let Some(block) = pos.next_block() {
         pos.goto_last_inst(block);
         if let Some(inst) = pos.current_inst() {
-            if pos.func.dfg[inst].opcode().is_return() {
+            let opcode = pos.func.dfg[inst].opcode();
+            if opcode.is_return() || opcode.is_tail_call() {
                 insert_common_epilogue(inst, stack_size, pos, reg_type, csrs, sp_arg_index);
             }
         }
     }
 }
 
-/// Insert an epilogue given a specific `return` instruction.
+/// Insert an epilogue given a specific `return` or tail call instruction.
 /// This is used by common calling conventions such as System V.
 fn insert_common_epilogue(
     inst: ir::Inst,
@@ -995,11 +1078,17 @@ fn insert_common_epilogue(
     csrs: &RegisterSet,
     sp_arg_index: Option<usize>,
 ) {
+    // The restored registers are used by returns, but tail calls must keep matching the
+    // callee's signature.
+    let is_return = pos.func.dfg[inst].opcode().is_return();
+
     // Insert the pop of the frame pointer
     let fp_pop = pos.ins().x86_pop(reg_type);
     let fp_pop_inst = pos.prev_inst().unwrap();
     pos.func.locations[fp_pop] = ir::ValueLoc::Reg(RU::rbp as RegUnit);
-    pos.func.dfg.append_inst_arg(inst, fp_pop);
+    if is_return {
+        pos.func.dfg.append_inst_arg(inst, fp_pop);
+    }
 
     // Insert the CSR pops
     let mut first_csr_pop_inst = None;
@@ -1008,7 +1097,9 @@ fn insert_common_epilogue(
         first_csr_pop_inst = pos.prev_inst();
         assert!(first_csr_pop_inst.is_some());
         pos.func.locations[csr_pop] = ir::ValueLoc::Reg(reg);
-        pos.func.dfg.append_inst_arg(inst, csr_pop);
+        if is_return {
+            pos.func.dfg.append_inst_arg(inst, csr_pop);
+        }
     }
 
     // Insert the adjustment of SP
@@ -1042,7 +1133,9 @@ fn insert_common_epilogue(
             first_fpr_load.get_or_insert(pos.current_inst().expect("current inst"));
 
             pos.func.locations[value] = ir::ValueLoc::Reg(reg);
-            pos.func.dfg.append_inst_arg(inst, value);
+            if is_return {
+                pos.func.dfg.append_inst_arg(inst, value);
+            }
         }
     } else {
         assert!(csrs.iter(FPR).len() == 0);
@@ -1066,7 +1159,7 @@ pub fn create_unwind_info(
     // Assumption: RBP is being used as the frame pointer for both calling conventions
     // In the future, we should be omitting frame pointer as an optimization, so this will change
     Ok(match func.signature.call_conv {
-        CallConv::Fast | CallConv::Cold | CallConv::SystemV => {
+        CallConv::Fast | CallConv::Cold | CallConv::SystemV | CallConv::Tail => {
             super::unwind::systemv::create_unwind_info(func, isa, Some(RU::rbp.into()))?
                 .map(|u| UnwindInfo::SystemV(u))
         }
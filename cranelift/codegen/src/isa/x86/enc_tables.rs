@@ -596,6 +596,79 @@ fn expand_minmax(
     cfg.recompute_block(pos.func, done);
 }
 
+/// x86 has no instructions returning the old value for most atomic read-modify-write operations,
+/// so we expand them to a compare-and-swap loop.
+fn expand_atomic_rmw(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    let (flags, op, p, x) = match func.dfg[inst] {
+        ir::InstructionData::AtomicRmw {
+            opcode: ir::Opcode::AtomicRmw,
+            flags,
+            op,
+            args,
+        } => (flags, op, args[0], args[1]),
+        _ => panic!("Expected atomic_rmw: {}", func.dfg.display_inst(inst, None)),
+    };
+    let old_block = func.layout.pp_block(inst);
+
+    // The expansion is:
+    //
+    //     v0 = load p
+    //     jump retry(v0)
+    //
+    // retry(old):
+    //     new = op old, x
+    //     actual = atomic_cas p, old, new
+    //     ok = icmp eq actual, old
+    //     brz ok, retry(actual)
+    //     jump done(old)
+    //
+    // done(result):
+    //
+    // The initial load doesn't need to be atomic, since the compare-and-swap checks its value.
+    let retry = func.dfg.make_block();
+    let done = func.dfg.make_block();
+
+    // Move the `inst` result value onto the `done` block.
+    let result = func.dfg.first_result(inst);
+    let ty = func.dfg.value_type(result);
+    func.dfg.clear_results(inst);
+    func.dfg.attach_block_param(done, result);
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+    let initial = pos.ins().load(ty, flags, p, 0);
+    pos.ins().jump(retry, &[initial]);
+
+    pos.insert_block(retry);
+    let old = pos.func.dfg.append_block_param(retry, ty);
+    let new = match op {
+        ir::AtomicRmwOp::Add => pos.ins().iadd(old, x),
+        ir::AtomicRmwOp::Sub => pos.ins().isub(old, x),
+        ir::AtomicRmwOp::And => pos.ins().band(old, x),
+        ir::AtomicRmwOp::Or => pos.ins().bor(old, x),
+        ir::AtomicRmwOp::Xor => pos.ins().bxor(old, x),
+        ir::AtomicRmwOp::Xchg => x,
+    };
+    let actual = pos.ins().atomic_cas(flags, p, old, new);
+    let ok = pos.ins().icmp(IntCC::Equal, actual, old);
+    pos.ins().brz(ok, retry, &[actual]);
+    // Recycle the original instruction as a jump.
+    pos.func.dfg.replace(inst).jump(done, &[old]);
+
+    // Finally insert a label for the completion.
+    pos.next_inst();
+    pos.insert_block(done);
+
+    cfg.recompute_block(pos.func, old_block);
+    cfg.recompute_block(pos.func, retry);
+    cfg.recompute_block(pos.func, done);
+}
+
 /// x86 has no unsigned-to-float conversions. We handle the easy case of zero-extending i32 to
 /// i64 with a pattern, the rest needs more code.
 ///
@@ -102,6 +102,7 @@ struct InstructionBuilder<'a> {
     instructions: Vec<(u32, CallFrameInstruction)>,
     stack_size: Option<i32>,
     epilogue_pop_offsets: Vec<u32>,
+    epilogue_pop_values: Vec<Value>,
 }
 
 impl<'a> InstructionBuilder<'a> {
@@ -114,6 +115,7 @@ impl<'a> InstructionBuilder<'a> {
             instructions: Vec::new(),
             stack_size: None,
             epilogue_pop_offsets: Vec::new(),
+            epilogue_pop_values: Vec::new(),
         }
     }
 
@@ -211,8 +213,20 @@ impl<'a> InstructionBuilder<'a> {
     }
 
     fn ret(&mut self, inst: Inst) -> Result<(), RegisterMappingError> {
-        let args = self.func.dfg.inst_args(inst);
+        let func = self.func;
+        self.restore_popped_regs(func.dfg.inst_args(inst))
+    }
+
+    fn tail_call(&mut self) -> Result<(), RegisterMappingError> {
+        // Tail calls don't take the restored registers as arguments, so use the popped values.
+        // They're in the order of the pops, while a return's arguments are in the order of the
+        // pushes, which is what `restore_popped_regs` expects.
+        let mut values = core::mem::replace(&mut self.epilogue_pop_values, Vec::new());
+        values.reverse();
+        self.restore_popped_regs(&values)
+    }
 
+    fn restore_popped_regs(&mut self, args: &[Value]) -> Result<(), RegisterMappingError> {
         for (i, arg) in args.iter().rev().enumerate() {
             // Only walk back the args for the pop instructions encountered
             if i >= self.epilogue_pop_offsets.len() {
@@ -252,12 +266,15 @@ impl<'a> InstructionBuilder<'a> {
         }
 
         self.epilogue_pop_offsets.clear();
+        self.epilogue_pop_values.clear();
 
         Ok(())
     }
 
-    fn insert_pop_offset(&mut self, offset: u32) {
+    fn insert_pop(&mut self, offset: u32, inst: Inst) {
         self.epilogue_pop_offsets.push(offset);
+        self.epilogue_pop_values
+            .push(self.func.dfg.first_result(inst));
     }
 
     fn remember_state(&mut self, offset: u32) {
@@ -286,7 +303,7 @@ pub(crate) fn create_unwind_info(
 ) -> CodegenResult<Option<UnwindInfo>> {
     // Only System V-like calling conventions are supported
     match func.signature.call_conv {
-        CallConv::Fast | CallConv::Cold | CallConv::SystemV => {}
+        CallConv::Fast | CallConv::Cold | CallConv::SystemV | CallConv::Tail => {}
         _ => return Ok(None),
     }
 
@@ -344,7 +361,7 @@ pub(crate) fn create_unwind_info(
                 }
                 InstructionData::NullAry { opcode } => match opcode {
                     Opcode::X86Pop => {
-                        builder.insert_pop_offset(offset);
+                        builder.insert_pop(offset, inst);
                     }
                     _ => {}
                 },
@@ -352,7 +369,7 @@ pub(crate) fn create_unwind_info(
                     Opcode::Iconst => {
                         builder.prologue_imm_const(imm.into());
                     }
-                    Opcode::AdjustSpDownImm => {
+                    Opcode::AdjustSpDownImm | Opcode::X86ProbestackLoop => {
                         builder.adjust_sp_down_imm(offset, imm.into());
                     }
                     Opcode::AdjustSpUpImm => {
@@ -374,6 +391,24 @@ pub(crate) fn create_unwind_info(
                     }
                     _ => {}
                 },
+                InstructionData::Call {
+                    opcode: Opcode::ReturnCall,
+                    ..
+                }
+                | InstructionData::CallIndirect {
+                    opcode: Opcode::ReturnCallIndirect,
+                    ..
+                } => {
+                    builder
+                        .tail_call()
+                        .map_err(CodegenError::RegisterMappingError)?;
+
+                    if !is_last_block {
+                        builder.restore_state(offset);
+                    }
+
+                    in_epilogue = false;
+                }
                 _ => {}
             };
         }
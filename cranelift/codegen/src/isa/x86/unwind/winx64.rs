@@ -98,6 +98,15 @@ pub(crate) fn create_unwind_info(
                             size: imm as u32,
                         });
                     }
+                    Opcode::X86ProbestackLoop => {
+                        let imm: i64 = imm.into();
+                        assert!(imm <= core::u32::MAX as i64);
+
+                        unwind_codes.push(UnwindCode::StackAlloc {
+                            offset: unwind_offset,
+                            size: imm as u32,
+                        });
+                    }
                     _ => {}
                 }
             }
@@ -1,15 +1,17 @@
 //! Legalization of calls.
 //!
 //! This module exports the `expand_call` function which transforms a `call`
-//! instruction into `func_addr` and `call_indirect` instructions.
+//! or `return_call` instruction into `func_addr` and `call_indirect` or
+//! `return_call_indirect` instructions.
 
 use crate::cursor::{Cursor, FuncCursor};
 use crate::flowgraph::ControlFlowGraph;
 use crate::ir::{self, InstBuilder};
 use crate::isa::TargetIsa;
 
-/// Expand a `call` instruction. This lowers it to a `call_indirect`, which
-/// is only done if the ABI doesn't support direct calls.
+/// Expand a `call` or `return_call` instruction. This lowers it to a
+/// `call_indirect` or `return_call_indirect`, which is only done if the ABI
+/// doesn't support direct calls.
 pub fn expand_call(
     inst: ir::Inst,
     func: &mut ir::Function,
@@ -17,14 +19,14 @@ pub fn expand_call(
     isa: &dyn TargetIsa,
 ) {
     // Unpack the instruction.
-    let (func_ref, old_args) = match func.dfg[inst] {
+    let (opcode, func_ref, old_args) = match func.dfg[inst] {
         ir::InstructionData::Call {
             opcode,
             ref args,
             func_ref,
         } => {
-            debug_assert_eq!(opcode, ir::Opcode::Call);
-            (func_ref, args.clone())
+            debug_assert!(opcode == ir::Opcode::Call || opcode == ir::Opcode::ReturnCall);
+            (opcode, func_ref, args.clone())
         }
         _ => panic!("Wanted call: {}", func.dfg.display_inst(inst, None)),
     };
@@ -48,7 +50,12 @@ pub fn expand_call(
         );
     }
 
+    let new_opcode = if opcode == ir::Opcode::ReturnCall {
+        ir::Opcode::ReturnCallIndirect
+    } else {
+        ir::Opcode::CallIndirect
+    };
     func.dfg
         .replace(inst)
-        .CallIndirect(ir::Opcode::CallIndirect, ptr_ty, sig, new_args);
+        .CallIndirect(new_opcode, ptr_ty, sig, new_args);
 }
@@ -3,6 +3,7 @@
 use crate::ir::{ArgumentExtension, StackSlot};
 use crate::machinst::*;
 use crate::settings;
+use crate::CodegenResult;
 
 use regalloc::{Reg, Set, SpillSlot, Writable};
 
@@ -113,6 +114,12 @@ pub trait ABIBody {
     /// likely closely related.
     fn gen_epilogue(&self) -> Vec<Self::I>;
 
+    /// Generate the epilogue that precedes a tail call, post-regalloc. This
+    /// restores callee-saved registers and tears down the frame just like
+    /// `gen_epilogue()`, but leaves out the return instruction: the tail call
+    /// itself transfers control.
+    fn gen_tail_call_epilogue(&self) -> Vec<Self::I>;
+
     /// Returns the full frame size for the given function, after prologue emission has run. This
     /// comprises the spill slots and stack-storage slots (but not storage for clobbered callee-save
     /// registers, arguments pushed at callsites within this function, or other ephemeral pushes).
@@ -187,4 +194,15 @@ pub trait ABICall {
     /// This function should only be called once, as it is allowed to re-use
     /// parts of the ABICall object in emitting instructions.
     fn emit_call<C: LowerCtx<I = Self::I>>(&mut self, ctx: &mut C);
+
+    /// Emit a tail call, which reuses the caller's frame and never returns
+    /// to it.
+    ///
+    /// The argument copies must have been emitted beforehand; there is no
+    /// stack pre- or post-adjustment and no return-value copies. The caller's
+    /// epilogue is inserted in front of the returned instruction after
+    /// register allocation. Signatures that pass arguments or return values
+    /// on the stack are rejected, because the caller's frame is gone by the
+    /// time the callee runs.
+    fn emit_tail_call<C: LowerCtx<I = Self::I>>(&mut self, ctx: &mut C) -> CodegenResult<()>;
 }
@@ -861,7 +861,11 @@ impl<'func, I: VCodeInst> LowerCtx for Lower<'func, I> {
             &InstructionData::Load { flags, .. }
             | &InstructionData::LoadComplex { flags, .. }
             | &InstructionData::Store { flags, .. }
-            | &InstructionData::StoreComplex { flags, .. } => Some(flags),
+            | &InstructionData::StoreComplex { flags, .. }
+            | &InstructionData::LoadNoOffset { flags, .. }
+            | &InstructionData::StoreNoOffset { flags, .. }
+            | &InstructionData::AtomicRmw { flags, .. }
+            | &InstructionData::AtomicCas { flags, .. } => Some(flags),
             _ => None,
         }
     }
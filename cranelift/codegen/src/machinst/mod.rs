@@ -245,6 +245,8 @@ pub enum MachTerminator<'a> {
     None,
     /// A return instruction.
     Ret,
+    /// A tail call, which leaves the function after the epilogue has run.
+    TailCall,
     /// An unconditional branch to another block.
     Uncond(MachLabel),
     /// A conditional branch to one of two other blocks.
@@ -168,7 +168,7 @@ impl<I: VCodeInst> VCodeBuilder<I> {
     /// Push an instruction for the current BB and current IR inst within the BB.
     pub fn push(&mut self, insn: I) {
         match insn.is_term() {
-            MachTerminator::None | MachTerminator::Ret => {}
+            MachTerminator::None | MachTerminator::Ret | MachTerminator::TailCall => {}
             MachTerminator::Uncond(target) => {
                 self.vcode.block_succs.push(BlockIx::new(target.get()));
             }
@@ -322,13 +322,21 @@ impl<I: VCodeInst> VCode<I> {
                 };
 
                 // Whenever encountering a return instruction, replace it
-                // with the epilogue.
-                let is_ret = insn.is_term() == MachTerminator::Ret;
-                if is_ret {
+                // with the epilogue. Tail calls keep their jump, but the
+                // frame must be torn down before it.
+                let term = insn.is_term();
+                if term == MachTerminator::Ret {
                     let epilogue = self.abi.gen_epilogue();
                     let len = epilogue.len();
                     final_insns.extend(epilogue.into_iter());
                     final_srclocs.extend(iter::repeat(srcloc).take(len));
+                } else if term == MachTerminator::TailCall {
+                    let epilogue = self.abi.gen_tail_call_epilogue();
+                    let len = epilogue.len();
+                    final_insns.extend(epilogue.into_iter());
+                    final_srclocs.extend(iter::repeat(srcloc).take(len));
+                    final_insns.push(insn.clone());
+                    final_srclocs.push(srcloc);
                 } else {
                     final_insns.push(insn.clone());
                     final_srclocs.push(srcloc);
@@ -382,6 +382,7 @@ regalloc = "backtracking"
 opt_level = "none"
 tls_model = "none"
 libcall_call_conv = "isa_default"
+probestack_strategy = "outline"
 baldrdash_prologue_words = 0
 probestack_size_log2 = 12
 enable_verifier = true
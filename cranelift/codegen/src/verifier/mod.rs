@@ -581,10 +581,13 @@ impl<'a> Verifier<'a> {
         }
 
         let num_fixed_results = inst_data.opcode().constraints().num_fixed_results();
-        // var_results is 0 if we aren't a call instruction
-        let var_results = dfg
-            .call_signature(inst)
-            .map_or(0, |sig| dfg.signatures[sig].returns.len());
+        // var_results is 0 if we aren't a call instruction, or if we are a tail call
+        let var_results = if inst_data.opcode().is_tail_call() {
+            0
+        } else {
+            dfg.call_signature(inst)
+                .map_or(0, |sig| dfg.signatures[sig].returns.len())
+        };
         let total_results = num_fixed_results + var_results;
 
         // All result values for multi-valued instructions are created
@@ -769,6 +772,10 @@ impl<'a> Verifier<'a> {
             | IntSelect { .. }
             | Load { .. }
             | Store { .. }
+            | LoadNoOffset { .. }
+            | StoreNoOffset { .. }
+            | AtomicRmw { .. }
+            | AtomicCas { .. }
             | RegMove { .. }
             | CopySpecial { .. }
             | CopyToSsa { .. }
@@ -1524,6 +1531,30 @@ impl<'a> Verifier<'a> {
                     ));
                 }
             }
+        } else if self.func.dfg[inst].opcode().is_tail_call() {
+            // The callee returns directly to the caller's caller, so it must return the same
+            // values and follow the same calling convention as the calling function.
+            let sig_ref = self.func.dfg.call_signature(inst).unwrap();
+            let callee_sig = &self.func.dfg.signatures[sig_ref];
+            if callee_sig.call_conv != self.func.signature.call_conv {
+                return errors.nonfatal((
+                    inst,
+                    self.context(inst),
+                    format!(
+                        "tail call to a function with calling convention {}, must match {}",
+                        callee_sig.call_conv, self.func.signature.call_conv
+                    ),
+                ));
+            }
+            let callee_returns = callee_sig.returns.iter().map(|a| a.value_type);
+            let expected_returns = self.func.signature.returns.iter().map(|a| a.value_type);
+            if !callee_returns.eq(expected_returns) {
+                return errors.nonfatal((
+                    inst,
+                    self.context(inst),
+                    "return types of tail call must match function signature",
+                ));
+            }
         }
         Ok(())
     }
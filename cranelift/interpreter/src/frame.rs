@@ -1,6 +1,6 @@
 //! Implements a call frame (activation record) for the Cranelift interpreter.
 
-use cranelift_codegen::ir::{Function, Value as ValueRef};
+use cranelift_codegen::ir::{Function, StackSlot, Value as ValueRef};
 use cranelift_reader::DataValue;
 use log::trace;
 use std::collections::HashMap;
@@ -16,6 +16,8 @@ pub struct Frame<'a> {
     pub function: &'a Function,
     /// The current mapping of SSA value-references to their actual values.
     registers: HashMap<ValueRef, DataValue>,
+    /// The addresses at which the function's stack slots have been allocated.
+    stack_slots: HashMap<StackSlot, u64>,
 }
 
 impl<'a> Frame<'a> {
@@ -27,6 +29,7 @@ impl<'a> Frame<'a> {
         Self {
             function,
             registers: HashMap::with_capacity(function.dfg.num_values()),
+            stack_slots: HashMap::new(),
         }
     }
 
@@ -59,19 +62,35 @@ impl<'a> Frame<'a> {
         }
     }
 
-    /// Rename all of the SSA references in `old_names` to those in `new_names`. This will remove
-    /// any old references that are not in `old_names`. TODO This performs an extra allocation that
-    /// could be removed if we copied the values in the right order (i.e. when modifying in place,
-    /// we need to avoid changing a value before it is referenced).
+    /// Record the address at which the stack slot `slot` has been allocated.
+    pub fn set_stack_slot_address(&mut self, slot: StackSlot, address: u64) {
+        trace!("Stack slot {} at {:#x}", slot, address);
+        self.stack_slots.insert(slot, address);
+    }
+
+    /// Retrieve the address of the stack slot `slot`, if it has been allocated.
+    pub fn stack_slot_address(&self, slot: StackSlot) -> Option<u64> {
+        self.stack_slots.get(&slot).cloned()
+    }
+
+    /// Retrieve the addresses of all allocated stack slots, e.g. to free them when the frame is
+    /// popped.
+    pub fn stack_slot_addresses(&self) -> impl Iterator<Item = u64> + '_ {
+        self.stack_slots.values().cloned()
+    }
+
+    /// Rename all of the SSA references in `old_names` to those in `new_names`, e.g. when passing
+    /// arguments to a block. Other references are kept, since they may still be used by the
+    /// blocks they dominate. TODO This performs an extra allocation that could be removed if we
+    /// copied the values in the right order (i.e. when modifying in place, we need to avoid
+    /// changing a value before it is referenced).
     pub fn rename(&mut self, old_names: &[ValueRef], new_names: &[ValueRef]) {
         trace!("Renaming {:?} -> {:?}", old_names, new_names);
         assert_eq!(old_names.len(), new_names.len());
-        let mut registers = HashMap::with_capacity(self.registers.len());
-        for (on, nn) in old_names.iter().zip(new_names) {
-            let v = self.registers.get(on).unwrap().clone();
-            registers.insert(*nn, v);
+        let values = self.get_all(old_names);
+        for (nn, v) in new_names.iter().zip(values) {
+            self.registers.insert(*nn, v);
         }
-        self.registers = registers;
     }
 }
 
@@ -120,4 +139,20 @@ mod tests {
         let a = ValueRef::with_number(1).unwrap();
         frame.get(&a);
     }
+
+    #[test]
+    fn rename() {
+        let func = empty_function();
+        let mut frame = Frame::new(&func);
+
+        let a = ValueRef::with_number(1).unwrap();
+        let b = ValueRef::with_number(2).unwrap();
+        let c = ValueRef::with_number(3).unwrap();
+        frame.set(a, DataValue::I32(1));
+        frame.set(b, DataValue::I32(2));
+        frame.rename(&[a, b], &[b, c]);
+        assert_eq!(frame.get(&a), &DataValue::I32(1));
+        assert_eq!(frame.get(&b), &DataValue::I32(1));
+        assert_eq!(frame.get(&c), &DataValue::I32(2));
+    }
 }
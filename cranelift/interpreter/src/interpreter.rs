@@ -4,15 +4,19 @@
 
 use crate::environment::Environment;
 use crate::frame::Frame;
-use crate::interpreter::Trap::InvalidType;
-use cranelift_codegen::ir::condcodes::IntCC;
+use crate::memory::Memory;
+use crate::value::{
+    as_bool, as_i64, as_u64, from_bytes, from_i64, from_lanes, lanes, max_signed, max_unsigned,
+    min_signed, nearest_f32, nearest_f64, to_bytes,
+};
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
 use cranelift_codegen::ir::{
-    Block, FuncRef, Function, Inst, InstructionData, InstructionData::*, Opcode, Opcode::*, Type,
-    Value as ValueRef, ValueList,
+    types, ArgumentPurpose, Block, FuncRef, Function, GlobalValue, GlobalValueData, Inst,
+    InstructionData, Opcode, TrapCode, Type, Value as ValueRef,
 };
 use cranelift_reader::{DataValue, DataValueCastFailure};
 use log::trace;
-use std::ops::{Add, Div, Mul, Sub};
+use std::cell::RefCell;
 use thiserror::Error;
 
 /// The valid control flow states.
@@ -53,6 +57,10 @@ pub enum Trap {
     InvalidFunctionReference(FuncRef),
     #[error("invalid function name: {0}")]
     InvalidFunctionName(String),
+    #[error("values of type {0} cannot be stored in memory")]
+    InvalidMemoryType(Type),
+    #[error("trap: {0}")]
+    Code(TrapCode),
 }
 
 /// The Cranelift interpreter; it contains immutable elements such as the function environment and
@@ -60,22 +68,18 @@ pub enum Trap {
 #[derive(Default)]
 pub struct Interpreter {
     pub env: Environment,
-}
-
-/// Helper for more concise matching.
-macro_rules! binary_op {
-    ( $op:path[$arg1:ident, $arg2:ident]; [ $( $data_value_ty:ident ),* ]; $inst:ident ) => {
-        match ($arg1, $arg2) {
-            $( (DataValue::$data_value_ty(a), DataValue::$data_value_ty(b)) => { Ok(DataValue::$data_value_ty($op(a, b))) } )*
-            _ => Err(Trap::Unsupported($inst)),
-        }
-    };
+    /// The memory shared by all of the functions called through this interpreter; it holds their
+    /// stack slots and heaps.
+    pub memory: RefCell<Memory>,
 }
 
 impl Interpreter {
     /// Construct a new [Interpreter] using the given [Environment].
     pub fn new(env: Environment) -> Self {
-        Self { env }
+        Self {
+            env,
+            memory: RefCell::new(Memory::default()),
+        }
     }
 
     /// Call a function by name; this is a helpful proxy for [Interpreter::call_by_index].
@@ -114,7 +118,17 @@ impl Interpreter {
         let parameters = function.dfg.block_params(first_block);
         let mut frame = Frame::new(function);
         frame.set_all(parameters, arguments.to_vec());
-        self.block(&mut frame, first_block)
+
+        // The stack slots live as long as the frame does, regardless of how it is exited.
+        for (slot, data) in function.stack_slots.iter() {
+            let address = self.memory.borrow_mut().allocate(data.size as u64);
+            frame.set_stack_slot_address(slot, address);
+        }
+        let result = self.block(&mut frame, first_block);
+        for address in frame.stack_slot_addresses() {
+            self.memory.borrow_mut().free(address);
+        }
+        result
     }
 
     /// Interpret a [Block] in a [Function]. This drives the interpretation over sequences of
@@ -144,168 +158,976 @@ impl Interpreter {
         use ControlFlow::{Continue, ContinueAt};
         trace!("Inst: {}", &frame.function.dfg.display_inst(inst, None));
 
-        let data = &frame.function.dfg[inst];
-        match data {
-            Binary { opcode, args } => {
-                let arg1 = frame.get(&args[0]);
-                let arg2 = frame.get(&args[1]);
-                let result = match opcode {
-                    Iadd => binary_op!(Add::add[arg1, arg2]; [I8, I16, I32, I64]; inst),
-                    Isub => binary_op!(Sub::sub[arg1, arg2]; [I8, I16, I32, I64]; inst),
-                    Imul => binary_op!(Mul::mul[arg1, arg2]; [I8, I16, I32, I64]; inst),
-                    Fadd => binary_op!(Add::add[arg1, arg2]; [F32, F64]; inst),
-                    Fsub => binary_op!(Sub::sub[arg1, arg2]; [F32, F64]; inst),
-                    Fmul => binary_op!(Mul::mul[arg1, arg2]; [F32, F64]; inst),
-                    Fdiv => binary_op!(Div::div[arg1, arg2]; [F32, F64]; inst),
-                    _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
-                }?;
-                frame.set(first_result(frame.function, inst), result);
-                Ok(Continue)
-            }
+        let function = frame.function;
+        let dfg = &function.dfg;
+        let data = &dfg[inst];
+        let arg_refs = dfg.inst_args(inst);
+        let results = dfg.inst_results(inst);
+        let ctrl_ty = dfg.ctrl_typevar(inst);
+        let result_ty = results
+            .first()
+            .map(|r| type_of(*r, function))
+            .unwrap_or(types::INVALID);
+        let unsupported = || Trap::Unsupported(inst);
 
-            BinaryImm64 { opcode, arg, imm } => {
-                let imm = DataValue::from_integer(*imm, type_of(*arg, frame.function))?;
-                let arg = frame.get(&arg);
-                let result = match opcode {
-                    IaddImm => binary_op!(Add::add[arg, imm]; [I8, I16, I32, I64]; inst),
-                    IrsubImm => binary_op!(Sub::sub[imm, arg]; [I8, I16, I32, I64]; inst),
-                    ImulImm => binary_op!(Mul::mul[arg, imm]; [I8, I16, I32, I64]; inst),
-                    _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
-                }?;
-                frame.set(first_result(frame.function, inst), result);
-                Ok(Continue)
+        // Instructions with an integer immediate are interpreted as their non-immediate
+        // counterparts, with the immediate converted to the type of the other operand.
+        let (opcode, args, arg_tys) = match *data {
+            InstructionData::BinaryImm64 { opcode, arg, imm }
+            | InstructionData::IntCompareImm {
+                opcode, arg, imm, ..
+            } => {
+                let ty = type_of(arg, function);
+                let imm = DataValue::from_integer(imm, ty)?;
+                let arg = frame.get(&arg).clone();
+                let (opcode, args) = match opcode {
+                    Opcode::IrsubImm => (Opcode::Isub, vec![imm, arg]),
+                    _ => (
+                        non_imm_opcode(opcode).ok_or_else(unsupported)?,
+                        vec![arg, imm],
+                    ),
+                };
+                (opcode, args, vec![ty, ty])
             }
+            _ => (
+                data.opcode(),
+                frame.get_all(arg_refs),
+                arg_refs.iter().map(|r| type_of(*r, function)).collect(),
+            ),
+        };
 
-            Branch {
-                opcode,
-                args,
-                destination,
-            } => match opcode {
-                Brnz => {
-                    let mut args = value_refs(frame.function, args);
-                    let first = args.remove(0);
-                    match frame.get(&first) {
-                        DataValue::B(false)
-                        | DataValue::I8(0)
-                        | DataValue::I16(0)
-                        | DataValue::I32(0)
-                        | DataValue::I64(0) => Ok(Continue),
-                        DataValue::B(true)
-                        | DataValue::I8(_)
-                        | DataValue::I16(_)
-                        | DataValue::I32(_)
-                        | DataValue::I64(_) => Ok(ContinueAt(*destination, args)),
-                        _ => Err(Trap::InvalidType("boolean or integer".to_string(), args[0])),
-                    }
+        // Assign the single result of the instruction.
+        let assign = |frame: &mut Frame, value: Option<DataValue>| -> Result<ControlFlow, Trap> {
+            frame.set(first_result(function, inst), value.ok_or_else(unsupported)?);
+            Ok(Continue)
+        };
+        let unsigned = |i: usize| as_u64(&args[i]).ok_or_else(unsupported);
+        let boolean = |i: usize| {
+            as_bool(&args[i])
+                .ok_or_else(|| Trap::InvalidType("boolean or integer".to_string(), arg_refs[i]))
+        };
+
+        match opcode {
+            // Control flow.
+            Opcode::Jump | Opcode::Fallthrough => {
+                let destination = data.branch_destination().ok_or_else(unsupported)?;
+                Ok(ContinueAt(destination, arg_refs.to_vec()))
+            }
+            Opcode::Brz | Opcode::Brnz => {
+                if boolean(0)? == (opcode == Opcode::Brnz) {
+                    let destination = data.branch_destination().ok_or_else(unsupported)?;
+                    Ok(ContinueAt(destination, arg_refs[1..].to_vec()))
+                } else {
+                    Ok(Continue)
+                }
+            }
+            Opcode::BrIcmp => {
+                let cond = int_cond_of(data).ok_or_else(unsupported)?;
+                if icmp(cond, &args[0], &args[1]).ok_or_else(unsupported)? {
+                    let destination = data.branch_destination().ok_or_else(unsupported)?;
+                    Ok(ContinueAt(destination, arg_refs[2..].to_vec()))
+                } else {
+                    Ok(Continue)
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+            }
+            Opcode::BrTable => match *data {
+                InstructionData::BranchTable {
+                    destination, table, ..
+                } => {
+                    let index = unsigned(0)?;
+                    let block = function.jump_tables[table]
+                        .as_slice()
+                        .get(index as usize)
+                        .cloned()
+                        .unwrap_or(destination);
+                    Ok(ContinueAt(block, vec![]))
+                }
+                _ => Err(unsupported()),
             },
-            InstructionData::Call { args, func_ref, .. } => {
-                // Find the function to call.
-                let func_name = function_name_of_func_ref(*func_ref, frame.function);
+            Opcode::Return | Opcode::FallthroughReturn => Ok(ControlFlow::Return(args)),
 
-                // Call function.
-                let args = frame.get_all(args.as_slice(&frame.function.dfg.value_lists));
+            // Calls.
+            Opcode::Call => {
+                let func_ref = match *data {
+                    InstructionData::Call { func_ref, .. } => func_ref,
+                    _ => return Err(unsupported()),
+                };
+                let func_name = function_name_of_func_ref(func_ref, function);
                 let result = self.call_by_name(&func_name, &args)?;
+                self.assign_call_results(frame, inst, result)
+            }
+            Opcode::CallIndirect => {
+                // Function addresses are the indices of the functions in the environment; see
+                // `func_addr`.
+                let func_ref = FuncRef::from_u32(unsigned(0)? as u32);
+                let result = self.call_by_index(func_ref, &args[1..])?;
+                self.assign_call_results(frame, inst, result)
+            }
+            Opcode::FuncAddr => {
+                let func_ref = match *data {
+                    InstructionData::FuncAddr { func_ref, .. } => func_ref,
+                    _ => return Err(unsupported()),
+                };
+                let func_name = function_name_of_func_ref(func_ref, function);
+                let index = self
+                    .env
+                    .index_of(&func_name)
+                    .ok_or_else(|| Trap::InvalidFunctionName(func_name))?;
+                assign(frame, from_i64(result_ty, index.as_u32() as i64))
+            }
 
-                // Save results.
-                if let ControlFlow::Return(returned_values) = result {
-                    let ssa_values = frame.function.dfg.inst_results(inst);
-                    assert_eq!(
-                        ssa_values.len(),
-                        returned_values.len(),
-                        "expected result length ({}) to match SSA values length ({}): {}",
-                        returned_values.len(),
-                        ssa_values.len(),
-                        frame.function.dfg.display_inst(inst, None)
-                    );
-                    frame.set_all(ssa_values, returned_values);
+            // Traps.
+            Opcode::Trap | Opcode::ResumableTrap => {
+                Err(Trap::Code(data.trap_code().ok_or_else(unsupported)?))
+            }
+            Opcode::Trapz | Opcode::Trapnz | Opcode::ResumableTrapnz => {
+                if boolean(0)? == (opcode != Opcode::Trapz) {
+                    Err(Trap::Code(data.trap_code().ok_or_else(unsupported)?))
+                } else {
                     Ok(Continue)
+                }
+            }
+            Opcode::Debugtrap | Opcode::Nop => Ok(Continue),
+
+            // Memory.
+            Opcode::Load
+            | Opcode::LoadComplex
+            | Opcode::Uload8
+            | Opcode::Uload8Complex
+            | Opcode::Sload8
+            | Opcode::Sload8Complex
+            | Opcode::Uload16
+            | Opcode::Uload16Complex
+            | Opcode::Sload16
+            | Opcode::Sload16Complex
+            | Opcode::Uload32
+            | Opcode::Uload32Complex
+            | Opcode::Sload32
+            | Opcode::Sload32Complex
+            | Opcode::Uload8x8
+            | Opcode::Uload8x8Complex
+            | Opcode::Sload8x8
+            | Opcode::Sload8x8Complex
+            | Opcode::Uload16x4
+            | Opcode::Uload16x4Complex
+            | Opcode::Sload16x4
+            | Opcode::Sload16x4Complex
+            | Opcode::Uload32x2
+            | Opcode::Uload32x2Complex
+            | Opcode::Sload32x2
+            | Opcode::Sload32x2Complex => {
+                let address = address_of(&args, offset_of(data).ok_or_else(unsupported)?)
+                    .ok_or_else(unsupported)?;
+                let (memory_ty, signed) = loaded_type(opcode, result_ty);
+                let loaded = self.memory.borrow().load(address, memory_ty)?;
+                let value = if memory_ty == result_ty {
+                    Some(loaded)
+                } else if result_ty.is_vector() {
+                    lanes(memory_ty, &loaded)
+                        .and_then(|ls| {
+                            ls.iter()
+                                .map(|l| extend(result_ty.lane_type(), l, signed))
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .and_then(|ls| from_lanes(result_ty, &ls))
+                } else {
+                    extend(result_ty, &loaded, signed)
+                };
+                assign(frame, value)
+            }
+            Opcode::Store
+            | Opcode::StoreComplex
+            | Opcode::Istore8
+            | Opcode::Istore8Complex
+            | Opcode::Istore16
+            | Opcode::Istore16Complex
+            | Opcode::Istore32
+            | Opcode::Istore32Complex => {
+                let address = address_of(&args[1..], offset_of(data).ok_or_else(unsupported)?)
+                    .ok_or_else(unsupported)?;
+                let memory_ty = match opcode {
+                    Opcode::Istore8 | Opcode::Istore8Complex => types::I8,
+                    Opcode::Istore16 | Opcode::Istore16Complex => types::I16,
+                    Opcode::Istore32 | Opcode::Istore32Complex => types::I32,
+                    _ => arg_tys[0],
+                };
+                let value = if memory_ty == arg_tys[0] {
+                    args[0].clone()
                 } else {
-                    Err(Trap::InvalidControlFlow(format!(
-                        "did not return from: {}",
-                        frame.function.dfg.display_inst(inst, None)
-                    )))
+                    as_i64(&args[0])
+                        .and_then(|x| from_i64(memory_ty, x))
+                        .ok_or_else(unsupported)?
+                };
+                self.memory.borrow_mut().store(address, memory_ty, &value)?;
+                Ok(Continue)
+            }
+            Opcode::StackLoad | Opcode::StackStore | Opcode::StackAddr => {
+                let (slot, offset) = match *data {
+                    InstructionData::StackLoad {
+                        stack_slot, offset, ..
+                    }
+                    | InstructionData::StackStore {
+                        stack_slot, offset, ..
+                    } => (stack_slot, Into::<i64>::into(offset)),
+                    _ => return Err(unsupported()),
+                };
+                let address = frame
+                    .stack_slot_address(slot)
+                    .ok_or_else(unsupported)?
+                    .wrapping_add(offset as u64);
+                match opcode {
+                    Opcode::StackLoad => {
+                        let value = self.memory.borrow().load(address, result_ty)?;
+                        assign(frame, Some(value))
+                    }
+                    Opcode::StackStore => {
+                        self.memory
+                            .borrow_mut()
+                            .store(address, arg_tys[0], &args[0])?;
+                        Ok(Continue)
+                    }
+                    _ => assign(frame, from_i64(result_ty, address as i64)),
                 }
             }
-            InstructionData::Jump {
-                opcode,
-                destination,
-                args,
-            } => match opcode {
-                Opcode::Fallthrough => {
-                    Ok(ContinueAt(*destination, value_refs(frame.function, args)))
+            Opcode::GlobalValue => match *data {
+                InstructionData::UnaryGlobalValue { global_value, .. } => {
+                    let value = self.global_value(frame, global_value, inst)?;
+                    assign(frame, Some(value))
                 }
-                Opcode::Jump => Ok(ContinueAt(*destination, value_refs(frame.function, args))),
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => Err(unsupported()),
             },
-            IntCompareImm {
-                opcode,
-                arg,
-                cond,
-                imm,
-            } => match opcode {
-                IcmpImm => {
-                    let arg_value = match *frame.get(arg) {
-                        DataValue::I8(i) => Ok(i as i64),
-                        DataValue::I16(i) => Ok(i as i64),
-                        DataValue::I32(i) => Ok(i as i64),
-                        DataValue::I64(i) => Ok(i),
-                        _ => Err(InvalidType("integer".to_string(), *arg)),
-                    }?;
-                    let imm_value = (*imm).into();
-                    let result = match cond {
-                        IntCC::UnsignedLessThanOrEqual => arg_value <= imm_value,
-                        IntCC::Equal => arg_value == imm_value,
-                        _ => unimplemented!(
-                            "interpreter does not support condition code yet: {}",
-                            cond
-                        ),
-                    };
-                    let res = first_result(frame.function, inst);
-                    frame.set(res, DataValue::B(result));
-                    Ok(Continue)
+            Opcode::HeapAddr => match *data {
+                InstructionData::HeapAddr { heap, imm, .. } => {
+                    // The heap is modelled as `min_size` bytes of memory, regardless of its base
+                    // global value and style; accesses beyond that trap.
+                    let heap_size: u64 = function.heaps[heap].min_size.into();
+                    let access_size: u32 = imm.into();
+                    let index = unsigned(0)?;
+                    if index.saturating_add(access_size as u64) > heap_size {
+                        return Err(Trap::Code(TrapCode::HeapOutOfBounds));
+                    }
+                    let base = self.memory.borrow_mut().heap(heap, heap_size);
+                    assign(frame, from_i64(result_ty, (base + index) as i64))
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => Err(unsupported()),
             },
-            MultiAry { opcode, args } => match opcode {
-                Return => {
-                    let rs: Vec<DataValue> = args
-                        .as_slice(&frame.function.dfg.value_lists)
-                        .iter()
-                        .map(|r| frame.get(r).clone())
-                        .collect();
-                    Ok(ControlFlow::Return(rs))
+
+            // Constants.
+            Opcode::Iconst => match *data {
+                InstructionData::UnaryImm { imm, .. } => {
+                    assign(frame, Some(DataValue::from_integer(imm, result_ty)?))
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => Err(unsupported()),
             },
-            NullAry { opcode } => match opcode {
-                Nop => Ok(Continue),
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+            Opcode::F32const => match *data {
+                InstructionData::UnaryIeee32 { imm, .. } => {
+                    assign(frame, Some(DataValue::F32(f32::from_bits(imm.bits()))))
+                }
+                _ => Err(unsupported()),
             },
-            UnaryImm { opcode, imm } => match opcode {
-                Iconst => {
-                    let res = first_result(frame.function, inst);
-                    let imm_value = DataValue::from_integer(*imm, type_of(res, frame.function))?;
-                    frame.set(res, imm_value);
-                    Ok(Continue)
+            Opcode::F64const => match *data {
+                InstructionData::UnaryIeee64 { imm, .. } => {
+                    assign(frame, Some(DataValue::F64(f64::from_bits(imm.bits()))))
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => Err(unsupported()),
             },
-            UnaryBool { opcode, imm } => match opcode {
-                Bconst => {
-                    let res = first_result(frame.function, inst);
-                    frame.set(res, DataValue::B(*imm));
-                    Ok(Continue)
+            Opcode::Bconst => match *data {
+                InstructionData::UnaryBool { imm, .. } => assign(frame, Some(DataValue::B(imm))),
+                _ => Err(unsupported()),
+            },
+            Opcode::Vconst => match *data {
+                InstructionData::UnaryConst {
+                    constant_handle, ..
+                } => {
+                    let bytes = dfg.constants.get(constant_handle).iter().cloned();
+                    assign(frame, from_bytes(result_ty, &bytes.collect::<Vec<_>>()))
+                }
+                _ => Err(unsupported()),
+            },
+
+            // Selection.
+            Opcode::Copy => assign(frame, Some(args[0].clone())),
+            Opcode::Select => {
+                let value = if boolean(0)? { &args[1] } else { &args[2] };
+                assign(frame, Some(value.clone()))
+            }
+            Opcode::Vselect => {
+                let value = lanewise(inst, &args, &arg_tys, result_ty, |_, ls| {
+                    let condition = as_bool(&ls[0]).ok_or_else(unsupported)?;
+                    Ok(if condition { &ls[1] } else { &ls[2] }.clone())
+                })?;
+                assign(frame, Some(value))
+            }
+            Opcode::Bitselect => {
+                let value = bitwise(ctrl_ty, &args, |ls| (ls[0] & ls[1]) | (!ls[0] & ls[2]));
+                assign(frame, value)
+            }
+
+            // Bitwise operations work on the in-memory representation of any type.
+            Opcode::Band => assign(frame, bitwise(ctrl_ty, &args, |ls| ls[0] & ls[1])),
+            Opcode::Bor => assign(frame, bitwise(ctrl_ty, &args, |ls| ls[0] | ls[1])),
+            Opcode::Bxor => assign(frame, bitwise(ctrl_ty, &args, |ls| ls[0] ^ ls[1])),
+            Opcode::Bnot => assign(frame, bitwise(ctrl_ty, &args, |ls| !ls[0])),
+            Opcode::BandNot => assign(frame, bitwise(ctrl_ty, &args, |ls| ls[0] & !ls[1])),
+            Opcode::BorNot => assign(frame, bitwise(ctrl_ty, &args, |ls| ls[0] | !ls[1])),
+            Opcode::BxorNot => assign(frame, bitwise(ctrl_ty, &args, |ls| ls[0] ^ !ls[1])),
+
+            // Integer arithmetic.
+            Opcode::Iadd
+            | Opcode::Isub
+            | Opcode::Imul
+            | Opcode::Ineg
+            | Opcode::UaddSat
+            | Opcode::SaddSat
+            | Opcode::UsubSat
+            | Opcode::SsubSat
+            | Opcode::Umulhi
+            | Opcode::Smulhi
+            | Opcode::Udiv
+            | Opcode::Sdiv
+            | Opcode::Urem
+            | Opcode::Srem
+            | Opcode::Imin
+            | Opcode::Umin
+            | Opcode::Imax
+            | Opcode::Umax
+            | Opcode::AvgRound
+            | Opcode::Ishl
+            | Opcode::Ushr
+            | Opcode::Sshr
+            | Opcode::Rotl
+            | Opcode::Rotr
+            | Opcode::Bitrev
+            | Opcode::Clz
+            | Opcode::Cls
+            | Opcode::Ctz
+            | Opcode::Popcnt => {
+                let value = lanewise(inst, &args, &arg_tys, result_ty, |ty, ls| {
+                    integer_op(inst, opcode, ty, ls)
+                })?;
+                assign(frame, Some(value))
+            }
+            Opcode::IaddCin
+            | Opcode::IaddCout
+            | Opcode::IaddCarry
+            | Opcode::IsubBin
+            | Opcode::IsubBout
+            | Opcode::IsubBorrow => {
+                let a = unsigned(0)? as i128;
+                let b = unsigned(1)? as i128;
+                let carry_in = match opcode {
+                    Opcode::IaddCin | Opcode::IaddCarry | Opcode::IsubBin | Opcode::IsubBorrow => {
+                        boolean(2)? as i128
+                    }
+                    _ => 0,
+                };
+                let (result, carry_out) = match opcode {
+                    Opcode::IaddCin | Opcode::IaddCout | Opcode::IaddCarry => {
+                        let result = a + b + carry_in;
+                        (result, result > max_unsigned(ctrl_ty) as i128)
+                    }
+                    _ => {
+                        let result = a - b - carry_in;
+                        (result, result < 0)
+                    }
+                };
+                let result = from_i64(ctrl_ty, result as i64).ok_or_else(unsupported)?;
+                frame.set(results[0], result);
+                if let Some(carry) = results.get(1) {
+                    frame.set(*carry, DataValue::B(carry_out));
+                }
+                Ok(Continue)
+            }
+            Opcode::Isplit => {
+                let half_ty = ctrl_ty.half_width().ok_or_else(unsupported)?;
+                let x = as_i64(&args[0]).ok_or_else(unsupported)?;
+                let lo = from_i64(half_ty, x).ok_or_else(unsupported)?;
+                let hi = from_i64(half_ty, x >> half_ty.bits()).ok_or_else(unsupported)?;
+                frame.set_all(results, vec![lo, hi]);
+                Ok(Continue)
+            }
+            Opcode::Iconcat => {
+                let lo = unsigned(0)?;
+                let hi = unsigned(1)?;
+                let x = lo | hi.checked_shl(arg_tys[0].bits() as u32).unwrap_or(0);
+                assign(frame, from_i64(result_ty, x as i64))
+            }
+
+            // Comparisons.
+            Opcode::Icmp => {
+                let cond = int_cond_of(data).ok_or_else(unsupported)?;
+                let value = lanewise(inst, &args, &arg_tys, result_ty, |_, ls| {
+                    icmp(cond, &ls[0], &ls[1])
+                        .map(DataValue::B)
+                        .ok_or_else(unsupported)
+                })?;
+                assign(frame, Some(value))
+            }
+            Opcode::Fcmp => {
+                let cond = match *data {
+                    InstructionData::FloatCompare { cond, .. } => cond,
+                    _ => return Err(unsupported()),
+                };
+                let value = lanewise(inst, &args, &arg_tys, result_ty, |_, ls| {
+                    fcmp(cond, &ls[0], &ls[1])
+                        .map(DataValue::B)
+                        .ok_or_else(unsupported)
+                })?;
+                assign(frame, Some(value))
+            }
+
+            // Floating point arithmetic.
+            Opcode::Fadd
+            | Opcode::Fsub
+            | Opcode::Fmul
+            | Opcode::Fdiv
+            | Opcode::Sqrt
+            | Opcode::Fma
+            | Opcode::Fneg
+            | Opcode::Fabs
+            | Opcode::Fcopysign
+            | Opcode::Fmin
+            | Opcode::Fmax
+            | Opcode::Ceil
+            | Opcode::Floor
+            | Opcode::Trunc
+            | Opcode::Nearest => {
+                let value = lanewise(inst, &args, &arg_tys, result_ty, |_, ls| {
+                    float_op(inst, opcode, ls)
+                })?;
+                assign(frame, Some(value))
+            }
+
+            // Conversions.
+            Opcode::Bint
+            | Opcode::Bmask
+            | Opcode::Breduce
+            | Opcode::Bextend
+            | Opcode::Ireduce
+            | Opcode::Uextend
+            | Opcode::Sextend
+            | Opcode::Fpromote
+            | Opcode::Fdemote
+            | Opcode::FcvtToUint
+            | Opcode::FcvtToUintSat
+            | Opcode::FcvtToSint
+            | Opcode::FcvtToSintSat
+            | Opcode::FcvtFromUint
+            | Opcode::FcvtFromSint => {
+                let value = lanewise(inst, &args, &arg_tys, result_ty, |ty, ls| {
+                    convert(inst, opcode, ty, &ls[0])
+                })?;
+                assign(frame, Some(value))
+            }
+            Opcode::Bitcast | Opcode::RawBitcast => {
+                let value = to_bytes(arg_tys[0], &args[0]).and_then(|b| from_bytes(result_ty, &b));
+                assign(frame, value)
+            }
+
+            // Vector operations.
+            Opcode::Splat => {
+                let lanes = vec![args[0].clone(); result_ty.lane_count() as usize];
+                assign(frame, from_lanes(result_ty, &lanes))
+            }
+            Opcode::ScalarToVector => {
+                let mut bytes = [0; 16];
+                let scalar = to_bytes(arg_tys[0], &args[0]).ok_or_else(unsupported)?;
+                bytes[..scalar.len()].copy_from_slice(&scalar);
+                assign(frame, Some(DataValue::V128(bytes)))
+            }
+            Opcode::Extractlane | Opcode::Insertlane => {
+                let lane = match *data {
+                    InstructionData::BinaryImm8 { imm, .. }
+                    | InstructionData::TernaryImm8 { imm, .. } => imm as usize,
+                    _ => return Err(unsupported()),
+                };
+                let mut lanes = lanes(arg_tys[0], &args[0]).ok_or_else(unsupported)?;
+                if lane >= lanes.len() {
+                    return Err(unsupported());
+                }
+                if opcode == Opcode::Extractlane {
+                    assign(frame, Some(lanes.swap_remove(lane)))
+                } else {
+                    lanes[lane] = args[1].clone();
+                    assign(frame, from_lanes(result_ty, &lanes))
+                }
+            }
+            Opcode::Shuffle => match *data {
+                InstructionData::Shuffle { mask, .. } => {
+                    let mask = dfg.immediates.get(mask).ok_or_else(unsupported)?;
+                    let a = to_bytes(arg_tys[0], &args[0]).ok_or_else(unsupported)?;
+                    let b = to_bytes(arg_tys[1], &args[1]).ok_or_else(unsupported)?;
+                    let bytes = mask
+                        .iter()
+                        .map(|i| *a.iter().chain(b.iter()).nth(*i as usize).unwrap_or(&0))
+                        .collect::<Vec<_>>();
+                    assign(frame, from_bytes(result_ty, &bytes))
                 }
-                _ => unimplemented!("interpreter does not support opcode yet: {}", opcode),
+                _ => Err(unsupported()),
             },
+            Opcode::Swizzle => {
+                let a = to_bytes(arg_tys[0], &args[0]).ok_or_else(unsupported)?;
+                let indices = to_bytes(arg_tys[1], &args[1]).ok_or_else(unsupported)?;
+                let bytes = indices
+                    .iter()
+                    .map(|i| *a.get(*i as usize).unwrap_or(&0))
+                    .collect::<Vec<_>>();
+                assign(frame, from_bytes(result_ty, &bytes))
+            }
+            Opcode::VanyTrue | Opcode::VallTrue => {
+                let lanes = lanes(arg_tys[0], &args[0]).ok_or_else(unsupported)?;
+                let lanes = lanes
+                    .iter()
+                    .map(as_bool)
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(unsupported)?;
+                let value = if opcode == Opcode::VanyTrue {
+                    lanes.iter().any(|l| *l)
+                } else {
+                    lanes.iter().all(|l| *l)
+                };
+                assign(frame, Some(DataValue::B(value)))
+            }
+
+            // CPU flags, references, register and stack pointer manipulation, and the other
+            // ISA-level instructions have no meaning in the interpreter.
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Save the results of a call instruction.
+    fn assign_call_results(
+        &self,
+        frame: &mut Frame,
+        inst: Inst,
+        result: ControlFlow,
+    ) -> Result<ControlFlow, Trap> {
+        if let ControlFlow::Return(returned_values) = result {
+            let ssa_values = frame.function.dfg.inst_results(inst);
+            assert_eq!(
+                ssa_values.len(),
+                returned_values.len(),
+                "expected result length ({}) to match SSA values length ({}): {}",
+                returned_values.len(),
+                ssa_values.len(),
+                frame.function.dfg.display_inst(inst, None)
+            );
+            frame.set_all(ssa_values, returned_values);
+            Ok(ControlFlow::Continue)
+        } else {
+            Err(Trap::InvalidControlFlow(format!(
+                "did not return from: {}",
+                frame.function.dfg.display_inst(inst, None)
+            )))
+        }
+    }
 
-            _ => unimplemented!("interpreter does not support instruction yet: {:?}", data),
+    /// Compute the value of a [GlobalValue]. Symbols cannot be resolved by the interpreter.
+    fn global_value(
+        &self,
+        frame: &Frame,
+        global_value: GlobalValue,
+        inst: Inst,
+    ) -> Result<DataValue, Trap> {
+        let function = frame.function;
+        match function.global_values[global_value] {
+            GlobalValueData::VMContext => {
+                let vmctx = function
+                    .special_param(ArgumentPurpose::VMContext)
+                    .ok_or(Trap::Unsupported(inst))?;
+                Ok(frame.get(&vmctx).clone())
+            }
+            GlobalValueData::IAddImm {
+                base,
+                offset,
+                global_type,
+            } => {
+                let base = self.global_value(frame, base, inst)?;
+                let base = as_i64(&base).ok_or(Trap::Unsupported(inst))?;
+                from_i64(global_type, base.wrapping_add(offset.into()))
+                    .ok_or(Trap::Unsupported(inst))
+            }
+            GlobalValueData::Load {
+                base,
+                offset,
+                global_type,
+                ..
+            } => {
+                let base = self.global_value(frame, base, inst)?;
+                let address = address_of(&[base], offset.into()).ok_or(Trap::Unsupported(inst))?;
+                self.memory.borrow().load(address, global_type)
+            }
+            GlobalValueData::Symbol { .. } => Err(Trap::Unsupported(inst)),
         }
     }
 }
 
+/// Apply `op` to each lane of the arguments of a vector instruction or, for a scalar instruction,
+/// to the arguments themselves. `op` is passed the (lane) type of the result; scalar arguments of
+/// vector instructions, such as shift amounts, are passed unchanged for every lane.
+fn lanewise<F>(
+    inst: Inst,
+    args: &[DataValue],
+    arg_tys: &[Type],
+    result_ty: Type,
+    op: F,
+) -> Result<DataValue, Trap>
+where
+    F: Fn(Type, &[DataValue]) -> Result<DataValue, Trap>,
+{
+    if !result_ty.is_vector() {
+        return op(result_ty, args);
+    }
+
+    let lane_count = result_ty.lane_count() as usize;
+    let arg_lanes = args
+        .iter()
+        .zip(arg_tys)
+        .map(|(arg, ty)| {
+            if ty.is_vector() {
+                lanes(*ty, arg).filter(|ls| ls.len() == lane_count)
+            } else {
+                Some(vec![arg.clone(); lane_count])
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(Trap::Unsupported(inst))?;
+    let result_lanes = (0..lane_count)
+        .map(|i| {
+            let lane_args = arg_lanes.iter().map(|ls| ls[i].clone()).collect::<Vec<_>>();
+            op(result_ty.lane_type(), &lane_args)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    from_lanes(result_ty, &result_lanes).ok_or(Trap::Unsupported(inst))
+}
+
+/// Apply `op` to the bytes of the in-memory representations of `args`, all of type `ty`.
+fn bitwise<F>(ty: Type, args: &[DataValue], op: F) -> Option<DataValue>
+where
+    F: Fn(&[u8]) -> u8,
+{
+    let arg_bytes = args
+        .iter()
+        .map(|arg| to_bytes(ty, arg))
+        .collect::<Option<Vec<_>>>()?;
+    let bytes = (0..ty.bytes() as usize)
+        .map(|i| op(&arg_bytes.iter().map(|b| b[i]).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    from_bytes(ty, &bytes)
+}
+
+/// Interpret an integer arithmetic instruction on scalar arguments, producing a value of type
+/// `ty`. Intermediate results are computed in 128 bits so that overflow can be observed.
+fn integer_op(inst: Inst, opcode: Opcode, ty: Type, args: &[DataValue]) -> Result<DataValue, Trap> {
+    let unsupported = || Trap::Unsupported(inst);
+    let bits = ty.bits() as u32;
+    let s = |i: usize| as_i64(&args[i]).map(i128::from).ok_or_else(unsupported);
+    let u = |i: usize| as_u64(&args[i]).map(i128::from).ok_or_else(unsupported);
+    let amount = |i: usize| u(i).map(|n| (n % bits as i128) as u32);
+
+    let result = match opcode {
+        Opcode::Iadd => s(0)? + s(1)?,
+        Opcode::Isub => s(0)? - s(1)?,
+        Opcode::Imul => s(0)? * s(1)?,
+        Opcode::Ineg => -s(0)?,
+        Opcode::UaddSat => (u(0)? + u(1)?).min(max_unsigned(ty) as i128),
+        Opcode::SaddSat => (s(0)? + s(1)?)
+            .max(min_signed(ty) as i128)
+            .min(max_signed(ty) as i128),
+        Opcode::UsubSat => (u(0)? - u(1)?).max(0),
+        Opcode::SsubSat => (s(0)? - s(1)?)
+            .max(min_signed(ty) as i128)
+            .min(max_signed(ty) as i128),
+        Opcode::Umulhi => ((u(0)? as u128 * u(1)? as u128) >> bits) as i128,
+        Opcode::Smulhi => (s(0)? * s(1)?) >> bits,
+        Opcode::Udiv | Opcode::Urem => {
+            let (a, b) = (u(0)?, u(1)?);
+            if b == 0 {
+                return Err(Trap::Code(TrapCode::IntegerDivisionByZero));
+            }
+            if opcode == Opcode::Udiv {
+                a / b
+            } else {
+                a % b
+            }
+        }
+        Opcode::Sdiv | Opcode::Srem => {
+            let (a, b) = (s(0)?, s(1)?);
+            if b == 0 {
+                return Err(Trap::Code(TrapCode::IntegerDivisionByZero));
+            }
+            if opcode == Opcode::Sdiv {
+                if a == min_signed(ty) as i128 && b == -1 {
+                    return Err(Trap::Code(TrapCode::IntegerOverflow));
+                }
+                a / b
+            } else {
+                a % b
+            }
+        }
+        Opcode::Imin => s(0)?.min(s(1)?),
+        Opcode::Umin => u(0)?.min(u(1)?),
+        Opcode::Imax => s(0)?.max(s(1)?),
+        Opcode::Umax => u(0)?.max(u(1)?),
+        Opcode::AvgRound => (u(0)? + u(1)? + 1) >> 1,
+        Opcode::Ishl => s(0)? << amount(1)?,
+        Opcode::Ushr => u(0)? >> amount(1)?,
+        Opcode::Sshr => s(0)? >> amount(1)?,
+        Opcode::Rotl => {
+            let (x, n) = (u(0)?, amount(1)?);
+            (x << n) | (x >> ((bits - n) % bits))
+        }
+        Opcode::Rotr => {
+            let (x, n) = (u(0)?, amount(1)?);
+            (x >> n) | (x << ((bits - n) % bits))
+        }
+        Opcode::Bitrev => ((u(0)? as u64).reverse_bits() >> (64 - bits)) as i128,
+        Opcode::Clz => ((u(0)? as u64).leading_zeros() - (64 - bits)) as i128,
+        Opcode::Cls => {
+            let x = s(0)?;
+            let x = if x < 0 { !x } else { x };
+            ((x as u64).leading_zeros() - (64 - bits) - 1) as i128
+        }
+        Opcode::Ctz => (u(0)? as u64).trailing_zeros().min(bits) as i128,
+        Opcode::Popcnt => (u(0)? as u64).count_ones() as i128,
+        _ => return Err(unsupported()),
+    };
+    from_i64(ty, result as i64).ok_or_else(unsupported)
+}
+
+/// Interpret a floating point arithmetic instruction on scalar arguments.
+fn float_op(inst: Inst, opcode: Opcode, args: &[DataValue]) -> Result<DataValue, Trap> {
+    macro_rules! float_op {
+        ( $( $data_value_ty:ident($nearest:ident) ),* ) => {
+            match args {
+                $(
+                    [DataValue::$data_value_ty(a)] => Ok(DataValue::$data_value_ty(match opcode {
+                        Opcode::Sqrt => a.sqrt(),
+                        Opcode::Fneg => -a,
+                        Opcode::Fabs => a.abs(),
+                        Opcode::Ceil => a.ceil(),
+                        Opcode::Floor => a.floor(),
+                        Opcode::Trunc => a.trunc(),
+                        Opcode::Nearest => $nearest(*a),
+                        _ => return Err(Trap::Unsupported(inst)),
+                    })),
+                    [DataValue::$data_value_ty(a), DataValue::$data_value_ty(b)] => {
+                        Ok(DataValue::$data_value_ty(match opcode {
+                            Opcode::Fadd => a + b,
+                            Opcode::Fsub => a - b,
+                            Opcode::Fmul => a * b,
+                            Opcode::Fdiv => a / b,
+                            Opcode::Fcopysign => a.copysign(*b),
+                            // Unlike the Rust functions, `fmin` and `fmax` propagate NaNs and order
+                            // -0.0 before 0.0.
+                            Opcode::Fmin | Opcode::Fmax if a.is_nan() || b.is_nan() => a + b,
+                            Opcode::Fmin if a == b => if a.is_sign_negative() { *a } else { *b },
+                            Opcode::Fmax if a == b => if a.is_sign_positive() { *a } else { *b },
+                            Opcode::Fmin => a.min(*b),
+                            Opcode::Fmax => a.max(*b),
+                            _ => return Err(Trap::Unsupported(inst)),
+                        }))
+                    }
+                    [
+                        DataValue::$data_value_ty(a),
+                        DataValue::$data_value_ty(b),
+                        DataValue::$data_value_ty(c),
+                    ]
+                        if opcode == Opcode::Fma =>
+                    {
+                        Ok(DataValue::$data_value_ty(a.mul_add(*b, *c)))
+                    }
+                )*
+                _ => Err(Trap::Unsupported(inst)),
+            }
+        };
+    }
+    float_op!(F32(nearest_f32), F64(nearest_f64))
+}
+
+/// Interpret a conversion instruction on a scalar argument, producing a value of type `ty`.
+fn convert(inst: Inst, opcode: Opcode, ty: Type, arg: &DataValue) -> Result<DataValue, Trap> {
+    let unsupported = || Trap::Unsupported(inst);
+    let value = match opcode {
+        Opcode::Bint => from_i64(ty, as_bool(arg).ok_or_else(unsupported)? as i64),
+        Opcode::Bmask => from_i64(ty, -(as_bool(arg).ok_or_else(unsupported)? as i64)),
+        Opcode::Breduce | Opcode::Bextend => as_bool(arg).map(DataValue::B),
+        Opcode::Ireduce => as_i64(arg).and_then(|x| from_i64(ty, x)),
+        Opcode::Uextend => extend(ty, arg, false),
+        Opcode::Sextend => extend(ty, arg, true),
+        Opcode::Fpromote => match arg {
+            DataValue::F32(f) => Some(DataValue::F64(*f as f64)),
+            _ => None,
+        },
+        Opcode::Fdemote => match arg {
+            DataValue::F64(f) => Some(DataValue::F32(*f as f32)),
+            _ => None,
+        },
+        Opcode::FcvtFromSint => as_i64(arg).and_then(|x| match ty {
+            types::F32 => Some(DataValue::F32(x as f32)),
+            types::F64 => Some(DataValue::F64(x as f64)),
+            _ => None,
+        }),
+        Opcode::FcvtFromUint => as_u64(arg).and_then(|x| match ty {
+            types::F32 => Some(DataValue::F32(x as f32)),
+            types::F64 => Some(DataValue::F64(x as f64)),
+            _ => None,
+        }),
+        Opcode::FcvtToSint | Opcode::FcvtToSintSat | Opcode::FcvtToUint | Opcode::FcvtToUintSat => {
+            let x = match arg {
+                DataValue::F32(f) => *f as f64,
+                DataValue::F64(f) => *f,
+                _ => return Err(unsupported()),
+            };
+            let signed = opcode == Opcode::FcvtToSint || opcode == Opcode::FcvtToSintSat;
+            let saturating = opcode == Opcode::FcvtToSintSat || opcode == Opcode::FcvtToUintSat;
+            // The representable range is [min, max), with bounds that are exact in an f64.
+            let (min, max) = if signed {
+                (
+                    -(2f64.powi(ty.bits() as i32 - 1)),
+                    2f64.powi(ty.bits() as i32 - 1),
+                )
+            } else {
+                (0.0, 2f64.powi(ty.bits() as i32))
+            };
+            let x = x.trunc();
+            let result = if x.is_nan() {
+                if !saturating {
+                    return Err(Trap::Code(TrapCode::BadConversionToInteger));
+                }
+                0
+            } else if x < min || x >= max {
+                if !saturating {
+                    return Err(Trap::Code(TrapCode::IntegerOverflow));
+                }
+                match (signed, x < min) {
+                    (true, true) => min_signed(ty),
+                    (true, false) => max_signed(ty),
+                    (false, true) => 0,
+                    (false, false) => max_unsigned(ty) as i64,
+                }
+            } else if signed {
+                x as i64
+            } else {
+                x as u64 as i64
+            };
+            from_i64(ty, result)
+        }
+        _ => None,
+    };
+    value.ok_or_else(unsupported)
+}
+
+/// Sign- or zero-extend the integer `arg` to the type `ty`.
+fn extend(ty: Type, arg: &DataValue, signed: bool) -> Option<DataValue> {
+    let x = if signed {
+        as_i64(arg)?
+    } else {
+        as_u64(arg)? as i64
+    };
+    from_i64(ty, x)
+}
+
+/// Compare two integers.
+fn icmp(cond: IntCC, a: &DataValue, b: &DataValue) -> Option<bool> {
+    let (sa, sb) = (as_i64(a)?, as_i64(b)?);
+    let (ua, ub) = (as_u64(a)?, as_u64(b)?);
+    let overflow = || {
+        let difference = sa as i128 - sb as i128;
+        difference < min_signed(a.ty()) as i128 || difference > max_signed(a.ty()) as i128
+    };
+    Some(match cond {
+        IntCC::Equal => ua == ub,
+        IntCC::NotEqual => ua != ub,
+        IntCC::SignedLessThan => sa < sb,
+        IntCC::SignedGreaterThanOrEqual => sa >= sb,
+        IntCC::SignedGreaterThan => sa > sb,
+        IntCC::SignedLessThanOrEqual => sa <= sb,
+        IntCC::UnsignedLessThan => ua < ub,
+        IntCC::UnsignedGreaterThanOrEqual => ua >= ub,
+        IntCC::UnsignedGreaterThan => ua > ub,
+        IntCC::UnsignedLessThanOrEqual => ua <= ub,
+        IntCC::Overflow => overflow(),
+        IntCC::NotOverflow => !overflow(),
+    })
+}
+
+/// Compare two floating point numbers.
+fn fcmp(cond: FloatCC, a: &DataValue, b: &DataValue) -> Option<bool> {
+    // Promoting an f32 to an f64 is exact, so the comparison is unaffected.
+    let (a, b) = match (a, b) {
+        (DataValue::F32(a), DataValue::F32(b)) => (*a as f64, *b as f64),
+        (DataValue::F64(a), DataValue::F64(b)) => (*a, *b),
+        _ => return None,
+    };
+    let unordered = a.is_nan() || b.is_nan();
+    Some(match cond {
+        FloatCC::Ordered => !unordered,
+        FloatCC::Unordered => unordered,
+        FloatCC::Equal => a == b,
+        FloatCC::NotEqual => a != b,
+        FloatCC::OrderedNotEqual => !unordered && a != b,
+        FloatCC::UnorderedOrEqual => unordered || a == b,
+        FloatCC::LessThan => a < b,
+        FloatCC::LessThanOrEqual => a <= b,
+        FloatCC::GreaterThan => a > b,
+        FloatCC::GreaterThanOrEqual => a >= b,
+        FloatCC::UnorderedOrLessThan => unordered || a < b,
+        FloatCC::UnorderedOrLessThanOrEqual => unordered || a <= b,
+        FloatCC::UnorderedOrGreaterThan => unordered || a > b,
+        FloatCC::UnorderedOrGreaterThanOrEqual => unordered || a >= b,
+    })
+}
+
+/// Return the type loaded from memory by a load instruction producing a `result_ty`, and whether
+/// it is sign-extended to the result.
+fn loaded_type(opcode: Opcode, result_ty: Type) -> (Type, bool) {
+    match opcode {
+        Opcode::Uload8 | Opcode::Uload8Complex => (types::I8, false),
+        Opcode::Sload8 | Opcode::Sload8Complex => (types::I8, true),
+        Opcode::Uload16 | Opcode::Uload16Complex => (types::I16, false),
+        Opcode::Sload16 | Opcode::Sload16Complex => (types::I16, true),
+        Opcode::Uload32 | Opcode::Uload32Complex => (types::I32, false),
+        Opcode::Sload32 | Opcode::Sload32Complex => (types::I32, true),
+        Opcode::Uload8x8 | Opcode::Uload8x8Complex => (types::I8X8, false),
+        Opcode::Sload8x8 | Opcode::Sload8x8Complex => (types::I8X8, true),
+        Opcode::Uload16x4 | Opcode::Uload16x4Complex => (types::I16X4, false),
+        Opcode::Sload16x4 | Opcode::Sload16x4Complex => (types::I16X4, true),
+        Opcode::Uload32x2 | Opcode::Uload32x2Complex => (types::I32X2, false),
+        Opcode::Sload32x2 | Opcode::Sload32x2Complex => (types::I32X2, true),
+        _ => (result_ty, false),
+    }
+}
+
+/// Compute an effective address: the sum of the address operands and the offset.
+fn address_of(args: &[DataValue], offset: i64) -> Option<u64> {
+    args.iter().try_fold(offset as u64, |address, arg| {
+        Some(address.wrapping_add(as_u64(arg)?))
+    })
+}
+
+/// Return the non-immediate counterpart of an instruction with an integer immediate.
+fn non_imm_opcode(opcode: Opcode) -> Option<Opcode> {
+    Some(match opcode {
+        Opcode::IaddImm => Opcode::Iadd,
+        Opcode::ImulImm => Opcode::Imul,
+        Opcode::UdivImm => Opcode::Udiv,
+        Opcode::SdivImm => Opcode::Sdiv,
+        Opcode::UremImm => Opcode::Urem,
+        Opcode::SremImm => Opcode::Srem,
+        Opcode::BandImm => Opcode::Band,
+        Opcode::BorImm => Opcode::Bor,
+        Opcode::BxorImm => Opcode::Bxor,
+        Opcode::RotlImm => Opcode::Rotl,
+        Opcode::RotrImm => Opcode::Rotr,
+        Opcode::IshlImm => Opcode::Ishl,
+        Opcode::UshrImm => Opcode::Ushr,
+        Opcode::SshrImm => Opcode::Sshr,
+        Opcode::IcmpImm => Opcode::Icmp,
+        _ => return None,
+    })
+}
+
 /// Return the first result of an instruction.
 ///
 /// This helper cushions the interpreter from changes to the [Function] API.
@@ -314,14 +1136,6 @@ fn first_result(function: &Function, inst: Inst) -> ValueRef {
     function.dfg.first_result(inst)
 }
 
-/// Return a list of IR values as a vector.
-///
-/// This helper cushions the interpreter from changes to the [Function] API.
-#[inline]
-fn value_refs(function: &Function, args: &ValueList) -> Vec<ValueRef> {
-    args.as_slice(&function.dfg.value_lists).to_vec()
-}
-
 /// Return the (external) function name of `func_ref` in a local `function`. Note that this may
 /// be truncated.
 ///
@@ -337,6 +1151,33 @@ fn function_name_of_func_ref(func_ref: FuncRef, function: &Function) -> String {
         .to_string()
 }
 
+/// Return the integer condition code of an instruction, if it has one.
+///
+/// This helper cushions the interpreter from changes to the [InstructionData] API.
+#[inline]
+fn int_cond_of(data: &InstructionData) -> Option<IntCC> {
+    match *data {
+        InstructionData::IntCompare { cond, .. }
+        | InstructionData::IntCompareImm { cond, .. }
+        | InstructionData::BranchIcmp { cond, .. } => Some(cond),
+        _ => None,
+    }
+}
+
+/// Return the offset immediate of a memory access, if it has one.
+///
+/// This helper cushions the interpreter from changes to the [InstructionData] API.
+#[inline]
+fn offset_of(data: &InstructionData) -> Option<i64> {
+    match *data {
+        InstructionData::Load { offset, .. }
+        | InstructionData::LoadComplex { offset, .. }
+        | InstructionData::Store { offset, .. }
+        | InstructionData::StoreComplex { offset, .. } => Some(offset.into()),
+        _ => None,
+    }
+}
+
 /// Helper for calculating the type of an IR value. TODO move to Frame?
 #[inline]
 fn type_of(value: ValueRef, function: &Function) -> Type {
@@ -348,6 +1189,15 @@ mod tests {
     use super::*;
     use cranelift_reader::parse_functions;
 
+    /// Parse `code` into an [Interpreter] environment containing all of its functions.
+    fn interpreter(code: &str) -> Interpreter {
+        let mut env = Environment::default();
+        for func in parse_functions(code).unwrap() {
+            env.add(func.name.to_string(), func);
+        }
+        Interpreter::new(env)
+    }
+
     // Most interpreter tests should use the more ergonomic `test interpret` filetest but this
     // unit test serves as a sanity check that the interpreter still works without all of the
     // filetest infrastructure.
@@ -373,4 +1223,127 @@ mod tests {
 
         assert_eq!(result, vec![DataValue::B(true)])
     }
+
+    // Traps cannot be checked by the `test interpret` filetests.
+    #[test]
+    fn traps() {
+        let interpreter = interpreter(
+            "function %div(i32, i32) -> i32 {
+            block0(v0: i32, v1: i32):
+                v2 = sdiv v0, v1
+                return v2
+            }
+
+            function %trapz(i32) {
+            block0(v0: i32):
+                trapz v0, user42
+                return
+            }
+
+            function %cvt(f32) -> i32 {
+            block0(v0: f32):
+                v1 = fcvt_to_sint.i32 v0
+                return v1
+            }",
+        );
+        let trap_code = |name, args: &[DataValue]| match interpreter.call_by_name(name, args) {
+            Err(Trap::Code(code)) => Some(code),
+            _ => None,
+        };
+
+        assert_eq!(
+            trap_code("%div", &[DataValue::I32(1), DataValue::I32(0)]),
+            Some(TrapCode::IntegerDivisionByZero)
+        );
+        assert_eq!(
+            trap_code(
+                "%div",
+                &[DataValue::I32(i32::min_value()), DataValue::I32(-1)]
+            ),
+            Some(TrapCode::IntegerOverflow)
+        );
+        assert_eq!(
+            trap_code("%div", &[DataValue::I32(7), DataValue::I32(2)]),
+            None
+        );
+        assert_eq!(
+            trap_code("%trapz", &[DataValue::I32(0)]),
+            Some(TrapCode::User(42))
+        );
+        assert_eq!(trap_code("%trapz", &[DataValue::I32(1)]), None);
+        assert_eq!(
+            trap_code("%cvt", &[DataValue::F32(std::f32::NAN)]),
+            Some(TrapCode::BadConversionToInteger)
+        );
+        assert_eq!(
+            trap_code("%cvt", &[DataValue::F32(3e9)]),
+            Some(TrapCode::IntegerOverflow)
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let interpreter = interpreter(
+            "function %double(i64) -> i64 {
+            block0(v0: i64):
+                v1 = imul_imm v0, 2
+                return v1
+            }
+
+            function %test(i64) -> i64, i64 {
+                sig0 = (i64) -> i64
+                fn0 = %double sig0
+            block0(v0: i64):
+                v1 = call fn0(v0)
+                v2 = func_addr.i64 fn0
+                v3 = call_indirect sig0, v2(v1)
+                return v1, v3
+            }",
+        );
+        let result = interpreter
+            .call_by_name("%test", &[DataValue::I64(21)])
+            .unwrap()
+            .unwrap_return();
+        assert_eq!(result, vec![DataValue::I64(42), DataValue::I64(84)]);
+    }
+
+    #[test]
+    fn heap() {
+        let interpreter = interpreter(
+            "function %store(i64 vmctx, i32, i32) {
+                gv0 = vmctx
+                heap0 = static gv0, min 0x1000, bound 0x1_0000_0000, offset_guard 0
+            block0(v0: i64, v1: i32, v2: i32):
+                v3 = heap_addr.i64 heap0, v1, 4
+                store v2, v3
+                return
+            }
+
+            function %load(i64 vmctx, i32) -> i32 {
+                gv0 = vmctx
+                heap0 = static gv0, min 0x1000, bound 0x1_0000_0000, offset_guard 0
+            block0(v0: i64, v1: i32):
+                v2 = heap_addr.i64 heap0, v1, 4
+                v3 = load.i32 v2
+                return v3
+            }",
+        );
+        let vmctx = DataValue::I64(0);
+        interpreter
+            .call_by_name(
+                "%store",
+                &[vmctx.clone(), DataValue::I32(8), DataValue::I32(42)],
+            )
+            .unwrap();
+        let result = interpreter
+            .call_by_name("%load", &[vmctx.clone(), DataValue::I32(8)])
+            .unwrap()
+            .unwrap_return();
+        assert_eq!(result, vec![DataValue::I32(42)]);
+
+        match interpreter.call_by_name("%load", &[vmctx, DataValue::I32(0xffe)]) {
+            Err(Trap::Code(TrapCode::HeapOutOfBounds)) => {}
+            _ => panic!("expected an out-of-bounds heap access to trap"),
+        }
+    }
 }
@@ -5,3 +5,5 @@
 pub mod environment;
 pub mod frame;
 pub mod interpreter;
+pub mod memory;
+mod value;
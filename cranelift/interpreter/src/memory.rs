@@ -0,0 +1,161 @@
+//! Implements the memory model of the Cranelift interpreter.
+//!
+//! Memory is modelled as a sparse 64-bit address space made up of disjoint regions: one for each
+//! stack slot of each active frame and one for each heap. Any access that does not fall entirely
+//! within a single region traps with [TrapCode::HeapOutOfBounds], much like an access to an
+//! unmapped page would fault in compiled code.
+
+use crate::interpreter::Trap;
+use crate::value::{from_bytes, to_bytes};
+use cranelift_codegen::ir::{Heap, TrapCode, Type};
+use cranelift_reader::DataValue;
+use log::trace;
+use std::collections::{BTreeMap, HashMap};
+
+/// The address of the first allocated region; leaving the first page unmapped means that null
+/// pointer accesses trap.
+const FIRST_ADDRESS: u64 = 0x1000;
+
+/// Regions are aligned to, and separated by at least, this many bytes so that accesses running off
+/// the end of one region do not silently land in the next.
+const REGION_ALIGNMENT: u64 = 0x1000;
+
+/// The memory of an interpretation.
+#[derive(Debug)]
+pub struct Memory {
+    /// The allocated regions, keyed by their base address.
+    regions: BTreeMap<u64, Vec<u8>>,
+    /// The base address of the next region to allocate.
+    next_address: u64,
+    /// The base addresses of the heaps allocated so far. Heaps are identified only by their index
+    /// so that all functions in an environment share them, as they would share a wasm linear
+    /// memory.
+    heaps: HashMap<Heap, u64>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self {
+            regions: BTreeMap::new(),
+            next_address: FIRST_ADDRESS,
+            heaps: HashMap::new(),
+        }
+    }
+}
+
+impl Memory {
+    /// Allocate a zeroed region of `size` bytes and return its base address.
+    pub fn allocate(&mut self, size: u64) -> u64 {
+        let address = self.next_address;
+        let rounded_size = (size + REGION_ALIGNMENT - 1) / REGION_ALIGNMENT * REGION_ALIGNMENT;
+        self.next_address = address + rounded_size + REGION_ALIGNMENT;
+        trace!("Allocate {} bytes at {:#x}", size, address);
+        self.regions.insert(address, vec![0; size as usize]);
+        address
+    }
+
+    /// Free the region starting at `address`. Subsequent accesses to it will trap.
+    pub fn free(&mut self, address: u64) {
+        trace!("Free {:#x}", address);
+        self.regions.remove(&address);
+    }
+
+    /// Return the base address of `heap`, allocating `size` zeroed bytes for it the first time it
+    /// is used.
+    pub fn heap(&mut self, heap: Heap, size: u64) -> u64 {
+        if let Some(address) = self.heaps.get(&heap) {
+            return *address;
+        }
+        let address = self.allocate(size);
+        self.heaps.insert(heap, address);
+        address
+    }
+
+    /// Borrow the `size` bytes at `address`.
+    pub fn read(&self, address: u64, size: u64) -> Result<&[u8], Trap> {
+        let (base, region) = self
+            .regions
+            .range(..=address)
+            .next_back()
+            .ok_or(Trap::Code(TrapCode::HeapOutOfBounds))?;
+        let start = (address - base) as usize;
+        region
+            .get(start..start + size as usize)
+            .ok_or(Trap::Code(TrapCode::HeapOutOfBounds))
+    }
+
+    /// Overwrite the bytes at `address` with `bytes`.
+    pub fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), Trap> {
+        let (base, region) = self
+            .regions
+            .range_mut(..=address)
+            .next_back()
+            .ok_or(Trap::Code(TrapCode::HeapOutOfBounds))?;
+        let start = (address - *base) as usize;
+        region
+            .get_mut(start..start + bytes.len())
+            .ok_or(Trap::Code(TrapCode::HeapOutOfBounds))?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Load a value of type `ty` from `address`.
+    pub fn load(&self, address: u64, ty: Type) -> Result<DataValue, Trap> {
+        let bytes = self.read(address, ty.bytes() as u64)?;
+        let value = from_bytes(ty, bytes).ok_or_else(|| Trap::InvalidMemoryType(ty))?;
+        trace!("Load {} from {:#x}", value, address);
+        Ok(value)
+    }
+
+    /// Store `value`, of type `ty`, to `address`.
+    pub fn store(&mut self, address: u64, ty: Type, value: &DataValue) -> Result<(), Trap> {
+        trace!("Store {} to {:#x}", value, address);
+        let bytes = to_bytes(ty, value).ok_or_else(|| Trap::InvalidMemoryType(ty))?;
+        self.write(address, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cranelift_codegen::ir::types;
+    use cranelift_entity::EntityRef;
+
+    #[test]
+    fn load_and_store() {
+        let mut memory = Memory::default();
+        let a = memory.allocate(8);
+        memory.store(a, types::I32, &DataValue::I32(42)).unwrap();
+        memory
+            .store(a + 4, types::I16, &DataValue::I16(-1))
+            .unwrap();
+        assert_eq!(memory.load(a, types::I32).unwrap(), DataValue::I32(42));
+        assert_eq!(
+            memory.load(a, types::I64).unwrap(),
+            DataValue::I64(0xffff_0000_002a)
+        );
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut memory = Memory::default();
+        let a = memory.allocate(8);
+        let b = memory.allocate(8);
+        assert!(memory.load(0, types::I8).is_err());
+        assert!(memory.load(a + 4, types::I64).is_err());
+        assert!(memory.load(a + 8, types::I8).is_err());
+        assert!(memory.load(b, types::I64).is_ok());
+
+        memory.free(b);
+        assert!(memory.store(b, types::I8, &DataValue::I8(1)).is_err());
+    }
+
+    #[test]
+    fn heaps_are_shared() {
+        let mut memory = Memory::default();
+        let heap = Heap::new(0);
+        let a = memory.heap(heap, 0x10000);
+        assert_eq!(memory.heap(heap, 0x10000), a);
+        assert_ne!(memory.heap(Heap::new(1), 0x10000), a);
+    }
+}
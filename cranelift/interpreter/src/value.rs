@@ -0,0 +1,212 @@
+//! Helpers for converting [DataValue]s to and from the host representations used to implement
+//! the Cranelift IR semantics.
+//!
+//! Integers are operated on as sign- or zero-extended 64-bit host integers and truncated back
+//! to the width of their type, vectors are operated on lane by lane and everything can be
+//! converted to and from its little-endian in-memory representation.
+
+use cranelift_codegen::ir::{types, Type};
+use cranelift_reader::DataValue;
+
+/// Return the signed value of an integer [DataValue], sign-extended to 64 bits.
+pub fn as_i64(value: &DataValue) -> Option<i64> {
+    match *value {
+        DataValue::I8(i) => Some(i as i64),
+        DataValue::I16(i) => Some(i as i64),
+        DataValue::I32(i) => Some(i as i64),
+        DataValue::I64(i) => Some(i),
+        _ => None,
+    }
+}
+
+/// Return the unsigned value of an integer [DataValue], zero-extended to 64 bits.
+pub fn as_u64(value: &DataValue) -> Option<u64> {
+    match *value {
+        DataValue::I8(i) => Some(i as u8 as u64),
+        DataValue::I16(i) => Some(i as u16 as u64),
+        DataValue::I32(i) => Some(i as u32 as u64),
+        DataValue::I64(i) => Some(i as u64),
+        _ => None,
+    }
+}
+
+/// Return the value of a boolean [DataValue] or, like the branch instructions do, whether an
+/// integer [DataValue] is non-zero.
+pub fn as_bool(value: &DataValue) -> Option<bool> {
+    match value {
+        DataValue::B(b) => Some(*b),
+        _ => as_i64(value).map(|i| i != 0),
+    }
+}
+
+/// Build an integer [DataValue] of type `ty` from `x`, truncating it to the width of `ty`.
+pub fn from_i64(ty: Type, x: i64) -> Option<DataValue> {
+    match ty {
+        types::I8 => Some(DataValue::I8(x as i8)),
+        types::I16 => Some(DataValue::I16(x as i16)),
+        types::I32 => Some(DataValue::I32(x as i32)),
+        types::I64 => Some(DataValue::I64(x)),
+        _ => None,
+    }
+}
+
+/// Split a vector [DataValue] of type `ty` into a [DataValue] per lane.
+pub fn lanes(ty: Type, value: &DataValue) -> Option<Vec<DataValue>> {
+    let bytes = match value {
+        DataValue::V128(bytes) => bytes,
+        _ => return None,
+    };
+    let lane_type = ty.lane_type();
+    let lane_bytes = lane_type.bytes() as usize;
+    bytes[..ty.bytes() as usize]
+        .chunks(lane_bytes)
+        .map(|lane| from_bytes(lane_type, lane))
+        .collect()
+}
+
+/// Build a vector [DataValue] of type `ty` from a [DataValue] per lane.
+pub fn from_lanes(ty: Type, lanes: &[DataValue]) -> Option<DataValue> {
+    let lane_type = ty.lane_type();
+    let mut bytes = [0; 16];
+    let lane_bytes = lane_type.bytes() as usize;
+    for (i, lane) in lanes.iter().enumerate() {
+        let start = i * lane_bytes;
+        bytes
+            .get_mut(start..start + lane_bytes)?
+            .copy_from_slice(&to_bytes(lane_type, lane)?);
+    }
+    Some(DataValue::V128(bytes))
+}
+
+/// Return the little-endian in-memory representation of a [DataValue] of type `ty`. Booleans
+/// are represented with all bits set when true, like boolean vector lanes are.
+pub fn to_bytes(ty: Type, value: &DataValue) -> Option<Vec<u8>> {
+    let size = ty.bytes() as usize;
+    let bytes = match *value {
+        DataValue::B(b) => vec![if b { 0xff } else { 0 }; size],
+        DataValue::I8(i) => i.to_le_bytes().to_vec(),
+        DataValue::I16(i) => i.to_le_bytes().to_vec(),
+        DataValue::I32(i) => i.to_le_bytes().to_vec(),
+        DataValue::I64(i) => i.to_le_bytes().to_vec(),
+        DataValue::F32(f) => f.to_bits().to_le_bytes().to_vec(),
+        DataValue::F64(f) => f.to_bits().to_le_bytes().to_vec(),
+        DataValue::V128(bytes) => bytes[..size].to_vec(),
+    };
+    if bytes.len() == size {
+        Some(bytes)
+    } else {
+        None
+    }
+}
+
+/// Build a [DataValue] of type `ty` from its little-endian in-memory representation.
+pub fn from_bytes(ty: Type, bytes: &[u8]) -> Option<DataValue> {
+    if bytes.len() != ty.bytes() as usize {
+        return None;
+    }
+    let mut buf = [0; 16];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let value = if ty.is_vector() {
+        DataValue::V128(buf)
+    } else if ty.is_bool() {
+        DataValue::B(bytes.iter().any(|b| *b != 0))
+    } else {
+        let mut word = [0; 8];
+        word.copy_from_slice(&buf[..8]);
+        let bits = u64::from_le_bytes(word);
+        match ty {
+            types::F32 => DataValue::F32(f32::from_bits(bits as u32)),
+            types::F64 => DataValue::F64(f64::from_bits(bits)),
+            _ => from_i64(ty, bits as i64)?,
+        }
+    };
+    Some(value)
+}
+
+/// The smallest signed value of an integer type.
+pub fn min_signed(ty: Type) -> i64 {
+    i64::min_value() >> (64 - ty.bits())
+}
+
+/// The largest signed value of an integer type.
+pub fn max_signed(ty: Type) -> i64 {
+    i64::max_value() >> (64 - ty.bits())
+}
+
+/// The largest unsigned value of an integer type.
+pub fn max_unsigned(ty: Type) -> u64 {
+    u64::max_value() >> (64 - ty.bits())
+}
+
+/// Round `x` to the nearest integer, with ties to even.
+pub fn nearest_f32(x: f32) -> f32 {
+    let t = x.trunc();
+    if (x - t).abs() == 0.5 {
+        if t % 2.0 == 0.0 {
+            t
+        } else {
+            t + x.signum()
+        }
+    } else {
+        x.round()
+    }
+}
+
+/// Round `x` to the nearest integer, with ties to even.
+pub fn nearest_f64(x: f64) -> f64 {
+    let t = x.trunc();
+    if (x - t).abs() == 0.5 {
+        if t % 2.0 == 0.0 {
+            t
+        } else {
+            t + x.signum()
+        }
+    } else {
+        x.round()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(as_i64(&DataValue::I8(-1)), Some(-1));
+        assert_eq!(as_u64(&DataValue::I8(-1)), Some(0xff));
+        assert_eq!(
+            from_i64(types::I16, 0x1_8000),
+            Some(DataValue::I16(-0x8000))
+        );
+        assert_eq!(min_signed(types::I8), -128);
+        assert_eq!(max_signed(types::I32), i32::max_value() as i64);
+        assert_eq!(max_unsigned(types::I16), 0xffff);
+    }
+
+    #[test]
+    fn vector_lanes() {
+        let lanes = [1, -2, 3, -4]
+            .iter()
+            .map(|i| DataValue::I32(*i))
+            .collect::<Vec<_>>();
+        let vector = from_lanes(types::I32X4, &lanes).unwrap();
+        assert_eq!(super::lanes(types::I32X4, &vector).unwrap(), lanes);
+
+        let mask = from_lanes(types::B64X2, &[DataValue::B(true), DataValue::B(false)]).unwrap();
+        assert_eq!(
+            mask,
+            DataValue::V128([
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0
+            ])
+        );
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(nearest_f32(2.5), 2.0);
+        assert_eq!(nearest_f32(3.5), 4.0);
+        assert_eq!(nearest_f64(-2.5), -2.0);
+        assert_eq!(nearest_f64(-0.4).to_bits(), (-0.0f64).to_bits());
+        assert_eq!(nearest_f64(1.6), 2.0);
+    }
+}
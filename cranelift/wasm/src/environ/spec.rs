@@ -270,6 +270,39 @@ pub trait FuncEnvironment: TargetEnvironment {
         Ok(pos.ins().call(callee, call_args))
     }
 
+    /// Translate a `return_call_indirect` WebAssembly instruction at `pos`.
+    ///
+    /// Like `translate_call_indirect()`, but the call is a tail call that replaces the current
+    /// function's frame and returns the callee's results directly to the caller. The inserted
+    /// instructions must end with a `return_call` or `return_call_indirect` instruction.
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn translate_return_call_indirect(
+        &mut self,
+        pos: FuncCursor,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        sig_ref: ir::SigRef,
+        callee: ir::Value,
+        call_args: &[ir::Value],
+    ) -> WasmResult<()>;
+
+    /// Translate a `return_call` WebAssembly instruction at `pos`.
+    ///
+    /// Like `translate_call()`, but the call is a tail call that replaces the current function's
+    /// frame and returns the callee's results directly to the caller. The inserted instructions
+    /// must end with a `return_call` or `return_call_indirect` instruction.
+    fn translate_return_call(
+        &mut self,
+        mut pos: FuncCursor,
+        _callee_index: FuncIndex,
+        callee: ir::FuncRef,
+        call_args: &[ir::Value],
+    ) -> WasmResult<()> {
+        pos.ins().return_call(callee, call_args);
+        Ok(())
+    }
+
     /// Translate a `memory.grow` WebAssembly instruction.
     ///
     /// The `index` provided identifies the linear memory to grow, and `heap` is the heap reference
@@ -347,6 +380,43 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Translate a `data.drop` WebAssembly instruction.
     fn translate_data_drop(&mut self, pos: FuncCursor, seg_index: u32) -> WasmResult<()>;
 
+    /// Translate an `i32.atomic.wait` or `i64.atomic.wait` WebAssembly instruction.
+    ///
+    /// The `index` provided identifies the linear memory containing the value to wait on, and
+    /// `heap` is the heap reference returned by `make_heap` for the same index. `addr` is the
+    /// native address of the value, which has been bounds-checked and checked for alignment.
+    /// Whether the value is an `i32` or an `i64` is given by the type of `expected`, and `timeout`
+    /// is an `i64` number of nanoseconds, negative if the wait never times out.
+    ///
+    /// Returns an `i32`: 0 if the thread was woken up, 1 if the value wasn't `expected`, or 2 if
+    /// the wait timed out.
+    fn translate_atomic_wait(
+        &mut self,
+        pos: FuncCursor,
+        index: MemoryIndex,
+        heap: ir::Heap,
+        addr: ir::Value,
+        expected: ir::Value,
+        timeout: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
+    /// Translate an `atomic.notify` WebAssembly instruction.
+    ///
+    /// The `index` provided identifies the linear memory containing the value waited on, and
+    /// `heap` is the heap reference returned by `make_heap` for the same index. `addr` is the
+    /// native address of the value, which has been bounds-checked and checked for alignment, and
+    /// `count` is the `i32` maximum number of waiters to wake up.
+    ///
+    /// Returns the `i32` number of waiters woken up.
+    fn translate_atomic_notify(
+        &mut self,
+        pos: FuncCursor,
+        index: MemoryIndex,
+        heap: ir::Heap,
+        addr: ir::Value,
+        count: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
     /// Translate a `table.size` WebAssembly instruction.
     fn translate_table_size(
         &mut self,
@@ -483,11 +553,43 @@ pub trait FuncEnvironment: TargetEnvironment {
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
     /// the beginnings of loops.
-    fn translate_loop_header(&mut self, _pos: FuncCursor) -> WasmResult<()> {
+    fn translate_loop_header(&mut self, _builder: &mut FunctionBuilder) -> WasmResult<()> {
         // By default, don't emit anything.
         Ok(())
     }
 
+    /// Optional callback for the `FunctionEnvironment` notifying it that all
+    /// of the wasm locals, including parameters, have been declared.
+    ///
+    /// The `num_locals` provided is the number of `Variable`s already declared
+    /// by the translator, so any further variables the environment declares
+    /// must use indices starting at `num_locals`.
+    fn after_locals(&mut self, num_locals: usize) {
+        let _ = num_locals;
+    }
+
+    /// Optional callback for the `FunctionEnvironment` performing this
+    /// translation to emit code at the very start of the function body, after
+    /// the locals have been declared but before the first operator.
+    fn before_translate_function(
+        &mut self,
+        _builder: &mut FunctionBuilder,
+        _state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        Ok(())
+    }
+
+    /// Optional callback for the `FunctionEnvironment` performing this
+    /// translation to emit code after the last operator has been translated,
+    /// but before the implicit return at the end of the function is inserted.
+    fn after_translate_function(
+        &mut self,
+        _builder: &mut FunctionBuilder,
+        _state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        Ok(())
+    }
+
     /// Optional callback for the `FunctionEnvironment` performing this translation to maintain
     /// internal state or prepare custom state for the operator to translate
     fn before_translate_operator(
@@ -108,6 +108,7 @@ impl FuncTranslator {
         self.state.initialize(&builder.func.signature, exit_block);
 
         parse_local_decls(&mut reader, &mut builder, num_params, environ)?;
+        environ.before_translate_function(&mut builder, &self.state)?;
         parse_function_body(
             module_translation_state,
             reader,
@@ -172,6 +173,8 @@ fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
         declare_locals(builder, count, ty, &mut next_local, environ)?;
     }
 
+    environ.after_locals(next_local);
+
     Ok(())
 }
 
@@ -233,6 +236,7 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
         translate_operator(module_translation_state, &op, builder, state, environ)?;
         environ.after_translate_operator(&op, builder, state)?;
     }
+    environ.after_translate_function(builder, state)?;
 
     // The final `End` operator left us in the exit block where we need to manually add a return
     // instruction.
@@ -71,5 +71,8 @@ pub use crate::translation_utils::{
     SignatureIndex, Table, TableElementType, TableIndex,
 };
 
+#[cfg(feature = "enable-serde")]
+pub use crate::translation_utils::serde_wasm_types;
+
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
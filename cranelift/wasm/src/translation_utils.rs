@@ -19,51 +19,61 @@ entity_impl!(FuncIndex);
 
 /// Index type of a defined function inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct DefinedFuncIndex(u32);
 entity_impl!(DefinedFuncIndex);
 
 /// Index type of a defined table inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct DefinedTableIndex(u32);
 entity_impl!(DefinedTableIndex);
 
 /// Index type of a defined memory inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct DefinedMemoryIndex(u32);
 entity_impl!(DefinedMemoryIndex);
 
 /// Index type of a defined global inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct DefinedGlobalIndex(u32);
 entity_impl!(DefinedGlobalIndex);
 
 /// Index type of a table (imported or defined) inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct TableIndex(u32);
 entity_impl!(TableIndex);
 
 /// Index type of a global variable (imported or defined) inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct GlobalIndex(u32);
 entity_impl!(GlobalIndex);
 
 /// Index type of a linear memory (imported or defined) inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct MemoryIndex(u32);
 entity_impl!(MemoryIndex);
 
 /// Index type of a signature (imported or defined) inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct SignatureIndex(u32);
 entity_impl!(SignatureIndex);
 
 /// Index type of a passive data segment inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct DataIndex(u32);
 entity_impl!(DataIndex);
 
 /// Index type of a passive element segment inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct ElemIndex(u32);
 entity_impl!(ElemIndex);
 
@@ -75,8 +85,10 @@ entity_impl!(ElemIndex);
 /// Wasm `i64` and a `funcref` might be represented with a Cranelift `i64` on
 /// 64-bit architectures, and when GC is not required for func refs.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct Global {
     /// The Wasm type of the value stored in the global.
+    #[cfg_attr(feature = "enable-serde", serde(with = "serde_wasm_types::wasm_type"))]
     pub wasm_ty: crate::WasmType,
     /// The Cranelift IR type of the value stored in the global.
     pub ty: ir::Type,
@@ -88,6 +100,7 @@ pub struct Global {
 
 /// Globals are initialized via the `const` operators or by referring to another import.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub enum GlobalInit {
     /// An `i32.const`.
     I32Const(i32),
@@ -111,8 +124,10 @@ pub enum GlobalInit {
 
 /// WebAssembly table.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct Table {
     /// The table elements' Wasm type.
+    #[cfg_attr(feature = "enable-serde", serde(with = "serde_wasm_types::wasm_type"))]
     pub wasm_ty: WasmType,
     /// The table elements' Cranelift type.
     pub ty: TableElementType,
@@ -124,6 +139,7 @@ pub struct Table {
 
 /// WebAssembly table element. Can be a function or a scalar type.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub enum TableElementType {
     /// A scalar type.
     Val(ir::Type),
@@ -133,6 +149,7 @@ pub enum TableElementType {
 
 /// WebAssembly linear memory.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct Memory {
     /// The minimum number of pages in the memory.
     pub minimum: u32,
@@ -142,6 +159,85 @@ pub struct Memory {
     pub shared: bool,
 }
 
+/// Serde support for the wasm types which are defined by `wasmparser`, for use
+/// with `#[serde(with = "...")]`.
+#[cfg(feature = "enable-serde")]
+pub mod serde_wasm_types {
+    use crate::environ::{WasmFuncType, WasmType};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::boxed::Box;
+    use std::vec::Vec;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "WasmType")]
+    enum WasmTypeDef {
+        I32,
+        I64,
+        F32,
+        F64,
+        V128,
+        FuncRef,
+        ExternRef,
+        Func,
+        EmptyBlockType,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Ty(#[serde(with = "WasmTypeDef")] WasmType);
+
+    /// Serde support for a `WasmType`.
+    pub mod wasm_type {
+        use super::*;
+
+        /// Serializes `ty`.
+        pub fn serialize<S: Serializer>(ty: &WasmType, serializer: S) -> Result<S::Ok, S::Error> {
+            WasmTypeDef::serialize(ty, serializer)
+        }
+
+        /// Deserializes a `WasmType`.
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<WasmType, D::Error> {
+            WasmTypeDef::deserialize(deserializer)
+        }
+    }
+
+    /// Serde support for a `WasmFuncType`.
+    pub mod wasm_func_type {
+        use super::*;
+
+        #[derive(Serialize, Deserialize)]
+        struct FuncTypeDef {
+            params: Vec<Ty>,
+            returns: Vec<Ty>,
+        }
+
+        /// Serializes `ty`.
+        pub fn serialize<S: Serializer>(
+            ty: &WasmFuncType,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            FuncTypeDef {
+                params: ty.params.iter().map(|ty| Ty(*ty)).collect(),
+                returns: ty.returns.iter().map(|ty| Ty(*ty)).collect(),
+            }
+            .serialize(serializer)
+        }
+
+        /// Deserializes a `WasmFuncType`.
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<WasmFuncType, D::Error> {
+            let def = FuncTypeDef::deserialize(deserializer)?;
+            let types = |tys: Vec<Ty>| tys.into_iter().map(|ty| ty.0).collect::<Box<[_]>>();
+            Ok(WasmFuncType {
+                params: types(def.params),
+                returns: types(def.returns),
+            })
+        }
+    }
+}
+
 /// Helper function translating wasmparser types to Cranelift types when possible.
 pub fn type_to_type<PE: TargetEnvironment + ?Sized>(
     ty: wasmparser::Type,
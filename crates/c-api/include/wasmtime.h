@@ -56,6 +56,7 @@ WASMTIME_CONFIG_PROP(void, wasm_reference_types, bool)
 WASMTIME_CONFIG_PROP(void, wasm_simd, bool)
 WASMTIME_CONFIG_PROP(void, wasm_bulk_memory, bool)
 WASMTIME_CONFIG_PROP(void, wasm_multi_value, bool)
+WASMTIME_CONFIG_PROP(void, wasm_tail_call, bool)
 WASMTIME_CONFIG_PROP(wasmtime_error_t*, strategy, wasmtime_strategy_t)
 WASMTIME_CONFIG_PROP(void, cranelift_debug_verifier, bool)
 WASMTIME_CONFIG_PROP(void, cranelift_opt_level, wasmtime_opt_level_t)
@@ -183,6 +184,30 @@ WASM_API_EXTERN void wasmtime_interrupt_handle_interrupt(wasmtime_interrupt_hand
 // pointer. If `false` is returned then this is not a wasi exit trap.
 WASM_API_EXTERN bool wasmtime_trap_exit_status(const wasm_trap_t*, int *status);
 
+// Codes describing why WebAssembly code trapped, see `wasmtime_trap_code`.
+typedef uint8_t wasmtime_trap_code_t;
+
+enum wasmtime_trap_code_enum {
+  WASMTIME_TRAP_CODE_STACK_OVERFLOW,
+  WASMTIME_TRAP_CODE_MEMORY_OUT_OF_BOUNDS,
+  WASMTIME_TRAP_CODE_TABLE_OUT_OF_BOUNDS,
+  WASMTIME_TRAP_CODE_INDIRECT_CALL_TO_NULL,
+  WASMTIME_TRAP_CODE_BAD_SIGNATURE,
+  WASMTIME_TRAP_CODE_INTEGER_OVERFLOW,
+  WASMTIME_TRAP_CODE_INTEGER_DIVISION_BY_ZERO,
+  WASMTIME_TRAP_CODE_BAD_CONVERSION_TO_INTEGER,
+  WASMTIME_TRAP_CODE_UNREACHABLE_CODE_REACHED,
+  WASMTIME_TRAP_CODE_INTERRUPT,
+  WASMTIME_TRAP_CODE_OUT_OF_FUEL,
+  WASMTIME_TRAP_CODE_HEAP_MISALIGNED,
+};
+
+// Returns `true` if the trap was raised by a WebAssembly instruction, in which
+// case the reason is returned through the `code` pointer. If `false` is
+// returned then the trap has no code, for example because it was created by a
+// host function.
+WASM_API_EXTERN bool wasmtime_trap_code(const wasm_trap_t*, wasmtime_trap_code_t *code);
+
 ///////////////////////////////////////////////////////////////////////////////
 //
 // Extensions to `wasm_frame_t`
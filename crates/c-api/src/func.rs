@@ -48,6 +48,10 @@ struct Finalizer {
     finalizer: Option<extern "C" fn(*mut c_void)>,
 }
 
+// It's up to the embedder to only move a store to another thread if the
+// environments of its functions can be used from there.
+unsafe impl Send for Finalizer {}
+
 impl Drop for Finalizer {
     fn drop(&mut self) {
         if let Some(f) = self.finalizer {
@@ -89,7 +93,9 @@ impl From<HostRef<Func>> for wasm_func_t {
 fn create_function(
     store: &wasm_store_t,
     ty: &wasm_functype_t,
-    func: impl Fn(Caller<'_>, *const wasm_val_t, *mut wasm_val_t) -> Option<Box<wasm_trap_t>> + 'static,
+    func: impl Fn(Caller<'_>, *const wasm_val_t, *mut wasm_val_t) -> Option<Box<wasm_trap_t>>
+        + Send
+        + 'static,
 ) -> Box<wasm_func_t> {
     let store = &store.store;
     let ty = ty.ty().ty.clone();
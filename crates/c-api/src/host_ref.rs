@@ -1,47 +1,75 @@
 use std::any::Any;
-use std::cell::{self, RefCell};
 use std::convert::TryFrom;
 use std::marker::PhantomData;
-use wasmtime::{ExternRef, Store};
+use wasmtime::{ExternRef, Func, Global, Instance, Memory, Module, Store, Table, Trap};
 
 /// Represents a piece of data located in the host environment.
 #[derive(Debug)]
 pub struct HostRef<T>
 where
-    T: 'static + Any,
+    T: 'static + Any + Send,
 {
     externref: ExternRef,
     _phantom: PhantomData<T>,
 }
 
+/// A value which can be wrapped in a `HostRef`.
+pub trait HostRefItem: 'static + Any + Send + Sized {
+    /// Wraps `self` in an `ExternRef`.
+    fn into_externref(self, store: &Store) -> ExternRef;
+
+    /// Borrows the value wrapped by `externref`, if it's a `Self`.
+    fn from_externref(externref: &ExternRef) -> Option<&Self>;
+}
+
+macro_rules! data_items {
+    ($($item:ident)*) => ($(
+        impl HostRefItem for $item {
+            fn into_externref(self, store: &Store) -> ExternRef {
+                ExternRef::new(store, self)
+            }
+
+            fn from_externref(externref: &ExternRef) -> Option<&Self> {
+                externref.data().downcast_ref()
+            }
+        }
+    )*)
+}
+
+data_items!(Module Trap);
+
+// Store items aren't `Sync`, so they're wrapped as such.
+macro_rules! store_items {
+    ($($item:ident)*) => ($(
+        impl HostRefItem for $item {
+            fn into_externref(self, store: &Store) -> ExternRef {
+                ExternRef::from_store_item(store, self)
+            }
+
+            fn from_externref(externref: &ExternRef) -> Option<&Self> {
+                externref.store_item()
+            }
+        }
+    )*)
+}
+
+store_items!(Func Global Instance Memory Table);
+
 impl<T> HostRef<T>
 where
-    T: 'static + Any,
+    T: HostRefItem,
 {
     /// Creates a new `HostRef<T>` from `T`.
     pub fn new(store: &Store, item: T) -> HostRef<T> {
         HostRef {
-            externref: ExternRef::new(store, RefCell::new(item)),
+            externref: item.into_externref(store),
             _phantom: PhantomData,
         }
     }
 
     /// Immutably borrows the wrapped data.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the value is currently mutably borrowed.
-    pub fn borrow(&self) -> cell::Ref<T> {
-        self.inner().borrow()
-    }
-
-    /// Mutably borrows the wrapped data.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the `HostRef<T>` is already borrowed.
-    pub fn borrow_mut(&self) -> cell::RefMut<T> {
-        self.inner().borrow_mut()
+    pub fn borrow(&self) -> &T {
+        T::from_externref(&self.externref).expect("`HostRef<T>`s always wrap an `ExternRef` of `T`")
     }
 
     /// Returns true if the two `HostRef<T>`'s point to the same value (not just
@@ -49,16 +77,12 @@ where
     pub fn ptr_eq(&self, other: &HostRef<T>) -> bool {
         self.externref.ptr_eq(&other.externref)
     }
-
-    fn inner(&self) -> &RefCell<T> {
-        self.externref
-            .data()
-            .downcast_ref::<RefCell<T>>()
-            .expect("`HostRef<T>`s always wrap an `ExternRef` of `RefCell<T>`")
-    }
 }
 
-impl<T> AsRef<ExternRef> for HostRef<T> {
+impl<T> AsRef<ExternRef> for HostRef<T>
+where
+    T: 'static + Any + Send,
+{
     fn as_ref(&self) -> &ExternRef {
         &self.externref
     }
@@ -66,7 +90,7 @@ impl<T> AsRef<ExternRef> for HostRef<T> {
 
 impl<T> From<HostRef<T>> for ExternRef
 where
-    T: 'static + Any,
+    T: 'static + Any + Send,
 {
     fn from(host: HostRef<T>) -> ExternRef {
         host.externref
@@ -75,12 +99,12 @@ where
 
 impl<T> TryFrom<ExternRef> for HostRef<T>
 where
-    T: 'static + Any,
+    T: HostRefItem,
 {
     type Error = ExternRef;
 
     fn try_from(externref: ExternRef) -> Result<Self, ExternRef> {
-        if externref.data().is::<RefCell<T>>() {
+        if T::from_externref(&externref).is_some() {
             Ok(HostRef {
                 externref,
                 _phantom: PhantomData,
@@ -91,7 +115,10 @@ where
     }
 }
 
-impl<T> Clone for HostRef<T> {
+impl<T> Clone for HostRef<T>
+where
+    T: 'static + Any + Send,
+{
     fn clone(&self) -> HostRef<T> {
         HostRef {
             externref: self.externref.clone(),
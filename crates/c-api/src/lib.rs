@@ -69,6 +69,11 @@ struct HostInfoState {
     finalizer: Option<extern "C" fn(arg1: *mut std::ffi::c_void)>,
 }
 
+// Like function environments, it's up to the embedder to only move a store to
+// another thread if the host info attached to its references can be used from
+// there.
+unsafe impl Send for HostInfoState {}
+
 impl Drop for HostInfoState {
     fn drop(&mut self) {
         if let Some(f) = &self.finalizer {
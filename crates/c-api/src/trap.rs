@@ -1,7 +1,7 @@
 use crate::host_ref::HostRef;
 use crate::{wasm_frame_vec_t, wasm_instance_t, wasm_name_t, wasm_store_t};
 use once_cell::unsync::OnceCell;
-use wasmtime::{Store, Trap};
+use wasmtime::{Store, Trap, TrapCode};
 
 #[repr(C)]
 #[derive(Clone)]
@@ -103,6 +103,48 @@ pub extern "C" fn wasmtime_trap_exit_status(raw: &wasm_trap_t, status: &mut i32)
     }
 }
 
+pub type wasmtime_trap_code_t = u8;
+
+pub const WASMTIME_TRAP_CODE_STACK_OVERFLOW: wasmtime_trap_code_t = 0;
+pub const WASMTIME_TRAP_CODE_MEMORY_OUT_OF_BOUNDS: wasmtime_trap_code_t = 1;
+pub const WASMTIME_TRAP_CODE_TABLE_OUT_OF_BOUNDS: wasmtime_trap_code_t = 2;
+pub const WASMTIME_TRAP_CODE_INDIRECT_CALL_TO_NULL: wasmtime_trap_code_t = 3;
+pub const WASMTIME_TRAP_CODE_BAD_SIGNATURE: wasmtime_trap_code_t = 4;
+pub const WASMTIME_TRAP_CODE_INTEGER_OVERFLOW: wasmtime_trap_code_t = 5;
+pub const WASMTIME_TRAP_CODE_INTEGER_DIVISION_BY_ZERO: wasmtime_trap_code_t = 6;
+pub const WASMTIME_TRAP_CODE_BAD_CONVERSION_TO_INTEGER: wasmtime_trap_code_t = 7;
+pub const WASMTIME_TRAP_CODE_UNREACHABLE_CODE_REACHED: wasmtime_trap_code_t = 8;
+pub const WASMTIME_TRAP_CODE_INTERRUPT: wasmtime_trap_code_t = 9;
+pub const WASMTIME_TRAP_CODE_OUT_OF_FUEL: wasmtime_trap_code_t = 10;
+pub const WASMTIME_TRAP_CODE_HEAP_MISALIGNED: wasmtime_trap_code_t = 11;
+
+#[no_mangle]
+pub extern "C" fn wasmtime_trap_code(raw: &wasm_trap_t, code: &mut wasmtime_trap_code_t) -> bool {
+    let trap = raw.trap.borrow();
+    let trap_code = match trap.trap_code() {
+        Some(trap_code) => trap_code,
+        None => return false,
+    };
+    *code = match trap_code {
+        TrapCode::StackOverflow => WASMTIME_TRAP_CODE_STACK_OVERFLOW,
+        TrapCode::MemoryOutOfBounds => WASMTIME_TRAP_CODE_MEMORY_OUT_OF_BOUNDS,
+        TrapCode::TableOutOfBounds => WASMTIME_TRAP_CODE_TABLE_OUT_OF_BOUNDS,
+        TrapCode::IndirectCallToNull => WASMTIME_TRAP_CODE_INDIRECT_CALL_TO_NULL,
+        TrapCode::BadSignature => WASMTIME_TRAP_CODE_BAD_SIGNATURE,
+        TrapCode::IntegerOverflow => WASMTIME_TRAP_CODE_INTEGER_OVERFLOW,
+        TrapCode::IntegerDivisionByZero => WASMTIME_TRAP_CODE_INTEGER_DIVISION_BY_ZERO,
+        TrapCode::BadConversionToInteger => WASMTIME_TRAP_CODE_BAD_CONVERSION_TO_INTEGER,
+        TrapCode::UnreachableCodeReached => WASMTIME_TRAP_CODE_UNREACHABLE_CODE_REACHED,
+        TrapCode::Interrupt => WASMTIME_TRAP_CODE_INTERRUPT,
+        TrapCode::OutOfFuel => WASMTIME_TRAP_CODE_OUT_OF_FUEL,
+        TrapCode::HeapMisaligned => WASMTIME_TRAP_CODE_HEAP_MISALIGNED,
+        // Codes added to `wasmtime` without a C counterpart yet are reported
+        // as if the trap had no code.
+        _ => return false,
+    };
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn wasm_frame_func_index(frame: &wasm_frame_t) -> u32 {
     frame.trap.borrow().trace()[frame.idx].func_index()
@@ -12,10 +12,12 @@ use std::collections::HashMap;
 use wasmtime_environ::isa::TargetIsa;
 
 pub use crate::read_debuginfo::{read_debuginfo, DebugInfoData, WasmFileInfo};
+pub use crate::symbolize::{SourceFrame, Symbolizer};
 pub use crate::write_debuginfo::{emit_dwarf, DwarfSection};
 
 mod gc;
 mod read_debuginfo;
+mod symbolize;
 mod transform;
 mod write_debuginfo;
 
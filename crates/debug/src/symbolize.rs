@@ -0,0 +1,316 @@
+//! Resolution of wasm code offsets to source locations using the DWARF line
+//! tables and inlining information embedded in a wasm module.
+
+use crate::read_debuginfo::{read_debuginfo, Dwarf};
+use anyhow::Result;
+use gimli::{AttributeValue, ColumnType, EndianSlice, LittleEndian, Unit, UnitOffset};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+type Reader<'input> = EndianSlice<'input, LittleEndian>;
+
+/// A source-level frame which a wasm code offset belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFrame {
+    /// The name of the source function, possibly mangled.
+    pub function: Option<String>,
+    /// The path of the source file.
+    pub file: Option<String>,
+    /// The line in `file`.
+    pub line: Option<u32>,
+    /// The column in `line`.
+    pub column: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    file: Option<usize>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+#[derive(Debug)]
+struct LineRow {
+    address: u64,
+    /// `None` for the row ending a sequence, which covers no code.
+    location: Option<Location>,
+}
+
+#[derive(Debug)]
+struct FunctionRange {
+    begin: u64,
+    end: u64,
+    /// The depth of the function's entry in the DIE tree, which increases
+    /// with each level of inlining.
+    depth: isize,
+    name: Option<String>,
+    /// Where the function was inlined, if it's an inlined subroutine.
+    call_site: Option<Location>,
+}
+
+/// Source locations of the code of a wasm module, extracted from its DWARF.
+#[derive(Debug, Default)]
+pub struct Symbolizer {
+    code_section_offset: u64,
+    files: Vec<String>,
+    rows: Vec<LineRow>,
+    functions: Vec<FunctionRange>,
+}
+
+impl Symbolizer {
+    /// Reads the DWARF sections of the wasm module `wasm`.
+    ///
+    /// The result is empty if the module has no DWARF.
+    pub fn new(wasm: &[u8]) -> Result<Self> {
+        let debuginfo = read_debuginfo(wasm)?;
+        let mut symbolizer = Symbolizer {
+            code_section_offset: debuginfo.wasm_file.code_section_offset,
+            ..Symbolizer::default()
+        };
+        let dwarf = &debuginfo.dwarf;
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let mut files = HashMap::new();
+            symbolizer.add_line_rows(dwarf, &unit, &mut files)?;
+            symbolizer.add_functions(dwarf, &unit, &mut files)?;
+        }
+        // Sort rows which end a sequence before rows starting another one at
+        // the same address, so that the latter take precedence in `lookup`.
+        symbolizer
+            .rows
+            .sort_by_key(|row| (row.address, row.location.is_some()));
+        Ok(symbolizer)
+    }
+
+    /// Returns whether no source locations are known.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.functions.is_empty()
+    }
+
+    /// Returns the source frames for the instruction at `offset` in the wasm
+    /// module, innermost first.
+    ///
+    /// There's more than one frame if functions were inlined into the one the
+    /// instruction belongs to, and none if no source location is known.
+    pub fn lookup(&self, offset: u64) -> Vec<SourceFrame> {
+        let address = match offset.checked_sub(self.code_section_offset) {
+            Some(address) => address,
+            None => return Vec::new(),
+        };
+
+        // Find the last row at or before `address`.
+        let location = match self.rows.binary_search_by(|row| {
+            if row.address <= address {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }) {
+            Ok(_) => unreachable!(),
+            Err(0) => None,
+            Err(i) => self.rows[i - 1].location,
+        };
+
+        let mut functions = self
+            .functions
+            .iter()
+            .filter(|f| f.begin <= address && address < f.end)
+            .collect::<Vec<_>>();
+        functions.sort_by_key(|f| -f.depth);
+
+        if functions.is_empty() {
+            return location
+                .map(|location| vec![self.frame(None, Some(location))])
+                .unwrap_or_default();
+        }
+
+        // Each inlined function is described by its own location, and the
+        // call site of an inlined function is the location in its caller.
+        let mut frames = Vec::with_capacity(functions.len());
+        let mut location = location;
+        for function in functions {
+            frames.push(self.frame(function.name.clone(), location));
+            location = function.call_site;
+        }
+        frames
+    }
+
+    fn frame(&self, function: Option<String>, location: Option<Location>) -> SourceFrame {
+        let location = location.unwrap_or(Location {
+            file: None,
+            line: None,
+            column: None,
+        });
+        SourceFrame {
+            function,
+            file: location.file.map(|i| self.files[i].clone()),
+            line: location.line,
+            column: location.column,
+        }
+    }
+
+    fn add_line_rows(
+        &mut self,
+        dwarf: &Dwarf,
+        unit: &Unit<Reader>,
+        files: &mut HashMap<u64, Option<usize>>,
+    ) -> Result<()> {
+        let program = match &unit.line_program {
+            Some(program) => program.clone(),
+            None => return Ok(()),
+        };
+        let mut rows = program.rows();
+        while let Some((header, row)) = rows.next_row()? {
+            let location = if row.end_sequence() {
+                None
+            } else {
+                let file_index = row.file_index();
+                let file = match files.get(&file_index) {
+                    Some(file) => *file,
+                    None => {
+                        let file = self.file(dwarf, unit, header, file_index);
+                        files.insert(file_index, file);
+                        file
+                    }
+                };
+                Some(Location {
+                    file,
+                    line: row.line().map(|line| line as u32),
+                    column: match row.column() {
+                        ColumnType::LeftEdge => None,
+                        ColumnType::Column(column) => Some(column as u32),
+                    },
+                })
+            };
+            self.rows.push(LineRow {
+                address: row.address(),
+                location,
+            });
+        }
+        Ok(())
+    }
+
+    fn add_functions(
+        &mut self,
+        dwarf: &Dwarf,
+        unit: &Unit<Reader>,
+        files: &mut HashMap<u64, Option<usize>>,
+    ) -> Result<()> {
+        let header = unit.line_program.as_ref().map(|program| program.header());
+        let mut depth = 0;
+        let mut entries = unit.entries();
+        while let Some((delta, entry)) = entries.next_dfs()? {
+            depth += delta;
+            let call_site = match entry.tag() {
+                gimli::DW_TAG_subprogram => None,
+                gimli::DW_TAG_inlined_subroutine => {
+                    let file = match entry.attr_value(gimli::DW_AT_call_file)? {
+                        Some(AttributeValue::FileIndex(index)) => Some(index),
+                        Some(value) => value.udata_value(),
+                        None => None,
+                    };
+                    let file = match (file, header) {
+                        (Some(index), Some(header)) => match files.get(&index) {
+                            Some(file) => *file,
+                            None => {
+                                let file = self.file(dwarf, unit, header, index);
+                                files.insert(index, file);
+                                file
+                            }
+                        },
+                        _ => None,
+                    };
+                    let udata = |name| -> Result<Option<u32>> {
+                        Ok(entry
+                            .attr_value(name)?
+                            .and_then(|value| value.udata_value())
+                            .map(|value| value as u32))
+                    };
+                    Some(Location {
+                        file,
+                        line: udata(gimli::DW_AT_call_line)?,
+                        column: udata(gimli::DW_AT_call_column)?,
+                    })
+                }
+                _ => continue,
+            };
+            let name = function_name(dwarf, unit, entry.offset(), 0)?;
+            let mut ranges = dwarf.die_ranges(unit, entry)?;
+            while let Some(range) = ranges.next()? {
+                if range.begin >= range.end {
+                    continue;
+                }
+                self.functions.push(FunctionRange {
+                    begin: range.begin,
+                    end: range.end,
+                    depth,
+                    name: name.clone(),
+                    call_site,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn file(
+        &mut self,
+        dwarf: &Dwarf,
+        unit: &Unit<Reader>,
+        header: &gimli::LineProgramHeader<Reader>,
+        index: u64,
+    ) -> Option<usize> {
+        let file = header.file(index)?;
+        let mut path = PathBuf::new();
+        if let Some(comp_dir) = &unit.comp_dir {
+            path.push(&*comp_dir.to_string_lossy());
+        }
+        if let Some(directory) = file.directory(header) {
+            path.push(&*dwarf.attr_string(unit, directory).ok()?.to_string_lossy());
+        }
+        path.push(
+            &*dwarf
+                .attr_string(unit, file.path_name())
+                .ok()?
+                .to_string_lossy(),
+        );
+        let path = path.to_string_lossy().into_owned();
+        let index = match self.files.iter().position(|f| *f == path) {
+            Some(index) => index,
+            None => {
+                self.files.push(path);
+                self.files.len() - 1
+            }
+        };
+        Some(index)
+    }
+}
+
+/// Returns the name of the function described by the entry at `offset`,
+/// following references to its abstract origin or specification.
+fn function_name(
+    dwarf: &Dwarf,
+    unit: &Unit<Reader>,
+    offset: UnitOffset,
+    recursion: usize,
+) -> Result<Option<String>> {
+    // Guard against cycles in malformed DWARF.
+    if recursion > 16 {
+        return Ok(None);
+    }
+    let entry = unit.entry(offset)?;
+    for attr in &[gimli::DW_AT_linkage_name, gimli::DW_AT_name] {
+        if let Some(value) = entry.attr_value(*attr)? {
+            if let Ok(name) = dwarf.attr_string(unit, value) {
+                return Ok(Some(name.to_string_lossy().into_owned()));
+            }
+        }
+    }
+    for attr in &[gimli::DW_AT_abstract_origin, gimli::DW_AT_specification] {
+        if let Some(AttributeValue::UnitRef(offset)) = entry.attr_value(*attr)? {
+            return function_name(dwarf, unit, offset, recursion + 1);
+        }
+    }
+    Ok(None)
+}
@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 /// Tunable parameters for WebAssembly compilation.
-#[derive(Clone, Hash)]
+#[derive(Clone, Hash, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Tunables {
     /// For static heaps, the size in wasm pages of the heap protected by bounds checking.
     pub static_memory_bound: u32,
@@ -20,6 +22,10 @@ pub struct Tunables {
     /// calls and interrupts are implemented through the `VMInterrupts`
     /// structure, or `InterruptHandle` in the `wasmtime` crate.
     pub interruptable: bool,
+
+    /// Whether or not fuel is enabled for generated code, meaning that fuel
+    /// will be consumed every time a wasm instruction is executed.
+    pub consume_fuel: bool,
 }
 
 impl Default for Tunables {
@@ -53,6 +59,7 @@ impl Default for Tunables {
 
             debug_info: false,
             interruptable: false,
+            consume_fuel: false,
         }
     }
 }
@@ -0,0 +1,24 @@
+use std::path::Path;
+
+fn main() {
+    let arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    let family = std::env::var("CARGO_CFG_TARGET_FAMILY").unwrap();
+
+    // Windows uses the native fiber API of the OS, so there's no assembly to
+    // build there.
+    if family == "windows" {
+        return;
+    }
+
+    let file = format!("src/arch/{}.S", arch);
+    if !Path::new(&file).exists() {
+        panic!(
+            "wasmtime-fiber doesn't support the `{}` architecture yet; \
+             disable the `async` feature of the `wasmtime` crate to build \
+             without it",
+            arch
+        );
+    }
+    println!("cargo:rerun-if-changed={}", file);
+    cc::Build::new().file(&file).compile("wasmtime-fiber");
+}
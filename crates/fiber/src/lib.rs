@@ -0,0 +1,208 @@
+//! Stackful coroutines ("fibers") used by Wasmtime to execute WebAssembly on
+//! a separate native stack which can be suspended and resumed.
+
+#![deny(missing_docs)]
+
+use std::any::Any;
+use std::cell::Cell;
+use std::io;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+
+cfg_if::cfg_if! {
+    if #[cfg(windows)] {
+        mod windows;
+        use windows as imp;
+    } else if #[cfg(unix)] {
+        mod unix;
+        use unix as imp;
+    } else {
+        compile_error!("fibers are not supported on this platform");
+    }
+}
+
+/// A computation running on its own native stack which can be suspended and
+/// resumed.
+///
+/// The computation receives values of type `Resume` each time it's resumed,
+/// hands back values of type `Yield` each time it suspends itself, and
+/// finally produces a value of type `Return` when it completes.
+pub struct Fiber<'a, Resume, Yield, Return> {
+    inner: imp::Fiber,
+    done: Cell<bool>,
+    _phantom: PhantomData<&'a (Resume, Yield, Return)>,
+}
+
+/// A handle given to the body of a [`Fiber`] which is used to suspend its
+/// execution.
+pub struct Suspend<Resume, Yield, Return> {
+    inner: imp::Suspend,
+    _phantom: PhantomData<(Resume, Yield, Return)>,
+}
+
+/// The value exchanged between the two sides of a stack switch.
+enum RunResult<Resume, Yield, Return> {
+    Executing,
+    Resuming(Resume),
+    Yield(Yield),
+    Returned(Return),
+    Panicked(Box<dyn Any + Send>),
+}
+
+impl<'a, Resume, Yield, Return> Fiber<'a, Resume, Yield, Return> {
+    /// Creates a new fiber which will execute `func` on a freshly allocated
+    /// native stack of `stack_size` bytes.
+    ///
+    /// The body does not start running until the first call to
+    /// [`Fiber::resume`], whose argument becomes the first argument of
+    /// `func`.
+    pub fn new(
+        stack_size: usize,
+        func: impl FnOnce(Resume, &Suspend<Resume, Yield, Return>) -> Return + 'a,
+    ) -> io::Result<Fiber<'a, Resume, Yield, Return>> {
+        Ok(Fiber {
+            inner: imp::Fiber::new(stack_size, func)?,
+            done: Cell::new(false),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Resumes execution of this fiber, passing `val` to it.
+    ///
+    /// Returns `Ok` with the fiber's return value if the body ran to
+    /// completion, or `Err` with the value passed to [`Suspend::suspend`] if
+    /// the body suspended itself. A panic in the body is propagated to the
+    /// caller of this function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fiber has already finished executing.
+    pub fn resume(&self, val: Resume) -> Result<Return, Yield> {
+        assert!(!self.done.replace(true), "cannot resume a finished fiber");
+        let result = Cell::new(RunResult::Resuming(val));
+        self.inner.resume(&result);
+        match result.into_inner() {
+            RunResult::Resuming(_) | RunResult::Executing => unreachable!(),
+            RunResult::Yield(y) => {
+                self.done.set(false);
+                Err(y)
+            }
+            RunResult::Returned(r) => Ok(r),
+            RunResult::Panicked(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Returns whether this fiber has finished executing.
+    pub fn done(&self) -> bool {
+        self.done.get()
+    }
+}
+
+impl<Resume, Yield, Return> Suspend<Resume, Yield, Return> {
+    /// Suspends the execution of the current fiber, handing `value` back to
+    /// the caller of [`Fiber::resume`].
+    ///
+    /// Returns the value passed to the next call of [`Fiber::resume`].
+    pub fn suspend(&self, value: Yield) -> Resume {
+        self.inner
+            .switch::<Resume, Yield, Return>(RunResult::Yield(value))
+    }
+
+    fn execute(
+        inner: imp::Suspend,
+        initial: Resume,
+        func: impl FnOnce(Resume, &Suspend<Resume, Yield, Return>) -> Return,
+    ) {
+        let suspend = Suspend {
+            inner,
+            _phantom: PhantomData,
+        };
+        // Panics can't unwind off the top of a fiber's stack, so catch them
+        // here and hand them to the resuming side to be propagated there.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| func(initial, &suspend)));
+        suspend.inner.switch::<Resume, Yield, Return>(match result {
+            Ok(result) => RunResult::Returned(result),
+            Err(panic) => RunResult::Panicked(panic),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fiber;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    #[test]
+    fn small_stacks() {
+        Fiber::<(), (), ()>::new(0, |_, _| {})
+            .unwrap()
+            .resume(())
+            .unwrap();
+        Fiber::<(), (), ()>::new(1, |_, _| {})
+            .unwrap()
+            .resume(())
+            .unwrap();
+    }
+
+    #[test]
+    fn smoke() {
+        let hit = Rc::new(Cell::new(false));
+        let hit2 = hit.clone();
+        let fiber = Fiber::<(), (), ()>::new(1024 * 1024, move |_, _| {
+            hit2.set(true);
+        })
+        .unwrap();
+        assert!(!hit.get());
+        fiber.resume(()).unwrap();
+        assert!(hit.get());
+        assert!(fiber.done());
+    }
+
+    #[test]
+    fn suspend_and_resume() {
+        let fiber = Fiber::<i32, i32, i32>::new(1024 * 1024, move |x, s| {
+            assert_eq!(x, 1);
+            let y = s.suspend(x + 1);
+            assert_eq!(y, 3);
+            let z = s.suspend(y + 1);
+            z + 1
+        })
+        .unwrap();
+        assert_eq!(fiber.resume(1), Err(2));
+        assert!(!fiber.done());
+        assert_eq!(fiber.resume(3), Err(4));
+        assert_eq!(fiber.resume(5), Ok(6));
+        assert!(fiber.done());
+    }
+
+    #[test]
+    fn nested_fibers() {
+        let outer = Fiber::<(), i32, ()>::new(1024 * 1024, |_, s| {
+            let inner = Fiber::<(), i32, ()>::new(1024 * 1024, |_, s| {
+                s.suspend(1);
+                s.suspend(2);
+            })
+            .unwrap();
+            while let Err(n) = inner.resume(()) {
+                s.suspend(n * 10);
+            }
+        })
+        .unwrap();
+        assert_eq!(outer.resume(()), Err(10));
+        assert_eq!(outer.resume(()), Err(20));
+        assert_eq!(outer.resume(()), Ok(()));
+    }
+
+    #[test]
+    fn panics_propagated() {
+        let fiber = Fiber::<(), (), ()>::new(1024 * 1024, |_, _| {
+            panic!("test");
+        })
+        .unwrap();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| fiber.resume(())));
+        assert!(result.is_err());
+        assert!(fiber.done());
+    }
+}
@@ -8,6 +8,7 @@ use cranelift_codegen::print_errors::pretty_error;
 use cranelift_codegen::Context;
 use cranelift_codegen::{binemit, ir};
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use serde::{Deserialize, Serialize};
 use wasmtime_debug::{emit_dwarf, DebugInfoData, DwarfSection};
 use wasmtime_environ::entity::{EntityRef, PrimaryMap};
 use wasmtime_environ::isa::{TargetFrontendConfig, TargetIsa};
@@ -20,7 +21,7 @@ use wasmtime_environ::{
 use wasmtime_runtime::{InstantiationError, VMFunctionBody, VMTrampoline};
 
 /// Select which kind of compilation to use.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CompilationStrategy {
     /// Let Wasmtime pick the strategy.
     Auto,
@@ -33,6 +34,21 @@ pub enum CompilationStrategy {
     Lightbeam,
 }
 
+/// The WebAssembly proposals enabled when validating and translating modules.
+///
+/// Code compiled with one set of features may rely on them being enabled, so
+/// this is recorded alongside serialized modules.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub struct WasmFeatures {
+    pub threads: bool,
+    pub reference_types: bool,
+    pub simd: bool,
+    pub bulk_memory: bool,
+    pub multi_value: bool,
+    pub tail_call: bool,
+}
+
 /// A WebAssembly code JIT compiler.
 ///
 /// A `Compiler` instance owns the executable memory that it allocates.
@@ -46,6 +62,7 @@ pub struct Compiler {
     strategy: CompilationStrategy,
     cache_config: CacheConfig,
     tunables: Tunables,
+    features: WasmFeatures,
 }
 
 impl Compiler {
@@ -55,12 +72,14 @@ impl Compiler {
         strategy: CompilationStrategy,
         cache_config: CacheConfig,
         tunables: Tunables,
+        features: WasmFeatures,
     ) -> Self {
         Self {
             isa,
             strategy,
             cache_config,
             tunables,
+            features,
         }
     }
 }
@@ -76,7 +95,7 @@ fn transform_dwarf_data(
     debug_data: &DebugInfoData,
     address_transform: &ModuleAddressMap,
     value_ranges: &ValueLabelsRanges,
-    stack_slots: PrimaryMap<DefinedFuncIndex, ir::StackSlots>,
+    stack_slots: &PrimaryMap<DefinedFuncIndex, ir::StackSlots>,
     compilation: &wasmtime_environ::Compilation,
 ) -> Result<Vec<DwarfSection>, SetupError> {
     let target_config = isa.frontend_config();
@@ -93,7 +112,7 @@ fn transform_dwarf_data(
             } else {
                 ModuleMemoryOffset::None
             },
-            stack_slots,
+            stack_slots: stack_slots.clone(),
         }
     };
     emit_dwarf(
@@ -129,6 +148,21 @@ fn get_code_range(
     (start as *const u8, end - start)
 }
 
+/// The results of compiling a module's functions which don't depend on where
+/// the code is placed in memory, and which can therefore be serialized and
+/// loaded again later without recompiling.
+#[derive(Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub struct CompilationArtifacts {
+    pub compilation: wasmtime_environ::Compilation,
+    pub relocations: Relocations,
+    pub address_transform: ModuleAddressMap,
+    pub value_ranges: ValueLabelsRanges,
+    pub stack_slots: PrimaryMap<DefinedFuncIndex, ir::StackSlots>,
+    pub traps: Traps,
+    pub stack_maps: StackMaps,
+}
+
 #[allow(missing_docs)]
 pub struct Compilation {
     pub code_memory: CodeMemory,
@@ -137,9 +171,6 @@ pub struct Compilation {
     pub trampolines: PrimaryMap<SignatureIndex, VMTrampoline>,
     pub jt_offsets: PrimaryMap<DefinedFuncIndex, ir::JumpTableOffsets>,
     pub dwarf_sections: Vec<DwarfSection>,
-    pub traps: Traps,
-    pub stack_maps: StackMaps,
-    pub address_transform: ModuleAddressMap,
 }
 
 impl Compiler {
@@ -158,14 +189,25 @@ impl Compiler {
         &self.tunables
     }
 
+    /// Return the strategy functions are compiled with, with `Auto` resolved
+    /// to the strategy it currently stands for.
+    pub fn strategy(&self) -> CompilationStrategy {
+        match self.strategy {
+            CompilationStrategy::Auto => CompilationStrategy::Cranelift,
+            strategy => strategy,
+        }
+    }
+
+    /// Return the WebAssembly features enabled for this engine.
+    pub fn features(&self) -> &WasmFeatures {
+        &self.features
+    }
+
     /// Compile the given function bodies.
     pub(crate) fn compile<'data>(
         &self,
         translation: &ModuleTranslation,
-        debug_data: Option<DebugInfoData>,
-    ) -> Result<Compilation, SetupError> {
-        let mut code_memory = CodeMemory::new();
-
+    ) -> Result<CompilationArtifacts, SetupError> {
         let (
             compilation,
             relocations,
@@ -195,15 +237,37 @@ impl Compiler {
         }
         .map_err(SetupError::Compile)?;
 
+        Ok(CompilationArtifacts {
+            compilation,
+            relocations,
+            address_transform,
+            value_ranges,
+            stack_slots,
+            traps,
+            stack_maps,
+        })
+    }
+
+    /// Place previously compiled function bodies into executable memory and
+    /// generate the trampolines and debug information that go along with them.
+    pub(crate) fn link(
+        &self,
+        module: &Module,
+        artifacts: &CompilationArtifacts,
+        debug_data: Option<DebugInfoData>,
+    ) -> Result<Compilation, SetupError> {
+        let mut code_memory = CodeMemory::new();
+        let compilation = &artifacts.compilation;
+
         let dwarf_sections = if debug_data.is_some() && !compilation.is_empty() {
             transform_dwarf_data(
                 &*self.isa,
-                &translation.module,
+                module,
                 debug_data.as_ref().unwrap(),
-                &address_transform,
-                &value_ranges,
-                stack_slots,
-                &compilation,
+                &artifacts.address_transform,
+                &artifacts.value_ranges,
+                &artifacts.stack_slots,
+                compilation,
             )?
         } else {
             vec![]
@@ -211,13 +275,15 @@ impl Compiler {
 
         // Allocate all of the compiled functions into executable memory,
         // copying over their contents.
-        let finished_functions = allocate_functions(&mut code_memory, &compilation, &relocations)
-            .map_err(|message| {
-            SetupError::Instantiate(InstantiationError::Resource(format!(
-                "failed to allocate memory for functions: {}",
-                message
-            )))
-        })?;
+        let finished_functions =
+            allocate_functions(&mut code_memory, compilation, &artifacts.relocations).map_err(
+                |message| {
+                    SetupError::Instantiate(InstantiationError::Resource(format!(
+                        "failed to allocate memory for functions: {}",
+                        message
+                    )))
+                },
+            )?;
 
         // Eagerly generate a entry trampoline for every type signature in the
         // module. This should be "relatively lightweight" for most modules and
@@ -225,7 +291,7 @@ impl Compiler {
         // tables) have a trampoline when invoked through the wasmtime API.
         let mut cx = FunctionBuilderContext::new();
         let mut trampolines = PrimaryMap::new();
-        for (_, (_, native_sig)) in translation.module.local.signatures.iter() {
+        for (_, (_, native_sig)) in module.local.signatures.iter() {
             let trampoline = make_trampoline(
                 &*self.isa,
                 &mut code_memory,
@@ -237,7 +303,7 @@ impl Compiler {
         }
 
         let jt_offsets = compilation.get_jt_offsets();
-        let code_range = get_code_range(&compilation, &finished_functions);
+        let code_range = get_code_range(compilation, &finished_functions);
 
         Ok(Compilation {
             code_memory,
@@ -246,9 +312,6 @@ impl Compiler {
             trampolines,
             jt_offsets,
             dwarf_sections,
-            traps,
-            stack_maps,
-            address_transform,
         })
     }
 }
@@ -4,27 +4,32 @@
 //! steps.
 
 use crate::code_memory::CodeMemory;
-use crate::compiler::{Compilation, Compiler};
+use crate::compiler::{
+    Compilation, CompilationArtifacts, CompilationStrategy, Compiler, WasmFeatures,
+};
 use crate::imports::resolve_imports;
 use crate::link::link_module;
 use crate::resolver::Resolver;
+use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
+use wasmparser::{ModuleReader, SectionCode};
 use wasmtime_debug::{read_debuginfo, write_debugsections_image, DwarfSection};
 use wasmtime_environ::entity::{BoxedSlice, PrimaryMap};
 use wasmtime_environ::isa::TargetIsa;
 use wasmtime_environ::wasm::{DefinedFuncIndex, SignatureIndex};
 use wasmtime_environ::{
     CompileError, DataInitializer, DataInitializerLocation, Module, ModuleAddressMap,
-    ModuleEnvironment, ModuleTranslation, StackMaps, Traps,
+    ModuleEnvironment, ModuleTranslation, StackMaps, Traps, Tunables,
 };
 use wasmtime_profiling::ProfilingAgent;
 use wasmtime_runtime::VMInterrupts;
 use wasmtime_runtime::{
-    GdbJitImageRegistration, InstanceHandle, InstantiationError, RuntimeMemoryCreator,
-    SignatureRegistry, StackMapRegistry, VMExternRefActivationsTable, VMFunctionBody, VMTrampoline,
+    GdbJitImageRegistration, InstanceAllocationRequest, InstanceAllocator, InstanceHandle,
+    InstantiationError, MemoryImages, ResourceTracker, SignatureRegistry, StackMapRegistry,
+    VMExternRefActivationsTable, VMFunctionBody, VMTrampoline,
 };
 
 /// An error condition while setting up a wasm instance, be it validation,
@@ -47,6 +52,11 @@ pub enum SetupError {
     /// Debug information generation error occured.
     #[error("Debug information error")]
     DebugInfo(#[from] anyhow::Error),
+
+    /// A precompiled module could not be serialized or loaded, for example
+    /// because it was produced with an incompatible configuration.
+    #[error("Module serialization error: {0}")]
+    Serialization(String),
 }
 
 struct FinishedFunctions(BoxedSlice<DefinedFuncIndex, *mut [VMFunctionBody]>);
@@ -67,10 +77,77 @@ pub struct CompiledModule {
     code: Arc<ModuleCode>,
     finished_functions: FinishedFunctions,
     trampolines: PrimaryMap<SignatureIndex, VMTrampoline>,
+    memory_images: MemoryImages,
     data_initializers: Box<[OwnedDataInitializer]>,
-    traps: Traps,
-    stack_maps: StackMaps,
-    address_transform: ModuleAddressMap,
+    artifacts: CompilationArtifacts,
+    wasm: Option<Box<[u8]>>,
+}
+
+/// Bytes at the start of every serialized module, used to quickly reject data
+/// that was never produced by `CompiledModule::precompile`.
+const SERIALIZED_MODULE_MAGIC: &[u8] = b"\0wasmtime-module";
+
+/// Describes the configuration a serialized module was compiled with. A
+/// module can only be deserialized by a `Compiler` which matches exactly.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct SerializedModuleHeader {
+    version: String,
+    triple: String,
+    isa_flags: String,
+    strategy: CompilationStrategy,
+    features: WasmFeatures,
+    tunables: Tunables,
+}
+
+impl SerializedModuleHeader {
+    fn new(compiler: &Compiler) -> Self {
+        Self {
+            version: crate::VERSION.to_string(),
+            triple: compiler.isa().triple().to_string(),
+            isa_flags: compiler.isa().to_string(),
+            strategy: compiler.strategy(),
+            features: *compiler.features(),
+            tunables: compiler.tunables().clone(),
+        }
+    }
+
+    fn check_compatible(&self, compiler: &Compiler) -> Result<(), SetupError> {
+        let expected = Self::new(compiler);
+        let mismatch = if self.version != expected.version {
+            format!(
+                "compiled with wasmtime {} but this is wasmtime {}",
+                self.version, expected.version
+            )
+        } else if self.triple != expected.triple {
+            format!(
+                "compiled for target `{}` but the engine targets `{}`",
+                self.triple, expected.triple
+            )
+        } else if self.isa_flags != expected.isa_flags {
+            "compiled with different code generation settings than the engine".to_string()
+        } else if self.strategy != expected.strategy {
+            format!(
+                "compiled with the {:?} strategy but the engine uses {:?}",
+                self.strategy, expected.strategy
+            )
+        } else if self.features != expected.features {
+            format!(
+                "compiled with wasm features {:?} but the engine enables {:?}",
+                self.features, expected.features
+            )
+        } else if self.tunables != expected.tunables {
+            format!(
+                "compiled with tunables {:?} but the engine uses {:?}",
+                self.tunables, expected.tunables
+            )
+        } else {
+            return Ok(());
+        };
+        Err(SetupError::Serialization(format!(
+            "module is incompatible with this engine: {}",
+            mismatch
+        )))
+    }
 }
 
 impl CompiledModule {
@@ -80,16 +157,161 @@ impl CompiledModule {
         data: &'data [u8],
         profiler: &dyn ProfilingAgent,
     ) -> Result<Self, SetupError> {
-        let environ = ModuleEnvironment::new(compiler.frontend_config(), compiler.tunables());
+        let translation = translate(compiler, data)?;
+        let artifacts = compiler.compile(&translation)?;
+        let ModuleTranslation {
+            module,
+            data_initializers,
+            ..
+        } = translation;
+        let wasm = debug_wasm(compiler, data);
+        Self::from_artifacts(
+            compiler,
+            module,
+            data_initializers,
+            artifacts,
+            wasm,
+            profiler,
+        )
+    }
 
-        let translation = environ
-            .translate(data)
-            .map_err(|error| SetupError::Compile(CompileError::Wasm(error)))?;
+    /// Compile a data buffer into a byte vector which can later be loaded
+    /// with `CompiledModule::deserialize`, without making its code executable.
+    ///
+    /// Only the translated module and its compiled code are kept; the
+    /// original wasm is included only if it's needed for debug information.
+    pub fn precompile(compiler: &Compiler, data: &[u8]) -> Result<Vec<u8>, SetupError> {
+        let translation = translate(compiler, data)?;
+        let artifacts = compiler.compile(&translation)?;
+        let data_initializers = translation
+            .data_initializers
+            .into_iter()
+            .map(OwnedDataInitializer::new)
+            .collect::<Vec<_>>();
+        write_serialized(
+            compiler,
+            &translation.module,
+            &data_initializers,
+            &artifacts,
+            &debug_wasm(compiler, data),
+        )
+    }
 
+    /// Serialize this module into a byte vector which can later be loaded
+    /// with `CompiledModule::deserialize`, in the same format as
+    /// `CompiledModule::precompile`. `compiler` must be the one this module
+    /// was compiled or deserialized with.
+    pub fn serialize(&self, compiler: &Compiler) -> Result<Vec<u8>, SetupError> {
+        // The data segments which were evaluated into memory images are
+        // written as one segment per image, at the start of its memory. They
+        // come before the other segments, just as the images are applied
+        // first when instantiating.
+        let mut data_initializers = Vec::new();
+        for (index, image) in self.memory_images.iter() {
+            let data = image
+                .to_vec()
+                .map_err(|e| SetupError::Serialization(format!("failed to serialize: {}", e)))?;
+            data_initializers.push(OwnedDataInitializer {
+                location: DataInitializerLocation {
+                    memory_index: self.module.local.memory_index(index),
+                    base: None,
+                    offset: 0,
+                },
+                data: data.into_boxed_slice(),
+            });
+        }
+        data_initializers.extend(
+            self.data_initializers
+                .iter()
+                .map(|init| OwnedDataInitializer {
+                    location: init.location.clone(),
+                    data: init.data.clone(),
+                }),
+        );
+        write_serialized(
+            compiler,
+            &self.module,
+            &data_initializers,
+            &self.artifacts,
+            &self.wasm,
+        )
+    }
+
+    /// Returns whether `bytes` look like a module produced by
+    /// `CompiledModule::precompile`, rather than a wasm module.
+    pub fn is_serialized(bytes: &[u8]) -> bool {
+        bytes.starts_with(SERIALIZED_MODULE_MAGIC)
+    }
+
+    /// Load a module previously produced by `CompiledModule::precompile`
+    /// without translating or compiling it again.
+    ///
+    /// # Unsafety
+    ///
+    /// The machine code and metadata contained in `bytes` are trusted and
+    /// used as-is. Only the configuration they were compiled with is checked
+    /// against `compiler`, so `bytes` must come from a trusted call to
+    /// `precompile`.
+    pub unsafe fn deserialize(
+        compiler: &Compiler,
+        bytes: &[u8],
+        profiler: &dyn ProfilingAgent,
+    ) -> Result<Self, SetupError> {
+        fn read<T: serde::de::DeserializeOwned>(bytes: &mut &[u8]) -> Result<T, SetupError> {
+            bincode::deserialize_from(bytes)
+                .map_err(|e| SetupError::Serialization(format!("malformed data: {}", e)))
+        }
+
+        if !Self::is_serialized(bytes) {
+            return Err(SetupError::Serialization(
+                "data is not a serialized wasmtime module".to_string(),
+            ));
+        }
+        let mut bytes = &bytes[SERIALIZED_MODULE_MAGIC.len()..];
+        let header: SerializedModuleHeader = read(&mut bytes)?;
+        header.check_compatible(compiler)?;
+        let module: Module = read(&mut bytes)?;
+        let data_initializers: Vec<OwnedDataInitializer> = read(&mut bytes)?;
+        let artifacts: CompilationArtifacts = read(&mut bytes)?;
+        let wasm: Option<Box<[u8]>> = read(&mut bytes)?;
+
+        let num_defined_funcs = module.local.functions.len() - module.local.num_imported_funcs;
+        if artifacts.compilation.len() != num_defined_funcs {
+            return Err(SetupError::Serialization(
+                "compiled code does not match the module's functions".to_string(),
+            ));
+        }
+        let data_initializers = data_initializers
+            .iter()
+            .map(|init| DataInitializer {
+                location: init.location.clone(),
+                data: &*init.data,
+            })
+            .collect();
+        Self::from_artifacts(
+            compiler,
+            module,
+            data_initializers,
+            artifacts,
+            wasm,
+            profiler,
+        )
+    }
+
+    fn from_artifacts(
+        compiler: &Compiler,
+        module: Module,
+        data_initializers: Vec<DataInitializer<'_>>,
+        artifacts: CompilationArtifacts,
+        wasm: Option<Box<[u8]>>,
+        profiler: &dyn ProfilingAgent,
+    ) -> Result<Self, SetupError> {
         let mut debug_data = None;
         if compiler.tunables().debug_info {
-            // TODO Do we want to ignore invalid DWARF data?
-            debug_data = Some(read_debuginfo(&data)?);
+            if let Some(wasm) = &wasm {
+                // TODO Do we want to ignore invalid DWARF data?
+                debug_data = Some(read_debuginfo(wasm)?);
+            }
         }
 
         let Compilation {
@@ -99,22 +321,16 @@ impl CompiledModule {
             trampolines,
             jt_offsets,
             dwarf_sections,
-            traps,
-            stack_maps,
-            address_transform,
-        } = compiler.compile(&translation, debug_data)?;
-
-        let ModuleTranslation {
-            module,
-            data_initializers,
-            ..
-        } = translation;
+        } = compiler.link(&module, &artifacts, debug_data)?;
 
         link_module(&mut code_memory, &module, &finished_functions, &jt_offsets);
 
         // Make all code compiled thus far executable.
         code_memory.publish(compiler.isa());
 
+        // Evaluate as many data segments as possible into memory images now,
+        // so that instantiation only needs to map them.
+        let (memory_images, data_initializers) = MemoryImages::new(&module, data_initializers);
         let data_initializers = data_initializers
             .into_iter()
             .map(OwnedDataInitializer::new)
@@ -139,6 +355,8 @@ impl CompiledModule {
             None
         };
 
+        // The artifacts are kept, alongside the executable copy of the code
+        // in `code_memory`, so that the module can be serialized again.
         let finished_functions = FinishedFunctions(finished_functions.into_boxed_slice());
         Ok(Self {
             module: Arc::new(module),
@@ -148,10 +366,10 @@ impl CompiledModule {
             }),
             finished_functions,
             trampolines,
+            memory_images,
             data_initializers,
-            traps,
-            stack_maps,
-            address_transform,
+            artifacts,
+            wasm,
         })
     }
 
@@ -163,16 +381,17 @@ impl CompiledModule {
     ///
     /// # Unsafety
     ///
-    /// See `InstanceHandle::new`
+    /// See `InstanceAllocator::allocate`
     pub unsafe fn instantiate(
         &self,
         resolver: &mut dyn Resolver,
         signature_registry: &mut SignatureRegistry,
-        mem_creator: Option<&dyn RuntimeMemoryCreator>,
+        allocator: &dyn InstanceAllocator,
         interrupts: Arc<VMInterrupts>,
-        host_state: Box<dyn Any>,
+        host_state: Box<dyn Any + Send>,
         externref_activations_table: *mut VMExternRefActivationsTable,
         stack_map_registry: *mut StackMapRegistry,
+        resources: *const ResourceTracker,
     ) -> Result<InstanceHandle, InstantiationError> {
         // Compute indices into the shared signature table.
         let signatures = {
@@ -194,22 +413,34 @@ impl CompiledModule {
         let finished_functions = self.finished_functions.0.clone();
 
         let imports = resolve_imports(&self.module, signature_registry, resolver)?;
-        InstanceHandle::new(
-            self.module.clone(),
-            self.code.clone(),
+        allocator.allocate(InstanceAllocationRequest {
+            module: self.module.clone(),
+            code: self.code.clone(),
             finished_functions,
             trampolines,
             imports,
-            mem_creator,
-            signatures.into_boxed_slice(),
+            shared_signatures: signatures.into_boxed_slice(),
             host_state,
             interrupts,
             externref_activations_table,
             stack_map_registry,
-        )
+            resources,
+        })
+    }
+
+    /// Returns the original wasm binary this module was compiled from, if it
+    /// was kept around for its debug information.
+    pub fn wasm(&self) -> Option<&[u8]> {
+        self.wasm.as_deref()
+    }
+
+    /// Returns the memory images to pass to `InstanceHandle::initialize`.
+    pub fn memory_images(&self) -> &MemoryImages {
+        &self.memory_images
     }
 
-    /// Returns data initializers to pass to `InstanceHandle::initialize`
+    /// Returns data initializers to pass to `InstanceHandle::initialize`,
+    /// which only include the data segments not covered by `memory_images`.
     pub fn data_initializers(&self) -> Vec<DataInitializer<'_>> {
         self.data_initializers
             .iter()
@@ -237,17 +468,17 @@ impl CompiledModule {
 
     /// Returns the map for all traps in this module.
     pub fn traps(&self) -> &Traps {
-        &self.traps
+        &self.artifacts.traps
     }
 
     /// Returns the map for each of this module's stack maps.
     pub fn stack_maps(&self) -> &StackMaps {
-        &self.stack_maps
+        &self.artifacts.stack_maps
     }
 
     /// Returns a map of compiled addresses back to original bytecode offsets.
     pub fn address_transform(&self) -> &ModuleAddressMap {
-        &self.address_transform
+        &self.artifacts.address_transform
     }
 
     /// Returns all ranges convered by JIT code.
@@ -263,6 +494,7 @@ impl CompiledModule {
 
 /// Similar to `DataInitializer`, but owns its own copy of the data rather
 /// than holding a slice of the original module.
+#[derive(Serialize, Deserialize)]
 pub struct OwnedDataInitializer {
     /// The location where the initialization is to be performed.
     location: DataInitializerLocation,
@@ -280,6 +512,68 @@ impl OwnedDataInitializer {
     }
 }
 
+/// Writes the parts of a module in the format read by
+/// `CompiledModule::deserialize`.
+fn write_serialized(
+    compiler: &Compiler,
+    module: &Module,
+    data_initializers: &[OwnedDataInitializer],
+    artifacts: &CompilationArtifacts,
+    wasm: &Option<Box<[u8]>>,
+) -> Result<Vec<u8>, SetupError> {
+    fn write(bytes: &mut Vec<u8>, value: &impl Serialize) -> Result<(), SetupError> {
+        bincode::serialize_into(bytes, value)
+            .map_err(|e| SetupError::Serialization(format!("failed to serialize: {}", e)))
+    }
+
+    let mut bytes = SERIALIZED_MODULE_MAGIC.to_vec();
+    write(&mut bytes, &SerializedModuleHeader::new(compiler))?;
+    write(&mut bytes, module)?;
+    write(&mut bytes, &data_initializers)?;
+    write(&mut bytes, artifacts)?;
+    write(&mut bytes, wasm)?;
+    Ok(bytes)
+}
+
+fn translate<'data>(
+    compiler: &Compiler,
+    data: &'data [u8],
+) -> Result<ModuleTranslation<'data>, SetupError> {
+    ModuleEnvironment::new(compiler.frontend_config(), compiler.tunables())
+        .translate(data)
+        .map_err(|error| SetupError::Compile(CompileError::Wasm(error)))
+}
+
+/// Returns a copy of `data` if it will be needed after compilation, either to
+/// generate debug information or to symbolize backtraces with its DWARF.
+fn debug_wasm(compiler: &Compiler, data: &[u8]) -> Option<Box<[u8]>> {
+    if compiler.tunables().debug_info || has_dwarf(data) {
+        Some(data.into())
+    } else {
+        None
+    }
+}
+
+fn has_dwarf(data: &[u8]) -> bool {
+    let mut reader = match ModuleReader::new(data) {
+        Ok(reader) => reader,
+        Err(_) => return false,
+    };
+    while !reader.eof() {
+        match reader.read() {
+            Ok(section) => {
+                if let SectionCode::Custom { name, .. } = section.code {
+                    if name.starts_with(".debug_") {
+                        return true;
+                    }
+                }
+            }
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
 fn create_dbg_image(
     dwarf_sections: Vec<DwarfSection>,
     isa: &dyn TargetIsa,
@@ -33,7 +33,9 @@ pub mod native;
 pub mod trampoline;
 
 pub use crate::code_memory::CodeMemory;
-pub use crate::compiler::{make_trampoline, Compilation, CompilationStrategy, Compiler};
+pub use crate::compiler::{
+    make_trampoline, Compilation, CompilationArtifacts, CompilationStrategy, Compiler, WasmFeatures,
+};
 pub use crate::instantiate::{CompiledModule, SetupError};
 pub use crate::link::link_module;
 pub use crate::resolver::{NullResolver, Resolver};
@@ -3,8 +3,8 @@
 //! `VMExternRef` is a reference-counted box for any kind of data that is
 //! external and opaque to running Wasm. Sometimes it might hold a Wasmtime
 //! thing, other times it might hold something from a Wasmtime embedder and is
-//! opaque even to us. It is morally equivalent to `Rc<dyn Any>` in Rust, but
-//! additionally always fits in a pointer-sized word. `VMExternRef` is
+//! opaque even to us. It is morally equivalent to `Arc<dyn Any + Send + Sync>`
+//! in Rust, but additionally always fits in a pointer-sized word. `VMExternRef` is
 //! non-nullable, but `Option<VMExternRef>` is a null pointer.
 //!
 //! The one part of `VMExternRef` that can't ever be opaque to us is the
@@ -109,7 +109,8 @@ use std::hash::{Hash, Hasher};
 use std::mem;
 use std::ops::Deref;
 use std::ptr::{self, NonNull};
-use std::rc::Rc;
+use std::sync::atomic::{self, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use wasmtime_environ::{ir::Stackmap, StackMapInformation};
 
 /// An external reference to some opaque data.
@@ -125,8 +126,12 @@ use wasmtime_environ::{ir::Stackmap, StackMapInformation};
 /// `VMExternRef`s are reference counted, so cloning is a cheap, shallow
 /// operation. It also means they are inherently shared, so you may not get a
 /// mutable, exclusive reference to their inner contents, only a shared,
-/// immutable reference. You may use interior mutability with `RefCell` or
-/// `Mutex` to work around this restriction, if necessary.
+/// immutable reference. You may use interior mutability with `Mutex` or
+/// `RwLock` to work around this restriction, if necessary.
+///
+/// Like `Arc`, `VMExternRef`s are atomically reference counted and can be sent
+/// to and shared with other threads, so the values they hold must be `Send` and
+/// `Sync`.
 ///
 /// `VMExternRef`s have pointer-equality semantics, not structural-equality
 /// semantics. Given two `VMExternRef`s `a` and `b`, `a == b` only if `a` and
@@ -139,7 +144,7 @@ use wasmtime_environ::{ir::Stackmap, StackMapInformation};
 ///
 /// ```
 /// # fn foo() -> Result<(), Box<dyn std::error::Error>> {
-/// use std::cell::RefCell;
+/// use std::sync::Mutex;
 /// use wasmtime_runtime::VMExternRef;
 ///
 /// // Open a file. Wasm doesn't know about files, but we can let Wasm instances
@@ -147,17 +152,17 @@ use wasmtime_environ::{ir::Stackmap, StackMapInformation};
 /// let file = std::fs::File::create("some/file/path")?;
 ///
 /// // Wrap the file up as an `VMExternRef` that can be passed to Wasm.
-/// let extern_ref_to_file = VMExternRef::new(RefCell::new(file));
+/// let extern_ref_to_file = VMExternRef::new(Mutex::new(file));
 ///
 /// // `VMExternRef`s dereference to `dyn Any`, so you can use `Any` methods to
 /// // perform runtime type checks and downcasts.
 ///
-/// assert!(extern_ref_to_file.is::<RefCell<std::fs::File>>());
+/// assert!(extern_ref_to_file.is::<Mutex<std::fs::File>>());
 /// assert!(!extern_ref_to_file.is::<String>());
 ///
-/// if let Some(file) = extern_ref_to_file.downcast_ref::<RefCell<std::fs::File>>() {
+/// if let Some(file) = extern_ref_to_file.downcast_ref::<Mutex<std::fs::File>>() {
 ///     use std::io::Write;
-///     let mut file = file.borrow_mut();
+///     let mut file = file.lock().unwrap();
 ///     writeln!(&mut file, "Hello, `VMExternRef`!")?;
 /// }
 /// # Ok(())
@@ -167,6 +172,11 @@ use wasmtime_environ::{ir::Stackmap, StackMapInformation};
 #[repr(transparent)]
 pub struct VMExternRef(NonNull<VMExternData>);
 
+// The reference count is atomic and the value is required to be `Send` and
+// `Sync`, so `VMExternRef`s can be used from any thread just like `Arc`s.
+unsafe impl Send for VMExternRef {}
+unsafe impl Sync for VMExternRef {}
+
 #[repr(C)]
 struct VMExternData {
     // Implicit, dynamically-sized member that always preceded an
@@ -176,17 +186,18 @@ struct VMExternData {
     //
     /// The reference count for this `VMExternData` and value. When it reaches
     /// zero, we can safely destroy the value and free this heap
-    /// allocation. This is an `UnsafeCell`, rather than plain `Cell`, because
+    /// allocation. This is atomic because references may be cloned and dropped
+    /// on different threads, and it has the same layout as a `usize` because
     /// it can be modified by compiled Wasm code.
     ///
     /// Note: this field's offset must be kept in sync with
     /// `wasmtime_environ::VMOffsets::vm_extern_data_ref_count()` which is
     /// currently always zero.
-    ref_count: UnsafeCell<usize>,
+    ref_count: AtomicUsize,
 
     /// Always points to the implicit, dynamically-sized `value` member that
     /// precedes this `VMExternData`.
-    value_ptr: NonNull<dyn Any>,
+    value_ptr: NonNull<dyn Any + Send + Sync>,
 }
 
 impl Clone for VMExternRef {
@@ -201,8 +212,11 @@ impl Drop for VMExternRef {
     #[inline]
     fn drop(&mut self) {
         let data = self.extern_data();
-        data.decrement_ref_count();
-        if data.get_ref_count() == 0 {
+        if data.decrement_ref_count() == 0 {
+            // Like `Arc`, synchronize with the decrements of other threads
+            // before destroying the value they were referencing.
+            atomic::fence(AtomicOrdering::Acquire);
+
             // Drop our live reference to `data` before we drop it itself.
             drop(data);
             unsafe {
@@ -264,23 +278,18 @@ impl VMExternData {
 
     #[inline]
     fn get_ref_count(&self) -> usize {
-        unsafe { *self.ref_count.get() }
+        self.ref_count.load(AtomicOrdering::SeqCst)
     }
 
     #[inline]
     fn increment_ref_count(&self) {
-        unsafe {
-            let count = self.ref_count.get();
-            *count += 1;
-        }
+        self.ref_count.fetch_add(1, AtomicOrdering::Relaxed);
     }
 
+    /// Decrements the reference count, returning the new count.
     #[inline]
-    fn decrement_ref_count(&self) {
-        unsafe {
-            let count = self.ref_count.get();
-            *count -= 1;
-        }
+    fn decrement_ref_count(&self) -> usize {
+        self.ref_count.fetch_sub(1, AtomicOrdering::Release) - 1
     }
 }
 
@@ -295,7 +304,7 @@ impl VMExternRef {
     /// Wrap the given value inside an `VMExternRef`.
     pub fn new<T>(value: T) -> VMExternRef
     where
-        T: 'static + Any,
+        T: 'static + Any + Send + Sync,
     {
         VMExternRef::new_with(|| value)
     }
@@ -303,7 +312,7 @@ impl VMExternRef {
     /// Construct a new `VMExternRef` in place by invoking `make_value`.
     pub fn new_with<T>(make_value: impl FnOnce() -> T) -> VMExternRef
     where
-        T: 'static + Any,
+        T: 'static + Any + Send + Sync,
     {
         unsafe {
             let (layout, footer_offset) =
@@ -318,9 +327,9 @@ impl VMExternRef {
             ptr::write(value_ptr.as_ptr(), make_value());
 
             let value_ref: &T = value_ptr.as_ref();
-            let value_ref: &dyn Any = value_ref as _;
-            let value_ptr: *const dyn Any = value_ref as _;
-            let value_ptr: *mut dyn Any = value_ptr as _;
+            let value_ref: &(dyn Any + Send + Sync) = value_ref as _;
+            let value_ptr: *const (dyn Any + Send + Sync) = value_ref as _;
+            let value_ptr: *mut (dyn Any + Send + Sync) = value_ptr as _;
             let value_ptr = NonNull::new_unchecked(value_ptr);
 
             let extern_data_ptr =
@@ -328,7 +337,7 @@ impl VMExternRef {
             ptr::write(
                 extern_data_ptr,
                 VMExternData {
-                    ref_count: UnsafeCell::new(1),
+                    ref_count: AtomicUsize::new(1),
                     value_ptr,
                 },
             );
@@ -504,6 +513,10 @@ pub struct VMExternRefActivationsTable {
     stack_canary: Cell<Option<NonNull<u8>>>,
 }
 
+// The `next` and `end` pointers only ever point into the table's own `chunk`,
+// so the table can be moved to another thread along with everything in it.
+unsafe impl Send for VMExternRefActivationsTable {}
+
 impl VMExternRefActivationsTable {
     const CHUNK_SIZE: usize = 4096 / mem::size_of::<usize>();
 
@@ -745,7 +758,7 @@ struct ModuleStackMaps {
 
     /// A map from a PC in this module (that is a GC safepoint) to its
     /// associated stack map.
-    pc_to_stack_map: Vec<(usize, Rc<Stackmap>)>,
+    pc_to_stack_map: Vec<(usize, Arc<Stackmap>)>,
 }
 
 impl StackMapRegistry {
@@ -773,7 +786,7 @@ impl StackMapRegistry {
                 assert!((info.code_offset as usize) < len);
                 pc_to_stack_map.push((
                     range.start + (info.code_offset as usize),
-                    Rc::new(info.stack_map.clone()),
+                    Arc::new(info.stack_map.clone()),
                 ));
             }
         }
@@ -814,7 +827,7 @@ impl StackMapRegistry {
     }
 
     /// Lookup the stack map for the given PC, if any.
-    pub fn lookup_stack_map(&self, pc: usize) -> Option<Rc<Stackmap>> {
+    pub fn lookup_stack_map(&self, pc: usize) -> Option<Arc<Stackmap>> {
         let inner = self.inner.borrow();
         let stack_maps = inner.module_stack_maps(pc)?;
 
@@ -1088,12 +1101,12 @@ mod tests {
     #[test]
     fn ref_count_is_at_correct_offset() {
         let s = "hi";
-        let s: &dyn Any = &s as _;
-        let s: *const dyn Any = s as _;
-        let s: *mut dyn Any = s as _;
+        let s: &(dyn Any + Send + Sync) = &s as _;
+        let s: *const (dyn Any + Send + Sync) = s as _;
+        let s: *mut (dyn Any + Send + Sync) = s as _;
 
         let extern_data = VMExternData {
-            ref_count: UnsafeCell::new(0),
+            ref_count: AtomicUsize::new(0),
             value_ptr: NonNull::new(s).unwrap(),
         };
 
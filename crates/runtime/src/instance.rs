@@ -4,8 +4,10 @@
 
 use crate::export::Export;
 use crate::externref::{StackMapRegistry, VMExternRefActivationsTable};
-use crate::imports::Imports;
-use crate::memory::{DefaultMemoryCreator, RuntimeLinearMemory, RuntimeMemoryCreator};
+use crate::limits::{pages_to_bytes, ResourceTracker};
+use crate::memory::{RuntimeLinearMemory, SharedMemory};
+use crate::memory_image::MemoryImages;
+use crate::parking_spot::{ParkResult, PARKING_SPOT};
 use crate::table::{Table, TableElement};
 use crate::traphandlers::Trap;
 use crate::vmcontext::{
@@ -16,13 +18,14 @@ use crate::vmcontext::{
 use crate::{ExportFunction, ExportGlobal, ExportMemory, ExportTable};
 use memoffset::offset_of;
 use more_asserts::assert_lt;
-use std::alloc::{self, Layout};
+use std::alloc::Layout;
 use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::ptr::NonNull;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{mem, ptr, slice};
 use thiserror::Error;
 use wasmtime_environ::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
@@ -33,6 +36,10 @@ use wasmtime_environ::wasm::{
 };
 use wasmtime_environ::{ir, DataInitializer, EntityIndex, Module, TableElements, VMOffsets};
 
+mod allocator;
+
+pub use self::allocator::*;
+
 /// A WebAssembly instance.
 ///
 /// This is repr(C) to ensure that the vmctx field is last.
@@ -69,12 +76,16 @@ pub(crate) struct Instance {
     trampolines: HashMap<VMSharedSignatureIndex, VMTrampoline>,
 
     /// Hosts can store arbitrary per-instance information here.
-    host_state: Box<dyn Any>,
+    host_state: Box<dyn Any + Send>,
 
     /// Externally allocated data indicating how this instance will be
     /// interrupted.
     pub(crate) interrupts: Arc<VMInterrupts>,
 
+    /// Tracks the resources used by the store this instance belongs to, and
+    /// limits their growth.
+    resources: *const ResourceTracker,
+
     /// Additional context used by compiled wasm code. This field is last, and
     /// represents a dynamically-sized array that extends beyond the nominal
     /// end of the struct (similar to a flexible array member).
@@ -186,7 +197,11 @@ impl Instance {
 
     /// Return the indexed `VMMemoryDefinition`.
     fn memory(&self, index: DefinedMemoryIndex) -> VMMemoryDefinition {
-        unsafe { *self.memory_ptr(index) }
+        // Another thread may have grown a shared memory since the `VMContext` was last updated.
+        match self.memories[index].as_shared() {
+            Some(shared) => unsafe { *shared.vmmemory_ptr() },
+            None => unsafe { *self.memory_ptr(index) },
+        }
     }
 
     /// Set the indexed memory to `VMMemoryDefinition`.
@@ -298,7 +313,13 @@ impl Instance {
             EntityIndex::Memory(index) => {
                 let (definition, vmctx) =
                     if let Some(def_index) = self.module.local.defined_memory_index(*index) {
-                        (self.memory_ptr(def_index), self.vmctx_ptr())
+                        // Instances importing a shared memory use its own definition, which
+                        // stays current as any of them grows it.
+                        let definition = match self.memories[def_index].as_shared() {
+                            Some(shared) => shared.vmmemory_ptr(),
+                            None => self.memory_ptr(def_index),
+                        };
+                        (definition, self.vmctx_ptr())
                     } else {
                         let import = self.imported_memory(*index);
                         (import.from, import.vmctx)
@@ -363,6 +384,16 @@ impl Instance {
 
     /// Return the memory index for the given `VMMemoryDefinition`.
     pub(crate) fn memory_index(&self, memory: &VMMemoryDefinition) -> DefinedMemoryIndex {
+        // Shared memories are exported with their own definition rather than the one in the
+        // `VMContext`.
+        for (index, defined) in self.memories.iter() {
+            if let Some(shared) = defined.as_shared() {
+                if ptr::eq(shared.vmmemory_ptr(), memory) {
+                    return index;
+                }
+            }
+        }
+
         let offsets = &self.offsets;
         let begin = unsafe {
             (&self.vmctx as *const VMContext as *const u8)
@@ -377,27 +408,56 @@ impl Instance {
         index
     }
 
+    fn resources(&self) -> &ResourceTracker {
+        unsafe { &*self.resources }
+    }
+
     /// Grow memory by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
-    pub(crate) fn memory_grow(&self, memory_index: DefinedMemoryIndex, delta: u32) -> Option<u32> {
-        let result = self
+    /// of pages, and a trap if the store's resource limiter raised one.
+    pub(crate) fn memory_grow(
+        &self,
+        memory_index: DefinedMemoryIndex,
+        delta: u32,
+    ) -> Result<Option<u32>, Trap> {
+        let memory = self
             .memories
             .get(memory_index)
-            .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()))
-            .grow(delta);
+            .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()));
+
+        if delta > 0 {
+            let current = memory.size();
+            let desired = match current.checked_add(delta) {
+                Some(desired) => desired,
+                None => return Ok(None),
+            };
+            let plan =
+                &self.module.local.memory_plans[self.module.local.memory_index(memory_index)];
+            if !self.resources().memory_growing(
+                pages_to_bytes(current),
+                pages_to_bytes(desired),
+                plan.memory.maximum.map(pages_to_bytes),
+            )? {
+                return Ok(None);
+            }
+        }
+
+        let result = memory.grow(delta);
+        if result.is_some() {
+            self.resources().memory_grown(pages_to_bytes(delta));
+        }
 
         // Keep current the VMContext pointers used by compiled wasm code.
         self.set_memory(memory_index, self.memories[memory_index].vmmemory());
 
-        result
+        Ok(result)
     }
 
     /// Grow imported memory by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
+    /// of pages, and a trap if the store's resource limiter raised one.
     ///
     /// # Safety
     /// This and `imported_memory_size` are currently unsafe because they
@@ -406,7 +466,7 @@ impl Instance {
         &self,
         memory_index: MemoryIndex,
         delta: u32,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, Trap> {
         let import = self.imported_memory(memory_index);
         let foreign_instance = (&*import.vmctx).instance();
         let foreign_memory = &*import.from;
@@ -437,6 +497,50 @@ impl Instance {
         foreign_instance.memory_size(foreign_index)
     }
 
+    /// Implementation of `memory.atomic.wait`, parking the current thread on
+    /// the native address `addr` of memory `memory_index` if `validate`
+    /// returns `true`.
+    ///
+    /// Returns 0 if the thread was woken up by `memory.atomic.notify`, 1 if
+    /// `validate` returned `false`, and 2 if `timeout` nanoseconds elapsed
+    /// first. A negative `timeout` never expires.
+    pub(crate) fn memory_atomic_wait(
+        &self,
+        memory_index: MemoryIndex,
+        addr: usize,
+        validate: impl FnOnce() -> bool,
+        timeout: i64,
+    ) -> Result<u32, Trap> {
+        if !self.module.local.memory_plans[memory_index].memory.shared {
+            return Err(Trap::User("expected shared memory".into()));
+        }
+        let deadline = u64::try_from(timeout)
+            .ok()
+            .and_then(|timeout| Instant::now().checked_add(Duration::from_nanos(timeout)));
+        Ok(match PARKING_SPOT.park(addr, validate, deadline) {
+            ParkResult::Unparked => 0,
+            ParkResult::Invalid => 1,
+            ParkResult::TimedOut => 2,
+        })
+    }
+
+    /// Implementation of `memory.atomic.notify`, waking up at most `count`
+    /// threads waiting on the native address `addr` of memory `memory_index`.
+    ///
+    /// Returns the number of threads woken up.
+    pub(crate) fn memory_atomic_notify(
+        &self,
+        memory_index: MemoryIndex,
+        addr: usize,
+        count: u32,
+    ) -> u32 {
+        // Threads can't wait on memories which aren't shared.
+        if !self.module.local.memory_plans[memory_index].memory.shared {
+            return 0;
+        }
+        PARKING_SPOT.unpark(addr, count)
+    }
+
     pub(crate) fn table_element_type(&self, table_index: TableIndex) -> TableElementType {
         let table = self.get_table(table_index);
         table.element_type()
@@ -446,13 +550,14 @@ impl Instance {
     /// `init_value`.
     ///
     /// Returns `None` if table can't be grown by the specified amount of
-    /// elements, or if `init_value` is the wrong type of table element.
+    /// elements, or if `init_value` is the wrong type of table element, and a
+    /// trap if the store's resource limiter raised one.
     pub(crate) fn table_grow(
         &self,
         table_index: TableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, Trap> {
         let (defined_table_index, instance) =
             self.get_defined_table_index_and_instance(table_index);
         instance.defined_table_grow(defined_table_index, delta, init_value)
@@ -463,20 +568,38 @@ impl Instance {
         table_index: DefinedTableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
-        unsafe {
-            let orig_size = self
-                .tables
-                .get(table_index)
-                .unwrap_or_else(|| panic!("no table for index {}", table_index.index()))
-                .grow(delta, init_value)?;
-
-            // Keep the `VMContext` pointers used by compiled Wasm code up to
-            // date.
-            self.set_table(table_index, self.tables[table_index].vmtable());
+    ) -> Result<Option<u32>, Trap> {
+        let table = self
+            .tables
+            .get(table_index)
+            .unwrap_or_else(|| panic!("no table for index {}", table_index.index()));
 
-            Some(orig_size)
+        if delta > 0 {
+            let current = table.size();
+            let desired = match current.checked_add(delta) {
+                Some(desired) => desired,
+                None => return Ok(None),
+            };
+            let plan = &self.module.local.table_plans[self.module.local.table_index(table_index)];
+            if !self
+                .resources()
+                .table_growing(current, desired, plan.table.maximum)?
+            {
+                return Ok(None);
+            }
         }
+
+        let orig_size = match unsafe { table.grow(delta, init_value) } {
+            Some(orig_size) => orig_size,
+            None => return Ok(None),
+        };
+        self.resources().table_grown(delta);
+
+        // Keep the `VMContext` pointers used by compiled Wasm code up to
+        // date.
+        self.set_table(table_index, self.tables[table_index].vmtable());
+
+        Ok(Some(orig_size))
     }
 
     // Get table element by index.
@@ -499,11 +622,13 @@ impl Instance {
             .set(index, val)
     }
 
-    fn alloc_layout(&self) -> Layout {
-        let size = mem::size_of_val(self)
-            .checked_add(usize::try_from(self.offsets.size_of_vmctx()).unwrap())
+    /// Returns the layout of an `Instance` along with its trailing `VMContext`
+    /// for a module with the given `offsets`.
+    fn alloc_layout(offsets: &VMOffsets) -> Layout {
+        let size = mem::size_of::<Self>()
+            .checked_add(usize::try_from(offsets.size_of_vmctx()).unwrap())
             .unwrap();
-        let align = mem::align_of_val(self);
+        let align = mem::align_of::<Self>();
         Layout::from_size_align(size, align).unwrap()
     }
 
@@ -790,175 +915,18 @@ pub struct InstanceHandle {
 }
 
 impl InstanceHandle {
-    /// Create a new `InstanceHandle` pointing at a new `Instance`.
-    ///
-    /// # Unsafety
-    ///
-    /// This method is not necessarily inherently unsafe to call, but in general
-    /// the APIs of an `Instance` are quite unsafe and have not been really
-    /// audited for safety that much. As a result the unsafety here on this
-    /// method is a low-overhead way of saying "this is an extremely unsafe type
-    /// to work with".
-    ///
-    /// Extreme care must be taken when working with `InstanceHandle` and it's
-    /// recommended to have relatively intimate knowledge of how it works
-    /// internally if you'd like to do so. If possible it's recommended to use
-    /// the `wasmtime` crate API rather than this type since that is vetted for
-    /// safety.
-    ///
-    /// It is your responsibility to ensure that the given raw
-    /// `externref_activations_table` and `stack_map_registry` outlive this
-    /// instance.
-    pub unsafe fn new(
-        module: Arc<Module>,
-        code: Arc<dyn Any>,
-        finished_functions: BoxedSlice<DefinedFuncIndex, *mut [VMFunctionBody]>,
-        trampolines: HashMap<VMSharedSignatureIndex, VMTrampoline>,
-        imports: Imports,
-        mem_creator: Option<&dyn RuntimeMemoryCreator>,
-        vmshared_signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
-        host_state: Box<dyn Any>,
-        interrupts: Arc<VMInterrupts>,
-        externref_activations_table: *mut VMExternRefActivationsTable,
-        stack_map_registry: *mut StackMapRegistry,
-    ) -> Result<Self, InstantiationError> {
-        debug_assert!(!externref_activations_table.is_null());
-        debug_assert!(!stack_map_registry.is_null());
-
-        let tables = create_tables(&module);
-        let memories = create_memories(&module, mem_creator.unwrap_or(&DefaultMemoryCreator {}))?;
-
-        let vmctx_tables = tables
-            .values()
-            .map(Table::vmtable)
-            .collect::<PrimaryMap<DefinedTableIndex, _>>()
-            .into_boxed_slice();
-
-        let vmctx_memories = memories
-            .values()
-            .map(|a| a.vmmemory())
-            .collect::<PrimaryMap<DefinedMemoryIndex, _>>()
-            .into_boxed_slice();
-
-        let vmctx_globals = create_globals(&module);
-
-        let offsets = VMOffsets::new(mem::size_of::<*const u8>() as u8, &module.local);
-
-        let passive_data = RefCell::new(module.passive_data.clone());
-
-        let handle = {
-            let instance = Instance {
-                module,
-                code,
-                offsets,
-                memories,
-                tables,
-                passive_elements: Default::default(),
-                passive_data,
-                finished_functions,
-                trampolines,
-                host_state,
-                interrupts,
-                vmctx: VMContext {},
-            };
-            let layout = instance.alloc_layout();
-            let instance_ptr = alloc::alloc(layout) as *mut Instance;
-            if instance_ptr.is_null() {
-                alloc::handle_alloc_error(layout);
-            }
-            ptr::write(instance_ptr, instance);
-            InstanceHandle {
-                instance: instance_ptr,
-            }
-        };
-        let instance = handle.instance();
-
-        ptr::copy(
-            vmshared_signatures.values().as_slice().as_ptr(),
-            instance.signature_ids_ptr() as *mut VMSharedSignatureIndex,
-            vmshared_signatures.len(),
-        );
-        ptr::copy(
-            imports.functions.values().as_slice().as_ptr(),
-            instance.imported_functions_ptr() as *mut VMFunctionImport,
-            imports.functions.len(),
-        );
-        ptr::copy(
-            imports.tables.values().as_slice().as_ptr(),
-            instance.imported_tables_ptr() as *mut VMTableImport,
-            imports.tables.len(),
-        );
-        ptr::copy(
-            imports.memories.values().as_slice().as_ptr(),
-            instance.imported_memories_ptr() as *mut VMMemoryImport,
-            imports.memories.len(),
-        );
-        ptr::copy(
-            imports.globals.values().as_slice().as_ptr(),
-            instance.imported_globals_ptr() as *mut VMGlobalImport,
-            imports.globals.len(),
-        );
-        ptr::copy(
-            vmctx_tables.values().as_slice().as_ptr(),
-            instance.tables_ptr() as *mut VMTableDefinition,
-            vmctx_tables.len(),
-        );
-        ptr::copy(
-            vmctx_memories.values().as_slice().as_ptr(),
-            instance.memories_ptr() as *mut VMMemoryDefinition,
-            vmctx_memories.len(),
-        );
-        ptr::copy(
-            vmctx_globals.values().as_slice().as_ptr(),
-            instance.globals_ptr() as *mut VMGlobalDefinition,
-            vmctx_globals.len(),
-        );
-        ptr::write(
-            instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
-            VMBuiltinFunctionsArray::initialized(),
-        );
-        *instance.interrupts() = &*instance.interrupts;
-        *instance.externref_activations_table() = externref_activations_table;
-        *instance.stack_map_registry() = stack_map_registry;
-
-        for (index, sig) in instance.module.local.functions.iter() {
-            let type_index = instance.signature_id(*sig);
-
-            let (func_ptr, vmctx) =
-                if let Some(def_index) = instance.module.local.defined_func_index(index) {
-                    (
-                        NonNull::new(instance.finished_functions[def_index] as *mut _).unwrap(),
-                        instance.vmctx_ptr(),
-                    )
-                } else {
-                    let import = instance.imported_function(index);
-                    (import.body, import.vmctx)
-                };
-
-            ptr::write(
-                instance.anyfunc_ptr(index),
-                VMCallerCheckedAnyfunc {
-                    func_ptr,
-                    type_index,
-                    vmctx,
-                },
-            );
-        }
-
-        // Perform infallible initialization in this constructor, while fallible
-        // initialization is deferred to the `initialize` method.
-        initialize_passive_elements(instance);
-        initialize_globals(instance);
-
-        Ok(handle)
-    }
-
-    /// Finishes the instantiation process started by `Instance::new`.
+    /// Finishes the instantiation process started by
+    /// `InstanceAllocator::allocate`.
     ///
     /// Only safe to call immediately after instantiation.
+    ///
+    /// The defined memories with an image in `memory_images` are initialized
+    /// from it before `data_initializers` are applied, which must therefore
+    /// only contain the segments that weren't evaluated into those images.
     pub unsafe fn initialize(
         &self,
         is_bulk_memory: bool,
+        memory_images: &MemoryImages,
         data_initializers: &[DataInitializer<'_>],
     ) -> Result<(), InstantiationError> {
         // Check initializer bounds before initializing anything. Only do this
@@ -973,7 +941,7 @@ impl InstanceHandle {
         // Apply fallible initializers. Note that this can "leak" state even if
         // it fails.
         initialize_tables(self.instance())?;
-        initialize_memories(self.instance(), data_initializers)?;
+        initialize_memories(self.instance(), memory_images, data_initializers)?;
 
         Ok(())
     }
@@ -1038,11 +1006,21 @@ impl InstanceHandle {
     /// Grow memory in this instance by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
-    pub fn memory_grow(&self, memory_index: DefinedMemoryIndex, delta: u32) -> Option<u32> {
+    /// of pages, and a trap if the store's resource limiter raised one.
+    pub fn memory_grow(
+        &self,
+        memory_index: DefinedMemoryIndex,
+        delta: u32,
+    ) -> Result<Option<u32>, Trap> {
         self.instance().memory_grow(memory_index, delta)
     }
 
+    /// Returns the memory of this instance at `memory_index` if it's a shared
+    /// memory.
+    pub fn shared_memory(&self, memory_index: DefinedMemoryIndex) -> Option<SharedMemory> {
+        self.instance().memories[memory_index].as_shared().cloned()
+    }
+
     /// Return the table index for the given `VMTableDefinition` in this instance.
     pub fn table_index(&self, table: &VMTableDefinition) -> DefinedTableIndex {
         self.instance().table_index(table)
@@ -1054,13 +1032,14 @@ impl InstanceHandle {
     /// table.
     ///
     /// Returns `None` if memory can't be grown by the specified amount of pages
-    /// or if the `init_value` is the incorrect table element type.
+    /// or if the `init_value` is the incorrect table element type, and a trap
+    /// if the store's resource limiter raised one.
     pub fn table_grow(
         &self,
         table_index: TableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, Trap> {
         self.instance().table_grow(table_index, delta, init_value)
     }
 
@@ -1070,13 +1049,14 @@ impl InstanceHandle {
     /// table.
     ///
     /// Returns `None` if memory can't be grown by the specified amount of pages
-    /// or if the `init_value` is the incorrect table element type.
+    /// or if the `init_value` is the incorrect table element type, and a trap
+    /// if the store's resource limiter raised one.
     pub fn defined_table_grow(
         &self,
         table_index: DefinedTableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, Trap> {
         self.instance()
             .defined_table_grow(table_index, delta, init_value)
     }
@@ -1126,18 +1106,6 @@ impl InstanceHandle {
             instance: self.instance,
         }
     }
-
-    /// Deallocates memory associated with this instance.
-    ///
-    /// Note that this is unsafe because there might be other handles to this
-    /// `InstanceHandle` elsewhere, and there's nothing preventing usage of
-    /// this handle after this function is called.
-    pub unsafe fn dealloc(&self) {
-        let instance = self.instance();
-        let layout = instance.alloc_layout();
-        ptr::drop_in_place(self.instance);
-        alloc::dealloc(self.instance.cast(), layout);
-    }
 }
 
 fn check_table_init_bounds(instance: &Instance) -> Result<(), InstantiationError> {
@@ -1214,17 +1182,6 @@ fn check_memory_init_bounds(
     Ok(())
 }
 
-/// Allocate memory for just the tables of the current module.
-fn create_tables(module: &Module) -> BoxedSlice<DefinedTableIndex, Table> {
-    let num_imports = module.local.num_imported_tables;
-    let mut tables: PrimaryMap<DefinedTableIndex, _> =
-        PrimaryMap::with_capacity(module.local.table_plans.len() - num_imports);
-    for table in &module.local.table_plans.values().as_slice()[num_imports..] {
-        tables.push(Table::new(table));
-    }
-    tables.into_boxed_slice()
-}
-
 /// Compute the offset for a table element initializer.
 fn get_table_init_start(init: &TableElements, instance: &Instance) -> usize {
     let mut start = init.offset;
@@ -1309,29 +1266,20 @@ fn initialize_passive_elements(instance: &Instance) {
     );
 }
 
-/// Allocate memory for just the memories of the current module.
-fn create_memories(
-    module: &Module,
-    mem_creator: &dyn RuntimeMemoryCreator,
-) -> Result<BoxedSlice<DefinedMemoryIndex, Box<dyn RuntimeLinearMemory>>, InstantiationError> {
-    let num_imports = module.local.num_imported_memories;
-    let mut memories: PrimaryMap<DefinedMemoryIndex, _> =
-        PrimaryMap::with_capacity(module.local.memory_plans.len() - num_imports);
-    for plan in &module.local.memory_plans.values().as_slice()[num_imports..] {
-        memories.push(
-            mem_creator
-                .new_memory(plan)
-                .map_err(InstantiationError::Resource)?,
-        );
-    }
-    Ok(memories.into_boxed_slice())
-}
-
-/// Initialize the table memory from the provided initializers.
+/// Initialize the memories from the provided images and initializers.
 fn initialize_memories(
     instance: &Instance,
+    memory_images: &MemoryImages,
     data_initializers: &[DataInitializer<'_>],
 ) -> Result<(), InstantiationError> {
+    for (index, memory) in instance.memories.iter() {
+        if let Some(image) = memory_images.get(index) {
+            memory
+                .initialize_with_image(image)
+                .map_err(InstantiationError::Resource)?;
+        }
+    }
+
     for init in data_initializers {
         let memory = instance.get_memory(init.location.memory_index);
 
@@ -1416,4 +1364,8 @@ pub enum InstantiationError {
     /// A trap ocurred during instantiation, after linking.
     #[error("Trap occurred during instantiation")]
     Trap(Trap),
+
+    /// A limit on how many instances are supported has been reached.
+    #[error("Limit of {0} concurrent instances has been reached")]
+    Limit(u32),
 }
@@ -0,0 +1,358 @@
+//! Strategies for allocating the memory backing an `Instance`, along with its
+//! linear memories and tables.
+
+use crate::externref::{StackMapRegistry, VMExternRefActivationsTable};
+use crate::imports::Imports;
+use crate::instance::{
+    create_globals, initialize_globals, initialize_passive_elements, Instance, InstanceHandle,
+    InstantiationError,
+};
+use crate::limits::{pages_to_bytes, ResourceTracker};
+use crate::memory::{DefaultMemoryCreator, RuntimeLinearMemory, RuntimeMemoryCreator};
+use crate::table::Table;
+use crate::vmcontext::{
+    VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody, VMFunctionImport,
+    VMGlobalDefinition, VMGlobalImport, VMInterrupts, VMMemoryDefinition, VMMemoryImport,
+    VMSharedSignatureIndex, VMTableDefinition, VMTableImport, VMTrampoline,
+};
+use std::alloc;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+use wasmtime_environ::entity::{BoxedSlice, PrimaryMap};
+use wasmtime_environ::wasm::{
+    DefinedFuncIndex, DefinedMemoryIndex, DefinedTableIndex, SignatureIndex,
+};
+use wasmtime_environ::{Module, Tunables, VMOffsets};
+
+mod pooling;
+
+pub use self::pooling::{InstanceLimits, ModuleLimits, PoolingInstanceAllocator};
+
+/// Everything needed to allocate and initialize a new `Instance`.
+pub struct InstanceAllocationRequest {
+    /// The module being instantiated.
+    pub module: Arc<Module>,
+
+    /// The module's JIT code (if exists).
+    pub code: Arc<dyn Any>,
+
+    /// Pointers to the finished functions of the module.
+    pub finished_functions: BoxedSlice<DefinedFuncIndex, *mut [VMFunctionBody]>,
+
+    /// Pointers to trampoline functions used to enter particular signatures.
+    pub trampolines: HashMap<VMSharedSignatureIndex, VMTrampoline>,
+
+    /// The resolved imports of the instance.
+    pub imports: Imports,
+
+    /// The shared signature indices of the module's signatures.
+    pub shared_signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+
+    /// Arbitrary host state to associate with the instance.
+    pub host_state: Box<dyn Any + Send>,
+
+    /// Externally allocated data indicating how the instance will be
+    /// interrupted.
+    pub interrupts: Arc<VMInterrupts>,
+
+    /// The table of `externref`s in use by wasm frames, which must outlive the
+    /// instance.
+    pub externref_activations_table: *mut VMExternRefActivationsTable,
+
+    /// The registry of stack maps, which must outlive the instance.
+    pub stack_map_registry: *mut StackMapRegistry,
+
+    /// The tracker of the resources used by the store the instance is created
+    /// in, which must outlive the instance.
+    pub resources: *const ResourceTracker,
+}
+
+/// A strategy for allocating instances along with their memories and tables.
+///
+/// # Safety
+///
+/// Implementations must hand out memory which stays valid and unaliased until
+/// the corresponding `deallocate`, and which is correctly sized for the
+/// `VMContext` and the memory and table plans of the module being
+/// instantiated.
+pub unsafe trait InstanceAllocator: Send + Sync {
+    /// Adjusts the tunables used to compile modules for this allocator.
+    ///
+    /// This is called before any module is compiled so an allocator can, for
+    /// example, limit the static memory bound to what it has reserved.
+    fn adjust_tunables(&self, tunables: &mut Tunables) {
+        let _ = tunables;
+    }
+
+    /// Validates that `module` can be instantiated with this allocator.
+    fn validate(&self, module: &Module) -> Result<(), String> {
+        let _ = module;
+        Ok(())
+    }
+
+    /// Allocates a new instance for the given request.
+    ///
+    /// The returned handle still needs to be initialized with
+    /// `InstanceHandle::initialize`.
+    ///
+    /// # Safety
+    ///
+    /// It is the caller's responsibility to ensure that the raw
+    /// `externref_activations_table`, `stack_map_registry` and `resources` of
+    /// the request outlive the instance, and that the handle is eventually
+    /// passed to `deallocate` of this same allocator.
+    unsafe fn allocate(
+        &self,
+        req: InstanceAllocationRequest,
+    ) -> Result<InstanceHandle, InstantiationError>;
+
+    /// Deallocates an instance previously returned by `allocate`.
+    ///
+    /// # Safety
+    ///
+    /// There might be other handles to this instance elsewhere, and there's
+    /// nothing preventing their usage after this function is called.
+    unsafe fn deallocate(&self, handle: &InstanceHandle);
+}
+
+/// An instance allocator which allocates everything on demand, with the global
+/// allocator for instances and a `RuntimeMemoryCreator` for linear memories.
+///
+/// This is the default instance allocator.
+#[derive(Clone, Default)]
+pub struct OnDemandInstanceAllocator {
+    mem_creator: Option<Arc<dyn RuntimeMemoryCreator>>,
+}
+
+impl OnDemandInstanceAllocator {
+    /// Creates a new on-demand instance allocator, using `mem_creator` to
+    /// create linear memories if specified.
+    pub fn new(mem_creator: Option<Arc<dyn RuntimeMemoryCreator>>) -> Self {
+        Self { mem_creator }
+    }
+
+    fn create_tables(module: &Module) -> BoxedSlice<DefinedTableIndex, Table> {
+        let num_imports = module.local.num_imported_tables;
+        let mut tables: PrimaryMap<DefinedTableIndex, _> =
+            PrimaryMap::with_capacity(module.local.table_plans.len() - num_imports);
+        for table in &module.local.table_plans.values().as_slice()[num_imports..] {
+            tables.push(Table::new(table));
+        }
+        tables.into_boxed_slice()
+    }
+
+    fn create_memories(
+        &self,
+        module: &Module,
+    ) -> Result<BoxedSlice<DefinedMemoryIndex, Box<dyn RuntimeLinearMemory>>, InstantiationError>
+    {
+        let creator = self.mem_creator.as_deref().unwrap_or(&DefaultMemoryCreator);
+        let num_imports = module.local.num_imported_memories;
+        let mut memories: PrimaryMap<DefinedMemoryIndex, _> =
+            PrimaryMap::with_capacity(module.local.memory_plans.len() - num_imports);
+        for plan in &module.local.memory_plans.values().as_slice()[num_imports..] {
+            let memory = if plan.memory.shared {
+                creator
+                    .new_shared_memory(plan)
+                    .map(|memory| Box::new(memory) as Box<dyn RuntimeLinearMemory>)
+            } else {
+                creator.new_memory(plan)
+            };
+            memories.push(memory.map_err(InstantiationError::Resource)?);
+        }
+        Ok(memories.into_boxed_slice())
+    }
+}
+
+unsafe impl InstanceAllocator for OnDemandInstanceAllocator {
+    unsafe fn allocate(
+        &self,
+        req: InstanceAllocationRequest,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        check_resource_limits(&req)?;
+        let memories = self.create_memories(&req.module)?;
+        let tables = Self::create_tables(&req.module);
+
+        let layout = Instance::alloc_layout(&offsets_for(&req.module));
+        let instance_ptr = alloc::alloc(layout) as *mut Instance;
+        if instance_ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        Ok(initialize_instance(instance_ptr, req, memories, tables))
+    }
+
+    unsafe fn deallocate(&self, handle: &InstanceHandle) {
+        let layout = Instance::alloc_layout(&handle.instance().offsets);
+        ptr::drop_in_place(handle.instance);
+        alloc::dealloc(handle.instance.cast(), layout);
+    }
+}
+
+fn offsets_for(module: &Module) -> VMOffsets {
+    VMOffsets::new(std::mem::size_of::<*const u8>() as u8, &module.local)
+}
+
+/// Consults the store's resource limiter about the instance and the memories
+/// and tables the request's module defines, before they're created.
+///
+/// # Safety
+///
+/// The request's `resources` must be valid.
+unsafe fn check_resource_limits(req: &InstanceAllocationRequest) -> Result<(), InstantiationError> {
+    debug_assert!(!req.resources.is_null());
+    let resources = &*req.resources;
+    resources.check_instance()?;
+    let local = &req.module.local;
+    resources.check_creation(
+        &local.memory_plans.values().as_slice()[local.num_imported_memories..],
+        &local.table_plans.values().as_slice()[local.num_imported_tables..],
+    )
+}
+
+/// Writes a new `Instance` to `instance_ptr` and initializes its `VMContext`.
+///
+/// Only infallible initialization is performed here, fallible initialization
+/// is deferred to `InstanceHandle::initialize`.
+///
+/// # Safety
+///
+/// `instance_ptr` must point to uninitialized memory which is valid for the
+/// layout returned by `Instance::alloc_layout` for the request's module.
+unsafe fn initialize_instance(
+    instance_ptr: *mut Instance,
+    req: InstanceAllocationRequest,
+    memories: BoxedSlice<DefinedMemoryIndex, Box<dyn RuntimeLinearMemory>>,
+    tables: BoxedSlice<DefinedTableIndex, Table>,
+) -> InstanceHandle {
+    debug_assert!(!req.externref_activations_table.is_null());
+    debug_assert!(!req.stack_map_registry.is_null());
+    debug_assert!(!req.resources.is_null());
+
+    let resources = &*req.resources;
+    resources.instance_created();
+    resources.memory_grown(memories.values().map(|m| pages_to_bytes(m.size())).sum());
+    resources.table_grown(tables.values().map(Table::size).sum());
+
+    let vmctx_tables = tables
+        .values()
+        .map(Table::vmtable)
+        .collect::<PrimaryMap<DefinedTableIndex, _>>()
+        .into_boxed_slice();
+
+    let vmctx_memories = memories
+        .values()
+        .map(|a| a.vmmemory())
+        .collect::<PrimaryMap<DefinedMemoryIndex, _>>()
+        .into_boxed_slice();
+
+    let vmctx_globals = create_globals(&req.module);
+
+    let offsets = offsets_for(&req.module);
+
+    let passive_data = RefCell::new(req.module.passive_data.clone());
+
+    ptr::write(
+        instance_ptr,
+        Instance {
+            module: req.module,
+            code: req.code,
+            offsets,
+            memories,
+            tables,
+            passive_elements: Default::default(),
+            passive_data,
+            finished_functions: req.finished_functions,
+            trampolines: req.trampolines,
+            host_state: req.host_state,
+            interrupts: req.interrupts,
+            resources: req.resources,
+            vmctx: VMContext {},
+        },
+    );
+    let handle = InstanceHandle {
+        instance: instance_ptr,
+    };
+    let instance = handle.instance();
+
+    let imports = &req.imports;
+    ptr::copy(
+        req.shared_signatures.values().as_slice().as_ptr(),
+        instance.signature_ids_ptr() as *mut VMSharedSignatureIndex,
+        req.shared_signatures.len(),
+    );
+    ptr::copy(
+        imports.functions.values().as_slice().as_ptr(),
+        instance.imported_functions_ptr() as *mut VMFunctionImport,
+        imports.functions.len(),
+    );
+    ptr::copy(
+        imports.tables.values().as_slice().as_ptr(),
+        instance.imported_tables_ptr() as *mut VMTableImport,
+        imports.tables.len(),
+    );
+    ptr::copy(
+        imports.memories.values().as_slice().as_ptr(),
+        instance.imported_memories_ptr() as *mut VMMemoryImport,
+        imports.memories.len(),
+    );
+    ptr::copy(
+        imports.globals.values().as_slice().as_ptr(),
+        instance.imported_globals_ptr() as *mut VMGlobalImport,
+        imports.globals.len(),
+    );
+    ptr::copy(
+        vmctx_tables.values().as_slice().as_ptr(),
+        instance.tables_ptr() as *mut VMTableDefinition,
+        vmctx_tables.len(),
+    );
+    ptr::copy(
+        vmctx_memories.values().as_slice().as_ptr(),
+        instance.memories_ptr() as *mut VMMemoryDefinition,
+        vmctx_memories.len(),
+    );
+    ptr::copy(
+        vmctx_globals.values().as_slice().as_ptr(),
+        instance.globals_ptr() as *mut VMGlobalDefinition,
+        vmctx_globals.len(),
+    );
+    ptr::write(
+        instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
+        VMBuiltinFunctionsArray::initialized(),
+    );
+    *instance.interrupts() = &*instance.interrupts;
+    *instance.externref_activations_table() = req.externref_activations_table;
+    *instance.stack_map_registry() = req.stack_map_registry;
+
+    for (index, sig) in instance.module.local.functions.iter() {
+        let type_index = instance.signature_id(*sig);
+
+        let (func_ptr, vmctx) =
+            if let Some(def_index) = instance.module.local.defined_func_index(index) {
+                (
+                    NonNull::new(instance.finished_functions[def_index] as *mut _).unwrap(),
+                    instance.vmctx_ptr(),
+                )
+            } else {
+                let import = instance.imported_function(index);
+                (import.body, import.vmctx)
+            };
+
+        ptr::write(
+            instance.anyfunc_ptr(index),
+            VMCallerCheckedAnyfunc {
+                func_ptr,
+                type_index,
+                vmctx,
+            },
+        );
+    }
+
+    initialize_passive_elements(instance);
+    initialize_globals(instance);
+
+    handle
+}
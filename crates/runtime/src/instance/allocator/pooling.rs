@@ -0,0 +1,673 @@
+//! The pooling instance allocator.
+//!
+//! This allocator reserves all of the address space it needs up front and
+//! carves it into fixed-size slots for instances, linear memories and tables.
+//! A slot is reset and returned to the pool when its instance is deallocated,
+//! so instantiation doesn't need to map or allocate any memory at all.
+
+use super::{initialize_instance, InstanceAllocationRequest, InstanceAllocator};
+use crate::instance::{Instance, InstanceHandle, InstantiationError};
+use crate::memory::RuntimeLinearMemory;
+use crate::memory_image::MemoryImage;
+use crate::mmap::Mmap;
+use crate::table::Table;
+use crate::vmcontext::{VMCallerCheckedAnyfunc, VMMemoryDefinition};
+use std::cell::Cell;
+use std::cmp;
+use std::convert::TryFrom;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::Mutex;
+use wasmtime_environ::entity::{EntityRef, PrimaryMap};
+use wasmtime_environ::wasm::{DefinedMemoryIndex, DefinedTableIndex, TableElementType};
+use wasmtime_environ::{
+    MemoryPlan, MemoryStyle, Module, Tunables, VMOffsets, WASM_MAX_PAGES, WASM_PAGE_SIZE,
+};
+
+/// Limits on the modules which can be instantiated by the pooling instance
+/// allocator.
+///
+/// These limits determine the size of each instance, memory and table slot in
+/// the pool, and modules exceeding any of them are rejected.
+#[derive(Debug, Copy, Clone)]
+pub struct ModuleLimits {
+    /// The maximum number of imported functions for a module (default is 1000).
+    pub imported_functions: u32,
+
+    /// The maximum number of imported tables for a module (default is 0).
+    pub imported_tables: u32,
+
+    /// The maximum number of imported memories for a module (default is 0).
+    pub imported_memories: u32,
+
+    /// The maximum number of imported globals for a module (default is 0).
+    pub imported_globals: u32,
+
+    /// The maximum number of defined types for a module (default is 100).
+    pub types: u32,
+
+    /// The maximum number of defined functions for a module (default is
+    /// 10000).
+    pub functions: u32,
+
+    /// The maximum number of defined tables for a module (default is 1).
+    pub tables: u32,
+
+    /// The maximum number of defined memories for a module (default is 1).
+    pub memories: u32,
+
+    /// The maximum number of defined globals for a module (default is 10).
+    pub globals: u32,
+
+    /// The maximum number of elements for any table defined in a module
+    /// (default is 10000).
+    ///
+    /// Tables may not grow beyond this number of elements.
+    pub table_elements: u32,
+
+    /// The maximum number of wasm pages for any memory defined in a module
+    /// (default is 160, i.e. 10 MiB).
+    ///
+    /// Memories may not grow beyond this number of pages.
+    pub memory_pages: u32,
+}
+
+impl ModuleLimits {
+    fn validate(&self, module: &Module) -> Result<(), String> {
+        let local = &module.local;
+
+        check_limit(
+            "imported function",
+            local.num_imported_funcs,
+            self.imported_functions,
+        )?;
+        check_limit(
+            "imported table",
+            local.num_imported_tables,
+            self.imported_tables,
+        )?;
+        check_limit(
+            "imported memory",
+            local.num_imported_memories,
+            self.imported_memories,
+        )?;
+        check_limit(
+            "imported global",
+            local.num_imported_globals,
+            self.imported_globals,
+        )?;
+        check_limit("defined type", local.signatures.len(), self.types)?;
+        check_limit(
+            "defined function",
+            local.functions.len() - local.num_imported_funcs,
+            self.functions,
+        )?;
+        check_limit(
+            "defined table",
+            local.table_plans.len() - local.num_imported_tables,
+            self.tables,
+        )?;
+        check_limit(
+            "defined memory",
+            local.memory_plans.len() - local.num_imported_memories,
+            self.memories,
+        )?;
+        check_limit(
+            "defined global",
+            local.globals.len() - local.num_imported_globals,
+            self.globals,
+        )?;
+
+        for (i, plan) in local
+            .table_plans
+            .values()
+            .skip(local.num_imported_tables)
+            .enumerate()
+        {
+            if plan.table.ty != TableElementType::Func {
+                return Err(format!(
+                    "table index {} has an element type which is not supported by the \
+                     pooling instance allocator",
+                    i
+                ));
+            }
+            if plan.table.minimum > self.table_elements {
+                return Err(format!(
+                    "table index {} has a minimum element size of {} which exceeds the limit of {}",
+                    i, plan.table.minimum, self.table_elements
+                ));
+            }
+        }
+
+        for (i, plan) in local
+            .memory_plans
+            .values()
+            .skip(local.num_imported_memories)
+            .enumerate()
+        {
+            if plan.memory.shared {
+                return Err(format!(
+                    "memory index {} is a shared memory, which is not supported by the pooling \
+                     instance allocator",
+                    i
+                ));
+            }
+            if plan.memory.minimum > self.memory_pages {
+                return Err(format!(
+                    "memory index {} has a minimum page size of {} which exceeds the limit of {}",
+                    i, plan.memory.minimum, self.memory_pages
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `VMOffsets` of the largest `VMContext` a module within
+    /// these limits can have.
+    fn max_offsets(&self) -> Result<VMOffsets, String> {
+        // `VMOffsets::new` counts imported entities towards the defined ones,
+        // so do the same here.
+        let total = |imported: u32, defined: u32| {
+            imported
+                .checked_add(defined)
+                .ok_or_else(|| "module limits are too large".to_string())
+        };
+        Ok(VMOffsets {
+            pointer_size: mem::size_of::<*const u8>() as u8,
+            num_signature_ids: self.types,
+            num_imported_functions: self.imported_functions,
+            num_imported_tables: self.imported_tables,
+            num_imported_memories: self.imported_memories,
+            num_imported_globals: self.imported_globals,
+            num_defined_functions: total(self.imported_functions, self.functions)?,
+            num_defined_tables: total(self.imported_tables, self.tables)?,
+            num_defined_memories: total(self.imported_memories, self.memories)?,
+            num_defined_globals: total(self.imported_globals, self.globals)?,
+        })
+    }
+}
+
+impl Default for ModuleLimits {
+    fn default() -> Self {
+        Self {
+            imported_functions: 1000,
+            imported_tables: 0,
+            imported_memories: 0,
+            imported_globals: 0,
+            types: 100,
+            functions: 10000,
+            tables: 1,
+            memories: 1,
+            globals: 10,
+            table_elements: 10000,
+            memory_pages: 160,
+        }
+    }
+}
+
+fn check_limit(what: &str, count: usize, limit: u32) -> Result<(), String> {
+    if count > usize::try_from(limit).unwrap() {
+        return Err(format!(
+            "{} count of {} exceeds the limit of {}",
+            what, count, limit
+        ));
+    }
+    Ok(())
+}
+
+/// Limits on the instances of the pooling instance allocator.
+#[derive(Debug, Copy, Clone)]
+pub struct InstanceLimits {
+    /// The maximum number of instances which can be allocated at the same time
+    /// (default is 1000).
+    pub count: u32,
+
+    /// The size, in bytes, of the address space reserved for each linear
+    /// memory, including its guard region.
+    ///
+    /// Static memories are bounded such that they, along with their guard
+    /// region, fit in this reservation. Dynamic memories are never moved, so
+    /// they can't grow beyond the reservation minus their guard region.
+    ///
+    /// The default is 6 GiB on 64-bit platforms, which allows the default
+    /// static memory bound and guard region, and 10 MiB on 32-bit platforms.
+    pub memory_reservation_size: u64,
+}
+
+impl Default for InstanceLimits {
+    fn default() -> Self {
+        Self {
+            count: 1000,
+            #[cfg(target_pointer_width = "32")]
+            memory_reservation_size: 0xA0_0000,
+            #[cfg(target_pointer_width = "64")]
+            memory_reservation_size: 0x1_8000_0000,
+        }
+    }
+}
+
+/// An instance allocator which allocates instances, memories and tables from
+/// pools of address space reserved up front.
+///
+/// The number of instances which can be allocated at the same time is limited
+/// by [`InstanceLimits::count`], and every module instantiated must fit within
+/// the [`ModuleLimits`] given on creation.
+#[derive(Debug)]
+pub struct PoolingInstanceAllocator {
+    module_limits: ModuleLimits,
+    instance_limits: InstanceLimits,
+
+    // Slots for the `Instance` structures, along with their trailing
+    // `VMContext`. These are always accessible.
+    instances: Mmap,
+    instance_size: usize,
+
+    // Slots for the linear memories of each instance, which are only made
+    // accessible as memories are created and grown.
+    memories: Mmap,
+    memory_size: usize,
+
+    // Slots for the tables of each instance, which are made accessible while
+    // their instance is allocated.
+    tables: Mmap,
+    table_size: usize,
+
+    // Indices of the instance slots which aren't currently in use.
+    free_list: Mutex<Vec<usize>>,
+}
+
+impl PoolingInstanceAllocator {
+    /// Creates a new pooling instance allocator with the given limits.
+    ///
+    /// Returns an error if the limits are invalid or if the address space for
+    /// the pools couldn't be reserved.
+    pub fn new(
+        module_limits: ModuleLimits,
+        instance_limits: InstanceLimits,
+    ) -> Result<Self, String> {
+        if instance_limits.count == 0 {
+            return Err("the instance count limit cannot be zero".to_string());
+        }
+        if module_limits.memory_pages > WASM_MAX_PAGES {
+            return Err(format!(
+                "module memory page limit of {} exceeds the maximum of {}",
+                module_limits.memory_pages, WASM_MAX_PAGES
+            ));
+        }
+
+        let page_size = region::page::size();
+        let count = usize::try_from(instance_limits.count).unwrap();
+
+        let layout = Instance::alloc_layout(&module_limits.max_offsets()?);
+        let instance_size = layout.pad_to_align().size();
+
+        let memory_size = usize::try_from(instance_limits.memory_reservation_size)
+            .map_err(|_| "memory reservation size exceeds addressable memory".to_string())?;
+        if memory_size % page_size != 0 {
+            return Err(format!(
+                "memory reservation size of {} bytes is not a multiple of the page size",
+                memory_size
+            ));
+        }
+
+        let table_size = round_up_to_page_size(
+            usize::try_from(module_limits.table_elements)
+                .unwrap()
+                .checked_mul(mem::size_of::<*mut VMCallerCheckedAnyfunc>())
+                .ok_or_else(|| "table element limit is too large".to_string())?,
+            page_size,
+        );
+
+        let pool_size = |slot_size: usize, per_instance: u32| {
+            slot_size
+                .checked_mul(usize::try_from(per_instance).unwrap())
+                .and_then(|size| size.checked_mul(count))
+                .ok_or_else(|| "total size of the pool exceeds addressable memory".to_string())
+        };
+        let instances = Mmap::with_at_least(pool_size(instance_size, 1)?)?;
+        let memories =
+            Mmap::accessible_reserved(0, pool_size(memory_size, module_limits.memories)?)?;
+        let tables = Mmap::accessible_reserved(0, pool_size(table_size, module_limits.tables)?)?;
+
+        Ok(Self {
+            module_limits,
+            instance_limits,
+            instances,
+            instance_size,
+            memories,
+            memory_size,
+            tables,
+            table_size,
+            free_list: Mutex::new((0..count).rev().collect()),
+        })
+    }
+
+    fn instance_ptr(&self, index: usize) -> *mut Instance {
+        debug_assert!(index < usize::try_from(self.instance_limits.count).unwrap());
+        unsafe { self.instances.as_ptr().add(index * self.instance_size) as *mut Instance }
+    }
+
+    fn memory_ptr(&self, index: usize, memory: DefinedMemoryIndex) -> *mut u8 {
+        let max = usize::try_from(self.module_limits.memories).unwrap();
+        debug_assert!(memory.index() < max);
+        let slot = index * max + memory.index();
+        unsafe { self.memories.as_ptr().add(slot * self.memory_size) as *mut u8 }
+    }
+
+    fn tables_ptr(&self, index: usize) -> *mut u8 {
+        let max = usize::try_from(self.module_limits.tables).unwrap();
+        unsafe { self.tables.as_ptr().add(index * max * self.table_size) as *mut u8 }
+    }
+
+    fn create_memories(
+        &self,
+        index: usize,
+        module: &Module,
+    ) -> Result<PrimaryMap<DefinedMemoryIndex, Box<dyn RuntimeLinearMemory>>, InstantiationError>
+    {
+        let num_imports = module.local.num_imported_memories;
+        let mut memories: PrimaryMap<DefinedMemoryIndex, _> =
+            PrimaryMap::with_capacity(module.local.memory_plans.len() - num_imports);
+        for plan in &module.local.memory_plans.values().as_slice()[num_imports..] {
+            let base = self.memory_ptr(index, memories.next_key());
+            let memory = unsafe {
+                PooledMemory::new(base, self.max_memory_pages(plan), plan.memory.minimum)
+                    .map_err(InstantiationError::Resource)?
+            };
+            memories.push(Box::new(memory) as Box<dyn RuntimeLinearMemory>);
+        }
+        Ok(memories)
+    }
+
+    fn create_tables(
+        &self,
+        index: usize,
+        module: &Module,
+    ) -> Result<PrimaryMap<DefinedTableIndex, Table>, InstantiationError> {
+        let num_imports = module.local.num_imported_tables;
+        let base = self.tables_ptr(index);
+        let len = usize::try_from(self.module_limits.tables).unwrap() * self.table_size;
+        unsafe { commit(base, len).map_err(InstantiationError::Resource)? };
+
+        let mut tables: PrimaryMap<DefinedTableIndex, _> =
+            PrimaryMap::with_capacity(module.local.table_plans.len() - num_imports);
+        for plan in &module.local.table_plans.values().as_slice()[num_imports..] {
+            let data = unsafe { base.add(tables.len() * self.table_size) };
+            tables.push(unsafe {
+                Table::new_static(plan, data.cast(), self.module_limits.table_elements)
+            });
+        }
+        Ok(tables)
+    }
+
+    fn release_tables(&self, index: usize) {
+        let len = usize::try_from(self.module_limits.tables).unwrap() * self.table_size;
+        unsafe {
+            decommit(self.tables_ptr(index), len).expect("failed to decommit table pages");
+        }
+    }
+
+    /// Returns the number of pages a memory for `plan` may grow to, taking the
+    /// plan, the module limits and the reservation into account.
+    fn max_memory_pages(&self, plan: &MemoryPlan) -> u32 {
+        let reserved = match plan.style {
+            MemoryStyle::Static { bound } => bound,
+            MemoryStyle::Dynamic => {
+                let bytes = (self.memory_size as u64).saturating_sub(plan.offset_guard_size);
+                u32::try_from(bytes / u64::from(WASM_PAGE_SIZE)).unwrap_or(WASM_MAX_PAGES)
+            }
+        };
+        cmp::min(
+            plan.memory.maximum.unwrap_or(WASM_MAX_PAGES),
+            cmp::min(self.module_limits.memory_pages, reserved),
+        )
+    }
+}
+
+unsafe impl InstanceAllocator for PoolingInstanceAllocator {
+    fn adjust_tunables(&self, tunables: &mut Tunables) {
+        // Bound static memories by what fits in a memory slot along with the
+        // guard region. Given a large enough reservation this makes all
+        // memories static, which never need to move.
+        let bytes = self
+            .instance_limits
+            .memory_reservation_size
+            .saturating_sub(tunables.static_memory_offset_guard_size);
+        let pages = u32::try_from(bytes / u64::from(WASM_PAGE_SIZE)).unwrap_or(u32::max_value());
+        tunables.static_memory_bound = cmp::min(tunables.static_memory_bound, pages);
+    }
+
+    fn validate(&self, module: &Module) -> Result<(), String> {
+        self.module_limits.validate(module)?;
+
+        let local = &module.local;
+        for (i, plan) in local
+            .memory_plans
+            .values()
+            .skip(local.num_imported_memories)
+            .enumerate()
+        {
+            let pages = match plan.style {
+                MemoryStyle::Static { bound } => bound,
+                MemoryStyle::Dynamic => plan.memory.minimum,
+            };
+            let size = u64::from(pages) * u64::from(WASM_PAGE_SIZE) + plan.offset_guard_size;
+            if size > self.memory_size as u64 {
+                return Err(format!(
+                    "memory index {} requires a reservation of {} bytes which exceeds the \
+                     memory reservation size of {} bytes",
+                    i, size, self.memory_size
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn allocate(
+        &self,
+        req: InstanceAllocationRequest,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        debug_assert!(self.validate(&req.module).is_ok());
+        debug_assert!(
+            Instance::alloc_layout(&super::offsets_for(&req.module)).size() <= self.instance_size
+        );
+        super::check_resource_limits(&req)?;
+
+        let index = self
+            .free_list
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or(InstantiationError::Limit(self.instance_limits.count))?;
+
+        // Memories reset their slot when dropped, so only the tables and the
+        // instance slot need to be released on failure.
+        let slots = self
+            .create_memories(index, &req.module)
+            .and_then(|memories| {
+                let tables = self.create_tables(index, &req.module).map_err(|e| {
+                    self.release_tables(index);
+                    e
+                })?;
+                Ok((memories, tables))
+            });
+        let (memories, tables) = match slots {
+            Ok(slots) => slots,
+            Err(e) => {
+                self.free_list.lock().unwrap().push(index);
+                return Err(e);
+            }
+        };
+
+        Ok(initialize_instance(
+            self.instance_ptr(index),
+            req,
+            memories.into_boxed_slice(),
+            tables.into_boxed_slice(),
+        ))
+    }
+
+    unsafe fn deallocate(&self, handle: &InstanceHandle) {
+        let addr = handle.instance as usize;
+        let base = self.instances.as_ptr() as usize;
+        debug_assert!(addr >= base && addr < base + self.instances.len());
+        let index = (addr - base) / self.instance_size;
+        debug_assert_eq!(self.instance_ptr(index), handle.instance);
+
+        // Dropping the instance drops its memories, which resets their slots.
+        ptr::drop_in_place(handle.instance);
+        self.release_tables(index);
+
+        self.free_list.lock().unwrap().push(index);
+    }
+}
+
+/// A linear memory living in a slot of the memory pool.
+///
+/// Pages are made accessible as the memory grows and the whole slot is reset
+/// when the memory is dropped.
+struct PooledMemory {
+    base: *mut u8,
+    size: Cell<u32>,
+    maximum: u32,
+}
+
+// The pages at `base` belong to this memory until it's dropped.
+unsafe impl Send for PooledMemory {}
+
+impl PooledMemory {
+    unsafe fn new(base: *mut u8, maximum: u32, minimum: u32) -> Result<Self, String> {
+        debug_assert!(minimum <= maximum);
+        commit(base, pages_to_bytes(minimum))?;
+        Ok(Self {
+            base,
+            size: Cell::new(minimum),
+            maximum,
+        })
+    }
+}
+
+impl RuntimeLinearMemory for PooledMemory {
+    fn size(&self) -> u32 {
+        self.size.get()
+    }
+
+    fn grow(&self, delta: u32) -> Option<u32> {
+        let prev_pages = self.size.get();
+        if delta == 0 {
+            return Some(prev_pages);
+        }
+
+        let new_pages = prev_pages.checked_add(delta)?;
+        if new_pages > self.maximum {
+            return None;
+        }
+
+        unsafe {
+            commit(
+                self.base.add(pages_to_bytes(prev_pages)),
+                pages_to_bytes(delta),
+            )
+            .ok()?;
+        }
+        self.size.set(new_pages);
+
+        Some(prev_pages)
+    }
+
+    fn vmmemory(&self) -> VMMemoryDefinition {
+        VMMemoryDefinition {
+            base: self.base,
+            current_length: pages_to_bytes(self.size.get()),
+        }
+    }
+
+    fn initialize_with_image(&self, image: &MemoryImage) -> Result<(), String> {
+        // The slot is reset with fresh anonymous pages when the memory is
+        // dropped, which also discards the image's mapping.
+        unsafe { image.map_at(self.base, pages_to_bytes(self.size.get())) }
+    }
+}
+
+impl Drop for PooledMemory {
+    fn drop(&mut self) {
+        unsafe {
+            decommit(self.base, pages_to_bytes(self.size.get()))
+                .expect("failed to decommit memory pages");
+        }
+    }
+}
+
+fn pages_to_bytes(pages: u32) -> usize {
+    usize::try_from(pages).unwrap() * WASM_PAGE_SIZE as usize
+}
+
+fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+    (size + (page_size - 1)) & !(page_size - 1)
+}
+
+/// Makes the `len` bytes at `addr` accessible.
+#[cfg(not(target_os = "windows"))]
+unsafe fn commit(addr: *mut u8, len: usize) -> Result<(), String> {
+    if len == 0 {
+        return Ok(());
+    }
+    region::protect(addr, len, region::Protection::READ_WRITE).map_err(|e| e.to_string())
+}
+
+/// Makes the `len` bytes at `addr` accessible.
+#[cfg(target_os = "windows")]
+unsafe fn commit(addr: *mut u8, len: usize) -> Result<(), String> {
+    use winapi::um::memoryapi::VirtualAlloc;
+    use winapi::um::winnt::{MEM_COMMIT, PAGE_READWRITE};
+
+    if len == 0 {
+        return Ok(());
+    }
+    if VirtualAlloc(addr as _, len, MEM_COMMIT, PAGE_READWRITE).is_null() {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
+/// Makes the `len` bytes at `addr` inaccessible again, releasing the physical
+/// pages backing them so they're zeroed when next committed.
+#[cfg(not(target_os = "windows"))]
+unsafe fn decommit(addr: *mut u8, len: usize) -> Result<(), String> {
+    if len == 0 {
+        return Ok(());
+    }
+    // Map fresh pages over the range rather than using `madvise`, as not all
+    // platforms guarantee that `MADV_DONTNEED` zeroes the pages.
+    let ptr = libc::mmap(
+        addr as _,
+        len,
+        libc::PROT_NONE,
+        libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+        -1,
+        0,
+    );
+    if ptr as isize == -1_isize {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
+/// Makes the `len` bytes at `addr` inaccessible again, releasing the physical
+/// pages backing them so they're zeroed when next committed.
+#[cfg(target_os = "windows")]
+unsafe fn decommit(addr: *mut u8, len: usize) -> Result<(), String> {
+    use winapi::um::memoryapi::VirtualFree;
+    use winapi::um::winnt::MEM_DECOMMIT;
+
+    if len == 0 {
+        return Ok(());
+    }
+    if VirtualFree(addr as _, len, MEM_DECOMMIT) == 0 {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
@@ -26,8 +26,11 @@ mod externref;
 mod imports;
 mod instance;
 mod jit_int;
+mod limits;
 mod memory;
+mod memory_image;
 mod mmap;
+mod parking_spot;
 mod sig_registry;
 mod table;
 mod traphandlers;
@@ -39,14 +42,21 @@ pub mod libcalls;
 pub use crate::export::*;
 pub use crate::externref::*;
 pub use crate::imports::Imports;
-pub use crate::instance::{InstanceHandle, InstantiationError, LinkError};
+pub use crate::instance::{
+    InstanceAllocationRequest, InstanceAllocator, InstanceHandle, InstanceLimits,
+    InstantiationError, LinkError, ModuleLimits, OnDemandInstanceAllocator,
+    PoolingInstanceAllocator,
+};
 pub use crate::jit_int::GdbJitImageRegistration;
-pub use crate::memory::{RuntimeLinearMemory, RuntimeMemoryCreator};
+pub use crate::limits::{ResourceTracker, RuntimeResourceLimiter};
+pub use crate::memory::{RuntimeLinearMemory, RuntimeMemoryCreator, SharedMemory};
+pub use crate::memory_image::{MemoryImage, MemoryImages};
 pub use crate::mmap::Mmap;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::table::{Table, TableElement};
 pub use crate::traphandlers::{
-    catch_traps, init_traps, raise_lib_trap, raise_user_trap, resume_panic, SignalHandler, Trap,
+    catch_traps, init_traps, raise_lib_trap, raise_user_trap, resume_panic, SignalHandler,
+    TlsRestore, Trap,
 };
 pub use crate::vmcontext::{
     VMCallerCheckedAnyfunc, VMContext, VMFunctionBody, VMFunctionImport, VMGlobalDefinition,
@@ -60,6 +60,7 @@ use crate::externref::VMExternRef;
 use crate::table::Table;
 use crate::traphandlers::raise_lib_trap;
 use crate::vmcontext::{VMCallerCheckedAnyfunc, VMContext};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering::SeqCst};
 use wasmtime_environ::wasm::{
     DataIndex, DefinedMemoryIndex, ElemIndex, MemoryIndex, TableElementType, TableIndex,
 };
@@ -187,12 +188,15 @@ pub unsafe extern "C" fn wasmtime_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    let instance = (&mut *vmctx).instance();
-    let memory_index = DefinedMemoryIndex::from_u32(memory_index);
-
-    instance
-        .memory_grow(memory_index, delta)
-        .unwrap_or(u32::max_value())
+    let result = {
+        let instance = (&mut *vmctx).instance();
+        let memory_index = DefinedMemoryIndex::from_u32(memory_index);
+        instance.memory_grow(memory_index, delta)
+    };
+    match result {
+        Ok(result) => result.unwrap_or(u32::max_value()),
+        Err(trap) => raise_lib_trap(trap),
+    }
 }
 
 /// Implementation of memory.grow for imported 32-bit memories.
@@ -201,12 +205,15 @@ pub unsafe extern "C" fn wasmtime_imported_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    let instance = (&mut *vmctx).instance();
-    let memory_index = MemoryIndex::from_u32(memory_index);
-
-    instance
-        .imported_memory_grow(memory_index, delta)
-        .unwrap_or(u32::max_value())
+    let result = {
+        let instance = (&mut *vmctx).instance();
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        instance.imported_memory_grow(memory_index, delta)
+    };
+    match result {
+        Ok(result) => result.unwrap_or(u32::max_value()),
+        Err(trap) => raise_lib_trap(trap),
+    }
 }
 
 /// Implementation of memory.size for locally-defined 32-bit memories.
@@ -237,28 +244,30 @@ pub unsafe extern "C" fn wasmtime_table_grow(
     // or is a `VMExternRef` until we look at the table type.
     init_value: *mut u8,
 ) -> u32 {
-    let instance = (&mut *vmctx).instance();
-    let table_index = TableIndex::from_u32(table_index);
-    match instance.table_element_type(table_index) {
-        TableElementType::Func => {
-            let func = init_value as *mut VMCallerCheckedAnyfunc;
-            instance
-                .table_grow(table_index, delta, func.into())
-                .unwrap_or(-1_i32 as u32)
-        }
-        TableElementType::Val(ty) => {
-            debug_assert_eq!(ty, crate::ref_type());
-
-            let init_value = if init_value.is_null() {
-                None
-            } else {
-                Some(VMExternRef::clone_from_raw(init_value))
-            };
-
-            instance
-                .table_grow(table_index, delta, init_value.into())
-                .unwrap_or(-1_i32 as u32)
+    let result = {
+        let instance = (&mut *vmctx).instance();
+        let table_index = TableIndex::from_u32(table_index);
+        match instance.table_element_type(table_index) {
+            TableElementType::Func => {
+                let func = init_value as *mut VMCallerCheckedAnyfunc;
+                instance.table_grow(table_index, delta, func.into())
+            }
+            TableElementType::Val(ty) => {
+                debug_assert_eq!(ty, crate::ref_type());
+
+                let init_value = if init_value.is_null() {
+                    None
+                } else {
+                    Some(VMExternRef::clone_from_raw(init_value))
+                };
+
+                instance.table_grow(table_index, delta, init_value.into())
+            }
         }
+    };
+    match result {
+        Ok(result) => result.unwrap_or(-1_i32 as u32),
+        Err(trap) => raise_lib_trap(trap),
     }
 }
 
@@ -409,3 +418,67 @@ pub unsafe extern "C" fn wasmtime_data_drop(vmctx: *mut VMContext, data_index: u
     let instance = (&mut *vmctx).instance();
     instance.data_drop(data_index)
 }
+
+/// Implementation of `memory.atomic.notify`.
+pub unsafe extern "C" fn wasmtime_memory_atomic_notify(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: *mut u8,
+    count: u32,
+) -> u32 {
+    let memory_index = MemoryIndex::from_u32(memory_index);
+    let instance = (&mut *vmctx).instance();
+    instance.memory_atomic_notify(memory_index, addr as usize, count)
+}
+
+/// Implementation of `i32.atomic.wait`.
+pub unsafe extern "C" fn wasmtime_memory_atomic_wait32(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: *mut u8,
+    expected: u32,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&mut *vmctx).instance();
+        // The address was bounds-checked and checked for alignment by the
+        // compiled code.
+        let value = &*(addr as *const AtomicU32);
+        instance.memory_atomic_wait(
+            memory_index,
+            addr as usize,
+            || value.load(SeqCst) == expected,
+            timeout,
+        )
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `i64.atomic.wait`.
+pub unsafe extern "C" fn wasmtime_memory_atomic_wait64(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: *mut u8,
+    expected: u64,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&mut *vmctx).instance();
+        let value = &*(addr as *const AtomicU64);
+        instance.memory_atomic_wait(
+            memory_index,
+            addr as usize,
+            || value.load(SeqCst) == expected,
+            timeout,
+        )
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
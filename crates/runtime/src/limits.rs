@@ -0,0 +1,172 @@
+//! Limits on the resources used by the instances of a store.
+
+use crate::instance::InstantiationError;
+use crate::traphandlers::Trap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasmtime_environ::{MemoryPlan, TablePlan, WASM_PAGE_SIZE};
+
+/// Decides whether the linear memories, tables and instances of a store may
+/// grow.
+pub trait RuntimeResourceLimiter: Send + Sync {
+    /// Called before a linear memory is created with, or grown to, `desired`
+    /// bytes from `current` bytes. `maximum` is the memory's declared maximum
+    /// in bytes, if any.
+    ///
+    /// Returning `Ok(false)` denies the growth and returning an error traps.
+    fn memory_growing(
+        &self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Called before a table is created with, or grown to, `desired` elements
+    /// from `current` elements. `maximum` is the table's declared maximum, if
+    /// any.
+    ///
+    /// Returning `Ok(false)` denies the growth and returning an error traps.
+    fn table_growing(
+        &self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// The maximum number of instances which can be created in the store.
+    fn instances(&self) -> usize;
+}
+
+/// Tracks the resources used by the instances of a store, consulting a
+/// `RuntimeResourceLimiter`, if any, before they grow.
+#[derive(Default)]
+pub struct ResourceTracker {
+    limiter: Option<Box<dyn RuntimeResourceLimiter>>,
+    memory_bytes: AtomicUsize,
+    table_elements: AtomicUsize,
+    instances: AtomicUsize,
+}
+
+impl ResourceTracker {
+    /// Creates a new tracker which consults `limiter`, if specified.
+    pub fn new(limiter: Option<Box<dyn RuntimeResourceLimiter>>) -> Self {
+        Self {
+            limiter,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the total size in bytes of the linear memories in the store.
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of elements of the tables in the store.
+    pub fn table_elements(&self) -> usize {
+        self.table_elements.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of instances created in the store.
+    pub fn instances(&self) -> usize {
+        self.instances.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether another instance can be created in the store.
+    pub(crate) fn check_instance(&self) -> Result<(), InstantiationError> {
+        if let Some(limiter) = &self.limiter {
+            let max = limiter.instances();
+            if self.instances() >= max {
+                return Err(InstantiationError::Limit(
+                    u32::try_from(max).unwrap_or(u32::max_value()),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that an instance was created in the store.
+    pub(crate) fn instance_created(&self) {
+        self.instances.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Checks whether the memories and tables of a new instance, with the
+    /// given plans, can be created.
+    pub(crate) fn check_creation<'a>(
+        &self,
+        memories: impl IntoIterator<Item = &'a MemoryPlan>,
+        tables: impl IntoIterator<Item = &'a TablePlan>,
+    ) -> Result<(), InstantiationError> {
+        for plan in memories {
+            let maximum = plan.memory.maximum.map(pages_to_bytes);
+            if !self
+                .memory_growing(0, pages_to_bytes(plan.memory.minimum), maximum)
+                .map_err(InstantiationError::Trap)?
+            {
+                return Err(InstantiationError::Resource(format!(
+                    "memory minimum size of {} pages exceeds the store's resource limits",
+                    plan.memory.minimum
+                )));
+            }
+        }
+        for plan in tables {
+            if !self
+                .table_growing(0, plan.table.minimum, plan.table.maximum)
+                .map_err(InstantiationError::Trap)?
+            {
+                return Err(InstantiationError::Resource(format!(
+                    "table minimum size of {} elements exceeds the store's resource limits",
+                    plan.table.minimum
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Consults the limiter before a memory grows from `current` to `desired`
+    /// bytes.
+    pub(crate) fn memory_growing(
+        &self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, Trap> {
+        match &self.limiter {
+            Some(limiter) => limiter
+                .memory_growing(current, desired, maximum)
+                .map_err(Trap::User),
+            None => Ok(true),
+        }
+    }
+
+    /// Consults the limiter before a table grows from `current` to `desired`
+    /// elements.
+    pub(crate) fn table_growing(
+        &self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> Result<bool, Trap> {
+        match &self.limiter {
+            Some(limiter) => limiter
+                .table_growing(current, desired, maximum)
+                .map_err(Trap::User),
+            None => Ok(true),
+        }
+    }
+
+    /// Records that the memories of the store grew by `delta` bytes.
+    pub(crate) fn memory_grown(&self, delta: usize) {
+        self.memory_bytes.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    /// Records that the tables of the store grew by `delta` elements.
+    pub(crate) fn table_grown(&self, delta: u32) {
+        self.table_elements
+            .fetch_add(usize::try_from(delta).unwrap(), Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn pages_to_bytes(pages: u32) -> usize {
+    usize::try_from(pages).unwrap() * WASM_PAGE_SIZE as usize
+}
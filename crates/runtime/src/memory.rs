@@ -2,17 +2,28 @@
 //!
 //! `RuntimeLinearMemory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+use crate::memory_image::MemoryImage;
 use crate::mmap::Mmap;
 use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::{assert_ge, assert_le};
-use std::cell::RefCell;
+use std::cell::{RefCell, UnsafeCell};
 use std::convert::TryFrom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use wasmtime_environ::{MemoryPlan, MemoryStyle, WASM_MAX_PAGES, WASM_PAGE_SIZE};
 
 /// A memory allocator
 pub trait RuntimeMemoryCreator: Send + Sync {
     /// Create new RuntimeLinearMemory
     fn new_memory(&self, plan: &MemoryPlan) -> Result<Box<dyn RuntimeLinearMemory>, String>;
+
+    /// Create a new SharedMemory for a shared memory `plan`.
+    ///
+    /// Shared memories can outlive the instance they're defined by, so they're
+    /// always allocated by wasmtime by default.
+    fn new_shared_memory(&self, plan: &MemoryPlan) -> Result<SharedMemory, String> {
+        SharedMemory::new(plan.clone())
+    }
 }
 
 /// A default memory allocator used by Wasmtime
@@ -26,7 +37,7 @@ impl RuntimeMemoryCreator for DefaultMemoryCreator {
 }
 
 /// A linear memory
-pub trait RuntimeLinearMemory {
+pub trait RuntimeLinearMemory: Send {
     /// Returns the number of allocated wasm pages.
     fn size(&self) -> u32;
 
@@ -38,6 +49,21 @@ pub trait RuntimeLinearMemory {
 
     /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
     fn vmmemory(&self) -> VMMemoryDefinition;
+
+    /// Initialize this memory, which must not have been used yet, with the
+    /// contents of `image`.
+    ///
+    /// The image is copied into the memory by default. Memories which own
+    /// their mapping can instead map the image copy-on-write.
+    fn initialize_with_image(&self, image: &MemoryImage) -> Result<(), String> {
+        let memory = self.vmmemory();
+        unsafe { image.copy_to(memory.base, memory.current_length) }
+    }
+
+    /// Returns this memory as a `SharedMemory`, if it's shared between threads.
+    fn as_shared(&self) -> Option<&SharedMemory> {
+        None
+    }
 }
 
 /// A linear memory instance.
@@ -183,4 +209,101 @@ impl RuntimeLinearMemory for MmapMemory {
             current_length: mmap.size as usize * WASM_PAGE_SIZE as usize,
         }
     }
+
+    /// Map `image` copy-on-write over the start of this memory.
+    fn initialize_with_image(&self, image: &MemoryImage) -> Result<(), String> {
+        let mut mmap = self.mmap.borrow_mut();
+        let len = mmap.size as usize * WASM_PAGE_SIZE as usize;
+        unsafe { image.map_at(mmap.alloc.as_mut_ptr(), len) }
+    }
+}
+
+/// A linear memory which can be shared between threads, and imported into
+/// instances of different stores.
+///
+/// Shared memories are always static, so their base address never changes as
+/// they grow.
+#[derive(Clone)]
+pub struct SharedMemory(Arc<SharedMemoryInner>);
+
+struct SharedMemoryInner {
+    memory: Mutex<MmapMemory>,
+    // The definition which importing instances point to, so that they all see
+    // the current length of the memory. Its base never changes, and its
+    // length is only written by `grow`, atomically and while `memory` is
+    // locked. See `grow` for why readers on other threads may load it without
+    // synchronization.
+    definition: UnsafeCell<VMMemoryDefinition>,
+    plan: MemoryPlan,
+}
+
+unsafe impl Send for SharedMemoryInner {}
+unsafe impl Sync for SharedMemoryInner {}
+
+impl SharedMemory {
+    /// Create a new shared memory for `plan`, which must be the static plan of
+    /// a shared memory.
+    pub fn new(plan: MemoryPlan) -> Result<Self, String> {
+        assert!(plan.memory.shared);
+        match plan.style {
+            MemoryStyle::Static { .. } => {}
+            MemoryStyle::Dynamic => return Err("shared memories must be static".to_string()),
+        }
+        let memory = MmapMemory::new(&plan)?;
+        let definition = UnsafeCell::new(memory.vmmemory());
+        Ok(Self(Arc::new(SharedMemoryInner {
+            memory: Mutex::new(memory),
+            definition,
+            plan,
+        })))
+    }
+
+    /// Returns the plan this memory was created with.
+    pub fn plan(&self) -> &MemoryPlan {
+        &self.0.plan
+    }
+
+    /// Returns a pointer to the definition of this memory, which stays valid
+    /// as long as any clone of this `SharedMemory` is alive.
+    pub fn vmmemory_ptr(&self) -> *mut VMMemoryDefinition {
+        self.0.definition.get()
+    }
+}
+
+impl RuntimeLinearMemory for SharedMemory {
+    fn size(&self) -> u32 {
+        self.0.memory.lock().unwrap().size()
+    }
+
+    fn grow(&self, delta: u32) -> Option<u32> {
+        let memory = self.0.memory.lock().unwrap();
+        let prev_pages = memory.grow(delta)?;
+        let new_length = memory.vmmemory().current_length;
+        // Other threads may be reading the length concurrently, from compiled
+        // code or from the runtime's bounds checks, without taking the lock.
+        // The length is therefore stored atomically, as `AtomicUsize` has the
+        // same layout as `usize`, and a reader sees either the old or the new
+        // length. Both are safe to use: the memory is static, so its base
+        // doesn't move, and it only ever grows, so the pages below the old
+        // length stay accessible. A reader seeing the old length merely
+        // observes the grow as not having happened yet, which the threads
+        // proposal allows for unsynchronized accesses.
+        unsafe {
+            let current_length = &(*self.0.definition.get()).current_length as *const usize;
+            (*(current_length as *const AtomicUsize)).store(new_length, Ordering::SeqCst);
+        }
+        Some(prev_pages)
+    }
+
+    fn vmmemory(&self) -> VMMemoryDefinition {
+        self.0.memory.lock().unwrap().vmmemory()
+    }
+
+    fn initialize_with_image(&self, image: &MemoryImage) -> Result<(), String> {
+        self.0.memory.lock().unwrap().initialize_with_image(image)
+    }
+
+    fn as_shared(&self) -> Option<&SharedMemory> {
+        Some(self)
+    }
 }
@@ -0,0 +1,254 @@
+//! Precomputed images of the initial contents of linear memories.
+//!
+//! Data segments which are placed at constant offsets within a module's own
+//! memories can be evaluated once, when the module is compiled, into an image
+//! of each memory's initial contents. Instantiation then only has to map that
+//! image into the new memory, which on Linux is done copy-on-write so that it
+//! takes the same time no matter how much data the module has.
+
+use std::convert::TryFrom;
+use std::ptr;
+use wasmtime_environ::entity::{EntityRef, PrimaryMap};
+use wasmtime_environ::wasm::DefinedMemoryIndex;
+use wasmtime_environ::{DataInitializer, Module, WASM_PAGE_SIZE};
+
+/// The initial contents of a linear memory, starting at its first byte.
+#[derive(Debug)]
+pub struct MemoryImage {
+    len: usize,
+    contents: ImageContents,
+}
+
+/// Where the bytes of a `MemoryImage` are kept. Only one copy is kept, so that
+/// large images don't take up twice their size for the lifetime of a module.
+#[derive(Debug)]
+enum ImageContents {
+    /// The bytes themselves, used where they can't be placed in a file.
+    Data(Box<[u8]>),
+
+    /// A file holding the bytes, padded to a whole number of pages, which is
+    /// mapped copy-on-write into new memories.
+    #[cfg(target_os = "linux")]
+    File(ImageFile),
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct ImageFile {
+    file: std::fs::File,
+    len: usize,
+}
+
+impl MemoryImage {
+    fn new(data: Vec<u8>) -> Self {
+        let len = data.len();
+
+        #[cfg(target_os = "linux")]
+        {
+            match ImageFile::new(&data) {
+                Ok(file) => {
+                    return Self {
+                        len,
+                        contents: ImageContents::File(file),
+                    }
+                }
+                Err(e) => log::warn!("failed to create a file for a memory image: {}", e),
+            }
+        }
+
+        Self {
+            len,
+            contents: ImageContents::Data(data.into_boxed_slice()),
+        }
+    }
+
+    /// Returns the length of this image in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether this image is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a copy of the bytes of this image.
+    pub fn to_vec(&self) -> Result<Vec<u8>, String> {
+        let mut data = vec![0; self.len];
+        // Safety: `data` has room for exactly this image.
+        unsafe {
+            self.copy_to(data.as_mut_ptr(), data.len())?;
+        }
+        Ok(data)
+    }
+
+    /// Copies this image to the start of the `len` bytes of memory at `base`.
+    ///
+    /// # Safety
+    ///
+    /// `base` must point to `len` accessible bytes which aren't otherwise
+    /// borrowed.
+    pub unsafe fn copy_to(&self, base: *mut u8, len: usize) -> Result<(), String> {
+        if self.len > len {
+            return Err(format!(
+                "memory image of {} bytes does not fit in a memory of {} bytes",
+                self.len, len
+            ));
+        }
+        match &self.contents {
+            ImageContents::Data(data) => {
+                ptr::copy_nonoverlapping(data.as_ptr(), base, data.len());
+            }
+            #[cfg(target_os = "linux")]
+            ImageContents::File(image) => {
+                use std::os::unix::fs::FileExt;
+
+                let dst = std::slice::from_raw_parts_mut(base, self.len);
+                image
+                    .file
+                    .read_exact_at(dst, 0)
+                    .map_err(|e| format!("failed to read memory image: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Maps this image copy-on-write over the start of the `len` bytes of
+    /// memory at `base`, falling back to copying it where that isn't
+    /// supported.
+    ///
+    /// # Safety
+    ///
+    /// In addition to the requirements of `copy_to`, `base` must be
+    /// page-aligned and the pages it points to must belong to a mapping owned
+    /// by the caller, since they may be replaced with a new mapping.
+    pub unsafe fn map_at(&self, base: *mut u8, len: usize) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        {
+            if let ImageContents::File(image) = &self.contents {
+                if image.len <= len {
+                    return image.map_at(base);
+                }
+            }
+        }
+        self.copy_to(base, len)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ImageFile {
+    fn new(data: &[u8]) -> std::io::Result<Self> {
+        use std::io::Write;
+        use std::os::unix::io::FromRawFd;
+
+        let page_size = region::page::size();
+        let len = (data.len() + (page_size - 1)) & !(page_size - 1);
+
+        let fd = unsafe {
+            libc::memfd_create(
+                b"wasm-memory-image\0".as_ptr() as *const libc::c_char,
+                libc::MFD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+        file.write_all(data)?;
+        file.set_len(u64::try_from(len).unwrap())?;
+        Ok(Self { file, len })
+    }
+
+    unsafe fn map_at(&self, base: *mut u8) -> Result<(), String> {
+        use std::os::unix::io::AsRawFd;
+
+        let ptr = libc::mmap(
+            base as _,
+            self.len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_FIXED,
+            self.file.as_raw_fd(),
+            0,
+        );
+        if ptr as isize == -1_isize {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The images of a module's defined memories, for those memories which have
+/// data that could be evaluated ahead of time.
+#[derive(Debug, Default)]
+pub struct MemoryImages {
+    images: PrimaryMap<DefinedMemoryIndex, Option<MemoryImage>>,
+}
+
+impl MemoryImages {
+    /// Evaluates the data initializers of `module` into images of its defined
+    /// memories.
+    ///
+    /// A segment is placed into an image when it has a constant offset and
+    /// fits within its memory's initial size. The first segment of a memory
+    /// which doesn't, and every later segment of that memory, is returned
+    /// along with the segments of imported memories so that they can be
+    /// applied in order when instantiating, after the images.
+    pub fn new<'data>(
+        module: &Module,
+        data_initializers: Vec<DataInitializer<'data>>,
+    ) -> (Self, Vec<DataInitializer<'data>>) {
+        let local = &module.local;
+        let mut contents = local
+            .memory_plans
+            .values()
+            .skip(local.num_imported_memories)
+            .map(|plan| {
+                let minimum = usize::try_from(plan.memory.minimum).unwrap();
+                (Vec::new(), minimum * WASM_PAGE_SIZE as usize, true)
+            })
+            .collect::<Vec<_>>();
+
+        let mut remaining = Vec::new();
+        for init in data_initializers {
+            let location = &init.location;
+            if let Some(index) = local.defined_memory_index(location.memory_index) {
+                let (image, size, open) = &mut contents[index.index()];
+                match location.offset.checked_add(init.data.len()) {
+                    Some(end) if *open && location.base.is_none() && end <= *size => {
+                        if image.len() < end {
+                            image.resize(end, 0);
+                        }
+                        image[location.offset..end].copy_from_slice(init.data);
+                        continue;
+                    }
+                    _ => *open = false,
+                }
+            }
+            remaining.push(init);
+        }
+
+        let images = contents
+            .into_iter()
+            .map(|(image, _, _)| {
+                if image.is_empty() {
+                    None
+                } else {
+                    Some(MemoryImage::new(image))
+                }
+            })
+            .collect();
+        (Self { images }, remaining)
+    }
+
+    /// Returns the image of the defined memory `index`, if it has one.
+    pub fn get(&self, index: DefinedMemoryIndex) -> Option<&MemoryImage> {
+        self.images.get(index).and_then(|image| image.as_ref())
+    }
+
+    /// Returns the images of the defined memories which have one.
+    pub fn iter(&self) -> impl Iterator<Item = (DefinedMemoryIndex, &MemoryImage)> {
+        self.images
+            .iter()
+            .filter_map(|(index, image)| Some((index, image.as_ref()?)))
+    }
+}
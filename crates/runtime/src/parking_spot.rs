@@ -0,0 +1,191 @@
+//! Support for `memory.atomic.wait` and `memory.atomic.notify`.
+//!
+//! Threads waiting on an address of a shared memory are parked in a `ParkingSpot`, keyed by the
+//! native address, and woken up in the order in which they started waiting.
+
+use lazy_static::lazy_static;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+lazy_static! {
+    /// The parking spot shared by all the memories of the process.
+    ///
+    /// Shared memories can be imported into instances of different stores, so waiting threads
+    /// must be found by any of them.
+    pub(crate) static ref PARKING_SPOT: ParkingSpot = ParkingSpot::default();
+}
+
+/// The result of parking a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParkResult {
+    /// The thread was woken up by `unpark`.
+    Unparked,
+    /// The validation callback returned `false`, so the thread wasn't parked.
+    Invalid,
+    /// The timeout expired before the thread was woken up.
+    TimedOut,
+}
+
+/// The threads waiting on the same address.
+#[derive(Default)]
+struct Spot {
+    // Shared with the waiting threads, which need it after releasing the lock of the map.
+    cvar: Arc<Condvar>,
+    // The identifiers of the parked threads, in the order in which they will be woken up.
+    waiting: VecDeque<u64>,
+    // The identifiers of the threads which were woken up, but haven't returned yet.
+    unparked: Vec<u64>,
+}
+
+#[derive(Default)]
+struct Inner {
+    spots: HashMap<usize, Spot>,
+    next_id: u64,
+}
+
+/// A set of threads waiting on addresses.
+#[derive(Default)]
+pub struct ParkingSpot {
+    inner: Mutex<Inner>,
+}
+
+impl ParkingSpot {
+    /// Parks the current thread on `addr` until it's unparked or `deadline` is reached.
+    ///
+    /// `validate` is called with the lock of the parking spot held, so that checking whether the
+    /// thread should wait can't race with `unpark`. If it returns `false` the thread isn't parked.
+    pub fn park(
+        &self,
+        addr: usize,
+        validate: impl FnOnce() -> bool,
+        deadline: Option<Instant>,
+    ) -> ParkResult {
+        let mut inner = self.inner.lock().unwrap();
+        if !validate() {
+            return ParkResult::Invalid;
+        }
+
+        let id = inner.next_id;
+        inner.next_id += 1;
+        let spot = inner.spots.entry(addr).or_insert_with(Spot::default);
+        spot.waiting.push_back(id);
+        let cvar = spot.cvar.clone();
+
+        loop {
+            inner = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now < deadline {
+                        cvar.wait_timeout(inner, deadline - now).unwrap().0
+                    } else {
+                        inner
+                    }
+                }
+                None => cvar.wait(inner).unwrap(),
+            };
+
+            let spot = inner.spots.get_mut(&addr).unwrap();
+            if let Some(i) = spot.unparked.iter().position(|&other| other == id) {
+                spot.unparked.swap_remove(i);
+                inner.remove_if_unused(addr);
+                return ParkResult::Unparked;
+            }
+            if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                let i = spot.waiting.iter().position(|&other| other == id).unwrap();
+                spot.waiting.remove(i);
+                inner.remove_if_unused(addr);
+                return ParkResult::TimedOut;
+            }
+            // A spurious wakeup, or another thread was woken up.
+        }
+    }
+
+    /// Wakes up at most `count` threads parked on `addr`, returning how many were woken up.
+    pub fn unpark(&self, addr: usize, count: u32) -> u32 {
+        let mut inner = self.inner.lock().unwrap();
+        let spot = match inner.spots.get_mut(&addr) {
+            Some(spot) => spot,
+            None => return 0,
+        };
+        let mut unparked = 0;
+        while unparked < count {
+            match spot.waiting.pop_front() {
+                Some(id) => spot.unparked.push(id),
+                None => break,
+            }
+            unparked += 1;
+        }
+        if unparked > 0 {
+            spot.cvar.notify_all();
+        }
+        unparked
+    }
+}
+
+impl Inner {
+    fn remove_if_unused(&mut self, addr: usize) {
+        let spot = &self.spots[&addr];
+        if spot.waiting.is_empty() && spot.unparked.is_empty() {
+            self.spots.remove(&addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParkResult, ParkingSpot};
+    use std::sync::atomic::{AtomicU32, Ordering::SeqCst};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn invalid() {
+        let spot = ParkingSpot::default();
+        assert_eq!(spot.park(0, || false, None), ParkResult::Invalid);
+        assert_eq!(spot.unpark(0, 1), 0);
+    }
+
+    #[test]
+    fn timeout() {
+        let spot = ParkingSpot::default();
+        let deadline = Instant::now() + Duration::from_millis(10);
+        assert_eq!(spot.park(0, || true, Some(deadline)), ParkResult::TimedOut);
+        assert!(Instant::now() >= deadline);
+        assert_eq!(spot.unpark(0, 1), 0);
+    }
+
+    #[test]
+    fn unpark_some() {
+        let spot = Arc::new(ParkingSpot::default());
+        let parked = Arc::new(AtomicU32::new(0));
+        let threads = (0..3)
+            .map(|_| {
+                let spot = spot.clone();
+                let parked = parked.clone();
+                thread::spawn(move || {
+                    spot.park(
+                        8,
+                        || {
+                            parked.fetch_add(1, SeqCst);
+                            true
+                        },
+                        None,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        while parked.load(SeqCst) < 3 {
+            thread::yield_now();
+        }
+
+        assert_eq!(spot.unpark(4, 3), 0);
+        assert_eq!(spot.unpark(8, 2), 2);
+        assert_eq!(spot.unpark(8, 2), 1);
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), ParkResult::Unparked);
+        }
+        assert_eq!(spot.unpark(8, 1), 0);
+    }
+}
@@ -5,8 +5,10 @@
 use crate::vmcontext::{VMCallerCheckedAnyfunc, VMTableDefinition};
 use crate::{Trap, VMExternRef};
 use std::cell::RefCell;
+use std::cmp;
 use std::convert::{TryFrom, TryInto};
 use std::ptr;
+use std::slice;
 use wasmtime_environ::wasm::TableElementType;
 use wasmtime_environ::{ir, TablePlan, TableStyle};
 
@@ -30,6 +32,12 @@ pub enum TableElement {
 enum TableElements {
     FuncRefs(Vec<*mut VMCallerCheckedAnyfunc>),
     ExternRefs(Vec<Option<VMExternRef>>),
+    /// Function references stored in memory owned by an instance allocator,
+    /// which is valid for `maximum` elements of which `size` are in use.
+    StaticFuncRefs {
+        data: *mut *mut VMCallerCheckedAnyfunc,
+        size: u32,
+    },
 }
 
 impl Table {
@@ -51,10 +59,42 @@ impl Table {
         }
     }
 
+    /// Create a new `funcref` table instance whose elements are stored in
+    /// `data`, which can't grow beyond `max_elements` elements.
+    ///
+    /// # Unsafety
+    ///
+    /// `data` must be valid for reads and writes of `max_elements` elements
+    /// for the lifetime of the table.
+    pub unsafe fn new_static(
+        plan: &TablePlan,
+        data: *mut *mut VMCallerCheckedAnyfunc,
+        max_elements: u32,
+    ) -> Self {
+        assert_eq!(plan.table.ty, TableElementType::Func);
+        assert!(plan.table.minimum <= max_elements);
+        let size = plan.table.minimum;
+        for i in 0..usize::try_from(size).unwrap() {
+            ptr::write(data.add(i), ptr::null_mut());
+        }
+        let maximum = Some(cmp::min(
+            plan.table.maximum.unwrap_or(max_elements),
+            max_elements,
+        ));
+        match plan.style {
+            TableStyle::CallerChecksSignature => Self {
+                elements: RefCell::new(TableElements::StaticFuncRefs { data, size }),
+                maximum,
+            },
+        }
+    }
+
     /// Returns the type of the elements in this table.
     pub fn element_type(&self) -> TableElementType {
         match &*self.elements.borrow() {
-            TableElements::FuncRefs(_) => TableElementType::Func,
+            TableElements::FuncRefs(_) | TableElements::StaticFuncRefs { .. } => {
+                TableElementType::Func
+            }
             TableElements::ExternRefs(_) => TableElementType::Val(crate::ref_type()),
         }
     }
@@ -64,6 +104,7 @@ impl Table {
         match &*self.elements.borrow() {
             TableElements::FuncRefs(x) => x.len().try_into().unwrap(),
             TableElements::ExternRefs(x) => x.len().try_into().unwrap(),
+            TableElements::StaticFuncRefs { size, .. } => *size,
         }
     }
 
@@ -103,6 +144,13 @@ impl Table {
                 let init_value = init_value.try_into().ok()?;
                 x.resize(new_len, init_value)
             }
+            TableElements::StaticFuncRefs { data, size } => {
+                let init_value = init_value.try_into().ok()?;
+                for i in usize::try_from(*size).unwrap()..new_len {
+                    ptr::write(data.add(i), init_value);
+                }
+                *size = u32::try_from(new_len).unwrap();
+            }
         }
 
         Some(size)
@@ -117,6 +165,10 @@ impl Table {
             TableElements::ExternRefs(x) => {
                 x.get(index as usize).cloned().map(TableElement::ExternRef)
             }
+            TableElements::StaticFuncRefs { data, size } => {
+                let x = unsafe { static_elements(*data, *size) };
+                x.get(index as usize).cloned().map(TableElement::FuncRef)
+            }
         }
     }
 
@@ -137,6 +189,11 @@ impl Table {
                 let slot = x.get_mut(index as usize).ok_or(())?;
                 *slot = elem.try_into().or(Err(()))?;
             }
+            TableElements::StaticFuncRefs { data, size } => {
+                let x = unsafe { static_elements(*data, *size) };
+                let slot = x.get_mut(index as usize).ok_or(())?;
+                *slot = elem.try_into().or(Err(()))?;
+            }
         }
         Ok(())
     }
@@ -197,10 +254,21 @@ impl Table {
                 base: x.as_ptr() as *const u8 as *mut u8,
                 current_elements: x.len().try_into().unwrap(),
             },
+            TableElements::StaticFuncRefs { data, size } => VMTableDefinition {
+                base: *data as *mut u8,
+                current_elements: *size,
+            },
         }
     }
 }
 
+unsafe fn static_elements<'a>(
+    data: *mut *mut VMCallerCheckedAnyfunc,
+    size: u32,
+) -> &'a mut [*mut VMCallerCheckedAnyfunc] {
+    slice::from_raw_parts_mut(data, usize::try_from(size).unwrap())
+}
+
 impl TryFrom<TableElement> for *mut VMCallerCheckedAnyfunc {
     type Error = TableElement;
 
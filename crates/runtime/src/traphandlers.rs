@@ -1,7 +1,7 @@
 //! WebAssembly trap handling, which is built on top of the lower-level
 //! signalhandling mechanisms.
 
-use crate::VMContext;
+use crate::{VMContext, VMInterrupts};
 use backtrace::Backtrace;
 use std::any::Any;
 use std::cell::Cell;
@@ -395,6 +395,65 @@ where
     }
 }
 
+/// Per-thread state for wasm calls which must be saved and restored when a wasm
+/// computation running on a separate native stack (a fiber) is suspended and
+/// later resumed.
+///
+/// The `CallThreadState` registered for wasm lives on the fiber's stack, so
+/// while the fiber is suspended it must not be visible to the thread, for
+/// example to a trap handler invoked for unrelated code. Similarly the wasm
+/// stack limit configured by the fiber's outermost call into wasm only makes
+/// sense while executing on that fiber's stack.
+pub struct TlsRestore {
+    state: *const CallThreadState<'static>,
+    stack_limit: usize,
+}
+
+impl TlsRestore {
+    /// Takes the state of the current thread's wasm calls, if any, leaving the
+    /// thread as if no wasm were executing.
+    ///
+    /// # Unsafety
+    ///
+    /// The returned value must be passed to `replace` on this same thread
+    /// once the native stack that was active when `take` was called is
+    /// switched back to.
+    pub unsafe fn take() -> TlsRestore {
+        let state = tls::replace(ptr::null());
+        let stack_limit = if state.is_null() {
+            usize::max_value()
+        } else {
+            (*state)
+                .interrupts()
+                .stack_limit
+                .swap(usize::max_value(), SeqCst)
+        };
+        TlsRestore { state, stack_limit }
+    }
+
+    /// Restores state previously taken with `take`.
+    ///
+    /// If the store was interrupted in the meantime the interrupt is left in
+    /// place so it'll be delivered to the resumed wasm code.
+    ///
+    /// # Unsafety
+    ///
+    /// Must be called on the thread, and native stack, that `take` was called
+    /// on.
+    pub unsafe fn replace(self) {
+        let prev = tls::replace(self.state);
+        debug_assert!(prev.is_null());
+        if !self.state.is_null() {
+            let _ = (*self.state).interrupts().stack_limit.compare_exchange(
+                usize::max_value(),
+                self.stack_limit,
+                SeqCst,
+                SeqCst,
+            );
+        }
+    }
+}
+
 /// Temporary state stored on the stack which is registered in the `tls` module
 /// below for calls into wasm.
 pub struct CallThreadState<'a> {
@@ -495,12 +554,18 @@ impl<'a> CallThreadState<'a> {
     ///
     /// Note that this function must be called with `self` on the stack, not the
     /// heap/etc.
+    ///
+    /// When wasm executes on a fiber the stack limit is relative to the
+    /// fiber's stack, which is where `self` lives. A suspended fiber removes
+    /// its stack limit (see `TlsRestore`) so that computations on other stacks
+    /// start out in case 1 above, and puts it back when it's resumed. This
+    /// means the native stack of a fiber must be larger than `max_wasm_stack`.
     fn update_stack_limit(&self, max_wasm_stack: usize) -> Result<impl Drop + '_, Trap> {
         // Make an "educated guess" to figure out where the wasm sp value should
         // start trapping if it drops below.
         let wasm_stack_limit = self as *const _ as usize - max_wasm_stack;
 
-        let interrupts = unsafe { &**(&*self.vmctx).instance().interrupts() };
+        let interrupts = self.interrupts();
         let reset_stack_limit = match interrupts.stack_limit.compare_exchange(
             usize::max_value(),
             wasm_stack_limit,
@@ -547,6 +612,10 @@ impl<'a> CallThreadState<'a> {
         Ok(Reset(reset_stack_limit, &interrupts.stack_limit))
     }
 
+    fn interrupts(&self) -> &VMInterrupts {
+        unsafe { &**(&*self.vmctx).instance().interrupts() }
+    }
+
     fn unwind_with(&self, reason: UnwindReason) -> ! {
         self.unwind.replace(reason);
         unsafe {
@@ -667,6 +736,13 @@ mod tls {
         })
     }
 
+    /// Replaces the pointer configured with `set`, returning the previous
+    /// value. Used to move the state of a suspended fiber off of this thread
+    /// and back again.
+    pub fn replace(ptr: *const CallThreadState<'static>) -> *const CallThreadState<'static> {
+        PTR.with(|p| p.replace(ptr))
+    }
+
     /// Returns the last pointer configured with `set` above. Panics if `set`
     /// has not been previously called.
     pub fn with<R>(closure: impl FnOnce(Option<&CallThreadState<'_>>) -> R) -> R {
@@ -0,0 +1,199 @@
+//! Read-only filesystems backed by the files of a tar archive.
+
+use crate::virtfs::{FileContents, VirtualDirEntry};
+use crate::wasi::{types, Errno, Result};
+use log::{error, warn};
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+/// The contents of a file stored in a tar archive, read from the archive on demand.
+#[derive(Debug, Clone)]
+struct ArchiveFileContents {
+    archive: Arc<File>,
+    offset: u64,
+    size: u64,
+}
+
+impl ArchiveFileContents {
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        use std::os::unix::fs::FileExt;
+        self.archive.read_at(buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        use std::os::windows::fs::FileExt;
+        self.archive.seek_read(buf, offset)
+    }
+}
+
+impl FileContents for ArchiveFileContents {
+    fn max_size(&self) -> types::Filesize {
+        self.size
+    }
+
+    fn size(&self) -> types::Filesize {
+        self.size
+    }
+
+    fn resize(&mut self, _new_size: types::Filesize) -> Result<()> {
+        Err(Errno::Rofs)
+    }
+
+    fn preadv(&self, iovs: &mut [io::IoSliceMut], offset: types::Filesize) -> Result<usize> {
+        let mut read_total = 0usize;
+        for iov in iovs.iter_mut() {
+            let read = self.pread(iov, offset + read_total as types::Filesize)?;
+            read_total += read;
+            if read < iov.len() {
+                break;
+            }
+        }
+        Ok(read_total)
+    }
+
+    fn pwritev(&mut self, _iovs: &[io::IoSlice], _offset: types::Filesize) -> Result<usize> {
+        Err(Errno::Rofs)
+    }
+
+    fn pwrite(&mut self, _buf: &[u8], _offset: types::Filesize) -> Result<usize> {
+        Err(Errno::Rofs)
+    }
+
+    fn pread(&self, buf: &mut [u8], offset: types::Filesize) -> Result<usize> {
+        if offset >= self.size {
+            return Ok(0);
+        }
+        let len = std::cmp::min(buf.len() as u64, self.size - offset) as usize;
+        let buf = &mut buf[..len];
+        let mut read_total = 0;
+        while read_total < len {
+            let position = self.offset + offset + read_total as u64;
+            match self.read_at(&mut buf[read_total..], position) {
+                // The archive was truncated after it was opened.
+                Ok(0) => break,
+                Ok(read) => read_total += read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    error!("failed to read from the archive: {}", e);
+                    return Err(Errno::Io);
+                }
+            }
+        }
+        Ok(read_total)
+    }
+}
+
+/// Reads the entries of the tar archive `archive`, returning the tree of its directories and files.
+///
+/// The contents of the files aren't read, but are read from `archive` when the guest reads them.
+/// Symbolic links, special files, and entries with paths leading outside of the archive are
+/// skipped.
+pub(crate) fn read_tar(archive: File) -> io::Result<HashMap<String, VirtualDirEntry>> {
+    let archive = Arc::new(archive);
+    let mut root = HashMap::new();
+    // The contents of the files read so far, by path, to resolve hard links.
+    let mut files: HashMap<Vec<String>, ArchiveFileContents> = HashMap::new();
+
+    let mut tar = tar::Archive::new(&*archive);
+    for entry in tar.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let components = match normalize(&path) {
+            Some(components) => components,
+            None => {
+                warn!(
+                    "skipping `{}` in the archive: it leads outside of the archive",
+                    path.display()
+                );
+                continue;
+            }
+        };
+
+        let entry_type = entry.header().entry_type();
+        let contents = if entry_type.is_dir() {
+            None
+        } else if entry_type.is_file() {
+            Some(ArchiveFileContents {
+                archive: Arc::clone(&archive),
+                offset: entry.raw_file_position(),
+                size: entry.size(),
+            })
+        } else if entry_type.is_hard_link() {
+            let target = entry.link_name()?.and_then(|target| normalize(&target));
+            match target.and_then(|target| files.get(&target)) {
+                Some(contents) => Some(contents.clone()),
+                None => {
+                    warn!(
+                        "skipping `{}` in the archive: its link target wasn't found",
+                        path.display()
+                    );
+                    continue;
+                }
+            }
+        } else {
+            warn!(
+                "skipping `{}` in the archive: unsupported entry type {:?}",
+                path.display(),
+                entry_type
+            );
+            continue;
+        };
+
+        let (name, parents) = match components.split_last() {
+            Some(split) => split,
+            // The root directory itself.
+            None => continue,
+        };
+        let mut dir = &mut root;
+        for parent in parents {
+            let entry = dir
+                .entry(parent.clone())
+                .or_insert_with(VirtualDirEntry::empty_directory);
+            dir = match entry {
+                VirtualDirEntry::Directory(entries) => entries,
+                VirtualDirEntry::File(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("`{}` is both a file and a directory", parent),
+                    ))
+                }
+            };
+        }
+        match contents {
+            Some(contents) => {
+                // Later entries replace earlier ones, as when extracting the archive.
+                dir.insert(
+                    name.clone(),
+                    VirtualDirEntry::File(Box::new(contents.clone())),
+                );
+                files.insert(components, contents);
+            }
+            None => {
+                if let Entry::Vacant(e) = dir.entry(name.clone()) {
+                    e.insert(VirtualDirEntry::empty_directory());
+                }
+            }
+        }
+    }
+
+    Ok(root)
+}
+
+/// Returns the components of the relative path of an archive entry, or `None` if it leads outside
+/// of the archive.
+fn normalize(path: &Path) -> Option<Vec<String>> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(components)
+}
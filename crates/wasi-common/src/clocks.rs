@@ -0,0 +1,121 @@
+//! The clocks read by a `WasiCtx`.
+
+use crate::sys::clock;
+use crate::wasi::{types, Result};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The clocks of a `WasiCtx`, used by `clock_res_get`, `clock_time_get` and
+/// the timeouts of `poll_oneoff`.
+pub trait WasiClocks: Send {
+    /// Returns the resolution of the clock `id`, in nanoseconds.
+    fn res_get(&self, id: types::Clockid) -> Result<types::Timestamp>;
+
+    /// Returns the time of the clock `id`, in nanoseconds.
+    fn time_get(&self, id: types::Clockid) -> Result<types::Timestamp>;
+
+    /// Returns whether the clocks are virtual, only advancing when the host
+    /// tells them to, rather than with the time of the host.
+    ///
+    /// `poll_oneoff` doesn't wait on the host for the timeouts of virtual
+    /// clocks. If no file descriptor is ready, the timeout expires right away,
+    /// after calling `sleep` with its delay.
+    fn is_virtual(&self) -> bool {
+        false
+    }
+
+    /// Lets `delay` nanoseconds pass on virtual clocks, when a `poll_oneoff`
+    /// timeout expires.
+    fn sleep(&self, _delay: u128) {}
+}
+
+/// The clocks of the host.
+///
+/// These are used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClocks;
+
+impl WasiClocks for SystemClocks {
+    fn res_get(&self, id: types::Clockid) -> Result<types::Timestamp> {
+        clock::res_get(id)
+    }
+
+    fn time_get(&self, id: types::Clockid) -> Result<types::Timestamp> {
+        clock::time_get(id)
+    }
+}
+
+/// Virtual clocks, which only advance when the host advances them, or when
+/// the program waits for a timeout.
+///
+/// All clocks read the same time, the time since the Unix epoch, with a
+/// resolution of one nanosecond. Clones of a `FakeClocks` share that time, so
+/// the host can keep one to advance the time of the clocks it passed to a
+/// `WasiCtxBuilder`.
+#[derive(Debug, Clone, Default)]
+pub struct FakeClocks {
+    now: Arc<AtomicU64>,
+}
+
+impl FakeClocks {
+    /// Creates clocks reading `now`, the time since the Unix epoch.
+    pub fn new(now: Duration) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(to_nanos(now))),
+        }
+    }
+
+    /// Returns the time the clocks read.
+    pub fn now(&self) -> Duration {
+        Duration::from_nanos(self.now.load(Ordering::SeqCst))
+    }
+
+    /// Sets the time the clocks read to `now`.
+    pub fn set(&self, now: Duration) {
+        self.now.store(to_nanos(now), Ordering::SeqCst);
+    }
+
+    /// Advances the clocks by `delay`.
+    pub fn advance(&self, delay: Duration) {
+        self.advance_nanos(to_nanos(delay));
+    }
+
+    fn advance_nanos(&self, delay: u64) {
+        let mut now = self.now.load(Ordering::SeqCst);
+        loop {
+            match self.now.compare_exchange_weak(
+                now,
+                now.saturating_add(delay),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => break,
+                Err(current) => now = current,
+            }
+        }
+    }
+}
+
+impl WasiClocks for FakeClocks {
+    fn res_get(&self, _id: types::Clockid) -> Result<types::Timestamp> {
+        Ok(1)
+    }
+
+    fn time_get(&self, _id: types::Clockid) -> Result<types::Timestamp> {
+        Ok(self.now.load(Ordering::SeqCst))
+    }
+
+    fn is_virtual(&self) -> bool {
+        true
+    }
+
+    fn sleep(&self, delay: u128) {
+        self.advance_nanos(u64::try_from(delay).unwrap_or(u64::max_value()));
+    }
+}
+
+fn to_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::max_value())
+}
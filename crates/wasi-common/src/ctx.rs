@@ -1,9 +1,15 @@
+use crate::archive;
+use crate::clocks::{SystemClocks, WasiClocks};
 use crate::entry::{Entry, EntryHandle};
 use crate::fdpool::FdPool;
 use crate::handle::Handle;
+use crate::random::{SystemRandom, WasiRandom};
+use crate::record::{Call, GuestWrite, Trace, TraceError, Traced};
 use crate::sys::osdir::OsDir;
 use crate::sys::stdio::NullDevice;
 use crate::sys::stdio::{Stderr, StderrExt, Stdin, StdinExt, Stdout, StdoutExt};
+#[cfg(unix)]
+use crate::sys::OsSocket;
 use crate::virtfs::{VirtualDir, VirtualDirEntry};
 use crate::wasi::types;
 use crate::wasi::{Errno, Result};
@@ -13,6 +19,7 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::ffi::{self, CString, OsString};
 use std::fs::File;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::{env, io, string};
@@ -41,6 +48,9 @@ pub enum WasiCtxBuilderError {
     /// `WasiCtx` has too many opened files.
     #[error("context object has too many opened files")]
     TooManyFilesOpen,
+    /// The trace to record or replay couldn't be started.
+    #[error("failed to start the trace: {0}")]
+    Trace(#[from] TraceError),
 }
 
 type WasiCtxBuilderResult<T> = std::result::Result<T, WasiCtxBuilderError>;
@@ -108,6 +118,30 @@ impl PendingCString {
     }
 }
 
+enum PendingTrace {
+    Record(Box<dyn Write + Send>),
+    Replay(Box<dyn Read + Send>),
+}
+
+fn populate_directory(
+    virtentry: HashMap<String, VirtualDirEntry>,
+    dir: &mut VirtualDir,
+    writable: bool,
+) {
+    for (path, entry) in virtentry.into_iter() {
+        match entry {
+            VirtualDirEntry::Directory(dir_entries) => {
+                let mut subdir = VirtualDir::new(writable);
+                populate_directory(dir_entries, &mut subdir, writable);
+                dir.add_dir(subdir, path);
+            }
+            VirtualDirEntry::File(content) => {
+                dir.add_file(content, path);
+            }
+        }
+    }
+}
+
 struct PendingPreopen(Box<dyn FnOnce() -> WasiCtxBuilderResult<Box<dyn Handle>>>);
 
 impl PendingPreopen {
@@ -129,8 +163,12 @@ pub struct WasiCtxBuilder {
     stdout: Option<PendingEntry>,
     stderr: Option<PendingEntry>,
     preopens: Option<Vec<(PathBuf, PendingPreopen)>>,
+    sockets: Option<Vec<Box<dyn Handle>>>,
     args: Option<Vec<PendingCString>>,
     env: Option<HashMap<PendingCString, PendingCString>>,
+    clocks: Option<Box<dyn WasiClocks>>,
+    random: Option<Box<dyn WasiRandom>>,
+    trace: Option<PendingTrace>,
 }
 
 impl WasiCtxBuilder {
@@ -145,8 +183,12 @@ impl WasiCtxBuilder {
             stdout,
             stderr,
             preopens: Some(Vec::new()),
+            sockets: Some(Vec::new()),
             args: Some(Vec::new()),
             env: Some(HashMap::new()),
+            clocks: Some(Box::new(SystemClocks)),
+            random: Some(Box::new(SystemRandom)),
+            trace: None,
         }
     }
 
@@ -284,26 +326,11 @@ impl WasiCtxBuilder {
         dir: VirtualDirEntry,
         guest_path: P,
     ) -> &mut Self {
-        fn populate_directory(virtentry: HashMap<String, VirtualDirEntry>, dir: &mut VirtualDir) {
-            for (path, entry) in virtentry.into_iter() {
-                match entry {
-                    VirtualDirEntry::Directory(dir_entries) => {
-                        let mut subdir = VirtualDir::new(true);
-                        populate_directory(dir_entries, &mut subdir);
-                        dir.add_dir(subdir, path);
-                    }
-                    VirtualDirEntry::File(content) => {
-                        dir.add_file(content, path);
-                    }
-                }
-            }
-        }
-
         let guest_path_owned = guest_path.as_ref().to_owned();
         let preopen = PendingPreopen::new(move || {
             if let VirtualDirEntry::Directory(entries) = dir {
                 let mut dir = VirtualDir::new(true);
-                populate_directory(entries, &mut dir);
+                populate_directory(entries, &mut dir, true);
                 Ok(Box::new(dir))
             } else {
                 Err(WasiCtxBuilderError::VirtualDirEntryRootNotADirectory(
@@ -318,6 +345,92 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Add a preopened read-only directory with the contents of the tar archive `archive`.
+    ///
+    /// The files are read from `archive` as the guest reads them, rather than being loaded in
+    /// memory.
+    pub fn preopened_archive<P: AsRef<Path>>(&mut self, archive: File, guest_path: P) -> &mut Self {
+        let preopen = PendingPreopen::new(move || {
+            let entries = archive::read_tar(archive)?;
+            let mut dir = VirtualDir::new(false);
+            populate_directory(entries, &mut dir, false);
+            Ok(Box::new(dir))
+        });
+        self.preopens
+            .as_mut()
+            .unwrap()
+            .push((guest_path.as_ref().to_owned(), preopen));
+        self
+    }
+
+    /// Add a preopened empty, writable, in-memory directory.
+    ///
+    /// If `max_size` is given, the files created in the directory can't total more than
+    /// `max_size` bytes; writes beyond that fail with `Errno::Nospc`.
+    pub fn preopened_tmpfs<P: AsRef<Path>>(
+        &mut self,
+        guest_path: P,
+        max_size: Option<u64>,
+    ) -> &mut Self {
+        let preopen = PendingPreopen::new(move || {
+            let dir = match max_size {
+                Some(max_size) => VirtualDir::with_size_limit(max_size),
+                None => VirtualDir::new(true),
+            };
+            Ok(Box::new(dir))
+        });
+        self.preopens
+            .as_mut()
+            .unwrap()
+            .push((guest_path.as_ref().to_owned(), preopen));
+        self
+    }
+
+    /// Add a preopened socket, either listening for incoming connections or
+    /// already connected to a peer.
+    ///
+    /// Preopened sockets are assigned descriptors after all of the preopened
+    /// directories, in the order they were added.
+    #[cfg(unix)]
+    pub fn preopened_socket<S: Into<OsSocket>>(&mut self, socket: S) -> &mut Self {
+        self.sockets.as_mut().unwrap().push(Box::new(socket.into()));
+        self
+    }
+
+    /// Provide the clocks read by the program, instead of the host's clocks.
+    pub fn clocks<T: WasiClocks + 'static>(&mut self, clocks: T) -> &mut Self {
+        self.clocks = Some(Box::new(clocks));
+        self
+    }
+
+    /// Provide the source of the random data read by the program, instead of the host's random
+    /// number generator.
+    pub fn random<T: WasiRandom + 'static>(&mut self, random: T) -> &mut Self {
+        self.random = Some(Box::new(random));
+        self
+    }
+
+    /// Record the results of the nondeterministic calls made through the `WasiCtx` to `writer`.
+    ///
+    /// These are the calls reading clocks, random data, files and their metadata, and polling.
+    /// The trace is only guaranteed to be complete once `WasiCtx::finish_trace()` has been
+    /// called.
+    pub fn record<W: Write + Send + 'static>(&mut self, writer: W) -> &mut Self {
+        self.trace = Some(PendingTrace::Record(Box::new(writer)));
+        self
+    }
+
+    /// Replay the trace recorded by `WasiCtxBuilder::record()` which is read from `reader`.
+    ///
+    /// The nondeterministic calls made through the `WasiCtx` aren't made on the host, and
+    /// return the results from the trace instead. If a call doesn't match the one in the trace,
+    /// it and all subsequent calls fail with `Errno::Notcapable`, and the divergence is reported
+    /// by `WasiCtx::finish_trace()`.
+    pub fn replay<R: Read + Send + 'static>(&mut self, reader: R) -> &mut Self {
+        self.trace = Some(PendingTrace::Replay(Box::new(reader)));
+        self
+    }
+
     /// Build a `WasiCtx`, consuming this `WasiCtxBuilder`.
     ///
     /// If any of the arguments or environment variables in this builder cannot be converted into
@@ -388,11 +501,28 @@ impl WasiCtxBuilder {
                 .ok_or(WasiCtxBuilderError::TooManyFilesOpen)?;
             log::debug!("WasiCtx inserted at {:?}", fd);
         }
+        // And finally the preopened sockets.
+        for socket in self.sockets.take().unwrap() {
+            let entry = Entry::new(EntryHandle::from(socket));
+            let fd = entries
+                .insert(entry)
+                .ok_or(WasiCtxBuilderError::TooManyFilesOpen)?;
+            log::debug!("WasiCtx inserted at {:?}", fd);
+        }
+
+        let trace = match self.trace.take() {
+            Some(PendingTrace::Record(writer)) => Some(RefCell::new(Trace::record(writer)?)),
+            Some(PendingTrace::Replay(reader)) => Some(RefCell::new(Trace::replay(reader)?)),
+            None => None,
+        };
 
         Ok(WasiCtx {
             args,
             env,
             entries: RefCell::new(entries),
+            clocks: self.clocks.take().unwrap(),
+            random: RefCell::new(self.random.take().unwrap()),
+            trace,
         })
     }
 }
@@ -439,8 +569,20 @@ pub struct WasiCtx {
     entries: RefCell<EntryTable>,
     pub(crate) args: Vec<CString>,
     pub(crate) env: Vec<CString>,
+    pub(crate) clocks: Box<dyn WasiClocks>,
+    pub(crate) random: RefCell<Box<dyn WasiRandom>>,
+    trace: Option<RefCell<Trace>>,
 }
 
+// Entries and their handles are reference counted with `Rc`, which is safe to
+// send along with the context as long as the context owns every reference
+// whenever it's moved. Clones of those `Rc`s are only handed out by the
+// `pub(crate)` methods below, which borrow the context, and nothing but the
+// entry table keeps them beyond the WASI call that made them, so they're all
+// dropped before that call releases its borrow. The handles themselves are
+// required to be `Send`.
+unsafe impl Send for WasiCtx {}
+
 impl WasiCtx {
     /// Make a new `WasiCtx` with some default settings.
     ///
@@ -457,6 +599,74 @@ impl WasiCtx {
             .build()
     }
 
+    /// Finish recording or replaying a trace, if one was configured with `WasiCtxBuilder`.
+    ///
+    /// When recording, this flushes the trace. When replaying, this checks that the whole trace
+    /// was replayed, and reports where the program diverged from it otherwise. In both cases, it
+    /// reports any error accessing the trace.
+    pub fn finish_trace(&self) -> std::result::Result<(), TraceError> {
+        match &self.trace {
+            Some(trace) => trace.borrow_mut().finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Make the nondeterministic `call` with `f`, recording its result if recording a trace, or
+    /// return the recorded result without calling `f` if replaying one.
+    pub(crate) fn nondeterministic<T: Traced>(
+        &self,
+        call: Call,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        match &self.trace {
+            Some(trace) => Trace::call(trace, call, f),
+            None => f(),
+        }
+    }
+
+    /// Like `nondeterministic`, but for a call `f` which writes its result into guest memory.
+    ///
+    /// When recording, `save` reads back what `f` wrote, given the size it returned. When
+    /// replaying, `restore` writes what was recorded into guest memory again.
+    pub(crate) fn nondeterministic_write(
+        &self,
+        call: Call,
+        f: impl FnOnce() -> Result<types::Size>,
+        save: impl FnOnce(types::Size) -> Result<Vec<u8>>,
+        restore: impl FnOnce(&[u8]) -> Result<()>,
+    ) -> Result<types::Size> {
+        let trace = match &self.trace {
+            Some(trace) => trace,
+            None => return f(),
+        };
+        let write = Trace::call(trace, call, || {
+            let size = f()?;
+            Ok(GuestWrite {
+                size,
+                bytes: save(size)?,
+            })
+        })?;
+        if self.replaying() {
+            restore(&write.bytes)?;
+        }
+        Ok(write.size)
+    }
+
+    /// Whether calls are being replayed from a trace rather than made on the host.
+    ///
+    /// Replayed calls which move a file descriptor's position must move the host position
+    /// too, so that the calls which aren't traced, like `fd_write` and `fd_tell`, see the
+    /// same position as when recording.
+    pub(crate) fn replaying(&self) -> bool {
+        match &self.trace {
+            Some(trace) => match &*trace.borrow() {
+                Trace::Replay { .. } => true,
+                Trace::Record { .. } => false,
+            },
+            None => false,
+        }
+    }
+
     /// Check if `WasiCtx` contains the specified raw WASI `fd`.
     pub(crate) fn contains_entry(&self, fd: types::Fd) -> bool {
         self.entries.borrow().contains(&fd)
@@ -5,6 +5,8 @@ use std::ops::Deref;
 use std::path::PathBuf;
 use std::rc::Rc;
 
+/// A reference counted `Handle`. Clones must not outlive the WASI call which
+/// made them, since `WasiCtx` relies on that to be `Send`.
 pub(crate) struct EntryHandle(Rc<dyn Handle>);
 
 impl EntryHandle {
@@ -62,7 +62,8 @@ impl fmt::Display for HandleRights {
 }
 
 /// Generic interface for all WASI-compatible handles. We currently group these into two groups:
-/// * OS-based resources (actual, real resources): `OsFile`, `OsDir`, `OsOther`, and `Stdio`,
+/// * OS-based resources (actual, real resources): `OsFile`, `OsDir`, `OsOther`, `OsSocket`, and
+///   `Stdio`,
 /// * virtual files and directories: VirtualDir`, and `InMemoryFile`.
 ///
 /// # Constructing `Handle`s representing OS-based resources
@@ -79,7 +80,10 @@ impl fmt::Display for HandleRights {
 /// let some_file = OpenOptions::new().read(true).open("some_file").unwrap();
 /// let wasi_handle: Box<dyn Handle> = some_file.try_into().unwrap();
 /// ```
-pub trait Handle {
+///
+/// Handles are required to be `Send` so that a `WasiCtx` can be moved to
+/// another thread along with the `wasmtime::Store` it's used in.
+pub trait Handle: Send {
     fn as_any(&self) -> &dyn Any;
     fn try_clone(&self) -> io::Result<Box<dyn Handle>>;
     fn get_file_type(&self) -> types::Filetype;
@@ -214,4 +218,22 @@ pub trait Handle {
     fn unlink_file(&self, _path: &str) -> Result<()> {
         Err(Errno::Acces)
     }
+    // TODO perhaps should be a separate trait?
+    // SockOps
+    fn sock_accept(&self, _fdflags: types::Fdflags) -> Result<Box<dyn Handle>> {
+        Err(Errno::Notsock)
+    }
+    fn sock_recv(
+        &self,
+        _ri_data: &mut [io::IoSliceMut],
+        _ri_flags: types::Riflags,
+    ) -> Result<(usize, types::Roflags)> {
+        Err(Errno::Notsock)
+    }
+    fn sock_send(&self, _si_data: &[io::IoSlice], _si_flags: types::Siflags) -> Result<usize> {
+        Err(Errno::Notsock)
+    }
+    fn sock_shutdown(&self, _how: types::Sdflags) -> Result<()> {
+        Err(Errno::Notsock)
+    }
 }
@@ -21,6 +21,8 @@
     )
 )]
 
+mod archive;
+mod clocks;
 mod ctx;
 mod entry;
 mod fdpool;
@@ -29,16 +31,23 @@ mod handle;
 pub mod old;
 mod path;
 mod poll;
+mod random;
+mod record;
 mod sandboxed_tty_writer;
 pub mod snapshots;
 mod sys;
 mod virtfs;
 pub mod wasi;
 
+pub use clocks::{FakeClocks, SystemClocks, WasiClocks};
 pub use ctx::{WasiCtx, WasiCtxBuilder, WasiCtxBuilderError};
 pub use handle::{Handle, HandleRights};
+pub use random::{SeededRandom, SystemRandom, WasiRandom};
+pub use record::TraceError;
 pub use sys::osdir::OsDir;
 pub use sys::osfile::OsFile;
 pub use sys::osother::OsOther;
 pub use sys::preopen_dir;
+#[cfg(unix)]
+pub use sys::OsSocket;
 pub use virtfs::{FileContents, VirtualDirEntry};
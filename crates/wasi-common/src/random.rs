@@ -0,0 +1,104 @@
+//! The source of random data of a `WasiCtx`.
+
+use crate::wasi::{Errno, Result};
+
+/// The source of the random data returned by `random_get`.
+pub trait WasiRandom: Send {
+    /// Fills `buf` with random bytes.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<()>;
+}
+
+/// The random number generator of the host's operating system.
+///
+/// This is used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemRandom;
+
+impl WasiRandom for SystemRandom {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<()> {
+        getrandom::getrandom(buf).map_err(|err| {
+            log::error!("getrandom failure: {:?}", err);
+            Errno::Io
+        })
+    }
+}
+
+/// A pseudorandom number generator, which always produces the same sequence
+/// of bytes for a given seed.
+///
+/// This is xoshiro256**, seeded with SplitMix64. It isn't cryptographically
+/// secure.
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: [u64; 4],
+}
+
+impl SeededRandom {
+    /// Creates a generator seeded with `seed`.
+    pub fn new(mut seed: u64) -> Self {
+        let mut state = [0; 4];
+        for word in state.iter_mut() {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *word = z ^ (z >> 31);
+        }
+        Self { state }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+}
+
+impl WasiRandom for SeededRandom {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<()> {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeded_random_is_deterministic() {
+        let mut a = [0; 20];
+        let mut b = [0; 20];
+        SeededRandom::new(42).fill(&mut a).unwrap();
+        SeededRandom::new(42).fill(&mut b).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, [0; 20]);
+
+        SeededRandom::new(43).fill(&mut b).unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn seeded_random_continues_sequence() {
+        let mut whole = [0; 16];
+        SeededRandom::new(7).fill(&mut whole).unwrap();
+
+        let mut random = SeededRandom::new(7);
+        let mut first = [0; 8];
+        let mut second = [0; 8];
+        random.fill(&mut first).unwrap();
+        random.fill(&mut second).unwrap();
+        assert_eq!(whole[..8], first);
+        assert_eq!(whole[8..], second);
+    }
+}
@@ -0,0 +1,484 @@
+//! Recording and replaying of the results of nondeterministic WASI calls.
+//!
+//! When recording, the results of the calls whose outcome depends on the host,
+//! such as clocks, randomness, polling, reads, seeks and file metadata, are
+//! appended to a trace. When replaying, those calls aren't made on the host at
+//! all and the results stored in the trace are returned instead, so that a
+//! program follows exactly the same path as when it was recorded. Only the
+//! position of the file descriptors which are read or seeked is still moved
+//! on the host, so that the calls which aren't traced see the same position.
+//!
+//! A trace starts with a header, followed by one record per call:
+//!
+//! - the syscall, as a `u8`, followed by the number of its arguments, as a
+//!   `u8`, and the arguments identifying the call, as `u64`s;
+//! - the path the call was made on, as a `u32` length followed by its UTF-8
+//!   bytes, which is empty for calls made on file descriptors only;
+//! - the resulting errno, as a `u16`;
+//! - on success, the length of the result, as a `u32`, followed by the result
+//!   itself.
+//!
+//! All integers are little-endian.
+
+use crate::wasi::{types, Errno, Result};
+use std::cell::RefCell;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+const MAGIC: &[u8; 8] = b"\0wasitrc";
+const VERSION: u32 = 1;
+
+/// Errors while recording or replaying a trace.
+#[derive(Debug, thiserror::Error)]
+pub enum TraceError {
+    /// An I/O error was encountered reading or writing the trace.
+    #[error("I/O error accessing the trace: {0}")]
+    Io(#[from] io::Error),
+    /// The trace doesn't start with a valid header.
+    #[error("not a WASI trace, or one recorded by an incompatible version")]
+    InvalidHeader,
+    /// The record of a call in the trace couldn't be decoded.
+    #[error("malformed trace at call #{0}")]
+    Malformed(u64),
+    /// The program made a different call than the one recorded in the trace.
+    #[error("replay diverged at call #{index}: expected {expected}, found {actual}")]
+    Divergence {
+        /// The index of the call, starting at 1.
+        index: u64,
+        /// The call recorded in the trace, or the end of the trace.
+        expected: String,
+        /// The call the program made, or the end of the program.
+        actual: String,
+    },
+}
+
+/// The nondeterministic WASI calls which are traced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Syscall {
+    ClockResGet = 1,
+    ClockTimeGet,
+    RandomGet,
+    PollOneoff,
+    FdRead,
+    FdPread,
+    FdReaddir,
+    FdFilestatGet,
+    PathFilestatGet,
+    FdSeek,
+}
+
+impl Syscall {
+    const ALL: [Self; 10] = [
+        Self::ClockResGet,
+        Self::ClockTimeGet,
+        Self::RandomGet,
+        Self::PollOneoff,
+        Self::FdRead,
+        Self::FdPread,
+        Self::FdReaddir,
+        Self::FdFilestatGet,
+        Self::PathFilestatGet,
+        Self::FdSeek,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::ClockResGet => "clock_res_get",
+            Self::ClockTimeGet => "clock_time_get",
+            Self::RandomGet => "random_get",
+            Self::PollOneoff => "poll_oneoff",
+            Self::FdRead => "fd_read",
+            Self::FdPread => "fd_pread",
+            Self::FdReaddir => "fd_readdir",
+            Self::FdFilestatGet => "fd_filestat_get",
+            Self::PathFilestatGet => "path_filestat_get",
+            Self::FdSeek => "fd_seek",
+        }
+    }
+}
+
+/// A traced call: the syscall and the arguments and path identifying it,
+/// which are compared on replay to detect divergence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Call {
+    syscall: Syscall,
+    args: Vec<u64>,
+    path: String,
+}
+
+impl Call {
+    pub(crate) fn new(syscall: Syscall, args: &[u64]) -> Self {
+        Self::with_path(syscall, args, String::new())
+    }
+
+    pub(crate) fn with_path(syscall: Syscall, args: &[u64], path: String) -> Self {
+        Self {
+            syscall,
+            args: args.to_vec(),
+            path,
+        }
+    }
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.syscall.name())?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", arg)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, ", {:?}", self.path)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A result which can be stored in a trace.
+pub(crate) trait Traced: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl Traced for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(Self::from_le_bytes(bytes.try_into().ok()?))
+    }
+}
+
+impl Traced for types::Filestat {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for field in &[self.dev, self.ino, self.nlink, self.size] {
+            field.encode(out);
+        }
+        for field in &[self.atim, self.mtim, self.ctim] {
+            field.encode(out);
+        }
+        out.push(self.filetype.into());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 7 * 8 + 1 {
+            return None;
+        }
+        let field = |i: usize| u64::decode(&bytes[i * 8..(i + 1) * 8]);
+        Some(Self {
+            dev: field(0)?,
+            ino: field(1)?,
+            nlink: field(2)?,
+            size: field(3)?,
+            atim: field(4)?,
+            mtim: field(5)?,
+            ctim: field(6)?,
+            filetype: types::Filetype::try_from(bytes[7 * 8]).ok()?,
+        })
+    }
+}
+
+/// The result of a call which writes `bytes` into guest memory and returns
+/// `size`, usually the number of bytes written.
+pub(crate) struct GuestWrite {
+    pub(crate) size: types::Size,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Traced for GuestWrite {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.size.to_le_bytes());
+        out.extend_from_slice(&self.bytes);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 {
+            return None;
+        }
+        Some(Self {
+            size: types::Size::from_le_bytes(bytes[..4].try_into().ok()?),
+            bytes: bytes[4..].to_vec(),
+        })
+    }
+}
+
+/// A trace being recorded or replayed by a `WasiCtx`.
+pub(crate) enum Trace {
+    Record {
+        writer: BufWriter<Box<dyn Write + Send>>,
+        calls: u64,
+        error: Option<TraceError>,
+    },
+    Replay {
+        reader: BufReader<Box<dyn Read + Send>>,
+        calls: u64,
+        error: Option<TraceError>,
+    },
+}
+
+impl Trace {
+    /// Starts recording a trace to `writer`.
+    pub(crate) fn record(writer: Box<dyn Write + Send>) -> std::result::Result<Self, TraceError> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(Self::Record {
+            writer,
+            calls: 0,
+            error: None,
+        })
+    }
+
+    /// Starts replaying the trace read from `reader`.
+    pub(crate) fn replay(reader: Box<dyn Read + Send>) -> std::result::Result<Self, TraceError> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0; 12];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| TraceError::InvalidHeader)?;
+        if &header[..8] != MAGIC || header[8..] != VERSION.to_le_bytes() {
+            return Err(TraceError::InvalidHeader);
+        }
+        Ok(Self::Replay {
+            reader,
+            calls: 0,
+            error: None,
+        })
+    }
+
+    /// Makes `call` with `f` when recording, and stores its result, or
+    /// returns the result stored for it when replaying, without calling `f`.
+    ///
+    /// Once replay has diverged, or the trace couldn't be accessed, all calls
+    /// fail with `Errno::Notcapable`.
+    pub(crate) fn call<T: Traced>(
+        trace: &RefCell<Self>,
+        call: Call,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let replaying = match &*trace.borrow() {
+            Self::Record { .. } => false,
+            Self::Replay { .. } => true,
+        };
+        if replaying {
+            return trace.borrow_mut().replay_call(&call);
+        }
+        let result = f();
+        trace.borrow_mut().record_call(&call, &result);
+        result
+    }
+
+    fn record_call<T: Traced>(&mut self, call: &Call, result: &Result<T>) {
+        let (writer, calls, error) = match self {
+            Self::Record {
+                writer,
+                calls,
+                error,
+            } => (writer, calls, error),
+            Self::Replay { .. } => unreachable!(),
+        };
+        if error.is_some() {
+            return;
+        }
+        *calls += 1;
+
+        let mut record = vec![call.syscall as u8, call.args.len() as u8];
+        for arg in &call.args {
+            arg.encode(&mut record);
+        }
+        record.extend_from_slice(&(call.path.len() as u32).to_le_bytes());
+        record.extend_from_slice(call.path.as_bytes());
+        match result {
+            Ok(value) => {
+                record.extend_from_slice(&u16::from(Errno::Success).to_le_bytes());
+                let mut bytes = Vec::new();
+                value.encode(&mut bytes);
+                record.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                record.extend_from_slice(&bytes);
+            }
+            Err(errno) => record.extend_from_slice(&u16::from(*errno).to_le_bytes()),
+        }
+        if let Err(e) = writer.write_all(&record) {
+            log::error!("failed to record WASI call {}: {}", call, e);
+            *error = Some(e.into());
+        }
+    }
+
+    fn replay_call<T: Traced>(&mut self, call: &Call) -> Result<T> {
+        let (reader, calls, error) = match self {
+            Self::Replay {
+                reader,
+                calls,
+                error,
+            } => (reader, calls, error),
+            Self::Record { .. } => unreachable!(),
+        };
+        if error.is_some() {
+            return Err(Errno::Notcapable);
+        }
+        *calls += 1;
+
+        match read_record(reader, *calls) {
+            Ok(Some((expected, _))) if expected != *call => {
+                *error = Some(TraceError::Divergence {
+                    index: *calls,
+                    expected: format!("`{}`", expected),
+                    actual: format!("`{}`", call),
+                });
+            }
+            Ok(Some((_, Ok(bytes)))) => match T::decode(&bytes) {
+                Some(value) => return Ok(value),
+                None => *error = Some(TraceError::Malformed(*calls)),
+            },
+            Ok(Some((_, Err(errno)))) => return Err(errno),
+            Ok(None) => {
+                *error = Some(TraceError::Divergence {
+                    index: *calls,
+                    expected: "the end of the trace".to_string(),
+                    actual: format!("`{}`", call),
+                });
+            }
+            Err(e) => *error = Some(e),
+        }
+        log::error!(
+            "failed to replay WASI call {}: {}",
+            call,
+            error.as_ref().unwrap()
+        );
+        Err(Errno::Notcapable)
+    }
+
+    /// Finishes recording, flushing the trace, or replaying, checking that
+    /// the whole trace was replayed.
+    pub(crate) fn finish(&mut self) -> std::result::Result<(), TraceError> {
+        match self {
+            Self::Record { writer, error, .. } => {
+                if let Some(error) = error.take() {
+                    return Err(error);
+                }
+                writer.flush()?;
+            }
+            Self::Replay {
+                reader,
+                calls,
+                error,
+            } => {
+                if let Some(error) = error.take() {
+                    return Err(error);
+                }
+                if let Some((expected, _)) = read_record(reader, *calls + 1)? {
+                    return Err(TraceError::Divergence {
+                        index: *calls + 1,
+                        expected: format!("`{}`", expected),
+                        actual: "the end of the program".to_string(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the record of the call at `index` from `reader`, or returns `None`
+/// at the end of the trace.
+fn read_record(
+    reader: &mut impl Read,
+    index: u64,
+) -> std::result::Result<Option<(Call, Result<Vec<u8>>)>, TraceError> {
+    let malformed = |e: io::Error| match e.kind() {
+        io::ErrorKind::UnexpectedEof => TraceError::Malformed(index),
+        _ => TraceError::Io(e),
+    };
+
+    let mut header = [0; 2];
+    match reader.read(&mut header[..1])? {
+        0 => return Ok(None),
+        _ => reader.read_exact(&mut header[1..]).map_err(malformed)?,
+    }
+    let syscall = match Syscall::ALL.iter().find(|s| **s as u8 == header[0]) {
+        Some(syscall) => *syscall,
+        None => return Err(TraceError::Malformed(index)),
+    };
+    let mut args = Vec::with_capacity(header[1] as usize);
+    for _ in 0..header[1] {
+        let mut arg = [0; 8];
+        reader.read_exact(&mut arg).map_err(malformed)?;
+        args.push(u64::from_le_bytes(arg));
+    }
+    let path =
+        String::from_utf8(read_bytes(reader, index)?).map_err(|_| TraceError::Malformed(index))?;
+
+    let mut errno = [0; 2];
+    reader.read_exact(&mut errno).map_err(malformed)?;
+    let errno =
+        Errno::try_from(u16::from_le_bytes(errno)).map_err(|_| TraceError::Malformed(index))?;
+    let result = if errno == Errno::Success {
+        Ok(read_bytes(reader, index)?)
+    } else {
+        Err(errno)
+    };
+    Ok(Some((
+        Call {
+            syscall,
+            args,
+            path,
+        },
+        result,
+    )))
+}
+
+/// Reads bytes prefixed by their `u32` length from the record of the call at
+/// `index` in `reader`.
+fn read_bytes(reader: &mut impl Read, index: u64) -> std::result::Result<Vec<u8>, TraceError> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => TraceError::Malformed(index),
+        _ => TraceError::Io(e),
+    })?;
+    let len = u32::from_le_bytes(len);
+    let mut bytes = Vec::new();
+    reader.by_ref().take(len.into()).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(TraceError::Malformed(index));
+    }
+    Ok(bytes)
+}
+
+/// Reads the first `len` bytes of the buffers of `iovs` from guest memory.
+pub(crate) fn read_iovecs(iovs: &types::IovecArray<'_>, len: types::Size) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut remaining = len;
+    for iov_ptr in iovs.iter() {
+        if remaining == 0 {
+            break;
+        }
+        let iov: types::Iovec = iov_ptr?.read()?;
+        let len = remaining.min(iov.buf_len);
+        bytes.extend_from_slice(&iov.buf.as_array(len).as_slice()?);
+        remaining -= len;
+    }
+    Ok(bytes)
+}
+
+/// Writes `bytes` into the buffers of `iovs` in guest memory.
+pub(crate) fn write_iovecs(iovs: &types::IovecArray<'_>, mut bytes: &[u8]) -> Result<()> {
+    for iov_ptr in iovs.iter() {
+        if bytes.is_empty() {
+            break;
+        }
+        let iov: types::Iovec = iov_ptr?.read()?;
+        let len = bytes.len().min(iov.buf_len as usize);
+        iov.buf
+            .as_array(len.try_into()?)
+            .copy_from_slice(&bytes[..len])?;
+        bytes = &bytes[len..];
+    }
+    if !bytes.is_empty() {
+        return Err(Errno::Fault);
+    }
+    Ok(())
+}
@@ -1,14 +1,15 @@
 use crate::entry::{Entry, EntryHandle};
 use crate::handle::HandleRights;
+use crate::record::{self, Call, Syscall};
 use crate::sys::clock;
 use crate::wasi::wasi_snapshot_preview1::WasiSnapshotPreview1;
 use crate::wasi::{types, AsBytes, Errno, Result};
 use crate::WasiCtx;
 use crate::{path, poll};
-use log::{debug, error, trace};
+use log::{debug, trace};
 use std::convert::TryInto;
 use std::io::{self, SeekFrom};
-use wiggle::{GuestPtr, GuestSlice};
+use wiggle::{GuestPtr, GuestSlice, GuestType};
 
 impl<'a> WasiSnapshotPreview1 for WasiCtx {
     fn args_get<'b>(
@@ -72,7 +73,8 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn clock_res_get(&self, id: types::Clockid) -> Result<types::Timestamp> {
-        let resolution = clock::res_get(id)?;
+        let call = Call::new(Syscall::ClockResGet, &[u32::from(id).into()]);
+        let resolution = self.nondeterministic(call, || self.clocks.res_get(id))?;
         Ok(resolution)
     }
 
@@ -81,7 +83,8 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         id: types::Clockid,
         _precision: types::Timestamp,
     ) -> Result<types::Timestamp> {
-        let time = clock::time_get(id)?;
+        let call = Call::new(Syscall::ClockTimeGet, &[u32::from(id).into()]);
+        let time = self.nondeterministic(call, || self.clocks.time_get(id))?;
         Ok(time)
     }
 
@@ -165,7 +168,9 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     fn fd_filestat_get(&self, fd: types::Fd) -> Result<types::Filestat> {
         let required_rights = HandleRights::from_base(types::Rights::FD_FILESTAT_GET);
         let entry = self.get_entry(fd)?;
-        let host_filestat = entry.as_handle(&required_rights)?.filestat_get()?;
+        let call = Call::new(Syscall::FdFilestatGet, &[u32::from(fd).into()]);
+        let host_filestat =
+            self.nondeterministic(call, || entry.as_handle(&required_rights)?.filestat_get())?;
         Ok(host_filestat)
     }
 
@@ -199,31 +204,39 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         iovs: &types::IovecArray<'_>,
         offset: types::Filesize,
     ) -> Result<types::Size> {
-        let mut guest_slices: Vec<GuestSlice<'_, u8>> = Vec::new();
-        for iov_ptr in iovs.iter() {
-            let iov_ptr = iov_ptr?;
-            let iov: types::Iovec = iov_ptr.read()?;
-            guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice()?);
-        }
+        let call = Call::new(Syscall::FdPread, &[u32::from(fd).into(), offset]);
+        self.nondeterministic_write(
+            call,
+            || {
+                let mut guest_slices: Vec<GuestSlice<'_, u8>> = Vec::new();
+                for iov_ptr in iovs.iter() {
+                    let iov_ptr = iov_ptr?;
+                    let iov: types::Iovec = iov_ptr.read()?;
+                    guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice()?);
+                }
 
-        let required_rights =
-            HandleRights::from_base(types::Rights::FD_READ | types::Rights::FD_SEEK);
-        let entry = self.get_entry(fd)?;
-        if offset > i64::max_value() as u64 {
-            return Err(Errno::Io);
-        }
+                let required_rights =
+                    HandleRights::from_base(types::Rights::FD_READ | types::Rights::FD_SEEK);
+                let entry = self.get_entry(fd)?;
+                if offset > i64::max_value() as u64 {
+                    return Err(Errno::Io);
+                }
 
-        let host_nread = {
-            let mut buf = guest_slices
-                .iter_mut()
-                .map(|s| io::IoSliceMut::new(&mut *s))
-                .collect::<Vec<io::IoSliceMut<'_>>>();
-            entry
-                .as_handle(&required_rights)?
-                .preadv(&mut buf, offset)?
-                .try_into()?
-        };
-        Ok(host_nread)
+                let host_nread = {
+                    let mut buf = guest_slices
+                        .iter_mut()
+                        .map(|s| io::IoSliceMut::new(&mut *s))
+                        .collect::<Vec<io::IoSliceMut<'_>>>();
+                    entry
+                        .as_handle(&required_rights)?
+                        .preadv(&mut buf, offset)?
+                        .try_into()?
+                };
+                Ok(host_nread)
+            },
+            |nread| record::read_iovecs(iovs, nread),
+            |bytes| record::write_iovecs(iovs, bytes),
+        )
     }
 
     fn fd_prestat_get(&self, fd: types::Fd) -> Result<types::Prestat> {
@@ -302,27 +315,47 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn fd_read(&self, fd: types::Fd, iovs: &types::IovecArray<'_>) -> Result<types::Size> {
-        let mut guest_slices = Vec::new();
-        for iov_ptr in iovs.iter() {
-            let iov_ptr = iov_ptr?;
-            let iov: types::Iovec = iov_ptr.read()?;
-            guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice()?);
-        }
-
-        let required_rights = HandleRights::from_base(types::Rights::FD_READ);
-        let entry = self.get_entry(fd)?;
-        let host_nread = {
-            let mut slices: Vec<io::IoSliceMut> = guest_slices
-                .iter_mut()
-                .map(|s| io::IoSliceMut::new(&mut *s))
-                .collect();
-            entry
-                .as_handle(&required_rights)?
-                .read_vectored(&mut slices)?
-                .try_into()?
-        };
+        let call = Call::new(Syscall::FdRead, &[u32::from(fd).into()]);
+        let nread = self.nondeterministic_write(
+            call,
+            || {
+                let mut guest_slices = Vec::new();
+                for iov_ptr in iovs.iter() {
+                    let iov_ptr = iov_ptr?;
+                    let iov: types::Iovec = iov_ptr.read()?;
+                    guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice()?);
+                }
 
-        Ok(host_nread)
+                let required_rights = HandleRights::from_base(types::Rights::FD_READ);
+                let entry = self.get_entry(fd)?;
+                let host_nread = {
+                    let mut slices: Vec<io::IoSliceMut> = guest_slices
+                        .iter_mut()
+                        .map(|s| io::IoSliceMut::new(&mut *s))
+                        .collect();
+                    entry
+                        .as_handle(&required_rights)?
+                        .read_vectored(&mut slices)?
+                        .try_into()?
+                };
+
+                Ok(host_nread)
+            },
+            |nread| record::read_iovecs(iovs, nread),
+            |bytes| record::write_iovecs(iovs, bytes),
+        )?;
+        // The read wasn't made on the host when replaying, so advance the host position
+        // past the replayed bytes as the read would have. Note that `fd_pread` and
+        // `fd_readdir` don't need this, since they neither use nor move the position.
+        if self.replaying() {
+            let handle = self
+                .get_entry(fd)?
+                .as_handle(&HandleRights::from_base(types::Rights::FD_READ))?;
+            if handle.get_file_type() == types::Filetype::RegularFile {
+                handle.seek(SeekFrom::Current(nread.into()))?;
+            }
+        }
+        Ok(nread)
     }
 
     fn fd_readdir(
@@ -332,30 +365,45 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         buf_len: types::Size,
         cookie: types::Dircookie,
     ) -> Result<types::Size> {
-        let required_rights = HandleRights::from_base(types::Rights::FD_READDIR);
-        let entry = self.get_entry(fd)?;
-
-        let mut bufused = 0;
-        let mut buf = buf.clone();
-        for pair in entry.as_handle(&required_rights)?.readdir(cookie)? {
-            let (dirent, name) = pair?;
-            let dirent_raw = dirent.as_bytes()?;
-            let dirent_len: types::Size = dirent_raw.len().try_into()?;
-            let name_raw = name.as_bytes();
-            let name_len = name_raw.len().try_into()?;
-            let offset = dirent_len.checked_add(name_len).ok_or(Errno::Overflow)?;
-            if (buf_len - bufused) < offset {
-                break;
-            } else {
-                buf.as_array(dirent_len).copy_from_slice(&dirent_raw)?;
-                buf = buf.add(dirent_len)?;
-                buf.as_array(name_len).copy_from_slice(name_raw)?;
-                buf = buf.add(name_len)?;
-                bufused += offset;
-            }
-        }
+        let call = Call::new(
+            Syscall::FdReaddir,
+            &[u32::from(fd).into(), buf_len.into(), cookie],
+        );
+        self.nondeterministic_write(
+            call,
+            || {
+                let required_rights = HandleRights::from_base(types::Rights::FD_READDIR);
+                let entry = self.get_entry(fd)?;
+
+                let mut bufused = 0;
+                let mut buf = buf.clone();
+                for pair in entry.as_handle(&required_rights)?.readdir(cookie)? {
+                    let (dirent, name) = pair?;
+                    let dirent_raw = dirent.as_bytes()?;
+                    let dirent_len: types::Size = dirent_raw.len().try_into()?;
+                    let name_raw = name.as_bytes();
+                    let name_len = name_raw.len().try_into()?;
+                    let offset = dirent_len.checked_add(name_len).ok_or(Errno::Overflow)?;
+                    if (buf_len - bufused) < offset {
+                        break;
+                    } else {
+                        buf.as_array(dirent_len).copy_from_slice(&dirent_raw)?;
+                        buf = buf.add(dirent_len)?;
+                        buf.as_array(name_len).copy_from_slice(name_raw)?;
+                        buf = buf.add(name_len)?;
+                        bufused += offset;
+                    }
+                }
 
-        Ok(bufused)
+                Ok(bufused)
+            },
+            |bufused| Ok(buf.as_array(bufused).as_slice()?.to_vec()),
+            |bytes| {
+                Ok(buf
+                    .as_array(bytes.len().try_into()?)
+                    .copy_from_slice(bytes)?)
+            },
+        )
     }
 
     fn fd_renumber(&self, from: types::Fd, to: types::Fd) -> Result<()> {
@@ -399,7 +447,17 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             types::Whence::End => SeekFrom::End(offset),
             types::Whence::Set => SeekFrom::Start(offset as u64),
         };
-        let host_newoffset = entry.as_handle(&required_rights)?.seek(pos)?;
+        let handle = entry.as_handle(&required_rights)?;
+        // Seeking relative to the end depends on the size of the file, so the resulting
+        // position is traced. When replaying, the host position is moved to it directly.
+        let call = Call::new(
+            Syscall::FdSeek,
+            &[u32::from(fd).into(), offset as u64, u8::from(whence).into()],
+        );
+        let host_newoffset = self.nondeterministic(call, || handle.seek(pos))?;
+        if self.replaying() {
+            handle.seek(SeekFrom::Start(host_newoffset))?;
+        }
         Ok(host_newoffset)
     }
 
@@ -460,10 +518,16 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         path: &GuestPtr<'_, str>,
     ) -> Result<types::Filestat> {
         let required_rights = HandleRights::from_base(types::Rights::PATH_FILESTAT_GET);
+        let call = Call::with_path(
+            Syscall::PathFilestatGet,
+            &[u32::from(dirfd).into(), u32::from(flags).into()],
+            path.as_str()?.to_string(),
+        );
         let entry = self.get_entry(dirfd)?;
         let (dirfd, path) = path::get(&entry, &required_rights, flags, path, false)?;
-        let host_filestat =
-            dirfd.filestat_get_at(&path, flags.contains(&types::Lookupflags::SYMLINK_FOLLOW))?;
+        let host_filestat = self.nondeterministic(call, || {
+            dirfd.filestat_get_at(&path, flags.contains(&types::Lookupflags::SYMLINK_FOLLOW))
+        })?;
         Ok(host_filestat)
     }
 
@@ -700,7 +764,7 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         for subscription in subscriptions {
             match subscription.u {
                 types::SubscriptionU::Clock(clock) => {
-                    let delay = clock::to_relative_ns_delay(&clock)?;
+                    let delay = clock::to_relative_ns_delay(&clock, &*self.clocks)?;
                     debug!("poll_oneoff event.u.clock = {:?}", clock);
                     debug!("poll_oneoff delay = {:?}ns", delay);
                     let current = poll::ClockEventData {
@@ -771,14 +835,49 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         // The underlying implementation should successfully and immediately return
         // if no events have been passed. Such situation may occur if all provided
         // events have been filtered out as errors in the code above.
-        poll::oneoff(timeout, fd_events, &mut events)?;
-        let nevents = events.len().try_into()?;
+        let call = Call::new(Syscall::PollOneoff, &[nsubscriptions.into()]);
+        let nevents = self.nondeterministic_write(
+            call,
+            || {
+                // Virtual clocks don't advance while the host waits, so only
+                // check whether a file descriptor is ready, and let the delay
+                // pass on the clocks if the timeout expires.
+                let is_virtual = self.clocks.is_virtual();
+                let host_timeout = match timeout {
+                    Some(timeout) if is_virtual => Some(poll::ClockEventData {
+                        delay: 0,
+                        ..timeout
+                    }),
+                    timeout => timeout,
+                };
+                poll::oneoff(host_timeout, fd_events, &mut events)?;
+                if let Some(timeout) = timeout {
+                    if is_virtual && events.iter().any(|e| e.type_ == types::Eventtype::Clock) {
+                        self.clocks.sleep(timeout.delay);
+                    }
+                }
+                let nevents = events.len().try_into()?;
 
-        let out_events = out.as_array(nevents);
-        for (event, event_ptr) in events.into_iter().zip(out_events.iter()) {
-            let event_ptr = event_ptr?;
-            event_ptr.write(event)?;
-        }
+                let out_events = out.as_array(nevents);
+                for (event, event_ptr) in events.into_iter().zip(out_events.iter()) {
+                    let event_ptr = event_ptr?;
+                    event_ptr.write(event)?;
+                }
+                Ok(nevents)
+            },
+            |nevents| {
+                let len = nevents
+                    .checked_mul(types::Event::guest_size())
+                    .ok_or(Errno::Overflow)?;
+                Ok(out.cast::<u8>().as_array(len).as_slice()?.to_vec())
+            },
+            |bytes| {
+                Ok(out
+                    .cast::<u8>()
+                    .as_array(bytes.len().try_into()?)
+                    .copy_from_slice(bytes)?)
+            },
+        )?;
 
         trace!("     | *nevents={:?}", nevents);
 
@@ -801,32 +900,106 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn random_get(&self, buf: &GuestPtr<u8>, buf_len: types::Size) -> Result<()> {
-        let mut slice = buf.as_array(buf_len).as_slice()?;
-        getrandom::getrandom(&mut *slice).map_err(|err| {
-            error!("getrandom failure: {:?}", err);
-            Errno::Io
-        })
+        let call = Call::new(Syscall::RandomGet, &[buf_len.into()]);
+        self.nondeterministic_write(
+            call,
+            || {
+                let mut slice = buf.as_array(buf_len).as_slice()?;
+                self.random.borrow_mut().fill(&mut *slice)?;
+                Ok(buf_len)
+            },
+            |len| Ok(buf.as_array(len).as_slice()?.to_vec()),
+            |bytes| {
+                Ok(buf
+                    .as_array(bytes.len().try_into()?)
+                    .copy_from_slice(bytes)?)
+            },
+        )?;
+        Ok(())
     }
 
     fn sock_recv(
         &self,
-        _fd: types::Fd,
-        _ri_data: &types::IovecArray<'_>,
-        _ri_flags: types::Riflags,
+        fd: types::Fd,
+        ri_data: &types::IovecArray<'_>,
+        ri_flags: types::Riflags,
     ) -> Result<(types::Size, types::Roflags)> {
-        unimplemented!("sock_recv")
+        let mut guest_slices = Vec::new();
+        for iov_ptr in ri_data.iter() {
+            let iov_ptr = iov_ptr?;
+            let iov: types::Iovec = iov_ptr.read()?;
+            guest_slices.push(iov.buf.as_array(iov.buf_len).as_slice()?);
+        }
+
+        let required_rights = HandleRights::from_base(types::Rights::FD_READ);
+        let entry = self.get_entry(fd)?;
+        let (host_nread, ro_flags) = {
+            let mut slices: Vec<io::IoSliceMut> = guest_slices
+                .iter_mut()
+                .map(|s| io::IoSliceMut::new(&mut *s))
+                .collect();
+            entry
+                .as_handle(&required_rights)?
+                .sock_recv(&mut slices, ri_flags)?
+        };
+
+        Ok((host_nread.try_into()?, ro_flags))
     }
 
     fn sock_send(
         &self,
-        _fd: types::Fd,
-        _si_data: &types::CiovecArray<'_>,
-        _si_flags: types::Siflags,
+        fd: types::Fd,
+        si_data: &types::CiovecArray<'_>,
+        si_flags: types::Siflags,
     ) -> Result<types::Size> {
-        unimplemented!("sock_send")
+        let mut guest_slices = Vec::new();
+        for ciov_ptr in si_data.iter() {
+            let ciov_ptr = ciov_ptr?;
+            let ciov: types::Ciovec = ciov_ptr.read()?;
+            guest_slices.push(ciov.buf.as_array(ciov.buf_len).as_slice()?);
+        }
+        let required_rights = HandleRights::from_base(types::Rights::FD_WRITE);
+        let entry = self.get_entry(fd)?;
+        let host_nwritten = {
+            let slices: Vec<io::IoSlice> =
+                guest_slices.iter().map(|s| io::IoSlice::new(&*s)).collect();
+            entry
+                .as_handle(&required_rights)?
+                .sock_send(&slices, si_flags)?
+                .try_into()?
+        };
+        Ok(host_nwritten)
+    }
+
+    fn sock_shutdown(&self, fd: types::Fd, how: types::Sdflags) -> Result<()> {
+        let required_rights = HandleRights::from_base(types::Rights::SOCK_SHUTDOWN);
+        let entry = self.get_entry(fd)?;
+        entry.as_handle(&required_rights)?.sock_shutdown(how)
     }
+}
 
-    fn sock_shutdown(&self, _fd: types::Fd, _how: types::Sdflags) -> Result<()> {
-        unimplemented!("sock_shutdown")
+impl WasiCtx {
+    /// Accepts a new connection on the listening socket `fd`, returning the
+    /// descriptor of the connected socket.
+    ///
+    /// The snapshot this context implements doesn't define `sock_accept` yet,
+    /// so this is exposed separately for runtimes to provide to their guests.
+    pub fn sock_accept(&self, fd: types::Fd, flags: types::Fdflags) -> Result<types::Fd> {
+        trace!("sock_accept(fd={:?}, flags={:?})", fd, flags);
+
+        let required_rights = HandleRights::from_base(types::Rights::FD_READ);
+        let entry = self.get_entry(fd)?;
+        let handle = entry.as_handle(&required_rights)?.sock_accept(flags)?;
+        // The rights of the accepted connection are limited by those the
+        // listening socket is allowed to pass on.
+        let rights = handle.get_rights();
+        let inheriting = entry.get_rights().inheriting;
+        handle.set_rights(HandleRights::new(
+            rights.base & inheriting,
+            rights.inheriting & inheriting,
+        ));
+        let fd = self.insert_entry(Entry::new(EntryHandle::from(handle)))?;
+        trace!("     | *fd={:?}", fd);
+        Ok(fd)
     }
 }
@@ -1,17 +1,17 @@
+use crate::clocks::WasiClocks;
 use crate::wasi::types::{Subclockflags, SubscriptionClock};
-use crate::wasi::{Errno, Result};
-use std::time::SystemTime;
+use crate::wasi::Result;
 
 pub(crate) use super::sys_impl::clock::*;
 
-pub(crate) fn to_relative_ns_delay(clock: &SubscriptionClock) -> Result<u128> {
+pub(crate) fn to_relative_ns_delay(
+    clock: &SubscriptionClock,
+    clocks: &dyn WasiClocks,
+) -> Result<u128> {
     if clock.flags != Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME {
         return Ok(u128::from(clock.timeout));
     }
-    let now: u128 = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map_err(|_| Errno::Notcapable)?
-        .as_nanos();
+    let now = u128::from(clocks.time_get(clock.id)?);
     let deadline = u128::from(clock.timeout);
     Ok(deadline.saturating_sub(now))
 }
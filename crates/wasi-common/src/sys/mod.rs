@@ -12,6 +12,7 @@ cfg_if! {
         mod unix;
         use unix as sys_impl;
         pub use unix::preopen_dir;
+        pub use unix::ossocket::OsSocket;
     } else if #[cfg(windows)] {
         mod windows;
         use windows as sys_impl;
@@ -42,6 +43,12 @@ pub(crate) trait AsFile {
 
 impl AsFile for dyn Handle + 'static {
     fn as_file(&self) -> io::Result<ManuallyDrop<File>> {
+        #[cfg(unix)]
+        {
+            if let Some(socket) = self.as_any().downcast_ref::<OsSocket>() {
+                return socket.as_file();
+            }
+        }
         if let Some(file) = self.as_any().downcast_ref::<OsFile>() {
             file.as_file()
         } else if let Some(dir) = self.as_any().downcast_ref::<OsDir>() {
@@ -0,0 +1,171 @@
+use super::fd;
+use crate::handle::{Handle, HandleRights};
+use crate::sys::AsFile;
+use crate::wasi::types::{self, Filetype};
+use crate::wasi::{Errno, Result, RightsExt};
+use std::any::Any;
+use std::cell::Cell;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::os::unix::prelude::{AsRawFd, RawFd};
+use yanix::socket::{recvmsg, MsgFlags};
+
+#[derive(Debug)]
+enum Socket {
+    Listener(TcpListener),
+    Stream(TcpStream),
+}
+
+/// A TCP socket handed to the guest by the host, either listening for incoming
+/// connections or connected to a peer.
+///
+/// Listening sockets can only be used to accept new connections, which are
+/// themselves represented as connected `OsSocket`s.
+///
+/// # Constructing `OsSocket`
+///
+/// `OsSocket` can be constructed from either `std::net::TcpListener` or
+/// `std::net::TcpStream` using the `std::convert::From` trait:
+///
+/// ```rust,no_run
+/// use std::net::TcpListener;
+/// use wasi_common::OsSocket;
+///
+/// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+/// let os_socket = OsSocket::from(listener);
+/// ```
+#[derive(Debug)]
+pub struct OsSocket {
+    rights: Cell<HandleRights>,
+    socket: Socket,
+}
+
+impl OsSocket {
+    fn new(socket: Socket) -> Self {
+        let rights = Cell::new(HandleRights::new(
+            types::Rights::socket_base(),
+            types::Rights::socket_inheriting(),
+        ));
+        Self { rights, socket }
+    }
+
+    fn stream(&self) -> Result<&TcpStream> {
+        match &self.socket {
+            Socket::Stream(stream) => Ok(stream),
+            Socket::Listener(_) => Err(Errno::Notconn),
+        }
+    }
+}
+
+impl From<TcpListener> for OsSocket {
+    fn from(listener: TcpListener) -> Self {
+        Self::new(Socket::Listener(listener))
+    }
+}
+
+impl From<TcpStream> for OsSocket {
+    fn from(stream: TcpStream) -> Self {
+        Self::new(Socket::Stream(stream))
+    }
+}
+
+impl AsRawFd for OsSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        match &self.socket {
+            Socket::Listener(listener) => listener.as_raw_fd(),
+            Socket::Stream(stream) => stream.as_raw_fd(),
+        }
+    }
+}
+
+impl Handle for OsSocket {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn try_clone(&self) -> io::Result<Box<dyn Handle>> {
+        let socket = match &self.socket {
+            Socket::Listener(listener) => Socket::Listener(listener.try_clone()?),
+            Socket::Stream(stream) => Socket::Stream(stream.try_clone()?),
+        };
+        let rights = self.rights.clone();
+        Ok(Box::new(Self { rights, socket }))
+    }
+    fn get_file_type(&self) -> Filetype {
+        Filetype::SocketStream
+    }
+    fn get_rights(&self) -> HandleRights {
+        self.rights.get()
+    }
+    fn set_rights(&self, new_rights: HandleRights) {
+        self.rights.set(new_rights)
+    }
+    // FdOps
+    fn fdstat_get(&self) -> Result<types::Fdflags> {
+        fd::fdstat_get(&*self.as_file()?)
+    }
+    fn fdstat_set_flags(&self, fdflags: types::Fdflags) -> Result<()> {
+        fd::fdstat_set_flags(&*self.as_file()?, fdflags)?;
+        Ok(())
+    }
+    fn filestat_get(&self) -> Result<types::Filestat> {
+        fd::filestat_get(&*self.as_file()?)
+    }
+    fn read_vectored(&self, iovs: &mut [io::IoSliceMut]) -> Result<usize> {
+        let nread = self.stream()?.read_vectored(iovs)?;
+        Ok(nread)
+    }
+    fn write_vectored(&self, iovs: &[io::IoSlice]) -> Result<usize> {
+        let nwritten = self.stream()?.write_vectored(iovs)?;
+        Ok(nwritten)
+    }
+    // SockOps
+    fn sock_accept(&self, fdflags: types::Fdflags) -> Result<Box<dyn Handle>> {
+        let listener = match &self.socket {
+            Socket::Listener(listener) => listener,
+            Socket::Stream(_) => return Err(Errno::Inval),
+        };
+        let (stream, addr) = listener.accept()?;
+        log::debug!("OsSocket accepted a connection from {}", addr);
+        let socket = Self::from(stream);
+        if !fdflags.is_empty() {
+            fd::fdstat_set_flags(&*socket.as_file()?, fdflags)?;
+        }
+        Ok(Box::new(socket))
+    }
+    fn sock_recv(
+        &self,
+        ri_data: &mut [io::IoSliceMut],
+        ri_flags: types::Riflags,
+    ) -> Result<(usize, types::Roflags)> {
+        let mut flags = MsgFlags::empty();
+        if ri_flags.contains(&types::Riflags::RECV_PEEK) {
+            flags.insert(MsgFlags::PEEK);
+        }
+        if ri_flags.contains(&types::Riflags::RECV_WAITALL) {
+            flags.insert(MsgFlags::WAITALL);
+        }
+        let (nread, msg_flags) = unsafe { recvmsg(self.stream()?.as_raw_fd(), ri_data, flags)? };
+        let mut ro_flags = types::Roflags::empty();
+        if msg_flags.contains(MsgFlags::TRUNC) {
+            ro_flags |= types::Roflags::RECV_DATA_TRUNCATED;
+        }
+        Ok((nread, ro_flags))
+    }
+    fn sock_send(&self, si_data: &[io::IoSlice], _si_flags: types::Siflags) -> Result<usize> {
+        let nwritten = self.stream()?.write_vectored(si_data)?;
+        Ok(nwritten)
+    }
+    fn sock_shutdown(&self, how: types::Sdflags) -> Result<()> {
+        let how = if how == types::Sdflags::RD | types::Sdflags::WR {
+            Shutdown::Both
+        } else if how == types::Sdflags::RD {
+            Shutdown::Read
+        } else if how == types::Sdflags::WR {
+            Shutdown::Write
+        } else {
+            return Err(Errno::Inval);
+        };
+        self.stream()?.shutdown(how)?;
+        Ok(())
+    }
+}
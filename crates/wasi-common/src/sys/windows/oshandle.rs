@@ -8,6 +8,9 @@ use std::os::windows::prelude::{AsRawHandle, FromRawHandle, IntoRawHandle, RawHa
 #[derive(Debug)]
 pub struct RawOsHandle(Cell<RawHandle>);
 
+// The handle is owned, just like the one in a `std::fs::File`.
+unsafe impl Send for RawOsHandle {}
+
 impl RawOsHandle {
     /// Tries cloning `self`.
     pub(crate) fn try_clone(&self) -> io::Result<Self> {
@@ -2,14 +2,15 @@ use crate::handle::{Handle, HandleRights};
 use crate::wasi::{self, types, Errno, Result, RightsExt};
 use log::trace;
 use std::any::Any;
-use std::cell::{Cell, RefCell};
+use std::cell::Cell;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io;
 use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// An entry in a virtual filesystem
 pub enum VirtualDirEntry {
@@ -33,7 +34,7 @@ pub(crate) trait MovableFile {
     fn set_parent(&self, new_parent: Option<Box<dyn Handle>>);
 }
 
-pub trait FileContents {
+pub trait FileContents: Send {
     /// The implementation-defined maximum size of the store corresponding to a `FileContents`
     /// implementation.
     fn max_size(&self) -> types::Filesize;
@@ -69,6 +70,7 @@ impl FileContents for VecFileContents {
 
     fn resize(&mut self, new_size: types::Filesize) -> Result<()> {
         let new_size: usize = new_size.try_into().map_err(|_| Errno::Inval)?;
+        self.reserve(new_size)?;
         self.content.resize(new_size, 0);
         Ok(())
     }
@@ -76,8 +78,11 @@ impl FileContents for VecFileContents {
     fn preadv(&self, iovs: &mut [io::IoSliceMut], offset: types::Filesize) -> Result<usize> {
         let mut read_total = 0usize;
         for iov in iovs.iter_mut() {
-            let read = self.pread(iov, offset)?;
+            let read = self.pread(iov, offset + read_total as types::Filesize)?;
             read_total = read_total.checked_add(read).expect("FileContents::preadv must not be called when reads could total to more bytes than the return value can hold");
+            if read < iov.len() {
+                break;
+            }
         }
         Ok(read_total)
     }
@@ -85,7 +90,7 @@ impl FileContents for VecFileContents {
     fn pwritev(&mut self, iovs: &[io::IoSlice], offset: types::Filesize) -> Result<usize> {
         let mut write_total = 0usize;
         for iov in iovs.iter() {
-            let written = self.pwrite(iov, offset)?;
+            let written = self.pwrite(iov, offset + write_total as types::Filesize)?;
             write_total = write_total.checked_add(written).expect("FileContents::pwritev must not be called when writes could total to more bytes than the return value can hold");
         }
         Ok(write_total)
@@ -112,6 +117,7 @@ impl FileContents for VecFileContents {
         let write_end = offset.checked_add(buf.len()).ok_or(Errno::Fbig)?;
 
         if write_end > self.content.len() {
+            self.reserve(write_end)?;
             self.content.resize(write_end, 0);
         }
 
@@ -123,12 +129,65 @@ impl FileContents for VecFileContents {
 
 struct VecFileContents {
     content: Vec<u8>,
+    size_limit: Option<Arc<SizeLimit>>,
 }
 
 impl VecFileContents {
-    fn new() -> Self {
+    fn new(size_limit: Option<Arc<SizeLimit>>) -> Self {
         Self {
             content: Vec::new(),
+            size_limit,
+        }
+    }
+
+    /// Reserves the bytes needed to resize the contents to `new_size` from the size limit, if
+    /// there's one, or releases them if the contents shrink.
+    fn reserve(&self, new_size: usize) -> Result<()> {
+        match &self.size_limit {
+            Some(limit) => limit.resize(self.content.len() as u64, new_size as u64),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for VecFileContents {
+    fn drop(&mut self) {
+        if let Some(limit) = &self.size_limit {
+            limit
+                .resize(self.content.len() as u64, 0)
+                .expect("shrinking never exceeds the limit");
+        }
+    }
+}
+
+/// The maximum total size of the files of a `VirtualDir`, shared by all of its files and
+/// subdirectories.
+struct SizeLimit {
+    max_size: u64,
+    used: AtomicU64,
+}
+
+impl SizeLimit {
+    /// Accounts for a file being resized from `old_size` to `new_size` bytes, failing with
+    /// `Errno::Nospc` if that would exceed the limit.
+    fn resize(&self, old_size: u64, new_size: u64) -> Result<()> {
+        let mut used = self.used.load(Ordering::SeqCst);
+        loop {
+            let new_used = (used - old_size)
+                .checked_add(new_size)
+                .ok_or(Errno::Nospc)?;
+            if new_size > old_size && new_used > self.max_size {
+                return Err(Errno::Nospc);
+            }
+            match self.used.compare_exchange_weak(
+                used,
+                new_used,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(()),
+                Err(current) => used = current,
+            }
         }
     }
 }
@@ -139,14 +198,14 @@ impl VecFileContents {
 pub struct InMemoryFile {
     rights: Cell<HandleRights>,
     cursor: Cell<types::Filesize>,
-    parent: Rc<RefCell<Option<Box<dyn Handle>>>>,
+    parent: Arc<Mutex<Option<Box<dyn Handle>>>>,
     fd_flags: Cell<types::Fdflags>,
-    data: Rc<RefCell<Box<dyn FileContents>>>,
+    data: Arc<Mutex<Box<dyn FileContents>>>,
 }
 
 impl InMemoryFile {
     pub fn memory_backed() -> Self {
-        Self::new(Box::new(VecFileContents::new()))
+        Self::new(Box::new(VecFileContents::new(None)))
     }
 
     pub fn new(contents: Box<dyn FileContents>) -> Self {
@@ -159,15 +218,15 @@ impl InMemoryFile {
             rights,
             cursor: Cell::new(0),
             fd_flags: Cell::new(types::Fdflags::empty()),
-            parent: Rc::new(RefCell::new(None)),
-            data: Rc::new(RefCell::new(contents)),
+            parent: Arc::new(Mutex::new(None)),
+            data: Arc::new(Mutex::new(contents)),
         }
     }
 }
 
 impl MovableFile for InMemoryFile {
     fn set_parent(&self, new_parent: Option<Box<dyn Handle>>) {
-        *self.parent.borrow_mut() = new_parent;
+        *self.parent.lock().unwrap() = new_parent;
     }
 }
 
@@ -180,8 +239,8 @@ impl Handle for InMemoryFile {
             rights: self.rights.clone(),
             cursor: Cell::new(0),
             fd_flags: self.fd_flags.clone(),
-            parent: Rc::clone(&self.parent),
-            data: Rc::clone(&self.data),
+            parent: Arc::clone(&self.parent),
+            data: Arc::clone(&self.data),
         }))
     }
     fn get_file_type(&self) -> types::Filetype {
@@ -205,7 +264,7 @@ impl Handle for InMemoryFile {
     }
     fn allocate(&self, offset: types::Filesize, len: types::Filesize) -> Result<()> {
         let new_limit = offset.checked_add(len).ok_or(Errno::Fbig)?;
-        let mut data = self.data.borrow_mut();
+        let mut data = self.data.lock().unwrap();
 
         if new_limit > data.max_size() {
             return Err(Errno::Fbig);
@@ -229,7 +288,7 @@ impl Handle for InMemoryFile {
             dev: 0,
             ino: 0,
             nlink: 0,
-            size: self.data.borrow().size(),
+            size: self.data.lock().unwrap().size(),
             atim: 0,
             ctim: 0,
             mtim: 0,
@@ -238,25 +297,27 @@ impl Handle for InMemoryFile {
         Ok(stat)
     }
     fn filestat_set_size(&self, st_size: types::Filesize) -> Result<()> {
-        let mut data = self.data.borrow_mut();
+        let mut data = self.data.lock().unwrap();
         if st_size > data.max_size() {
             return Err(Errno::Fbig);
         }
         data.resize(st_size)
     }
     fn preadv(&self, buf: &mut [io::IoSliceMut], offset: types::Filesize) -> Result<usize> {
-        self.data.borrow_mut().preadv(buf, offset)
+        self.data.lock().unwrap().preadv(buf, offset)
     }
     fn pwritev(&self, buf: &[io::IoSlice], offset: types::Filesize) -> Result<usize> {
-        self.data.borrow_mut().pwritev(buf, offset)
+        self.data.lock().unwrap().pwritev(buf, offset)
     }
     fn read_vectored(&self, iovs: &mut [io::IoSliceMut]) -> Result<usize> {
         trace!("read_vectored(iovs={:?})", iovs);
         trace!("     | *read_start={:?}", self.cursor.get());
-        self.data.borrow_mut().preadv(iovs, self.cursor.get())
+        let read = self.data.lock().unwrap().preadv(iovs, self.cursor.get())?;
+        self.cursor.set(self.cursor.get() + read as u64);
+        Ok(read)
     }
     fn seek(&self, offset: SeekFrom) -> Result<types::Filesize> {
-        let content_len = self.data.borrow().size();
+        let content_len = self.data.lock().unwrap().size();
         match offset {
             SeekFrom::Current(offset) => {
                 let new_cursor = if offset < 0 {
@@ -288,7 +349,7 @@ impl Handle for InMemoryFile {
     }
     fn write_vectored(&self, iovs: &[io::IoSlice]) -> Result<usize> {
         trace!("write_vectored(iovs={:?})", iovs);
-        let mut data = self.data.borrow_mut();
+        let mut data = self.data.lock().unwrap();
 
         let append_mode = self.fd_flags.get().contains(&types::Fdflags::APPEND);
         trace!("     | fd_flags={}", self.fd_flags.get());
@@ -365,7 +426,7 @@ impl Handle for InMemoryFile {
         if path == "." {
             return self.try_clone().map_err(Into::into);
         } else if path == ".." {
-            match &*self.parent.borrow() {
+            match &*self.parent.lock().unwrap() {
                 Some(file) => file.try_clone().map_err(Into::into),
                 None => self.try_clone().map_err(Into::into),
             }
@@ -407,9 +468,12 @@ pub struct VirtualDir {
     rights: Cell<HandleRights>,
     writable: bool,
     // All copies of this `VirtualDir` must share `parent`, and changes in one copy's `parent`
-    // must be reflected in all handles, so they share `Rc` of an underlying `parent`.
-    parent: Rc<RefCell<Option<Box<dyn Handle>>>>,
-    entries: Rc<RefCell<HashMap<PathBuf, Box<dyn Handle>>>>,
+    // must be reflected in all handles, so they share `Arc` of an underlying `parent`.
+    parent: Arc<Mutex<Option<Box<dyn Handle>>>>,
+    entries: Arc<Mutex<HashMap<PathBuf, Box<dyn Handle>>>>,
+    // The limit on the total size of the files created in this directory and its subdirectories,
+    // if any.
+    size_limit: Option<Arc<SizeLimit>>,
 }
 
 impl VirtualDir {
@@ -422,8 +486,35 @@ impl VirtualDir {
         Self {
             rights,
             writable,
-            parent: Rc::new(RefCell::new(None)),
-            entries: Rc::new(RefCell::new(HashMap::new())),
+            parent: Arc::new(Mutex::new(None)),
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            size_limit: None,
+        }
+    }
+
+    /// Create a writable directory in which the files created, including in its subdirectories,
+    /// can't total more than `max_size` bytes.
+    pub fn with_size_limit(max_size: types::Filesize) -> Self {
+        Self {
+            size_limit: Some(Arc::new(SizeLimit {
+                max_size,
+                used: AtomicU64::new(0),
+            })),
+            ..Self::new(true)
+        }
+    }
+
+    /// Returns whether this directory is `ancestor`, or one of its subdirectories.
+    fn is_within(&self, ancestor: &Self) -> bool {
+        if Arc::ptr_eq(&self.entries, &ancestor.entries) {
+            return true;
+        }
+        match &*self.parent.lock().unwrap() {
+            Some(parent) => parent
+                .as_any()
+                .downcast_ref::<Self>()
+                .map_or(false, |parent| parent.is_within(ancestor)),
+            None => false,
         }
     }
 
@@ -438,7 +529,8 @@ impl VirtualDir {
         let entry = Box::new(dir);
         entry.set_parent(Some(self.try_clone().expect("can clone self")));
         self.entries
-            .borrow_mut()
+            .lock()
+            .unwrap()
             .insert(path.as_ref().to_owned(), entry);
     }
 
@@ -453,14 +545,15 @@ impl VirtualDir {
         let entry = Box::new(InMemoryFile::new(content));
         entry.set_parent(Some(self.try_clone().expect("can clone self")));
         self.entries
-            .borrow_mut()
+            .lock()
+            .unwrap()
             .insert(path.as_ref().to_owned(), entry);
     }
 }
 
 impl MovableFile for VirtualDir {
     fn set_parent(&self, new_parent: Option<Box<dyn Handle>>) {
-        *self.parent.borrow_mut() = new_parent;
+        *self.parent.lock().unwrap() = new_parent;
     }
 }
 
@@ -479,8 +572,9 @@ impl Handle for VirtualDir {
         Ok(Box::new(Self {
             rights: self.rights.clone(),
             writable: self.writable,
-            parent: Rc::clone(&self.parent),
-            entries: Rc::clone(&self.entries),
+            parent: Arc::clone(&self.parent),
+            entries: Arc::clone(&self.entries),
+            size_limit: self.size_limit.clone(),
         }))
     }
     fn get_file_type(&self) -> types::Filetype {
@@ -512,7 +606,7 @@ impl Handle for VirtualDir {
     ) -> Result<Box<dyn Iterator<Item = Result<(types::Dirent, String)>>>> {
         struct VirtualDirIter {
             start: u32,
-            entries: Rc<RefCell<HashMap<PathBuf, Box<dyn Handle>>>>,
+            entries: Arc<Mutex<HashMap<PathBuf, Box<dyn Handle>>>>,
         }
         impl Iterator for VirtualDirIter {
             type Item = Result<(types::Dirent, String)>;
@@ -542,7 +636,7 @@ impl Handle for VirtualDir {
                     return Some(Ok((dirent, name)));
                 }
 
-                let entries = self.entries.borrow();
+                let entries = self.entries.lock().unwrap();
 
                 // Adjust `start` to be an appropriate number of HashMap entries.
                 let start = self.start - RESERVED_ENTRY_COUNT;
@@ -584,17 +678,20 @@ impl Handle for VirtualDir {
         };
         Ok(Box::new(VirtualDirIter {
             start: cookie,
-            entries: Rc::clone(&self.entries),
+            entries: Arc::clone(&self.entries),
         }))
     }
     // PathOps
     fn create_directory(&self, path: &str) -> Result<()> {
-        let mut entries = self.entries.borrow_mut();
+        let mut entries = self.entries.lock().unwrap();
         match entries.entry(PathBuf::from(path)) {
             Entry::Occupied(_) => Err(Errno::Exist),
             Entry::Vacant(v) => {
                 if self.writable {
-                    let new_dir = Box::new(Self::new(true));
+                    let new_dir = Box::new(Self {
+                        size_limit: self.size_limit.clone(),
+                        ..Self::new(true)
+                    });
                     new_dir.set_parent(Some(self.try_clone()?));
                     v.insert(new_dir);
                     Ok(())
@@ -654,7 +751,7 @@ impl Handle for VirtualDir {
         if path == "." {
             return self.try_clone().map_err(Into::into);
         } else if path == ".." {
-            match &*self.parent.borrow() {
+            match &*self.parent.lock().unwrap() {
                 Some(file) => {
                     return file.try_clone().map_err(Into::into);
                 }
@@ -667,7 +764,7 @@ impl Handle for VirtualDir {
         // openat may have been passed a path with a trailing slash, but files are mapped to paths
         // with trailing slashes normalized out.
         let file_name = Path::new(path).file_name().ok_or(Errno::Inval)?;
-        let mut entries = self.entries.borrow_mut();
+        let mut entries = self.entries.lock().unwrap();
         let entry_count = entries.len();
         match entries.entry(Path::new(file_name).to_path_buf()) {
             Entry::Occupied(e) => {
@@ -689,7 +786,16 @@ impl Handle for VirtualDir {
                     return Err(Errno::Notdir);
                 }
 
-                e.get().try_clone().map_err(Into::into)
+                let file = e.get().try_clone()?;
+                if oflags.contains(&types::Oflags::TRUNC)
+                    && file.get_file_type() == types::Filetype::RegularFile
+                {
+                    if !self.writable {
+                        return Err(Errno::Acces);
+                    }
+                    file.filestat_set_size(0)?;
+                }
+                Ok(file)
             }
             Entry::Vacant(v) => {
                 if self.writable {
@@ -703,7 +809,8 @@ impl Handle for VirtualDir {
 
                     log::trace!("VirtualDir::openat creating an InMemoryFile named {}", path);
 
-                    let file = Box::new(InMemoryFile::memory_backed());
+                    let contents = VecFileContents::new(self.size_limit.clone());
+                    let file = Box::new(InMemoryFile::new(Box::new(contents)));
                     file.fd_flags.set(fd_flags);
                     file.set_parent(Some(self.try_clone().expect("can clone self")));
                     v.insert(file).try_clone().map_err(Into::into)
@@ -717,9 +824,100 @@ impl Handle for VirtualDir {
         // Files are not symbolic links or directories, faithfully report Notdir.
         Err(Errno::Notdir)
     }
+    fn rename(&self, old_path: &str, new_handle: Box<dyn Handle>, new_path: &str) -> Result<()> {
+        let new_dir = match new_handle.as_any().downcast_ref::<Self>() {
+            Some(dir) => dir,
+            // Files can't be moved between virtual and OS-backed filesystems.
+            None => return Err(Errno::Xdev),
+        };
+        if !self.writable || !new_dir.writable {
+            return Err(Errno::Acces);
+        }
+        // Directories with different size limits are separate mounts, and files moved between
+        // them would stay accounted against the limit of the one they were created in.
+        let same_limit = match (&self.size_limit, &new_dir.size_limit) {
+            (Some(old_limit), Some(new_limit)) => Arc::ptr_eq(old_limit, new_limit),
+            (None, None) => true,
+            _ => false,
+        };
+        if !same_limit {
+            return Err(Errno::Xdev);
+        }
+        let old_name = PathBuf::from(old_path.trim_end_matches('/'));
+        let new_name = PathBuf::from(new_path.trim_end_matches('/'));
+        for name in &[&old_name, &new_name] {
+            if name.as_os_str() == "." || name.as_os_str() == ".." {
+                return Err(Errno::Busy);
+            }
+        }
+
+        // Both directories may be the same, in which case their entries must only be locked once.
+        // Otherwise they're locked in the order of their addresses, so that concurrent renames
+        // in opposite directions can't deadlock.
+        let same_dir = Arc::ptr_eq(&self.entries, &new_dir.entries);
+        let (mut old_entries, mut new_entries_guard) = if same_dir {
+            (self.entries.lock().unwrap(), None)
+        } else if (&*self.entries as *const Mutex<_>) < (&*new_dir.entries as *const Mutex<_>) {
+            let old_entries = self.entries.lock().unwrap();
+            (old_entries, Some(new_dir.entries.lock().unwrap()))
+        } else {
+            let new_entries = new_dir.entries.lock().unwrap();
+            (self.entries.lock().unwrap(), Some(new_entries))
+        };
+
+        let file_type = match old_entries.get(&old_name) {
+            Some(file) => {
+                // A directory can't be moved into itself or one of its subdirectories.
+                if let Some(dir) = file.as_any().downcast_ref::<Self>() {
+                    if new_dir.is_within(dir) {
+                        return Err(Errno::Inval);
+                    }
+                }
+                file.get_file_type()
+            }
+            None => return Err(Errno::Noent),
+        };
+        {
+            let new_entries = new_entries_guard.as_deref().unwrap_or(&*old_entries);
+            if let Some(existing) = new_entries.get(&new_name) {
+                if same_dir && old_name == new_name {
+                    return Ok(());
+                }
+                match (file_type, existing.get_file_type()) {
+                    (types::Filetype::Directory, types::Filetype::Directory) => {
+                        // Replacing this directory's own ancestor, which can't be empty.
+                        if self.is_within(existing.as_any().downcast_ref::<Self>().unwrap()) {
+                            return Err(Errno::Notempty);
+                        }
+                        let iter = existing.readdir(wasi::DIRCOOKIE_START)?;
+                        if iter.skip(RESERVED_ENTRY_COUNT as usize).next().is_some() {
+                            return Err(Errno::Notempty);
+                        }
+                    }
+                    (types::Filetype::Directory, _) => return Err(Errno::Notdir),
+                    (_, types::Filetype::Directory) => return Err(Errno::Isdir),
+                    _ => {}
+                }
+            }
+        }
+
+        let file = old_entries.remove(&old_name).unwrap();
+        set_parent(&*file, Some(new_dir.try_clone()?));
+        let new_entries = match &mut new_entries_guard {
+            Some(new_entries) => &mut **new_entries,
+            None => &mut *old_entries,
+        };
+        if let Some(replaced) = new_entries.insert(new_name, file) {
+            set_parent(&*replaced, None);
+        }
+        Ok(())
+    }
     fn remove_directory(&self, path: &str) -> Result<()> {
+        if !self.writable {
+            return Err(Errno::Acces);
+        }
         let trimmed_path = path.trim_end_matches('/');
-        let mut entries = self.entries.borrow_mut();
+        let mut entries = self.entries.lock().unwrap();
         match entries.entry(Path::new(trimmed_path).to_path_buf()) {
             Entry::Occupied(e) => {
                 // first, does this name a directory?
@@ -736,15 +934,8 @@ impl Handle for VirtualDir {
                 // Alright, it's an empty directory. We can remove it.
                 let removed = e.remove_entry();
 
-                // TODO refactor
-                // And sever the file's parent ref to avoid Rc cycles.
-                if let Some(dir) = removed.1.as_any().downcast_ref::<Self>() {
-                    dir.set_parent(None);
-                } else if let Some(file) = removed.1.as_any().downcast_ref::<InMemoryFile>() {
-                    file.set_parent(None);
-                } else {
-                    panic!("neither VirtualDir nor InMemoryFile");
-                }
+                // And sever the file's parent ref to avoid Arc cycles.
+                set_parent(&*removed.1, None);
 
                 Ok(())
             }
@@ -766,8 +957,11 @@ impl Handle for VirtualDir {
         if trimmed_path == "." || trimmed_path == ".." {
             return Err(Errno::Isdir);
         }
+        if !self.writable {
+            return Err(Errno::Acces);
+        }
 
-        let mut entries = self.entries.borrow_mut();
+        let mut entries = self.entries.lock().unwrap();
         match entries.entry(Path::new(trimmed_path).to_path_buf()) {
             Entry::Occupied(e) => {
                 // Directories must be removed through `remove_directory`, not `unlink_file`.
@@ -777,15 +971,8 @@ impl Handle for VirtualDir {
 
                 let removed = e.remove_entry();
 
-                // TODO refactor
-                // Sever the file's parent ref to avoid Rc cycles.
-                if let Some(dir) = removed.1.as_any().downcast_ref::<Self>() {
-                    dir.set_parent(None);
-                } else if let Some(file) = removed.1.as_any().downcast_ref::<InMemoryFile>() {
-                    file.set_parent(None);
-                } else {
-                    panic!("neither VirtualDir nor InMemoryFile");
-                }
+                // Sever the file's parent ref to avoid Arc cycles.
+                set_parent(&*removed.1, None);
 
                 Ok(())
             }
@@ -799,3 +986,14 @@ impl Handle for VirtualDir {
         }
     }
 }
+
+/// Sets the parent of `handle`, a `VirtualDir` or an `InMemoryFile`.
+fn set_parent(handle: &dyn Handle, parent: Option<Box<dyn Handle>>) {
+    if let Some(dir) = handle.as_any().downcast_ref::<VirtualDir>() {
+        dir.set_parent(parent);
+    } else if let Some(file) = handle.as_any().downcast_ref::<InMemoryFile>() {
+        file.set_parent(parent);
+    } else {
+        panic!("neither VirtualDir nor InMemoryFile");
+    }
+}
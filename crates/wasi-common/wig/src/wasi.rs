@@ -215,7 +215,7 @@ pub fn define_struct(args: TokenStream) -> TokenStream {
                                 }
                             };
                             hostcalls::#name_ident(
-                                &mut my_cx.borrow_mut(),
+                                &mut my_cx.lock().unwrap(),
                                 memory.data_unchecked_mut(),
                                 #(#hostcall_args),*
                             ) #cvt_ret
@@ -245,7 +245,7 @@ pub fn define_struct(args: TokenStream) -> TokenStream {
             /// configuration of the wasi instance itself should be all
             /// contained in the `cx` parameter.
             pub fn new(store: &wasmtime::Store, cx: WasiCtx) -> Wasi {
-                let cx = std::rc::Rc::new(std::cell::RefCell::new(cx));
+                let cx = std::sync::Arc::new(std::sync::Mutex::new(cx));
                 #(#ctor_externs)*
 
                 Wasi {
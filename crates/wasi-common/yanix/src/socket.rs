@@ -1,5 +1,8 @@
-use crate::from_success_code;
-use std::io::Result;
+use crate::{from_result, from_success_code};
+use bitflags::bitflags;
+use std::convert::TryInto;
+use std::io::{Error, IoSliceMut, Result};
+use std::mem;
 use std::os::unix::prelude::*;
 
 #[derive(Debug, Clone, Copy)]
@@ -13,7 +16,7 @@ pub enum SockType {
 }
 
 pub unsafe fn get_socket_type(fd: RawFd) -> Result<SockType> {
-    use std::mem::{self, MaybeUninit};
+    use std::mem::MaybeUninit;
     let mut buffer = MaybeUninit::<SockType>::zeroed().assume_init();
     let mut out_len = mem::size_of::<SockType>() as libc::socklen_t;
     from_success_code(libc::getsockopt(
@@ -30,3 +33,29 @@ pub unsafe fn get_socket_type(fd: RawFd) -> Result<SockType> {
     );
     Ok(buffer)
 }
+
+bitflags! {
+    pub struct MsgFlags: libc::c_int {
+        const PEEK = libc::MSG_PEEK;
+        const TRUNC = libc::MSG_TRUNC;
+        const WAITALL = libc::MSG_WAITALL;
+    }
+}
+
+/// Receives a message from the socket `fd` into `iovs`, returning the number of
+/// bytes received along with the flags describing the received message.
+pub unsafe fn recvmsg(
+    fd: RawFd,
+    iovs: &mut [IoSliceMut],
+    flags: MsgFlags,
+) -> Result<(usize, MsgFlags)> {
+    let mut msg: libc::msghdr = mem::zeroed();
+    // `IoSliceMut` is guaranteed to be ABI compatible with `iovec` on Unix.
+    msg.msg_iov = iovs.as_mut_ptr() as *mut libc::iovec;
+    msg.msg_iovlen = iovs
+        .len()
+        .try_into()
+        .map_err(|_| Error::from_raw_os_error(libc::EINVAL))?;
+    let nread = from_result(libc::recvmsg(fd, &mut msg, flags.bits()))?;
+    Ok((nread as usize, MsgFlags::from_bits_truncate(msg.msg_flags)))
+}
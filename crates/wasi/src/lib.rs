@@ -1,4 +1,7 @@
-use wasmtime::Trap;
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+use wasi_common::wasi::{types, Errno};
+use wasmtime::{Caller, Extern, Func, Linker, Trap};
 
 pub mod old;
 
@@ -55,3 +58,45 @@ fn wasi_proc_exit(status: i32) -> Result<(), Trap> {
         ))
     }
 }
+
+/// Defines `sock_accept` in the `wasi_snapshot_preview1` module of `linker`.
+///
+/// The witx snapshot [`Wasi`] is generated from doesn't define `sock_accept`,
+/// so it's provided separately here to let guests accept connections on the
+/// listening sockets preopened in `cx`. The same `cx` should be passed to
+/// [`Wasi::new_shared`] so both operate on the same descriptors.
+pub fn add_sock_accept_to_linker(
+    linker: &mut Linker,
+    cx: Arc<Mutex<WasiCtx>>,
+) -> anyhow::Result<()> {
+    let func = Func::wrap(
+        linker.store(),
+        move |caller: Caller<'_>, fd: i32, flags: i32, ro_fd: i32| -> i32 {
+            let mem = match caller.get_export("memory") {
+                Some(Extern::Memory(m)) => m,
+                _ => {
+                    log::warn!("callee does not export a memory as \"memory\"");
+                    return Errno::Inval.into();
+                }
+            };
+            let result = types::Fdflags::try_from(flags)
+                .map_err(|_| Errno::Inval)
+                .and_then(|flags| cx.lock().unwrap().sock_accept(types::Fd::from(fd), flags));
+            let new_fd = match result {
+                Ok(new_fd) => u32::from(new_fd),
+                Err(e) => return e.into(),
+            };
+            let start = ro_fd as u32 as usize;
+            // Safe since the guest can't run while we hold this borrow, and
+            // the slice is bounds-checked against the memory's current size.
+            let data = unsafe { mem.data_unchecked_mut() };
+            match data.get_mut(start..).and_then(|s| s.get_mut(..4)) {
+                Some(slot) => slot.copy_from_slice(&new_fd.to_le_bytes()),
+                None => return Errno::Fault.into(),
+            }
+            Errno::Success.into()
+        },
+    );
+    linker.define("wasi_snapshot_preview1", "sock_accept", func)?;
+    Ok(())
+}
@@ -1,15 +1,16 @@
 use crate::trampoline::{
-    generate_global_export, generate_memory_export, generate_table_export, StoreInstanceHandle,
+    generate_global_export, generate_memory_export, generate_shared_memory_export,
+    generate_table_export, StoreInstanceHandle,
 };
 use crate::values::{from_checked_anyfunc, into_checked_anyfunc, Val};
 use crate::{
-    ExternRef, ExternType, Func, GlobalType, MemoryType, Mutability, Store, TableType, Trap,
-    ValType,
+    Engine, ExternRef, ExternType, Func, GlobalType, MemoryType, Mutability, Store, TableType,
+    Trap, ValType,
 };
 use anyhow::{anyhow, bail, Result};
 use std::slice;
-use wasmtime_environ::wasm;
-use wasmtime_runtime::{self as runtime, InstanceHandle};
+use wasmtime_environ::{wasm, MemoryPlan};
+use wasmtime_runtime::{self as runtime, InstanceHandle, RuntimeLinearMemory};
 
 // Externals
 
@@ -169,6 +170,9 @@ pub struct Global {
     wasmtime_export: wasmtime_runtime::ExportGlobal,
 }
 
+// The global's definition is only accessed while its store is entered.
+unsafe impl Send for Global {}
+
 impl Global {
     /// Creates a new WebAssembly `global` value with the provide type `ty` and
     /// initial value `val`.
@@ -220,6 +224,7 @@ impl Global {
 
     /// Returns the current [`Val`] of this global.
     pub fn get(&self) -> Val {
+        let _entered = self.instance.store.enter();
         unsafe {
             let definition = &mut *self.wasmtime_export.definition;
             match self.val_type() {
@@ -249,6 +254,7 @@ impl Global {
         if !val.comes_from_same_store(&self.instance.store) {
             bail!("cross-`Store` values are not supported");
         }
+        let _entered = self.instance.store.enter();
         unsafe {
             let definition = &mut *self.wasmtime_export.definition;
             match val {
@@ -280,8 +286,9 @@ impl Global {
 /// common usages of a table is a function table for wasm modules, where each
 /// element has the `Func` type.
 ///
-/// Tables, like globals, are not threadsafe and can only be used on one thread.
-/// Tables can be grown in size and each element can be read/written.
+/// Tables, like globals, can only be used by one thread at a time, along with
+/// the rest of their [`Store`]. Tables can be grown in size and each element
+/// can be read/written.
 ///
 /// # `Table` and `Clone`
 ///
@@ -294,6 +301,9 @@ pub struct Table {
     wasmtime_export: wasmtime_runtime::ExportTable,
 }
 
+// The table's definition is only accessed while its store is entered.
+unsafe impl Send for Table {}
+
 fn set_table_item(
     instance: &InstanceHandle,
     table_index: wasm::DefinedTableIndex,
@@ -348,6 +358,7 @@ impl Table {
     ///
     /// Returns `None` if `index` is out of bounds.
     pub fn get(&self, index: u32) -> Option<Val> {
+        let _entered = self.instance.store.enter();
         let table_index = self.wasmtime_table_index();
         let item = self.instance.table_get(table_index, index)?;
         match item {
@@ -369,6 +380,7 @@ impl Table {
     /// Returns an error if `index` is out of bounds or if `val` does not have
     /// the right type to be stored in this table.
     pub fn set(&self, index: u32, val: Val) -> Result<()> {
+        let _entered = self.instance.store.enter();
         let table_index = self.wasmtime_table_index();
         let item = into_checked_anyfunc(val, &self.instance.store)?;
         set_table_item(&self.instance, table_index, index, item)
@@ -376,6 +388,7 @@ impl Table {
 
     /// Returns the current size of this table.
     pub fn size(&self) -> u32 {
+        let _entered = self.instance.store.enter();
         unsafe { (*self.wasmtime_export.definition).current_elements }
     }
 
@@ -387,9 +400,11 @@ impl Table {
     /// # Errors
     ///
     /// Returns an error if the table cannot be grown by `delta`, for example
-    /// if it would cause the table to exceed its maximum size. Also returns an
-    /// error if `init` is not of the right type.
+    /// if it would cause the table to exceed its maximum size or the store's
+    /// [`ResourceLimiter`](crate::ResourceLimiter) denies the growth. Also
+    /// returns an error if `init` is not of the right type.
     pub fn grow(&self, delta: u32, init: Val) -> Result<u32> {
+        let _entered = self.instance.store.enter();
         let index = self.wasmtime_table_index();
         let orig_size = match self.ty().element() {
             ValType::FuncRef => {
@@ -410,10 +425,10 @@ impl Table {
             }
             _ => unreachable!("only `funcref` and `externref` tables are supported"),
         };
-        if let Some(size) = orig_size {
-            Ok(size)
-        } else {
-            bail!("failed to grow table by `{}`", delta)
+        match orig_size {
+            Ok(Some(size)) => Ok(size),
+            Ok(None) => bail!("failed to grow table by `{}`", delta),
+            Err(trap) => Err(Trap::from_runtime(trap).into()),
         }
     }
 
@@ -434,6 +449,7 @@ impl Table {
         if !Store::same(&dst_table.instance.store, &src_table.instance.store) {
             bail!("cross-`Store` table copies are not supported");
         }
+        let _entered = dst_table.instance.store.enter();
 
         // NB: We must use the `dst_table`'s `wasmtime_handle` for the
         // `dst_table_index` and vice versa for `src_table` since each table can
@@ -478,9 +494,16 @@ impl Table {
 ///
 /// # `Memory` and threads
 ///
-/// It is intended that `Memory` is safe to share between threads. At this time
-/// this is not implemented in `wasmtime`, however. This is planned to be
-/// implemented though!
+/// A `Memory` can be sent to and shared with other threads, but like the rest
+/// of its [`Store`] it can only be used by one thread at a time. Note that
+/// this only covers calls to methods on `Memory` itself: slices returned by
+/// [`Memory::data_unchecked`] and pointers returned by [`Memory::data_ptr`]
+/// aren't tracked, and it's up to you to not use them while another thread
+/// is using the store.
+///
+/// Shared memories, which instances on different threads can access at the
+/// same time as in the wasm threads proposal, are used from other threads
+/// through a [`SharedMemory`] handle instead.
 ///
 /// # `Memory` and Safety
 ///
@@ -639,9 +662,10 @@ impl Table {
 ///
 /// ## `Memory` Safety and Threads
 ///
-/// Currently the `wasmtime` crate does not implement the wasm threads proposal,
-/// but it is planned to do so. It's additionally worthwhile discussing how this
-/// affects memory safety and what was previously just discussed as well.
+/// Shared memories of the wasm threads proposal can be accessed by instances
+/// on several threads at once, through [`SharedMemory`]. It's worthwhile
+/// discussing how this affects memory safety and what was previously just
+/// discussed as well.
 ///
 /// Once threads are added into the mix, all of the above rules still apply.
 /// There's an additional, rule, however, that all reads and writes can
@@ -673,6 +697,9 @@ pub struct Memory {
     wasmtime_export: wasmtime_runtime::ExportMemory,
 }
 
+// The memory's definition is only accessed while its store is entered.
+unsafe impl Send for Memory {}
+
 impl Memory {
     /// Creates a new WebAssembly memory given the configuration of `ty`.
     ///
@@ -774,6 +801,7 @@ impl Memory {
     /// For more information and examples see the documentation on the
     /// [`Memory`] type.
     pub unsafe fn data_unchecked_mut(&self) -> &mut [u8] {
+        let _entered = self.instance.store.enter();
         let definition = &*self.wasmtime_export.definition;
         slice::from_raw_parts_mut(definition.base, definition.current_length)
     }
@@ -788,6 +816,7 @@ impl Memory {
     /// For more information and examples see the documentation on the
     /// [`Memory`] type.
     pub fn data_ptr(&self) -> *mut u8 {
+        let _entered = self.instance.store.enter();
         unsafe { (*self.wasmtime_export.definition).base }
     }
 
@@ -798,6 +827,7 @@ impl Memory {
     /// For more information and examples see the documentation on the
     /// [`Memory`] type.
     pub fn data_size(&self) -> usize {
+        let _entered = self.instance.store.enter();
         unsafe { (*self.wasmtime_export.definition).current_length }
     }
 
@@ -819,7 +849,8 @@ impl Memory {
     /// # Errors
     ///
     /// Returns an error if memory could not be grown, for example if it exceeds
-    /// the maximum limits of this memory.
+    /// the maximum limits of this memory or the store's
+    /// [`ResourceLimiter`](crate::ResourceLimiter) denies the growth.
     ///
     /// # Examples
     ///
@@ -842,12 +873,71 @@ impl Memory {
     /// # }
     /// ```
     pub fn grow(&self, delta: u32) -> Result<u32> {
+        let _entered = self.instance.store.enter();
         let index = self
             .instance
             .memory_index(unsafe { &*self.wasmtime_export.definition });
-        self.instance
-            .memory_grow(index, delta)
-            .ok_or_else(|| anyhow!("failed to grow memory"))
+        match self.instance.memory_grow(index, delta) {
+            Ok(Some(size)) => Ok(size),
+            Ok(None) => bail!("failed to grow memory"),
+            Err(trap) => Err(Trap::from_runtime(trap).into()),
+        }
+    }
+
+    /// Creates a `Memory` in `store` for the shared memory `memory`, so that
+    /// it can be imported into instances of `store`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `store` doesn't belong to the [`Engine`] `memory`
+    /// was created with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut config = Config::new();
+    /// config.wasm_threads(true);
+    /// let engine = Engine::new(&config);
+    /// let memory = SharedMemory::new(&engine, MemoryType::shared(Limits::new(1, Some(2))))?;
+    ///
+    /// let module = Module::new(&engine, "(module (memory (import \"\" \"\") 1 2 shared))")?;
+    /// let handle = std::thread::spawn(move || -> anyhow::Result<()> {
+    ///     let store = Store::new(&engine);
+    ///     let memory = Memory::from_shared(&store, &memory)?;
+    ///     Instance::new(&store, &module, &[memory.into()])?;
+    ///     Ok(())
+    /// });
+    /// handle.join().unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_shared(store: &Store, memory: &SharedMemory) -> Result<Memory> {
+        if !Engine::same(store.engine(), &memory.engine) {
+            bail!("cross-`Engine` shared memories are not supported");
+        }
+        let (instance, wasmtime_export) = generate_shared_memory_export(store, &memory.memory)?;
+        Ok(Memory {
+            instance,
+            wasmtime_export,
+        })
+    }
+
+    /// Returns a [`SharedMemory`] handle to this memory if it's a shared
+    /// memory, to use it from other threads.
+    pub fn to_shared(&self) -> Option<SharedMemory> {
+        if !self.wasmtime_export.memory.memory.shared {
+            return None;
+        }
+        // The memory may be re-exported by another instance than the one
+        // defining it.
+        let owner = unsafe { InstanceHandle::from_vmctx(self.wasmtime_export.vmctx) };
+        let index = owner.memory_index(unsafe { &*self.wasmtime_export.definition });
+        Some(SharedMemory {
+            memory: owner.shared_memory(index)?,
+            engine: self.instance.store.engine().clone(),
+        })
     }
 
     pub(crate) fn from_wasmtime_memory(
@@ -861,6 +951,111 @@ impl Memory {
     }
 }
 
+/// A WebAssembly shared memory, which instances on different threads can use
+/// at the same time.
+///
+/// Shared memories are part of the [WebAssembly threads proposal], which must
+/// be enabled with [`Config::wasm_threads`](crate::Config::wasm_threads).
+/// Unlike a [`Memory`], a `SharedMemory` doesn't belong to a [`Store`]: it can
+/// be sent to other threads and imported into instances of any store of the
+/// same [`Engine`] by creating a [`Memory`] with [`Memory::from_shared`].
+/// WebAssembly code synchronizes its accesses with atomic instructions,
+/// `memory.atomic.wait` and `memory.atomic.notify`.
+///
+/// Shared memories reserve the whole 4GiB index space when they're created, so
+/// growing them never relocates their base pointer. See the section about
+/// threads in the [`Memory`] documentation for what that means for accessing
+/// their contents from the host.
+///
+/// [WebAssembly threads proposal]: https://github.com/webassembly/threads
+#[derive(Clone)]
+pub struct SharedMemory {
+    memory: runtime::SharedMemory,
+    engine: Engine,
+}
+
+impl SharedMemory {
+    /// Creates a new shared memory in `engine` given the configuration of
+    /// `ty`, which must be a [shared](MemoryType::shared) memory type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the threads proposal isn't enabled in `engine`, if
+    /// `ty` isn't shared or has no maximum size, or if the memory couldn't be
+    /// allocated.
+    pub fn new(engine: &Engine, ty: MemoryType) -> Result<SharedMemory> {
+        if !engine
+            .config()
+            .validating_config
+            .operator_config
+            .enable_threads
+        {
+            bail!("shared memories require the threads proposal to be enabled");
+        }
+        if !ty.is_shared() {
+            bail!("shared memories must have a shared memory type");
+        }
+        if ty.limits().max().is_none() {
+            bail!("shared memories must have a maximum size");
+        }
+        let memory = wasm::Memory {
+            minimum: ty.limits().min(),
+            maximum: ty.limits().max(),
+            shared: true,
+        };
+        let plan = MemoryPlan::for_memory(memory, &engine.config().tunables);
+        let memory = runtime::SharedMemory::new(plan).map_err(|e| anyhow!(e))?;
+        Ok(SharedMemory {
+            memory,
+            engine: engine.clone(),
+        })
+    }
+
+    /// Returns the underlying type of this memory.
+    pub fn ty(&self) -> MemoryType {
+        MemoryType::from_wasmtime_memory(&self.memory.plan().memory)
+    }
+
+    /// Returns the base pointer, in the host's address space, that the memory
+    /// is located at.
+    ///
+    /// The base pointer never changes. The contents of the memory may be
+    /// modified by other threads at any time, so they should only be accessed
+    /// atomically.
+    pub fn data_ptr(&self) -> *mut u8 {
+        unsafe { (*self.memory.vmmemory_ptr()).base }
+    }
+
+    /// Returns the byte length of this memory.
+    ///
+    /// The returned value will be a multiple of the wasm page size, 64k.
+    /// Another thread may grow the memory at any time.
+    pub fn data_size(&self) -> usize {
+        unsafe { (*self.memory.vmmemory_ptr()).current_length }
+    }
+
+    /// Returns the size, in pages, of this wasm memory.
+    pub fn size(&self) -> u32 {
+        self.memory.size()
+    }
+
+    /// Grows this WebAssembly memory by `delta` pages.
+    ///
+    /// On success returns the number of pages this memory previously had
+    /// before the growth succeeded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if memory could not be grown, for example if it exceeds
+    /// the maximum limits of this memory.
+    pub fn grow(&self, delta: u32) -> Result<u32> {
+        match self.memory.grow(delta) {
+            Some(size) => Ok(size),
+            None => bail!("failed to grow memory"),
+        }
+    }
+}
+
 /// A linear memory. This trait provides an interface for raw memory buffers which are used
 /// by wasmtime, e.g. inside ['Memory']. Such buffers are in principle not thread safe.
 /// By implementing this trait together with MemoryCreator,
@@ -874,7 +1069,7 @@ impl Memory {
 ///
 /// Note that this is a relatively new and experimental feature and it is recommended
 /// to be familiar with wasmtime runtime code to use it.
-pub unsafe trait LinearMemory {
+pub unsafe trait LinearMemory: Send {
     /// Returns the number of allocated wasm pages.
     fn size(&self) -> u32;
 
@@ -1,7 +1,10 @@
+use backtrace::Backtrace;
 use itertools::Itertools;
+use log::warn;
 use std::cmp;
 use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
+use wasmtime_debug::{SourceFrame, Symbolizer};
 use wasmtime_environ::entity::EntityRef;
 use wasmtime_environ::ir;
 use wasmtime_environ::wasm::FuncIndex;
@@ -44,6 +47,8 @@ struct ModuleFrameInfo {
     start: usize,
     functions: BTreeMap<usize, FunctionInfo>,
     module: Arc<Module>,
+    /// Source locations from the module's DWARF, if enabled and present.
+    symbolizer: Option<Symbolizer>,
     // #[allow(dead_code)]
     // module_code: Arc<dyn std::any::Any + Send + Sync>,
 }
@@ -105,15 +110,62 @@ impl GlobalFrameInfo {
             Some(pos) => func.instr_map.instructions[pos].srcloc,
             None => func.instr_map.start_srcloc,
         };
+        let symbols = match &module.symbolizer {
+            Some(symbolizer) => symbolizer
+                .lookup(u64::from(instr.bits()))
+                .into_iter()
+                .map(FrameSymbol::new)
+                .collect(),
+            None => Vec::new(),
+        };
         Some(FrameInfo {
             module_name: module.module.name.clone(),
             func_index: func.index.index() as u32,
             func_name: module.module.func_names.get(&func.index).cloned(),
             instr,
             func_start: func.instr_map.start_srcloc,
+            symbols,
         })
     }
 
+    /// Fetches frame information about each WebAssembly frame in
+    /// `native_trace`, innermost first, skipping frames whose program counter
+    /// doesn't satisfy `filter`.
+    ///
+    /// `trap_pc` is the program counter a trap happened at, if any.
+    pub fn lookup_backtrace(
+        &self,
+        native_trace: &Backtrace,
+        trap_pc: Option<usize>,
+        filter: impl Fn(usize) -> bool,
+    ) -> Vec<FrameInfo> {
+        let mut wasm_trace = Vec::new();
+        for frame in native_trace.frames() {
+            let pc = frame.ip() as usize;
+            if pc == 0 {
+                continue;
+            }
+            // Note that we need to be careful about the pc we pass in here to
+            // lookup frame information. This program counter is used to
+            // translate back to an original source location in the origin wasm
+            // module. If this pc is the exact pc that the trap happened at,
+            // then we look up that pc precisely. Otherwise backtrace
+            // information typically points at the pc *after* the call
+            // instruction (because otherwise it's likely a call instruction on
+            // the stack). In that case we want to lookup information for the
+            // previous instruction (the call instruction) so we subtract one as
+            // the lookup.
+            let pc_to_lookup = if Some(pc) == trap_pc { pc } else { pc - 1 };
+            if !filter(pc_to_lookup) {
+                continue;
+            }
+            if let Some(info) = self.lookup_frame_info(pc_to_lookup) {
+                wasm_trace.push(info);
+            }
+        }
+        wasm_trace
+    }
+
     /// Fetches trap information about a program counter in a backtrace.
     pub fn lookup_trap_info(&self, pc: usize) -> Option<&TrapInformation> {
         let (_module, func) = self.func(pc)?;
@@ -151,7 +203,10 @@ impl Drop for GlobalFrameInfoRegistration {
 /// compiled functions within `module`. If the `module` has no functions
 /// then `None` will be returned. Otherwise the returned object, when
 /// dropped, will be used to unregister all name information from this map.
-pub fn register(module: &CompiledModule) -> Option<GlobalFrameInfoRegistration> {
+///
+/// If `symbolize` is set then the module's DWARF, if any, is read to resolve
+/// frames to source locations.
+pub fn register(module: &CompiledModule, symbolize: bool) -> Option<GlobalFrameInfoRegistration> {
     let mut min = usize::max_value();
     let mut max = 0;
     let mut functions = BTreeMap::new();
@@ -190,6 +245,20 @@ pub fn register(module: &CompiledModule) -> Option<GlobalFrameInfoRegistration>
         return None;
     }
 
+    // Invalid DWARF only loses source locations, so it's not an error.
+    // The wasm is only kept around when it has debug information to read.
+    let symbolizer = match module.wasm() {
+        Some(wasm) if symbolize => match Symbolizer::new(wasm) {
+            Ok(symbolizer) if !symbolizer.is_empty() => Some(symbolizer),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("failed to read DWARF of wasm module: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
     let mut info = FRAME_INFO.write().unwrap();
     // First up assert that our chunk of jit functions doesn't collide with
     // any other known chunks of jit functions...
@@ -207,6 +276,7 @@ pub fn register(module: &CompiledModule) -> Option<GlobalFrameInfoRegistration>
             start: min,
             functions,
             module: module.module().clone(),
+            symbolizer,
             // module_code: module.code().clone(),
         },
     );
@@ -218,9 +288,12 @@ pub fn register(module: &CompiledModule) -> Option<GlobalFrameInfoRegistration>
 ///
 /// Whenever a WebAssembly trap occurs an instance of [`Trap`] is created. Each
 /// [`Trap`] has a backtrace of the WebAssembly frames that led to the trap, and
-/// each frame is described by this structure.
+/// each frame is described by this structure. Backtraces can also be captured
+/// on demand with [`Store::capture_backtrace`] and [`Caller::backtrace`].
 ///
 /// [`Trap`]: crate::Trap
+/// [`Store::capture_backtrace`]: crate::Store::capture_backtrace
+/// [`Caller::backtrace`]: crate::Caller::backtrace
 #[derive(Debug)]
 pub struct FrameInfo {
     module_name: Option<String>,
@@ -228,6 +301,7 @@ pub struct FrameInfo {
     func_name: Option<String>,
     func_start: ir::SourceLoc,
     instr: ir::SourceLoc,
+    symbols: Vec<FrameSymbol>,
 }
 
 impl FrameInfo {
@@ -288,4 +362,59 @@ impl FrameInfo {
     pub fn func_offset(&self) -> usize {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
+
+    /// Returns the source locations of this frame, as described by the DWARF
+    /// debug information of its module.
+    ///
+    /// This is only available when [`Config::wasm_backtrace_details`] or
+    /// [`Config::debug_info`] is enabled and the module has DWARF. There's
+    /// more than one symbol if functions were inlined, in which case the
+    /// innermost inlined function comes first.
+    ///
+    /// [`Config::wasm_backtrace_details`]: crate::Config::wasm_backtrace_details
+    /// [`Config::debug_info`]: crate::Config::debug_info
+    pub fn symbols(&self) -> &[FrameSymbol] {
+        &self.symbols
+    }
+}
+
+/// A source-level function within a [`FrameInfo`], along with the location
+/// its frame was at.
+#[derive(Debug)]
+pub struct FrameSymbol {
+    name: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+impl FrameSymbol {
+    fn new(frame: SourceFrame) -> FrameSymbol {
+        FrameSymbol {
+            name: frame.function,
+            file: frame.file,
+            line: frame.line,
+            column: frame.column,
+        }
+    }
+
+    /// Returns the name of the function, which may be mangled.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the path of the source file.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Returns the line number in the source file.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Returns the column number in the source line.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
 }
@@ -1,13 +1,16 @@
-use crate::runtime::StoreInner;
+use crate::runtime::WeakStore;
 use crate::trampoline::StoreInstanceHandle;
-use crate::{Extern, FuncType, Memory, Store, Trap, Val, ValType};
+use crate::{Extern, FrameInfo, FuncType, Memory, Store, Trap, Val, ValType};
 use anyhow::{bail, ensure, Context as _, Result};
 use std::cmp::max;
 use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::mem;
 use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "async")]
+use std::pin::Pin;
 use std::ptr::{self, NonNull};
-use std::rc::Weak;
 use wasmtime_runtime::{
     raise_user_trap, Export, InstanceHandle, VMContext, VMFunctionBody, VMTrampoline,
 };
@@ -31,6 +34,12 @@ use wasmtime_runtime::{
 /// cloning process only performs a shallow clone, so two cloned `Func`
 /// instances are equivalent in their functionality.
 ///
+/// # `Func` and threads
+///
+/// A `Func` can be sent to another thread and called there, as long as its
+/// [`Store`] isn't being used by any other thread at the same time. See the
+/// [`Store`] documentation for more information.
+///
 /// # Examples
 ///
 /// One way to get a `Func` is from an [`Instance`] after you've instantiated
@@ -145,6 +154,9 @@ pub struct Func {
     export: wasmtime_runtime::ExportFunction,
 }
 
+// The function's `anyfunc` is only used while its store is entered.
+unsafe impl Send for Func {}
+
 macro_rules! getters {
     ($(
         $(#[$doc:meta])*
@@ -228,6 +240,9 @@ impl Func {
     /// signature given, error or traps may occur if it does not respect the
     /// `ty` signature.
     ///
+    /// The closure is required to be `Send` since, like the rest of the
+    /// `store`, it may be moved to another thread and called from there.
+    ///
     /// Additionally note that this is quite a dynamic function since signatures
     /// are not statically known. For a more performant `Func` it's recommended
     /// to use [`Func::wrap`] if you can because with statically known
@@ -235,7 +250,7 @@ impl Func {
     pub fn new(
         store: &Store,
         ty: FuncType,
-        func: impl Fn(Caller<'_>, &[Val], &mut [Val]) -> Result<(), Trap> + 'static,
+        func: impl Fn(Caller<'_>, &[Val], &mut [Val]) -> Result<(), Trap> + Send + 'static,
     ) -> Self {
         let store_clone: Store = store.clone();
         let ty_clone = ty.clone();
@@ -322,6 +337,72 @@ impl Func {
         }
     }
 
+    /// Creates a new host-defined WebAssembly function which, instead of being
+    /// driven by a synchronous closure, returns a future to produce its
+    /// results.
+    ///
+    /// This function is the asynchronous analogue of [`Func::new`], and much
+    /// of that documentation applies to this as well. The `data` provided is
+    /// passed to each invocation of `func`, which allows the returned future
+    /// to borrow from it. The future returned by `func` is driven to
+    /// completion on behalf of the calling WebAssembly, which is suspended
+    /// while the future is pending.
+    ///
+    /// Async host functions can only be used through asynchronous calls into
+    /// WebAssembly such as [`Func::call_async`]. If one is reached through a
+    /// synchronous call, such as [`Func::call`], then it traps instead.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `store` doesn't have
+    /// [`Config::async_support`](crate::Config::async_support) enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let engine = Engine::new(Config::new().async_support(true));
+    /// let store = Store::new(&engine);
+    /// let ty = FuncType::new(Box::new([ValType::I32]), Box::new([ValType::I32]));
+    /// let double = Func::new_async(&store, ty, 2, |_caller, factor, params, results| {
+    ///     Box::new(async move {
+    ///         // Await some asynchronous host operation here...
+    ///         results[0] = Val::I32(params[0].unwrap_i32() * *factor);
+    ///         Ok(())
+    ///     })
+    /// });
+    /// # drop(double);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn new_async<T, F>(store: &Store, ty: FuncType, data: T, func: F) -> Func
+    where
+        T: Send + 'static,
+        F: for<'a> Fn(
+                Caller<'a>,
+                &'a T,
+                &'a [Val],
+                &'a mut [Val],
+            ) -> Box<dyn Future<Output = Result<(), Trap>> + 'a>
+            + Send
+            + 'static,
+    {
+        assert!(
+            store.async_support(),
+            "cannot use `new_async` without enabling async support in the config"
+        );
+        Func::new(store, ty, move |caller, params, results| {
+            let store = caller.store();
+            let mut future = Pin::from(func(caller, &data, params, results));
+            match store.block_on(future.as_mut()) {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(trap)) | Err(trap) => Err(trap),
+            }
+        })
+    }
+
     /// Creates a new `Func` from the given Rust closure.
     ///
     /// This function will create a new `Func` which, when called, will
@@ -562,6 +643,8 @@ impl Func {
     /// This function should not panic unless the underlying function itself
     /// initiates a panic.
     pub fn call(&self, params: &[Val]) -> Result<Box<[Val]>> {
+        let _entered = self.instance.store.enter();
+
         // We need to perform a dynamic check that the arguments given to us
         // match the signature of this function and are appropriate to pass to
         // this function. This involves checking to make sure we have the right
@@ -621,6 +704,32 @@ impl Func {
         Ok(results.into())
     }
 
+    /// Invokes this function with the `params` given, returning the results
+    /// asynchronously.
+    ///
+    /// This function is the same as [`Func::call`] except that the
+    /// WebAssembly executes on a separate native stack, which allows it to
+    /// call host functions defined with [`Func::new_async`]. While such a
+    /// host function's future is pending the WebAssembly is suspended and the
+    /// future returned here is pending too. The returned future can be
+    /// polled by any executor, but it must be polled to completion for the
+    /// WebAssembly to finish executing; if it's dropped beforehand then the
+    /// suspended WebAssembly is unwound with a trap.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if this function's store doesn't have
+    /// [`Config::async_support`](crate::Config::async_support) enabled.
+    #[cfg(feature = "async")]
+    pub async fn call_async(&self, params: &[Val]) -> Result<Box<[Val]>> {
+        assert!(
+            self.store().async_support(),
+            "cannot use `call_async` without enabling async support in the config"
+        );
+        let result = self.store().on_fiber(|| self.call(params)).await??;
+        Ok(result)
+    }
+
     pub(crate) fn wasmtime_function(&self) -> &wasmtime_runtime::ExportFunction {
         &self.export
     }
@@ -788,6 +897,7 @@ pub(crate) fn invoke_wasm_and_catch_traps(
     store: &Store,
     closure: impl FnMut(),
 ) -> Result<(), Trap> {
+    let _entered = store.enter();
     let signalhandler = store.signal_handler();
     unsafe {
         let canary = 0;
@@ -1066,8 +1176,8 @@ pub trait IntoFunc<Params, Results> {
 /// the caller's memory until interface types has been fully standardized and
 /// implemented.
 pub struct Caller<'a> {
-    // Note that this is a `Weak` pointer instead of a `&'a Store`,
-    // intentionally so. This allows us to break an `Rc` cycle which would
+    // Note that this is a weak pointer instead of a `&'a Store`,
+    // intentionally so. This allows us to break an `Arc` cycle which would
     // otherwise look like this:
     //
     // * A `Store` object ...
@@ -1076,13 +1186,13 @@ pub struct Caller<'a> {
     // * ... where the custom host data needs to point to `Store` to be stored
     //   here
     //
-    // This `Rc` cycle means that we would never actually reclaim any memory or
+    // This `Arc` cycle means that we would never actually reclaim any memory or
     // deallocate any instances. To break this cycle we use a weak pointer here
     // which points back to `Store`. A `Caller` should only ever be usable
     // when the original `Store` is alive, however, so this should always be an
     // upgrade-able pointer. Alternative solutions or other ideas to break this
     // cycle would be most welcome!
-    store: &'a Weak<StoreInner>,
+    store: &'a WeakStore,
     caller_vmctx: *mut VMContext,
 }
 
@@ -1116,13 +1226,12 @@ impl Caller<'_> {
                 Some(Export::Memory(m)) => m,
                 _ => return None,
             };
-            // Our `Weak` pointer is used only to break a cycle where `Store`
+            // Our weak pointer is used only to break a cycle where `Store`
             // stores instance handles which have this weak pointer as their
             // custom host data. This function should only be invoke-able while
             // the `Store` is active, so this upgrade should always succeed.
-            debug_assert!(self.store.upgrade().is_some());
-            let handle =
-                Store::from_inner(self.store.upgrade()?).existing_instance_handle(instance);
+            debug_assert!(Store::upgrade(self.store).is_some());
+            let handle = Store::upgrade(self.store)?.existing_instance_handle(instance);
             let mem = Memory::from_wasmtime_memory(export, handle);
             Some(Extern::Memory(mem))
         }
@@ -1133,6 +1242,14 @@ impl Caller<'_> {
         // See comment above the `store` member for why this unwrap is OK.
         Store::upgrade(&self.store).unwrap()
     }
+
+    /// Captures a backtrace of the WebAssembly frames which led to this call,
+    /// innermost first, so the first frame is the caller's.
+    ///
+    /// See [`Store::capture_backtrace`] for more information.
+    pub fn backtrace(&self) -> Vec<FrameInfo> {
+        self.store().capture_backtrace()
+    }
 }
 
 macro_rules! impl_into_func {
@@ -1144,7 +1261,7 @@ macro_rules! impl_into_func {
         // `Caller` parameter.
         impl<F, $($args,)* R> IntoFunc<($($args,)*), R> for F
         where
-            F: Fn($($args),*) -> R + 'static,
+            F: Fn($($args),*) -> R + Send + 'static,
             $($args: WasmTy,)*
             R: WasmRet,
         {
@@ -1159,7 +1276,7 @@ macro_rules! impl_into_func {
         #[allow(non_snake_case)]
         impl<F, $($args,)* R> IntoFunc<(Caller<'_>, $($args,)*), R> for F
         where
-            F: Fn(Caller<'_>, $($args),*) -> R + 'static,
+            F: Fn(Caller<'_>, $($args),*) -> R + Send + 'static,
             $($args: WasmTy,)*
             R: WasmRet,
         {
@@ -1173,7 +1290,7 @@ macro_rules! impl_into_func {
                     $($args: $args,)*
                 ) -> R::Abi
                 where
-                    F: Fn(Caller<'_>, $($args),*) -> R + 'static,
+                    F: Fn(Caller<'_>, $($args),*) -> R + Send + 'static,
                     $($args: WasmTy,)*
                     R: WasmRet,
                 {
@@ -1182,8 +1299,8 @@ macro_rules! impl_into_func {
                         // Double-check ourselves in debug mode, but we control
                         // the `Any` here so an unsafe downcast should also
                         // work.
-                        debug_assert!(state.is::<(F, Weak<StoreInner>)>());
-                        let (func, store) = &*(state as *const _ as *const (F, Weak<StoreInner>));
+                        debug_assert!(state.is::<(F, WeakStore)>());
+                        let (func, store) = &*(state as *const _ as *const (F, WeakStore));
                         panic::catch_unwind(AssertUnwindSafe(|| {
                             func(
                                 Caller { store, caller_vmctx },
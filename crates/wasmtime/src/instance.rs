@@ -25,7 +25,7 @@ fn instantiate(
     store: &Store,
     compiled_module: &CompiledModule,
     imports: &[Extern],
-    host: Box<dyn Any>,
+    host: Box<dyn Any + Send>,
 ) -> Result<StoreInstanceHandle, Error> {
     // For now we have a restriction that the `Store` that we're working
     // with is the same for everything involved here.
@@ -46,15 +46,18 @@ fn instantiate(
     let mut resolver = SimpleResolver { imports };
     let config = store.engine().config();
     let instance = unsafe {
-        let instance = compiled_module.instantiate(
-            &mut resolver,
-            &mut store.signatures_mut(),
-            config.memory_creator.as_ref().map(|a| a as _),
-            store.interrupts().clone(),
-            host,
-            &**store.externref_activations_table() as *const VMExternRefActivationsTable as *mut _,
-            &**store.stack_map_registry() as *const StackMapRegistry as *mut _,
-        )?;
+        let instance = compiled_module
+            .instantiate(
+                &mut resolver,
+                &mut store.signatures_mut(),
+                store.engine().allocator(),
+                store.interrupts().clone(),
+                host,
+                store.externref_activations_table() as *const VMExternRefActivationsTable as *mut _,
+                store.stack_map_registry() as *const StackMapRegistry as *mut _,
+                store.resources(),
+            )
+            .map_err(instantiation_error)?;
 
         // After we've created the `InstanceHandle` we still need to run
         // initialization to set up data/elements/etc. We do this after adding
@@ -63,18 +66,14 @@ fn instantiate(
         // initializers may have run which placed elements into other instance's
         // tables. This means that from this point on, regardless of whether
         // initialization is successful, we need to keep the instance alive.
-        let instance = store.add_instance(instance);
+        let instance = store.add_instance(instance, false);
         instance
             .initialize(
                 config.validating_config.operator_config.enable_bulk_memory,
+                compiled_module.memory_images(),
                 &compiled_module.data_initializers(),
             )
-            .map_err(|e| -> Error {
-                match e {
-                    InstantiationError::Trap(trap) => Trap::from_runtime(trap).into(),
-                    other => other.into(),
-                }
-            })?;
+            .map_err(instantiation_error)?;
 
         instance
     };
@@ -107,6 +106,13 @@ fn instantiate(
     Ok(instance)
 }
 
+fn instantiation_error(e: InstantiationError) -> Error {
+    match e {
+        InstantiationError::Trap(trap) => Trap::from_runtime(trap).into(),
+        other => other.into(),
+    }
+}
+
 /// An instantiated WebAssembly module.
 ///
 /// This type represents the instantiation of a [`Module`]. Once instantiated
@@ -123,6 +129,9 @@ fn instantiate(
 ///
 /// When interacting with any wasm code you'll want to make an [`Instance`] to
 /// call any code or execute anything!
+///
+/// Instances can be sent to other threads along with their [`Store`]. See the
+/// [`Store`] documentation for more information.
 #[derive(Clone)]
 pub struct Instance {
     pub(crate) handle: StoreInstanceHandle,
@@ -130,6 +139,9 @@ pub struct Instance {
     module: Module,
 }
 
+// The instance's state is only mutated while its store is entered.
+unsafe impl Send for Instance {}
+
 impl Instance {
     /// Creates a new [`Instance`] from the previously compiled [`Module`] and
     /// list of `imports` specified.
@@ -188,6 +200,7 @@ impl Instance {
         if !Engine::same(store.engine(), module.engine()) {
             bail!("cross-`Engine` instantiation is not currently supported");
         }
+        let _entered = store.enter();
 
         let host_info = Box::new({
             let frame_info_registration = module.register_frame_info();
@@ -205,6 +218,33 @@ impl Instance {
         })
     }
 
+    /// Same as [`Instance::new`], except that the instantiation, including
+    /// the module's start function, happens asynchronously.
+    ///
+    /// This is required to instantiate modules whose start function may call
+    /// host functions defined with [`Func::new_async`]. See
+    /// [`Func::call_async`] for more information about how the asynchronous
+    /// execution of WebAssembly works.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `store` doesn't have
+    /// [`Config::async_support`](crate::Config::async_support) enabled.
+    #[cfg(feature = "async")]
+    pub async fn new_async(
+        store: &Store,
+        module: &Module,
+        imports: &[Extern],
+    ) -> Result<Instance, Error> {
+        assert!(
+            store.async_support(),
+            "cannot use `new_async` without enabling async support in the config"
+        );
+        store
+            .on_fiber(|| Instance::new(store, module, imports))
+            .await?
+    }
+
     /// Get the internal store handle for this `Instance` (*use with caution*)
     pub fn handle(&self) -> &StoreInstanceHandle {
         &self.handle
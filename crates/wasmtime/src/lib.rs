@@ -14,6 +14,7 @@ mod externals;
 mod frame_info;
 mod func;
 mod instance;
+mod limits;
 mod linker;
 mod module;
 mod r#ref;
@@ -24,16 +25,18 @@ mod types;
 mod values;
 
 pub use crate::externals::*;
-pub use crate::frame_info::FrameInfo;
+pub use crate::frame_info::{FrameInfo, FrameSymbol};
 pub use crate::func::*;
 pub use crate::instance::Instance;
+pub use crate::limits::{ResourceLimiter, DEFAULT_INSTANCE_LIMIT};
 pub use crate::linker::*;
 pub use crate::module::Module;
-pub use crate::r#ref::ExternRef;
+pub use crate::r#ref::{ExternRef, StoreItem};
 pub use crate::runtime::*;
-pub use crate::trap::Trap;
+pub use crate::trap::{Trap, TrapCode};
 pub use crate::types::*;
 pub use crate::values::*;
+pub use wasmtime_runtime::{InstanceLimits, ModuleLimits};
 
 cfg_if::cfg_if! {
     if #[cfg(unix)] {
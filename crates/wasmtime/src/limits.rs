@@ -0,0 +1,86 @@
+use crate::Trap;
+use std::error::Error;
+use wasmtime_runtime::RuntimeResourceLimiter;
+
+/// The default maximum number of instances a [`ResourceLimiter`] allows in a
+/// store.
+pub const DEFAULT_INSTANCE_LIMIT: usize = 10000;
+
+/// Used by hosts to limit the resources a [`Store`](crate::Store) may use.
+///
+/// A limiter is attached to a store with
+/// [`Store::new_with_limiter`](crate::Store::new_with_limiter). It's
+/// consulted whenever a linear memory or table is created or grown in the
+/// store, and whenever an instance is created in it.
+///
+/// Growth requested by wasm with `memory.grow` or `table.grow` which is
+/// denied makes the instruction return `-1`, as if the memory or table had
+/// reached its maximum. Creation of a memory or table which is denied makes
+/// instantiation fail.
+pub trait ResourceLimiter: Send + Sync + 'static {
+    /// Notifies the limiter that a linear memory is being created with, or
+    /// grown to, `desired` bytes from `current` bytes.
+    ///
+    /// `maximum` is the maximum size of the memory in bytes, as declared by
+    /// its module, if it has one.
+    ///
+    /// Returning `Ok(true)` allows the memory to grow, `Ok(false)` denies the
+    /// growth, and returning an error traps.
+    fn memory_growing(
+        &self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, Trap>;
+
+    /// Notifies the limiter that a table is being created with, or grown to,
+    /// `desired` elements from `current` elements.
+    ///
+    /// `maximum` is the maximum number of elements of the table, as declared
+    /// by its module, if it has one.
+    ///
+    /// Returning `Ok(true)` allows the table to grow, `Ok(false)` denies the
+    /// growth, and returning an error traps.
+    fn table_growing(&self, current: u32, desired: u32, maximum: Option<u32>)
+        -> Result<bool, Trap>;
+
+    /// The maximum number of instances that can be created in the store.
+    ///
+    /// This includes instances created by the host for functions, globals,
+    /// tables and memories defined with the wasmtime API.
+    ///
+    /// Defaults to [`DEFAULT_INSTANCE_LIMIT`].
+    fn instances(&self) -> usize {
+        DEFAULT_INSTANCE_LIMIT
+    }
+}
+
+pub(crate) struct ResourceLimiterProxy<T>(pub(crate) T);
+
+impl<T: ResourceLimiter> RuntimeResourceLimiter for ResourceLimiterProxy<T> {
+    fn memory_growing(
+        &self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.0
+            .memory_growing(current, desired, maximum)
+            .map_err(|trap| Box::new(trap) as _)
+    }
+
+    fn table_growing(
+        &self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.0
+            .table_growing(current, desired, maximum)
+            .map_err(|trap| Box::new(trap) as _)
+    }
+
+    fn instances(&self) -> usize {
+        self.0.instances()
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
 use anyhow::{anyhow, bail, Context, Error, Result};
 use log::warn;
 use std::collections::hash_map::{Entry, HashMap};
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// Structure used to link wasm modules/instances together.
 ///
@@ -39,8 +39,8 @@ use std::rc::Rc;
 /// controlled with the [`Linker::allow_shadowing`] method as well.
 pub struct Linker {
     store: Store,
-    string2idx: HashMap<Rc<str>, usize>,
-    strings: Vec<Rc<str>>,
+    string2idx: HashMap<Arc<str>, usize>,
+    strings: Vec<Arc<str>>,
     map: HashMap<ImportKey, Extern>,
     allow_shadowing: bool,
 }
@@ -518,7 +518,7 @@ impl Linker {
         if let Some(idx) = self.string2idx.get(string) {
             return *idx;
         }
-        let string: Rc<str> = string.into();
+        let string: Arc<str> = string.into();
         let idx = self.strings.len();
         self.strings.push(string.clone());
         self.string2idx.insert(string, idx);
@@ -572,6 +572,24 @@ impl Linker {
         Instance::new(&self.store, module, &imports)
     }
 
+    /// Attempts to instantiate the `module` provided asynchronously.
+    ///
+    /// This is the same as [`Linker::instantiate`] except that it uses
+    /// [`Instance::new_async`] to perform the instantiation, which is required
+    /// if the module's start function may call host functions defined with
+    /// [`Func::new_async`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if this linker's store doesn't have
+    /// [`Config::async_support`](crate::Config::async_support) enabled.
+    #[cfg(feature = "async")]
+    pub async fn instantiate_async(&self, module: &Module) -> Result<Instance> {
+        let imports = self.compute_imports(module)?;
+
+        Instance::new_async(&self.store, module, &imports).await
+    }
+
     fn compute_imports(&self, module: &Module) -> Result<Vec<Extern>> {
         module
             .imports()
@@ -1,7 +1,7 @@
 use crate::frame_info::GlobalFrameInfoRegistration;
 use crate::runtime::Engine;
 use crate::types::{EntityType, ExportType, ExternType, ImportType};
-use anyhow::{Error, Result};
+use anyhow::{bail, Context, Error, Result};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use wasmparser::validate;
@@ -176,6 +176,14 @@ impl Module {
     /// pass the bytes to the [`Module::new`] function. For more information
     /// see [`Module::new`]
     ///
+    /// Only the wasm binary and text formats are accepted. Files holding a
+    /// module precompiled with [`Engine::precompile_module`] or
+    /// [`Module::serialize`] (such as the `.cwasm` files written by
+    /// `wasmtime compile`) are rejected with an error: loading them runs their
+    /// native code as-is, which is only sound if the file comes from a trusted
+    /// source, so it has to be requested explicitly with the `unsafe`
+    /// [`Module::deserialize_file`] function instead.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -198,11 +206,22 @@ impl Module {
     /// # }
     /// ```
     pub fn from_file(engine: &Engine, file: impl AsRef<Path>) -> Result<Module> {
+        let file = file.as_ref();
+        let bytes = std::fs::read(file)
+            .with_context(|| format!("failed to read input file: {}", file.display()))?;
+        if CompiledModule::is_serialized(&bytes) {
+            bail!(
+                "`{}` is a precompiled module and must be loaded with \
+                 `Module::deserialize_file`",
+                file.display()
+            );
+        }
         #[cfg(feature = "wat")]
-        let wasm = wat::parse_file(file)?;
-        #[cfg(not(feature = "wat"))]
-        let wasm = std::fs::read(file)?;
-        Module::new(engine, &wasm)
+        let bytes = wat::parse_bytes(&bytes).map_err(|mut e| {
+            e.set_path(file);
+            e
+        })?;
+        Module::new(engine, &bytes)
     }
 
     /// Creates a new WebAssembly `Module` from the given in-memory `binary`
@@ -302,7 +321,16 @@ impl Module {
 
     unsafe fn compile(engine: &Engine, binary: &[u8]) -> Result<Self> {
         let compiled = CompiledModule::new(engine.compiler(), binary, &*engine.config().profiler)?;
+        Module::from_compiled(engine, compiled)
+    }
 
+    fn from_compiled(engine: &Engine, compiled: CompiledModule) -> Result<Module> {
+        // Reject modules which can't be instantiated by the engine's
+        // allocator early, rather than on every instantiation.
+        engine
+            .allocator()
+            .validate(compiled.module())
+            .map_err(Error::msg)?;
         Ok(Module {
             engine: engine.clone(),
             compiled: Arc::new(compiled),
@@ -310,6 +338,77 @@ impl Module {
         })
     }
 
+    /// Serializes this module into a byte vector which can later be loaded
+    /// with [`Module::deserialize`], without compiling the module again.
+    ///
+    /// The result is in the same format as that of
+    /// [`Engine::precompile_module`], and can only be deserialized by an
+    /// [`Engine`] with the same target and configuration as this module's.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let engine = Engine::default();
+    /// let module = Module::new(&engine, "(module (func (export \"f\")))")?;
+    /// let bytes = module.serialize()?;
+    /// let module = unsafe { Module::deserialize(&engine, &bytes)? };
+    /// assert!(module.get_export("f").is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        Ok(self.compiled.serialize(self.engine.compiler())?)
+    }
+
+    /// Deserializes a module previously produced by [`Module::serialize`] or
+    /// [`Engine::precompile_module`], without compiling it again.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `bytes` is not a serialized module, or if the
+    /// module was serialized by a different version of Wasmtime or with an
+    /// [`Engine`] whose target or configuration (such as the tunables
+    /// affecting generated code, debug info, interruption or fuel) differs
+    /// from `engine`.
+    ///
+    /// # Unsafety
+    ///
+    /// This function is `unsafe` because the machine code and metadata
+    /// contained in `bytes` are loaded and used as-is. Only the compatibility of the
+    /// configuration is checked, not the code itself, so `bytes` must have
+    /// been produced by [`Module::serialize`] or [`Engine::precompile_module`]
+    /// and must come from a trusted source.
+    pub unsafe fn deserialize(engine: &Engine, bytes: &[u8]) -> Result<Module> {
+        let compiled =
+            CompiledModule::deserialize(engine.compiler(), bytes, &*engine.config().profiler)?;
+        Module::from_compiled(engine, compiled)
+    }
+
+    /// Deserializes a module previously produced by
+    /// [`Engine::precompile_module`] from the contents of the given `file` on
+    /// disk.
+    ///
+    /// This is a convenience function that will read the `file` provided and
+    /// pass the bytes to the [`Module::deserialize`] function; see its
+    /// documentation for the errors that can occur.
+    ///
+    /// # Unsafety
+    ///
+    /// This function is `unsafe` for the same reasons as
+    /// [`Module::deserialize`]: the contents of `file` are loaded and executed
+    /// as native code, so it must have been produced by
+    /// [`Engine::precompile_module`] (for example by `wasmtime compile`) and
+    /// must come from a trusted source.
+    pub unsafe fn deserialize_file(engine: &Engine, file: impl AsRef<Path>) -> Result<Module> {
+        let file = file.as_ref();
+        let bytes = std::fs::read(file)
+            .with_context(|| format!("failed to read input file: {}", file.display()))?;
+        Module::deserialize(engine, &bytes)
+            .with_context(|| format!("failed to load precompiled module: {}", file.display()))
+    }
+
     pub(crate) fn compiled_module(&self) -> &CompiledModule {
         &self.compiled
     }
@@ -530,7 +629,9 @@ impl Module {
         if let Some(info) = &*info {
             return info.clone();
         }
-        let ret = super::frame_info::register(&self.compiled).map(Arc::new);
+        let config = self.engine.config();
+        let symbolize = config.wasm_backtrace_details || config.tunables.debug_info;
+        let ret = super::frame_info::register(&self.compiled, symbolize).map(Arc::new);
         *info = Some(ret.clone());
         return ret;
     }
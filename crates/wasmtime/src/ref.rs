@@ -1,29 +1,85 @@
 #![allow(missing_docs)]
 
+use crate::{Extern, Func, Global, Instance, Memory, Table};
 use std::any::Any;
-use std::cell::RefCell;
 use std::fmt;
-use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
 use wasmtime_runtime::VMExternRef;
 
+/// One of the items belonging to a [`Store`](crate::Store): a [`Func`],
+/// [`Global`], [`Table`], [`Memory`], [`Instance`] or [`Extern`].
+///
+/// This trait is sealed and can't be implemented outside of this crate.
+pub trait StoreItem: private::Sealed + Clone + Send + 'static {}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! store_items {
+    ($($item:ident)*) => ($(
+        impl private::Sealed for $item {}
+        impl StoreItem for $item {}
+    )*)
+}
+
+store_items!(Func Global Table Memory Instance Extern);
+
+/// A store item, as wrapped by an `ExternRef`.
+struct SharedStoreItem<T>(T);
+
+// Clones of a store item share its state, and store items are `Send`, so
+// sharing one between threads is no different from sending clones of it to
+// them. Either way, the item's state is only accessed by the thread which has
+// entered its store.
+unsafe impl<T: StoreItem> Sync for SharedStoreItem<T> {}
+
 /// Represents an opaque reference to any data within WebAssembly.
 #[derive(Clone)]
 pub struct ExternRef {
     pub(crate) inner: VMExternRef,
-    pub(crate) store: Weak<crate::runtime::StoreInner>,
+    pub(crate) store: crate::runtime::WeakStore,
 }
 
 impl ExternRef {
     /// Creates a new instance of `ExternRef` wrapping the given value.
+    ///
+    /// The value must be `Send` and `Sync`: unlike its store, an `ExternRef`
+    /// can be cloned and used from several threads at once, and its value is
+    /// accessed through [`ExternRef::data`] without entering the store.
     pub fn new<T>(store: &crate::Store, value: T) -> ExternRef
     where
-        T: 'static + Any,
+        T: 'static + Any + Send + Sync,
     {
         let inner = VMExternRef::new(value);
         let store = store.weak();
         ExternRef { inner, store }
     }
 
+    /// Creates a new instance of `ExternRef` wrapping `item`, one of the
+    /// items of `store`.
+    ///
+    /// Store items are `Send` but not `Sync`, so they can't be wrapped with
+    /// [`ExternRef::new`]. They're retrieved with [`ExternRef::store_item`]
+    /// rather than [`ExternRef::data`].
+    pub fn from_store_item<T>(store: &crate::Store, item: T) -> ExternRef
+    where
+        T: StoreItem,
+    {
+        ExternRef::new(store, SharedStoreItem(item))
+    }
+
+    /// Get the store item wrapped by this `ExternRef`, if it was created with
+    /// [`ExternRef::from_store_item`] for an item of type `T`.
+    pub fn store_item<T>(&self) -> Option<&T>
+    where
+        T: StoreItem,
+    {
+        self.data()
+            .downcast_ref::<SharedStoreItem<T>>()
+            .map(|item| &item.0)
+    }
+
     /// Get this reference's store.
     ///
     /// Returns `None` if this reference outlived its store.
@@ -51,24 +107,24 @@ impl ExternRef {
 
     /// Returns the host information for this `externref`, if previously created
     /// with `set_host_info`.
-    pub fn host_info(&self) -> Option<Rc<RefCell<dyn Any>>> {
+    pub fn host_info(&self) -> Option<Arc<Mutex<dyn Any + Send>>> {
         let store = crate::Store::upgrade(&self.store)?;
         store.host_info(self)
     }
 
     /// Set the host information for this `externref`, returning the old host
     /// information if it was previously set.
-    pub fn set_host_info<T>(&self, info: T) -> Option<Rc<RefCell<dyn Any>>>
+    pub fn set_host_info<T>(&self, info: T) -> Option<Arc<Mutex<dyn Any + Send>>>
     where
-        T: 'static + Any,
+        T: 'static + Any + Send,
     {
         let store = crate::Store::upgrade(&self.store)?;
-        store.set_host_info(self, Some(Rc::new(RefCell::new(info))))
+        store.set_host_info(self, Some(Arc::new(Mutex::new(info))))
     }
 
     /// Remove the host information for this `externref`, returning the old host
     /// information if it was previously set.
-    pub fn remove_host_info(&self) -> Option<Rc<RefCell<dyn Any>>> {
+    pub fn remove_host_info(&self) -> Option<Arc<Mutex<dyn Any + Send>>> {
         let store = crate::Store::upgrade(&self.store)?;
         store.set_host_info(self, None)
     }
@@ -1,27 +1,42 @@
 use crate::externals::MemoryCreator;
+use crate::frame_info::FRAME_INFO;
+use crate::limits::{ResourceLimiter, ResourceLimiterProxy};
 use crate::r#ref::ExternRef;
 use crate::trampoline::{MemoryCreatorProxy, StoreInstanceHandle};
-use crate::Module;
-use anyhow::{bail, Result};
+use crate::{FrameInfo, Module, Trap};
+use anyhow::{anyhow, bail, Result};
+use backtrace::Backtrace;
 use std::any::Any;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 use std::path::Path;
-use std::rc::{Rc, Weak};
-use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::ptr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use target_lexicon::{Architecture, Triple};
 use wasmparser::{OperatorValidatorConfig, ValidatingParserConfig};
 use wasmtime_environ::settings::{self, Configurable, SetError};
 use wasmtime_environ::{ir, isa, isa::TargetIsa, wasm, CacheConfig, Tunables};
-use wasmtime_jit::{native, CompilationStrategy, Compiler};
+use wasmtime_jit::{native, CompilationStrategy, CompiledModule, Compiler, WasmFeatures};
 use wasmtime_profiling::{JitDumpAgent, NullProfilerAgent, ProfilingAgent, VTuneAgent};
 use wasmtime_runtime::{
-    debug_builtins, InstanceHandle, RuntimeMemoryCreator, SignalHandler, SignatureRegistry,
-    StackMapRegistry, VMExternRef, VMExternRefActivationsTable, VMInterrupts,
-    VMSharedSignatureIndex,
+    debug_builtins, InstanceAllocator, InstanceHandle, InstanceLimits, ModuleLimits,
+    OnDemandInstanceAllocator, PoolingInstanceAllocator, ResourceTracker, RuntimeMemoryCreator,
+    SignalHandler, SignatureRegistry, StackMapRegistry, VMExternRef, VMExternRefActivationsTable,
+    VMInterrupts, VMSharedSignatureIndex,
 };
 
 // Runtime Environment
@@ -37,13 +52,22 @@ use wasmtime_runtime::{
 pub struct Config {
     pub(crate) flags: settings::Builder,
     pub(crate) isa_flags: isa::Builder,
+    /// The ISA flags which were explicitly configured, so that they can be
+    /// applied again when the target changes.
+    isa_flag_values: Vec<(String, String)>,
     pub(crate) validating_config: ValidatingParserConfig,
     pub(crate) tunables: Tunables,
     pub(crate) strategy: CompilationStrategy,
     pub(crate) cache_config: CacheConfig,
     pub(crate) profiler: Arc<dyn ProfilingAgent>,
     pub(crate) memory_creator: Option<MemoryCreatorProxy>,
+    pub(crate) instance_allocator: Option<Arc<dyn InstanceAllocator>>,
     pub(crate) max_wasm_stack: usize,
+    #[cfg(feature = "async")]
+    pub(crate) async_support: bool,
+    #[cfg(feature = "async")]
+    pub(crate) async_stack_size: usize,
+    pub(crate) wasm_backtrace_details: bool,
 }
 
 impl Config {
@@ -96,14 +120,62 @@ impl Config {
             },
             flags,
             isa_flags: native::builder(),
+            isa_flag_values: Vec::new(),
             strategy: CompilationStrategy::Auto,
             cache_config: CacheConfig::new_cache_disabled(),
             profiler: Arc::new(NullProfilerAgent),
             memory_creator: None,
+            instance_allocator: None,
             max_wasm_stack: 1 << 20,
+            #[cfg(feature = "async")]
+            async_support: false,
+            #[cfg(feature = "async")]
+            async_stack_size: 2 << 20,
+            wasm_backtrace_details: false,
         }
     }
 
+    /// Configures the target platform that code is compiled for.
+    ///
+    /// This can be used to compile modules for another platform, for example
+    /// ahead of time with [`Engine::precompile_module`], and then load them on
+    /// that platform with [`Module::deserialize`]. Modules compiled for a
+    /// platform other than the host can't be instantiated.
+    ///
+    /// By default the target is the host, using all of the CPU features it
+    /// supports. The same is true when `target` is the host's triple. Any
+    /// target-specific Cranelift flags configured previously, for example with
+    /// [`Config::cranelift_other_flag`], are applied to the new target too.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `target` isn't a valid target triple, if compiling
+    /// for it isn't supported, or if a previously configured target-specific
+    /// flag doesn't apply to it. The configuration is unchanged in that case.
+    pub fn target(&mut self, target: &str) -> Result<&mut Self> {
+        let triple = Triple::from_str(target).map_err(|e| anyhow!(e))?;
+        let mut isa_flags = if triple == Triple::host() {
+            native::builder()
+        } else {
+            isa::lookup(triple)?
+        };
+        for (name, value) in self.isa_flag_values.iter() {
+            isa_flags.set(name, value).map_err(|e| {
+                anyhow!("cannot apply flag `{}` to target `{}`: {}", name, target, e)
+            })?;
+        }
+        self.isa_flags = isa_flags;
+        Ok(self)
+    }
+
+    fn set_isa_flag(&mut self, name: &str, value: &str) -> Result<()> {
+        self.isa_flags.set(name, value)?;
+        self.isa_flag_values.retain(|(n, _)| n != name);
+        self.isa_flag_values
+            .push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
     /// Configures whether DWARF debug information will be emitted during
     /// compilation.
     ///
@@ -113,6 +185,21 @@ impl Config {
         self
     }
 
+    /// Configures whether the frames of backtraces, such as those of
+    /// [`Trap::trace`], are resolved to source locations using the DWARF debug
+    /// information embedded in wasm modules.
+    ///
+    /// When enabled, [`FrameInfo::symbols`] describes the source file, line
+    /// and column of each frame, including functions which were inlined into
+    /// it. The DWARF of a module is read when it's first instantiated. This is
+    /// also enabled by [`Config::debug_info`].
+    ///
+    /// By default this option is `false`.
+    pub fn wasm_backtrace_details(&mut self, enable: bool) -> &mut Self {
+        self.wasm_backtrace_details = enable;
+        self
+    }
+
     /// Configures whether functions and loops will be interruptable via the
     /// [`Store::interrupt_handle`] method.
     ///
@@ -125,6 +212,26 @@ impl Config {
         self
     }
 
+    /// Configures whether execution of WebAssembly will "consume fuel" to
+    /// halt execution once it runs out.
+    ///
+    /// This option is similar in purpose to [`Config::interruptable`] where
+    /// you can prevent infinitely-executing WebAssembly code. The difference
+    /// is that this option allows deterministic execution of WebAssembly code
+    /// by instrumenting generated code to consume fuel as it executes. When fuel
+    /// runs out the wasm code will immediately trap with a dedicated "out of
+    /// fuel" trap.
+    ///
+    /// Note that a [`Store`] starts with no fuel, so if you enable this option
+    /// you'll have to be sure to pour some fuel into [`Store`] before
+    /// executing some code with [`Store::add_fuel`].
+    ///
+    /// By default this option is `false`.
+    pub fn consume_fuel(&mut self, enable: bool) -> &mut Self {
+        self.tunables.consume_fuel = enable;
+        self
+    }
+
     /// Configures the maximum amount of native stack space available to
     /// executing WebAssembly code.
     ///
@@ -144,24 +251,64 @@ impl Config {
         self
     }
 
+    /// Configures whether WebAssembly can be executed asynchronously, with
+    /// host functions defined by [`Func::new_async`](crate::Func::new_async)
+    /// returning futures.
+    ///
+    /// When enabled, calls into WebAssembly made through
+    /// [`Func::call_async`](crate::Func::call_async),
+    /// [`Instance::new_async`](crate::Instance::new_async) or
+    /// [`Linker::instantiate_async`](crate::Linker::instantiate_async) run on
+    /// a separately allocated native stack. When an async host function
+    /// returns a future which isn't ready yet, that stack is suspended and
+    /// the future returned by the call into WebAssembly returns
+    /// `Poll::Pending` to its executor. The WebAssembly is resumed once the
+    /// host future makes progress.
+    ///
+    /// Synchronous calls into WebAssembly, such as [`Func::call`], are still
+    /// allowed in stores with async support enabled, but any async host
+    /// function called from them will trap.
+    ///
+    /// By default this option is `false`.
+    ///
+    /// [`Func::call`]: crate::Func::call
+    #[cfg(feature = "async")]
+    pub fn async_support(&mut self, enable: bool) -> &mut Self {
+        self.async_support = enable;
+        self
+    }
+
+    /// Configures the size of the native stacks allocated to execute
+    /// WebAssembly asynchronously when [`Config::async_support`] is enabled.
+    ///
+    /// This stack holds both WebAssembly frames and the frames of host
+    /// functions called by WebAssembly, so it must be larger than
+    /// [`Config::max_wasm_stack`]. Asynchronous calls fail with a trap if
+    /// it isn't.
+    ///
+    /// By default this option is 2 MB.
+    #[cfg(feature = "async")]
+    pub fn async_stack_size(&mut self, size: usize) -> &mut Self {
+        self.async_stack_size = size;
+        self
+    }
+
     /// Configures whether the WebAssembly threads proposal will be enabled for
     /// compilation.
     ///
     /// The [WebAssembly threads proposal][threads] is not currently fully
-    /// standardized and is undergoing development. Additionally the support in
-    /// wasmtime itself is still being worked on. Support for this feature can
+    /// standardized and is undergoing development. Support for this feature can
     /// be enabled through this method for appropriate wasm modules.
     ///
     /// This feature gates items such as shared memories and atomic
-    /// instructions. Note that enabling the threads feature will
-    /// also enable the bulk memory feature.
+    /// instructions. Shared memories can be used by instances on several
+    /// threads through [`SharedMemory`](crate::SharedMemory). Note that
+    /// enabling the threads feature will also enable the bulk memory feature.
     ///
     /// This is `false` by default.
     ///
-    /// > **Note**: Wasmtime does not implement everything for the wasm threads
-    /// > spec at this time, so bugs, panics, and possibly segfaults should be
-    /// > expected. This should not be enabled in a production setting right
-    /// > now.
+    /// > **Note**: atomic instructions are only compiled for x86_64 and
+    /// > aarch64 at this time.
     ///
     /// [threads]: https://github.com/webassembly/threads
     pub fn wasm_threads(&mut self, enable: bool) -> &mut Self {
@@ -274,6 +421,26 @@ impl Config {
         self
     }
 
+    /// Configures whether the WebAssembly tail call proposal will be enabled
+    /// for compilation.
+    ///
+    /// The [WebAssembly tail call proposal][proposal] is not currently fully
+    /// standardized and is undergoing development. This feature gates the
+    /// `return_call` and `return_call_indirect` instructions, which call a
+    /// function while reusing the caller's stack frame.
+    ///
+    /// Note that Cranelift can't yet make tail calls to functions taking some
+    /// of their arguments on the stack, so modules that do so will fail to
+    /// compile.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/webassembly/tail-call
+    pub fn wasm_tail_call(&mut self, enable: bool) -> &mut Self {
+        self.validating_config.operator_config.enable_tail_call = enable;
+        self
+    }
+
     /// Configures which compilation strategy will be used for wasm modules.
     ///
     /// This method can be used to configure which compiler is used for wasm
@@ -365,6 +532,39 @@ impl Config {
         self
     }
 
+    /// Configures whether Cranelift uses its new, experimental x86_64 backend
+    /// to generate code.
+    ///
+    /// The new backend is incomplete. It doesn't support atomics, so the
+    /// threads proposal can't be used with it. Of the SIMD proposal, it only
+    /// supports loads, stores, constants, lane arithmetic, bitwise operations,
+    /// `splat` and `extract_lane`; shuffles, `replace_lane`, comparisons and
+    /// the remaining operations aren't supported yet. It doesn't generate the
+    /// stack maps needed by reference types either. Modules using any of these
+    /// fail to compile. Finally, it doesn't emit unwind information, so
+    /// backtraces through the code it generates may be incomplete.
+    ///
+    /// The default value for this is `false`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the `experimental_x64` feature of this
+    /// crate wasn't enabled at compile time, or if the target isn't x86_64.
+    pub fn cranelift_x64_backend(&mut self, enable: bool) -> Result<&mut Self> {
+        if !cfg!(feature = "experimental_x64") {
+            bail!("the x64 backend wasn't enabled at compile time");
+        }
+        if self.isa_flags.triple().architecture != Architecture::X86_64 {
+            bail!(
+                "the x64 backend can't generate code for target `{}`",
+                self.isa_flags.triple()
+            );
+        }
+        let val = if enable { "true" } else { "false" };
+        self.set_isa_flag("use_new_backend", val)?;
+        Ok(self)
+    }
+
     /// Allows settings another Cranelift flag defined by a flag name and value. This allows
     /// fine-tuning of Cranelift settings.
     ///
@@ -383,7 +583,7 @@ impl Config {
             match err {
                 SetError::BadName(_) => {
                     // Try the target-specific flags.
-                    self.isa_flags.set(name, value)?;
+                    self.set_isa_flag(name, value)?;
                 }
                 _ => bail!(err),
             }
@@ -435,11 +635,45 @@ impl Config {
     }
 
     /// Sets a custom memory creator
+    ///
+    /// Note that the memory creator is only used with the
+    /// [`InstanceAllocationStrategy::OnDemand`] allocation strategy, and for
+    /// memories created with [`Memory::new`](crate::Memory::new).
     pub fn with_host_memory(&mut self, mem_creator: Arc<dyn MemoryCreator>) -> &mut Self {
         self.memory_creator = Some(MemoryCreatorProxy { mem_creator });
         self
     }
 
+    /// Configures how instances, along with their linear memories and tables,
+    /// are allocated.
+    ///
+    /// For more information see the [`InstanceAllocationStrategy`]
+    /// enumeration and its documentation.
+    ///
+    /// The default value for this is `InstanceAllocationStrategy::OnDemand`.
+    ///
+    /// # Errors
+    ///
+    /// The pooling strategy reserves all of the address space for its pool
+    /// when this method is called, and an error is returned if the limits are
+    /// invalid or the address space couldn't be reserved.
+    pub fn allocation_strategy(
+        &mut self,
+        strategy: InstanceAllocationStrategy,
+    ) -> Result<&mut Self> {
+        self.instance_allocator = match strategy {
+            InstanceAllocationStrategy::OnDemand => None,
+            InstanceAllocationStrategy::Pooling {
+                module_limits,
+                instance_limits,
+            } => Some(Arc::new(
+                PoolingInstanceAllocator::new(module_limits, instance_limits)
+                    .map_err(anyhow::Error::msg)?,
+            )),
+        };
+        Ok(self)
+    }
+
     /// Configures the maximum size, in bytes, where a linear memory is
     /// considered static, above which it'll be considered dynamic.
     ///
@@ -615,13 +849,40 @@ impl Config {
             .finish(settings::Flags::new(self.flags.clone()))
     }
 
-    fn build_compiler(&self) -> Compiler {
+    fn build_compiler(&self, allocator: &dyn InstanceAllocator) -> Compiler {
         let isa = self.target_isa();
+        let mut tunables = self.tunables.clone();
+        allocator.adjust_tunables(&mut tunables);
+        let features = &self.validating_config.operator_config;
+        let features = WasmFeatures {
+            threads: features.enable_threads,
+            reference_types: features.enable_reference_types,
+            simd: features.enable_simd,
+            bulk_memory: features.enable_bulk_memory,
+            multi_value: features.enable_multi_value,
+            tail_call: features.enable_tail_call,
+        };
         Compiler::new(
             isa,
             self.strategy,
             self.cache_config.clone(),
-            self.tunables.clone(),
+            tunables,
+            features,
+        )
+    }
+
+    fn build_allocator(&self) -> Arc<dyn InstanceAllocator> {
+        match &self.instance_allocator {
+            Some(allocator) => allocator.clone(),
+            None => Arc::new(self.build_on_demand_allocator()),
+        }
+    }
+
+    fn build_on_demand_allocator(&self) -> OnDemandInstanceAllocator {
+        OnDemandInstanceAllocator::new(
+            self.memory_creator
+                .clone()
+                .map(|c| Arc::new(c) as Arc<dyn RuntimeMemoryCreator>),
         )
     }
 }
@@ -643,8 +904,13 @@ impl Default for Config {
 impl fmt::Debug for Config {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let features = &self.validating_config.operator_config;
-        f.debug_struct("Config")
-            .field("debug_info", &self.tunables.debug_info)
+        let mut f = f.debug_struct("Config");
+        f.field("debug_info", &self.tunables.debug_info)
+            .field("wasm_backtrace_details", &self.wasm_backtrace_details)
+            .field("consume_fuel", &self.tunables.consume_fuel);
+        #[cfg(feature = "async")]
+        f.field("async_support", &self.async_support);
+        f.field("pooling_allocator", &self.instance_allocator.is_some())
             .field("strategy", &self.strategy)
             .field("wasm_threads", &features.enable_threads)
             .field("wasm_reference_types", &features.enable_reference_types)
@@ -654,8 +920,8 @@ impl fmt::Debug for Config {
             .field(
                 "flags",
                 &settings::Flags::new(self.flags.clone()).to_string(),
-            )
-            .finish()
+            );
+        f.finish()
     }
 }
 
@@ -702,6 +968,43 @@ pub enum OptLevel {
     SpeedAndSize,
 }
 
+/// Possible strategies for allocating instances, along with their linear
+/// memories and tables.
+///
+/// This is used as an argument to the [`Config::allocation_strategy`] method.
+#[derive(Clone, Debug)]
+pub enum InstanceAllocationStrategy {
+    /// Allocate everything when an instance is created, and free it again when
+    /// the instance's store is dropped.
+    ///
+    /// This is the default strategy, which imposes no limits on the modules
+    /// being instantiated.
+    OnDemand,
+
+    /// Allocate instances from pools of address space reserved up front.
+    ///
+    /// Every instance, memory and table is given a fixed-size slot in a pool,
+    /// which is reset and reused once its store is dropped. This makes
+    /// instantiation much cheaper, at the cost of limiting the modules which
+    /// can be instantiated by `module_limits`. Modules exceeding those limits
+    /// fail to compile, and instantiation fails once `instance_limits.count`
+    /// instances are alive at the same time.
+    ///
+    /// Only `funcref` tables are supported by this strategy.
+    Pooling {
+        /// The limits on modules instantiated with this strategy.
+        module_limits: ModuleLimits,
+        /// The limits on the pool of instances.
+        instance_limits: InstanceLimits,
+    },
+}
+
+impl Default for InstanceAllocationStrategy {
+    fn default() -> Self {
+        InstanceAllocationStrategy::OnDemand
+    }
+}
+
 /// Select which profiling technique to support.
 #[derive(Debug, Clone, Copy)]
 pub enum ProfilingStrategy {
@@ -748,6 +1051,8 @@ pub struct Engine {
 struct EngineInner {
     config: Config,
     compiler: Compiler,
+    allocator: Arc<dyn InstanceAllocator>,
+    on_demand_allocator: OnDemandInstanceAllocator,
 }
 
 impl Engine {
@@ -755,10 +1060,13 @@ impl Engine {
     /// configuration settings.
     pub fn new(config: &Config) -> Engine {
         debug_builtins::ensure_exported();
+        let allocator = config.build_allocator();
         Engine {
             inner: Arc::new(EngineInner {
                 config: config.clone(),
-                compiler: config.build_compiler(),
+                compiler: config.build_compiler(&*allocator),
+                allocator,
+                on_demand_allocator: config.build_on_demand_allocator(),
             }),
         }
     }
@@ -772,6 +1080,50 @@ impl Engine {
         &self.inner.compiler
     }
 
+    /// Returns the allocator used to instantiate modules.
+    pub(crate) fn allocator(&self) -> &dyn InstanceAllocator {
+        &*self.inner.allocator
+    }
+
+    /// Returns the allocator used for instances created by the host, which
+    /// always allocates on demand.
+    pub(crate) fn on_demand_allocator(&self) -> &dyn InstanceAllocator {
+        &self.inner.on_demand_allocator
+    }
+
+    /// Ahead-of-time compiles a WebAssembly module for this engine, without
+    /// making its code executable.
+    ///
+    /// The returned bytes can later be turned into a [`Module`] with
+    /// [`Module::deserialize`], skipping translation and compilation entirely.
+    /// This is useful for shipping precompiled modules as build artifacts or
+    /// for avoiding recompiling the same module every time a process starts.
+    ///
+    /// The output records the version of Wasmtime, the compilation target and
+    /// the configuration of this engine, and it can only be deserialized by an
+    /// [`Engine`] with a matching configuration. Like [`Module::new`], `bytes`
+    /// may be in the text format if the `wat` feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let engine = Engine::default();
+    /// let bytes = engine.precompile_module(b"(module (func (export \"f\")))")?;
+    ///
+    /// let module = unsafe { Module::deserialize(&engine, &bytes)? };
+    /// assert!(module.get_export("f").is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn precompile_module(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes)?;
+        Module::validate(self, &bytes)?;
+        Ok(CompiledModule::precompile(self.compiler(), &bytes)?)
+    }
+
     /// Returns whether the engine `a` and `b` refer to the same configuration.
     pub fn same(a: &Engine, b: &Engine) -> bool {
         Arc::ptr_eq(&a.inner, &b.inner)
@@ -798,6 +1150,20 @@ impl Default for Engine {
 /// entirely new store, but rather just a new reference to the existing object.
 /// In other words it's a shallow copy, not a deep copy.
 ///
+/// # Stores and threads
+///
+/// A `Store`, along with everything that belongs to it such as instances and
+/// functions, can be sent to other threads. For example an instance can be
+/// created on one thread and its functions called on another, which allows
+/// stores to be driven by executors that move work between threads.
+///
+/// A store can only be used by one thread at a time, however. A thread is
+/// using a store while it's executing wasm in it, including any host
+/// functions that wasm calls, or while it's working with the store's
+/// instances, globals, tables and memories. Attempting to use a store from a
+/// second thread during that time panics. For this reason `Store`, and
+/// everything belonging to it, is `Send` but not `Sync`.
+///
 /// ## Stores and `Default`
 ///
 /// You can create a store with default configuration settings using
@@ -805,21 +1171,83 @@ impl Default for Engine {
 /// ocnfiguration (see [`Config`] for more information).
 #[derive(Clone)]
 pub struct Store {
-    inner: Rc<StoreInner>,
+    inner: Arc<StoreInner>,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+// The state in `StoreInner` which isn't synchronized is only accessed by the
+// thread which has entered the store, and `Store::enter` panics if another
+// thread already has. So a `Store` can be sent to another thread, even though
+// clones of it may remain on this one.
+unsafe impl Send for Store {}
+
+/// A weak reference to a [`Store`], held by values which mustn't keep their
+/// store alive.
+#[derive(Clone)]
+pub(crate) struct WeakStore(Weak<StoreInner>);
+
+// The store can only be accessed by upgrading a `WeakStore` to a `Store`,
+// which is subject to the same checks as any other `Store`.
+unsafe impl Send for WeakStore {}
+unsafe impl Sync for WeakStore {}
+
+impl WeakStore {
+    pub(crate) fn ptr_eq(&self, other: &WeakStore) -> bool {
+        Weak::ptr_eq(&self.0, &other.0)
+    }
 }
 
 pub(crate) struct StoreInner {
     engine: Engine,
     interrupts: Arc<VMInterrupts>,
-    signatures: RefCell<SignatureRegistry>,
-    instances: RefCell<Vec<InstanceHandle>>,
+    signatures: Mutex<SignatureRegistry>,
+    instances: Mutex<Vec<StoreInstance>>,
+    host_info: Mutex<HashMap<HostInfoKey, Arc<Mutex<dyn Any + Send>>>>,
+    resources: ResourceTracker,
+    /// The thread which has entered this store with `Store::enter`, as
+    /// returned by `current_thread`, or zero if no thread has.
+    entered_by: AtomicUsize,
+    entered: EnteredState,
+}
+
+/// The state of a `Store` which is only accessed by the thread that has
+/// entered it, and which therefore doesn't need any synchronization.
+struct EnteredState {
+    /// How many times the thread using this store has entered it.
+    depth: Cell<usize>,
     signal_handler: RefCell<Option<Box<SignalHandler<'static>>>>,
     jit_code_ranges: RefCell<Vec<(usize, usize)>>,
-    host_info: RefCell<HashMap<HostInfoKey, Rc<RefCell<dyn Any>>>>,
-    externref_activations_table: Rc<VMExternRefActivationsTable>,
-    stack_map_registry: Rc<StackMapRegistry>,
+    externref_activations_table: VMExternRefActivationsTable,
+    stack_map_registry: StackMapRegistry,
+    /// An adjustment to add to the fuel consumed value in `interrupts` above
+    /// to get the true amount of fuel consumed.
+    fuel_adj: Cell<i64>,
+    /// The suspension point of the fiber currently executing wasm for an
+    /// asynchronous call, or null if there isn't one.
+    #[cfg(feature = "async")]
+    current_suspend: Cell<*const wasmtime_fiber::Suspend<Result<(), Trap>, (), Result<(), Trap>>>,
+    /// The context of the executor currently polling the future of an
+    /// asynchronous call, or null if there isn't one.
+    #[cfg(feature = "async")]
+    current_poll_cx: Cell<*mut Context<'static>>,
 }
 
+// Signal handlers are required to be `Send` and the raw pointers are only set
+// while a call is on the stack, so this state can move to another thread
+// along with its store.
+unsafe impl Send for EnteredState {}
+
+struct StoreInstance {
+    handle: InstanceHandle,
+    /// Whether the instance was created by the host with the on-demand
+    /// allocator, rather than with the engine's allocator.
+    ondemand: bool,
+}
+
+// Instances are only used by the thread which has entered their store, and
+// the host state and memories they own are all required to be `Send`.
+unsafe impl Send for StoreInstance {}
+
 struct HostInfoKey(VMExternRef);
 
 impl PartialEq for HostInfoKey {
@@ -842,6 +1270,23 @@ impl Hash for HostInfoKey {
 impl Store {
     /// Creates a new store to be associated with the given [`Engine`].
     pub fn new(engine: &Engine) -> Store {
+        Store::new_with_resources(engine, ResourceTracker::default())
+    }
+
+    /// Creates a new store to be associated with the given [`Engine`] whose
+    /// use of resources is limited by `limiter`.
+    ///
+    /// The limiter is consulted whenever a memory, table or instance is
+    /// created in the store and whenever a memory or table grows. See
+    /// [`ResourceLimiter`] for more information.
+    pub fn new_with_limiter(engine: &Engine, limiter: impl ResourceLimiter) -> Store {
+        Store::new_with_resources(
+            engine,
+            ResourceTracker::new(Some(Box::new(ResourceLimiterProxy(limiter)))),
+        )
+    }
+
+    fn new_with_resources(engine: &Engine, resources: ResourceTracker) -> Store {
         // Ensure that wasmtime_runtime's signal handlers are configured. Note
         // that at the `Store` level it means we should perform this
         // once-per-thread. Platforms like Unix, however, only require this
@@ -849,23 +1294,34 @@ impl Store {
         // each one that's not relevant just won't do anything.
         wasmtime_runtime::init_traps();
 
-        Store {
-            inner: Rc::new(StoreInner {
-                engine: engine.clone(),
-                interrupts: Arc::new(Default::default()),
-                signatures: RefCell::new(Default::default()),
-                instances: RefCell::new(Vec::new()),
+        Store::from_inner(Arc::new(StoreInner {
+            engine: engine.clone(),
+            interrupts: Arc::new(Default::default()),
+            signatures: Mutex::new(Default::default()),
+            instances: Mutex::new(Vec::new()),
+            host_info: Mutex::new(HashMap::new()),
+            resources,
+            entered_by: AtomicUsize::new(0),
+            entered: EnteredState {
+                depth: Cell::new(0),
                 signal_handler: RefCell::new(None),
                 jit_code_ranges: RefCell::new(Vec::new()),
-                host_info: RefCell::new(HashMap::new()),
-                externref_activations_table: Rc::new(VMExternRefActivationsTable::new()),
-                stack_map_registry: Rc::new(StackMapRegistry::default()),
-            }),
-        }
+                externref_activations_table: VMExternRefActivationsTable::new(),
+                stack_map_registry: StackMapRegistry::default(),
+                fuel_adj: Cell::new(0),
+                #[cfg(feature = "async")]
+                current_suspend: Cell::new(ptr::null()),
+                #[cfg(feature = "async")]
+                current_poll_cx: Cell::new(ptr::null_mut()),
+            },
+        }))
     }
 
-    pub(crate) fn from_inner(inner: Rc<StoreInner>) -> Store {
-        Store { inner }
+    pub(crate) fn from_inner(inner: Arc<StoreInner>) -> Store {
+        Store {
+            inner,
+            _not_sync: PhantomData,
+        }
     }
 
     /// Returns the [`Engine`] that this store is associated with.
@@ -873,19 +1329,38 @@ impl Store {
         &self.inner.engine
     }
 
-    /// Returns an optional reference to a ['RuntimeMemoryCreator']
-    pub(crate) fn memory_creator(&self) -> Option<&dyn RuntimeMemoryCreator> {
-        self.engine()
-            .config()
-            .memory_creator
-            .as_ref()
-            .map(|x| x as _)
+    /// Marks the current thread as the one using this store until the
+    /// returned guard is dropped.
+    ///
+    /// Everything which touches the state of this store or of its instances
+    /// enters the store first, which the same thread may do recursively, for
+    /// example when wasm calls a host function which calls back into wasm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread has entered this store.
+    pub(crate) fn enter(&self) -> StoreEntry<'_> {
+        let thread = current_thread();
+        match self.inner.entered_by.compare_exchange(
+            0,
+            thread,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => {}
+            Err(owner) if owner == thread => {}
+            Err(_) => panic!("a `Store` can only be used from one thread at a time"),
+        }
+        let depth = &self.inner.entered.depth;
+        depth.set(depth.get() + 1);
+        StoreEntry { inner: &self.inner }
     }
 
     pub(crate) fn lookup_signature(&self, sig_index: VMSharedSignatureIndex) -> wasm::WasmFuncType {
         self.inner
             .signatures
-            .borrow()
+            .lock()
+            .unwrap()
             .lookup_wasm(sig_index)
             .expect("failed to lookup signature")
     }
@@ -897,18 +1372,20 @@ impl Store {
     ) -> VMSharedSignatureIndex {
         self.inner
             .signatures
-            .borrow_mut()
+            .lock()
+            .unwrap()
             .register(wasm_sig, native)
     }
 
-    pub(crate) fn signatures_mut(&self) -> std::cell::RefMut<'_, SignatureRegistry> {
-        self.inner.signatures.borrow_mut()
+    pub(crate) fn signatures_mut(&self) -> MutexGuard<'_, SignatureRegistry> {
+        self.inner.signatures.lock().unwrap()
     }
 
     /// Returns whether or not the given address falls within the JIT code
     /// managed by the compiler
     pub(crate) fn is_in_jit_code(&self, addr: usize) -> bool {
         self.inner
+            .entered
             .jit_code_ranges
             .borrow()
             .iter()
@@ -924,7 +1401,7 @@ impl Store {
                 if !self.is_in_jit_code(first.0) {
                     // The range is not registered -- add all ranges (including
                     // first one) to the jit_code_ranges.
-                    let mut jit_code_ranges = self.inner.jit_code_ranges.borrow_mut();
+                    let mut jit_code_ranges = self.inner.entered.jit_code_ranges.borrow_mut();
                     jit_code_ranges.push(first);
                     jit_code_ranges.extend(ranges);
                 }
@@ -950,8 +1427,20 @@ impl Store {
         );
     }
 
-    pub(crate) unsafe fn add_instance(&self, handle: InstanceHandle) -> StoreInstanceHandle {
-        self.inner.instances.borrow_mut().push(handle.clone());
+    /// Registers `handle` with this store, which deallocates it once the store
+    /// is dropped.
+    ///
+    /// The `ondemand` flag indicates that the instance was allocated with the
+    /// engine's on-demand allocator rather than its configured allocator.
+    pub(crate) unsafe fn add_instance(
+        &self,
+        handle: InstanceHandle,
+        ondemand: bool,
+    ) -> StoreInstanceHandle {
+        self.inner.instances.lock().unwrap().push(StoreInstance {
+            handle: handle.clone(),
+            ondemand,
+        });
         StoreInstanceHandle {
             store: self.clone(),
             handle,
@@ -962,43 +1451,44 @@ impl Store {
         debug_assert!(self
             .inner
             .instances
-            .borrow()
+            .lock()
+            .unwrap()
             .iter()
-            .any(|i| i.vmctx_ptr() == handle.vmctx_ptr()));
+            .any(|i| i.handle.vmctx_ptr() == handle.vmctx_ptr()));
         StoreInstanceHandle {
             store: self.clone(),
             handle,
         }
     }
 
-    pub(crate) fn weak(&self) -> Weak<StoreInner> {
-        Rc::downgrade(&self.inner)
+    pub(crate) fn weak(&self) -> WeakStore {
+        WeakStore(Arc::downgrade(&self.inner))
     }
 
-    pub(crate) fn upgrade(weak: &Weak<StoreInner>) -> Option<Self> {
-        let inner = weak.upgrade()?;
-        Some(Self { inner })
+    pub(crate) fn upgrade(weak: &WeakStore) -> Option<Self> {
+        let inner = weak.0.upgrade()?;
+        Some(Self::from_inner(inner))
     }
 
-    pub(crate) fn host_info(&self, externref: &ExternRef) -> Option<Rc<RefCell<dyn Any>>> {
+    pub(crate) fn host_info(&self, externref: &ExternRef) -> Option<Arc<Mutex<dyn Any + Send>>> {
         debug_assert!(
-            std::rc::Weak::ptr_eq(&self.weak(), &externref.store),
+            self.weak().ptr_eq(&externref.store),
             "externref must be from this store"
         );
-        let infos = self.inner.host_info.borrow();
+        let infos = self.inner.host_info.lock().unwrap();
         infos.get(&HostInfoKey(externref.inner.clone())).cloned()
     }
 
     pub(crate) fn set_host_info(
         &self,
         externref: &ExternRef,
-        info: Option<Rc<RefCell<dyn Any>>>,
-    ) -> Option<Rc<RefCell<dyn Any>>> {
+        info: Option<Arc<Mutex<dyn Any + Send>>>,
+    ) -> Option<Arc<Mutex<dyn Any + Send>>> {
         debug_assert!(
-            std::rc::Weak::ptr_eq(&self.weak(), &externref.store),
+            self.weak().ptr_eq(&externref.store),
             "externref must be from this store"
         );
-        let mut infos = self.inner.host_info.borrow_mut();
+        let mut infos = self.inner.host_info.lock().unwrap();
         if let Some(info) = info {
             infos.insert(HostInfoKey(externref.inner.clone()), info)
         } else {
@@ -1007,13 +1497,13 @@ impl Store {
     }
 
     pub(crate) fn signal_handler(&self) -> std::cell::Ref<'_, Option<Box<SignalHandler<'static>>>> {
-        self.inner.signal_handler.borrow()
+        self.inner.entered.signal_handler.borrow()
     }
 
     pub(crate) fn signal_handler_mut(
         &self,
     ) -> std::cell::RefMut<'_, Option<Box<SignalHandler<'static>>>> {
-        self.inner.signal_handler.borrow_mut()
+        self.inner.entered.signal_handler.borrow_mut()
     }
 
     pub(crate) fn interrupts(&self) -> &Arc<VMInterrupts> {
@@ -1027,7 +1517,25 @@ impl Store {
     /// to the same underlying storage, and this method can be used to determine
     /// whether two stores are indeed the same.
     pub fn same(a: &Store, b: &Store) -> bool {
-        Rc::ptr_eq(&a.inner, &b.inner)
+        Arc::ptr_eq(&a.inner, &b.inner)
+    }
+
+    /// Captures a backtrace of the WebAssembly frames of this store which are
+    /// currently on the stack of this thread, innermost first.
+    ///
+    /// This is most useful from within host functions called by wasm, for
+    /// example to check which module is calling or to attribute time spent in
+    /// the host to its callers. The frames are described in the same way as
+    /// those of [`Trap::trace`].
+    ///
+    /// Returns an empty list if no wasm of this store is executing.
+    pub fn capture_backtrace(&self) -> Vec<FrameInfo> {
+        let _entered = self.enter();
+        let native_trace = Backtrace::new_unresolved();
+        FRAME_INFO
+            .read()
+            .unwrap()
+            .lookup_backtrace(&native_trace, None, |pc| self.is_in_jit_code(pc))
     }
 
     /// Creates an [`InterruptHandle`] which can be used to interrupt the
@@ -1121,23 +1629,243 @@ impl Store {
         }
     }
 
-    pub(crate) fn externref_activations_table(&self) -> &Rc<VMExternRefActivationsTable> {
-        &self.inner.externref_activations_table
+    /// Returns the total size in bytes of the linear memories created in this
+    /// store, including memories defined by the host.
+    pub fn memory_bytes(&self) -> usize {
+        self.inner.resources.memory_bytes()
+    }
+
+    /// Returns the total number of elements of the tables created in this
+    /// store, including tables defined by the host.
+    pub fn table_elements(&self) -> usize {
+        self.inner.resources.table_elements()
     }
 
-    pub(crate) fn stack_map_registry(&self) -> &Rc<StackMapRegistry> {
-        &self.inner.stack_map_registry
+    /// Returns the number of instances created in this store, including the
+    /// instances created by the host for functions, globals, tables and
+    /// memories defined with this API.
+    pub fn instance_count(&self) -> usize {
+        self.inner.resources.instances()
+    }
+
+    pub(crate) fn resources(&self) -> &ResourceTracker {
+        &self.inner.resources
+    }
+
+    pub(crate) fn externref_activations_table(&self) -> &VMExternRefActivationsTable {
+        &self.inner.entered.externref_activations_table
+    }
+
+    pub(crate) fn stack_map_registry(&self) -> &StackMapRegistry {
+        &self.inner.entered.stack_map_registry
+    }
+
+    /// Returns the amount of fuel consumed by this store's execution so far.
+    ///
+    /// If fuel consumption is not enabled via
+    /// [`Config::consume_fuel`] then this function will return `None`. Also
+    /// note that fuel, if enabled, must be originally configured via
+    /// [`Store::add_fuel`].
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        if !self.engine().config().tunables.consume_fuel {
+            return None;
+        }
+        let _entered = self.enter();
+        let consumed = unsafe { *self.inner.interrupts.fuel_consumed.get() };
+        Some(u64::try_from(self.inner.entered.fuel_adj.get() + consumed).unwrap())
+    }
+
+    /// Adds fuel to this [`Store`] for wasm to consume while executing.
+    ///
+    /// For this method to work fuel consumption must be enabled via
+    /// [`Config::consume_fuel`]. By default a [`Store`] starts with 0 fuel
+    /// for wasm to execute with (meaning it will immediately trap).
+    /// This function must be called for the store to have
+    /// some fuel to allow WebAssembly to execute.
+    ///
+    /// Note that at this time when fuel is entirely consumed it will cause
+    /// wasm to trap. More usages of fuel are planned for the future.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if fuel consumption is not enabled
+    /// via [`Config::consume_fuel`].
+    pub fn add_fuel(&self, fuel: u64) -> Result<()> {
+        anyhow::ensure!(
+            self.engine().config().tunables.consume_fuel,
+            "fuel is not configured in this store"
+        );
+
+        // Fuel is stored as an i64, so we need to cast it. If the provided fuel
+        // value overflows that just assume that i64::max will suffice. Wasm
+        // execution isn't fast enough to burn through i64::max fuel in any
+        // reasonable amount of time anyway.
+        let fuel = i64::try_from(fuel).unwrap_or(i64::max_value());
+        let _entered = self.enter();
+        let fuel_adj = &self.inner.entered.fuel_adj;
+        let adj = fuel_adj.get();
+        let consumed_ptr = unsafe { &mut *self.inner.interrupts.fuel_consumed.get() };
+
+        match (consumed_ptr.checked_sub(fuel), adj.checked_add(fuel)) {
+            // If we succesfully did arithmetic without overflowing then we can
+            // just update our fields.
+            (Some(consumed), Some(adj)) => {
+                fuel_adj.set(adj);
+                *consumed_ptr = consumed;
+            }
+
+            // Otherwise something overflowed. Make sure that we preserve the
+            // amount of fuel that's already consumed, but otherwise assume that
+            // we were given infinite fuel.
+            _ => {
+                fuel_adj.set(i64::max_value());
+                *consumed_ptr = (*consumed_ptr + adj) - i64::max_value();
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn async_support(&self) -> bool {
+        self.engine().config().async_support
+    }
+
+    /// Executes `func` on a newly allocated native stack, returning a future
+    /// which resolves once `func` finishes.
+    ///
+    /// While executing on that stack `func` may call `block_on` to wait for
+    /// a future, which suspends the stack and makes the returned future
+    /// pending until the awaited future is ready.
+    #[cfg(feature = "async")]
+    pub(crate) async fn on_fiber<R>(&self, func: impl FnOnce() -> R) -> Result<R, Trap> {
+        debug_assert!(self.async_support());
+        let config = self.engine().config();
+        if config.async_stack_size <= config.max_wasm_stack {
+            return Err(Trap::new(
+                "async stack size must be larger than the maximum wasm stack size",
+            ));
+        }
+
+        let mut slot = None;
+        let fiber = wasmtime_fiber::Fiber::new(config.async_stack_size, |keep_going, suspend| {
+            // The future may have been dropped before the fiber ever started,
+            // in which case we don't run anything at all.
+            keep_going?;
+
+            let current_suspend = &self.inner.entered.current_suspend;
+            let _reset = Reset(current_suspend, current_suspend.replace(suspend));
+            slot = Some(func());
+            Ok(())
+        })
+        .map_err(|e| Trap::from(anyhow::Error::from(e)))?;
+
+        FiberFuture { fiber, store: self }.await?;
+        return Ok(slot.unwrap());
+
+        /// Drives a fiber's execution by resuming it whenever the future is
+        /// polled.
+        struct FiberFuture<'a> {
+            fiber: wasmtime_fiber::Fiber<'a, Result<(), Trap>, (), Result<(), Trap>>,
+            store: &'a Store,
+        }
+
+        impl Future for FiberFuture<'_> {
+            type Output = Result<(), Trap>;
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+                // Make the executor's context available to `block_on` for the
+                // duration of this poll. Its lifetime is erased here, but it's
+                // only ever used while this function is on the stack.
+                let cx = cx as *mut Context<'_> as *mut Context<'static>;
+                let current_poll_cx = &self.store.inner.entered.current_poll_cx;
+                let _reset = Reset(current_poll_cx, current_poll_cx.replace(cx));
+                unsafe {
+                    let tls = wasmtime_runtime::TlsRestore::take();
+                    let result = self.fiber.resume(Ok(()));
+                    tls.replace();
+                    match result {
+                        Ok(ret) => Poll::Ready(ret),
+                        Err(()) => Poll::Pending,
+                    }
+                }
+            }
+        }
+
+        impl Drop for FiberFuture<'_> {
+            fn drop(&mut self) {
+                if self.fiber.done() {
+                    return;
+                }
+                // The future was dropped while the wasm it was executing was
+                // suspended. Resume it with an error so that it unwinds and
+                // the fiber finishes, releasing everything on its stack.
+                unsafe {
+                    let tls = wasmtime_runtime::TlsRestore::take();
+                    let result = self.fiber.resume(Err(Trap::new("future dropped")));
+                    tls.replace();
+                    debug_assert!(result.is_ok());
+                }
+            }
+        }
+    }
+
+    /// Blocks the wasm executing on the current fiber until `future` is
+    /// ready, returning its output.
+    ///
+    /// While the future is pending the fiber is suspended, returning control
+    /// to the executor polling the future created by `on_fiber`. Returns a
+    /// trap if there's no such fiber, for example because this was reached
+    /// through a synchronous call into wasm, or if the asynchronous call was
+    /// dropped while suspended.
+    #[cfg(feature = "async")]
+    pub(crate) fn block_on<T>(
+        &self,
+        mut future: Pin<&mut dyn Future<Output = T>>,
+    ) -> Result<T, Trap> {
+        // Take the suspension point of the current fiber and keep it on our
+        // own stack frame. While polling `future` other asynchronous calls may
+        // start their own fibers, and they'll configure this field for
+        // themselves.
+        let entered = &self.inner.entered;
+        let suspend = entered.current_suspend.replace(ptr::null());
+        let _reset = Reset(&entered.current_suspend, suspend);
+        if suspend.is_null() {
+            return Err(Trap::new(
+                "async host functions can only be called from asynchronous calls into wasm",
+            ));
+        }
+
+        loop {
+            let future_result = unsafe {
+                let poll_cx = entered.current_poll_cx.replace(ptr::null_mut());
+                let _reset = Reset(&entered.current_poll_cx, poll_cx);
+                assert!(!poll_cx.is_null());
+                future.as_mut().poll(&mut *poll_cx)
+            };
+            if let Poll::Ready(t) = future_result {
+                return Ok(t);
+            }
+
+            unsafe {
+                let tls = wasmtime_runtime::TlsRestore::take();
+                let res = (*suspend).suspend(());
+                tls.replace();
+                res?;
+            }
+        }
     }
 
     /// Perform garbage collection of `ExternRef`s.
     pub fn gc(&self) {
         // For this crate's API, we ensure that `set_stack_canary` invariants
         // are upheld for all host-->Wasm calls, and we register every module
-        // used with this store in `self.inner.stack_map_registry`.
+        // used with this store in its `stack_map_registry`.
+        let _entered = self.enter();
         unsafe {
             wasmtime_runtime::gc(
-                &*self.inner.stack_map_registry,
-                &*self.inner.externref_activations_table,
+                self.stack_map_registry(),
+                self.externref_activations_table(),
             );
         }
     }
@@ -1158,9 +1886,15 @@ impl fmt::Debug for Store {
 
 impl Drop for StoreInner {
     fn drop(&mut self) {
-        for instance in self.instances.get_mut().iter() {
+        let allocator = self.engine.allocator();
+        let on_demand = self.engine.on_demand_allocator();
+        for instance in self.instances.get_mut().unwrap().iter() {
             unsafe {
-                instance.dealloc();
+                if instance.ondemand {
+                    on_demand.deallocate(&instance.handle);
+                } else {
+                    allocator.deallocate(&instance.handle);
+                }
             }
         }
     }
@@ -1187,11 +1921,54 @@ impl InterruptHandle {
     }
 }
 
+/// A guard which keeps a [`Store`] entered by the current thread, created by
+/// `Store::enter`.
+pub(crate) struct StoreEntry<'a> {
+    inner: &'a StoreInner,
+}
+
+impl Drop for StoreEntry<'_> {
+    fn drop(&mut self) {
+        let depth = &self.inner.entered.depth;
+        depth.set(depth.get() - 1);
+        if depth.get() == 0 {
+            self.inner.entered_by.store(0, Ordering::Release);
+        }
+    }
+}
+
+/// Returns a nonzero number identifying the current thread among all running
+/// threads, which is the address of a thread-local since that's cheaper to
+/// get than a `ThreadId`.
+fn current_thread() -> usize {
+    thread_local!(static ID: u8 = 0);
+    ID.with(|id| id as *const u8 as usize)
+}
+
+#[cfg(feature = "async")]
+struct Reset<'a, T: Copy>(&'a Cell<T>, T);
+
+#[cfg(feature = "async")]
+impl<T: Copy> Drop for Reset<'_, T> {
+    fn drop(&mut self) {
+        self.0.set(self.1);
+    }
+}
+
 fn _assert_send_sync() {
     fn _assert<T: Send + Sync>() {}
     _assert::<Engine>();
     _assert::<Config>();
     _assert::<InterruptHandle>();
+    _assert::<ExternRef>();
+}
+
+fn _assert_send() {
+    fn _assert<T: Send>() {}
+    _assert::<Store>();
+    _assert::<crate::Instance>();
+    _assert::<crate::Func>();
+    _assert::<crate::Extern>();
 }
 
 #[cfg(test)]
@@ -10,8 +10,8 @@ use wasmtime_environ::entity::PrimaryMap;
 use wasmtime_environ::wasm::DefinedFuncIndex;
 use wasmtime_environ::Module;
 use wasmtime_runtime::{
-    Imports, InstanceHandle, StackMapRegistry, VMExternRefActivationsTable, VMFunctionBody,
-    VMSharedSignatureIndex, VMTrampoline,
+    Imports, InstanceAllocationRequest, InstanceAllocator, StackMapRegistry,
+    VMExternRefActivationsTable, VMFunctionBody, VMSharedSignatureIndex, VMTrampoline,
 };
 
 pub(crate) fn create_handle(
@@ -19,7 +19,27 @@ pub(crate) fn create_handle(
     store: &Store,
     finished_functions: PrimaryMap<DefinedFuncIndex, *mut [VMFunctionBody]>,
     trampolines: HashMap<VMSharedSignatureIndex, VMTrampoline>,
-    state: Box<dyn Any>,
+    state: Box<dyn Any + Send>,
+) -> Result<StoreInstanceHandle> {
+    create_handle_with_allocator(
+        store.engine().on_demand_allocator(),
+        module,
+        store,
+        finished_functions,
+        trampolines,
+        state,
+    )
+}
+
+/// Like `create_handle`, but allocates the instance with `allocator`, which
+/// must deallocate instances like the engine's on-demand allocator does.
+pub(crate) fn create_handle_with_allocator(
+    allocator: &dyn InstanceAllocator,
+    module: Module,
+    store: &Store,
+    finished_functions: PrimaryMap<DefinedFuncIndex, *mut [VMFunctionBody]>,
+    trampolines: HashMap<VMSharedSignatureIndex, VMTrampoline>,
+    state: Box<dyn Any + Send>,
 ) -> Result<StoreInstanceHandle> {
     let imports = Imports::new(
         PrimaryMap::new(),
@@ -37,19 +57,21 @@ pub(crate) fn create_handle(
         .collect::<PrimaryMap<_, _>>();
 
     unsafe {
-        let handle = InstanceHandle::new(
-            Arc::new(module),
-            Arc::new(()),
-            finished_functions.into_boxed_slice(),
+        let handle = allocator.allocate(InstanceAllocationRequest {
+            module: Arc::new(module),
+            code: Arc::new(()),
+            finished_functions: finished_functions.into_boxed_slice(),
             trampolines,
             imports,
-            store.memory_creator(),
-            signatures.into_boxed_slice(),
-            state,
-            store.interrupts().clone(),
-            &**store.externref_activations_table() as *const VMExternRefActivationsTable as *mut _,
-            &**store.stack_map_registry() as *const StackMapRegistry as *mut _,
-        )?;
-        Ok(store.add_instance(handle))
+            shared_signatures: signatures.into_boxed_slice(),
+            host_state: state,
+            interrupts: store.interrupts().clone(),
+            externref_activations_table: store.externref_activations_table()
+                as *const VMExternRefActivationsTable
+                as *mut _,
+            stack_map_registry: store.stack_map_registry() as *const StackMapRegistry as *mut _,
+            resources: store.resources(),
+        })?;
+        Ok(store.add_instance(handle, true))
     }
 }
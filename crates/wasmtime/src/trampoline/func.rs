@@ -22,7 +22,7 @@ use wasmtime_jit::{native, CodeMemory};
 use wasmtime_runtime::{InstanceHandle, VMContext, VMFunctionBody, VMTrampoline};
 
 struct TrampolineState {
-    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap>>,
+    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap> + Send>,
     #[allow(dead_code)]
     code_memory: CodeMemory,
 }
@@ -205,7 +205,7 @@ fn make_trampoline(
 
 pub fn create_handle_with_function(
     ft: &FuncType,
-    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap>>,
+    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap> + Send>,
     store: &Store,
 ) -> Result<(StoreInstanceHandle, VMTrampoline)> {
     let isa = store.engine().config().target_isa();
@@ -268,7 +268,7 @@ pub unsafe fn create_handle_with_raw_function(
     func: *mut [VMFunctionBody],
     trampoline: VMTrampoline,
     store: &Store,
-    state: Box<dyn Any>,
+    state: Box<dyn Any + Send>,
 ) -> Result<StoreInstanceHandle> {
     let isa = {
         let isa_builder = native::builder();
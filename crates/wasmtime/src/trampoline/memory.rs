@@ -1,4 +1,4 @@
-use super::create_handle::create_handle;
+use super::create_handle::{create_handle, create_handle_with_allocator};
 use crate::externals::{LinearMemory, MemoryCreator};
 use crate::trampoline::StoreInstanceHandle;
 use crate::Store;
@@ -6,7 +6,10 @@ use crate::{Limits, MemoryType};
 use anyhow::Result;
 use wasmtime_environ::entity::PrimaryMap;
 use wasmtime_environ::{wasm, EntityIndex, MemoryPlan, MemoryStyle, Module, WASM_PAGE_SIZE};
-use wasmtime_runtime::{RuntimeLinearMemory, RuntimeMemoryCreator, VMMemoryDefinition};
+use wasmtime_runtime::{
+    OnDemandInstanceAllocator, RuntimeLinearMemory, RuntimeMemoryCreator, SharedMemory,
+    VMMemoryDefinition,
+};
 
 use std::sync::Arc;
 
@@ -19,7 +22,7 @@ pub fn create_handle_with_memory(
     let memory = wasm::Memory {
         minimum: memory.limits().min(),
         maximum: memory.limits().max(),
-        shared: false, // TODO
+        shared: memory.is_shared(),
     };
 
     let memory_plan =
@@ -38,6 +41,45 @@ pub fn create_handle_with_memory(
     )
 }
 
+pub fn create_handle_with_shared_memory(
+    store: &Store,
+    memory: &SharedMemory,
+) -> Result<StoreInstanceHandle> {
+    let mut module = Module::new();
+    let memory_id = module.local.memory_plans.push(memory.plan().clone());
+    module
+        .exports
+        .insert("memory".to_string(), EntityIndex::Memory(memory_id));
+
+    let allocator = OnDemandInstanceAllocator::new(Some(Arc::new(SharedMemoryCreator {
+        memory: memory.clone(),
+    })));
+    create_handle_with_allocator(
+        &allocator,
+        module,
+        store,
+        PrimaryMap::new(),
+        Default::default(),
+        Box::new(()),
+    )
+}
+
+/// Defines an existing shared memory in the instance created to import it into
+/// a store.
+struct SharedMemoryCreator {
+    memory: SharedMemory,
+}
+
+impl RuntimeMemoryCreator for SharedMemoryCreator {
+    fn new_memory(&self, _plan: &MemoryPlan) -> Result<Box<dyn RuntimeLinearMemory>, String> {
+        unreachable!("shared memory instances only define a shared memory")
+    }
+
+    fn new_shared_memory(&self, _plan: &MemoryPlan) -> Result<SharedMemory, String> {
+        Ok(self.memory.clone())
+    }
+}
+
 struct LinearMemoryProxy {
     mem: Box<dyn LinearMemory>,
 }
@@ -10,7 +10,7 @@ pub(crate) use memory::MemoryCreatorProxy;
 
 use self::func::create_handle_with_function;
 use self::global::create_global;
-use self::memory::create_handle_with_memory;
+use self::memory::{create_handle_with_memory, create_handle_with_shared_memory};
 use self::table::create_handle_with_table;
 use crate::{FuncType, GlobalType, MemoryType, Store, TableType, Trap, Val};
 use anyhow::Result;
@@ -47,7 +47,7 @@ impl Deref for StoreInstanceHandle {
 
 pub fn generate_func_export(
     ft: &FuncType,
-    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap>>,
+    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap> + Send>,
     store: &Store,
 ) -> Result<(
     StoreInstanceHandle,
@@ -69,7 +69,7 @@ pub unsafe fn generate_raw_func_export(
     func: *mut [VMFunctionBody],
     trampoline: VMTrampoline,
     store: &Store,
-    state: Box<dyn Any>,
+    state: Box<dyn Any + Send>,
 ) -> Result<(StoreInstanceHandle, wasmtime_runtime::ExportFunction)> {
     let instance = func::create_handle_with_raw_function(ft, func, trampoline, store, state)?;
     match instance.lookup("trampoline").expect("trampoline export") {
@@ -101,6 +101,17 @@ pub fn generate_memory_export(
     }
 }
 
+pub fn generate_shared_memory_export(
+    store: &Store,
+    m: &wasmtime_runtime::SharedMemory,
+) -> Result<(StoreInstanceHandle, wasmtime_runtime::ExportMemory)> {
+    let instance = create_handle_with_shared_memory(store, m)?;
+    match instance.lookup("memory").expect("memory export") {
+        wasmtime_runtime::Export::Memory(m) => Ok((instance, m)),
+        _ => unreachable!(),
+    }
+}
+
 pub fn generate_table_export(
     store: &Store,
     t: &TableType,
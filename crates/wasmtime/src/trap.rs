@@ -3,7 +3,7 @@ use crate::FrameInfo;
 use backtrace::Backtrace;
 use std::fmt;
 use std::sync::Arc;
-use wasmtime_environ::ir::TrapCode;
+use wasmtime_environ::ir;
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
@@ -23,6 +23,9 @@ enum TrapReason {
 
     /// A structured error describing a trap.
     Error(Box<dyn std::error::Error + Send + Sync>),
+
+    /// A specific code for a trap triggered while executing WebAssembly.
+    InstructionTrap(TrapCode),
 }
 
 impl fmt::Display for TrapReason {
@@ -31,10 +34,98 @@ impl fmt::Display for TrapReason {
             TrapReason::Message(s) => write!(f, "{}", s),
             TrapReason::I32Exit(status) => write!(f, "Exited with i32 exit status {}", status),
             TrapReason::Error(e) => write!(f, "{}", e),
+            TrapReason::InstructionTrap(code) => write!(f, "wasm trap: {}", code),
         }
     }
 }
 
+/// A trap code describing the reason for a trap raised while executing
+/// WebAssembly.
+///
+/// Note that more codes may be added in the future, so this enum should not be
+/// matched exhaustively.
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum TrapCode {
+    /// The current stack space was exhausted.
+    StackOverflow,
+
+    /// An out-of-bounds memory access.
+    MemoryOutOfBounds,
+
+    /// A misaligned atomic memory access.
+    HeapMisaligned,
+
+    /// An out-of-bounds access to a table.
+    TableOutOfBounds,
+
+    /// Indirect call to a null table entry.
+    IndirectCallToNull,
+
+    /// Signature mismatch on indirect call.
+    BadSignature,
+
+    /// An integer arithmetic operation caused an overflow.
+    IntegerOverflow,
+
+    /// An integer division by zero.
+    IntegerDivisionByZero,
+
+    /// Failed float-to-int conversion.
+    BadConversionToInteger,
+
+    /// Code that was supposed to have been unreachable was reached.
+    UnreachableCodeReached,
+
+    /// Execution was interrupted with an `InterruptHandle`.
+    Interrupt,
+
+    /// All of the fuel added to the `Store` was consumed.
+    OutOfFuel,
+}
+
+impl TrapCode {
+    /// Panics if `code` is `ir::TrapCode::User`, which wasm code never emits.
+    fn from_non_user(code: ir::TrapCode) -> Self {
+        match code {
+            ir::TrapCode::StackOverflow => TrapCode::StackOverflow,
+            ir::TrapCode::HeapOutOfBounds => TrapCode::MemoryOutOfBounds,
+            ir::TrapCode::HeapMisaligned => TrapCode::HeapMisaligned,
+            ir::TrapCode::TableOutOfBounds => TrapCode::TableOutOfBounds,
+            ir::TrapCode::IndirectCallToNull => TrapCode::IndirectCallToNull,
+            ir::TrapCode::BadSignature => TrapCode::BadSignature,
+            ir::TrapCode::IntegerOverflow => TrapCode::IntegerOverflow,
+            ir::TrapCode::IntegerDivisionByZero => TrapCode::IntegerDivisionByZero,
+            ir::TrapCode::BadConversionToInteger => TrapCode::BadConversionToInteger,
+            ir::TrapCode::UnreachableCodeReached => TrapCode::UnreachableCodeReached,
+            ir::TrapCode::Interrupt => TrapCode::Interrupt,
+            ir::TrapCode::OutOfFuel => TrapCode::OutOfFuel,
+            ir::TrapCode::User(_) => panic!("Called `TrapCode::from_non_user` with user code"),
+        }
+    }
+}
+
+impl fmt::Display for TrapCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TrapCode::*;
+        let desc = match self {
+            StackOverflow => "call stack exhausted",
+            MemoryOutOfBounds => "out of bounds memory access",
+            HeapMisaligned => "unaligned atomic",
+            TableOutOfBounds => "undefined element: out of bounds table access",
+            IndirectCallToNull => "uninitialized element",
+            BadSignature => "indirect call type mismatch",
+            IntegerOverflow => "integer overflow",
+            IntegerDivisionByZero => "integer divide by zero",
+            BadConversionToInteger => "invalid conversion to integer",
+            UnreachableCodeReached => "unreachable",
+            Interrupt => "interrupt",
+            OutOfFuel => "all fuel consumed by WebAssembly",
+        };
+        write!(f, "{}", desc)
+    }
+}
+
 struct TrapInner {
     reason: TrapReason,
     wasm_trace: Vec<FrameInfo>,
@@ -80,9 +171,9 @@ impl Trap {
                 maybe_interrupted,
             } => {
                 let mut code = (info.lookup_trap_info(pc).map(|info| info.trap_code))
-                    .unwrap_or(TrapCode::StackOverflow);
-                if maybe_interrupted && code == TrapCode::StackOverflow {
-                    code = TrapCode::Interrupt;
+                    .unwrap_or(ir::TrapCode::StackOverflow);
+                if maybe_interrupted && code == ir::TrapCode::StackOverflow {
+                    code = ir::TrapCode::Interrupt;
                 }
                 Trap::new_wasm(&info, Some(pc), code, backtrace)
             }
@@ -100,25 +191,11 @@ impl Trap {
     fn new_wasm(
         info: &GlobalFrameInfo,
         trap_pc: Option<usize>,
-        code: TrapCode,
+        code: ir::TrapCode,
         backtrace: Backtrace,
     ) -> Self {
-        use wasmtime_environ::ir::TrapCode::*;
-        let desc = match code {
-            StackOverflow => "call stack exhausted",
-            HeapOutOfBounds => "out of bounds memory access",
-            TableOutOfBounds => "undefined element: out of bounds table access",
-            IndirectCallToNull => "uninitialized element",
-            BadSignature => "indirect call type mismatch",
-            IntegerOverflow => "integer overflow",
-            IntegerDivisionByZero => "integer divide by zero",
-            BadConversionToInteger => "invalid conversion to integer",
-            UnreachableCodeReached => "unreachable",
-            Interrupt => "interrupt",
-            User(_) => unreachable!(),
-        };
-        let msg = TrapReason::Message(format!("wasm trap: {}", desc));
-        Trap::new_with_trace(info, trap_pc, msg, backtrace)
+        let code = TrapCode::from_non_user(code);
+        Trap::new_with_trace(info, trap_pc, TrapReason::InstructionTrap(code), backtrace)
     }
 
     fn new_with_trace(
@@ -127,27 +204,7 @@ impl Trap {
         reason: TrapReason,
         native_trace: Backtrace,
     ) -> Self {
-        let mut wasm_trace = Vec::new();
-        for frame in native_trace.frames() {
-            let pc = frame.ip() as usize;
-            if pc == 0 {
-                continue;
-            }
-            // Note that we need to be careful about the pc we pass in here to
-            // lookup frame information. This program counter is used to
-            // translate back to an original source location in the origin wasm
-            // module. If this pc is the exact pc that the trap happened at,
-            // then we look up that pc precisely. Otherwise backtrace
-            // information typically points at the pc *after* the call
-            // instruction (because otherwise it's likely a call instruction on
-            // the stack). In that case we want to lookup information for the
-            // previous instruction (the call instruction) so we subtract one as
-            // the lookup.
-            let pc_to_lookup = if Some(pc) == trap_pc { pc } else { pc - 1 };
-            if let Some(info) = info.lookup_frame_info(pc_to_lookup) {
-                wasm_trace.push(info);
-            }
-        }
+        let wasm_trace = info.lookup_backtrace(&native_trace, trap_pc, |_| true);
         Trap {
             inner: Arc::new(TrapInner {
                 reason,
@@ -166,6 +223,16 @@ impl Trap {
         }
     }
 
+    /// Returns the trap code describing why WebAssembly code trapped, or `None`
+    /// if this trap wasn't raised by a WebAssembly instruction, for example
+    /// because it was created by a host function.
+    pub fn trap_code(&self) -> Option<TrapCode> {
+        match self.inner.reason {
+            TrapReason::InstructionTrap(code) => Some(code),
+            _ => None,
+        }
+    }
+
     /// Returns a list of function frames in WebAssembly code that led to this
     /// trap happening.
     pub fn trace(&self) -> &[FrameInfo] {
@@ -195,23 +262,47 @@ impl fmt::Display for Trap {
             let name = frame.module_name().unwrap_or("<unknown>");
             write!(f, "  {}: {:#6x} - {}!", i, frame.module_offset(), name)?;
             match frame.func_name() {
-                Some(name) => match rustc_demangle::try_demangle(name) {
-                    Ok(name) => write!(f, "{}", name)?,
-                    Err(_) => write!(f, "{}", name)?,
-                },
+                Some(name) => demangle(f, name)?,
                 None => write!(f, "<wasm function {}>", frame.func_index())?,
             }
             writeln!(f, "")?;
+            for symbol in frame.symbols() {
+                write!(f, "            at ")?;
+                match symbol.name() {
+                    Some(name) => demangle(f, name)?,
+                    None => write!(f, "<unknown>")?,
+                }
+                if let Some(file) = symbol.file() {
+                    write!(f, " ({}", file)?;
+                    if let Some(line) = symbol.line() {
+                        write!(f, ":{}", line)?;
+                        if let Some(column) = symbol.column() {
+                            write!(f, ":{}", column)?;
+                        }
+                    }
+                    write!(f, ")")?;
+                }
+                writeln!(f, "")?;
+            }
         }
         Ok(())
     }
 }
 
+fn demangle(f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+    match rustc_demangle::try_demangle(name) {
+        Ok(name) => write!(f, "{}", name),
+        Err(_) => write!(f, "{}", name),
+    }
+}
+
 impl std::error::Error for Trap {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match &self.inner.reason {
             TrapReason::Error(e) => e.source(),
-            TrapReason::I32Exit(_) | TrapReason::Message(_) => None,
+            TrapReason::I32Exit(_) | TrapReason::Message(_) | TrapReason::InstructionTrap(_) => {
+                None
+            }
         }
     }
 }
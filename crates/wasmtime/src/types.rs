@@ -398,13 +398,32 @@ impl TableType {
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct MemoryType {
     limits: Limits,
+    shared: bool,
 }
 
 impl MemoryType {
     /// Creates a new descriptor for a WebAssembly memory given the specified
     /// limits of the memory.
     pub fn new(limits: Limits) -> MemoryType {
-        MemoryType { limits }
+        MemoryType {
+            limits,
+            shared: false,
+        }
+    }
+
+    /// Creates a new descriptor for a WebAssembly shared memory given the
+    /// specified limits of the memory.
+    ///
+    /// Shared memories are part of the [WebAssembly threads proposal] and must
+    /// have a maximum size. See [`SharedMemory`](crate::SharedMemory) for how
+    /// to use them from multiple threads.
+    ///
+    /// [WebAssembly threads proposal]: https://github.com/webassembly/threads
+    pub fn shared(limits: Limits) -> MemoryType {
+        MemoryType {
+            limits,
+            shared: true,
+        }
     }
 
     /// Returns the limits (in pages) that are configured for this memory.
@@ -412,8 +431,16 @@ impl MemoryType {
         &self.limits
     }
 
+    /// Returns whether this is a shared memory.
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
     pub(crate) fn from_wasmtime_memory(memory: &wasm::Memory) -> MemoryType {
-        MemoryType::new(Limits::new(memory.minimum, memory.maximum))
+        MemoryType {
+            limits: Limits::new(memory.minimum, memory.maximum),
+            shared: memory.shared,
+        }
     }
 }
 
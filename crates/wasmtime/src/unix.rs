@@ -18,14 +18,15 @@ pub trait StoreExt {
     /// [async-signal-safe](http://man7.org/linux/man-pages/man7/signal-safety.7.html).
     unsafe fn set_signal_handler<H>(&self, handler: H)
     where
-        H: 'static + Fn(libc::c_int, *const libc::siginfo_t, *const libc::c_void) -> bool;
+        H: 'static + Send + Fn(libc::c_int, *const libc::siginfo_t, *const libc::c_void) -> bool;
 }
 
 impl StoreExt for Store {
     unsafe fn set_signal_handler<H>(&self, handler: H)
     where
-        H: 'static + Fn(libc::c_int, *const libc::siginfo_t, *const libc::c_void) -> bool,
+        H: 'static + Send + Fn(libc::c_int, *const libc::siginfo_t, *const libc::c_void) -> bool,
     {
+        let _entered = self.enter();
         *self.signal_handler_mut() = Some(Box::new(handler));
     }
 }
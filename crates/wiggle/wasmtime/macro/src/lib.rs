@@ -124,6 +124,13 @@ configuration of the wasi instance itself should be all
 contained in the `cx` parameter.",
         module_conf.name.to_string()
     );
+    let shared_constructor_docs = format!(
+        "Creates a new [`{}`] instance with a context shared with its creator.
+
+This is the same as [`{0}::new`], except that `cx` can also be used by other
+host functions defined alongside this instance.",
+        module_conf.name.to_string()
+    );
 
     let ctx_type = names.ctx_type();
 
@@ -136,7 +143,14 @@ contained in the `cx` parameter.",
         impl #type_name {
             #[doc = #constructor_docs]
             pub fn new(store: &wasmtime::Store, cx: #ctx_type) -> Self {
-                let cx = std::rc::Rc::new(std::cell::RefCell::new(cx));
+                Self::new_shared(store, std::sync::Arc::new(std::sync::Mutex::new(cx)))
+            }
+
+            #[doc = #shared_constructor_docs]
+            pub fn new_shared(
+                store: &wasmtime::Store,
+                cx: std::sync::Arc<std::sync::Mutex<#ctx_type>>,
+            ) -> Self {
                 #(#ctor_externs)*
 
                 Self {
@@ -220,7 +234,7 @@ fn generate_func(
                     let bc = #runtime::BorrowChecker::new();
                     let mem = #runtime::WasmtimeGuestMemory::new( mem, bc );
                     #target_module::#name_ident(
-                        &mut my_cx.borrow_mut(),
+                        &mut my_cx.lock().unwrap(),
                         &mem,
                         #(#arg_names),*
                     )
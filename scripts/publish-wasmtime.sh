@@ -26,6 +26,7 @@ for cargo_toml in \
     crates/wiggle/Cargo.toml \
     crates/wasi-common/Cargo.toml \
     crates/lightbeam/Cargo.toml \
+    crates/fiber/Cargo.toml \
     crates/environ/Cargo.toml \
     crates/obj/Cargo.toml \
     crates/runtime/Cargo.toml \
@@ -57,6 +57,7 @@ RUST_BACKTRACE=1 cargo test \
   --package wasmtime-wast \
   --package wasmtime-debug \
   --package wasmtime-environ \
+  --package wasmtime-fiber \
   --package wasmtime-runtime \
   --package wasmtime-jit \
   --package wasmtime-obj \
@@ -6,7 +6,8 @@
 use anyhow::Result;
 use structopt::{clap::AppSettings, clap::ErrorKind, StructOpt};
 use wasmtime_cli::commands::{
-    ConfigCommand, RunCommand, WasmToObjCommand, WastCommand, WASM2OBJ_AFTER_HELP,
+    CompileCommand, ConfigCommand, RunCommand, WasmToObjCommand, WastCommand, COMPILE_AFTER_HELP,
+    WASM2OBJ_AFTER_HELP,
 };
 
 /// Wasmtime WebAssembly Runtime
@@ -32,10 +33,18 @@ use wasmtime_cli::commands::{
                   \n\
                   Invoking a specific function (e.g. `add`) in a WebAssembly module:\n\
                   \n  \
-                  wasmtime example.wasm --invoke add 1 2\n"
+                  wasmtime example.wasm --invoke add 1 2\n\
+                  \n\
+                  Compiling a WebAssembly module ahead of time and running it:\n\
+                  \n  \
+                  wasmtime compile example.wasm -o example.cwasm\n  \
+                  wasmtime example.cwasm\n"
 )]
 enum WasmtimeApp {
     // !!! IMPORTANT: if subcommands are added or removed, update `parse_module` in `src/commands/run.rs`. !!!
+    /// Compiles a WebAssembly module ahead of time
+    #[structopt(after_help = COMPILE_AFTER_HELP)]
+    Compile(CompileCommand),
     /// Controls Wasmtime configuration settings
     Config(ConfigCommand),
     /// Runs a WebAssembly module
@@ -51,6 +60,7 @@ impl WasmtimeApp {
     /// Executes the command.
     pub fn execute(&self) -> Result<()> {
         match self {
+            Self::Compile(c) => c.execute(),
             Self::Config(c) => c.execute(),
             Self::Run(c) => c.execute(),
             Self::WasmToObj(c) => c.execute(),
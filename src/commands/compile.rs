@@ -0,0 +1,73 @@
+//! The module that implements the `wasmtime compile` command.
+
+use crate::{init_file_per_thread_logger, CommonOptions};
+use anyhow::{Context as _, Result};
+use std::fs;
+use std::path::PathBuf;
+use structopt::{clap::AppSettings, StructOpt};
+use wasmtime::Engine;
+
+/// The after help text for the `compile` command.
+pub const COMPILE_AFTER_HELP: &str =
+    "The precompiled module can be run with `wasmtime run --allow-precompiled`, \
+     or loaded with `Module::deserialize_file`, without compiling it again.\n\
+     \n\
+     It can only be loaded by the same version of Wasmtime, on the target it \
+     was compiled for, and with the same code generation options and \
+     WebAssembly features.";
+
+/// Compiles a WebAssembly module ahead of time
+#[derive(StructOpt)]
+#[structopt(
+    name = "compile",
+    version = env!("CARGO_PKG_VERSION"),
+    setting = AppSettings::ColoredHelp,
+    after_help = COMPILE_AFTER_HELP,
+)]
+pub struct CompileCommand {
+    #[structopt(flatten)]
+    common: CommonOptions,
+
+    /// The target triple; default is the host triple
+    #[structopt(long, value_name = "TARGET")]
+    target: Option<String>,
+
+    /// The path of the output precompiled module; defaults to the module path
+    /// with a `.cwasm` extension
+    #[structopt(short = "o", long, value_name = "OUTPUT_PATH", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// The path of the WebAssembly module to compile
+    #[structopt(index = 1, value_name = "MODULE_PATH", parse(from_os_str))]
+    module: PathBuf,
+}
+
+impl CompileCommand {
+    /// Executes the command.
+    pub fn execute(&self) -> Result<()> {
+        if self.common.log_to_files {
+            let prefix = "wasmtime.dbg.";
+            init_file_per_thread_logger(prefix);
+        } else {
+            pretty_env_logger::init();
+        }
+
+        let mut config = self.common.config()?;
+        if let Some(target) = &self.target {
+            config.target(target)?;
+        }
+        let engine = Engine::new(&config);
+        let wasm = fs::read(&self.module)
+            .with_context(|| format!("failed to read `{}`", self.module.display()))?;
+        let bytes = engine.precompile_module(&wasm)?;
+
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => self.module.with_extension("cwasm"),
+        };
+        fs::write(&output, bytes)
+            .with_context(|| format!("failed to write `{}`", output.display()))?;
+
+        Ok(())
+    }
+}
@@ -2,25 +2,27 @@
 
 use crate::{init_file_per_thread_logger, CommonOptions};
 use anyhow::{bail, Context as _, Result};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use std::{
     ffi::{OsStr, OsString},
     fs::File,
-    path::{Component, PathBuf},
+    path::{Component, Path, PathBuf},
     process,
 };
 use structopt::{clap::AppSettings, StructOpt};
-use wasi_common::{preopen_dir, WasiCtxBuilder};
+use wasi_common::{preopen_dir, FakeClocks, SeededRandom, WasiCtx, WasiCtxBuilder};
 use wasmtime::{Engine, Func, Linker, Module, Store, Trap, Val, ValType};
+use wasmtime_jit::CompiledModule;
 use wasmtime_wasi::Wasi;
 
 fn parse_module(s: &OsStr) -> Result<PathBuf, OsString> {
     // Do not accept wasmtime subcommand names as the module name
     match s.to_str() {
-        Some("help") | Some("config") | Some("run") | Some("wasm2obj") | Some("wast") => {
-            Err("module name cannot be the same as a subcommand".into())
-        }
+        Some("help") | Some("compile") | Some("config") | Some("run") | Some("wasm2obj")
+        | Some("wast") => Err("module name cannot be the same as a subcommand".into()),
         _ => Ok(s.into()),
     }
 }
@@ -41,6 +43,29 @@ fn parse_map_dirs(s: &str) -> Result<(String, String)> {
     Ok((parts[0].into(), parts[1].into()))
 }
 
+fn parse_tmpfs(s: &str) -> Result<(String, Option<u64>)> {
+    let parts: Vec<&str> = s.split("::").collect();
+    match parts.as_slice() {
+        [guest] => Ok(((*guest).into(), None)),
+        [guest, size] => Ok(((*guest).into(), Some(parse_size(size)?))),
+        _ => bail!("must contain at most one double colon ('::')"),
+    }
+}
+
+fn parse_size(s: &str) -> Result<u64> {
+    let (digits, multiplier) = match s.chars().last().map(|c| c.to_ascii_lowercase()) {
+        Some('k') => (&s[..s.len() - 1], 1 << 10),
+        Some('m') => (&s[..s.len() - 1], 1 << 20),
+        Some('g') => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    let size: u64 = digits
+        .parse()
+        .with_context(|| format!("invalid size `{}`", s))?;
+    size.checked_mul(multiplier)
+        .with_context(|| format!("size `{}` is too large", s))
+}
+
 fn parse_dur(s: &str) -> Result<Duration> {
     // assume an integer without a unit specified is a number of seconds ...
     if let Ok(val) = s.parse() {
@@ -74,14 +99,27 @@ pub struct RunCommand {
     #[structopt(long = "env", number_of_values = 1, value_name = "NAME=VAL", parse(try_from_str = parse_env_var))]
     vars: Vec<(String, String)>,
 
-    /// The name of the function to run
-    #[structopt(long, value_name = "FUNCTION")]
-    invoke: Option<String>,
+    /// The name of a function to run instead of the default export, taking
+    /// its parameters from the arguments; may be repeated to call several
+    /// functions of the same instance in sequence
+    #[structopt(long, number_of_values = 1, value_name = "FUNCTION")]
+    invoke: Vec<String>,
 
     /// Grant access to a guest directory mapped as a host directory
     #[structopt(long = "mapdir", number_of_values = 1, value_name = "GUEST_DIR::HOST_DIR", parse(try_from_str = parse_map_dirs))]
     map_dirs: Vec<(String, String)>,
 
+    /// Grant access to a read-only guest directory with the contents of the
+    /// given tar archive
+    #[structopt(long = "mapdir-archive", number_of_values = 1, value_name = "GUEST_DIR::ARCHIVE", parse(try_from_str = parse_map_dirs))]
+    map_archives: Vec<(String, String)>,
+
+    /// Grant access to an empty, writable guest directory kept in memory, in
+    /// which files can total at most the given size, in bytes or with a `k`,
+    /// `m` or `g` suffix
+    #[structopt(long, number_of_values = 1, value_name = "GUEST_DIR[::MAX_SIZE]", parse(try_from_str = parse_tmpfs))]
+    tmpfs: Vec<(String, Option<u64>)>,
+
     /// The path of the WebAssembly module to run
     #[structopt(
         index = 1,
@@ -100,6 +138,34 @@ pub struct RunCommand {
     )]
     preloads: Vec<(String, PathBuf)>,
 
+    /// Grant access to a TCP listener bound to the given address
+    #[structopt(long = "tcplisten", number_of_values = 1, value_name = "ADDRESS")]
+    tcp_listen: Vec<String>,
+
+    /// Make the program's clocks start at the given time, in seconds since the
+    /// Unix epoch, and only advance while it waits for timeouts
+    #[structopt(long, value_name = "SECONDS")]
+    fake_clock: Option<u64>,
+
+    /// Make the program's random data a pseudorandom sequence generated from
+    /// the given seed
+    #[structopt(long, value_name = "SEED")]
+    random_seed: Option<u64>,
+
+    /// Record the results of nondeterministic WASI calls to the given trace file
+    #[structopt(
+        long,
+        value_name = "TRACE_PATH",
+        parse(from_os_str),
+        conflicts_with = "replay"
+    )]
+    record: Option<PathBuf>,
+
+    /// Replay the results of nondeterministic WASI calls from the given trace
+    /// file, recorded with `--record`
+    #[structopt(long, value_name = "TRACE_PATH", parse(from_os_str))]
+    replay: Option<PathBuf>,
+
     /// Maximum execution time of wasm code before timing out (1, 2s, 100ms, etc)
     #[structopt(
         long = "wasm-timeout",
@@ -108,6 +174,12 @@ pub struct RunCommand {
     )]
     wasm_timeout: Option<Duration>,
 
+    /// Allow running modules precompiled by `wasmtime compile`. Precompiled
+    /// modules contain native code that is loaded without being checked, so
+    /// only pass this flag for files from a trusted source.
+    #[structopt(long = "allow-precompiled")]
+    allow_precompiled: bool,
+
     // NOTE: this must come last for trailing varargs
     /// The arguments to pass to the module
     #[structopt(value_name = "ARGS")]
@@ -133,15 +205,47 @@ impl RunCommand {
 
         // Make wasi available by default.
         let preopen_dirs = self.compute_preopen_dirs()?;
+        let archives = self.compute_archives()?;
+        let listeners = self.compute_listeners()?;
         let argv = self.compute_argv();
+        let trace = self.compute_trace()?;
 
         let mut linker = Linker::new(&store);
-        populate_with_wasi(&mut linker, &preopen_dirs, &argv, &self.vars)?;
+        let ctx = populate_with_wasi(
+            &mut linker,
+            &preopen_dirs,
+            &listeners,
+            &argv,
+            &self.vars,
+            // These only apply to the current snapshot.
+            |cx| {
+                for (guest, archive) in archives {
+                    cx.preopened_archive(archive, guest);
+                }
+                for (guest, max_size) in self.tmpfs.iter() {
+                    cx.preopened_tmpfs(guest, *max_size);
+                }
+                if let Some(seconds) = self.fake_clock {
+                    cx.clocks(FakeClocks::new(Duration::from_secs(seconds)));
+                }
+                if let Some(seed) = self.random_seed {
+                    cx.random(SeededRandom::new(seed));
+                }
+                match trace {
+                    Some(WasiTrace::Record(file)) => {
+                        cx.record(file);
+                    }
+                    Some(WasiTrace::Replay(file)) => {
+                        cx.replay(file);
+                    }
+                    None => {}
+                }
+            },
+        )?;
 
         // Load the preload wasm modules.
         for (name, path) in self.preloads.iter() {
-            // Read the wasm module binary either as `*.wat` or a raw binary
-            let module = Module::from_file(&engine, path)?;
+            let module = self.load_module(&engine, path)?;
 
             // Add the module's functions to the linker.
             linker.module(name, &module).context(format!(
@@ -152,10 +256,23 @@ impl RunCommand {
         }
 
         // Load the main wasm module.
-        match self
+        let result = self
             .load_main_module(&mut linker)
-            .with_context(|| format!("failed to run main module `{}`", self.module.display()))
-        {
+            .with_context(|| format!("failed to run main module `{}`", self.module.display()));
+
+        // Finish recording or replaying the trace however the program ended. If
+        // replay diverged, that's reported instead of how the program ended,
+        // which is likely a consequence of it.
+        ctx.lock()
+            .unwrap()
+            .finish_trace()
+            .with_context(|| match (&self.record, &self.replay) {
+                (Some(path), _) => format!("failed to record trace `{}`", path.display()),
+                (_, Some(path)) => format!("failed to replay trace `{}`", path.display()),
+                (None, None) => unreachable!(),
+            })?;
+
+        match result {
             Ok(()) => (),
             Err(e) => {
                 // If the program exited because of a non-zero exit status, print
@@ -192,6 +309,15 @@ impl RunCommand {
         Ok(())
     }
 
+    fn compute_listeners(&self) -> Result<Vec<TcpListener>> {
+        self.tcp_listen
+            .iter()
+            .map(|addr| {
+                TcpListener::bind(addr).with_context(|| format!("failed to listen on '{}'", addr))
+            })
+            .collect()
+    }
+
     fn compute_preopen_dirs(&self) -> Result<Vec<(String, File)>> {
         let mut preopen_dirs = Vec::new();
 
@@ -213,6 +339,31 @@ impl RunCommand {
         Ok(preopen_dirs)
     }
 
+    fn compute_archives(&self) -> Result<Vec<(String, File)>> {
+        self.map_archives
+            .iter()
+            .map(|(guest, archive)| {
+                let file = File::open(archive)
+                    .with_context(|| format!("failed to open archive '{}'", archive))?;
+                Ok((guest.clone(), file))
+            })
+            .collect()
+    }
+
+    fn compute_trace(&self) -> Result<Option<WasiTrace>> {
+        if let Some(path) = &self.record {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create trace `{}`", path.display()))?;
+            return Ok(Some(WasiTrace::Record(file)));
+        }
+        if let Some(path) = &self.replay {
+            let file = File::open(path)
+                .with_context(|| format!("failed to open trace `{}`", path.display()))?;
+            return Ok(Some(WasiTrace::Replay(file)));
+        }
+        Ok(None)
+    }
+
     fn compute_argv(&self) -> Vec<String> {
         let mut result = Vec::new();
 
@@ -245,41 +396,85 @@ impl RunCommand {
             });
         }
 
-        // Read the wasm module binary either as `*.wat` or a raw binary.
         // Use "" as a default module name.
-        let module = Module::from_file(linker.store().engine(), &self.module)?;
+        let module = self.load_module(linker.store().engine(), &self.module)?;
         linker
             .module("", &module)
             .context(format!("failed to instantiate {:?}", self.module))?;
 
-        // If a function to invoke was given, invoke it.
-        if let Some(name) = self.invoke.as_ref() {
-            self.invoke_export(linker, name)
-        } else {
+        // If functions to invoke were given, invoke them in order, each taking
+        // its parameters from the front of the remaining arguments. All
+        // arguments are checked before the first call so that a mistake
+        // doesn't leave the program half run. Reactors were already
+        // initialized when added to the linker, so all calls share the same
+        // instance.
+        if self.invoke.is_empty() {
             let func = linker.get_default("")?;
-            self.invoke_func(func, None)
+            let args = self.parse_args(&func, None, &mut self.module_args.iter())?;
+            let results = self.invoke_func(func, &args, None)?;
+            if !results.is_empty() {
+                println!("{}", results_to_json(&results));
+            }
+        } else {
+            let mut args = self.module_args.iter();
+            let mut calls = Vec::new();
+            for name in self.invoke.iter() {
+                let func = match linker.get_one_by_name("", name)?.into_func() {
+                    Some(func) => func,
+                    None => bail!("export of `{}` wasn't a function", name),
+                };
+                let values = self.parse_args(&func, Some(name), &mut args)?;
+                calls.push((name, func, values));
+            }
+            if args.next().is_some() {
+                let (name, _, values) = calls.last().unwrap();
+                bail!(
+                    "too many arguments for `{}`: expected {}",
+                    name,
+                    values.len()
+                );
+            }
+            for (name, func, values) in calls {
+                let results = self.invoke_func(func, &values, Some(name))?;
+                println!("{}", results_to_json(&results));
+            }
         }
+        Ok(())
     }
 
-    fn invoke_export(&self, linker: &Linker, name: &str) -> Result<()> {
-        let func = match linker.get_one_by_name("", name)?.into_func() {
-            Some(func) => func,
-            None => bail!("export of `{}` wasn't a function", name),
-        };
-        self.invoke_func(func, Some(name))
-    }
-
-    fn invoke_func(&self, func: Func, name: Option<&str>) -> Result<()> {
-        let ty = func.ty();
-        if ty.params().len() > 0 {
-            eprintln!(
-                "warning: using `--invoke` with a function that takes arguments \
-                 is experimental and may break in the future"
+    /// Reads a module either as `*.wat` or a raw binary, or, with
+    /// `--allow-precompiled`, as a module precompiled by `wasmtime compile`.
+    fn load_module(&self, engine: &Engine, path: &Path) -> Result<Module> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("failed to read input file: {}", path.display()))?;
+        if !CompiledModule::is_serialized(&bytes) {
+            let bytes = wat::parse_bytes(&bytes).map_err(|mut e| {
+                e.set_path(path);
+                e
+            })?;
+            return Module::new(engine, &bytes);
+        }
+        if !self.allow_precompiled {
+            bail!(
+                "`{}` is a precompiled module; running it requires the \
+                 `--allow-precompiled` flag",
+                path.display()
             );
         }
-        let mut args = self.module_args.iter();
+        // Safety: the user opted into loading precompiled native code and
+        // vouches for where this file came from.
+        unsafe { Module::deserialize(engine, &bytes) }
+            .with_context(|| format!("failed to load precompiled module: {}", path.display()))
+    }
+
+    fn parse_args<'a>(
+        &self,
+        func: &Func,
+        name: Option<&str>,
+        args: &mut impl Iterator<Item = &'a String>,
+    ) -> Result<Vec<Val>> {
         let mut values = Vec::new();
-        for ty in ty.params() {
+        for ty in func.ty().params() {
             let val = match args.next() {
                 Some(s) => s,
                 None => {
@@ -290,68 +485,158 @@ impl RunCommand {
                     }
                 }
             };
-            values.push(match ty {
-                // TODO: integer parsing here should handle hexadecimal notation
-                // like `0x0...`, but the Rust standard library currently only
-                // parses base-10 representations.
-                ValType::I32 => Val::I32(val.parse()?),
-                ValType::I64 => Val::I64(val.parse()?),
-                ValType::F32 => Val::F32(val.parse()?),
-                ValType::F64 => Val::F64(val.parse()?),
-                t => bail!("unsupported argument type {:?}", t),
-            });
+            values.push(
+                parse_val(ty, val)
+                    .with_context(|| format!("invalid `{:?}` argument `{}`", ty, val))?,
+            );
         }
+        Ok(values)
+    }
 
-        // Invoke the function and then afterwards print all the results that came
-        // out, if there are any.
-        let results = func.call(&values).with_context(|| {
+    fn invoke_func(&self, func: Func, values: &[Val], name: Option<&str>) -> Result<Box<[Val]>> {
+        func.call(values).with_context(|| {
             if let Some(name) = name {
                 format!("failed to invoke `{}`", name)
             } else {
                 format!("failed to invoke command default")
             }
-        })?;
-        if !results.is_empty() {
-            eprintln!(
-                "warning: using `--invoke` with a function that returns values \
-                 is experimental and may break in the future"
-            );
+        })
+    }
+}
+
+/// Parses a command line argument as a value of type `ty`.
+///
+/// Integers may be written in decimal or, with a `0x` prefix, hexadecimal,
+/// and may be given as either signed or unsigned. A `v128` is an unsigned
+/// integer, typically written in hexadecimal, and references can only be
+/// `null`.
+fn parse_val(ty: &ValType, val: &str) -> Result<Val> {
+    fn parse_int(val: &str) -> Result<u128> {
+        let (negative, val) = if val.starts_with('-') {
+            (true, &val[1..])
+        } else {
+            (false, val)
+        };
+        let magnitude = if val.starts_with("0x") || val.starts_with("0X") {
+            u128::from_str_radix(&val[2..].replace('_', ""), 16)?
+        } else {
+            val.replace('_', "").parse()?
+        };
+        Ok(if negative {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        })
+    }
+
+    fn check_range(val: u128, bits: u32) -> Result<u128> {
+        // Accept both the signed and unsigned interpretation of `bits` bits.
+        let mask = (1u128 << bits) - 1;
+        let min = (1u128 << (bits - 1)).wrapping_neg();
+        if val & !mask == 0 || val >= min {
+            Ok(val & mask)
+        } else {
+            bail!("integer out of range for {} bits", bits)
         }
+    }
 
-        for result in results.into_vec() {
-            match result {
-                Val::I32(i) => println!("{}", i),
-                Val::I64(i) => println!("{}", i),
-                Val::F32(f) => println!("{}", f),
-                Val::F64(f) => println!("{}", f),
-                Val::ExternRef(_) => println!("<externref>"),
-                Val::FuncRef(_) => println!("<externref>"),
-                Val::V128(i) => println!("{}", i),
+    Ok(match ty {
+        ValType::I32 => Val::I32(check_range(parse_int(val)?, 32)? as u32 as i32),
+        ValType::I64 => Val::I64(check_range(parse_int(val)?, 64)? as u64 as i64),
+        ValType::F32 => Val::F32(val.parse::<f32>()?.to_bits()),
+        ValType::F64 => Val::F64(val.parse::<f64>()?.to_bits()),
+        ValType::V128 => {
+            if val.starts_with('-') {
+                bail!("v128 values must be unsigned");
             }
+            Val::V128(parse_int(val)?)
         }
+        ValType::ExternRef if val == "null" => Val::ExternRef(None),
+        ValType::FuncRef if val == "null" => Val::FuncRef(None),
+        ValType::ExternRef | ValType::FuncRef => bail!("references can only be `null`"),
+    })
+}
 
-        Ok(())
+/// Formats the results of a function call as a JSON array, with one
+/// `{"type": ..., "value": ...}` object per result.
+///
+/// Integers and finite floats are JSON numbers, while non-finite floats and
+/// `v128` values, written in hexadecimal, are strings. Null references are
+/// `null`, and other references are opaque strings.
+fn results_to_json(results: &[Val]) -> String {
+    fn float(f: impl std::fmt::Display, finite: bool) -> String {
+        if finite {
+            f.to_string()
+        } else {
+            format!("\"{}\"", f)
+        }
     }
+
+    let results = results
+        .iter()
+        .map(|result| {
+            let (ty, value) = match result {
+                Val::I32(i) => ("i32", i.to_string()),
+                Val::I64(i) => ("i64", i.to_string()),
+                Val::F32(bits) => {
+                    let f = f32::from_bits(*bits);
+                    ("f32", float(f, f.is_finite()))
+                }
+                Val::F64(bits) => {
+                    let f = f64::from_bits(*bits);
+                    ("f64", float(f, f.is_finite()))
+                }
+                Val::V128(i) => ("v128", format!("\"{:#034x}\"", i)),
+                Val::ExternRef(None) => ("externref", "null".to_string()),
+                Val::ExternRef(Some(_)) => ("externref", "\"<externref>\"".to_string()),
+                Val::FuncRef(None) => ("funcref", "null".to_string()),
+                Val::FuncRef(Some(_)) => ("funcref", "\"<funcref>\"".to_string()),
+            };
+            format!("{{\"type\":\"{}\",\"value\":{}}}", ty, value)
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", results.join(","))
+}
+
+/// A trace of the nondeterministic WASI calls of the program to record or
+/// replay.
+enum WasiTrace {
+    Record(File),
+    Replay(File),
 }
 
 /// Populates the given `Linker` with WASI APIs.
 fn populate_with_wasi(
     linker: &mut Linker,
     preopen_dirs: &[(String, File)],
+    listeners: &[TcpListener],
     argv: &[String],
     vars: &[(String, String)],
-) -> Result<()> {
+    configure: impl FnOnce(&mut WasiCtxBuilder),
+) -> Result<Arc<Mutex<WasiCtx>>> {
     // Add the current snapshot to the linker.
     let mut cx = WasiCtxBuilder::new();
     cx.inherit_stdio().args(argv).envs(vars);
+    configure(&mut cx);
 
     for (name, file) in preopen_dirs {
         cx.preopened_dir(file.try_clone()?, name);
     }
 
-    let cx = cx.build()?;
-    let wasi = Wasi::new(linker.store(), cx);
+    for listener in listeners {
+        #[cfg(unix)]
+        cx.preopened_socket(listener.try_clone()?);
+        #[cfg(not(unix))]
+        bail!(
+            "cannot listen on {}: sockets are not supported on this platform yet",
+            listener.local_addr()?
+        );
+    }
+
+    let ctx = Arc::new(Mutex::new(cx.build()?));
+    let wasi = Wasi::new_shared(linker.store(), ctx.clone());
     wasi.add_to_linker(linker)?;
+    wasmtime_wasi::add_sock_accept_to_linker(linker, ctx.clone())?;
 
     // Repeat the above, but this time for snapshot 0.
     let mut cx = wasi_common::old::snapshot_0::WasiCtxBuilder::new();
@@ -365,5 +650,5 @@ fn populate_with_wasi(
     let wasi = wasmtime_wasi::old::snapshot_0::Wasi::new(linker.store(), cx);
     wasi.add_to_linker(linker)?;
 
-    Ok(())
+    Ok(ctx)
 }
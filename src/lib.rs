@@ -120,6 +120,10 @@ struct CommonOptions {
     #[structopt(long)]
     enable_threads: bool,
 
+    /// Enable support for tail calls
+    #[structopt(long)]
+    enable_tail_call: bool,
+
     /// Enable support for bulk memory instructions
     #[structopt(long)]
     enable_bulk_memory: bool,
@@ -190,6 +194,7 @@ impl CommonOptions {
             .wasm_reference_types(self.enable_reference_types || self.enable_all)
             .wasm_multi_value(self.enable_multi_value.unwrap_or(true) || self.enable_all)
             .wasm_threads(self.enable_threads || self.enable_all)
+            .wasm_tail_call(self.enable_tail_call || self.enable_all)
             .cranelift_opt_level(self.opt_level())
             .strategy(pick_compilation_strategy(self.cranelift, self.lightbeam)?)?
             .profiler(pick_profiling_strategy(self.jitdump, self.vtune)?)?
@@ -0,0 +1,275 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::Arc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use wasmtime::*;
+
+fn async_store() -> Store {
+    Store::new(&Engine::new(Config::new().async_support(true)))
+}
+
+/// Polls `future` to completion on the current thread, with a waker which
+/// does nothing since all futures in these tests are immediately ready to
+/// make progress when polled again.
+fn run<F: Future>(future: F) -> F::Output {
+    let mut f = Box::pin(future);
+    let waker = dummy_waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match f.as_mut().poll(&mut cx) {
+            Poll::Ready(val) => break val,
+            Poll::Pending => {}
+        }
+    }
+}
+
+fn dummy_waker() -> Waker {
+    return unsafe { Waker::from_raw(clone(5 as *const _)) };
+
+    unsafe fn clone(ptr: *const ()) -> RawWaker {
+        assert_eq!(ptr as usize, 5);
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+        RawWaker::new(ptr, &VTABLE)
+    }
+
+    unsafe fn wake(ptr: *const ()) {
+        assert_eq!(ptr as usize, 5);
+    }
+
+    unsafe fn wake_by_ref(ptr: *const ()) {
+        assert_eq!(ptr as usize, 5);
+    }
+
+    unsafe fn drop(ptr: *const ()) {
+        assert_eq!(ptr as usize, 5);
+    }
+}
+
+/// A future which is pending for `n` polls before it's ready.
+struct PendingFor(u32);
+
+impl Future for PendingFor {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.0 == 0 {
+            Poll::Ready(())
+        } else {
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+fn pending_import(store: &Store, polls: u32) -> Func {
+    Func::new_async(
+        store,
+        FuncType::new(Box::new([ValType::I32]), Box::new([ValType::I32])),
+        polls,
+        |_caller, polls, params, results| {
+            Box::new(async move {
+                PendingFor(*polls).await;
+                results[0] = Val::I32(params[0].unwrap_i32() + 1);
+                Ok(())
+            })
+        },
+    )
+}
+
+#[test]
+fn smoke() {
+    let store = async_store();
+    let func = Func::new_async(
+        &store,
+        FuncType::new(Box::new([]), Box::new([])),
+        (),
+        move |_caller, _state, _params, _results| Box::new(async { Ok(()) }),
+    );
+    run(func.call_async(&[])).unwrap();
+}
+
+#[test]
+fn suspends_while_host_future_is_pending() {
+    let store = async_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "" (func $host (param i32) (result i32)))
+                (func (export "run") (param i32) (result i32)
+                    local.get 0
+                    call $host
+                    call $host))
+        "#,
+    )
+    .unwrap();
+    let import = pending_import(&store, 3);
+    let instance = run(Instance::new_async(&store, &module, &[import.into()])).unwrap();
+    let func = instance.get_func("run").unwrap();
+
+    // Count how many times our future is polled to ensure it was actually
+    // suspended while the host futures were pending.
+    let polls = Rc::new(Cell::new(0));
+    let polls2 = polls.clone();
+    let params = [Val::I32(1)];
+    let results = run(async {
+        let mut call = Box::pin(func.call_async(&params));
+        futures_poll_fn(|cx| {
+            polls2.set(polls2.get() + 1);
+            call.as_mut().poll(cx)
+        })
+        .await
+    })
+    .unwrap();
+    assert_eq!(results[0].unwrap_i32(), 3);
+    assert_eq!(polls.get(), 7);
+}
+
+#[test]
+fn host_traps_are_propagated() {
+    let store = async_store();
+    let func = Func::new_async(
+        &store,
+        FuncType::new(Box::new([]), Box::new([])),
+        (),
+        |_caller, _state, _params, _results| {
+            Box::new(async {
+                PendingFor(1).await;
+                Err(Trap::new("test"))
+            })
+        },
+    );
+    let err = run(func.call_async(&[])).unwrap_err();
+    assert!(err.to_string().contains("test"), "{}", err);
+}
+
+#[test]
+fn wasm_traps_on_fiber() {
+    let store = async_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (func (export "run") unreachable)
+                (func (export "recurse") call 1))
+        "#,
+    )
+    .unwrap();
+    let instance = run(Instance::new_async(&store, &module, &[])).unwrap();
+
+    let err = run(instance.get_func("run").unwrap().call_async(&[])).unwrap_err();
+    assert!(err.to_string().contains("unreachable"), "{}", err);
+
+    let err = run(instance.get_func("recurse").unwrap().call_async(&[])).unwrap_err();
+    assert!(err.to_string().contains("call stack exhausted"), "{}", err);
+}
+
+#[test]
+fn start_function_can_block() {
+    let store = async_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "" (func $host (param i32) (result i32)))
+                (func $start
+                    i32.const 0
+                    call $host
+                    drop)
+                (start $start))
+        "#,
+    )
+    .unwrap();
+    let mut linker = Linker::new(&store);
+    linker.define("", "", pending_import(&store, 2)).unwrap();
+    run(linker.instantiate_async(&module)).unwrap();
+}
+
+#[test]
+fn async_host_func_traps_in_sync_call() {
+    let store = async_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "" (func $host (param i32) (result i32)))
+                (func (export "run") (result i32)
+                    i32.const 0
+                    call $host))
+        "#,
+    )
+    .unwrap();
+    let instance = Instance::new(&store, &module, &[pending_import(&store, 0).into()]).unwrap();
+    let err = instance.get_func("run").unwrap().call(&[]).unwrap_err();
+    assert!(err.to_string().contains("asynchronous calls"), "{}", err);
+}
+
+#[test]
+fn dropping_pending_call_unwinds_wasm() {
+    let store = async_store();
+    let dropped = Arc::new(AtomicBool::new(false));
+
+    struct SetOnDrop(Arc<AtomicBool>);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, SeqCst);
+        }
+    }
+
+    let func = Func::new_async(
+        &store,
+        FuncType::new(Box::new([]), Box::new([])),
+        dropped.clone(),
+        |_caller, dropped, _params, _results| {
+            Box::new(async move {
+                let _guard = SetOnDrop(dropped.clone());
+                PendingFor(u32::max_value()).await;
+                Ok(())
+            })
+        },
+    );
+    let mut call = Box::pin(func.call_async(&[]));
+    let waker = dummy_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(call.as_mut().poll(&mut cx).is_pending());
+    assert!(!dropped.load(SeqCst));
+    drop(call);
+    assert!(dropped.load(SeqCst));
+
+    // The store is still usable afterwards.
+    let func = Func::new_async(
+        &store,
+        FuncType::new(Box::new([]), Box::new([])),
+        (),
+        |_caller, _state, _params, _results| Box::new(async { Ok(()) }),
+    );
+    run(func.call_async(&[])).unwrap();
+}
+
+#[test]
+#[should_panic]
+fn call_async_requires_async_support() {
+    let store = Store::default();
+    let func = Func::wrap(&store, || {});
+    let _ = run(func.call_async(&[]));
+}
+
+/// A minimal version of `futures::future::poll_fn`.
+fn futures_poll_fn<T>(f: impl FnMut(&mut Context) -> Poll<T> + Unpin) -> impl Future<Output = T> {
+    struct PollFn<F>(F);
+
+    impl<T, F: FnMut(&mut Context) -> Poll<T> + Unpin> Future for PollFn<F> {
+        type Output = T;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+            (self.0)(cx)
+        }
+    }
+
+    PollFn(f)
+}
@@ -4,8 +4,8 @@ use std::path::Path;
 use std::process::{Command, Output};
 use tempfile::NamedTempFile;
 
-// Run the wasmtime CLI with the provided args and return the `Output`.
-fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
+// Build a `Command` running the wasmtime CLI with the provided args.
+fn wasmtime_command(args: &[&str]) -> Result<Command> {
     let runner = std::env::vars()
         .filter(|(k, _v)| k.starts_with("CARGO_TARGET") && k.ends_with("RUNNER"))
         .next();
@@ -28,7 +28,13 @@ fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
     } else {
         Command::new(&me)
     };
-    cmd.args(args).output().map_err(Into::into)
+    cmd.args(args);
+    Ok(cmd)
+}
+
+// Run the wasmtime CLI with the provided args and return the `Output`.
+fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
+    wasmtime_command(args)?.output().map_err(Into::into)
 }
 
 // Run the wasmtime CLI with the provided args and, if it succeeds, return
@@ -350,3 +356,315 @@ fn greeter_preload_callable_command() -> Result<()> {
     assert_eq!(stdout, "Hello _start\nHello callable greet\nHello done\n");
     Ok(())
 }
+
+// Initialize a reactor once and invoke several of its exports in sequence.
+#[test]
+fn reactor_invoke_sequence() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/counter_reactor.wat")?;
+    let stdout = run_wasmtime(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--invoke",
+        "add",
+        "--invoke",
+        "add",
+        "--invoke",
+        "wrap",
+        "1",
+        "0x10",
+        "0xffffffff",
+    ])?;
+    assert_eq!(
+        stdout,
+        "[{\"type\":\"i64\",\"value\":11}]\n\
+         [{\"type\":\"i64\",\"value\":27}]\n\
+         [{\"type\":\"i32\",\"value\":0}]\n"
+    );
+    Ok(())
+}
+
+// Invoke exports taking and returning floats, vectors and references.
+#[test]
+fn invoke_typed_arguments() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/counter_reactor.wat")?;
+    let stdout = run_wasmtime(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--enable-simd",
+        "--enable-reference-types",
+        "--invoke",
+        "mul",
+        "--invoke",
+        "mul",
+        "--invoke",
+        "swap",
+        "1.5",
+        "-2",
+        "inf",
+        "0",
+        "0x0102030405060708090a0b0c0d0e0f10",
+        "null",
+    ])?;
+    assert_eq!(
+        stdout,
+        "[{\"type\":\"f64\",\"value\":-3}]\n\
+         [{\"type\":\"f64\",\"value\":\"NaN\"}]\n\
+         [{\"type\":\"externref\",\"value\":null},\
+         {\"type\":\"v128\",\"value\":\"0x0102030405060708090a0b0c0d0e0f10\"}]\n"
+    );
+
+    let output = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--invoke",
+        "wrap",
+        "0x100000000",
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("invalid `I32` argument"),
+        "bad stderr: {}",
+        stderr
+    );
+
+    let output = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--invoke",
+        "add",
+        "--invoke",
+        "wrap",
+        "1",
+        "2",
+        "3",
+    ])?;
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("too many arguments for `wrap`: expected 1"),
+        "bad stderr: {}",
+        stderr
+    );
+    Ok(())
+}
+
+// Record the nondeterministic WASI calls of a program and replay them.
+#[test]
+fn record_replay() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/nondeterministic.wat")?;
+    let trace = NamedTempFile::new()?;
+    let recorded = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--record",
+        trace.path().to_str().unwrap(),
+    ])?;
+    assert!(recorded.status.success());
+    assert_eq!(recorded.stdout.len(), 16);
+
+    let replayed = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--replay",
+        trace.path().to_str().unwrap(),
+    ])?;
+    assert!(replayed.status.success());
+    assert_eq!(replayed.stdout, recorded.stdout);
+
+    // A program which doesn't make the recorded calls diverges.
+    let wasm = build_wasm("tests/wasm/hello_wasi_snapshot1.wat")?;
+    let output = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--replay",
+        trace.path().to_str().unwrap(),
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "replay diverged at call #1: expected `random_get(8)`, found the end of the program"
+        ),
+        "bad stderr: {}",
+        stderr
+    );
+    Ok(())
+}
+
+// Run a program with fake clocks and seeded random data.
+#[test]
+fn fake_clock_random_seed() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/nondeterministic.wat")?;
+    let run = |seed| {
+        run_wasmtime_for_output(&[
+            "run",
+            wasm.path().to_str().unwrap(),
+            "--disable-cache",
+            "--fake-clock",
+            "1000",
+            "--random-seed",
+            seed,
+        ])
+    };
+    let first = run("42")?;
+    assert!(first.status.success());
+    assert_eq!(first.stdout.len(), 16);
+    assert_eq!(first.stdout[8..], 1_000_000_000_000u64.to_le_bytes());
+
+    let second = run("42")?;
+    assert_eq!(second.stdout, first.stdout);
+
+    let other = run("43")?;
+    assert_ne!(other.stdout[..8], first.stdout[..8]);
+    assert_eq!(other.stdout[8..], first.stdout[8..]);
+    Ok(())
+}
+
+// Serve a connection from wasm on a listener passed with `--tcplisten`.
+#[cfg(unix)]
+#[test]
+fn tcplisten_echo() -> Result<()> {
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    let wasm = build_wasm("tests/wasm/tcp_echo.wat")?;
+    // Let the OS pick a free port for the guest to listen on.
+    let addr = TcpListener::bind("127.0.0.1:0")?.local_addr()?.to_string();
+    let mut child = wasmtime_command(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--tcplisten",
+        &addr,
+    ])?
+    .spawn()?;
+
+    let mut stream = None;
+    for _ in 0..500 {
+        match TcpStream::connect(&addr) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    let mut stream = match stream {
+        Some(stream) => stream,
+        None => {
+            child.kill()?;
+            bail!("failed to connect to {}", addr);
+        }
+    };
+    stream.write_all(b"hello")?;
+    stream.shutdown(Shutdown::Write)?;
+    let mut echoed = String::new();
+    stream.read_to_string(&mut echoed)?;
+    assert_eq!(echoed, "hello");
+    assert!(child.wait()?.success());
+    Ok(())
+}
+
+// Compile a module ahead of time and run the precompiled artifact.
+#[test]
+fn compile_and_run() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/hello_wasi_snapshot1.wat")?;
+    let dir = tempfile::tempdir()?;
+    let cwasm = dir.path().join("hello.cwasm");
+    run_wasmtime(&[
+        "compile",
+        "--disable-cache",
+        wasm.path().to_str().unwrap(),
+        "-o",
+        cwasm.to_str().unwrap(),
+    ])?;
+    assert!(std::fs::read(&cwasm)?.starts_with(b"\0wasmtime-module"));
+
+    // Precompiled modules are only run when explicitly allowed.
+    let output = run_wasmtime_for_output(&["run", cwasm.to_str().unwrap(), "--disable-cache"])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--allow-precompiled"),
+        "bad stderr: {}",
+        stderr
+    );
+
+    let stdout = run_wasmtime(&[
+        "run",
+        "--allow-precompiled",
+        cwasm.to_str().unwrap(),
+        "--disable-cache",
+    ])?;
+    assert_eq!(stdout, "Hello, world!\n");
+
+    // Running with different code generation options fails to load it.
+    let output = run_wasmtime_for_output(&[
+        "run",
+        "--allow-precompiled",
+        cwasm.to_str().unwrap(),
+        "--disable-cache",
+        "--opt-level",
+        "0",
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("incompatible"), "bad stderr: {}", stderr);
+    Ok(())
+}
+
+// Mount a tar archive and an in-memory directory, and use their files.
+#[test]
+fn virtual_filesystems() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/virtual_fs.wat")?;
+    let archive = NamedTempFile::new()?;
+    {
+        let mut builder = tar::Builder::new(archive.reopen()?);
+        let contents = b"archive\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "hello.txt", &contents[..])?;
+        builder.finish()?;
+    }
+    let mapdir_archive = format!("/app::{}", archive.path().to_str().unwrap());
+
+    let stdout = run_wasmtime(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--mapdir-archive",
+        &mapdir_archive,
+        "--tmpfs",
+        "/tmp",
+    ])?;
+    // The guest exits with 100 or 101 if the size of a file in the archive or
+    // in the in-memory directory is wrong.
+    assert_eq!(stdout, ".\n..\nhello.txt\narchive\n.\n..\na.txt\ntmpfs\n");
+
+    // Writing more than the size limit of the in-memory directory fails with
+    // `nospc`.
+    let output = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--mapdir-archive",
+        &mapdir_archive,
+        "--tmpfs",
+        "/tmp::4",
+    ])?;
+    assert_eq!(output.status.code(), Some(51));
+    Ok(())
+}
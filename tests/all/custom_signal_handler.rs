@@ -1,8 +1,8 @@
 #[cfg(not(target_os = "windows"))]
 mod tests {
     use anyhow::Result;
-    use std::rc::Rc;
     use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
     use wasmtime::unix::StoreExt;
     use wasmtime::*;
 
@@ -47,7 +47,7 @@ mod tests {
     }
 
     // Locate "memory" export, get base address and size and set memory protection to PROT_NONE
-    fn set_up_memory(instance: &Instance) -> (*mut u8, usize) {
+    fn set_up_memory(instance: &Instance) -> (usize, usize) {
         let mem_export = instance.get_memory("memory").unwrap();
         let base = mem_export.data_ptr();
         let length = mem_export.data_size();
@@ -59,11 +59,11 @@ mod tests {
 
         println!("memory: base={:?}, length={}", base, length);
 
-        (base, length)
+        (base as usize, length)
     }
 
     fn handle_sigsegv(
-        base: *mut u8,
+        base: usize,
         length: usize,
         signum: libc::c_int,
         siginfo: *const libc::siginfo_t,
@@ -200,7 +200,7 @@ mod tests {
         // Set up multiple instances
 
         let instance1 = Instance::new(&store, &module, &make_externs(&store, &module))?;
-        let instance1_handler_triggered = Rc::new(AtomicBool::new(false));
+        let instance1_handler_triggered = Arc::new(AtomicBool::new(false));
 
         unsafe {
             let (base1, length1) = set_up_memory(&instance1);
@@ -243,7 +243,7 @@ mod tests {
 
         let instance2 = Instance::new(&store, &module, &make_externs(&store, &module))
             .expect("failed to instantiate module");
-        let instance2_handler_triggered = Rc::new(AtomicBool::new(false));
+        let instance2_handler_triggered = Arc::new(AtomicBool::new(false));
 
         unsafe {
             let (base2, length2) = set_up_memory(&instance2);
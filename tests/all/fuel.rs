@@ -0,0 +1,139 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn fuel_store() -> Store {
+    let engine = Engine::new(Config::new().consume_fuel(true));
+    Store::new(&engine)
+}
+
+#[test]
+fn fuel_not_configured() -> Result<()> {
+    let store = Store::default();
+    assert!(store.fuel_consumed().is_none());
+    assert!(store.add_fuel(1).is_err());
+    Ok(())
+}
+
+#[test]
+fn infinite_loop_runs_out_of_fuel() -> Result<()> {
+    let store = fuel_store();
+    let module = Module::new(store.engine(), r#"(func (export "loop") (loop br 0))"#)?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let iloop = instance.get_func("loop").unwrap().get0::<()>()?;
+    store.add_fuel(10_000)?;
+    let trap = iloop().unwrap_err();
+    assert!(
+        trap.to_string().contains("all fuel consumed"),
+        "{}",
+        trap.to_string()
+    );
+    assert_eq!(trap.trap_code(), Some(TrapCode::OutOfFuel));
+    assert!(store.fuel_consumed().unwrap() >= 10_000);
+    Ok(())
+}
+
+#[test]
+fn empty_store_traps_immediately() -> Result<()> {
+    let store = fuel_store();
+    let module = Module::new(store.engine(), r#"(func (export "f"))"#)?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let f = instance.get_func("f").unwrap().get0::<()>()?;
+    assert!(f().is_err());
+    store.add_fuel(1)?;
+    f()?;
+    Ok(())
+}
+
+#[test]
+fn fuel_consumption_is_deterministic() -> Result<()> {
+    let wat = r#"
+        (func (export "run") (param i32) (result i32)
+            (local i32)
+            (loop
+                local.get 1
+                local.get 0
+                i32.add
+                local.set 1
+                local.get 0
+                i32.const 1
+                i32.sub
+                local.tee 0
+                br_if 0)
+            local.get 1)
+    "#;
+    let mut consumed = Vec::new();
+    for _ in 0..2 {
+        let store = fuel_store();
+        let module = Module::new(store.engine(), wat)?;
+        let instance = Instance::new(&store, &module, &[])?;
+        let run = instance.get_func("run").unwrap().get1::<i32, i32>()?;
+        store.add_fuel(1_000_000)?;
+        assert_eq!(run(100)?, 5050);
+        consumed.push(store.fuel_consumed().unwrap());
+    }
+    assert!(consumed[0] > 0);
+    assert_eq!(consumed[0], consumed[1]);
+    Ok(())
+}
+
+#[test]
+fn host_can_refuel_between_calls() -> Result<()> {
+    let store = fuel_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (func (export "work")
+                i32.const 0
+                drop
+                i32.const 1
+                drop)
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let work = instance.get_func("work").unwrap().get0::<()>()?;
+
+    // Find out how much fuel one call takes, then run dry.
+    store.add_fuel(100)?;
+    work()?;
+    let first = store.fuel_consumed().unwrap();
+    assert!(first > 0);
+    let trap = loop {
+        if let Err(trap) = work() {
+            break trap;
+        }
+    };
+    assert_eq!(trap.trap_code(), Some(TrapCode::OutOfFuel));
+    assert!(work().is_err());
+
+    // Topping the store back up lets the next call succeed.
+    store.add_fuel(100)?;
+    work()?;
+    Ok(())
+}
+
+#[test]
+fn host_function_can_add_fuel() -> Result<()> {
+    let store = fuel_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (import "" "refuel" (func $refuel))
+            (func (export "run") (param i32)
+                (loop
+                    call $refuel
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.tee 0
+                    br_if 0))
+        "#,
+    )?;
+    let refuel = Func::wrap(&store, |caller: Caller<'_>| {
+        caller.store().add_fuel(100).unwrap();
+    });
+    let instance = Instance::new(&store, &module, &[refuel.into()])?;
+    let run = instance.get_func("run").unwrap().get1::<i32, ()>()?;
+    store.add_fuel(100)?;
+    run(1_000)?;
+    Ok(())
+}
@@ -31,6 +31,7 @@ fn loops_interruptable() -> anyhow::Result<()> {
     store.interrupt_handle()?.interrupt();
     let trap = iloop().unwrap_err();
     assert!(trap.to_string().contains("wasm trap: interrupt"));
+    assert_eq!(trap.trap_code(), Some(TrapCode::Interrupt));
     Ok(())
 }
 
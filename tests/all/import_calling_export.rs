@@ -1,6 +1,5 @@
 use anyhow::Result;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use wasmtime::*;
 
 #[test]
@@ -19,8 +18,8 @@ fn test_import_calling_export() {
     let store = Store::default();
     let module = Module::new(store.engine(), WAT).expect("failed to create module");
 
-    let other = Rc::new(RefCell::new(None::<Func>));
-    let other2 = Rc::downgrade(&other);
+    let other = Arc::new(Mutex::new(None::<Func>));
+    let other2 = Arc::downgrade(&other);
 
     let callback_func = Func::new(
         &store,
@@ -29,7 +28,8 @@ fn test_import_calling_export() {
             other2
                 .upgrade()
                 .unwrap()
-                .borrow()
+                .lock()
+                .unwrap()
                 .as_ref()
                 .expect("expected a function ref")
                 .call(&[])
@@ -46,7 +46,7 @@ fn test_import_calling_export() {
         .get_func("run")
         .expect("expected a run func in the module");
 
-    *other.borrow_mut() = Some(
+    *other.lock().unwrap() = Some(
         instance
             .get_func("other")
             .expect("expected an other func in the module"),
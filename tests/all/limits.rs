@@ -0,0 +1,220 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use wasmtime::*;
+
+const WASM_PAGE_SIZE: usize = 0x10000;
+
+struct MaxResources {
+    memory_bytes: usize,
+    table_elements: u32,
+    instances: usize,
+}
+
+impl ResourceLimiter for MaxResources {
+    fn memory_growing(
+        &self,
+        _current: usize,
+        desired: usize,
+        _max: Option<usize>,
+    ) -> Result<bool, Trap> {
+        Ok(desired <= self.memory_bytes)
+    }
+
+    fn table_growing(&self, _current: u32, desired: u32, _max: Option<u32>) -> Result<bool, Trap> {
+        Ok(desired <= self.table_elements)
+    }
+
+    fn instances(&self) -> usize {
+        self.instances
+    }
+}
+
+fn limited_store(memory_pages: usize, table_elements: u32, instances: usize) -> Store {
+    Store::new_with_limiter(
+        &Engine::new(Config::new().wasm_reference_types(true)),
+        MaxResources {
+            memory_bytes: memory_pages * WASM_PAGE_SIZE,
+            table_elements,
+            instances,
+        },
+    )
+}
+
+#[test]
+fn memory_growth_denied() -> Result<()> {
+    let store = limited_store(3, 10, 10);
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "m") 1)
+                (func (export "grow") (param i32) (result i32)
+                    local.get 0
+                    memory.grow))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow = instance.get_func("grow").unwrap().get1::<i32, i32>()?;
+    assert_eq!(grow(1)?, 1);
+    assert_eq!(grow(2)?, -1);
+    assert_eq!(grow(1)?, 2);
+    assert_eq!(grow(1)?, -1);
+
+    let memory = instance.get_memory("m").unwrap();
+    assert_eq!(memory.size(), 3);
+    assert!(memory.grow(1).is_err());
+    assert_eq!(memory.grow(0)?, 3);
+    Ok(())
+}
+
+#[test]
+fn table_growth_denied() -> Result<()> {
+    let store = limited_store(1, 5, 10);
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (table (export "t") 2 externref)
+                (func (export "grow") (param i32) (result i32)
+                    ref.null extern
+                    local.get 0
+                    table.grow 0))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow = instance.get_func("grow").unwrap().get1::<i32, i32>()?;
+    assert_eq!(grow(3)?, 2);
+    assert_eq!(grow(1)?, -1);
+
+    let table = instance.get_table("t").unwrap();
+    assert!(table.grow(1, Val::ExternRef(None)).is_err());
+    assert_eq!(table.size(), 5);
+    Ok(())
+}
+
+#[test]
+fn creation_denied() -> Result<()> {
+    let store = limited_store(1, 5, 10);
+    let module = Module::new(store.engine(), r#"(module (memory 2))"#)?;
+    let err = Instance::new(&store, &module, &[]).err().unwrap();
+    assert!(
+        err.to_string().contains("Insufficient resources"),
+        "{}",
+        err
+    );
+
+    let module = Module::new(store.engine(), r#"(module (table 6 funcref))"#)?;
+    assert!(Instance::new(&store, &module, &[]).is_err());
+
+    assert!(Memory::new(&store, MemoryType::new(Limits::new(2, None))).is_err());
+    Ok(())
+}
+
+#[test]
+fn instance_limit() -> Result<()> {
+    let store = limited_store(1, 5, 2);
+    let module = Module::new(store.engine(), r#"(module)"#)?;
+    Instance::new(&store, &module, &[])?;
+    Instance::new(&store, &module, &[])?;
+    let err = Instance::new(&store, &module, &[]).err().unwrap();
+    assert!(err.to_string().contains("Limit of 2"), "{}", err);
+    assert!(Global::new(
+        &store,
+        GlobalType::new(ValType::I32, Mutability::Const),
+        Val::I32(0),
+    )
+    .is_err());
+    assert_eq!(store.instance_count(), 2);
+    Ok(())
+}
+
+#[test]
+fn limiter_traps() -> Result<()> {
+    struct Trapping(Arc<AtomicUsize>);
+
+    impl ResourceLimiter for Trapping {
+        fn memory_growing(
+            &self,
+            current: usize,
+            _desired: usize,
+            _max: Option<usize>,
+        ) -> Result<bool, Trap> {
+            self.0.fetch_add(1, SeqCst);
+            if current == 0 {
+                Ok(true)
+            } else {
+                Err(Trap::new("memory limit exceeded"))
+            }
+        }
+
+        fn table_growing(
+            &self,
+            _current: u32,
+            _desired: u32,
+            _max: Option<u32>,
+        ) -> Result<bool, Trap> {
+            Ok(true)
+        }
+    }
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let store = Store::new_with_limiter(&Engine::default(), Trapping(calls.clone()));
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory 1)
+                (func (export "grow") (result i32)
+                    i32.const 1
+                    memory.grow))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow = instance.get_func("grow").unwrap().get0::<i32>()?;
+    let trap = grow().unwrap_err();
+    assert!(
+        trap.to_string().contains("memory limit exceeded"),
+        "{}",
+        trap
+    );
+    assert_eq!(calls.load(SeqCst), 2);
+    Ok(())
+}
+
+#[test]
+fn store_counters() -> Result<()> {
+    let store = Store::default();
+    assert_eq!(store.memory_bytes(), 0);
+    assert_eq!(store.table_elements(), 0);
+    assert_eq!(store.instance_count(), 0);
+
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "m") 1)
+                (table (export "t") 3 funcref))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    assert_eq!(store.memory_bytes(), WASM_PAGE_SIZE);
+    assert_eq!(store.table_elements(), 3);
+    assert_eq!(store.instance_count(), 1);
+
+    instance.get_memory("m").unwrap().grow(2)?;
+    instance
+        .get_table("t")
+        .unwrap()
+        .grow(4, Val::FuncRef(None))?;
+    assert_eq!(store.memory_bytes(), 3 * WASM_PAGE_SIZE);
+    assert_eq!(store.table_elements(), 7);
+
+    Global::new(
+        &store,
+        GlobalType::new(ValType::I32, Mutability::Const),
+        Val::I32(0),
+    )?;
+    assert_eq!(store.instance_count(), 2);
+    Ok(())
+}
@@ -1,9 +1,11 @@
 #![cfg(test)]
 
+mod async_functions;
 mod cli_tests;
 mod custom_signal_handler;
 mod debug;
 mod externals;
+mod fuel;
 mod func;
 mod fuzzing;
 mod globals;
@@ -12,11 +14,16 @@ mod import_calling_export;
 mod import_indexes;
 mod instance;
 mod invoke_func_via_table;
+mod limits;
 mod linker;
 mod memory_creator;
+mod memory_images;
+mod module_serialize;
 mod name;
+mod pooling_allocator;
 mod stack_overflow;
 mod table;
+mod threads;
 mod traps;
 mod use_after_drop;
 mod wast;
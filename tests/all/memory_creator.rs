@@ -21,6 +21,10 @@ mod not_for_windows {
         glob_page_counter: Arc<Mutex<u64>>,
     }
 
+    // The mapping at `mem` is owned by this memory, so it's fine to send it
+    // along with the rest of its store to another thread.
+    unsafe impl Send for CustomMemory {}
+
     impl CustomMemory {
         unsafe fn new(
             num_wasm_pages: u32,
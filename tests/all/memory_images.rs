@@ -0,0 +1,114 @@
+use anyhow::Result;
+use wasmtime::*;
+
+const DATA_MODULE: &str = r#"
+    (module
+        (memory (export "m") 2)
+        (data (i32.const 0) "\01\02\03")
+        (data (i32.const 65536) "\04")
+        (data (i32.const 1) "\05"))
+"#;
+
+fn check_initial_data(memory: &Memory) {
+    let data = unsafe { memory.data_unchecked() };
+    assert_eq!(&data[..4], &[1, 5, 3, 0]);
+    assert_eq!(data[65536], 4);
+    assert!(data[65537..].iter().all(|b| *b == 0));
+}
+
+#[test]
+fn instances_do_not_share_data() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, DATA_MODULE)?;
+    let store = Store::new(&engine);
+
+    let first = Instance::new(&store, &module, &[])?;
+    let first = first.get_memory("m").unwrap();
+    check_initial_data(&first);
+    unsafe {
+        first.data_unchecked_mut()[0] = 42;
+        first.data_unchecked_mut()[65537] = 42;
+    }
+
+    let second = Instance::new(&store, &module, &[])?;
+    let second = second.get_memory("m").unwrap();
+    check_initial_data(&second);
+    assert_eq!(unsafe { first.data_unchecked()[0] }, 42);
+
+    // Growing keeps the contents of the image.
+    second.grow(1)?;
+    check_initial_data(&second);
+    Ok(())
+}
+
+#[test]
+fn segments_apply_in_order() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "" "offset" (global i32))
+                (memory (export "m") 1)
+                (data (i32.const 0) "\01\01\01\01")
+                (data (global.get 0) "\02\02")
+                (data (i32.const 1) "\03"))
+        "#,
+    )?;
+    let store = Store::new(&engine);
+    let offset = Global::new(
+        &store,
+        GlobalType::new(ValType::I32, Mutability::Const),
+        Val::I32(0),
+    )?;
+    let instance = Instance::new(&store, &module, &[offset.into()])?;
+    let memory = instance.get_memory("m").unwrap();
+    assert_eq!(unsafe { &memory.data_unchecked()[..4] }, &[2, 3, 1, 1]);
+    Ok(())
+}
+
+#[test]
+fn out_of_bounds_segment_still_fails() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory 1)
+                (data (i32.const 0) "\01")
+                (data (i32.const 65536) "\02"))
+        "#,
+    )?;
+    let store = Store::new(&engine);
+    assert!(Instance::new(&store, &module, &[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn pooled_memories_are_reset() -> Result<()> {
+    let mut config = Config::new();
+    config.allocation_strategy(InstanceAllocationStrategy::Pooling {
+        module_limits: ModuleLimits {
+            memory_pages: 10,
+            ..Default::default()
+        },
+        instance_limits: InstanceLimits {
+            count: 1,
+            ..Default::default()
+        },
+    })?;
+    let engine = Engine::new(&config);
+    let module = Module::new(&engine, DATA_MODULE)?;
+
+    for _ in 0..2 {
+        let store = Store::new(&engine);
+        let instance = Instance::new(&store, &module, &[])?;
+        let memory = instance.get_memory("m").unwrap();
+        check_initial_data(&memory);
+        unsafe {
+            memory.data_unchecked_mut()[0] = 42;
+            memory.data_unchecked_mut()[65537] = 42;
+        }
+    }
+    Ok(())
+}
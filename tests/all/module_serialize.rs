@@ -0,0 +1,165 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn serialize(engine: &Engine, wat: &str) -> Result<Vec<u8>> {
+    engine.precompile_module(wat.as_bytes())
+}
+
+unsafe fn deserialize_and_instantiate(store: &Store, buffer: &[u8]) -> Result<Instance> {
+    let module = Module::deserialize(store.engine(), buffer)?;
+    Ok(Instance::new(store, &module, &[])?)
+}
+
+#[test]
+fn test_module_serialize_simple() -> Result<()> {
+    let buffer = serialize(
+        &Engine::default(),
+        r#"
+            (module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "\2a")
+                (func (export "run") (result i32)
+                    i32.const 0
+                    i32.load8_u))
+        "#,
+    )?;
+
+    let store = Store::default();
+    let instance = unsafe { deserialize_and_instantiate(&store, &buffer)? };
+    let run = instance.get_func("run").unwrap().get0::<i32>()?;
+    assert_eq!(run()?, 42);
+    Ok(())
+}
+
+#[test]
+fn test_module_serialize_compiled() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "" "g" (global $g i32))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "\2a")
+                (data (global.get $g) "\07")
+                (func (export "run") (result i32)
+                    i32.const 0
+                    i32.load8_u
+                    i32.const 4
+                    i32.load8_u
+                    i32.add))
+        "#,
+    )?;
+    let buffer = module.serialize()?;
+
+    let store = Store::new(&engine);
+    let module = unsafe { Module::deserialize(&engine, &buffer)? };
+    let g = Global::new(
+        &store,
+        GlobalType::new(ValType::I32, Mutability::Const),
+        Val::I32(4),
+    )?;
+    let instance = Instance::new(&store, &module, &[g.into()])?;
+    let run = instance.get_func("run").unwrap().get0::<i32>()?;
+    assert_eq!(run()?, 49);
+
+    // A deserialized module can be serialized again.
+    assert_eq!(module.serialize()?, buffer);
+    Ok(())
+}
+
+#[test]
+fn test_module_serialize_preserves_traps_and_names() -> Result<()> {
+    let engine = Engine::default();
+    let buffer = serialize(
+        &engine,
+        r#"
+            (module $named
+                (func $hello (export "hello") unreachable))
+        "#,
+    )?;
+
+    let store = Store::new(&engine);
+    let module = unsafe { Module::deserialize(&engine, &buffer)? };
+    assert_eq!(module.name(), Some("named"));
+    let instance = Instance::new(&store, &module, &[])?;
+    let hello = instance.get_func("hello").unwrap().get0::<()>()?;
+    let trap = hello().unwrap_err();
+    assert!(trap.to_string().contains("unreachable"));
+    let trace = trap.trace();
+    assert_eq!(trace.len(), 1);
+    assert_eq!(trace[0].module_name(), Some("named"));
+    assert_eq!(trace[0].func_name(), Some("hello"));
+    Ok(())
+}
+
+#[test]
+fn test_module_serialize_fail() -> Result<()> {
+    let buffer = serialize(&Engine::default(), "(module (func (export \"run\")))")?;
+
+    let mut config = Config::new();
+    config.static_memory_maximum_size(0);
+    let engine = Engine::new(&config);
+    let err = unsafe { Module::deserialize(&engine, &buffer) }
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("incompatible"), "{}", err);
+
+    let mut config = Config::new();
+    config.cranelift_opt_level(OptLevel::None);
+    let engine = Engine::new(&config);
+    assert!(unsafe { Module::deserialize(&engine, &buffer) }.is_err());
+
+    let mut config = Config::new();
+    config.wasm_bulk_memory(true);
+    let engine = Engine::new(&config);
+    let err = unsafe { Module::deserialize(&engine, &buffer) }
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("features"), "{}", err);
+
+    Ok(())
+}
+
+#[test]
+fn test_module_deserialize_garbage() -> Result<()> {
+    let engine = Engine::default();
+    assert!(unsafe { Module::deserialize(&engine, b"\0asm\x01\0\0\0") }.is_err());
+
+    let mut buffer = serialize(&engine, "(module (func (export \"run\")))")?;
+    buffer.truncate(buffer.len() / 2);
+    assert!(unsafe { Module::deserialize(&engine, &buffer) }.is_err());
+    Ok(())
+}
+
+#[test]
+fn test_module_deserialize_file() -> Result<()> {
+    let engine = Engine::default();
+    let buffer = serialize(
+        &engine,
+        "(module (func (export \"run\") (result i32) i32.const 42))",
+    )?;
+    let file = tempfile::NamedTempFile::new()?;
+    std::fs::write(file.path(), &buffer)?;
+
+    let store = Store::new(&engine);
+    let err = Module::from_file(&engine, file.path()).err().unwrap();
+    assert!(
+        format!("{:?}", err).contains("deserialize_file"),
+        "{:?}",
+        err
+    );
+
+    let module = unsafe { Module::deserialize_file(&engine, file.path())? };
+    let instance = Instance::new(&store, &module, &[])?;
+    let run = instance.get_func("run").unwrap().get0::<i32>()?;
+    assert_eq!(run()?, 42);
+
+    let mut config = Config::new();
+    config.cranelift_opt_level(OptLevel::None);
+    let err = unsafe { Module::deserialize_file(&Engine::new(&config), file.path()) }
+        .err()
+        .unwrap();
+    assert!(format!("{:?}", err).contains("incompatible"), "{:?}", err);
+    Ok(())
+}
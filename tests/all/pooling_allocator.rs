@@ -0,0 +1,196 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn pooling_engine(module_limits: ModuleLimits, count: u32) -> Result<Engine> {
+    let mut config = Config::new();
+    config.allocation_strategy(InstanceAllocationStrategy::Pooling {
+        module_limits,
+        instance_limits: InstanceLimits {
+            count,
+            ..Default::default()
+        },
+    })?;
+    Ok(Engine::new(&config))
+}
+
+fn small_limits() -> ModuleLimits {
+    ModuleLimits {
+        memory_pages: 10,
+        table_elements: 10,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn successful_instantiation() -> Result<()> {
+    let engine = pooling_engine(small_limits(), 1)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory (export "m") 1)
+                (table 10 funcref)
+                (data (i32.const 0) "\2a"))
+        "#,
+    )?;
+    let store = Store::new(&engine);
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("m").unwrap();
+    assert_eq!(memory.size(), 1);
+    assert_eq!(unsafe { memory.data_unchecked()[0] }, 42);
+    Ok(())
+}
+
+#[test]
+fn memory_limit() -> Result<()> {
+    let engine = pooling_engine(small_limits(), 1)?;
+
+    let err = Module::new(&engine, "(module (memory 11))").err().unwrap();
+    assert!(
+        err.to_string()
+            .contains("memory index 0 has a minimum page size of 11 which exceeds the limit of 10"),
+        "{}",
+        err
+    );
+
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory (export "m") 0)
+                (func (export "grow") (param i32) (result i32)
+                    local.get 0
+                    memory.grow))
+        "#,
+    )?;
+    let store = Store::new(&engine);
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow = instance.get_func("grow").unwrap().get1::<i32, i32>()?;
+    assert_eq!(grow(5)?, 0);
+    assert_eq!(grow(5)?, 5);
+    assert_eq!(grow(1)?, -1);
+
+    let memory = instance.get_memory("m").unwrap();
+    assert_eq!(memory.size(), 10);
+    assert!(memory.grow(1).is_err());
+    Ok(())
+}
+
+#[test]
+fn table_limit() -> Result<()> {
+    let engine = pooling_engine(small_limits(), 1)?;
+
+    let err = Module::new(&engine, "(module (table 11 funcref))")
+        .err()
+        .unwrap();
+    assert!(
+        err.to_string().contains(
+            "table index 0 has a minimum element size of 11 which exceeds the limit of 10"
+        ),
+        "{}",
+        err
+    );
+
+    let module = Module::new(&engine, "(module (table (export \"t\") 5 funcref))")?;
+    let store = Store::new(&engine);
+    let instance = Instance::new(&store, &module, &[])?;
+    let table = instance.get_table("t").unwrap();
+    assert_eq!(table.grow(5, Val::FuncRef(None))?, 5);
+    assert_eq!(table.size(), 10);
+    assert!(table.grow(1, Val::FuncRef(None)).is_err());
+    assert!(table.get(9).is_some());
+    Ok(())
+}
+
+#[test]
+fn module_limits() -> Result<()> {
+    let engine = pooling_engine(
+        ModuleLimits {
+            functions: 1,
+            ..small_limits()
+        },
+        1,
+    )?;
+    Module::new(&engine, "(module (func))")?;
+    let err = Module::new(&engine, "(module (func) (func))")
+        .err()
+        .unwrap();
+    assert!(
+        err.to_string()
+            .contains("defined function count of 2 exceeds the limit of 1"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn instance_limit_and_slot_reuse() -> Result<()> {
+    let engine = pooling_engine(small_limits(), 2)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory (export "m") 1)
+                (func (export "run") (result i32)
+                    (i32.store (i32.const 0) (i32.add (i32.load (i32.const 0)) (i32.const 1)))
+                    (i32.load (i32.const 0))))
+        "#,
+    )?;
+
+    for _ in 0..3 {
+        let store = Store::new(&engine);
+        let a = Instance::new(&store, &module, &[])?;
+        let b = Instance::new(&store, &module, &[])?;
+        let err = Instance::new(&store, &module, &[]).err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("Limit of 2 concurrent instances has been reached"),
+            "{}",
+            err
+        );
+
+        // Memories from previous iterations must have been reset.
+        for instance in [a, b].iter() {
+            let run = instance.get_func("run").unwrap().get0::<i32>()?;
+            assert_eq!(run()?, 1);
+            assert_eq!(run()?, 2);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn host_memories_are_not_pooled() -> Result<()> {
+    let engine = pooling_engine(small_limits(), 1)?;
+    let store = Store::new(&engine);
+    let memory = Memory::new(&store, MemoryType::new(Limits::new(20, None)));
+    assert_eq!(memory.size(), 20);
+
+    let module = Module::new(&engine, "(module (memory 1))")?;
+    Instance::new(&store, &module, &[])?;
+    Ok(())
+}
+
+#[test]
+fn invalid_limits() {
+    let mut config = Config::new();
+    assert!(config
+        .allocation_strategy(InstanceAllocationStrategy::Pooling {
+            module_limits: ModuleLimits::default(),
+            instance_limits: InstanceLimits {
+                count: 0,
+                ..Default::default()
+            },
+        })
+        .is_err());
+    assert!(config
+        .allocation_strategy(InstanceAllocationStrategy::Pooling {
+            module_limits: ModuleLimits {
+                memory_pages: 0x10001,
+                ..Default::default()
+            },
+            instance_limits: InstanceLimits::default(),
+        })
+        .is_err());
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+use wasmtime::*;
+
+#[test]
+fn call_on_another_thread() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "hit" (func $hit))
+                (func (export "run") (param i32) (result i32)
+                    call $hit
+                    local.get 0
+                    i32.const 1
+                    i32.add))
+        "#,
+    )?;
+    let hits = Arc::new(AtomicUsize::new(0));
+    let hit = Func::wrap(&store, {
+        let hits = hits.clone();
+        move || {
+            hits.fetch_add(1, SeqCst);
+        }
+    });
+    let instance = Instance::new(&store, &module, &[hit.into()])?;
+    let run = instance.get_func("run").unwrap().get1::<i32, i32>()?;
+    assert_eq!(run(1)?, 2);
+
+    let result = thread::spawn(move || {
+        let run = instance.get_func("run").unwrap().get1::<i32, i32>()?;
+        run(2)
+    })
+    .join()
+    .unwrap()?;
+    assert_eq!(result, 3);
+    assert_eq!(hits.load(SeqCst), 2);
+    Ok(())
+}
+
+#[test]
+fn instantiate_on_another_thread() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"(module (global (export "g") (mut i32) (i32.const 1)))"#,
+    )?;
+    let global = thread::spawn(move || -> Result<Global> {
+        let instance = Instance::new(&store, &module, &[])?;
+        Ok(instance.get_global("g").unwrap())
+    })
+    .join()
+    .unwrap()?;
+    global.set(Val::I32(2))?;
+    assert_eq!(global.get().i32(), Some(2));
+    Ok(())
+}
+
+#[test]
+fn concurrent_use_panics() -> Result<()> {
+    let store = Store::default();
+    let global = Global::new(
+        &store,
+        GlobalType::new(ValType::I32, Mutability::Var),
+        Val::I32(0),
+    )?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "f" (func $f))
+                (func (export "run") call $f))
+        "#,
+    )?;
+    let f = Func::wrap(&store, move || {
+        // While this thread is executing wasm in the store, other threads
+        // aren't allowed to use it.
+        let global = global.clone();
+        assert!(thread::spawn(move || global.get()).join().is_err());
+    });
+    let instance = Instance::new(&store, &module, &[f.into()])?;
+    instance.get_func("run").unwrap().call(&[])?;
+    Ok(())
+}
+
+fn threads_engine() -> Engine {
+    let mut config = Config::new();
+    config.wasm_threads(true);
+    Engine::new(&config)
+}
+
+#[test]
+fn wait_and_notify_across_threads() -> Result<()> {
+    let engine = threads_engine();
+    let memory = SharedMemory::new(&engine, MemoryType::shared(Limits::new(1, Some(1))))?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory (import "" "memory") 1 1 shared)
+                (func (export "wait") (result i32)
+                    (i32.atomic.wait (i32.const 0) (i32.const 0) (i64.const -1)))
+                (func (export "notify") (result i32)
+                    (atomic.notify (i32.const 0) (i32.const 1))))
+        "#,
+    )?;
+
+    let waiter = thread::spawn({
+        let engine = engine.clone();
+        let module = module.clone();
+        let memory = memory.clone();
+        move || -> Result<i32> {
+            let store = Store::new(&engine);
+            let memory = Memory::from_shared(&store, &memory)?;
+            let instance = Instance::new(&store, &module, &[memory.into()])?;
+            let wait = instance.get_func("wait").unwrap().get0::<i32>()?;
+            Ok(wait()?)
+        }
+    });
+
+    let store = Store::new(&engine);
+    let memory = Memory::from_shared(&store, &memory)?;
+    let instance = Instance::new(&store, &module, &[memory.into()])?;
+    let notify = instance.get_func("notify").unwrap().get0::<i32>()?;
+    // Keep notifying until the other thread started waiting.
+    while notify()? == 0 {
+        thread::yield_now();
+    }
+    assert_eq!(waiter.join().unwrap()?, 0);
+    Ok(())
+}
+
+#[test]
+fn shared_memory_visible_across_stores() -> Result<()> {
+    let engine = threads_engine();
+    let shared = SharedMemory::new(&engine, MemoryType::shared(Limits::new(1, Some(2))))?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory (import "" "memory") 1 2 shared)
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0)))
+                (func (export "size") (result i32)
+                    (memory.size))
+                (func (export "load") (param i32) (result i32)
+                    (i32.atomic.load (local.get 0)))
+                (func (export "store") (param i32 i32)
+                    (i32.atomic.store (local.get 0) (local.get 1))))
+        "#,
+    )?;
+
+    let store1 = Store::new(&engine);
+    let memory1 = Memory::from_shared(&store1, &shared)?;
+    let instance1 = Instance::new(&store1, &module, &[memory1.clone().into()])?;
+    let store2 = Store::new(&engine);
+    let memory2 = Memory::from_shared(&store2, &shared)?;
+    let instance2 = Instance::new(&store2, &module, &[memory2.clone().into()])?;
+    assert_eq!(memory1.data_ptr(), memory2.data_ptr());
+
+    let grow = instance1.get_func("grow").unwrap().get1::<i32, i32>()?;
+    let store = instance1
+        .get_func("store")
+        .unwrap()
+        .get2::<i32, i32, ()>()?;
+    let size = instance2.get_func("size").unwrap().get0::<i32>()?;
+    let load = instance2.get_func("load").unwrap().get1::<i32, i32>()?;
+
+    assert_eq!(grow(1)?, 1);
+    assert_eq!(size()?, 2);
+    assert_eq!(memory2.size(), 2);
+    assert_eq!(shared.data_size(), 2 * 65536);
+    assert_eq!(grow(1)?, -1);
+
+    store(65536, 42)?;
+    assert_eq!(load(65536)?, 42);
+    Ok(())
+}
+
+#[test]
+fn exported_shared_memory() -> Result<()> {
+    let engine = threads_engine();
+    let store = Store::new(&engine);
+    let shared = Module::new(&engine, r#"(module (memory (export "m") 1 1 shared))"#)?;
+    let unshared = Module::new(&engine, r#"(module (memory (export "m") 1))"#)?;
+
+    let shared = Instance::new(&store, &shared, &[])?
+        .get_memory("m")
+        .unwrap();
+    assert!(shared.ty().is_shared());
+    let handle = shared.to_shared().unwrap();
+    assert_eq!(handle.data_ptr(), shared.data_ptr());
+    assert_eq!(handle.ty().limits().max(), Some(1));
+
+    let unshared = Instance::new(&store, &unshared, &[])?
+        .get_memory("m")
+        .unwrap();
+    assert!(!unshared.ty().is_shared());
+    assert!(unshared.to_shared().is_none());
+    Ok(())
+}
+
+#[test]
+fn extern_ref_to_store_item() -> Result<()> {
+    let store = Store::default();
+    let global = Global::new(
+        &store,
+        GlobalType::new(ValType::I32, Mutability::Var),
+        Val::I32(1),
+    )?;
+    let r = ExternRef::from_store_item(&store, global);
+    assert!(r.store_item::<Func>().is_none());
+    assert!(!r.data().is::<Global>());
+
+    // The reference can move to another thread along with its store.
+    let r = thread::spawn(move || -> Result<ExternRef> {
+        r.store_item::<Global>().unwrap().set(Val::I32(2))?;
+        Ok(r)
+    })
+    .join()
+    .unwrap()?;
+    assert_eq!(r.store_item::<Global>().unwrap().get().i32(), Some(2));
+    Ok(())
+}
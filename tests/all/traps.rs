@@ -1,5 +1,6 @@
 use anyhow::Result;
 use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
 use wasmtime::*;
 
 #[test]
@@ -26,6 +27,7 @@ fn test_trap_return() -> Result<()> {
         .downcast::<Trap>()?;
 
     assert!(e.to_string().contains("test 123"));
+    assert_eq!(e.trap_code(), None);
 
     Ok(())
 }
@@ -108,6 +110,63 @@ fn test_trap_trace_cb() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg_attr(target_arch = "aarch64", ignore)] // FIXME(#1642)
+fn caller_backtrace() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"
+        (module $hello_mod
+            (import "" "inspect" (func $inspect))
+            (func (export "run") (call $hello))
+            (func $hello (call $inspect))
+        )
+    "#;
+
+    let traces = Arc::new(Mutex::new(Vec::new()));
+    let inspect = Func::wrap(&store, {
+        let traces = traces.clone();
+        move |caller: Caller<'_>| {
+            let trace = caller.backtrace();
+            assert_eq!(trace.len(), caller.store().capture_backtrace().len());
+            traces.lock().unwrap().push(
+                trace
+                    .iter()
+                    .map(|f| {
+                        (
+                            f.module_name().map(|s| s.to_string()),
+                            f.func_index(),
+                            f.func_name().map(|s| s.to_string()),
+                            f.module_offset(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+    });
+
+    let module = Module::new(store.engine(), wat)?;
+    let instance = Instance::new(&store, &module, &[inspect.into()])?;
+    let run_func = instance.get_func("run").expect("expected function export");
+    run_func.call(&[])?;
+
+    let traces = traces.lock().unwrap();
+    assert_eq!(traces.len(), 1);
+    let trace = &traces[0];
+    assert_eq!(trace.len(), 2);
+    assert_eq!(trace[0].0.as_deref(), Some("hello_mod"));
+    assert_eq!(trace[0].1, 2);
+    assert_eq!(trace[0].2.as_deref(), Some("hello"));
+    assert_eq!(trace[0].3, 0x34);
+    assert_eq!(trace[1].0.as_deref(), Some("hello_mod"));
+    assert_eq!(trace[1].1, 1);
+    assert_eq!(trace[1].3, 0x2f);
+
+    // Outside of wasm there's nothing on the stack.
+    assert!(store.capture_backtrace().is_empty());
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(target_arch = "aarch64", ignore)] // FIXME(#1642)
 fn test_trap_stack_overflow() -> Result<()> {
@@ -136,6 +195,7 @@ fn test_trap_stack_overflow() -> Result<()> {
         assert_eq!(trace[i].func_name(), Some("run"));
     }
     assert!(e.to_string().contains("call stack exhausted"));
+    assert_eq!(e.trap_code(), Some(TrapCode::StackOverflow));
 
     Ok(())
 }
@@ -383,6 +443,7 @@ fn call_signature_mismatch() -> Result<()> {
     assert!(err
         .to_string()
         .contains("wasm trap: indirect call type mismatch"));
+    assert_eq!(err.trap_code(), Some(TrapCode::BadSignature));
     Ok(())
 }
 
@@ -441,3 +502,84 @@ fn present_after_module_drop() -> Result<()> {
         assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[test]
+fn trap_codes() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory 1)
+                (table 1 funcref)
+                (func (export "unreachable") unreachable)
+                (func (export "oob") (drop (i32.load (i32.const 65536))))
+                (func (export "div") (drop (i32.div_s (i32.const 1) (i32.const 0))))
+                (func (export "overflow")
+                    (drop (i32.div_s (i32.const 0x80000000) (i32.const -1))))
+                (func (export "convert") (drop (i32.trunc_f32_s (f32.const nan))))
+                (func (export "null") (call_indirect (i32.const 0)))
+                (func (export "table") (call_indirect (i32.const 1))))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let code = |name: &str| -> Result<Option<TrapCode>> {
+        let trap = instance
+            .get_func(name)
+            .unwrap()
+            .call(&[])
+            .unwrap_err()
+            .downcast::<Trap>()?;
+        Ok(trap.trap_code())
+    };
+    assert_eq!(code("unreachable")?, Some(TrapCode::UnreachableCodeReached));
+    assert_eq!(code("oob")?, Some(TrapCode::MemoryOutOfBounds));
+    assert_eq!(code("div")?, Some(TrapCode::IntegerDivisionByZero));
+    assert_eq!(code("overflow")?, Some(TrapCode::IntegerOverflow));
+    assert_eq!(code("convert")?, Some(TrapCode::BadConversionToInteger));
+    assert_eq!(code("null")?, Some(TrapCode::IndirectCallToNull));
+    assert_eq!(code("table")?, Some(TrapCode::TableOutOfBounds));
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(target_arch = "aarch64", ignore)] // FIXME(#1642)
+fn trap_trace_source_locations() -> Result<()> {
+    let mut config = Config::new();
+    config.interruptable(true).wasm_backtrace_details(true);
+    let engine = Engine::new(&config);
+    let store = Store::new(&engine);
+    let module = Module::from_file(&engine, "tests/all/debug/testsuite/fib-wasm.wasm")?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let fib = instance.get_func("fib").unwrap().get1::<i32, i32>()?;
+
+    // Interrupt the call as soon as it starts so the trap happens in `fib`.
+    store.interrupt_handle()?.interrupt();
+    let trap = fib(i32::max_value()).unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::Interrupt));
+
+    let trace = trap.trace();
+    assert_eq!(trace.len(), 1);
+    let symbols = trace[0].symbols();
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].name(), Some("fib"));
+    assert!(symbols[0].file().unwrap().ends_with("fib-wasm.c"));
+    let line = symbols[0].line().unwrap();
+    assert!(8 <= line && line <= 15, "unexpected line {}", line);
+    assert!(
+        trap.to_string().contains("fib-wasm.c:"),
+        "wrong message: {}",
+        trap
+    );
+
+    // Without the option there are no source locations.
+    let store = Store::new(&Engine::new(Config::new().interruptable(true)));
+    let module = Module::from_file(store.engine(), "tests/all/debug/testsuite/fib-wasm.wasm")?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let fib = instance.get_func("fib").unwrap().get1::<i32, i32>()?;
+    store.interrupt_handle()?.interrupt();
+    let trap = fib(i32::max_value()).unwrap_err();
+    assert!(trap.trace()[0].symbols().is_empty());
+
+    Ok(())
+}
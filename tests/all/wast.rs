@@ -26,6 +26,10 @@ fn run_wast(wast: &str, strategy: Strategy) -> anyhow::Result<()> {
 
     let bulk_mem = wast.iter().any(|s| s == "bulk-memory-operations");
 
+    let threads = wast.iter().any(|s| s == "threads");
+
+    let tail_call = wast.iter().any(|s| s == "tail-call");
+
     // Some simd tests assume support for multiple tables, which are introduced
     // by reference types.
     let reftypes = simd || wast.iter().any(|s| s == "reference-types");
@@ -34,8 +38,13 @@ fn run_wast(wast: &str, strategy: Strategy) -> anyhow::Result<()> {
     cfg.wasm_simd(simd)
         .wasm_bulk_memory(bulk_mem)
         .wasm_reference_types(reftypes)
+        .wasm_threads(threads)
+        .wasm_tail_call(tail_call)
         .strategy(strategy)?
         .cranelift_debug_verifier(cfg!(debug_assertions));
+    if cfg!(feature = "experimental_x64") {
+        cfg.cranelift_x64_backend(true)?;
+    }
 
     // By default we'll allocate huge chunks (6gb) of the address space for each
     // linear memory. This is typically fine but when we emulate tests with QEMU
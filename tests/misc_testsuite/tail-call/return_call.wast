@@ -0,0 +1,64 @@
+(module $m
+  (func (export "add_one") (param i64) (result i64)
+    (i64.add (local.get 0) (i64.const 1)))
+)
+(register "m" $m)
+
+(module
+  (import "m" "add_one" (func $add_one (param i64) (result i64)))
+
+  (type $i64-i64 (func (param i64) (result i64)))
+  (table funcref (elem $const-i64 $count-indirect $add_one))
+
+  (func $const-i64 (type $i64-i64)
+    (i64.const 0x132))
+
+  ;; Counting down a million frames would run out of stack if each call kept
+  ;; its frame around.
+  (func $count (export "count") (param i64) (result i64)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (i64.const 42))
+      (else (return_call $count (i64.sub (local.get 0) (i64.const 1))))))
+
+  (func $even (export "even") (param i64) (result i32)
+    (if (result i32) (i64.eqz (local.get 0))
+      (then (i32.const 1))
+      (else (return_call $odd (i64.sub (local.get 0) (i64.const 1))))))
+  (func $odd (export "odd") (param i64) (result i32)
+    (if (result i32) (i64.eqz (local.get 0))
+      (then (i32.const 0))
+      (else (return_call $even (i64.sub (local.get 0) (i64.const 1))))))
+
+  (func $count-indirect (export "count-indirect") (type $i64-i64)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (i64.const 42))
+      (else
+        (return_call_indirect (type $i64-i64)
+          (i64.sub (local.get 0) (i64.const 1))
+          (i32.const 1)))))
+
+  (func (export "call-import") (param i64) (result i64)
+    (return_call $add_one (local.get 0)))
+
+  (func (export "dispatch") (param i32 i64) (result i64)
+    (return_call_indirect (type $i64-i64) (local.get 1) (local.get 0)))
+
+  (func (export "dispatch-wrong-type") (param i32) (result i32)
+    (return_call_indirect (result i32) (local.get 0)))
+)
+
+(assert_return (invoke "count" (i64.const 0)) (i64.const 42))
+(assert_return (invoke "count" (i64.const 1000000)) (i64.const 42))
+
+(assert_return (invoke "even" (i64.const 0)) (i32.const 1))
+(assert_return (invoke "even" (i64.const 1000001)) (i32.const 0))
+(assert_return (invoke "odd" (i64.const 1000001)) (i32.const 1))
+
+(assert_return (invoke "count-indirect" (i64.const 1000000)) (i64.const 42))
+
+(assert_return (invoke "call-import" (i64.const 41)) (i64.const 42))
+
+(assert_return (invoke "dispatch" (i32.const 0) (i64.const 2)) (i64.const 0x132))
+(assert_return (invoke "dispatch" (i32.const 2) (i64.const 2)) (i64.const 3))
+(assert_trap (invoke "dispatch" (i32.const 3) (i64.const 2)) "undefined element")
+(assert_trap (invoke "dispatch-wrong-type" (i32.const 0)) "indirect call type mismatch")
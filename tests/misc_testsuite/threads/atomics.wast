@@ -0,0 +1,112 @@
+(module
+  (memory 1 1 shared)
+
+  (func (export "init") (param $value i64)
+    (i64.store (i32.const 0) (local.get $value)))
+
+  (func (export "i32.atomic.load") (param $addr i32) (result i32)
+    (i32.atomic.load (local.get $addr)))
+  (func (export "i64.atomic.load") (param $addr i32) (result i64)
+    (i64.atomic.load (local.get $addr)))
+  (func (export "i32.atomic.load8_u") (param $addr i32) (result i32)
+    (i32.atomic.load8_u (local.get $addr)))
+  (func (export "i32.atomic.load16_u") (param $addr i32) (result i32)
+    (i32.atomic.load16_u (local.get $addr)))
+  (func (export "i64.atomic.load32_u") (param $addr i32) (result i64)
+    (i64.atomic.load32_u (local.get $addr)))
+  (func (export "i32.atomic.load_offset") (param $addr i32) (result i32)
+    (i32.atomic.load offset=4 (local.get $addr)))
+
+  (func (export "i32.atomic.store") (param $addr i32) (param $value i32)
+    (i32.atomic.store (local.get $addr) (local.get $value)))
+  (func (export "i64.atomic.store8") (param $addr i32) (param $value i64)
+    (i64.atomic.store8 (local.get $addr) (local.get $value)))
+  (func (export "i32.atomic.store16") (param $addr i32) (param $value i32)
+    (i32.atomic.store16 (local.get $addr) (local.get $value)))
+
+  (func (export "i32.atomic.rmw.add") (param $addr i32) (param $value i32) (result i32)
+    (i32.atomic.rmw.add (local.get $addr) (local.get $value)))
+  (func (export "i64.atomic.rmw.sub") (param $addr i32) (param $value i64) (result i64)
+    (i64.atomic.rmw.sub (local.get $addr) (local.get $value)))
+  (func (export "i32.atomic.rmw8.and_u") (param $addr i32) (param $value i32) (result i32)
+    (i32.atomic.rmw8.and_u (local.get $addr) (local.get $value)))
+  (func (export "i64.atomic.rmw16.or_u") (param $addr i32) (param $value i64) (result i64)
+    (i64.atomic.rmw16.or_u (local.get $addr) (local.get $value)))
+  (func (export "i64.atomic.rmw32.xor_u") (param $addr i32) (param $value i64) (result i64)
+    (i64.atomic.rmw32.xor_u (local.get $addr) (local.get $value)))
+  (func (export "i32.atomic.rmw.xchg") (param $addr i32) (param $value i32) (result i32)
+    (i32.atomic.rmw.xchg (local.get $addr) (local.get $value)))
+  (func (export "i32.atomic.rmw8.add_u") (param $addr i32) (param $value i32) (result i32)
+    (i32.atomic.rmw8.add_u (local.get $addr) (local.get $value)))
+
+  (func (export "i32.atomic.rmw.cmpxchg") (param $addr i32) (param $expected i32) (param $value i32) (result i32)
+    (i32.atomic.rmw.cmpxchg (local.get $addr) (local.get $expected) (local.get $value)))
+  (func (export "i64.atomic.rmw.cmpxchg") (param $addr i32) (param $expected i64) (param $value i64) (result i64)
+    (i64.atomic.rmw.cmpxchg (local.get $addr) (local.get $expected) (local.get $value)))
+  (func (export "i32.atomic.rmw8.cmpxchg_u") (param $addr i32) (param $expected i32) (param $value i32) (result i32)
+    (i32.atomic.rmw8.cmpxchg_u (local.get $addr) (local.get $expected) (local.get $value)))
+
+  (func (export "atomic.fence")
+    (atomic.fence))
+)
+
+;; Loads.
+(invoke "init" (i64.const 0x0807060504030201))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 0x04030201))
+(assert_return (invoke "i64.atomic.load" (i32.const 0)) (i64.const 0x0807060504030201))
+(assert_return (invoke "i32.atomic.load8_u" (i32.const 3)) (i32.const 0x04))
+(assert_return (invoke "i32.atomic.load16_u" (i32.const 6)) (i32.const 0x0807))
+(assert_return (invoke "i64.atomic.load32_u" (i32.const 4)) (i64.const 0x08070605))
+(assert_return (invoke "i32.atomic.load_offset" (i32.const 0)) (i32.const 0x08070605))
+
+;; Stores only write the low bits of their value.
+(invoke "i32.atomic.store" (i32.const 0) (i32.const 0x11223344))
+(invoke "i64.atomic.store8" (i32.const 4) (i64.const -1))
+(invoke "i32.atomic.store16" (i32.const 6) (i32.const 0x12345678))
+(assert_return (invoke "i64.atomic.load" (i32.const 0)) (i64.const 0x567806ff11223344))
+
+;; Read-modify-writes return the old value.
+(invoke "init" (i64.const 0x0807060504030201))
+(assert_return (invoke "i32.atomic.rmw.add" (i32.const 0) (i32.const 0x10101010)) (i32.const 0x04030201))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 0x14131211))
+(assert_return (invoke "i64.atomic.rmw.sub" (i32.const 0) (i64.const 1)) (i64.const 0x0807060514131211))
+(assert_return (invoke "i64.atomic.load" (i32.const 0)) (i64.const 0x0807060514131210))
+(assert_return (invoke "i32.atomic.rmw8.and_u" (i32.const 1) (i32.const 0xf0)) (i32.const 0x12))
+(assert_return (invoke "i64.atomic.rmw16.or_u" (i32.const 2) (i64.const 0xf0f0)) (i64.const 0x1413))
+(assert_return (invoke "i64.atomic.rmw32.xor_u" (i32.const 4) (i64.const 0xffffffff)) (i64.const 0x08070605))
+(assert_return (invoke "i64.atomic.load" (i32.const 0)) (i64.const 0xf7f8f9faf4f31010))
+(assert_return (invoke "i32.atomic.rmw.xchg" (i32.const 0) (i32.const 42)) (i32.const 0xf4f31010))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 42))
+
+;; Narrow read-modify-writes wrap around within their width.
+(invoke "init" (i64.const 0xff))
+(assert_return (invoke "i32.atomic.rmw8.add_u" (i32.const 0) (i32.const 0x102)) (i32.const 0xff))
+(assert_return (invoke "i64.atomic.load" (i32.const 0)) (i64.const 0x01))
+
+;; Compare-and-exchanges only store their value if the old value is the
+;; expected one, and always return the old value.
+(invoke "init" (i64.const 0x0807060504030201))
+(assert_return (invoke "i32.atomic.rmw.cmpxchg" (i32.const 0) (i32.const 0) (i32.const 1)) (i32.const 0x04030201))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 0x04030201))
+(assert_return (invoke "i32.atomic.rmw.cmpxchg" (i32.const 0) (i32.const 0x04030201) (i32.const 1)) (i32.const 0x04030201))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "i64.atomic.rmw.cmpxchg" (i32.const 0) (i64.const 0x0807060500000001) (i64.const -1)) (i64.const 0x0807060500000001))
+(assert_return (invoke "i64.atomic.load" (i32.const 0)) (i64.const -1))
+;; The expected value of narrow compare-and-exchanges is wrapped to their width.
+(assert_return (invoke "i32.atomic.rmw8.cmpxchg_u" (i32.const 0) (i32.const 0x1ff) (i32.const 0x12)) (i32.const 0xff))
+(assert_return (invoke "i64.atomic.load" (i32.const 0)) (i64.const 0xffffffffffffff12))
+
+(invoke "atomic.fence")
+
+;; Misaligned and out-of-bounds accesses trap.
+(assert_trap (invoke "i32.atomic.load" (i32.const 2)) "unaligned atomic")
+(assert_trap (invoke "i64.atomic.load" (i32.const 4)) "unaligned atomic")
+(assert_trap (invoke "i32.atomic.load_offset" (i32.const 1)) "unaligned atomic")
+(assert_trap (invoke "i32.atomic.rmw.add" (i32.const 1) (i32.const 0)) "unaligned atomic")
+(assert_trap (invoke "i32.atomic.rmw.cmpxchg" (i32.const 3) (i32.const 0) (i32.const 0)) "unaligned atomic")
+(assert_trap (invoke "i32.atomic.store16" (i32.const 1) (i32.const 0)) "unaligned atomic")
+(assert_trap (invoke "i32.atomic.load" (i32.const 65536)) "out of bounds memory access")
+(assert_trap (invoke "i32.atomic.load_offset" (i32.const 65532)) "out of bounds memory access")
+(assert_trap (invoke "i64.atomic.rmw.sub" (i32.const 65536) (i64.const 0)) "out of bounds memory access")
+;; Byte accesses are always aligned.
+(assert_return (invoke "i32.atomic.load8_u" (i32.const 65535)) (i32.const 0))
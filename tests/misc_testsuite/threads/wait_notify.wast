@@ -0,0 +1,46 @@
+(module
+  (memory 1 1 shared)
+
+  (func (export "init") (param $value i64)
+    (i64.store (i32.const 0) (local.get $value)))
+
+  (func (export "i32.atomic.wait") (param $addr i32) (param $expected i32) (param $timeout i64) (result i32)
+    (i32.atomic.wait (local.get $addr) (local.get $expected) (local.get $timeout)))
+  (func (export "i64.atomic.wait") (param $addr i32) (param $expected i64) (param $timeout i64) (result i32)
+    (i64.atomic.wait (local.get $addr) (local.get $expected) (local.get $timeout)))
+  (func (export "atomic.notify") (param $addr i32) (param $count i32) (result i32)
+    (atomic.notify (local.get $addr) (local.get $count)))
+)
+
+(invoke "init" (i64.const 0xffffffff))
+
+;; Waiting returns 1 right away if the value isn't the expected one.
+(assert_return (invoke "i32.atomic.wait" (i32.const 0) (i32.const 0) (i64.const -1)) (i32.const 1))
+(assert_return (invoke "i64.atomic.wait" (i32.const 0) (i64.const -1) (i64.const -1)) (i32.const 1))
+
+;; Otherwise it returns 2 once the timeout, in nanoseconds, expired.
+(assert_return (invoke "i32.atomic.wait" (i32.const 0) (i32.const -1) (i64.const 0)) (i32.const 2))
+(assert_return (invoke "i64.atomic.wait" (i32.const 0) (i64.const 0xffffffff) (i64.const 1000)) (i32.const 2))
+
+;; Nothing is waiting to be woken up.
+(assert_return (invoke "atomic.notify" (i32.const 0) (i32.const 1)) (i32.const 0))
+
+(assert_trap (invoke "i32.atomic.wait" (i32.const 2) (i32.const 0) (i64.const 0)) "unaligned atomic")
+(assert_trap (invoke "i64.atomic.wait" (i32.const 4) (i64.const 0) (i64.const 0)) "unaligned atomic")
+(assert_trap (invoke "atomic.notify" (i32.const 1) (i32.const 1)) "unaligned atomic")
+(assert_trap (invoke "i32.atomic.wait" (i32.const 65536) (i32.const 0) (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "atomic.notify" (i32.const 65536) (i32.const 1)) "out of bounds memory access")
+
+;; Threads can't wait on memories which aren't shared, so notifying them
+;; never wakes anything up.
+(module
+  (memory 1 1)
+
+  (func (export "i32.atomic.wait") (param $addr i32) (param $expected i32) (param $timeout i64) (result i32)
+    (i32.atomic.wait (local.get $addr) (local.get $expected) (local.get $timeout)))
+  (func (export "atomic.notify") (param $addr i32) (param $count i32) (result i32)
+    (atomic.notify (local.get $addr) (local.get $count)))
+)
+
+(assert_trap (invoke "i32.atomic.wait" (i32.const 0) (i32.const 0) (i64.const 0)) "expected shared memory")
+(assert_return (invoke "atomic.notify" (i32.const 0) (i32.const 1)) (i32.const 0))
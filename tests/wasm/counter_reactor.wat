@@ -0,0 +1,20 @@
+(module
+  (global $count (mut i64) (i64.const 0))
+  (func (export "_initialize")
+    (global.set $count (i64.const 10))
+  )
+  (func (export "add") (param i64) (result i64)
+    (global.set $count (i64.add (global.get $count) (local.get 0)))
+    (global.get $count)
+  )
+  (func (export "mul") (param f32 f64) (result f64)
+    (f64.mul (f64.promote_f32 (local.get 0)) (local.get 1))
+  )
+  (func (export "wrap") (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1))
+  )
+  (func (export "swap") (param v128 externref) (result externref v128)
+    (local.get 1)
+    (local.get 0)
+  )
+)
@@ -0,0 +1,44 @@
+(module
+  (import "wasi_snapshot_preview1" "sock_accept"
+    (func $__wasi_sock_accept (param i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "sock_recv"
+    (func $__wasi_sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "sock_send"
+    (func $__wasi_sock_send (param i32 i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "sock_shutdown"
+    (func $__wasi_sock_shutdown (param i32 i32) (result i32)))
+  (func $_start
+    (local $conn i32)
+    ;; Accept a connection on the listener preopened at fd 3.
+    (if (call $__wasi_sock_accept (i32.const 3) (i32.const 0) (i32.const 0))
+      (then unreachable))
+    (local.set $conn (i32.load (i32.const 0)))
+
+    ;; Receive up to 64 bytes into the buffer at 64.
+    (i32.store (i32.const 16) (i32.const 64))
+    (i32.store (i32.const 20) (i32.const 64))
+    (if (call $__wasi_sock_recv
+          (local.get $conn)
+          (i32.const 16)
+          (i32.const 1)
+          (i32.const 0)
+          (i32.const 4)
+          (i32.const 8))
+      (then unreachable))
+
+    ;; Echo back exactly what was received and close our end for writing.
+    (i32.store (i32.const 20) (i32.load (i32.const 4)))
+    (if (call $__wasi_sock_send
+          (local.get $conn)
+          (i32.const 16)
+          (i32.const 1)
+          (i32.const 0)
+          (i32.const 4))
+      (then unreachable))
+    (if (call $__wasi_sock_shutdown (local.get $conn) (i32.const 2))
+      (then unreachable))
+  )
+  (memory 1)
+  (export "memory" (memory 0))
+  (export "_start" (func $_start))
+)
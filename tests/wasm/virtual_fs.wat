@@ -0,0 +1,126 @@
+(module
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $__wasi_path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read"
+    (func $__wasi_fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $__wasi_fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_close"
+    (func $__wasi_fd_close (param i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_readdir"
+    (func $__wasi_fd_readdir (param i32 i32 i32 i64 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_filestat_get"
+    (func $__wasi_path_filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_rename"
+    (func $__wasi_path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_unlink_file"
+    (func $__wasi_path_unlink_file (param i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit"
+    (func $__wasi_proc_exit (param i32)))
+
+  ;; Exit with the error number of a failed call.
+  (func $check (param $errno i32)
+    (if (local.get $errno)
+      (then (call $__wasi_proc_exit (local.get $errno))))
+  )
+
+  ;; Open the file at the given path in the preopened directory `$dir`, for
+  ;; reading and writing, and return its file descriptor.
+  (func $open (param $dir i32) (param $path i32) (param $len i32) (param $oflags i32) (result i32)
+    (call $check (call $__wasi_path_open
+      (local.get $dir)
+      (i32.const 0)
+      (local.get $path)
+      (local.get $len)
+      (local.get $oflags)
+      (i64.const 66) ;; fd_read | fd_write
+      (i64.const 0)
+      (i32.const 0)
+      (i32.const 12)))
+    (i32.load (i32.const 12))
+  )
+
+  ;; Copy the contents of the file `$fd` to stdout, and close it.
+  (func $print (param $fd i32)
+    (i32.store (i32.const 0) (i32.const 200))
+    (i32.store (i32.const 4) (i32.const 64))
+    (call $check (call $__wasi_fd_read (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+    (i32.store (i32.const 4) (i32.load (i32.const 8)))
+    (call $check (call $__wasi_fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    (call $check (call $__wasi_fd_close (local.get $fd)))
+  )
+
+  ;; Print the name of each entry of the directory `$dir` on its own line.
+  (func $list (param $dir i32)
+    (local $ptr i32)
+    (local $end i32)
+    (local $namlen i32)
+    (call $check (call $__wasi_fd_readdir
+      (local.get $dir) (i32.const 1024) (i32.const 1024) (i64.const 0) (i32.const 16)))
+    (local.set $ptr (i32.const 1024))
+    (local.set $end (i32.add (i32.const 1024) (i32.load (i32.const 16))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $ptr) (local.get $end)))
+        ;; The name follows the 24 bytes of the dirent, which holds its length.
+        (local.set $namlen (i32.load offset=16 (local.get $ptr)))
+        (i32.store (i32.const 0) (i32.add (local.get $ptr) (i32.const 24)))
+        (i32.store (i32.const 4) (local.get $namlen))
+        (i32.store (i32.const 8) (i32.const 150))
+        (i32.store (i32.const 12) (i32.const 1))
+        (call $check (call $__wasi_fd_write (i32.const 1) (i32.const 0) (i32.const 2) (i32.const 16)))
+        (local.set $ptr (i32.add (local.get $ptr) (i32.add (i32.const 24) (local.get $namlen))))
+        (br $next)))
+  )
+
+  ;; Exit with `$code` unless the file at the given path in the preopened
+  ;; directory `$dir` is `$size` bytes long.
+  (func $check_size (param $dir i32) (param $path i32) (param $len i32) (param $size i64) (param $code i32)
+    (call $check (call $__wasi_path_filestat_get
+      (local.get $dir) (i32.const 0) (local.get $path) (local.get $len) (i32.const 300)))
+    (if (i64.ne (i64.load offset=32 (i32.const 300)) (local.get $size))
+      (then (call $__wasi_proc_exit (local.get $code))))
+  )
+
+  (func (export "_start")
+    (local $fd i32)
+
+    ;; List the archive mounted as the first preopen, check the size of
+    ;; `hello.txt` in it, and print it.
+    (call $list (i32.const 3))
+    (call $check_size (i32.const 3) (i32.const 100) (i32.const 9) (i64.const 8) (i32.const 100))
+    (call $print (call $open (i32.const 3) (i32.const 100) (i32.const 9) (i32.const 0)))
+
+    ;; Create `a.txt` in the tmpfs mounted as the second preopen, list the
+    ;; tmpfs, and check the size of the file.
+    (local.set $fd (call $open (i32.const 4) (i32.const 120) (i32.const 5) (i32.const 1)))
+    (i32.store (i32.const 0) (i32.const 140))
+    (i32.store (i32.const 4) (i32.const 6))
+    (call $check (call $__wasi_fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+    (call $check (call $__wasi_fd_close (local.get $fd)))
+    (call $list (i32.const 4))
+    (call $check_size (i32.const 4) (i32.const 120) (i32.const 5) (i64.const 6) (i32.const 101))
+
+    ;; Rename it to `b.txt`, print it, and remove it.
+    (call $check (call $__wasi_path_rename
+      (i32.const 4) (i32.const 120) (i32.const 5)
+      (i32.const 4) (i32.const 130) (i32.const 5)))
+    (call $print (call $open (i32.const 4) (i32.const 130) (i32.const 5) (i32.const 0)))
+    (call $check (call $__wasi_path_unlink_file (i32.const 4) (i32.const 130) (i32.const 5)))
+
+    ;; `b.txt` must be gone.
+    (if (i32.ne
+          (call $__wasi_path_open
+            (i32.const 4) (i32.const 0) (i32.const 130) (i32.const 5) (i32.const 0)
+            (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 12))
+          (i32.const 44)) ;; noent
+      (then (call $__wasi_proc_exit (i32.const 1))))
+  )
+
+  (memory (export "memory") 1)
+  (data (i32.const 100) "hello.txt")
+  (data (i32.const 120) "a.txt")
+  (data (i32.const 130) "b.txt")
+  (data (i32.const 140) "tmpfs\n")
+  (data (i32.const 150) "\n")
+)